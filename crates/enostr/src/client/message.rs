@@ -24,6 +24,24 @@ pub enum ClientMessage {
     Close {
         sub_id: String,
     },
+    /// NIP-45 count request
+    Count {
+        sub_id: String,
+        filters: Vec<Filter>,
+    },
+    /// NIP-77 negentropy reconciliation request
+    NegOpen {
+        sub_id: String,
+        filter: Filter,
+        initial_message: String,
+    },
+    NegMsg {
+        sub_id: String,
+        message: String,
+    },
+    NegClose {
+        sub_id: String,
+    },
     Raw(String),
 }
 
@@ -50,6 +68,26 @@ impl ClientMessage {
         ClientMessage::Close { sub_id }
     }
 
+    pub fn count(sub_id: String, filters: Vec<Filter>) -> Self {
+        ClientMessage::Count { sub_id, filters }
+    }
+
+    pub fn neg_open(sub_id: String, filter: Filter, initial_message: String) -> Self {
+        ClientMessage::NegOpen {
+            sub_id,
+            filter,
+            initial_message,
+        }
+    }
+
+    pub fn neg_msg(sub_id: String, message: String) -> Self {
+        ClientMessage::NegMsg { sub_id, message }
+    }
+
+    pub fn neg_close(sub_id: String) -> Self {
+        ClientMessage::NegClose { sub_id }
+    }
+
     pub fn to_json(&self) -> Result<String, Error> {
         Ok(match self {
             Self::Event(ecm) => ecm.to_json(),
@@ -69,6 +107,25 @@ impl ClientMessage {
                 }
             }
             Self::Close { sub_id } => json!(["CLOSE", sub_id]).to_string(),
+            Self::Count { sub_id, filters } => {
+                let filters_json_str: Result<Vec<String>, Error> = filters
+                    .iter()
+                    .map(|f| f.json().map_err(Into::<Error>::into))
+                    .collect();
+                format!("[\"COUNT\",\"{}\",{}]", sub_id, filters_json_str?.join(","))
+            }
+            Self::NegOpen {
+                sub_id,
+                filter,
+                initial_message,
+            } => format!(
+                "[\"NEG-OPEN\",\"{}\",{},\"{}\"]",
+                sub_id,
+                filter.json()?,
+                initial_message
+            ),
+            Self::NegMsg { sub_id, message } => json!(["NEG-MSG", sub_id, message]).to_string(),
+            Self::NegClose { sub_id } => json!(["NEG-CLOSE", sub_id]).to_string(),
         })
     }
 }
@@ -16,6 +16,7 @@ pub struct Keypair {
     pub secret_key: Option<SecretKey>,
 }
 
+#[derive(Clone, Copy)]
 pub struct KeypairUnowned<'a> {
     pub pubkey: &'a Pubkey,
     pub secret_key: Option<&'a SecretKey>,
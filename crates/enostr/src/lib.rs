@@ -2,6 +2,7 @@ mod client;
 mod error;
 mod filter;
 mod keypair;
+mod negentropy;
 mod note;
 mod profile;
 mod pubkey;
@@ -12,13 +13,21 @@ pub use error::Error;
 pub use ewebsock;
 pub use filter::Filter;
 pub use keypair::{FilledKeypair, FullKeypair, Keypair, KeypairUnowned, SerializableKeypair};
+pub use negentropy::{Negentropy, NegentropyItem};
 pub use nostr::SecretKey;
 pub use note::{Note, NoteId};
 pub use profile::Profile;
 pub use pubkey::{Pubkey, PubkeyRef};
+pub use relay::dial::ConnectOptions;
 pub use relay::message::{RelayEvent, RelayMessage};
+pub use relay::mock::MockRelay;
+pub use relay::mux::SubscriptionMux;
+pub use relay::nip11::{RelayInformation, RelayLimitation};
 pub use relay::pool::{PoolEvent, PoolRelay, RelayPool};
+pub use relay::roles::{RelayRole, RelayRoles};
+pub use relay::stats::RelayStats;
+pub use relay::sub_limit::OpenSubs;
 pub use relay::subs_debug::{OwnedRelayEvent, RelayLogEvent, SubsDebug, TransferStats};
-pub use relay::{Relay, RelayStatus};
+pub use relay::{Compression, Relay, RelayStatus};
 
 pub type Result<T> = std::result::Result<T, error::Error>;
@@ -0,0 +1,511 @@
+use crate::{Error, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+/// Negentropy protocol version 1, as used by NIP-77
+const PROTOCOL_VERSION: u8 = 0x61;
+
+/// How many buckets we split a mismatched range into
+const BUCKETS: usize = 16;
+
+const MAX_TIMESTAMP: u64 = u64::MAX;
+
+const ID_SIZE: usize = 32;
+const FINGERPRINT_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Skip = 0,
+    Fingerprint = 1,
+    IdList = 2,
+}
+
+impl Mode {
+    fn from_u64(mode: u64) -> Result<Self> {
+        match mode {
+            0 => Ok(Mode::Skip),
+            1 => Ok(Mode::Fingerprint),
+            2 => Ok(Mode::IdList),
+            _ => Err(Error::DecodeFailed(format!("negentropy: bad mode {mode}"))),
+        }
+    }
+}
+
+/// A (created_at, id) pair. Ordering is by timestamp and then id, which
+/// is the order both sides of the reconciliation agree on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NegentropyItem {
+    pub created_at: u64,
+    pub id: [u8; 32],
+}
+
+impl NegentropyItem {
+    pub fn new(created_at: u64, id: [u8; 32]) -> Self {
+        Self { created_at, id }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Bound {
+    timestamp: u64,
+    id_prefix: Vec<u8>,
+}
+
+impl Bound {
+    fn infinity() -> Self {
+        Self {
+            timestamp: MAX_TIMESTAMP,
+            id_prefix: vec![],
+        }
+    }
+
+    fn zero() -> Self {
+        Self {
+            timestamp: 0,
+            id_prefix: vec![],
+        }
+    }
+
+    /// The smallest bound that still sorts `curr` after `prev`
+    fn minimal(prev: &NegentropyItem, curr: &NegentropyItem) -> Self {
+        if curr.created_at != prev.created_at {
+            return Self {
+                timestamp: curr.created_at,
+                id_prefix: vec![],
+            };
+        }
+
+        let shared = prev
+            .id
+            .iter()
+            .zip(curr.id.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        Self {
+            timestamp: curr.created_at,
+            id_prefix: curr.id[..(shared + 1).min(ID_SIZE)].to_vec(),
+        }
+    }
+
+    fn is_above(&self, item: &NegentropyItem) -> bool {
+        if item.created_at != self.timestamp {
+            return item.created_at < self.timestamp;
+        }
+
+        let mut padded = [0u8; ID_SIZE];
+        padded[..self.id_prefix.len()].copy_from_slice(&self.id_prefix);
+        item.id < padded
+    }
+}
+
+/// Output side of a negentropy message. Timestamps are delta encoded
+/// against the previous bound written in the same message.
+struct Writer {
+    buf: Vec<u8>,
+    last_timestamp: u64,
+}
+
+impl Writer {
+    fn new() -> Self {
+        Self {
+            buf: vec![PROTOCOL_VERSION],
+            last_timestamp: 0,
+        }
+    }
+
+    fn varint(&mut self, n: u64) {
+        encode_varint(&mut self.buf, n);
+    }
+
+    fn bound(&mut self, bound: &Bound) {
+        if bound.timestamp == MAX_TIMESTAMP {
+            self.last_timestamp = MAX_TIMESTAMP;
+            self.varint(0);
+        } else {
+            let delta = bound.timestamp.saturating_sub(self.last_timestamp);
+            self.last_timestamp = bound.timestamp;
+            self.varint(delta + 1);
+        }
+
+        self.varint(bound.id_prefix.len() as u64);
+        self.buf.extend_from_slice(&bound.id_prefix);
+    }
+
+    fn mode(&mut self, mode: Mode) {
+        self.varint(mode as u64);
+    }
+
+    /// Only the protocol version byte has been written
+    fn is_empty(&self) -> bool {
+        self.buf.len() == 1
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    last_timestamp: u64,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            last_timestamp: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos + n;
+        if end > self.data.len() {
+            return Err(Error::DecodeFailed("negentropy: message too short".into()));
+        }
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut res: u64 = 0;
+        loop {
+            let byte = self.byte()?;
+            res = (res << 7) | (byte & 0x7f) as u64;
+            if byte & 0x80 == 0 {
+                return Ok(res);
+            }
+        }
+    }
+
+    fn bound(&mut self) -> Result<Bound> {
+        let encoded = self.varint()?;
+        let timestamp = if encoded == 0 {
+            MAX_TIMESTAMP
+        } else {
+            encoded - 1
+        };
+
+        let timestamp = if self.last_timestamp == MAX_TIMESTAMP || timestamp == MAX_TIMESTAMP {
+            MAX_TIMESTAMP
+        } else {
+            timestamp.saturating_add(self.last_timestamp)
+        };
+        self.last_timestamp = timestamp;
+
+        let len = self.varint()? as usize;
+        if len > ID_SIZE {
+            return Err(Error::DecodeFailed("negentropy: bound too large".into()));
+        }
+
+        Ok(Bound {
+            timestamp,
+            id_prefix: self.bytes(len)?.to_vec(),
+        })
+    }
+}
+
+fn encode_varint(buf: &mut Vec<u8>, mut n: u64) {
+    if n == 0 {
+        buf.push(0);
+        return;
+    }
+
+    let mut out = Vec::with_capacity(10);
+    while n != 0 {
+        out.push((n & 0x7f) as u8);
+        n >>= 7;
+    }
+    out.reverse();
+
+    let last = out.len() - 1;
+    for byte in &mut out[..last] {
+        *byte |= 0x80;
+    }
+
+    buf.extend_from_slice(&out);
+}
+
+/// sha256 of the 256-bit little endian sum of all the ids, followed by
+/// the element count. Truncated to 16 bytes.
+fn fingerprint(items: &[NegentropyItem]) -> [u8; FINGERPRINT_SIZE] {
+    let mut acc = [0u8; ID_SIZE];
+    for item in items {
+        let mut carry: u16 = 0;
+        for (a, b) in acc.iter_mut().zip(item.id.iter()) {
+            let sum = *a as u16 + *b as u16 + carry;
+            *a = sum as u8;
+            carry = sum >> 8;
+        }
+    }
+
+    let mut count = Vec::with_capacity(10);
+    encode_varint(&mut count, items.len() as u64);
+
+    let mut hasher = Sha256::new();
+    hasher.update(acc);
+    hasher.update(&count);
+    let hash = hasher.finalize();
+
+    let mut fp = [0u8; FINGERPRINT_SIZE];
+    fp.copy_from_slice(&hash[..FINGERPRINT_SIZE]);
+    fp
+}
+
+/// The client (initiator) side of a NIP-77 negentropy reconciliation.
+///
+/// Build it from the notes we already have locally, send [`Self::initiate`]
+/// in a NEG-OPEN, and then feed each NEG-MSG payload into
+/// [`Self::reconcile`] until it returns `None`. The ids the relay has that
+/// we don't accumulate in [`Self::need`].
+pub struct Negentropy {
+    items: Vec<NegentropyItem>,
+    have: Vec<[u8; 32]>,
+    need: Vec<[u8; 32]>,
+}
+
+impl Negentropy {
+    pub fn new(mut items: Vec<NegentropyItem>) -> Self {
+        items.sort();
+        items.dedup();
+
+        Self {
+            items,
+            have: vec![],
+            need: vec![],
+        }
+    }
+
+    /// The hex encoded initial message for NEG-OPEN
+    pub fn initiate(&self) -> String {
+        let mut writer = Writer::new();
+        self.split_range(&mut writer, 0, self.items.len(), &Bound::infinity());
+        hex::encode(writer.buf)
+    }
+
+    /// Process a hex encoded NEG-MSG from the relay. Returns the next
+    /// message to send, or `None` when reconciliation is complete.
+    pub fn reconcile(&mut self, msg: &str) -> Result<Option<String>> {
+        let data = hex::decode(msg)?;
+        let mut reader = Reader::new(&data);
+
+        let version = reader.byte()?;
+        if version != PROTOCOL_VERSION {
+            return Err(Error::DecodeFailed(format!(
+                "negentropy: unsupported protocol version {version:#x}"
+            )));
+        }
+
+        let mut writer = Writer::new();
+        let mut prev_bound = Bound::zero();
+        let mut prev_index = 0;
+        let mut skip = false;
+
+        while !reader.is_empty() {
+            let curr_bound = reader.bound()?;
+            let mode = Mode::from_u64(reader.varint()?)?;
+
+            let lower = prev_index;
+            let upper =
+                lower + self.items[lower..].partition_point(|item| curr_bound.is_above(item));
+
+            match mode {
+                Mode::Skip => {
+                    skip = true;
+                }
+
+                Mode::Fingerprint => {
+                    let theirs = reader.bytes(FINGERPRINT_SIZE)?;
+                    if theirs == fingerprint(&self.items[lower..upper]) {
+                        skip = true;
+                    } else {
+                        if skip {
+                            skip = false;
+                            writer.bound(&prev_bound);
+                            writer.mode(Mode::Skip);
+                        }
+                        self.split_range(&mut writer, lower, upper, &curr_bound);
+                    }
+                }
+
+                Mode::IdList => {
+                    let num_ids = reader.varint()? as usize;
+                    let mut theirs: HashSet<[u8; 32]> = HashSet::with_capacity(num_ids);
+                    for _ in 0..num_ids {
+                        let mut id = [0u8; 32];
+                        id.copy_from_slice(reader.bytes(ID_SIZE)?);
+                        theirs.insert(id);
+                    }
+
+                    for item in &self.items[lower..upper] {
+                        if !theirs.remove(&item.id) {
+                            self.have.push(item.id);
+                        }
+                    }
+
+                    self.need.extend(theirs);
+                    skip = true;
+                }
+            }
+
+            prev_index = upper;
+            prev_bound = curr_bound;
+        }
+
+        if writer.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(hex::encode(writer.buf)))
+        }
+    }
+
+    /// Ids the relay has that we are missing
+    pub fn need(&self) -> &[[u8; 32]] {
+        &self.need
+    }
+
+    /// Take the ids we need so far, so they can be fetched while
+    /// reconciliation continues
+    pub fn take_need(&mut self) -> Vec<[u8; 32]> {
+        std::mem::take(&mut self.need)
+    }
+
+    /// Ids we have that the relay is missing
+    pub fn have(&self) -> &[[u8; 32]] {
+        &self.have
+    }
+
+    fn split_range(&self, writer: &mut Writer, lower: usize, upper: usize, upper_bound: &Bound) {
+        let num_elems = upper - lower;
+
+        if num_elems < BUCKETS * 2 {
+            writer.bound(upper_bound);
+            writer.mode(Mode::IdList);
+            writer.varint(num_elems as u64);
+            for item in &self.items[lower..upper] {
+                writer.buf.extend_from_slice(&item.id);
+            }
+            return;
+        }
+
+        let items_per_bucket = num_elems / BUCKETS;
+        let buckets_with_extra = num_elems % BUCKETS;
+        let mut curr = lower;
+
+        for i in 0..BUCKETS {
+            let bucket_size = items_per_bucket + usize::from(i < buckets_with_extra);
+            let fp = fingerprint(&self.items[curr..curr + bucket_size]);
+            curr += bucket_size;
+
+            let next_bound = if curr == upper {
+                upper_bound.clone()
+            } else {
+                Bound::minimal(&self.items[curr - 1], &self.items[curr])
+            };
+
+            writer.bound(&next_bound);
+            writer.mode(Mode::Fingerprint);
+            writer.buf.extend_from_slice(&fp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(created_at: u64, n: u8) -> NegentropyItem {
+        NegentropyItem::new(created_at, [n; 32])
+    }
+
+    fn id_list_msg(ids: &[[u8; 32]]) -> String {
+        let mut writer = Writer::new();
+        writer.bound(&Bound::infinity());
+        writer.mode(Mode::IdList);
+        writer.varint(ids.len() as u64);
+        for id in ids {
+            writer.buf.extend_from_slice(id);
+        }
+        hex::encode(writer.buf)
+    }
+
+    #[test]
+    fn varint_roundtrip() {
+        for n in [0u64, 1, 127, 128, 300, 16384, u32::MAX as u64, u64::MAX] {
+            let mut buf = vec![];
+            encode_varint(&mut buf, n);
+            assert_eq!(Reader::new(&buf).varint().unwrap(), n);
+        }
+    }
+
+    #[test]
+    fn small_sets_send_id_lists() {
+        let neg = Negentropy::new(vec![item(2, 2), item(1, 1)]);
+        let msg = hex::decode(neg.initiate()).unwrap();
+
+        // version, infinity bound (0, 0 prefix), IdList mode, 2 ids
+        assert_eq!(&msg[..5], &[PROTOCOL_VERSION, 0, 0, 2, 2]);
+        assert_eq!(&msg[5..37], &[1u8; 32]);
+        assert_eq!(&msg[37..], &[2u8; 32]);
+    }
+
+    #[test]
+    fn large_sets_send_fingerprints() {
+        let items = (0..64).map(|n| item(n as u64, n)).collect();
+        let neg = Negentropy::new(items);
+        let msg = hex::decode(neg.initiate()).unwrap();
+
+        let mut reader = Reader::new(&msg);
+        assert_eq!(reader.byte().unwrap(), PROTOCOL_VERSION);
+
+        let mut ranges = 0;
+        while !reader.is_empty() {
+            reader.bound().unwrap();
+            assert_eq!(
+                Mode::from_u64(reader.varint().unwrap()).unwrap(),
+                Mode::Fingerprint
+            );
+            reader.bytes(FINGERPRINT_SIZE).unwrap();
+            ranges += 1;
+        }
+        assert_eq!(ranges, BUCKETS);
+    }
+
+    #[test]
+    fn id_list_response_computes_need_and_have() {
+        let mut neg = Negentropy::new(vec![item(1, 1), item(2, 2)]);
+        let relay_msg = id_list_msg(&[[2u8; 32], [3u8; 32]]);
+
+        assert_eq!(neg.reconcile(&relay_msg).unwrap(), None);
+        assert_eq!(neg.need(), &[[3u8; 32]]);
+        assert_eq!(neg.have(), &[[1u8; 32]]);
+    }
+
+    #[test]
+    fn matching_fingerprint_finishes() {
+        let items: Vec<NegentropyItem> = (0..40).map(|n| item(n as u64, n)).collect();
+        let mut neg = Negentropy::new(items.clone());
+
+        let mut writer = Writer::new();
+        writer.bound(&Bound::infinity());
+        writer.mode(Mode::Fingerprint);
+        writer.buf.extend_from_slice(&fingerprint(&items));
+
+        assert_eq!(neg.reconcile(&hex::encode(writer.buf)).unwrap(), None);
+        assert!(neg.need().is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_version() {
+        let mut neg = Negentropy::new(vec![]);
+        assert!(neg.reconcile("60").is_err());
+    }
+}
@@ -0,0 +1,274 @@
+//! permessage-deflate (RFC 7692)
+//!
+//! Relays send a lot of JSON, and most of it compresses well. We offer
+//! the extension in the websocket handshake, and if the relay accepts
+//! it, every message it sends with RSV1 set is a raw deflate stream we
+//! inflate here. Our own messages go out compressed too.
+
+use std::io;
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+/// What we send in `Sec-WebSocket-Extensions`. We don't offer
+/// `client_max_window_bits`, so the relay can't ask us to compress with
+/// a smaller window than flate2's 32K one.
+pub const OFFER: &str = "permessage-deflate";
+
+/// A sync flush ends with an empty stored block. The sender strips it
+/// off every message and the receiver puts it back.
+const TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// What the relay agreed to in its handshake response
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Params {
+    /// The relay starts a fresh compressor for every message
+    pub server_no_context_takeover: bool,
+
+    /// We have to start a fresh compressor for every message
+    pub client_no_context_takeover: bool,
+}
+
+impl Params {
+    /// Parse the `Sec-WebSocket-Extensions` the relay answered with. None
+    /// if it didn't turn compression on.
+    pub fn from_response(header: &str) -> Result<Option<Self>, String> {
+        let mut accepted = None;
+
+        for extension in header.split(',') {
+            let mut parts = extension.split(';').map(str::trim);
+            let name = parts.next().unwrap_or_default();
+            if name.is_empty() {
+                continue;
+            }
+
+            if name != OFFER || accepted.is_some() {
+                return Err(format!(
+                    "relay accepted an extension we didn't offer: {name}"
+                ));
+            }
+
+            let mut params = Params::default();
+            for param in parts {
+                let (key, value) = match param.split_once('=') {
+                    Some((key, value)) => (key.trim(), Some(value.trim().trim_matches('"'))),
+                    None => (param, None),
+                };
+
+                match (key, value) {
+                    ("server_no_context_takeover", None) => {
+                        params.server_no_context_takeover = true
+                    }
+                    ("client_no_context_takeover", None) => {
+                        params.client_no_context_takeover = true
+                    }
+                    // a 32K window inflates anything compressed with a
+                    // smaller one
+                    ("server_max_window_bits", Some(bits))
+                        if matches!(bits.parse::<u8>(), Ok(8..=15)) => {}
+                    _ => {
+                        return Err(format!(
+                            "relay sent an unsupported permessage-deflate parameter: {param}"
+                        ))
+                    }
+                }
+            }
+
+            accepted = Some(params);
+        }
+
+        Ok(accepted)
+    }
+}
+
+/// Compression state for one connection. Unless the relay said
+/// otherwise, both directions keep their window between messages.
+pub struct Deflate {
+    params: Params,
+    compress: Compress,
+    decompress: Decompress,
+}
+
+impl Deflate {
+    pub fn new(params: Params) -> Self {
+        Self {
+            params,
+            compress: Compress::new(Compression::default(), false),
+            decompress: Decompress::new(false),
+        }
+    }
+
+    /// Compress the payload of a message we're sending
+    pub fn compress(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        let start = self.compress.total_in();
+
+        loop {
+            if out.len() == out.capacity() {
+                out.reserve(out.capacity());
+            }
+
+            let consumed = (self.compress.total_in() - start) as usize;
+            self.compress
+                .compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)?;
+
+            // the flush is done once everything is in and it stopped
+            // short of filling the buffer
+            let consumed = (self.compress.total_in() - start) as usize;
+            if consumed == data.len() && out.len() < out.capacity() {
+                break;
+            }
+        }
+
+        if out.ends_with(&TAIL) {
+            out.truncate(out.len() - TAIL.len());
+        }
+
+        if self.params.client_no_context_takeover {
+            self.compress.reset();
+        }
+
+        Ok(out)
+    }
+
+    /// Inflate the payload of a message the relay sent with RSV1 set.
+    /// Gives up once it's inflated past `max_size`, so a small message
+    /// can't make us allocate without bound.
+    pub fn decompress(&mut self, data: &[u8], max_size: usize) -> io::Result<Vec<u8>> {
+        let mut input = Vec::with_capacity(data.len() + TAIL.len());
+        input.extend_from_slice(data);
+        input.extend_from_slice(&TAIL);
+
+        let mut out = Vec::with_capacity((data.len() * 4).clamp(64, max_size.max(64)));
+        let start = self.decompress.total_in();
+
+        loop {
+            if out.len() == out.capacity() {
+                if out.len() >= max_size {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "inflated message is too big",
+                    ));
+                }
+                out.reserve(out.capacity().min(max_size - out.len()));
+            }
+
+            let before = (self.decompress.total_in(), self.decompress.total_out());
+            let consumed = (before.0 - start) as usize;
+            let status = self.decompress.decompress_vec(
+                &input[consumed..],
+                &mut out,
+                FlushDecompress::Sync,
+            )?;
+
+            if status == Status::StreamEnd {
+                // the relay closed its deflate stream with a final
+                // block, the next message starts a new one
+                self.decompress.reset(false);
+                return Ok(out);
+            }
+
+            let consumed = (self.decompress.total_in() - start) as usize;
+            if consumed == input.len() && out.len() < out.capacity() {
+                break;
+            }
+
+            let after = (self.decompress.total_in(), self.decompress.total_out());
+            if after == before && out.len() < out.capacity() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "couldn't inflate message",
+                ));
+            }
+        }
+
+        if self.params.server_no_context_takeover {
+            self.decompress.reset(false);
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX: usize = 1 << 20;
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(Params::from_response(""), Ok(None));
+        assert_eq!(
+            Params::from_response("permessage-deflate"),
+            Ok(Some(Params::default()))
+        );
+        assert_eq!(
+            Params::from_response(
+                "permessage-deflate; server_no_context_takeover; server_max_window_bits=10"
+            ),
+            Ok(Some(Params {
+                server_no_context_takeover: true,
+                client_no_context_takeover: false,
+            }))
+        );
+        assert_eq!(
+            Params::from_response("permessage-deflate;client_no_context_takeover"),
+            Ok(Some(Params {
+                server_no_context_takeover: false,
+                client_no_context_takeover: true,
+            }))
+        );
+
+        // we never offered these
+        assert!(Params::from_response("x-webkit-deflate-frame").is_err());
+        assert!(Params::from_response("permessage-deflate; client_max_window_bits=10").is_err());
+        assert!(Params::from_response("permessage-deflate, permessage-deflate").is_err());
+    }
+
+    #[test]
+    fn test_inflate_rfc_example() {
+        // RFC 7692 section 7.2.3.2, "Hello" twice with the window kept
+        // between messages
+        let mut deflate = Deflate::new(Params::default());
+        let first = [0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00];
+        let second = [0xf2, 0x00, 0x11, 0x00, 0x00];
+
+        assert_eq!(deflate.decompress(&first, MAX).unwrap(), b"Hello");
+        assert_eq!(deflate.decompress(&second, MAX).unwrap(), b"Hello");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for params in [
+            Params::default(),
+            Params {
+                server_no_context_takeover: true,
+                client_no_context_takeover: true,
+            },
+        ] {
+            // the relay's compressor is our decompressor's peer and
+            // vice versa, so one side's params serve both here
+            let mut ours = Deflate::new(params);
+            let mut theirs = Deflate::new(Params {
+                server_no_context_takeover: params.client_no_context_takeover,
+                client_no_context_takeover: params.server_no_context_takeover,
+            });
+
+            let req = r#"["REQ","sub",{"kinds":[1],"limit":500}]"#.repeat(50);
+            for _ in 0..3 {
+                let compressed = ours.compress(req.as_bytes()).unwrap();
+                assert!(compressed.len() < req.len() / 4);
+                assert_eq!(theirs.decompress(&compressed, MAX).unwrap(), req.as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn test_inflate_limit() {
+        let mut ours = Deflate::new(Params::default());
+        let mut theirs = Deflate::new(Params::default());
+
+        let compressed = ours.compress(&vec![b'a'; 64 * 1024]).unwrap();
+        assert!(theirs.decompress(&compressed, 1024).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use std::io;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+
+/// How we dial relays
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectOptions {
+    /// Give up on a relay if we can't open a TCP connection and finish
+    /// the websocket handshake in this long
+    pub connect_timeout: Duration,
+
+    /// How long an address gets to connect before we start racing the
+    /// next one. RFC 8305 recommends 250ms.
+    pub attempt_delay: Duration,
+
+    /// Sent in the websocket handshake and NIP-11 requests
+    pub user_agent: &'static str,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            attempt_delay: Duration::from_millis(250),
+            user_agent: concat!("enostr/", env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+/// Connect to whichever of the host's addresses answers first
+/// (happy eyeballs, RFC 8305).
+///
+/// Addresses are tried in resolver order with the address families
+/// interleaved, each one getting [`ConnectOptions::attempt_delay`] to
+/// connect before we race the next. A host with a broken AAAA record
+/// costs us a quarter second instead of the OS connect timeout.
+pub fn happy_eyeballs(host: &str, port: u16, options: &ConnectOptions) -> io::Result<TcpStream> {
+    let deadline = Instant::now() + options.connect_timeout;
+    let addrs = interleave((host, port).to_socket_addrs()?.collect());
+    if addrs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no addresses for {host}"),
+        ));
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut pending = 0;
+    let mut last_err = None;
+
+    for addr in addrs {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+
+        let timeout = deadline - now;
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            // nobody's listening anymore if another address won
+            let _ = tx.send((addr, TcpStream::connect_timeout(&addr, timeout)));
+        });
+        pending += 1;
+
+        let next_attempt = (now + options.attempt_delay).min(deadline);
+        while pending > 0 {
+            let wait = next_attempt.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(wait) {
+                Ok((addr, Ok(stream))) => {
+                    debug!("connected to {host} via {addr}");
+                    return Ok(stream);
+                }
+                Ok((addr, Err(err))) => {
+                    debug!("couldn't connect to {host} via {addr}: {err}");
+                    pending -= 1;
+                    last_err = Some(err);
+                }
+                Err(_) => break,
+            }
+        }
+    }
+
+    // every address has had its turn, wait on the ones still going
+    while pending > 0 {
+        let wait = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(wait) {
+            Ok((addr, Ok(stream))) => {
+                debug!("connected to {host} via {addr}");
+                return Ok(stream);
+            }
+            Ok((_, Err(err))) => {
+                pending -= 1;
+                last_err = Some(err);
+            }
+            Err(_) => break,
+        }
+    }
+
+    Err(last_err
+        .filter(|_| pending == 0)
+        .unwrap_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "connect timed out")))
+}
+
+/// Alternate between address families, starting with whichever the
+/// resolver put first
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first() else {
+        return addrs;
+    };
+
+    let first_is_v6 = first.is_ipv6();
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_is_v6);
+
+    let mut out = Vec::with_capacity(preferred.len() + other.len());
+    preferred.reverse();
+    other.reverse();
+    loop {
+        match (preferred.pop(), other.pop()) {
+            (None, None) => break,
+            (a, b) => out.extend(a.into_iter().chain(b)),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interleave_families() {
+        let v6a: SocketAddr = "[2001:db8::1]:443".parse().unwrap();
+        let v6b: SocketAddr = "[2001:db8::2]:443".parse().unwrap();
+        let v6c: SocketAddr = "[2001:db8::3]:443".parse().unwrap();
+        let v4a: SocketAddr = "192.0.2.1:443".parse().unwrap();
+
+        assert_eq!(
+            interleave(vec![v6a, v6b, v6c, v4a]),
+            vec![v6a, v4a, v6b, v6c]
+        );
+        assert_eq!(interleave(vec![v4a, v6a, v6b]), vec![v4a, v6a, v6b]);
+        assert!(interleave(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_connects_to_local_listener() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let stream = happy_eyeballs("127.0.0.1", port, &ConnectOptions::default()).unwrap();
+        assert_eq!(stream.peer_addr().unwrap().port(), port);
+    }
+}
@@ -8,6 +8,23 @@ pub struct CommandResult<'a> {
     message: &'a str,
 }
 
+impl<'a> CommandResult<'a> {
+    /// The id of the event this is about, hex encoded
+    pub fn event_id(&self) -> &'a str {
+        self.event_id
+    }
+
+    /// Whether the relay took the event
+    pub fn accepted(&self) -> bool {
+        self.status
+    }
+
+    /// Why the relay turned it down, or anything else it had to say
+    pub fn message(&self) -> &'a str {
+        self.message
+    }
+}
+
 pub fn calculate_command_result_size(result: &CommandResult) -> usize {
     std::mem::size_of_val(result) + result.event_id.len() + result.message.len()
 }
@@ -18,6 +35,14 @@ pub enum RelayMessage<'a> {
     Eose(&'a str),
     Event(&'a str, &'a str),
     Notice(&'a str),
+    /// NIP-45 count result: (subid, count)
+    Count(&'a str, u64),
+    /// NIP-77 negentropy message: (subid, hex payload)
+    NegMsg(&'a str, &'a str),
+    /// NIP-77 negentropy error: (subid, reason)
+    NegErr(&'a str, &'a str),
+    /// The relay ended a subscription: (subid, reason)
+    Closed(&'a str, &'a str),
 }
 
 #[derive(Debug)]
@@ -73,6 +98,22 @@ impl<'a> RelayMessage<'a> {
         RelayMessage::Event(sub_id, ev)
     }
 
+    pub fn count(sub_id: &'a str, count: u64) -> Self {
+        RelayMessage::Count(sub_id, count)
+    }
+
+    pub fn neg_msg(sub_id: &'a str, msg: &'a str) -> Self {
+        RelayMessage::NegMsg(sub_id, msg)
+    }
+
+    pub fn neg_err(sub_id: &'a str, reason: &'a str) -> Self {
+        RelayMessage::NegErr(sub_id, reason)
+    }
+
+    pub fn closed(sub_id: &'a str, reason: &'a str) -> Self {
+        RelayMessage::Closed(sub_id, reason)
+    }
+
     pub fn from_json(msg: &'a str) -> Result<RelayMessage<'a>> {
         if msg.is_empty() {
             return Err(Error::Empty);
@@ -85,7 +126,7 @@ impl<'a> RelayMessage<'a> {
 
         // Notice
         // Relay response format: ["NOTICE", <message>]
-        if msg.len() >= 12 && &msg[0..=9] == "[\"NOTICE\"," {
+        if msg.starts_with("[\"NOTICE\",") {
             // TODO: there could be more than one space, whatever
             let start = if msg.as_bytes().get(10).copied() == Some(b' ') {
                 12
@@ -93,17 +134,20 @@ impl<'a> RelayMessage<'a> {
                 11
             };
             let end = msg.len() - 2;
-            return Ok(Self::notice(&msg[start..end]));
+            let notice = msg
+                .get(start..end)
+                .ok_or_else(|| Error::DecodeFailed("Invalid NOTICE format".into()))?;
+            return Ok(Self::notice(notice));
         }
 
         // Event
         // Relay response format: ["EVENT", <subscription id>, <event JSON>]
-        if &msg[0..=7] == "[\"EVENT\"" {
+        if msg.starts_with("[\"EVENT\"") {
             let mut start = 9;
             while let Some(&b' ') = msg.as_bytes().get(start) {
                 start += 1; // Move past optional spaces
             }
-            if let Some(comma_index) = msg[start..].find(',') {
+            if let Some(comma_index) = msg.get(start..).and_then(|rest| rest.find(',')) {
                 let subid_end = start + comma_index;
                 let subid = &msg[start..subid_end].trim().trim_matches('"');
                 return Ok(Self::event(msg, subid));
@@ -114,7 +158,7 @@ impl<'a> RelayMessage<'a> {
 
         // EOSE (NIP-15)
         // Relay response format: ["EOSE", <subscription_id>]
-        if &msg[0..=7] == "[\"EOSE\"," {
+        if msg.starts_with("[\"EOSE\",") {
             let start = if msg.as_bytes().get(8).copied() == Some(b' ') {
                 10 // Skip space after the comma
             } else {
@@ -124,9 +168,9 @@ impl<'a> RelayMessage<'a> {
             // Use rfind to locate the last quote
             if let Some(end_bracket_index) = msg.rfind(']') {
                 let end = end_bracket_index - 1; // Account for space before bracket
-                if start < end {
+                if let Some(subid) = msg.get(start..end).filter(|_| start < end) {
                     // Trim subscription id and remove extra spaces and quotes
-                    let subid = &msg[start..end].trim().trim_matches('"').trim();
+                    let subid = subid.trim().trim_matches('"').trim();
                     return Ok(RelayMessage::eose(subid));
                 }
             }
@@ -137,25 +181,88 @@ impl<'a> RelayMessage<'a> {
 
         // OK (NIP-20)
         // Relay response format: ["OK",<event_id>, <true|false>, <message>]
-        if &msg[0..=5] == "[\"OK\"," && msg.len() >= 78 {
-            let event_id = &msg[7..71];
-            let booly = &msg[73..77];
-            let status: bool = if booly == "true" {
+        if msg.starts_with("[\"OK\",") && msg.len() >= 78 {
+            let event_id = msg
+                .get(7..71)
+                .ok_or_else(|| Error::DecodeFailed("bad event id".into()))?;
+            let booly = msg.get(73..77);
+            let status: bool = if booly == Some("true") {
                 true
-            } else if booly == "false" {
+            } else if booly == Some("false") {
                 false
             } else {
                 return Err(Error::DecodeFailed("bad boolean value".into()));
             };
             let message_start = msg.rfind(',').unwrap() + 1;
-            let message = &msg[message_start..msg.len() - 2].trim().trim_matches('"');
+            let message = msg
+                .get(message_start..msg.len() - 2)
+                .ok_or_else(|| Error::DecodeFailed("Invalid OK format".into()))?
+                .trim()
+                .trim_matches('"');
             return Ok(Self::ok(event_id, status, message));
         }
 
+        // COUNT (NIP-45)
+        // Relay response format: ["COUNT", <subscription_id>, {"count": <integer>}]
+        if let Some(rest) = msg.strip_prefix("[\"COUNT\",") {
+            let (subid, payload) = rest
+                .split_once(',')
+                .ok_or_else(|| Error::DecodeFailed("Invalid COUNT format".into()))?;
+            let count = payload
+                .find("\"count\"")
+                .and_then(|start| payload[start + 7..].split_once(':'))
+                .and_then(|(_, num)| {
+                    let num = num.trim_start();
+                    let end = num.find(|c: char| !c.is_ascii_digit()).unwrap_or(num.len());
+                    num[..end].parse::<u64>().ok()
+                })
+                .ok_or_else(|| Error::DecodeFailed("Invalid COUNT result".into()))?;
+            return Ok(Self::count(subid.trim().trim_matches('"'), count));
+        }
+
+        // NEG-MSG (NIP-77)
+        // Relay response format: ["NEG-MSG", <subscription_id>, <hex message>]
+        if let Some(rest) = msg.strip_prefix("[\"NEG-MSG\",") {
+            let (subid, payload) = parse_two_strings(rest)?;
+            return Ok(Self::neg_msg(subid, payload));
+        }
+
+        // NEG-ERR (NIP-77)
+        // Relay response format: ["NEG-ERR", <subscription_id>, <reason>]
+        if let Some(rest) = msg.strip_prefix("[\"NEG-ERR\",") {
+            let (subid, reason) = parse_two_strings(rest)?;
+            return Ok(Self::neg_err(subid, reason));
+        }
+
+        // CLOSED
+        // Relay response format: ["CLOSED", <subscription_id>, <message>]
+        if let Some(rest) = msg.strip_prefix("[\"CLOSED\",") {
+            let (subid, reason) = parse_two_strings(rest)?;
+            return Ok(Self::closed(subid, reason));
+        }
+
         Err(Error::DecodeFailed("unrecognized message type".into()))
     }
 }
 
+/// Split the tail of a `["TYPE", "a", "b"]` message into its two string
+/// fields. The first field must not contain commas (subids never do).
+fn parse_two_strings(rest: &str) -> Result<(&str, &str)> {
+    let rest = rest
+        .trim_end()
+        .strip_suffix(']')
+        .ok_or_else(|| Error::DecodeFailed("missing closing bracket".into()))?;
+
+    let (first, second) = rest
+        .split_once(',')
+        .ok_or_else(|| Error::DecodeFailed("expected two fields".into()))?;
+
+    Ok((
+        first.trim().trim_matches('"'),
+        second.trim().trim_matches('"'),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -205,7 +312,41 @@ mod tests {
                     "pow: difficulty 25>=24",
                 )),
             ),
+            (
+                r#"["COUNT","count-sub",{"count":238}]"#,
+                Ok(RelayMessage::count("count-sub", 238)),
+            ),
+            (
+                r#"["COUNT", "count-sub", {"count": 93412452, "approximate": true}]"#,
+                Ok(RelayMessage::count("count-sub", 93412452)),
+            ),
+            (
+                r#"["NEG-MSG","neg-sub","6100000200"]"#,
+                Ok(RelayMessage::neg_msg("neg-sub", "6100000200")),
+            ),
+            (
+                r#"["NEG-ERR", "neg-sub", "blocked: too many query results"]"#,
+                Ok(RelayMessage::neg_err(
+                    "neg-sub",
+                    "blocked: too many query results",
+                )),
+            ),
+            (
+                r#"["CLOSED","sub1","error: too many subscriptions"]"#,
+                Ok(RelayMessage::closed(
+                    "sub1",
+                    "error: too many subscriptions",
+                )),
+            ),
             // Invalid cases
+            (
+                r#"["COUNT","count-sub",{"cnt":1}]"#,
+                Err(Error::DecodeFailed("Invalid COUNT result".into())),
+            ),
+            (
+                r#"["NEG-MSG","neg-sub"]"#,
+                Err(Error::DecodeFailed("expected two fields".into())),
+            ),
             (
                 r#"["EVENT","random_string"]"#,
                 Err(Error::DecodeFailed("Invalid EVENT format".into())),
@@ -238,6 +379,25 @@ mod tests {
                 r#"["OK","b1a649ebe8b435ec71d3784793f3bbf4b93e64e17568a741aecd4c7ddeafce30",hello,404]"#,
                 Err(Error::DecodeFailed("bad boolean value".into())),
             ),
+            // multibyte characters where ascii is expected
+            (
+                r#"["EVENT"é"sub",{}]"#,
+                Err(Error::DecodeFailed("Invalid EVENT format".into())),
+            ),
+            (
+                r#"["EOSE",éé]"#,
+                Err(Error::DecodeFailed(
+                    "Invalid subscription ID or format".into(),
+                )),
+            ),
+            (
+                r#"["NOTICE", ]"#,
+                Err(Error::DecodeFailed("Invalid NOTICE format".into())),
+            ),
+            (
+                r#"["OK","b1a649ebe8b435ec71d3784793f3bbf4b93e64e17568a741aecd4c7ddeafce3é",true,""]"#,
+                Err(Error::DecodeFailed("bad event id".into())),
+            ),
         ];
 
         for (input, expected) in tests {
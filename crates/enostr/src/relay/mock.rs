@@ -0,0 +1,467 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+use tungstenite::Message;
+
+/// How long connection threads nap when there's nothing to do
+const POLL_DELAY: Duration = Duration::from_millis(10);
+
+/// What the mock relay says about itself over NIP-11
+const MOCK_INFO: &str = r#"{"name":"mock relay","software":"enostr","supported_nips":[1,11,45]}"#;
+
+/// A relay that runs in-process on localhost and serves canned events
+/// over a real websocket, for integration tests and the offline demo.
+///
+/// It answers REQ, CLOSE, COUNT and EVENT. Published events are stored
+/// and sent to matching subscriptions on every connection, but nothing
+/// is verified: whatever the client sends is accepted.
+pub struct MockRelay {
+    url: String,
+    shared: Arc<Mutex<Shared>>,
+    shutdown: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct Shared {
+    events: Vec<Value>,
+
+    /// Raw EVENTs clients sent us, in order
+    published: Vec<String>,
+
+    /// New events for each open connection
+    listeners: Vec<mpsc::Sender<Value>>,
+}
+
+impl Shared {
+    fn add_event(&mut self, event: Value) {
+        self.listeners
+            .retain(|listener| listener.send(event.clone()).is_ok());
+        self.events.push(event);
+    }
+}
+
+impl MockRelay {
+    /// Start a relay on a free port, serving these events (as JSON)
+    pub fn start(events: impl IntoIterator<Item = String>) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let url = format!("ws://{}", listener.local_addr()?);
+
+        let mut shared = Shared::default();
+        for event in events {
+            match serde_json::from_str(&event) {
+                Ok(event) => shared.events.push(event),
+                Err(err) => warn!("mock relay: skipping bad event: {err}"),
+            }
+        }
+
+        let shared = Arc::new(Mutex::new(shared));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        {
+            let shared = shared.clone();
+            let shutdown = shutdown.clone();
+            std::thread::Builder::new()
+                .name(format!("mock relay {url}"))
+                .spawn(move || accept_loop(listener, shared, shutdown))?;
+        }
+
+        Ok(MockRelay {
+            url,
+            shared,
+            shutdown,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Serve another event, sending it to any matching subscriptions
+    pub fn add_event(&self, event: &str) {
+        match serde_json::from_str(event) {
+            Ok(event) => self.shared.lock().unwrap().add_event(event),
+            Err(err) => warn!("mock relay: not adding bad event: {err}"),
+        }
+    }
+
+    /// The events clients have published to us
+    pub fn published(&self) -> Vec<String> {
+        self.shared.lock().unwrap().published.clone()
+    }
+}
+
+impl Drop for MockRelay {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+    }
+}
+
+fn accept_loop(listener: TcpListener, shared: Arc<Mutex<Shared>>, shutdown: Arc<AtomicBool>) {
+    while !shutdown.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, addr)) => {
+                debug!("mock relay: connection from {addr}");
+                let shared = shared.clone();
+                let shutdown = shutdown.clone();
+                std::thread::spawn(move || {
+                    if let Err(err) = serve(stream, shared, shutdown) {
+                        debug!("mock relay: connection from {addr} ended: {err}");
+                    }
+                });
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                std::thread::sleep(POLL_DELAY);
+            }
+            Err(err) => {
+                warn!("mock relay: accept failed: {err}");
+                return;
+            }
+        }
+    }
+}
+
+fn serve(
+    mut stream: TcpStream,
+    shared: Arc<Mutex<Shared>>,
+    shutdown: Arc<AtomicBool>,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    // plain http requests are after our NIP-11 document
+    if !is_websocket_upgrade(&stream)? {
+        return serve_info(stream);
+    }
+
+    let tcp = stream.try_clone()?;
+    let mut socket = tungstenite::accept(stream).map_err(io::Error::other)?;
+    tcp.set_read_timeout(None)?;
+    tcp.set_nonblocking(true)?;
+
+    let (tx, new_events) = mpsc::channel();
+    shared.lock().unwrap().listeners.push(tx);
+
+    let mut conn = Connection::default();
+    while !shutdown.load(Ordering::Relaxed) {
+        let mut busy = false;
+
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                busy = true;
+                for reply in conn.handle(&text, &shared) {
+                    socket
+                        .write(Message::Text(reply.to_string()))
+                        .map_err(io::Error::other)?;
+                }
+            }
+            Ok(_) => busy = true,
+            Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => {}
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                return Ok(());
+            }
+            Err(err) => return Err(io::Error::other(err)),
+        }
+
+        while let Ok(event) = new_events.try_recv() {
+            busy = true;
+            for reply in conn.live_event(&event) {
+                socket
+                    .write(Message::Text(reply.to_string()))
+                    .map_err(io::Error::other)?;
+            }
+        }
+
+        match socket.flush() {
+            Ok(()) => {}
+            Err(tungstenite::Error::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => {}
+            Err(err) => return Err(io::Error::other(err)),
+        }
+
+        if !busy {
+            std::thread::sleep(POLL_DELAY);
+        }
+    }
+
+    let _ = socket.close(None);
+    let _ = socket.flush();
+    Ok(())
+}
+
+fn is_websocket_upgrade(stream: &TcpStream) -> io::Result<bool> {
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = stream.peek(&mut buf)?;
+        let head = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+        if head.contains("\r\n\r\n") || n == buf.len() || n == 0 {
+            return Ok(head.contains("upgrade: websocket"));
+        }
+        std::thread::sleep(POLL_DELAY);
+    }
+}
+
+fn serve_info(mut stream: TcpStream) -> io::Result<()> {
+    // we don't care what they asked for, it all gets the same answer
+    let mut buf = [0u8; 4096];
+    let _ = stream.read(&mut buf)?;
+
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: application/nostr+json\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{MOCK_INFO}",
+        MOCK_INFO.len()
+    )?;
+    stream.flush()
+}
+
+/// The subscriptions open on one connection
+#[derive(Default)]
+struct Connection {
+    subs: Vec<(String, Vec<Value>)>,
+}
+
+impl Connection {
+    fn handle(&mut self, text: &str, shared: &Mutex<Shared>) -> Vec<Value> {
+        let Ok(Value::Array(msg)) = serde_json::from_str::<Value>(text) else {
+            return vec![json!(["NOTICE", "could not parse message"])];
+        };
+
+        let cmd = msg.first().and_then(Value::as_str).unwrap_or_default();
+        let sub_id = msg.get(1).and_then(Value::as_str).unwrap_or_default();
+        let filters = msg.get(2..).unwrap_or_default();
+
+        match cmd {
+            "REQ" => {
+                let events = shared.lock().unwrap().events.clone();
+                let mut replies: Vec<Value> = query(&events, filters)
+                    .into_iter()
+                    .map(|event| json!(["EVENT", sub_id, event]))
+                    .collect();
+                replies.push(json!(["EOSE", sub_id]));
+
+                self.subs.retain(|(id, _)| id != sub_id);
+                self.subs.push((sub_id.to_owned(), filters.to_vec()));
+                replies
+            }
+
+            "CLOSE" => {
+                self.subs.retain(|(id, _)| id != sub_id);
+                vec![]
+            }
+
+            "COUNT" => {
+                let shared = shared.lock().unwrap();
+                let count = shared
+                    .events
+                    .iter()
+                    .filter(|event| filters.iter().any(|filter| matches(filter, event)))
+                    .count();
+                vec![json!(["COUNT", sub_id, {"count": count}])]
+            }
+
+            "EVENT" => {
+                let Some(event) = msg.get(1) else {
+                    return vec![json!(["NOTICE", "EVENT without an event"])];
+                };
+
+                let id = event["id"].as_str().unwrap_or_default().to_owned();
+                let mut shared = shared.lock().unwrap();
+                shared.published.push(event.to_string());
+                shared.add_event(event.clone());
+                vec![json!(["OK", id, true, ""])]
+            }
+
+            _ => vec![json!(["NOTICE", format!("unsupported: {cmd}")])],
+        }
+    }
+
+    fn live_event(&self, event: &Value) -> Vec<Value> {
+        self.subs
+            .iter()
+            .filter(|(_, filters)| filters.iter().any(|filter| matches(filter, event)))
+            .map(|(sub_id, _)| json!(["EVENT", sub_id, event]))
+            .collect()
+    }
+}
+
+/// The events matching any of the filters, newest first, each filter
+/// contributing at most its limit
+fn query<'a>(events: &'a [Value], filters: &[Value]) -> Vec<&'a Value> {
+    let mut newest: Vec<&Value> = events.iter().collect();
+    newest.sort_by_key(|event| std::cmp::Reverse(event["created_at"].as_u64()));
+
+    let mut out: Vec<&Value> = Vec::new();
+    for filter in filters {
+        let limit = filter["limit"].as_u64().map_or(usize::MAX, |l| l as usize);
+        let matching = newest
+            .iter()
+            .filter(|event| matches(filter, event))
+            .take(limit);
+
+        for event in matching {
+            if !out.iter().any(|seen| seen["id"] == event["id"]) {
+                out.push(event);
+            }
+        }
+    }
+
+    out
+}
+
+/// NIP-01 filter matching
+fn matches(filter: &Value, event: &Value) -> bool {
+    let Some(filter) = filter.as_object() else {
+        return false;
+    };
+
+    filter.iter().all(|(key, cond)| match key.as_str() {
+        "ids" => one_of(cond, &event["id"]),
+        "authors" => one_of(cond, &event["pubkey"]),
+        "kinds" => one_of(cond, &event["kind"]),
+        "since" => event["created_at"].as_u64() >= cond.as_u64(),
+        "until" => event["created_at"].as_u64() <= cond.as_u64(),
+        tag if tag.starts_with('#') && tag.len() == 2 => {
+            let name = &tag[1..];
+            event["tags"].as_array().is_some_and(|tags| {
+                tags.iter()
+                    .any(|t| t[0].as_str() == Some(name) && one_of(cond, &t[1]))
+            })
+        }
+        // limit, search and anything we don't know about don't narrow
+        // anything down
+        _ => true,
+    })
+}
+
+fn one_of(cond: &Value, value: &Value) -> bool {
+    cond.as_array().is_some_and(|values| values.contains(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientMessage, Filter, FullKeypair, RelayMessage, RelayPool};
+    use ewebsock::{WsEvent, WsMessage};
+    use nostrdb::{NoteBuildOptions, NoteBuilder};
+    use std::time::Instant;
+
+    fn event(id: &str, kind: u64, created_at: u64, tags: Value) -> Value {
+        json!({
+            "id": id,
+            "pubkey": "aa",
+            "kind": kind,
+            "created_at": created_at,
+            "tags": tags,
+            "content": "",
+            "sig": "",
+        })
+    }
+
+    #[test]
+    fn test_filter_matching() {
+        let note = event("01", 1, 100, json!([["p", "bb"]]));
+
+        assert!(matches(&json!({"kinds": [1]}), &note));
+        assert!(!matches(&json!({"kinds": [0]}), &note));
+        assert!(matches(&json!({"authors": ["aa"], "#p": ["bb"]}), &note));
+        assert!(!matches(&json!({"#e": ["bb"]}), &note));
+        assert!(matches(&json!({"since": 100, "until": 100}), &note));
+        assert!(!matches(&json!({"since": 101}), &note));
+    }
+
+    #[test]
+    fn test_query_limit_newest_first() {
+        let events = vec![
+            event("01", 1, 100, json!([])),
+            event("02", 1, 300, json!([])),
+            event("03", 1, 200, json!([])),
+        ];
+
+        let ids: Vec<&str> = query(&events, &[json!({"kinds": [1], "limit": 2})])
+            .into_iter()
+            .map(|ev| ev["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["02", "03"]);
+    }
+
+    fn signed_note(sec: &[u8; 32], content: &str) -> String {
+        NoteBuilder::new()
+            .kind(1)
+            .content(content)
+            .options(NoteBuildOptions::default().created_at(true).sign(sec))
+            .build()
+            .unwrap()
+            .json()
+            .unwrap()
+    }
+
+    /// Pump the pool until `done` sees the message it's waiting for
+    fn recv_until(pool: &mut RelayPool, mut done: impl FnMut(&RelayMessage) -> bool) {
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(10) {
+            let Some(ev) = pool.try_recv() else {
+                std::thread::sleep(POLL_DELAY);
+                continue;
+            };
+
+            if let WsEvent::Message(WsMessage::Text(text)) = &ev.event {
+                if RelayMessage::from_json(text).is_ok_and(|msg| done(&msg)) {
+                    return;
+                }
+            }
+        }
+
+        panic!("timed out waiting on the mock relay");
+    }
+
+    #[test]
+    fn test_subscribe_and_publish() {
+        let kp = FullKeypair::generate();
+        let sec = kp.secret_key.to_secret_bytes();
+        let relay = MockRelay::start([signed_note(&sec, "canned")]).unwrap();
+
+        let mut pool = RelayPool::new();
+        pool.add_url(relay.url().to_owned(), || {}).unwrap();
+        pool.subscribe(
+            "sub".to_owned(),
+            vec![Filter::new()
+                .kinds([1])
+                .authors([kp.pubkey.bytes()])
+                .build()],
+        );
+
+        let mut got_canned = false;
+        recv_until(&mut pool, |msg| match msg {
+            RelayMessage::Event(_, ev) => {
+                got_canned |= ev.contains("canned");
+                false
+            }
+            RelayMessage::Eose(_) => true,
+            _ => false,
+        });
+        assert!(got_canned);
+
+        pool.send(&ClientMessage::event_json(signed_note(&sec, "published")).unwrap());
+
+        // we hear the OK and then our own note on the live subscription
+        let mut got_ok = false;
+        recv_until(&mut pool, |msg| match msg {
+            RelayMessage::OK(_) => {
+                got_ok = true;
+                false
+            }
+            RelayMessage::Event(_, ev) => ev.contains("published"),
+            _ => false,
+        });
+        assert!(got_ok);
+        assert_eq!(relay.published().len(), 1);
+    }
+}
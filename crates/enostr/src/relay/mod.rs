@@ -1,4 +1,4 @@
-use ewebsock::{Options, WsEvent, WsMessage, WsReceiver, WsSender};
+use ewebsock::{WsEvent, WsMessage};
 use mio::net::UdpSocket;
 use std::io;
 use std::net::IpAddr;
@@ -11,9 +11,23 @@ use std::hash::{Hash, Hasher};
 use std::net::Ipv4Addr;
 use tracing::{debug, error};
 
+pub mod deflate;
+pub mod dial;
 pub mod message;
+pub mod mock;
+pub mod mux;
+pub mod nip11;
 pub mod pool;
+pub mod roles;
+pub mod seen;
+pub mod stats;
+pub mod sub_limit;
 pub mod subs_debug;
+pub mod temporary;
+pub mod websocket;
+
+use dial::ConnectOptions;
+use websocket::{WsReceiver, WsSender};
 
 #[derive(Debug, Copy, Clone)]
 pub enum RelayStatus {
@@ -148,11 +162,23 @@ pub fn setup_multicast_relay(
     Ok(MulticastRelay::new(multicast_address, socket, interface))
 }
 
+/// What a relay connection negotiated for message compression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Messages go over the wire as-is
+    None,
+
+    /// permessage-deflate (RFC 7692)
+    Deflate,
+}
+
 pub struct Relay {
     pub url: nostr::RelayUrl,
     pub status: RelayStatus,
     pub sender: WsSender,
     pub receiver: WsReceiver,
+
+    pub connect_options: ConnectOptions,
 }
 
 impl fmt::Debug for Relay {
@@ -160,6 +186,7 @@ impl fmt::Debug for Relay {
         f.debug_struct("Relay")
             .field("url", &self.url)
             .field("status", &self.status)
+            .field("compression", &self.compression())
             .finish()
     }
 }
@@ -180,16 +207,20 @@ impl PartialEq for Relay {
 impl Eq for Relay {}
 
 impl Relay {
-    pub fn new(url: nostr::RelayUrl, wakeup: impl Fn() + Send + Sync + 'static) -> Result<Self> {
+    pub fn new(
+        url: nostr::RelayUrl,
+        connect_options: ConnectOptions,
+        wakeup: impl Fn() + Send + Sync + 'static,
+    ) -> Result<Self> {
         let status = RelayStatus::Connecting;
-        let (sender, receiver) =
-            ewebsock::connect_with_wakeup(url.as_str(), Options::default(), wakeup)?;
+        let (sender, receiver) = websocket::connect(url.as_str(), connect_options, wakeup)?;
 
         Ok(Self {
             url,
             sender,
             receiver,
             status,
+            connect_options,
         })
     }
 
@@ -211,13 +242,18 @@ impl Relay {
 
     pub fn connect(&mut self, wakeup: impl Fn() + Send + Sync + 'static) -> Result<()> {
         let (sender, receiver) =
-            ewebsock::connect_with_wakeup(self.url.as_str(), Options::default(), wakeup)?;
+            websocket::connect(self.url.as_str(), self.connect_options, wakeup)?;
         self.status = RelayStatus::Connecting;
         self.sender = sender;
         self.receiver = receiver;
         Ok(())
     }
 
+    /// What the current connection negotiated, None until it's open
+    pub fn compression(&self) -> Option<Compression> {
+        self.receiver.compression()
+    }
+
     pub fn ping(&mut self) {
         let msg = WsMessage::Ping(vec![]);
         self.sender.send(msg);
@@ -0,0 +1,381 @@
+//! Shared subscriptions. A deck with a column per profile asks for the same
+//! notes over and over, with only the author changing, and each of those is
+//! a REQ on every relay. Live subscriptions that only differ in their
+//! authors are merged here into one REQ with the authors put together, and
+//! what comes back is handed out again under each subscription's own id, so
+//! whoever subscribed can't tell the difference.
+//!
+//! Only live tails are merged: a `since` and no `limit`. A merged limit
+//! would be shared by every author in it, and one busy author could use
+//! all of it, so subscriptions that backfill go out on their own. Callers
+//! with a limited filter send it as a backfill with
+//! [`RelayPool::subscribe_backfill`](crate::RelayPool::subscribe_backfill)
+//! and only the live tail through here.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nostrdb::Filter;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::relay::message::RelayMessage;
+
+/// Most authors in one merged filter. Relays turn down filters much past
+/// this, so bigger groups get split up.
+pub const MAX_MERGED_AUTHORS: usize = 256;
+
+/// Merged REQs go out under ids starting with this
+const WIRE_PREFIX: &str = "mux-";
+
+/// When a merged REQ is sent again it goes back this far before the last
+/// time it was sent, for notes that showed up late or with a slow clock
+const RESEND_OVERLAP_SECS: u64 = 60;
+
+/// A filter that's only authors, kinds and a since, which is the only kind
+/// we merge
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AuthorsFilter {
+    authors: BTreeSet<[u8; 32]>,
+    kinds: Vec<u64>,
+    since: u64,
+}
+
+impl AuthorsFilter {
+    fn parse(filter: &Filter) -> Option<Self> {
+        let json = filter.json().ok()?;
+        let Value::Object(fields) = serde_json::from_str(&json).ok()? else {
+            return None;
+        };
+
+        let mut parsed = AuthorsFilter {
+            authors: BTreeSet::new(),
+            kinds: Vec::new(),
+            since: 0,
+        };
+        let mut since = None;
+        for (key, value) in fields {
+            match key.as_str() {
+                "authors" => {
+                    for author in value.as_array()? {
+                        let mut pubkey = [0u8; 32];
+                        hex::decode_to_slice(author.as_str()?, &mut pubkey).ok()?;
+                        parsed.authors.insert(pubkey);
+                    }
+                }
+                "kinds" => {
+                    parsed.kinds = value
+                        .as_array()?
+                        .iter()
+                        .map(Value::as_u64)
+                        .collect::<Option<_>>()?;
+                }
+                "since" => since = Some(value.as_u64()?),
+                _ => return None,
+            }
+        }
+        parsed.since = since?;
+
+        if parsed.authors.is_empty() || parsed.kinds.is_empty() {
+            return None;
+        }
+        parsed.kinds.sort_unstable();
+        parsed.kinds.dedup();
+        Some(parsed)
+    }
+}
+
+/// Subscriptions sharing a REQ
+#[derive(Debug)]
+struct Group {
+    kinds: Vec<u64>,
+    members: BTreeMap<String, AuthorsFilter>,
+}
+
+impl Group {
+    fn authors(&self) -> BTreeSet<&[u8; 32]> {
+        self.members
+            .values()
+            .flat_map(|member| member.authors.iter())
+            .collect()
+    }
+
+    /// Whether `filter` can go in with the others without the group
+    /// getting too big
+    fn fits(&self, filter: &AuthorsFilter) -> bool {
+        if self.kinds != filter.kinds {
+            return false;
+        }
+        let authors = self.authors();
+        let added = filter
+            .authors
+            .iter()
+            .filter(|author| !authors.contains(author))
+            .count();
+        authors.len() + added <= MAX_MERGED_AUTHORS
+    }
+
+    /// The one filter that covers every member. It goes back as far as the
+    /// member that needs it furthest. Members that were already in the last
+    /// REQ have had everything up to then, see [`Group::sent`].
+    fn filter(&self) -> Filter {
+        let since = self
+            .members
+            .values()
+            .map(|member| member.since)
+            .min()
+            .unwrap_or_default();
+
+        Filter::new()
+            .authors(self.authors())
+            .kinds(self.kinds.iter().copied())
+            .since(since)
+            .build()
+    }
+
+    /// The group's REQ went out at `now`, so asking again only has to go
+    /// back to about then, not to when each member started
+    fn sent(&mut self, now: u64) {
+        let caught_up = now.saturating_sub(RESEND_OVERLAP_SECS);
+        for member in self.members.values_mut() {
+            member.since = member.since.max(caught_up);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct EventAuthor {
+    pubkey: String,
+}
+
+#[derive(Debug, Default)]
+pub struct SubscriptionMux {
+    /// By the id the merged REQ goes out under
+    groups: HashMap<String, Group>,
+
+    /// Which group each shared subscription is in
+    wire_ids: HashMap<String, String>,
+    next_id: u64,
+}
+
+impl SubscriptionMux {
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Put `subid` in with the subscriptions it can share a REQ with.
+    /// Returns the id of the REQ it went in, which has to be sent again,
+    /// or None if its filters can't be merged and it should go out on its
+    /// own. A `subid` that's already in a group has to be removed first.
+    pub fn add(&mut self, subid: &str, filters: &[Filter]) -> Option<String> {
+        let [filter] = filters else {
+            return None;
+        };
+        let filter = AuthorsFilter::parse(filter)?;
+
+        let existing = self
+            .groups
+            .iter()
+            .find(|(_, group)| group.fits(&filter))
+            .map(|(wire_id, _)| wire_id.clone());
+        let wire_id = existing.unwrap_or_else(|| {
+            let wire_id = format!("{WIRE_PREFIX}{}", self.next_id);
+            self.next_id += 1;
+            self.groups.insert(
+                wire_id.clone(),
+                Group {
+                    kinds: filter.kinds.clone(),
+                    members: BTreeMap::new(),
+                },
+            );
+            wire_id
+        });
+
+        if let Some(group) = self.groups.get_mut(&wire_id) {
+            group.members.insert(subid.to_owned(), filter);
+        }
+        self.wire_ids.insert(subid.to_owned(), wire_id.clone());
+        Some(wire_id)
+    }
+
+    /// Take `subid` out of the REQ it was sharing. Returns that REQ's id,
+    /// which has to be sent again or, if nothing's left in it, closed.
+    pub fn remove(&mut self, subid: &str) -> Option<String> {
+        let wire_id = self.wire_ids.remove(subid)?;
+        if let Some(group) = self.groups.get_mut(&wire_id) {
+            group.members.remove(subid);
+            if group.members.is_empty() {
+                self.groups.remove(&wire_id);
+            }
+        }
+        Some(wire_id)
+    }
+
+    /// The filters a merged REQ goes out with, None once it's empty and
+    /// should be closed
+    pub fn filters(&self, wire_id: &str) -> Option<Vec<Filter>> {
+        self.groups.get(wire_id).map(|group| vec![group.filter()])
+    }
+
+    /// Call after sending `wire_id`'s REQ, so sending it again after the
+    /// next add or remove doesn't fetch what its members already have
+    pub fn sent(&mut self, wire_id: &str) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        if let Some(group) = self.groups.get_mut(wire_id) {
+            group.sent(now);
+        }
+    }
+
+    /// Every merged REQ, for a relay that just connected
+    pub fn reqs(&self) -> impl Iterator<Item = (&String, Vec<Filter>)> {
+        self.groups
+            .iter()
+            .map(|(wire_id, group)| (wire_id, vec![group.filter()]))
+    }
+
+    /// The shared subscriptions a note by `author` that came in on
+    /// `wire_id` is for
+    pub fn subscribers(&self, wire_id: &str, author: &[u8; 32]) -> Vec<&str> {
+        let Some(group) = self.groups.get(wire_id) else {
+            return vec![];
+        };
+        group
+            .members
+            .iter()
+            .filter(|(_, member)| member.authors.contains(author))
+            .map(|(subid, _)| subid.as_str())
+            .collect()
+    }
+
+    /// Turn a message for a merged REQ back into ones for the
+    /// subscriptions in it: a note goes to the subscription it's for, and
+    /// an EOSE to all of them. None for messages that weren't for a merged
+    /// REQ, which go through as they are.
+    pub fn demux(&self, text: &str) -> Option<Vec<String>> {
+        if !text.contains(WIRE_PREFIX) {
+            return None;
+        }
+
+        match RelayMessage::from_json(text).ok()? {
+            RelayMessage::Event(wire_id, event) => {
+                let group = self.groups.get(wire_id)?;
+                let author: EventAuthor = serde_json::from_str(event).ok()?;
+                let mut pubkey = [0u8; 32];
+                hex::decode_to_slice(&author.pubkey, &mut pubkey).ok()?;
+
+                // a note from an author that was just taken out of the
+                // group still goes somewhere, nostrdb wants it either way
+                let subid = self
+                    .subscribers(wire_id, &pubkey)
+                    .into_iter()
+                    .next()
+                    .or_else(|| group.members.keys().next().map(String::as_str))?;
+                Some(vec![format!("[\"EVENT\",{},{event}]", Value::from(subid))])
+            }
+
+            RelayMessage::Eose(wire_id) => {
+                let group = self.groups.get(wire_id)?;
+                Some(
+                    group
+                        .members
+                        .keys()
+                        .map(|subid| format!("[\"EOSE\",{}]", Value::from(subid.as_str())))
+                        .collect(),
+                )
+            }
+
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(pubkey: u8, since: u64) -> Vec<Filter> {
+        vec![Filter::new()
+            .authors([&[pubkey; 32]])
+            .kinds([1, 6])
+            .since(since)
+            .build()]
+    }
+
+    #[test]
+    fn profile_subscriptions_share_one_req() {
+        let mut mux = SubscriptionMux::default();
+        let wire_id = mux.add("alice", &profile(1, 1_000)).unwrap();
+        assert_eq!(mux.add("bob", &profile(2, 500)), Some(wire_id.clone()));
+
+        // other kinds, or anything but authors, aren't merged
+        let replies = Filter::new()
+            .authors([&[3; 32]])
+            .kinds([7])
+            .since(1)
+            .build();
+        assert_ne!(mux.add("carol", &[replies]), Some(wire_id.clone()));
+        let tagged = Filter::new().pubkeys([&[4; 32]]).kinds([1]).build();
+        assert_eq!(mux.add("dave", &[tagged]), None);
+
+        let merged = AuthorsFilter::parse(&mux.filters(&wire_id).unwrap()[0]).unwrap();
+        assert_eq!(merged.authors.len(), 2);
+        assert_eq!(merged.since, 500);
+
+        let note = format!(
+            r#"["EVENT","{wire_id}",{{"pubkey":"{}","kind":1}}]"#,
+            hex::encode([2u8; 32])
+        );
+        let demuxed = mux.demux(&note).unwrap();
+        assert!(demuxed[0].starts_with(r#"["EVENT","bob","#));
+
+        let eose = format!(r#"["EOSE","{wire_id}"]"#);
+        assert_eq!(
+            mux.demux(&eose).unwrap(),
+            vec![r#"["EOSE","alice"]"#, r#"["EOSE","bob"]"#]
+        );
+        assert_eq!(mux.demux(r#"["EOSE","alice"]"#), None);
+
+        assert_eq!(mux.remove("alice"), Some(wire_id.clone()));
+        assert!(mux.filters(&wire_id).is_some());
+        assert_eq!(mux.remove("bob"), Some(wire_id.clone()));
+        assert!(mux.filters(&wire_id).is_none());
+    }
+
+    #[test]
+    fn backfills_go_out_on_their_own() {
+        let mut mux = SubscriptionMux::default();
+        let limited = Filter::new()
+            .authors([&[1; 32]])
+            .kinds([1, 6])
+            .limit(100)
+            .build();
+        assert_eq!(mux.add("alice", &[limited]), None);
+
+        // with no since it would fetch everything the authors ever wrote
+        let everything = Filter::new().authors([&[2; 32]]).kinds([1, 6]).build();
+        assert_eq!(mux.add("bob", &[everything]), None);
+        assert!(mux.is_empty());
+    }
+
+    #[test]
+    fn resending_only_goes_back_for_new_members() {
+        let mut mux = SubscriptionMux::default();
+        let wire_id = mux.add("alice", &profile(1, 1_000)).unwrap();
+        mux.sent(&wire_id);
+
+        let merged = AuthorsFilter::parse(&mux.filters(&wire_id).unwrap()[0]).unwrap();
+        assert!(merged.since > 1_000);
+
+        // bob's column needs its own window, alice's doesn't come back
+        mux.add("bob", &profile(2, 2_000));
+        let merged = AuthorsFilter::parse(&mux.filters(&wire_id).unwrap()[0]).unwrap();
+        assert_eq!(merged.since, 2_000);
+        mux.sent(&wire_id);
+
+        mux.remove("bob");
+        let merged = AuthorsFilter::parse(&mux.filters(&wire_id).unwrap()[0]).unwrap();
+        assert!(merged.since > 2_000);
+    }
+}
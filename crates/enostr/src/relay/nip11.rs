@@ -0,0 +1,148 @@
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::ClientMessage;
+
+/// What a relay tells us about itself (NIP-11)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RelayInformation {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub software: Option<String>,
+    pub version: Option<String>,
+
+    /// None when the document doesn't list them, which tells us nothing
+    /// either way
+    pub supported_nips: Option<Vec<u16>>,
+
+    pub limitation: Option<RelayLimitation>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RelayLimitation {
+    pub max_subscriptions: Option<u32>,
+    pub max_filters: Option<u32>,
+
+    #[serde(default)]
+    pub auth_required: bool,
+
+    #[serde(default)]
+    pub payment_required: bool,
+}
+
+impl RelayInformation {
+    /// Whether the relay says it supports `nip`. Relays that don't list
+    /// their NIPs get the benefit of the doubt.
+    pub fn supports(&self, nip: u16) -> bool {
+        match &self.supported_nips {
+            Some(nips) => nips.contains(&nip),
+            None => true,
+        }
+    }
+}
+
+/// The NIP a relay has to support for a message to make sense to it.
+/// Relays that don't support it either ignore the message or close the
+/// subscription, so we don't send it to them.
+pub fn required_nip(msg: &ClientMessage) -> Option<u16> {
+    match msg {
+        ClientMessage::Count { .. } => Some(45),
+        ClientMessage::NegOpen { .. } => Some(77),
+        ClientMessage::Req { filters, .. } => filters
+            .iter()
+            .any(|filter| filter.json().is_ok_and(|json| json.contains("\"search\":")))
+            .then_some(50),
+        _ => None,
+    }
+}
+
+/// The http url a relay serves its NIP-11 document on
+pub fn info_url(relay_url: &str) -> Option<String> {
+    if let Some(rest) = relay_url.strip_prefix("wss://") {
+        Some(format!("https://{rest}"))
+    } else {
+        relay_url
+            .strip_prefix("ws://")
+            .map(|rest| format!("http://{rest}"))
+    }
+}
+
+/// Fetch a relay's NIP-11 document in the background
+pub fn fetch(
+    relay_url: &str,
+    user_agent: &str,
+    on_done: impl FnOnce(Result<RelayInformation, String>) + Send + 'static,
+) {
+    let Some(url) = info_url(relay_url) else {
+        on_done(Err(format!("{relay_url} isn't a websocket url")));
+        return;
+    };
+
+    let request = ehttp::Request {
+        headers: ehttp::Headers::new(&[
+            ("Accept", "application/nostr+json"),
+            ("User-Agent", user_agent),
+        ]),
+        ..ehttp::Request::get(&url)
+    };
+
+    ehttp::fetch(request, move |response| {
+        let info = response.and_then(|response| {
+            if !response.ok {
+                return Err(format!("{} {}", response.status, response.status_text));
+            }
+            serde_json::from_slice::<RelayInformation>(&response.bytes).map_err(|e| e.to_string())
+        });
+
+        if let Err(err) = &info {
+            debug!("no NIP-11 info for {url}: {err}");
+        }
+        on_done(info);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_relay_information() {
+        let json = r#"{
+            "name": "relay",
+            "supported_nips": [1, 11, 45, 50],
+            "software": "git+https://github.com/hoytech/strfry.git",
+            "limitation": {"max_subscriptions": 20, "auth_required": false},
+            "icon": "https://example.com/icon.png"
+        }"#;
+
+        let info: RelayInformation = serde_json::from_str(json).unwrap();
+        assert!(info.supports(50));
+        assert!(!info.supports(77));
+        assert_eq!(info.limitation.unwrap().max_subscriptions, Some(20));
+
+        let empty: RelayInformation = serde_json::from_str(r#"{"supported_nips": []}"#).unwrap();
+        assert!(!empty.supports(1));
+    }
+
+    #[test]
+    fn test_unlisted_nips_are_allowed() {
+        let json = r#"{"name": "relay", "limitation": {"max_subscriptions": 20}}"#;
+        let info: RelayInformation = serde_json::from_str(json).unwrap();
+        assert_eq!(info.supported_nips, None);
+        assert!(info.supports(45));
+        assert!(info.supports(77));
+    }
+
+    #[test]
+    fn test_info_url() {
+        assert_eq!(
+            info_url("wss://relay.damus.io/").as_deref(),
+            Some("https://relay.damus.io/")
+        );
+        assert_eq!(
+            info_url("ws://localhost:8080").as_deref(),
+            Some("http://localhost:8080")
+        );
+        assert_eq!(info_url("multicast"), None);
+    }
+}
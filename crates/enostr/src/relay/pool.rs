@@ -1,8 +1,18 @@
-use crate::relay::{setup_multicast_relay, MulticastRelay, Relay, RelayStatus};
+use crate::relay::dial::ConnectOptions;
+use crate::relay::message::RelayMessage;
+use crate::relay::mux::SubscriptionMux;
+use crate::relay::nip11::{self, required_nip, RelayInformation};
+use crate::relay::roles::{needed_role, RelayRoles};
+use crate::relay::seen::{event_key, Seen, SeenEvents};
+use crate::relay::stats::{RelayStats, REQ_TIMEOUT};
+use crate::relay::sub_limit::OpenSubs;
+use crate::relay::temporary::TemporaryRelays;
+use crate::relay::{setup_multicast_relay, Compression, MulticastRelay, Relay, RelayStatus};
 use crate::{ClientMessage, Error, Result};
 use nostrdb::Filter;
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::mpsc;
 use std::time::{Duration, Instant};
 
 use url::Url;
@@ -45,6 +55,28 @@ pub struct WebsocketRelay {
     pub last_ping: Instant,
     pub last_connect_attempt: Instant,
     pub retry_connect_after: Duration,
+    pub stats: RelayStats,
+
+    /// The relay's NIP-11 document, once we've fetched it
+    pub info: Option<RelayInformation>,
+
+    /// Subscriptions open on the relay, and the ones waiting for room
+    pub open_subs: OpenSubs,
+}
+
+/// A REQ we sent to a few of our best relays instead of all of them
+struct RoutedQuery {
+    filters: Vec<Filter>,
+    fanout: usize,
+
+    /// Every relay we've sent this to so far
+    tried: BTreeSet<String>,
+
+    /// Relays from the latest round we haven't seen EOSE from
+    waiting: BTreeSet<String>,
+    sent_at: Instant,
+
+    events: usize,
 }
 
 impl PoolRelay {
@@ -59,6 +91,9 @@ impl PoolRelay {
         match self {
             Self::Websocket(wsr) => {
                 wsr.relay.status = status;
+                if matches!(status, RelayStatus::Disconnected) {
+                    wsr.open_subs.clear();
+                }
             }
             Self::Multicast(_mcr) => {}
         }
@@ -78,10 +113,98 @@ impl PoolRelay {
         }
     }
 
+    pub fn stats(&self) -> Option<&RelayStats> {
+        match self {
+            Self::Websocket(wsr) => Some(&wsr.stats),
+            Self::Multicast(_) => None,
+        }
+    }
+
+    /// What the connection negotiated. None for relays that aren't
+    /// websockets or haven't connected yet.
+    pub fn compression(&self) -> Option<Compression> {
+        match self {
+            Self::Websocket(wsr) => wsr.relay.compression(),
+            Self::Multicast(_) => None,
+        }
+    }
+
+    /// Whether the relay supports a NIP. We assume it does until its
+    /// NIP-11 document tells us otherwise.
+    pub fn supports(&self, nip: u16) -> bool {
+        match self {
+            Self::Websocket(wsr) => match &wsr.info {
+                Some(info) => info.supports(nip),
+                None => true,
+            },
+            Self::Multicast(_) => true,
+        }
+    }
+
+    /// Whether the relay's NIP-11 document lists this NIP. Unlike
+    /// [`PoolRelay::supports`], not knowing counts as no.
+    pub fn advertises(&self, nip: u16) -> bool {
+        match self {
+            Self::Websocket(wsr) => wsr
+                .info
+                .as_ref()
+                .and_then(|info| info.supported_nips.as_ref())
+                .is_some_and(|nips| nips.contains(&nip)),
+            Self::Multicast(_) => false,
+        }
+    }
+
+    pub fn info(&self) -> Option<&RelayInformation> {
+        match self {
+            Self::Websocket(wsr) => wsr.info.as_ref(),
+            Self::Multicast(_) => None,
+        }
+    }
+
     pub fn send(&mut self, msg: &ClientMessage) -> Result<()> {
+        if let Some(nip) = required_nip(msg) {
+            // a COUNT costs the relay a query and most don't answer it,
+            // only ask the ones that say they do
+            let supported = if matches!(msg, ClientMessage::Count { .. }) {
+                self.advertises(nip)
+            } else {
+                self.supports(nip)
+            };
+            if !supported {
+                debug!(
+                    "not sending to {}, it doesn't support NIP-{nip}",
+                    self.url()
+                );
+                return Ok(());
+            }
+        }
+
         match self {
             Self::Websocket(wsr) => {
-                wsr.relay.send(msg);
+                let connected = matches!(wsr.relay.status, RelayStatus::Connected);
+                match msg {
+                    ClientMessage::Req { sub_id, filters } if connected => {
+                        let max = wsr.max_subscriptions();
+                        if !wsr.open_subs.on_req(sub_id, filters, max) {
+                            debug!(
+                                "{} is at its subscription limit, {sub_id} waits",
+                                wsr.relay.url.as_str()
+                            );
+                            return Ok(());
+                        }
+                        wsr.stats.on_req(sub_id);
+                        wsr.relay.send(msg);
+                    }
+                    ClientMessage::Close { sub_id } => {
+                        wsr.relay.send(msg);
+                        if let Some((next, filters)) = wsr.open_subs.on_close(sub_id) {
+                            debug!("sending {next} to {}", wsr.relay.url.as_str());
+                            wsr.stats.on_req(&next);
+                            wsr.relay.send(&ClientMessage::req(next, filters));
+                        }
+                    }
+                    _ => wsr.relay.send(msg),
+                }
                 Ok(())
             }
 
@@ -115,9 +238,17 @@ impl WebsocketRelay {
             last_ping: Instant::now(),
             last_connect_attempt: Instant::now(),
             retry_connect_after: Self::initial_reconnect_duration(),
+            stats: RelayStats::default(),
+            info: None,
+            open_subs: OpenSubs::default(),
         }
     }
 
+    /// How many subscriptions the relay lets us have open, if it said
+    pub fn max_subscriptions(&self) -> Option<u32> {
+        self.info.as_ref()?.limitation.as_ref()?.max_subscriptions
+    }
+
     pub fn initial_reconnect_duration() -> Duration {
         Duration::from_secs(5)
     }
@@ -126,7 +257,38 @@ impl WebsocketRelay {
 pub struct RelayPool {
     pub relays: Vec<PoolRelay>,
     pub ping_rate: Duration,
+    pub connect_options: ConnectOptions,
     pub debug: Option<SubsDebug>,
+    routed: HashMap<String, RoutedQuery>,
+
+    /// Events we've already handed out, copies from other relays are
+    /// dropped before they reach the app
+    seen: SeenEvents,
+
+    /// What each relay is for, by url. Relays that aren't here do
+    /// everything.
+    roles: HashMap<String, RelayRoles>,
+    roles_changed: bool,
+
+    /// Subscriptions open on the pool, so a relay whose roles change can
+    /// be caught up
+    subs: HashMap<String, Vec<Filter>>,
+
+    /// Subscriptions that are closed on each relay once it sends EOSE
+    backfills: HashSet<String>,
+
+    /// Relays we're only connected to while following a hint. They're
+    /// left out of everything that goes to the whole pool.
+    temporary: TemporaryRelays,
+
+    /// Subscriptions sharing REQs with others, and the messages for them
+    /// that are still to be handed out
+    mux: SubscriptionMux,
+    mux_pending: VecDeque<(String, WsEvent)>,
+
+    /// NIP-11 documents coming back from background fetches
+    info_tx: mpsc::Sender<(String, Result<RelayInformation, String>)>,
+    info_rx: mpsc::Receiver<(String, Result<RelayInformation, String>)>,
 }
 
 impl Default for RelayPool {
@@ -138,10 +300,23 @@ impl Default for RelayPool {
 impl RelayPool {
     // Constructs a new, empty RelayPool.
     pub fn new() -> RelayPool {
+        let (info_tx, info_rx) = mpsc::channel();
         RelayPool {
             relays: vec![],
             ping_rate: Duration::from_secs(45),
+            connect_options: ConnectOptions::default(),
             debug: None,
+            routed: HashMap::new(),
+            seen: SeenEvents::default(),
+            roles: HashMap::new(),
+            roles_changed: false,
+            subs: HashMap::new(),
+            backfills: HashSet::new(),
+            temporary: TemporaryRelays::default(),
+            mux: SubscriptionMux::default(),
+            mux_pending: VecDeque::new(),
+            info_tx,
+            info_rx,
         }
     }
 
@@ -163,6 +338,30 @@ impl RelayPool {
         self
     }
 
+    /// How long to wait on a relay's connection before giving up and
+    /// trying again later
+    pub fn connect_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.connect_options.connect_timeout = timeout;
+        for relay in &mut self.relays {
+            if let PoolRelay::Websocket(wsr) = relay {
+                wsr.relay.connect_options = self.connect_options;
+            }
+        }
+        self
+    }
+
+    /// The user agent we send to relays, in the websocket handshake and
+    /// when fetching NIP-11 documents
+    pub fn user_agent(&mut self, user_agent: &'static str) -> &mut Self {
+        self.connect_options.user_agent = user_agent;
+        for relay in &mut self.relays {
+            if let PoolRelay::Websocket(wsr) = relay {
+                wsr.relay.connect_options = self.connect_options;
+            }
+        }
+        self
+    }
+
     pub fn has(&self, url: &str) -> bool {
         for relay in &self.relays {
             if relay.url() == url {
@@ -173,15 +372,116 @@ impl RelayPool {
         false
     }
 
+    /// The relays we're configured to use, leaving out temporary ones
     pub fn urls(&self) -> BTreeSet<String> {
         self.relays
             .iter()
-            .map(|pool_relay| pool_relay.url().to_string())
+            .map(|pool_relay| pool_relay.url())
+            .filter(|url| !self.temporary.contains(url))
+            .map(|url| url.to_string())
             .collect()
     }
 
+    /// Whether we're only connected to `url` to follow a hint
+    pub fn is_temporary(&self, url: &str) -> bool {
+        self.temporary.contains(url)
+    }
+
+    pub fn roles(&self, url: &str) -> RelayRoles {
+        self.roles.get(url).copied().unwrap_or_default()
+    }
+
+    /// Every relay that doesn't do everything, for saving
+    pub fn all_roles(&self) -> &HashMap<String, RelayRoles> {
+        &self.roles
+    }
+
+    /// Change what `url` is for. Subscriptions it may no longer be sent
+    /// are closed on it, and ones it's newly allowed are sent to it.
+    pub fn set_roles(&mut self, url: &str, roles: RelayRoles) {
+        let old = self.roles(url);
+        if roles == old {
+            return;
+        }
+        if roles == RelayRoles::default() {
+            self.roles.remove(url);
+        } else {
+            self.roles.insert(url.to_owned(), roles);
+        }
+        self.roles_changed = true;
+
+        if self.temporary.contains(url) {
+            return;
+        }
+
+        let mut cmds = Vec::new();
+        for (subid, filters) in &self.subs {
+            let req = ClientMessage::req(subid.clone(), filters.clone());
+            match (old.allows(&req), roles.allows(&req)) {
+                (true, false) => cmds.push(ClientMessage::close(subid.clone())),
+                (false, true) => cmds.push(req),
+                _ => {}
+            }
+        }
+
+        let Some(relay) = self.relays.iter_mut().find(|relay| relay.url() == url) else {
+            return;
+        };
+        for cmd in &cmds {
+            if let Some(debug) = &mut self.debug {
+                debug.send_cmd(url.to_owned(), cmd);
+            }
+            if let Err(err) = relay.send(cmd) {
+                error!("error sending {:?} to {url}: {err}", cmd);
+            }
+        }
+    }
+
+    /// Roles saved from an earlier run
+    pub fn load_roles(&mut self, roles: HashMap<String, RelayRoles>) {
+        self.roles = roles;
+    }
+
+    /// Whether roles were changed since the last call, and should be saved
+    pub fn take_roles_changed(&mut self) -> bool {
+        std::mem::take(&mut self.roles_changed)
+    }
+
+    /// Whether `relay`'s roles let it be sent `msg`. The multicast relay
+    /// only ever gets our own events, so it's never held back.
+    fn role_allows(
+        roles: &HashMap<String, RelayRoles>,
+        relay: &PoolRelay,
+        msg: &ClientMessage,
+    ) -> bool {
+        match relay {
+            PoolRelay::Multicast(_) => true,
+            PoolRelay::Websocket(_) => match roles.get(relay.url()) {
+                Some(roles) => roles.allows(msg),
+                None => true,
+            },
+        }
+    }
+
+    /// Send `cmd` to every relay whose roles allow it
     pub fn send(&mut self, cmd: &ClientMessage) {
+        match cmd {
+            ClientMessage::Req { sub_id, filters } => {
+                self.subs.insert(sub_id.clone(), filters.clone());
+            }
+            ClientMessage::Close { sub_id } => {
+                self.subs.remove(sub_id);
+            }
+            _ => {}
+        }
+
         for relay in &mut self.relays {
+            if self.temporary.contains(relay.url()) {
+                continue;
+            }
+            if !Self::role_allows(&self.roles, relay, cmd) {
+                continue;
+            }
             if let Some(debug) = &mut self.debug {
                 debug.send_cmd(relay.url().to_owned(), cmd);
             }
@@ -192,6 +492,13 @@ impl RelayPool {
     }
 
     pub fn unsubscribe(&mut self, subid: String) {
+        if let Some(wire_id) = self.mux.remove(&subid) {
+            self.send_merged(wire_id);
+            return;
+        }
+        self.subs.remove(&subid);
+        self.backfills.remove(&subid);
+
         for relay in &mut self.relays {
             let cmd = ClientMessage::close(subid.clone());
             if let Some(debug) = &mut self.debug {
@@ -207,21 +514,183 @@ impl RelayPool {
         }
     }
 
+    /// Subscribe on every relay we read from, or search on if `filter`
+    /// is a search
     pub fn subscribe(&mut self, subid: String, filter: Vec<Filter>) {
+        self.subs.insert(subid.clone(), filter.clone());
+        let req = ClientMessage::req(subid, filter);
         for relay in &mut self.relays {
+            if self.temporary.contains(relay.url()) {
+                continue;
+            }
+            if !Self::role_allows(&self.roles, relay, &req) {
+                continue;
+            }
             if let Some(debug) = &mut self.debug {
-                debug.send_cmd(
-                    relay.url().to_owned(),
-                    &ClientMessage::req(subid.clone(), filter.clone()),
-                );
+                debug.send_cmd(relay.url().to_owned(), &req);
             }
 
-            if let Err(err) = relay.send(&ClientMessage::req(subid.clone(), filter.clone())) {
+            if let Err(err) = relay.send(&req) {
                 error!("error subscribing to {}: {err}", relay.url());
             }
         }
     }
 
+    /// Subscribe like [`RelayPool::subscribe`] for notes relays already
+    /// have. It's closed on each relay once that relay sends EOSE, so it
+    /// doesn't hold one of the relay's subscription slots after.
+    pub fn subscribe_backfill(&mut self, subid: String, filters: Vec<Filter>) {
+        self.backfills.insert(subid.clone());
+        self.subscribe(subid, filters);
+    }
+
+    /// Subscribe like [`RelayPool::subscribe`], but share a REQ with other
+    /// subscriptions that only differ in their authors, like the ones for
+    /// profile columns. Notes and EOSEs still come back under `subid`.
+    pub fn subscribe_shared(&mut self, subid: String, filters: Vec<Filter>) {
+        if let Some(wire_id) = self.mux.remove(&subid) {
+            self.send_merged(wire_id);
+        }
+
+        match self.mux.add(&subid, &filters) {
+            Some(wire_id) => {
+                debug!("{subid} shares {wire_id}");
+                self.send_merged(wire_id);
+            }
+            None => self.subscribe(subid, filters),
+        }
+    }
+
+    /// Send a merged REQ again after what's in it changed, or close it
+    /// once there's nothing left
+    fn send_merged(&mut self, wire_id: String) {
+        match self.mux.filters(&wire_id) {
+            // a REQ with an id that's already open replaces it
+            Some(filters) => {
+                self.subscribe(wire_id.clone(), filters);
+                self.mux.sent(&wire_id);
+            }
+            None => self.unsubscribe(wire_id),
+        }
+    }
+
+    /// Connected websocket relays, fastest and most reliable first.
+    /// Temporary relays aren't ranked, they're only there for a hint.
+    pub fn ranked_relays(&self) -> Vec<&str> {
+        let mut relays: Vec<(&str, f64)> = self
+            .relays
+            .iter()
+            .filter(|relay| matches!(relay.status(), RelayStatus::Connected))
+            .filter(|relay| !self.temporary.contains(relay.url()))
+            .filter_map(|relay| Some((relay.url(), relay.stats()?.score())))
+            .collect();
+
+        relays.sort_by(|a, b| a.1.total_cmp(&b.1));
+        relays.into_iter().map(|(url, _)| url).collect()
+    }
+
+    /// Send a REQ to only the `fanout` best relays. If they all EOSE
+    /// without sending anything we widen to the next best relays, until
+    /// something turns up or we run out of relays.
+    ///
+    /// This is for one-shot queries where any relay having the data is
+    /// enough, like fetching notes or profiles by id. Live subscriptions
+    /// should keep using [`RelayPool::subscribe`].
+    pub fn subscribe_routed(&mut self, subid: String, filters: Vec<Filter>, fanout: usize) {
+        let mut query = RoutedQuery {
+            filters,
+            fanout: fanout.max(1),
+            tried: BTreeSet::new(),
+            waiting: BTreeSet::new(),
+            sent_at: Instant::now(),
+            events: 0,
+        };
+
+        if self.send_routed(&subid, &mut query) {
+            self.routed.insert(subid, query);
+        } else {
+            // nothing is connected yet, so we have nothing to rank
+            self.subscribe(subid, query.filters);
+        }
+    }
+
+    /// Send the next round of a routed query. Returns false if there
+    /// were no relays left to try.
+    fn send_routed(&mut self, subid: &str, query: &mut RoutedQuery) -> bool {
+        let msg = ClientMessage::req(subid.to_owned(), query.filters.clone());
+        let role = needed_role(&msg);
+        let next: Vec<String> = self
+            .ranked_relays()
+            .into_iter()
+            .filter(|url| !query.tried.contains(*url))
+            .filter(|url| match role {
+                Some(role) => self.roles(url).has(role),
+                None => true,
+            })
+            .take(query.fanout)
+            .map(|url| url.to_owned())
+            .collect();
+
+        if next.is_empty() {
+            return false;
+        }
+
+        debug!("routing {subid} to {:?}", next);
+        for url in &next {
+            self.send_to(&msg, url);
+        }
+
+        query.tried.extend(next.iter().cloned());
+        query.waiting = next.into_iter().collect();
+        query.sent_at = Instant::now();
+        true
+    }
+
+    /// Finish or widen routed queries whose relays have all answered,
+    /// disconnected or timed out
+    fn update_routed(&mut self) {
+        if self.routed.is_empty() {
+            return;
+        }
+
+        let connected: BTreeSet<&str> = self
+            .relays
+            .iter()
+            .filter(|relay| matches!(relay.status(), RelayStatus::Connected))
+            .map(|relay| relay.url())
+            .collect();
+
+        let mut finished = Vec::new();
+        for (subid, query) in &mut self.routed {
+            if query.sent_at.elapsed() > REQ_TIMEOUT {
+                query.waiting.clear();
+            } else {
+                query.waiting.retain(|url| connected.contains(url.as_str()));
+            }
+
+            if query.waiting.is_empty() {
+                finished.push(subid.clone());
+            }
+        }
+
+        for subid in finished {
+            let Some(mut query) = self.routed.remove(&subid) else {
+                continue;
+            };
+
+            if query.events > 0 {
+                continue;
+            }
+
+            if self.send_routed(&subid, &mut query) {
+                debug!("widening {subid}, nothing found on {:?}", query.tried);
+                self.routed.insert(subid, query);
+            } else {
+                debug!("{subid}: nothing found on any relay");
+            }
+        }
+    }
+
     /// Keep relay connectiongs alive by pinging relays that haven't been
     /// pinged in awhile. Adjust ping rate with [`ping_rate`].
     pub fn keepalive_ping(&mut self, wakeup: impl Fn() + Send + Sync + Clone + 'static) {
@@ -259,12 +728,21 @@ impl RelayPool {
                             if should_ping {
                                 debug!("pinging {}", relay.relay.url);
                                 relay.relay.ping();
+                                relay.stats.on_ping();
                                 relay.last_ping = Instant::now();
                             }
+
+                            relay.stats.expire();
                         }
 
                         RelayStatus::Connecting => {
-                            // cool story bro
+                            // the connection thread gives up on its own after the
+                            // connect timeout, this catches a dns lookup that hangs
+                            let timeout = relay.relay.connect_options.connect_timeout * 2;
+                            if now - relay.last_connect_attempt > timeout {
+                                debug!("{} took too long to connect", relay.relay.url);
+                                relay.relay.status = RelayStatus::Disconnected;
+                            }
                         }
                     }
                 }
@@ -272,7 +750,43 @@ impl RelayPool {
         }
     }
 
-    pub fn send_to(&mut self, cmd: &ClientMessage, relay_url: &str) {
+    /// Send `cmd` to one relay, if its roles allow it. Returns whether it
+    /// was sent.
+    pub fn send_to(&mut self, cmd: &ClientMessage, relay_url: &str) -> bool {
+        let allowed = self
+            .relays
+            .iter()
+            .find(|relay| relay.url() == relay_url)
+            .is_some_and(|relay| Self::role_allows(&self.roles, relay, cmd));
+        if !allowed {
+            debug!("not sending to {relay_url}, its roles don't allow it");
+            return false;
+        }
+        self.send_to_ignoring_roles(cmd, relay_url)
+    }
+
+    /// Subscribe on one relay with filters meant just for it, like a
+    /// timeline's since-optimized ones. It's held back from temporary
+    /// relays and relays whose roles don't allow it, like
+    /// [`RelayPool::subscribe`]. The filters differ by relay, so they
+    /// aren't replayed when roles change. Returns whether it was sent.
+    pub fn subscribe_to(&mut self, subid: String, filters: Vec<Filter>, relay_url: &str) -> bool {
+        if self.temporary.contains(relay_url) {
+            return false;
+        }
+        self.send_to(&ClientMessage::req(subid, filters), relay_url)
+    }
+
+    /// Send `cmd` to one relay whatever its roles, for when the user
+    /// picked the relay themselves, like broadcasting to it or testing it.
+    /// Returns whether it was sent.
+    pub fn send_to_ignoring_roles(&mut self, cmd: &ClientMessage, relay_url: &str) -> bool {
+        // a subscription closed on any relay is done with, don't replay
+        // it when roles change
+        if let ClientMessage::Close { sub_id } = cmd {
+            self.subs.remove(sub_id);
+        }
+
         for relay in &mut self.relays {
             if relay.url() == relay_url {
                 if let Some(debug) = &mut self.debug {
@@ -280,10 +794,13 @@ impl RelayPool {
                 }
                 if let Err(err) = relay.send(cmd) {
                     error!("send_to err: {err}");
+                    return false;
                 }
-                return;
+                return true;
             }
         }
+
+        false
     }
 
     /// check whether a relay url is valid to add
@@ -311,19 +828,105 @@ impl RelayPool {
         wakeup: impl Fn() + Send + Sync + Clone + 'static,
     ) -> Result<()> {
         let url = Self::canonicalize_url(url);
+        if self.temporary.remove(&url) {
+            // reconnect it as a regular relay, so the app sees it open
+            // and sends it the usual subscriptions
+            self.relays.retain(|relay| relay.url() != url);
+        }
         // Check if the URL already exists in the pool.
         if self.has(&url) {
             return Ok(());
         }
+        self.push_websocket(&url, wakeup.clone())?;
+
+        let tx = self.info_tx.clone();
+        let info_url = url.clone();
+        nip11::fetch(&info_url, self.connect_options.user_agent, move |info| {
+            let _ = tx.send((url, info));
+            wakeup();
+        });
+
+        Ok(())
+    }
+
+    fn push_websocket(
+        &mut self,
+        url: &str,
+        wakeup: impl Fn() + Send + Sync + 'static,
+    ) -> Result<()> {
         let relay = Relay::new(
             nostr::RelayUrl::parse(url).map_err(|_| Error::InvalidRelayUrl)?,
+            self.connect_options,
             wakeup,
         )?;
-        let pool_relay = PoolRelay::websocket(relay);
+        self.relays.push(PoolRelay::websocket(relay));
+        Ok(())
+    }
 
-        self.relays.push(pool_relay);
+    /// Run a one-off query on a relay we might not normally use, like
+    /// one hinted in an nevent. If it isn't in the pool it's connected
+    /// just for this, and dropped again once every query sent to it this
+    /// way has finished and it's been idle a while. Returns false when
+    /// there were already too many temporary relays to add another.
+    pub fn subscribe_temporary(
+        &mut self,
+        relay_url: String,
+        subid: String,
+        filters: Vec<Filter>,
+        wakeup: impl Fn() + Send + Sync + 'static,
+    ) -> bool {
+        let url = Self::canonicalize_url(relay_url);
+        if self.has(&url) && !self.temporary.contains(&url) {
+            self.send_to(&ClientMessage::req(subid, filters), &url);
+            return true;
+        }
 
-        Ok(())
+        let now = Instant::now();
+        if !self.temporary.contains(&url) {
+            if !self.temporary.has_room() {
+                let Some(done) = self.temporary.evict_done() else {
+                    debug!("too many temporary relays, not connecting to {url}");
+                    return false;
+                };
+                debug!("dropping temporary relay {done} to make room for {url}");
+                self.relays.retain(|relay| relay.url() != done);
+            }
+
+            if let Err(err) = self.push_websocket(&url, wakeup) {
+                error!("couldn't connect to temporary relay {url}: {err}");
+                return false;
+            }
+            debug!("connecting to {url} temporarily");
+            self.temporary.insert(url.clone(), now);
+        }
+
+        self.temporary
+            .add_sub(&url, subid.clone(), filters.clone(), now);
+        let connected = self
+            .relays
+            .iter()
+            .find(|relay| relay.url() == url)
+            .is_some_and(|relay| matches!(relay.status(), RelayStatus::Connected));
+        if connected {
+            // otherwise it goes out when the relay opens
+            self.send_to_ignoring_roles(&ClientMessage::req(subid, filters), &url);
+        }
+
+        true
+    }
+
+    /// Disconnect temporary relays that are done
+    fn update_temporary(&mut self) {
+        if self.temporary.is_empty() {
+            return;
+        }
+        let idle = self.temporary.take_idle(Instant::now());
+        if idle.is_empty() {
+            return;
+        }
+        debug!("disconnecting idle temporary relays {:?}", idle);
+        self.relays
+            .retain(|relay| !idle.iter().any(|url| url == relay.url()));
     }
 
     pub fn add_urls(
@@ -342,6 +945,21 @@ impl RelayPool {
             .retain(|pool_relay| !urls.contains(pool_relay.url()));
     }
 
+    fn update_relay_info(&mut self) {
+        while let Ok((url, info)) = self.info_rx.try_recv() {
+            let Ok(info) = info else {
+                // without a document we keep assuming it supports everything
+                continue;
+            };
+
+            let relay = self.relays.iter_mut().find(|relay| relay.url() == url);
+            if let Some(PoolRelay::Websocket(wsr)) = relay {
+                debug!("{url} supports NIPs {:?}", info.supported_nips);
+                wsr.info = Some(info);
+            }
+        }
+    }
+
     // standardize the format (ie, trailing slashes)
     fn canonicalize_url(url: String) -> String {
         match Url::parse(&url) {
@@ -355,6 +973,19 @@ impl RelayPool {
     /// receive a message from each. If a message is received, return it.
     /// If no message is received from any relays, None is returned.
     pub fn try_recv(&mut self) -> Option<PoolEvent<'_>> {
+        self.update_routed();
+        self.update_relay_info();
+        self.update_temporary();
+
+        if let Some((url, event)) = self.mux_pending.pop_front() {
+            if let Some(relay) = self.relays.iter().find(|relay| relay.url() == url) {
+                return Some(PoolEvent {
+                    relay: relay.url(),
+                    event,
+                });
+            }
+        }
+
         for relay in &mut self.relays {
             if let PoolRelay::Multicast(mcr) = relay {
                 // try rejoin on multicast
@@ -365,19 +996,54 @@ impl RelayPool {
                 }
             }
 
-            if let Some(event) = relay.try_recv() {
+            if let Some(event) = recv_unseen(relay, &mut self.seen, &mut self.routed) {
                 match &event {
                     WsEvent::Opened => {
                         relay.set_status(RelayStatus::Connected);
+                        let mut reqs: Vec<ClientMessage> = self
+                            .temporary
+                            .subs(relay.url())
+                            .map(|(subid, filters)| {
+                                ClientMessage::req(subid.clone(), filters.clone())
+                            })
+                            .collect();
+                        if !self.temporary.contains(relay.url()) {
+                            reqs.extend(
+                                self.mux
+                                    .reqs()
+                                    .map(|(wire_id, filters)| {
+                                        ClientMessage::req(wire_id.clone(), filters)
+                                    })
+                                    .filter(|req| Self::role_allows(&self.roles, relay, req)),
+                            );
+                        }
+                        for req in &reqs {
+                            if let Err(err) = relay.send(req) {
+                                error!("error subscribing to {}: {err}", relay.url());
+                            }
+                        }
                     }
                     WsEvent::Closed => {
                         relay.set_status(RelayStatus::Disconnected);
+                        if let PoolRelay::Websocket(wsr) = relay {
+                            wsr.stats.on_disconnect();
+                        }
                     }
                     WsEvent::Error(err) => {
                         error!("{:?}", err);
                         relay.set_status(RelayStatus::Disconnected);
+                        if let PoolRelay::Websocket(wsr) = relay {
+                            wsr.stats.on_disconnect();
+                        }
                     }
                     WsEvent::Message(ev) => {
+                        if self.temporary.contains(relay.url()) {
+                            finish_temporary(relay, &mut self.temporary, ev);
+                        }
+                        if !self.backfills.is_empty() {
+                            finish_backfill(relay, &self.backfills, ev);
+                        }
+
                         // let's just handle pongs here.
                         // We only need to do this natively.
                         #[cfg(not(target_arch = "wasm32"))]
@@ -393,6 +1059,27 @@ impl RelayPool {
                     }
                 }
 
+                // hand messages for merged REQs to the subscriptions in them
+                let event = match event {
+                    WsEvent::Message(WsMessage::Text(text)) => match self.mux.demux(&text) {
+                        Some(messages) => {
+                            let mut messages = messages.into_iter();
+                            let Some(first) = messages.next() else {
+                                continue;
+                            };
+                            for message in messages {
+                                self.mux_pending.push_back((
+                                    relay.url().to_owned(),
+                                    WsEvent::Message(WsMessage::Text(message)),
+                                ));
+                            }
+                            WsEvent::Message(WsMessage::Text(first))
+                        }
+                        None => WsEvent::Message(WsMessage::Text(text)),
+                    },
+                    event => event,
+                };
+
                 if let Some(debug) = &mut self.debug {
                     debug.receive_cmd(relay.url().to_owned(), (&event).into());
                 }
@@ -409,3 +1096,129 @@ impl RelayPool {
         None
     }
 }
+
+/// Receive the next event from a relay, dropping EVENTs this relay has
+/// already sent us so they don't get parsed again. Copies from other
+/// relays still go through so nostrdb records where the note was seen.
+/// Stats are updated here too, while we have the message parsed.
+fn recv_unseen(
+    relay: &mut PoolRelay,
+    seen: &mut SeenEvents,
+    routed: &mut HashMap<String, RoutedQuery>,
+) -> Option<WsEvent> {
+    loop {
+        let event = relay.try_recv()?;
+
+        let PoolRelay::Websocket(wsr) = &mut *relay else {
+            return Some(event);
+        };
+        let WsEvent::Message(msg) = &event else {
+            return Some(event);
+        };
+
+        let parsed = match msg {
+            WsMessage::Text(text) => RelayMessage::from_json(text).ok(),
+            _ => None,
+        };
+
+        // a duplicate still counts as a hit for routed queries
+        track_message(
+            &mut wsr.stats,
+            routed,
+            wsr.relay.url.as_str(),
+            msg,
+            parsed.as_ref(),
+        );
+
+        // the relay ended a subscription itself, which frees its slot
+        if let Some(RelayMessage::Closed(subid, _)) = &parsed {
+            if let Some((next, filters)) = wsr.open_subs.on_close(subid) {
+                debug!("sending {next} to {}", wsr.relay.url.as_str());
+                wsr.stats.on_req(&next);
+                wsr.relay.send(&ClientMessage::req(next, filters));
+            }
+        }
+
+        let key = match parsed {
+            Some(RelayMessage::Event(_, ev)) => event_key(ev),
+            _ => None,
+        };
+        let Some(key) = key else {
+            return Some(event);
+        };
+
+        let status = seen.insert(key, wsr.relay.url.as_str());
+        wsr.stats.on_event(status != Seen::New);
+
+        if status != Seen::SameRelay {
+            return Some(event);
+        }
+    }
+}
+
+/// Close a temporary relay's subscription once it's sent everything
+fn finish_temporary(relay: &mut PoolRelay, temporary: &mut TemporaryRelays, msg: &WsMessage) {
+    let now = Instant::now();
+    let WsMessage::Text(text) = msg else {
+        temporary.touch(relay.url(), now);
+        return;
+    };
+
+    match RelayMessage::from_json(text) {
+        Ok(RelayMessage::Eose(subid)) => {
+            if temporary.finish_sub(relay.url(), subid, now) {
+                if let Err(err) = relay.send(&ClientMessage::close(subid.to_owned())) {
+                    error!("error closing {subid} on {}: {err}", relay.url());
+                }
+            }
+        }
+        _ => temporary.touch(relay.url(), now),
+    }
+}
+
+/// Close a backfill subscription on a relay once it's sent what it has
+fn finish_backfill(relay: &mut PoolRelay, backfills: &HashSet<String>, msg: &WsMessage) {
+    let WsMessage::Text(text) = msg else {
+        return;
+    };
+    if !text.contains("EOSE") {
+        return;
+    }
+
+    if let Ok(RelayMessage::Eose(subid)) = RelayMessage::from_json(text) {
+        if backfills.contains(subid) {
+            if let Err(err) = relay.send(&ClientMessage::close(subid.to_owned())) {
+                error!("error closing {subid} on {}: {err}", relay.url());
+            }
+        }
+    }
+}
+
+/// Update latency stats and routed queries from an incoming message
+fn track_message(
+    stats: &mut RelayStats,
+    routed: &mut HashMap<String, RoutedQuery>,
+    relay_url: &str,
+    msg: &WsMessage,
+    parsed: Option<&RelayMessage>,
+) {
+    if let WsMessage::Pong(_) = msg {
+        stats.on_pong();
+        return;
+    }
+
+    match parsed {
+        Some(RelayMessage::Eose(subid)) => {
+            stats.on_eose(subid);
+            if let Some(query) = routed.get_mut(*subid) {
+                query.waiting.remove(relay_url);
+            }
+        }
+        Some(RelayMessage::Event(subid, _)) => {
+            if let Some(query) = routed.get_mut(*subid) {
+                query.events += 1;
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+use crate::relay::nip11::required_nip;
+use crate::ClientMessage;
+
+/// What we use a relay for. Relays start out doing everything and the
+/// user can turn roles off per relay; the pool only sends a relay the
+/// messages its roles allow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayRoles {
+    /// Subscriptions and queries
+    pub read: bool,
+
+    /// Publishing our events
+    pub write: bool,
+
+    /// NIP-50 search queries
+    pub search: bool,
+}
+
+impl Default for RelayRoles {
+    fn default() -> Self {
+        Self {
+            read: true,
+            write: true,
+            search: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayRole {
+    Read,
+    Write,
+    Search,
+}
+
+impl RelayRoles {
+    pub fn has(&self, role: RelayRole) -> bool {
+        match role {
+            RelayRole::Read => self.read,
+            RelayRole::Write => self.write,
+            RelayRole::Search => self.search,
+        }
+    }
+
+    /// Whether a relay with these roles should get `msg`
+    pub fn allows(&self, msg: &ClientMessage) -> bool {
+        match needed_role(msg) {
+            Some(role) => self.has(role),
+            None => true,
+        }
+    }
+}
+
+/// The role a relay needs to be sent `msg`. None for messages that go
+/// wherever they're needed, like closing a subscription we already sent.
+pub fn needed_role(msg: &ClientMessage) -> Option<RelayRole> {
+    match msg {
+        ClientMessage::Event(_) => Some(RelayRole::Write),
+        ClientMessage::Req { .. } | ClientMessage::Count { .. } | ClientMessage::NegOpen { .. } => {
+            if required_nip(msg) == Some(50) {
+                Some(RelayRole::Search)
+            } else {
+                Some(RelayRole::Read)
+            }
+        }
+        ClientMessage::Close { .. }
+        | ClientMessage::NegMsg { .. }
+        | ClientMessage::NegClose { .. }
+        | ClientMessage::Raw(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Filter;
+
+    #[test]
+    fn messages_need_the_matching_role() {
+        let req = ClientMessage::req("a".to_owned(), vec![Filter::new().kinds([1]).build()]);
+        let search = ClientMessage::req(
+            "b".to_owned(),
+            vec![Filter::new().search("gm").kinds([1]).build()],
+        );
+        let close = ClientMessage::close("a".to_owned());
+
+        assert_eq!(needed_role(&req), Some(RelayRole::Read));
+        assert_eq!(needed_role(&search), Some(RelayRole::Search));
+        assert_eq!(needed_role(&close), None);
+
+        let write_only = RelayRoles {
+            read: false,
+            write: true,
+            search: false,
+        };
+        assert!(!write_only.allows(&req));
+        assert!(!write_only.allows(&search));
+        assert!(write_only.allows(&close));
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::{HashMap, VecDeque};
+
+/// How many events we remember. Old ones fall out first.
+const DEFAULT_CAPACITY: usize = 50_000;
+
+/// An event's id and signature. A copy with a forged signature has a
+/// different key, so it can't shadow the real event before nostrdb has
+/// had a chance to reject it.
+pub type EventKey = ([u8; 32], [u8; 64]);
+
+/// How an incoming event compares to the ones we've handed to the app
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Seen {
+    New,
+
+    /// Seen before, but not from this relay. It still goes to nostrdb so
+    /// the relay is recorded as somewhere the note can be found; nostrdb
+    /// doesn't verify a note it already has again.
+    OtherRelay,
+
+    /// This relay already sent it, on this or another subscription
+    SameRelay,
+}
+
+/// Events we've already handed to the app this session and the relays
+/// they came from, so repeats from the same relay can be dropped before
+/// they get parsed again
+#[derive(Debug)]
+pub struct SeenEvents {
+    relays: HashMap<EventKey, Vec<String>>,
+    order: VecDeque<EventKey>,
+    capacity: usize,
+}
+
+impl Default for SeenEvents {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl SeenEvents {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            relays: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Remember that `relay` sent the event
+    pub fn insert(&mut self, key: EventKey, relay: &str) -> Seen {
+        if let Some(relays) = self.relays.get_mut(&key) {
+            if relays.iter().any(|r| r == relay) {
+                return Seen::SameRelay;
+            }
+            relays.push(relay.to_owned());
+            return Seen::OtherRelay;
+        }
+
+        self.relays.insert(key, vec![relay.to_owned()]);
+        self.order.push_back(key);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.relays.remove(&oldest);
+            }
+        }
+
+        Seen::New
+    }
+
+    pub fn len(&self) -> usize {
+        self.relays.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.relays.is_empty()
+    }
+}
+
+/// Pull the event id and signature out of a raw `["EVENT", <subid>,
+/// <event>]` message without parsing the whole thing.
+pub fn event_key(msg: &str) -> Option<EventKey> {
+    Some((hex_field(msg, "id")?, hex_field(msg, "sig")?))
+}
+
+/// The hex value of the event's `key` field.
+///
+/// Quotes inside json strings are always escaped and events have no
+/// nested objects, so the first `"<key>"` followed by a colon is the
+/// event's own key. A tag like `["id", ...]` is followed by a comma.
+fn hex_field<const N: usize>(msg: &str, key: &str) -> Option<[u8; N]> {
+    let quoted = format!("\"{key}\"");
+    let mut rest = msg;
+    let value = loop {
+        let at = rest.find(&quoted)?;
+        rest = &rest[at + quoted.len()..];
+        if let Some(value) = rest.trim_start().strip_prefix(':') {
+            break value;
+        }
+    };
+
+    let hex = value.trim_start().strip_prefix('"')?.get(..N * 2)?;
+
+    let mut bytes = [0u8; N];
+    hex::decode_to_slice(hex, &mut bytes).ok()?;
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "b2d670de53b27691c0c3400225b65c35a26d06093bcc41f48ffc71e0907f9d4a";
+    const SIG: &str = "2091b7f720586d7420ea7a90406ea856378339c8b0b3f3e695ccbfebaa8c4ea20a3cb850ff18cae957aa2e0ecb06c386d0bd27aa7a13bf7a8f7425a4c2a57903";
+
+    #[test]
+    fn test_event_key() {
+        let msg = format!(
+            r#"["EVENT","sub",{{"tags":[["id","x"],["sig","y"]],"content":"\"id\":\"nope\"","id": "{ID}","sig":"{SIG}"}}]"#
+        );
+        let (id, sig) = event_key(&msg).unwrap();
+        assert_eq!(hex::encode(id), ID);
+        assert_eq!(hex::encode(sig), SIG);
+
+        assert_eq!(event_key(r#"["EVENT","sub",{"id":"abc"}]"#), None);
+        assert_eq!(
+            event_key(&format!(r#"["EVENT","sub",{{"id":"{ID}"}}]"#)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_seen_events_by_relay() {
+        let mut seen = SeenEvents::new(10);
+        let key = ([1; 32], [1; 64]);
+        assert_eq!(seen.insert(key, "wss://a"), Seen::New);
+        assert_eq!(seen.insert(key, "wss://a"), Seen::SameRelay);
+        assert_eq!(seen.insert(key, "wss://b"), Seen::OtherRelay);
+        assert_eq!(seen.insert(key, "wss://b"), Seen::SameRelay);
+    }
+
+    #[test]
+    fn test_forged_signature_doesnt_shadow_the_event() {
+        let mut seen = SeenEvents::new(10);
+        assert_eq!(seen.insert(([1; 32], [0; 64]), "wss://a"), Seen::New);
+        assert_eq!(seen.insert(([1; 32], [1; 64]), "wss://a"), Seen::New);
+    }
+
+    #[test]
+    fn test_seen_events_evicts_oldest() {
+        let mut seen = SeenEvents::new(2);
+        let key = |n| ([n; 32], [n; 64]);
+        assert_eq!(seen.insert(key(1), "r"), Seen::New);
+        assert_eq!(seen.insert(key(1), "r"), Seen::SameRelay);
+        assert_eq!(seen.insert(key(2), "r"), Seen::New);
+        assert_eq!(seen.insert(key(3), "r"), Seen::New);
+
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen.insert(key(1), "r"), Seen::New);
+        assert_eq!(seen.insert(key(3), "r"), Seen::SameRelay);
+    }
+}
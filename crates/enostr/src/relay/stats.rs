@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How much a new RTT sample moves the rolling average
+const RTT_SMOOTHING: f64 = 0.2;
+
+/// What we assume for relays we haven't measured yet. Low enough that
+/// new relays get tried, high enough that measured fast relays win.
+const DEFAULT_RTT: Duration = Duration::from_millis(400);
+
+/// A REQ that hasn't seen EOSE by now counts against the relay
+pub const REQ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Rolling latency and reliability stats for a relay
+#[derive(Debug, Default)]
+pub struct RelayStats {
+    /// Exponentially weighted moving average of round trip times
+    rtt: Option<Duration>,
+
+    /// REQs we're waiting on EOSE for, by subid
+    pending: HashMap<String, Instant>,
+
+    /// When we sent a ping we haven't seen a pong for
+    ping_sent: Option<Instant>,
+
+    answered: u32,
+    failed: u32,
+
+    /// EVENTs received, and how many of those we'd already seen from
+    /// another relay (or this one)
+    events: u64,
+    duplicates: u64,
+}
+
+impl RelayStats {
+    pub fn rtt(&self) -> Option<Duration> {
+        self.rtt
+    }
+
+    /// Fraction of requests the relay answered, with a prior so a single
+    /// failure on a new relay doesn't sink it
+    pub fn reliability(&self) -> f64 {
+        (self.answered as f64 + 1.0) / (self.answered as f64 + self.failed as f64 + 2.0)
+    }
+
+    /// Lower is better. Expected time to get an answer, scaled up by how
+    /// often the relay doesn't give one.
+    pub fn score(&self) -> f64 {
+        let rtt = self.rtt.unwrap_or(DEFAULT_RTT).as_secs_f64() * 1000.0;
+        rtt / self.reliability()
+    }
+
+    pub fn events(&self) -> u64 {
+        self.events
+    }
+
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    pub fn on_event(&mut self, duplicate: bool) {
+        self.events += 1;
+        if duplicate {
+            self.duplicates += 1;
+        }
+    }
+
+    pub fn on_req(&mut self, subid: &str) {
+        self.pending.insert(subid.to_owned(), Instant::now());
+    }
+
+    pub fn on_eose(&mut self, subid: &str) {
+        if let Some(sent) = self.pending.remove(subid) {
+            self.answered += 1;
+            self.sample(sent.elapsed());
+        }
+    }
+
+    pub fn on_ping(&mut self) {
+        self.ping_sent = Some(Instant::now());
+    }
+
+    pub fn on_pong(&mut self) {
+        if let Some(sent) = self.ping_sent.take() {
+            self.sample(sent.elapsed());
+        }
+    }
+
+    /// Anything still outstanding when the connection drops failed
+    pub fn on_disconnect(&mut self) {
+        self.failed += self.pending.len() as u32;
+        self.pending.clear();
+        self.ping_sent = None;
+    }
+
+    /// Count REQs that have gone unanswered for too long as failures
+    pub fn expire(&mut self) {
+        let before = self.pending.len();
+        self.pending.retain(|_, sent| sent.elapsed() < REQ_TIMEOUT);
+        self.failed += (before - self.pending.len()) as u32;
+    }
+
+    fn sample(&mut self, rtt: Duration) {
+        self.rtt = Some(match self.rtt {
+            None => rtt,
+            Some(avg) => avg.mul_f64(1.0 - RTT_SMOOTHING) + rtt.mul_f64(RTT_SMOOTHING),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtt_smoothing() {
+        let mut stats = RelayStats::default();
+        stats.sample(Duration::from_millis(100));
+        assert_eq!(stats.rtt(), Some(Duration::from_millis(100)));
+
+        stats.sample(Duration::from_millis(600));
+        let rtt = stats.rtt().unwrap().as_secs_f64();
+        assert!((rtt - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_unreliable_relays_score_worse() {
+        let mut good = RelayStats::default();
+        let mut flaky = RelayStats::default();
+        good.sample(Duration::from_millis(200));
+        flaky.sample(Duration::from_millis(200));
+
+        good.on_req("a");
+        good.on_eose("a");
+
+        flaky.on_req("a");
+        flaky.on_req("b");
+        flaky.on_disconnect();
+
+        assert!(good.score() < flaky.score());
+    }
+}
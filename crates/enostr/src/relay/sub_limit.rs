@@ -0,0 +1,94 @@
+//! Keeping under a relay's subscription limit. Relays that publish a
+//! `max_subscriptions` in their NIP-11 document close or refuse REQs past
+//! it, so once a relay is at its limit new REQs wait here until one of the
+//! open ones is closed, by us or by the relay with a CLOSED.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use nostrdb::Filter;
+
+#[derive(Debug, Default)]
+pub struct OpenSubs {
+    open: BTreeSet<String>,
+
+    /// REQs waiting for room, oldest first
+    queued: VecDeque<(String, Vec<Filter>)>,
+}
+
+impl OpenSubs {
+    pub fn len(&self) -> usize {
+        self.open.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.open.is_empty()
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// A REQ is about to go out. Returns whether it can, or has to wait
+    /// for room under `max`. A REQ for a subscription that's already open
+    /// replaces it, so it can always go.
+    pub fn on_req(&mut self, subid: &str, filters: &[Filter], max: Option<u32>) -> bool {
+        if self.open.contains(subid) {
+            return true;
+        }
+
+        let full = max.is_some_and(|max| self.open.len() >= max as usize);
+        if full {
+            match self.queued.iter_mut().find(|(queued, _)| queued == subid) {
+                Some((_, queued)) => *queued = filters.to_vec(),
+                None => self.queued.push_back((subid.to_owned(), filters.to_vec())),
+            }
+            return false;
+        }
+
+        self.open.insert(subid.to_owned());
+        true
+    }
+
+    /// `subid` was closed, by us or the relay. Returns the REQ that gets its place, if one
+    /// was waiting.
+    pub fn on_close(&mut self, subid: &str) -> Option<(String, Vec<Filter>)> {
+        self.queued.retain(|(queued, _)| queued != subid);
+        if !self.open.remove(subid) {
+            return None;
+        }
+
+        let (next, filters) = self.queued.pop_front()?;
+        self.open.insert(next.clone());
+        Some((next, filters))
+    }
+
+    /// The relay disconnected, and took its subscriptions with it
+    pub fn clear(&mut self) {
+        self.open.clear();
+        self.queued.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reqs_past_the_limit_wait_for_a_close() {
+        let filters = vec![Filter::new().kinds([1]).build()];
+        let mut subs = OpenSubs::default();
+
+        assert!(subs.on_req("a", &filters, Some(2)));
+        assert!(subs.on_req("b", &filters, Some(2)));
+        assert!(!subs.on_req("c", &filters, Some(2)));
+        assert!(subs.on_req("a", &filters, Some(2)));
+        assert_eq!(subs.queued(), 1);
+
+        assert_eq!(
+            subs.on_close("a").map(|(subid, _)| subid).as_deref(),
+            Some("c")
+        );
+        assert_eq!(subs.len(), 2);
+        assert_eq!(subs.on_close("zzz"), None);
+    }
+}
@@ -60,6 +60,10 @@ impl From<RelayEvent<'_>> for OwnedRelayEvent {
                     RelayMessage::Eose(s) => format!("EOSE:{}", s),
                     RelayMessage::Event(_, s) => format!("EVENT:{}", s),
                     RelayMessage::Notice(s) => format!("NOTICE:{}", s),
+                    RelayMessage::Count(s, n) => format!("COUNT:{}:{}", s, n),
+                    RelayMessage::NegMsg(s, _) => format!("NEG-MSG:{}", s),
+                    RelayMessage::NegErr(s, reason) => format!("NEG-ERR:{}:{}", s, reason),
+                    RelayMessage::Closed(s, reason) => format!("CLOSED:{}:{}", s, reason),
                 };
                 OwnedRelayEvent::Message(relay_msg)
             }
@@ -194,6 +198,27 @@ fn calculate_client_message_size(message: &ClientMessage) -> usize {
         ClientMessage::Close { sub_id } => {
             mem::size_of_val(message) + mem::size_of_val(sub_id) + sub_id.len()
         }
+        ClientMessage::Count { sub_id, filters } => {
+            mem::size_of_val(message)
+                + mem::size_of_val(sub_id)
+                + sub_id.len()
+                + filters.iter().map(mem::size_of_val).sum::<usize>()
+        }
+        ClientMessage::NegOpen {
+            sub_id,
+            filter,
+            initial_message,
+        } => {
+            mem::size_of_val(message)
+                + sub_id.len()
+                + mem::size_of_val(filter)
+                + initial_message.len()
+        }
+        ClientMessage::NegMsg {
+            sub_id,
+            message: msg,
+        } => mem::size_of_val(message) + sub_id.len() + msg.len(),
+        ClientMessage::NegClose { sub_id } => mem::size_of_val(message) + sub_id.len(),
         ClientMessage::Raw(data) => mem::size_of_val(message) + data.len(),
     }
 }
@@ -249,6 +274,12 @@ fn calculate_relay_message_size(message: &RelayMessage) -> usize {
         RelayMessage::Eose(str_ref)
         | RelayMessage::Event(str_ref, _)
         | RelayMessage::Notice(str_ref) => mem::size_of_val(message) + str_ref.len(),
+        RelayMessage::Count(sub_id, _) => mem::size_of_val(message) + sub_id.len(),
+        RelayMessage::NegMsg(sub_id, payload)
+        | RelayMessage::NegErr(sub_id, payload)
+        | RelayMessage::Closed(sub_id, payload) => {
+            mem::size_of_val(message) + sub_id.len() + payload.len()
+        }
     }
 }
 
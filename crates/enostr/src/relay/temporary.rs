@@ -0,0 +1,180 @@
+//! Relays we only connect to for a moment, to follow a hint like the
+//! relays in an nevent or nprofile. They get just the subscriptions that
+//! sent us there, not the app's usual ones, and are dropped once those
+//! are done and the relay has been quiet for a while.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use nostrdb::Filter;
+
+/// Most temporary relays connected at once. Hints past this are
+/// skipped, so a note full of relay hints can't use up our sockets.
+pub const MAX_TEMPORARY_RELAYS: usize = 6;
+
+/// How long a temporary relay with nothing left to do stays connected
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long we wait on a temporary relay that still has subscriptions
+/// open but hasn't said anything
+pub const STALLED_TIMEOUT: Duration = Duration::from_secs(120);
+
+struct TemporaryRelay {
+    /// Subscriptions waiting on EOSE, kept so they can be sent again if
+    /// the relay wasn't connected yet or reconnects
+    subs: HashMap<String, Vec<Filter>>,
+    last_active: Instant,
+}
+
+impl TemporaryRelay {
+    fn is_idle(&self, now: Instant) -> bool {
+        let quiet = now.saturating_duration_since(self.last_active);
+        quiet >= IDLE_TIMEOUT && (self.subs.is_empty() || quiet >= STALLED_TIMEOUT)
+    }
+}
+
+/// The temporary relays in the pool, by url
+#[derive(Default)]
+pub struct TemporaryRelays {
+    relays: HashMap<String, TemporaryRelay>,
+}
+
+impl TemporaryRelays {
+    pub fn contains(&self, url: &str) -> bool {
+        self.relays.contains_key(url)
+    }
+
+    pub fn len(&self) -> usize {
+        self.relays.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.relays.is_empty()
+    }
+
+    /// Whether another temporary relay fits under the cap
+    pub fn has_room(&self) -> bool {
+        self.relays.len() < MAX_TEMPORARY_RELAYS
+    }
+
+    pub fn insert(&mut self, url: String, now: Instant) {
+        self.relays.entry(url).or_insert_with(|| TemporaryRelay {
+            subs: HashMap::new(),
+            last_active: now,
+        });
+    }
+
+    /// Stop treating `url` as temporary, like when the user adds it for
+    /// good. Returns whether it was.
+    pub fn remove(&mut self, url: &str) -> bool {
+        self.relays.remove(url).is_some()
+    }
+
+    pub fn add_sub(&mut self, url: &str, subid: String, filters: Vec<Filter>, now: Instant) {
+        if let Some(relay) = self.relays.get_mut(url) {
+            relay.subs.insert(subid, filters);
+            relay.last_active = now;
+        }
+    }
+
+    /// The subscriptions still open on `url`
+    pub fn subs(&self, url: &str) -> impl Iterator<Item = (&String, &Vec<Filter>)> {
+        self.relays
+            .get(url)
+            .into_iter()
+            .flat_map(|relay| relay.subs.iter())
+    }
+
+    /// `url` finished sending `subid`. Returns whether it was one of its
+    /// temporary subscriptions, which the caller should close.
+    pub fn finish_sub(&mut self, url: &str, subid: &str, now: Instant) -> bool {
+        let Some(relay) = self.relays.get_mut(url) else {
+            return false;
+        };
+        relay.last_active = now;
+        relay.subs.remove(subid).is_some()
+    }
+
+    /// Something came in from `url`
+    pub fn touch(&mut self, url: &str, now: Instant) {
+        if let Some(relay) = self.relays.get_mut(url) {
+            relay.last_active = now;
+        }
+    }
+
+    /// Forget the relays that have been idle long enough and return
+    /// them, so the pool can disconnect them
+    pub fn take_idle(&mut self, now: Instant) -> Vec<String> {
+        let idle: Vec<String> = self
+            .relays
+            .iter()
+            .filter(|(_, relay)| relay.is_idle(now))
+            .map(|(url, _)| url.clone())
+            .collect();
+        for url in &idle {
+            self.relays.remove(url);
+        }
+        idle
+    }
+
+    /// Make room under the cap by forgetting the relay that's been done
+    /// the longest. None if every one of them is still busy.
+    pub fn evict_done(&mut self) -> Option<String> {
+        let url = self
+            .relays
+            .iter()
+            .filter(|(_, relay)| relay.subs.is_empty())
+            .min_by_key(|(_, relay)| relay.last_active)
+            .map(|(url, _)| url.clone())?;
+        self.relays.remove(&url);
+        Some(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters() -> Vec<Filter> {
+        vec![Filter::new().kinds([1]).limit(1).build()]
+    }
+
+    #[test]
+    fn relays_are_dropped_once_done_and_idle() {
+        let start = Instant::now();
+        let mut temp = TemporaryRelays::default();
+        temp.insert("wss://a/".to_owned(), start);
+        temp.add_sub("wss://a/", "sub".to_owned(), filters(), start);
+
+        // still waiting on its EOSE
+        assert!(temp.take_idle(start + IDLE_TIMEOUT).is_empty());
+
+        assert!(temp.finish_sub("wss://a/", "sub", start));
+        assert!(!temp.finish_sub("wss://a/", "sub", start));
+        assert!(temp.take_idle(start + IDLE_TIMEOUT / 2).is_empty());
+        assert_eq!(temp.take_idle(start + IDLE_TIMEOUT), vec!["wss://a/"]);
+        assert!(temp.is_empty());
+
+        // a relay that never answers is given up on eventually
+        temp.insert("wss://b/".to_owned(), start);
+        temp.add_sub("wss://b/", "sub".to_owned(), filters(), start);
+        assert_eq!(temp.take_idle(start + STALLED_TIMEOUT), vec!["wss://b/"]);
+    }
+
+    #[test]
+    fn only_finished_relays_make_room() {
+        let start = Instant::now();
+        let mut temp = TemporaryRelays::default();
+        for i in 0..MAX_TEMPORARY_RELAYS {
+            let url = format!("wss://{i}/");
+            temp.insert(url.clone(), start);
+            temp.add_sub(&url, "sub".to_owned(), filters(), start);
+        }
+        assert!(!temp.has_room());
+        assert_eq!(temp.evict_done(), None);
+
+        temp.finish_sub("wss://3/", "sub", start);
+        assert_eq!(temp.evict_done().as_deref(), Some("wss://3/"));
+        assert!(temp.has_room());
+    }
+}
@@ -0,0 +1,627 @@
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, TryRecvError};
+use std::sync::{Arc, OnceLock};
+
+use ewebsock::{WsEvent, WsMessage};
+use mio::{Events, Interest, Poll, Token, Waker};
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use tracing::{debug, warn};
+use tungstenite::handshake::client::generate_key;
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::frame::coding::{Control, Data, OpCode};
+use tungstenite::protocol::frame::{Frame, FrameSocket};
+use tungstenite::stream::MaybeTlsStream;
+use url::{Position, Url};
+
+use super::deflate::{self, Deflate};
+use super::dial::{happy_eyeballs, ConnectOptions};
+use super::Compression;
+
+/// The connection thread sleeps until the socket is ready or one of
+/// these wakes it
+const SOCKET: Token = Token(0);
+const OUTGOING: Token = Token(1);
+
+/// tungstenite's defaults
+const MAX_FRAME_SIZE: usize = 16 << 20;
+const MAX_MESSAGE_SIZE: usize = 64 << 20;
+
+/// A relay that sends more than this without finishing its handshake
+/// response isn't a websocket server
+const MAX_RESPONSE_HEAD: usize = 16 * 1024;
+
+/// Sends messages to a relay connection. Dropping it closes the
+/// connection.
+pub struct WsSender {
+    tx: Option<mpsc::Sender<WsMessage>>,
+    waker: Arc<Waker>,
+}
+
+impl WsSender {
+    pub fn send(&mut self, msg: WsMessage) {
+        // the connection thread is gone if this fails, we hear about
+        // that on the receiver
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(msg);
+        }
+        let _ = self.waker.wake();
+    }
+}
+
+impl Drop for WsSender {
+    fn drop(&mut self) {
+        // hang up before waking the thread so it sees the disconnect
+        self.tx = None;
+        let _ = self.waker.wake();
+    }
+}
+
+pub struct WsReceiver {
+    rx: mpsc::Receiver<WsEvent>,
+    compression: Arc<OnceLock<Compression>>,
+}
+
+impl WsReceiver {
+    pub fn try_recv(&self) -> Option<WsEvent> {
+        self.rx.try_recv().ok()
+    }
+
+    /// What the handshake negotiated, None until the connection opens
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression.get().copied()
+    }
+}
+
+/// Open a websocket to a relay on its own thread.
+///
+/// This does the same job as `ewebsock::connect_with_wakeup`, but we
+/// dial the TCP connection ourselves so we can race IPv6 and IPv4 and
+/// give up after [`ConnectOptions::connect_timeout`], and we do our own
+/// handshake and framing so we can speak permessage-deflate.
+pub fn connect(
+    url: &str,
+    options: ConnectOptions,
+    wakeup: impl Fn() + Send + Sync + 'static,
+) -> io::Result<(WsSender, WsReceiver)> {
+    let (msg_tx, msg_rx) = mpsc::channel();
+    let (event_tx, event_rx) = mpsc::channel();
+    let compression = Arc::new(OnceLock::new());
+    let url = url.to_owned();
+
+    let poll = Poll::new()?;
+    let waker = Arc::new(Waker::new(poll.registry(), OUTGOING)?);
+
+    let on_event = move |event: WsEvent| {
+        let _ = event_tx.send(event);
+        wakeup();
+    };
+
+    let negotiated = compression.clone();
+    std::thread::Builder::new()
+        .name(format!("ws {url}"))
+        .spawn(move || match open(&url, &options) {
+            Ok((conn, tcp)) => {
+                let _ = negotiated.set(conn.compression());
+                on_event(WsEvent::Opened);
+                run(conn, tcp, poll, msg_rx, &on_event);
+            }
+            Err(err) => {
+                debug!("couldn't connect to {url}: {err}");
+                on_event(WsEvent::Error(err));
+            }
+        })?;
+
+    let sender = WsSender {
+        tx: Some(msg_tx),
+        waker,
+    };
+    let receiver = WsReceiver {
+        rx: event_rx,
+        compression,
+    };
+    Ok((sender, receiver))
+}
+
+type Stream = MaybeTlsStream<TcpStream>;
+
+/// Dial and handshake. Along with the websocket comes a second handle to
+/// its TCP socket, which the connection thread waits on for readiness.
+fn open(url: &str, options: &ConnectOptions) -> Result<(Connection, TcpStream), String> {
+    let parsed = Url::parse(url).map_err(|e| e.to_string())?;
+    let host = parsed.host_str().ok_or("relay url has no host")?;
+    let port = parsed
+        .port_or_known_default()
+        .ok_or("relay url has no port")?;
+
+    // ipv6 literals come with brackets
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let stream = happy_eyeballs(host, port, options).map_err(|e| e.to_string())?;
+
+    // keep a handle to the socket so we can change its mode and poll it
+    // once it's wrapped in tls
+    let tcp = stream.try_clone().map_err(|e| e.to_string())?;
+    let _ = tcp.set_nodelay(true);
+    tcp.set_read_timeout(Some(options.connect_timeout))
+        .and_then(|_| tcp.set_write_timeout(Some(options.connect_timeout)))
+        .map_err(|e| e.to_string())?;
+
+    let mut stream = match parsed.scheme() {
+        "wss" => {
+            let name = ServerName::try_from(host.to_owned()).map_err(|e| e.to_string())?;
+            let tls = ClientConnection::new(tls_config(), name).map_err(|e| e.to_string())?;
+            MaybeTlsStream::Rustls(StreamOwned::new(tls, stream))
+        }
+        "ws" => MaybeTlsStream::Plain(stream),
+        scheme => return Err(format!("not a websocket url scheme: {scheme}")),
+    };
+
+    let (rest, params) = handshake(&mut stream, &parsed, options)
+        .map_err(|e| format!("websocket handshake failed: {e}"))?;
+
+    tcp.set_read_timeout(None)
+        .and_then(|_| tcp.set_write_timeout(None))
+        .and_then(|_| tcp.set_nonblocking(true))
+        .map_err(|e| e.to_string())?;
+
+    let conn = Connection {
+        // the relay may have sent its first frames along with the
+        // handshake response
+        socket: FrameSocket::from_partially_read(stream, rest),
+        deflate: params.map(Deflate::new),
+        partial: None,
+    };
+    Ok((conn, tcp))
+}
+
+fn tls_config() -> Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| {
+            let mut roots = RootCertStore::empty();
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            Arc::new(
+                ClientConfig::builder()
+                    .with_root_certificates(roots)
+                    .with_no_client_auth(),
+            )
+        })
+        .clone()
+}
+
+/// Send the upgrade request and read the relay's response. Returns
+/// whatever the relay sent after the response head, and the
+/// permessage-deflate parameters if it accepted our offer.
+fn handshake(
+    stream: &mut Stream,
+    url: &Url,
+    options: &ConnectOptions,
+) -> Result<(Vec<u8>, Option<deflate::Params>), String> {
+    let key = generate_key();
+    let host = match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+        None => url.host_str().unwrap_or_default().to_owned(),
+    };
+    let path = match &url[Position::BeforePath..Position::AfterQuery] {
+        "" => "/",
+        path => path,
+    };
+
+    let request = format!(
+        "GET {path} HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         Connection: Upgrade\r\n\
+         Upgrade: websocket\r\n\
+         Sec-WebSocket-Version: 13\r\n\
+         Sec-WebSocket-Key: {key}\r\n\
+         Sec-WebSocket-Extensions: {}\r\n\
+         User-Agent: {}\r\n\
+         \r\n",
+        deflate::OFFER,
+        options.user_agent,
+    );
+    stream
+        .write_all(request.as_bytes())
+        .and_then(|_| stream.flush())
+        .map_err(|e| e.to_string())?;
+
+    let mut head = Vec::new();
+    let mut buf = [0; 4096];
+    let end = loop {
+        if let Some(pos) = head.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if head.len() > MAX_RESPONSE_HEAD {
+            return Err("response head is too big".to_owned());
+        }
+
+        match stream.read(&mut buf) {
+            Ok(0) => return Err("relay hung up".to_owned()),
+            Ok(n) => head.extend_from_slice(&buf[..n]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.to_string()),
+        }
+    };
+
+    let rest = head.split_off(end);
+    let head = std::str::from_utf8(&head).map_err(|_| "response head isn't utf-8")?;
+    let params = check_response(head, &key)?;
+    Ok((rest, params))
+}
+
+/// Make sure the relay switched protocols for us, and see what it made
+/// of our extension offer
+fn check_response(head: &str, key: &str) -> Result<Option<deflate::Params>, String> {
+    let mut lines = head.lines();
+    let status = lines.next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("101") {
+        return Err(format!("relay answered {status}"));
+    }
+
+    let headers: Vec<(&str, &str)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim(), value.trim()))
+        .collect();
+    let values = |name: &str| {
+        headers
+            .iter()
+            .filter(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+            .collect::<Vec<_>>()
+    };
+
+    if !values("Upgrade")
+        .iter()
+        .any(|value| value.eq_ignore_ascii_case("websocket"))
+    {
+        return Err("relay didn't upgrade to websocket".to_owned());
+    }
+
+    let accept = derive_accept_key(key.as_bytes());
+    if values("Sec-WebSocket-Accept") != [accept.as_str()] {
+        return Err("wrong Sec-WebSocket-Accept".to_owned());
+    }
+
+    // a relay can split the list over several headers
+    deflate::Params::from_response(&values("Sec-WebSocket-Extensions").join(","))
+}
+
+/// A message the relay has sent some of the frames for
+struct Partial {
+    data: Data,
+    compressed: bool,
+    payload: Vec<u8>,
+}
+
+/// An open websocket. tungstenite's `WebSocket` refuses frames with RSV1
+/// set, so we read and write frames ourselves and put messages together
+/// here.
+struct Connection {
+    socket: FrameSocket<Stream>,
+    deflate: Option<Deflate>,
+    partial: Option<Partial>,
+}
+
+/// What came of reading a frame
+enum Incoming {
+    Message(WsMessage),
+    /// A frame of a message that isn't finished yet
+    Fragment,
+    /// The relay sent a close frame, we've answered it
+    Closed,
+}
+
+impl Connection {
+    fn compression(&self) -> Compression {
+        if self.deflate.is_some() {
+            Compression::Deflate
+        } else {
+            Compression::None
+        }
+    }
+
+    fn on_frame(&mut self, frame: Frame) -> Result<Incoming, String> {
+        let header = frame.header();
+        let (is_final, rsv1, opcode) = (header.is_final, header.rsv1, header.opcode);
+        if header.rsv2 || header.rsv3 {
+            return Err("relay set a reserved bit".to_owned());
+        }
+
+        let (data, compressed, payload) = match opcode {
+            OpCode::Control(control) => {
+                if rsv1 || !is_final {
+                    return Err("relay sent a malformed control frame".to_owned());
+                }
+                return match control {
+                    Control::Ping => Ok(Incoming::Message(WsMessage::Ping(frame.into_payload()))),
+                    Control::Pong => Ok(Incoming::Message(WsMessage::Pong(frame.into_payload()))),
+                    Control::Close => {
+                        self.write(Frame::close(None))?;
+                        Ok(Incoming::Closed)
+                    }
+                    Control::Reserved(op) => Err(format!("relay sent unknown opcode {op}")),
+                };
+            }
+
+            OpCode::Data(Data::Continue) => {
+                let Some(mut partial) = self.partial.take() else {
+                    return Err("relay continued a message it never started".to_owned());
+                };
+                if rsv1 {
+                    return Err("relay set RSV1 on a continuation frame".to_owned());
+                }
+                if partial.payload.len() + frame.payload().len() > MAX_MESSAGE_SIZE {
+                    return Err("message is too big".to_owned());
+                }
+                partial.payload.extend_from_slice(frame.payload());
+                if !is_final {
+                    self.partial = Some(partial);
+                    return Ok(Incoming::Fragment);
+                }
+                (partial.data, partial.compressed, partial.payload)
+            }
+
+            OpCode::Data(data) => {
+                if self.partial.is_some() {
+                    return Err("relay started a message before finishing the last".to_owned());
+                }
+                if rsv1 && self.deflate.is_none() {
+                    return Err("relay compressed a message without negotiating it".to_owned());
+                }
+                if !is_final {
+                    self.partial = Some(Partial {
+                        data,
+                        compressed: rsv1,
+                        payload: frame.into_payload(),
+                    });
+                    return Ok(Incoming::Fragment);
+                }
+                (data, rsv1, frame.into_payload())
+            }
+        };
+
+        let payload = match &mut self.deflate {
+            Some(deflate) if compressed => deflate
+                .decompress(&payload, MAX_MESSAGE_SIZE)
+                .map_err(|e| e.to_string())?,
+            _ => payload,
+        };
+
+        match data {
+            Data::Text => String::from_utf8(payload)
+                .map(|text| Incoming::Message(WsMessage::Text(text)))
+                .map_err(|_| "relay sent a text message that isn't utf-8".to_owned()),
+            Data::Binary => Ok(Incoming::Message(WsMessage::Binary(payload))),
+            Data::Continue | Data::Reserved(_) => {
+                Err(format!("relay sent unknown opcode {opcode}"))
+            }
+        }
+    }
+
+    fn send(&mut self, msg: WsMessage) -> Result<(), String> {
+        let frame = match msg {
+            WsMessage::Text(text) => self.data_frame(Data::Text, text.into_bytes())?,
+            WsMessage::Binary(data) => self.data_frame(Data::Binary, data)?,
+            WsMessage::Ping(data) => Frame::ping(data),
+            WsMessage::Pong(data) => Frame::pong(data),
+            WsMessage::Unknown(_) => return Ok(()),
+        };
+        self.write(frame)
+    }
+
+    fn data_frame(&mut self, data: Data, payload: Vec<u8>) -> Result<Frame, String> {
+        let Some(deflate) = &mut self.deflate else {
+            return Ok(Frame::message(payload, OpCode::Data(data), true));
+        };
+
+        let compressed = deflate.compress(&payload).map_err(|e| e.to_string())?;
+        let mut frame = Frame::message(compressed, OpCode::Data(data), true);
+        frame.header_mut().rsv1 = true;
+        Ok(frame)
+    }
+
+    /// Queue a frame. Whatever doesn't fit in the socket goes out on a
+    /// later [`Connection::flush`].
+    fn write(&mut self, mut frame: Frame) -> Result<(), String> {
+        // clients mask everything they send
+        frame.header_mut().mask = Some(rand::random());
+        match self.socket.write(frame) {
+            Err(err) if !would_block(&err) => Err(err.to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        match self.socket.flush() {
+            Err(err) if !would_block(&err) => Err(err.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Pump the connection until either side hangs up. The thread sleeps in
+/// [`Poll::poll`] until the socket is readable or writable, or a
+/// [`WsSender`] wakes it with something to send.
+fn run(
+    mut conn: Connection,
+    tcp: TcpStream,
+    mut poll: Poll,
+    outgoing: mpsc::Receiver<WsMessage>,
+    on_event: &impl Fn(WsEvent),
+) {
+    let mut tcp = mio::net::TcpStream::from_std(tcp);
+    if let Err(err) =
+        poll.registry()
+            .register(&mut tcp, SOCKET, Interest::READABLE | Interest::WRITABLE)
+    {
+        on_event(WsEvent::Error(err.to_string()));
+        return;
+    }
+
+    let mut events = Events::with_capacity(4);
+    loop {
+        // readiness is edge triggered, so read until the socket runs dry
+        loop {
+            let incoming = match conn.socket.read(Some(MAX_FRAME_SIZE)) {
+                Ok(Some(frame)) => conn.on_frame(frame),
+                Ok(None) => Ok(Incoming::Closed),
+                Err(err) if would_block(&err) => break,
+                Err(err) => Err(err.to_string()),
+            };
+
+            match incoming {
+                Ok(Incoming::Message(msg)) => on_event(WsEvent::Message(msg)),
+                Ok(Incoming::Fragment) => {}
+                Ok(Incoming::Closed) => {
+                    let _ = conn.flush();
+                    on_event(WsEvent::Closed);
+                    return;
+                }
+                Err(err) => {
+                    warn!("websocket error: {err}");
+                    on_event(WsEvent::Error(err));
+                    return;
+                }
+            }
+        }
+
+        loop {
+            match outgoing.try_recv() {
+                Ok(msg) => {
+                    if let Err(err) = conn.send(msg) {
+                        on_event(WsEvent::Error(err));
+                        return;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    // the relay dropped us, probably reconnecting
+                    let _ = conn.write(Frame::close(None));
+                    let _ = conn.flush();
+                    return;
+                }
+            }
+        }
+
+        // anything that doesn't fit in the socket buffer goes out on the
+        // next writable event
+        if let Err(err) = conn.flush() {
+            on_event(WsEvent::Error(err));
+            return;
+        }
+
+        if let Err(err) = poll.poll(&mut events, None) {
+            if err.kind() != io::ErrorKind::Interrupted {
+                on_event(WsEvent::Error(err.to_string()));
+                return;
+            }
+        }
+    }
+}
+
+fn would_block(err: &tungstenite::Error) -> bool {
+    matches!(err, tungstenite::Error::Io(e) if e.kind() == io::ErrorKind::WouldBlock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_unresponsive_relay_times_out() {
+        // the kernel completes the TCP handshake from the backlog, but
+        // nobody ever answers the websocket upgrade
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let options = ConnectOptions {
+            connect_timeout: Duration::from_millis(300),
+            attempt_delay: Duration::from_millis(50),
+            ..Default::default()
+        };
+
+        let started = Instant::now();
+        assert!(open(&url, &options).is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    fn next_event(receiver: &WsReceiver) -> WsEvent {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(event) = receiver.try_recv() {
+                return event;
+            }
+            assert!(Instant::now() < deadline, "no event from the connection");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_deflate_relay() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let relay = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut byte = [0];
+            while !request.ends_with(b"\r\n\r\n") {
+                stream.read_exact(&mut byte).unwrap();
+                request.push(byte[0]);
+            }
+            let request = String::from_utf8(request).unwrap();
+            assert!(request.contains("Sec-WebSocket-Extensions: permessage-deflate\r\n"));
+
+            let key = request
+                .lines()
+                .find_map(|line| line.strip_prefix("Sec-WebSocket-Key: "))
+                .unwrap();
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\n\
+                 Upgrade: websocket\r\n\
+                 Connection: Upgrade\r\n\
+                 Sec-WebSocket-Accept: {}\r\n\
+                 Sec-WebSocket-Extensions: permessage-deflate\r\n\
+                 \r\n",
+                derive_accept_key(key.as_bytes())
+            );
+
+            // a compressed "Hello" in the same write as the response, so
+            // the client has to pick it up from what it read during the
+            // handshake
+            let mut out = response.into_bytes();
+            out.extend_from_slice(&[0xc1, 0x07, 0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00]);
+            stream.write_all(&out).unwrap();
+
+            // what the client sends back should be masked and compressed
+            let mut head = [0; 2];
+            stream.read_exact(&mut head).unwrap();
+            assert_eq!(head[0], 0xc1);
+            assert_eq!(head[1] & 0x80, 0x80);
+            let mut mask = [0; 4];
+            stream.read_exact(&mut mask).unwrap();
+            let mut payload = vec![0; (head[1] & 0x7f) as usize];
+            stream.read_exact(&mut payload).unwrap();
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+
+            Deflate::new(deflate::Params::default())
+                .decompress(&payload, 1024)
+                .unwrap()
+        });
+
+        let (mut sender, receiver) = connect(&url, ConnectOptions::default(), || {}).unwrap();
+        assert!(matches!(next_event(&receiver), WsEvent::Opened));
+        assert_eq!(receiver.compression(), Some(Compression::Deflate));
+        match next_event(&receiver) {
+            WsEvent::Message(WsMessage::Text(text)) => assert_eq!(text, "Hello"),
+            event => panic!("expected a text message, got {event:?}"),
+        }
+
+        sender.send(WsMessage::Text("Hello".to_owned()));
+        assert_eq!(relay.join().unwrap(), b"Hello");
+    }
+}
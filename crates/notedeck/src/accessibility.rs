@@ -0,0 +1,154 @@
+//! Display preferences for people who need more contrast, less
+//! movement on screen, or colors they can tell apart.
+
+use serde::{Deserialize, Serialize};
+
+use crate::indicators::{self, IndicatorPalette};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MotionPreference {
+    /// Reduce motion if the OS asks for it
+    #[default]
+    System,
+    Reduced,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    /// Use the high contrast variant of the light or dark theme
+    pub high_contrast: bool,
+    pub motion: MotionPreference,
+
+    /// Colors for zap, relay and other status indicators
+    pub palette: IndicatorPalette,
+
+    /// Give screen readers one line per note saying who posted it, when,
+    /// what it says and how it's been received, in place of the widgets
+    /// it's made of
+    pub note_summaries: bool,
+}
+
+pub struct Accessibility {
+    settings: AccessibilitySettings,
+    settings_changed: bool,
+
+    /// Whether the OS asked for reduced motion when we started
+    os_reduce_motion: bool,
+
+    /// What was last applied to the egui style
+    applied_reduce_motion: Option<bool>,
+    applied_palette: Option<IndicatorPalette>,
+}
+
+impl Accessibility {
+    pub fn new(settings: AccessibilitySettings) -> Self {
+        let os_reduce_motion = os_prefers_reduced_motion();
+        if os_reduce_motion {
+            tracing::info!("the OS prefers reduced motion");
+        }
+
+        Self {
+            settings,
+            settings_changed: false,
+            os_reduce_motion,
+            applied_reduce_motion: None,
+            applied_palette: None,
+        }
+    }
+
+    pub fn settings(&self) -> AccessibilitySettings {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: AccessibilitySettings) {
+        if settings != self.settings {
+            self.settings = settings;
+            self.settings_changed = true;
+        }
+    }
+
+    /// Whether the settings were changed since the last call, and should
+    /// be saved
+    pub fn take_settings_changed(&mut self) -> bool {
+        std::mem::take(&mut self.settings_changed)
+    }
+
+    /// Whether the OS asked for reduced motion, for showing next to the
+    /// setting
+    pub fn os_reduce_motion(&self) -> bool {
+        self.os_reduce_motion
+    }
+
+    /// Whether animations and auto-playing media should be held still
+    pub fn reduce_motion(&self) -> bool {
+        match self.settings.motion {
+            MotionPreference::System => self.os_reduce_motion,
+            MotionPreference::Reduced => true,
+            MotionPreference::Full => false,
+        }
+    }
+
+    /// Turn egui's own animations off or back on and switch indicator
+    /// palettes when the preferences change. Called once a frame.
+    pub fn apply(&mut self, ctx: &egui::Context) {
+        if self.applied_palette != Some(self.settings.palette) {
+            self.applied_palette = Some(self.settings.palette);
+            indicators::set_palette(ctx, self.settings.palette);
+        }
+
+        let reduce = self.reduce_motion();
+        if self.applied_reduce_motion == Some(reduce) {
+            return;
+        }
+        self.applied_reduce_motion = Some(reduce);
+
+        let animation_time = if reduce {
+            0.0
+        } else {
+            egui::Style::default().animation_time
+        };
+        ctx.all_styles_mut(|style| style.animation_time = animation_time);
+    }
+}
+
+/// Ask the desktop whether it has animations turned off. There's no
+/// portable way, so this only knows about a few platforms.
+fn os_prefers_reduced_motion() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        // GNOME and most things built on it
+        command_output(
+            "gsettings",
+            &["get", "org.gnome.desktop.interface", "enable-animations"],
+        )
+        .is_some_and(|out| out == "false")
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        command_output(
+            "defaults",
+            &["read", "com.apple.universalaccess", "reduceMotion"],
+        )
+        .is_some_and(|out| out == "1")
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        false
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
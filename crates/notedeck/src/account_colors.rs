@@ -0,0 +1,111 @@
+use egui::Color32;
+
+use crate::Accounts;
+
+/// Picked to stay apart from each other on both dark and light themes
+const PALETTE: [Color32; 8] = [
+    Color32::from_rgb(0xE4, 0x5A, 0xC9),
+    Color32::from_rgb(0x3B, 0xA3, 0xF5),
+    Color32::from_rgb(0xF5, 0x9E, 0x0B),
+    Color32::from_rgb(0x22, 0xC5, 0x5E),
+    Color32::from_rgb(0xA8, 0x55, 0xF7),
+    Color32::from_rgb(0xEF, 0x44, 0x44),
+    Color32::from_rgb(0x14, 0xB8, 0xA6),
+    Color32::from_rgb(0xEA, 0xB3, 0x08),
+];
+
+/// A color for each logged in account, so it's obvious which one is
+/// about to post. An account keeps its color across restarts and while
+/// other accounts come and go, unless two of them want the same one.
+///
+/// With a single account there's nothing to tell apart, so nothing gets a
+/// color.
+#[derive(Debug, Default, Clone)]
+pub struct AccountColors {
+    colors: Vec<([u8; 32], Color32)>,
+}
+
+impl AccountColors {
+    pub fn new(accounts: &Accounts) -> Self {
+        if accounts.num_accounts() < 2 {
+            return Self::default();
+        }
+
+        let pubkeys: Vec<[u8; 32]> = accounts
+            .get_accounts()
+            .iter()
+            .map(|acc| *acc.key.pubkey.bytes())
+            .collect();
+
+        let colors = assign_slots(&pubkeys, PALETTE.len())
+            .into_iter()
+            .map(|(pubkey, slot)| (pubkey, PALETTE[slot]))
+            .collect();
+
+        Self { colors }
+    }
+
+    pub fn get(&self, pubkey: &[u8; 32]) -> Option<Color32> {
+        self.colors
+            .iter()
+            .find(|(pk, _)| pk == pubkey)
+            .map(|(_, color)| *color)
+    }
+}
+
+/// Each pubkey starts from a slot derived from its own bytes and takes the
+/// next free one if that's taken. Pubkeys are placed in sorted order so
+/// the result doesn't depend on the order accounts were added in.
+fn assign_slots(pubkeys: &[[u8; 32]], num_slots: usize) -> Vec<([u8; 32], usize)> {
+    let mut sorted = pubkeys.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    let mut taken = vec![false; num_slots];
+    let mut slots = Vec::with_capacity(sorted.len());
+    for pubkey in sorted {
+        let preferred = pubkey.iter().fold(0usize, |acc, b| acc + *b as usize) % num_slots;
+        let slot = (0..num_slots)
+            .map(|offset| (preferred + offset) % num_slots)
+            .find(|slot| !taken[*slot])
+            // more accounts than colors, start sharing
+            .unwrap_or(preferred);
+
+        taken[slot] = true;
+        slots.push((pubkey, slot));
+    }
+
+    slots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot_of(slots: &[([u8; 32], usize)], pubkey: &[u8; 32]) -> usize {
+        slots.iter().find(|(pk, _)| pk == pubkey).unwrap().1
+    }
+
+    #[test]
+    fn test_assign_slots_distinct() {
+        // both prefer slot 0
+        let a = [1; 32];
+        let b = [9; 32];
+        let slots = assign_slots(&[b, a], 8);
+
+        assert_ne!(slot_of(&slots, &a), slot_of(&slots, &b));
+    }
+
+    #[test]
+    fn test_assign_slots_order_independent() {
+        let keys = [[3; 32], [200; 32], [17; 32]];
+        let mut reversed = keys;
+        reversed.reverse();
+
+        let slots = assign_slots(&keys, 8);
+        let reversed_slots = assign_slots(&reversed, 8);
+        for key in &keys {
+            assert_eq!(slot_of(&slots, key), slot_of(&reversed_slots, key));
+        }
+    }
+}
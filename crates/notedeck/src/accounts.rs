@@ -1,10 +1,18 @@
 use tracing::{debug, error, info};
 
+use crate::app_lock::LockUpdate;
+use crate::contacts::{edit_contact_list, AccountContactData, ContactAction, ContactConflict};
+use crate::lists::{note_tags, pin_list_filter, sign_list, ListError, PIN_LIST_KIND};
+use crate::muted::{edit_mute_tags, MuteAction};
+use crate::time::unix_now;
 use crate::{
-    AccountStorage, MuteFun, Muted, RelaySpec, SingleUnkIdAction, UnknownIds, UserAccount,
+    encrypt_in_background, AccountSigner, AccountStorage, AppLock, MuteFun, Muted, NcryptsecError,
+    RelayGroup, RelayGroups, RelaySpec, SignPurpose, SignerError, SingleUnkIdAction, UnknownIds,
+    UnsignedEvent, UserAccount,
 };
-use enostr::{ClientMessage, FilledKeypair, Keypair, Pubkey, RelayPool};
-use nostrdb::{Filter, Ndb, Note, NoteBuilder, NoteKey, Subscription, Transaction};
+use enostr::{ClientMessage, FilledKeypair, Keypair, KeypairUnowned, NoteId, Pubkey, RelayPool};
+use nostrdb::{Filter, IngestMetadata, Ndb, Note, NoteBuilder, NoteKey, Subscription, Transaction};
+use poll_promise::Promise;
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet};
 use url::Url;
@@ -13,6 +21,14 @@ use uuid::Uuid;
 // TODO: remove this
 use std::sync::Arc;
 
+/// Relays that keep everyone's relay lists and contacts, asked when an
+/// account shows up without relays of its own
+const INDEXER_RELAYS: [&str; 3] = [
+    "wss://purplepag.es",
+    "wss://user.kindpag.es",
+    "wss://indexer.coracle.social",
+];
+
 #[derive(Debug, Clone)]
 pub struct SwitchAccountAction {
     /// Some index representing the source of the action
@@ -170,18 +186,33 @@ impl AccountRelayData {
     }
 
     pub fn publish_nip65_relays(&self, seckey: &[u8; 32], pool: &mut RelayPool) {
-        let mut builder = NoteBuilder::new().kind(10002).content("");
-        for rs in &self.advertised {
-            builder = builder.start_tag().tag_str("r").tag_str(&rs.url);
-            if rs.has_read_marker {
-                builder = builder.tag_str("read");
-            } else if rs.has_write_marker {
-                builder = builder.tag_str("write");
-            }
+        publish_relay_list(&self.advertised, seckey, pool);
+    }
+}
+
+fn group_relay_specs(group: &RelayGroup) -> impl Iterator<Item = RelaySpec> + '_ {
+    group
+        .relays
+        .iter()
+        .map(|url| RelaySpec::new(AccountRelayData::canonicalize_url(url), false, false))
+}
+
+fn publish_relay_list<'a>(
+    relays: impl IntoIterator<Item = &'a RelaySpec>,
+    seckey: &[u8; 32],
+    pool: &mut RelayPool,
+) {
+    let mut builder = NoteBuilder::new().kind(10002).content("");
+    for rs in relays {
+        builder = builder.start_tag().tag_str("r").tag_str(&rs.url);
+        if rs.has_read_marker {
+            builder = builder.tag_str("read");
+        } else if rs.has_write_marker {
+            builder = builder.tag_str("write");
         }
-        let note = builder.sign(seckey).build().expect("note build");
-        pool.send(&enostr::ClientMessage::event(&note).expect("note client message"));
     }
+    let note = builder.sign(seckey).build().expect("note build");
+    pool.send(&enostr::ClientMessage::event(&note).expect("note client message"));
 }
 
 pub struct AccountMutedData {
@@ -189,6 +220,9 @@ pub struct AccountMutedData {
     subid: Option<String>,
     sub: Option<Subscription>,
     muted: Arc<Muted>,
+
+    /// A relay has sent us everything it has for this account
+    synced: bool,
 }
 
 impl AccountMutedData {
@@ -217,6 +251,7 @@ impl AccountMutedData {
             subid: None,
             sub: None,
             muted: Arc::new(muted),
+            synced: false,
         }
     }
 
@@ -256,6 +291,15 @@ impl AccountMutedData {
         self.subid = None;
     }
 
+    fn handle_eose(&mut self, subid: &str) -> bool {
+        if self.subid.as_deref() != Some(subid) {
+            return false;
+        }
+
+        self.synced = true;
+        true
+    }
+
     fn harvest_nip51_muted(ndb: &Ndb, txn: &Transaction, nks: &[NoteKey]) -> Muted {
         let mut muted = Muted::default();
         for nk in nks.iter() {
@@ -269,12 +313,12 @@ impl AccountMutedData {
                         }
                         Some("t") => {
                             if let Some(str) = tag.get(1).and_then(|f| f.variant().str()) {
-                                muted.hashtags.insert(str.to_string());
+                                muted.hashtags.insert(str.to_lowercase());
                             }
                         }
                         Some("word") => {
                             if let Some(str) = tag.get(1).and_then(|f| f.variant().str()) {
-                                muted.words.insert(str.to_string());
+                                muted.words.insert(str.to_lowercase());
                             }
                         }
                         Some("e") => {
@@ -301,6 +345,7 @@ impl AccountMutedData {
 pub struct AccountData {
     relay: AccountRelayData,
     muted: AccountMutedData,
+    contacts: AccountContactData,
 }
 
 /// The interface for managing the user's accounts.
@@ -313,7 +358,23 @@ pub struct Accounts {
     forced_relays: BTreeSet<RelaySpec>,
     bootstrap_relays: BTreeSet<RelaySpec>,
     needs_relay_config: bool,
+
+    /// Accounts we've asked the indexer relays for their relay list
+    indexer_queried: BTreeSet<[u8; 32]>,
+
+    /// Relay sets the user can switch to instead of the account's relays
+    relay_groups: RelayGroups,
+    relay_groups_changed: bool,
     fallback: Option<Pubkey>,
+
+    /// A signer we're logging in with that hasn't told us who it signs
+    /// for yet
+    pending_signer: Option<AccountSigner>,
+
+    /// Accounts whose contact list changed since the app last asked
+    contacts_changed: Vec<Pubkey>,
+
+    app_lock: AppLock,
 }
 
 impl Accounts {
@@ -360,7 +421,13 @@ impl Accounts {
             forced_relays,
             bootstrap_relays,
             needs_relay_config: true,
+            indexer_queried: BTreeSet::new(),
+            relay_groups: RelayGroups::default(),
+            relay_groups_changed: false,
             fallback: None,
+            pending_signer: None,
+            contacts_changed: Vec::new(),
+            app_lock: AppLock::default(),
         }
     }
 
@@ -459,12 +526,7 @@ impl Accounts {
                     pubkey
                 );
 
-                if let Some(key_store) = &self.key_store {
-                    if let Err(e) = key_store.write_account(&UserAccount::new(key.clone())) {
-                        tracing::error!("Could not add key for {:?}: {e}", key.pubkey);
-                    }
-                }
-
+                self.store_key(&key);
                 self.accounts[contains_acc.index].key = key;
             } else {
                 info!("already have account, not adding {}", pubkey);
@@ -472,11 +534,7 @@ impl Accounts {
             contains_acc.index
         } else {
             info!("adding new account {}", pubkey);
-            if let Some(key_store) = &self.key_store {
-                if let Err(e) = key_store.write_account(&UserAccount::new(key.clone())) {
-                    tracing::error!("Could not add key for {:?}: {e}", key.pubkey);
-                }
-            }
+            self.store_key(&key);
             self.accounts.push(UserAccount::new(key));
             self.accounts.len() - 1
         };
@@ -491,6 +549,26 @@ impl Accounts {
         }
     }
 
+    /// Save a newly added key. With the app lock on, the secret key
+    /// only goes to disk once it's encrypted with the passphrase.
+    fn store_key(&mut self, key: &Keypair) {
+        let Some(key_store) = &self.key_store else {
+            return;
+        };
+
+        let stored = match &key.secret_key {
+            Some(secret) if self.lock_enabled() => {
+                self.app_lock.save(secret.clone());
+                UserAccount::new(Keypair::only_pubkey(key.pubkey))
+            }
+            _ => UserAccount::new(key.clone()),
+        };
+
+        if let Err(e) = key_store.write_account(&stored) {
+            tracing::error!("Could not add key for {:?}: {e}", key.pubkey);
+        }
+    }
+
     /// Update the `UserAccount` via callback and save the result to disk.
     /// return true if the update was successful
     pub fn update_current_account(&mut self, update: impl FnOnce(&mut UserAccount)) -> bool {
@@ -612,6 +690,10 @@ impl Accounts {
     }
 
     pub fn send_initial_filters(&mut self, pool: &mut RelayPool, relay_url: &str) {
+        for signer in self.signers_mut() {
+            signer.send_initial_filters(pool, relay_url);
+        }
+
         for data in self.account_data.values() {
             // send the active account's relay list subscription
             if let Some(relay_subid) = &data.relay.subid {
@@ -627,6 +709,16 @@ impl Accounts {
                     relay_url,
                 );
             }
+            // and its contact list
+            if let Some(contacts_subid) = data.contacts.subid() {
+                pool.send_to(
+                    &ClientMessage::req(
+                        contacts_subid.to_owned(),
+                        vec![data.contacts.filter().clone()],
+                    ),
+                    relay_url,
+                );
+            }
         }
     }
 
@@ -655,6 +747,7 @@ impl Accounts {
         let new_account_data = AccountData {
             relay: AccountRelayData::new(ndb, pubkey),
             muted: AccountMutedData::new(ndb, pubkey),
+            contacts: AccountContactData::new(pubkey),
         };
         self.account_data.insert(*pubkey, new_account_data);
     }
@@ -700,10 +793,50 @@ impl Accounts {
                     changed = true;
                 }
             }
+            // contact lists don't affect relays, but contact timelines
+            // need to hear about them
+            if data.contacts.poll(ndb) {
+                self.contacts_changed.push(Pubkey::new(*pubkey));
+            }
         }
         changed
     }
 
+    /// Where the selected account publishes: its write relays, or the
+    /// relays everyone uses when it hasn't picked any
+    pub fn write_relays(&self) -> Vec<String> {
+        let relays = if let Some(group) = self.active_relay_group() {
+            group.relays.clone()
+        } else if self.forced_relays.is_empty() {
+            self.get_selected_account()
+                .and_then(|acc| self.account_data.get(acc.key.pubkey.bytes()))
+                .map(|data| {
+                    data.relay
+                        .local
+                        .iter()
+                        .chain(data.relay.advertised.iter())
+                        .filter(|spec| spec.is_writable())
+                        .map(|spec| spec.url.clone())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        } else {
+            self.forced_relays
+                .iter()
+                .map(|spec| spec.url.clone())
+                .collect()
+        };
+
+        if relays.is_empty() {
+            self.bootstrap_relays
+                .iter()
+                .map(|spec| spec.url.clone())
+                .collect()
+        } else {
+            relays
+        }
+    }
+
     fn update_relay_configuration(
         &mut self,
         pool: &mut RelayPool,
@@ -718,6 +851,11 @@ impl Accounts {
         // If forced relays are set use them only
         let mut desired_relays = self.forced_relays.clone();
 
+        // then the relay group the user switched to
+        if let Some(group) = self.active_relay_group() {
+            desired_relays.extend(group_relay_specs(group));
+        }
+
         // Compose the desired relay lists from the selected account
         if desired_relays.is_empty() {
             if let Some(data) = self.get_selected_account_data() {
@@ -731,6 +869,16 @@ impl Accounts {
             desired_relays = self.bootstrap_relays.clone();
         }
 
+        // We always need to be able to reach our signers
+        let signer_relays = self
+            .accounts
+            .iter()
+            .filter_map(|acc| acc.signer.as_ref())
+            .chain(self.pending_signer.as_ref())
+            .flat_map(|signer| signer.relays().iter())
+            .map(|url| RelaySpec::new(url.clone(), false, false));
+        desired_relays.extend(signer_relays);
+
         debug!("current relays: {:?}", pool.urls());
         debug!("desired relays: {:?}", desired_relays);
 
@@ -759,6 +907,47 @@ impl Accounts {
         debug!("current relays: {:?}", pool.urls());
     }
 
+    /// When the selected account has no relays of its own, ask the
+    /// indexer relays for its NIP-65 list and contacts. The list lands in
+    /// ndb like any other and the relay subscription picks it up, so the
+    /// account gets its own relays instead of sitting on the bootstrap ones.
+    fn query_indexers(
+        &mut self,
+        pool: &mut RelayPool,
+        wakeup: impl Fn() + Send + Sync + Clone + 'static,
+    ) {
+        if !self.forced_relays.is_empty() {
+            return;
+        }
+        let Some(pubkey) = self.selected_account_pubkey_bytes().copied() else {
+            return;
+        };
+        if self.indexer_queried.contains(&pubkey) {
+            return;
+        }
+        let Some(data) = self.account_data.get(&pubkey) else {
+            return;
+        };
+        if !data.relay.local.is_empty() || !data.relay.advertised.is_empty() {
+            return;
+        }
+
+        info!(
+            "{} has no relays yet, asking the indexers",
+            hex::encode(pubkey)
+        );
+        let filters = vec![data.relay.filter.clone(), data.contacts.filter().clone()];
+        for relay in INDEXER_RELAYS {
+            pool.subscribe_temporary(
+                relay.to_owned(),
+                Uuid::new_v4().to_string(),
+                filters.clone(),
+                wakeup.clone(),
+            );
+        }
+        self.indexer_queried.insert(pubkey);
+    }
+
     pub fn update(
         &mut self,
         ndb: &mut Ndb,
@@ -790,6 +979,9 @@ impl Accounts {
                         // this account has muted subs, deactivate them
                         data.muted.deactivate(ndb, pool);
                     }
+                    if data.contacts.is_active() {
+                        data.contacts.deactivate(ndb, pool);
+                    }
                 }
             }
         }
@@ -814,7 +1006,7 @@ impl Accounts {
 
         // If needed, update the relay configuration
         if need_reconfig {
-            self.update_relay_configuration(pool, wakeup);
+            self.update_relay_configuration(pool, wakeup.clone());
             self.needs_relay_config = false;
         }
 
@@ -828,7 +1020,14 @@ impl Accounts {
                 // the currently selected account doesn't have muted subs, activate them
                 data.muted.activate(ndb, pool);
             }
+            if !data.contacts.is_active() {
+                data.contacts.activate(ndb, pool);
+            }
         }
+
+        self.query_indexers(pool, wakeup);
+        self.update_signers(pool);
+        self.update_app_lock();
     }
 
     pub fn get_full<'a>(&'a self, pubkey: &[u8; 32]) -> Option<FilledKeypair<'a>> {
@@ -843,6 +1042,472 @@ impl Accounts {
         None
     }
 
+    /// Encrypt the secret key for `pubkey` with a passphrase as an
+    /// ncryptsec (NIP-49). None if we don't have the secret key.
+    pub fn export_ncryptsec(
+        &self,
+        pubkey: &[u8; 32],
+        passphrase: String,
+    ) -> Option<Promise<Result<String, NcryptsecError>>> {
+        let secret = self.get_full(pubkey)?.secret_key.clone();
+        Some(encrypt_in_background(secret, passphrase))
+    }
+
+    /// Whether secret keys are saved encrypted with an app lock passphrase
+    pub fn lock_enabled(&self) -> bool {
+        self.accounts.iter().any(|acc| acc.locked_key.is_some())
+    }
+
+    /// Whether any account's secret key is waiting on the passphrase.
+    /// We can still browse, but not sign for it.
+    pub fn is_locked(&self) -> bool {
+        self.accounts.iter().any(|acc| acc.is_locked())
+    }
+
+    /// Whether a new secret key would be saved. With the lock on it needs
+    /// the passphrase to be encrypted with first.
+    pub fn can_save_secret_keys(&self) -> bool {
+        !self.lock_enabled() || self.app_lock.has_passphrase()
+    }
+
+    pub fn app_lock(&self) -> &AppLock {
+        &self.app_lock
+    }
+
+    pub fn app_lock_mut(&mut self) -> &mut AppLock {
+        &mut self.app_lock
+    }
+
+    /// Decrypt the locked secret keys in the background. They're
+    /// picked up in [`Accounts::update`].
+    pub fn unlock(&mut self, passphrase: String) {
+        let locked = self
+            .accounts
+            .iter()
+            .filter(|acc| acc.is_locked())
+            .filter_map(|acc| acc.locked_key.clone())
+            .collect();
+        self.app_lock.unlock(locked, passphrase);
+    }
+
+    /// Forget the secret keys of locked accounts until the next unlock
+    pub fn lock(&mut self) {
+        for acc in &mut self.accounts {
+            if acc.locked_key.is_some() {
+                acc.key.secret_key = None;
+            }
+        }
+        self.app_lock.forget_passphrase();
+    }
+
+    /// Encrypt every secret key we have with a new passphrase. This also
+    /// changes the passphrase if the lock is already on.
+    pub fn enable_lock(&mut self, passphrase: String) {
+        let secrets = self
+            .accounts
+            .iter()
+            .filter_map(|acc| acc.key.secret_key.clone())
+            .collect();
+        self.app_lock.encrypt(secrets, passphrase);
+    }
+
+    /// Go back to saving secret keys without a passphrase. Only works
+    /// while unlocked, since we need the keys.
+    pub fn disable_lock(&mut self) -> bool {
+        if self.is_locked() || self.app_lock.is_busy() {
+            return false;
+        }
+
+        for acc in &mut self.accounts {
+            if acc.locked_key.take().is_none() {
+                continue;
+            }
+
+            if let Some(key_store) = &self.key_store {
+                if let Err(e) = key_store.write_account(acc) {
+                    tracing::error!("Could not save unlocked key for {:?}: {e}", acc.key.pubkey);
+                }
+            }
+        }
+
+        self.app_lock.forget_passphrase();
+        true
+    }
+
+    fn update_app_lock(&mut self) {
+        for update in self.app_lock.poll() {
+            match update {
+                LockUpdate::Unlocked(secrets) => {
+                    for secret in secrets {
+                        let key = Keypair::from_secret(secret);
+                        if let Some(acc) = self
+                            .accounts
+                            .iter_mut()
+                            .find(|acc| acc.key.pubkey == key.pubkey)
+                        {
+                            acc.key = key;
+                        }
+                    }
+                }
+
+                LockUpdate::Encrypted(encrypted) => {
+                    for (pubkey, ncryptsec) in encrypted {
+                        let Some(acc) = self
+                            .accounts
+                            .iter_mut()
+                            .find(|acc| acc.key.pubkey == pubkey)
+                        else {
+                            continue;
+                        };
+
+                        acc.locked_key = Some(ncryptsec);
+                        if let Some(key_store) = &self.key_store {
+                            if let Err(e) = key_store.write_account(acc) {
+                                tracing::error!("Could not save locked key for {:?}: {e}", pubkey);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start logging in with a NIP-46 bunker or NIP-55 signer app
+    pub fn start_signer_login(&mut self, signer: impl Into<AccountSigner>) {
+        self.pending_signer = Some(signer.into());
+        self.needs_relay_config = true;
+    }
+
+    pub fn pending_signer(&self) -> Option<&AccountSigner> {
+        self.pending_signer.as_ref()
+    }
+
+    pub fn cancel_signer_login(&mut self) {
+        if self.pending_signer.take().is_some() {
+            self.needs_relay_config = true;
+        }
+    }
+
+    /// Add the account for a pending signer once it has told us which
+    /// pubkey it signs for
+    #[must_use = "UnknownIdAction's must be handled. Use .process_unknown_id_action()"]
+    pub fn finish_signer_login(&mut self) -> Option<AddAccountAction> {
+        let pubkey = *self.pending_signer.as_ref()?.user_pubkey()?;
+        let signer = self.pending_signer.take()?;
+
+        let action = self.add_account(Keypair::only_pubkey(pubkey));
+        if let Some(ContainsAccount { index, .. }) = self.contains_account(pubkey.bytes()) {
+            let account = &mut self.accounts[index];
+            account.signer = Some(signer);
+
+            if let Some(key_store) = &self.key_store {
+                if let Err(e) = key_store.write_account(account) {
+                    tracing::error!("Could not save signer for {:?}: {e}", pubkey);
+                }
+            }
+        }
+
+        Some(action)
+    }
+
+    /// The selected account, or the first account we can sign for
+    pub fn selected_or_first_signer(&self) -> Option<KeypairUnowned<'_>> {
+        self.get_selected_account()
+            .filter(|acc| acc.can_sign())
+            .or_else(|| self.accounts.iter().find(|acc| acc.can_sign()))
+            .map(|acc| acc.keypair())
+    }
+
+    pub fn selected_signer(&self) -> Option<&AccountSigner> {
+        self.get_selected_account()?.signer.as_ref()
+    }
+
+    pub fn selected_signer_mut(&mut self) -> Option<&mut AccountSigner> {
+        self.get_selected_account_mut()?.signer.as_mut()
+    }
+
+    /// The key to build notes with for an account that signs remotely.
+    /// See [`AccountSigner::local_key`].
+    pub fn signer_session_key(&self, pubkey: &[u8; 32]) -> Option<[u8; 32]> {
+        self.find_account(pubkey)?
+            .signer
+            .as_ref()
+            .map(|signer| signer.local_key())
+    }
+
+    /// Ask the signer for `pubkey` to sign an event
+    pub fn sign_remote(
+        &mut self,
+        pool: &mut RelayPool,
+        pubkey: &[u8; 32],
+        unsigned: &UnsignedEvent,
+        purpose: SignPurpose,
+    ) {
+        match self
+            .find_account_mut(pubkey)
+            .and_then(|acc| acc.signer.as_mut())
+        {
+            Some(signer) => signer.sign_event(pool, unsigned, purpose),
+            None => purpose.fail(SignerError::NoSigner),
+        }
+    }
+
+    /// Follow or unfollow someone as `account`, on top of the freshest
+    /// copy of its contact list we have
+    pub fn apply_contact_action(
+        &mut self,
+        ndb: &Ndb,
+        pool: &mut RelayPool,
+        account: &Pubkey,
+        action: ContactAction,
+    ) -> Result<(), ListError> {
+        let (seckey, local) = self.list_signing_key(account)?;
+
+        let data = self
+            .account_data
+            .get_mut(account.bytes())
+            .ok_or(ListError::NotSynced)?;
+        let Some(json) = edit_contact_list(
+            ndb,
+            &mut data.contacts,
+            account,
+            &action,
+            &seckey,
+            unix_now(),
+        )?
+        else {
+            return Ok(());
+        };
+
+        // contact timelines refresh once the new list lands in ndb
+        self.publish_list(ndb, pool, account, json, local)
+    }
+
+    /// Mute or unmute something for `account` and publish its new NIP-51
+    /// mute list
+    pub fn apply_mute_action(
+        &mut self,
+        ndb: &Ndb,
+        pool: &mut RelayPool,
+        account: &Pubkey,
+        action: &MuteAction,
+    ) -> Result<(), ListError> {
+        let (seckey, local) = self.list_signing_key(account)?;
+        let data = self
+            .account_data
+            .get(account.bytes())
+            .ok_or(ListError::NotSynced)?;
+
+        let txn = Transaction::new(ndb).map_err(|e| ListError::Build(e.to_string()))?;
+        let current = ndb
+            .query(&txn, &[data.muted.filter.clone()], 1)
+            .ok()
+            .and_then(|results| results.into_iter().max_by_key(|r| r.note.created_at()))
+            .map(|r| r.note);
+
+        if current.is_none() && !data.muted.synced {
+            return Err(ListError::NotSynced);
+        }
+
+        // private mutes live encrypted in the content, keep them as is
+        let (tags, content, based_on) = match &current {
+            Some(note) => (
+                note_tags(note),
+                note.content().to_owned(),
+                note.created_at(),
+            ),
+            None => (vec![], String::new(), 0),
+        };
+
+        let Some(tags) = edit_mute_tags(tags, action) else {
+            return Ok(());
+        };
+
+        let created_at = unix_now().max(based_on + 1);
+        let json = sign_list(10000, &content, &tags, &seckey, created_at)?;
+
+        // the muted subscription picks the new list up once it's in ndb
+        self.publish_list(ndb, pool, account, json, local)
+    }
+
+    /// Pin a note to `account`'s profile. What's already pinned is kept,
+    /// as far as ndb knows about it, so the list wants fetching first.
+    pub fn pin_note(
+        &mut self,
+        ndb: &Ndb,
+        pool: &mut RelayPool,
+        account: &Pubkey,
+        note_id: &NoteId,
+    ) -> Result<(), ListError> {
+        let (seckey, local) = self.list_signing_key(account)?;
+
+        let txn = Transaction::new(ndb).map_err(|e| ListError::Build(e.to_string()))?;
+        let current = ndb
+            .query(&txn, &[pin_list_filter(account)], 1)
+            .ok()
+            .and_then(|results| results.into_iter().max_by_key(|r| r.note.created_at()))
+            .map(|r| r.note);
+
+        let (mut tags, content, based_on) = match &current {
+            Some(note) => (
+                note_tags(note),
+                note.content().to_owned(),
+                note.created_at(),
+            ),
+            None => (vec![], String::new(), 0),
+        };
+
+        let pin = vec!["e".to_owned(), note_id.hex()];
+        if tags.contains(&pin) {
+            return Ok(());
+        }
+        tags.push(pin);
+
+        let created_at = unix_now().max(based_on + 1);
+        let json = sign_list(PIN_LIST_KIND, &content, &tags, &seckey, created_at)?;
+        self.publish_list(ndb, pool, account, json, local)
+    }
+
+    /// Sign `unsigned` as `account` and send it. With a local key it's
+    /// also put in ndb straight away, otherwise it goes to the account's
+    /// remote signer and out from there.
+    pub fn publish_as(
+        &mut self,
+        ndb: &Ndb,
+        pool: &mut RelayPool,
+        account: &Pubkey,
+        unsigned: &UnsignedEvent,
+    ) -> Result<(), ListError> {
+        let Some(kp) = self.get_full(account.bytes()) else {
+            if self
+                .find_account(account.bytes())
+                .is_some_and(|acc| acc.signer.is_some())
+            {
+                self.sign_remote(pool, account.bytes(), unsigned, SignPurpose::Publish);
+                return Ok(());
+            }
+            return Err(ListError::CantSign);
+        };
+
+        let json = sign_list(
+            unsigned.kind,
+            &unsigned.content,
+            &unsigned.tags,
+            &kp.secret_key.to_secret_bytes(),
+            unsigned.created_at,
+        )?;
+        self.publish_list(ndb, pool, account, json, true)
+    }
+
+    /// The key to sign list edits with, and whether it's the account's
+    /// own key rather than a remote signer's session key
+    fn list_signing_key(&self, account: &Pubkey) -> Result<([u8; 32], bool), ListError> {
+        if let Some(kp) = self.get_full(account.bytes()) {
+            return Ok((kp.secret_key.to_secret_bytes(), true));
+        }
+
+        self.signer_session_key(account.bytes())
+            .map(|key| (key, false))
+            .ok_or(ListError::CantSign)
+    }
+
+    fn publish_list(
+        &mut self,
+        ndb: &Ndb,
+        pool: &mut RelayPool,
+        account: &Pubkey,
+        json: String,
+        local: bool,
+    ) -> Result<(), ListError> {
+        if !local {
+            let unsigned: UnsignedEvent =
+                serde_json::from_str(&json).map_err(|e| ListError::Build(e.to_string()))?;
+            self.sign_remote(pool, account.bytes(), &unsigned, SignPurpose::Publish);
+            return Ok(());
+        }
+
+        if let Err(err) = ndb.process_event_with(
+            &format!("[\"EVENT\",{json}]"),
+            IngestMetadata::new().client(true),
+        ) {
+            error!("couldn't ingest our list: {err}");
+        }
+        let msg = ClientMessage::event_json(json).map_err(|e| ListError::Build(e.to_string()))?;
+        pool.send(&msg);
+
+        Ok(())
+    }
+
+    pub fn muted(&self, account: &Pubkey) -> Option<&Muted> {
+        self.account_data
+            .get(account.bytes())
+            .map(|data| data.muted.muted.as_ref())
+    }
+
+    pub fn mutes_synced(&self, account: &Pubkey) -> bool {
+        self.account_data
+            .get(account.bytes())
+            .is_some_and(|data| data.muted.synced)
+    }
+
+    /// Whether a relay has sent us everything it has for this account's
+    /// contact list
+    pub fn contacts_synced(&self, account: &Pubkey) -> bool {
+        self.account_data
+            .get(account.bytes())
+            .is_some_and(|data| data.contacts.is_synced())
+    }
+
+    pub fn contact_conflict(&self, account: &Pubkey) -> Option<&ContactConflict> {
+        self.account_data.get(account.bytes())?.contacts.conflict()
+    }
+
+    pub fn dismiss_contact_conflict(&mut self, account: &Pubkey) {
+        if let Some(data) = self.account_data.get_mut(account.bytes()) {
+            data.contacts.dismiss_conflict();
+        }
+    }
+
+    /// Accounts whose contact list changed since the last call
+    pub fn take_contact_updates(&mut self) -> Vec<Pubkey> {
+        std::mem::take(&mut self.contacts_changed)
+    }
+
+    /// Let account subscriptions know a relay has sent all its stored
+    /// events. False if the subscription isn't one of ours.
+    pub fn handle_eose(&mut self, subid: &str) -> bool {
+        self.account_data
+            .values_mut()
+            .any(|data| data.contacts.handle_eose(subid) || data.muted.handle_eose(subid))
+    }
+
+    /// Process a relay EVENT message if it's a response from one of our
+    /// signers. Returns false if it wasn't.
+    pub fn process_signer_event(&mut self, sub_id: &str, ev: &str) -> bool {
+        self.signers_mut()
+            .any(|signer| signer.process_event(sub_id, ev))
+    }
+
+    fn signers_mut(&mut self) -> impl Iterator<Item = &mut AccountSigner> {
+        self.accounts
+            .iter_mut()
+            .filter_map(|acc| acc.signer.as_mut())
+            .chain(self.pending_signer.as_mut())
+    }
+
+    fn update_signers(&mut self, pool: &mut RelayPool) {
+        for signer in self.signers_mut() {
+            signer.update(pool);
+
+            for json in signer.take_signed() {
+                match ClientMessage::event_json(json) {
+                    Ok(msg) => pool.send(&msg),
+                    Err(err) => error!("could not publish signed note: {err}"),
+                }
+            }
+        }
+    }
+
     fn modify_advertised_relays(
         &mut self,
         relay_url: &str,
@@ -854,6 +1519,24 @@ impl Accounts {
             RelayAction::Add => info!("add advertised relay \"{}\"", relay_url),
             RelayAction::Remove => info!("remove advertised relay \"{}\"", relay_url),
         }
+
+        // while a group is in use, the relays shown are the group's
+        if self.forced_relays.is_empty() {
+            if let Some(group) = self.relay_groups.active_group_mut() {
+                match action {
+                    RelayAction::Add => {
+                        if !group.relays.contains(&relay_url) {
+                            group.relays.push(relay_url);
+                        }
+                    }
+                    RelayAction::Remove => group.relays.retain(|url| *url != relay_url),
+                }
+                self.relay_groups_changed = true;
+                self.needs_relay_config = true;
+                self.publish_active_relay_group(pool);
+                return;
+            }
+        }
         match self.currently_selected_account {
             None => error!("no account is currently selected."),
             Some(index) => match self.accounts.get(index) {
@@ -892,6 +1575,93 @@ impl Accounts {
         }
     }
 
+    pub fn relay_groups(&self) -> &RelayGroups {
+        &self.relay_groups
+    }
+
+    /// Set the saved relay groups at startup
+    pub fn load_relay_groups(&mut self, relay_groups: RelayGroups) {
+        self.relay_groups = relay_groups;
+        self.needs_relay_config = true;
+    }
+
+    /// Whether relay groups changed since the last call, and should be saved
+    pub fn take_relay_groups_changed(&mut self) -> bool {
+        std::mem::take(&mut self.relay_groups_changed)
+    }
+
+    /// The group the pool uses, unless relays were forced on the command line
+    fn active_relay_group(&self) -> Option<&RelayGroup> {
+        if !self.forced_relays.is_empty() {
+            return None;
+        }
+        self.relay_groups.active_group()
+    }
+
+    /// Save `relays` as a group, replacing one with the same name
+    pub fn save_relay_group(&mut self, name: String, relays: Vec<String>) {
+        let publish = self
+            .relay_groups
+            .get(&name)
+            .is_some_and(|group| group.publish);
+        self.relay_groups.insert(RelayGroup {
+            name,
+            relays,
+            publish,
+        });
+        self.relay_groups_changed = true;
+    }
+
+    pub fn remove_relay_group(&mut self, name: &str) {
+        if self.relay_groups.remove(name) {
+            self.needs_relay_config = true;
+        }
+        self.relay_groups_changed = true;
+    }
+
+    pub fn set_relay_group_publish(&mut self, name: &str, publish: bool) {
+        if let Some(group) = self.relay_groups.get_mut(name) {
+            group.publish = publish;
+            self.relay_groups_changed = true;
+        }
+    }
+
+    /// Switch to the relay group `name`, or back to the account's relays
+    /// with None. The pool drops the relays that aren't wanted anymore and
+    /// the app subscribes on the new ones as they connect.
+    pub fn switch_relay_group(&mut self, name: Option<&str>, pool: &mut RelayPool) {
+        if name.is_some_and(|name| self.relay_groups.get(name).is_none()) {
+            return;
+        }
+        if self.relay_groups.active.as_deref() == name {
+            return;
+        }
+
+        info!("switching to relay group {:?}", name);
+        self.relay_groups.active = name.map(|name| name.to_owned());
+        self.relay_groups_changed = true;
+        self.needs_relay_config = true;
+        self.publish_active_relay_group(pool);
+    }
+
+    /// Publish the active group as the selected account's relay list, if
+    /// it's set to be published and we can sign
+    fn publish_active_relay_group(&self, pool: &mut RelayPool) {
+        let Some(group) = self.active_relay_group().filter(|group| group.publish) else {
+            return;
+        };
+        let Some(secret_key) = self
+            .get_selected_account()
+            .and_then(|acc| acc.key.secret_key.as_ref())
+        else {
+            return;
+        };
+
+        info!("publishing relay group {} as our relay list", group.name);
+        let relays: Vec<RelaySpec> = group_relay_specs(group).collect();
+        publish_relay_list(&relays, &secret_key.to_secret_bytes(), pool);
+    }
+
     pub fn add_advertised_relay(&mut self, relay_to_add: &str, pool: &mut RelayPool) {
         self.modify_advertised_relays(relay_to_add, pool, RelayAction::Add);
     }
@@ -1,18 +1,26 @@
-use crate::persist::{AppSizeHandler, ZoomHandler};
+use crate::demo::Demo;
+use crate::nip19::NostrUri;
+use crate::paid::SavedPaidOrders;
+use crate::persist::{take_unreadable_settings, AppSizeHandler, JsonSettings, ZoomHandler};
+use crate::uri_inbox::UriInbox;
 use crate::wallet::GlobalWallet;
 use crate::zaps::Zaps;
 use crate::JobPool;
 use crate::{
-    frame_history::FrameHistory, AccountStorage, Accounts, AppContext, Args, DataPath,
-    DataPathType, Directory, Images, NoteAction, NoteCache, RelayDebugView, ThemeHandler,
-    UnknownIds,
+    frame_history::FrameHistory, Accessibility, AccessibilitySettings, AccountStorage, Accounts,
+    AnimationSettings, AppContext, Args, BlossomServers, CacheQuotaSettings, Counts, DataPath,
+    DataPathType, Directory, FollowedThreads, Images, LinkPreviewSettings, NoteAction, NoteCache,
+    NotificationFilterSettings, NotificationReadState, NotificationWatcher, OsNotificationSettings,
+    OsNotifications, PaidContent, Polls, QuietHours, QuietHoursSettings, RelayDebugView,
+    RelayGroups, SavedFollowedThreads, SoundSettings, Sounds, ThemeHandler, UnknownIds, WebOfTrust,
+    ZapGoals,
 };
 use egui::ThemePreference;
 use egui_winit::clipboard::Clipboard;
-use enostr::RelayPool;
+use enostr::{RelayPool, RelayRoles};
 use nostrdb::{Config, Ndb, Transaction};
 use std::cell::RefCell;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use std::path::Path;
 use std::rc::Rc;
 use tracing::{error, info};
@@ -32,6 +40,8 @@ pub struct Notedeck {
     img_cache: Images,
     unknown_ids: UnknownIds,
     pool: RelayPool,
+    relay_roles: JsonSettings<HashMap<String, RelayRoles>>,
+    relay_groups: JsonSettings<RelayGroups>,
     note_cache: NoteCache,
     accounts: Accounts,
     global_wallet: GlobalWallet,
@@ -41,11 +51,43 @@ pub struct Notedeck {
     app: Option<Rc<RefCell<dyn App>>>,
     zoom: ZoomHandler,
     app_size: AppSizeHandler,
+    animation_settings: JsonSettings<AnimationSettings>,
+    cache_quota: JsonSettings<CacheQuotaSettings>,
+    link_preview_settings: JsonSettings<LinkPreviewSettings>,
     unrecognized_args: BTreeSet<String>,
     clipboard: Clipboard,
     zaps: Zaps,
     frame_history: FrameHistory,
     job_pool: JobPool,
+    counts: Counts,
+    polls: Polls,
+    zap_goals: ZapGoals,
+    paid_content: PaidContent,
+    paid_orders: JsonSettings<SavedPaidOrders>,
+    wot: WebOfTrust,
+    blossom: BlossomServers,
+    accessibility: Accessibility,
+    accessibility_settings: JsonSettings<AccessibilitySettings>,
+    quiet_hours: QuietHours,
+    quiet_hours_settings: JsonSettings<QuietHoursSettings>,
+    notifications: NotificationWatcher,
+    read_state: NotificationReadState,
+    followed_threads: FollowedThreads,
+    followed_threads_handler: JsonSettings<SavedFollowedThreads>,
+    notification_filters: JsonSettings<NotificationFilterSettings>,
+    sounds: Sounds,
+    sound_settings: JsonSettings<SoundSettings>,
+    os_notifications: OsNotifications,
+    os_notification_settings: JsonSettings<OsNotificationSettings>,
+
+    /// `nostr:` uris sent by later launches
+    uri_inbox: Option<UriInbox>,
+
+    /// `nostr:` uris for the app to open
+    opened_uris: Vec<NostrUri>,
+
+    /// Kept alive for as long as we're running the demo
+    _demo: Option<Demo>,
 }
 
 /// Our chrome, which is basically nothing
@@ -85,6 +127,8 @@ fn render_notedeck(notedeck: &mut Notedeck, ctx: &egui::Context) {
         }
         */
     });
+
+    crate::toast::show(ctx);
 }
 
 impl eframe::App for Notedeck {
@@ -96,14 +140,123 @@ impl eframe::App for Notedeck {
         // handle account updates
         self.accounts
             .update(&mut self.ndb, &mut self.pool, ctx, &mut self.unknown_ids);
+        self.wot.require_hops(self.notifications.filters().hops());
+        self.wot.update(
+            &mut self.ndb,
+            &mut self.pool,
+            self.accounts.selected_account_pubkey(),
+        );
+        self.followed_threads.update(
+            &mut self.ndb,
+            &mut self.pool,
+            self.accounts.selected_account_pubkey(),
+        );
+        let followed = self
+            .accounts
+            .selected_account_pubkey()
+            .map_or(&[][..], |pk| self.followed_threads.threads(pk.bytes()));
+        let is_muted = self.accounts.mutefun();
+        for notification in self.notifications.update(
+            &mut self.ndb,
+            &mut self.pool,
+            self.accounts.selected_account_pubkey(),
+            followed,
+            &is_muted,
+            &self.wot,
+        ) {
+            self.sounds.notify(&notification, &self.quiet_hours);
+            self.os_notifications
+                .notify(ctx, &self.ndb, &notification, &self.quiet_hours);
+        }
+        self.opened_uris
+            .extend(self.os_notifications.take_clicked());
+        if self.blossom.update(
+            &mut self.ndb,
+            &mut self.pool,
+            self.accounts.selected_account_pubkey(),
+        ) {
+            self.img_cache.blossom_servers = self.blossom.servers().to_vec();
+        }
+        if self
+            .read_state
+            .update(&mut self.ndb, &mut self.pool, &self.accounts)
+        {
+            ctx.request_repaint();
+        }
 
-        self.zaps
-            .process(&mut self.accounts, &mut self.global_wallet, &self.ndb);
+        self.zaps.process(
+            &mut self.accounts,
+            &mut self.global_wallet,
+            &mut self.pool,
+            &self.ndb,
+        );
+        self.paid_content.update(ctx, &self.accounts, &self.zaps);
 
+        if let Some(inbox) = &self.uri_inbox {
+            self.opened_uris.extend(inbox.take());
+        }
+
+        for message in take_unreadable_settings() {
+            crate::toast::error(ctx, message);
+        }
+
+        self.accessibility.apply(ctx);
+        self.img_cache
+            .gif_states
+            .set_motion_reduced(self.accessibility.reduce_motion());
+        self.img_cache.gif_states.begin_frame();
+        self.img_cache.disk_quota.update();
+        self.zoom.handle_shortcuts(ctx);
         render_notedeck(self, ctx);
 
+        self.counts.count_local(&self.ndb);
+        self.counts.send_requests(&mut self.pool);
+        self.polls.send_requests(&mut self.pool);
+        self.zap_goals.send_requests(&mut self.pool);
+
         self.zoom.try_save_zoom_factor(ctx);
         self.app_size.try_save_app_size(ctx);
+        if self.img_cache.gif_states.take_settings_changed() {
+            self.animation_settings
+                .save(&self.img_cache.gif_states.settings());
+        }
+        if self.accessibility.take_settings_changed() {
+            self.accessibility_settings
+                .save(&self.accessibility.settings());
+        }
+        if self.quiet_hours.take_settings_changed() {
+            self.quiet_hours_settings.save(self.quiet_hours.settings());
+        }
+        if self.paid_content.take_changed() {
+            self.paid_orders.save(&self.paid_content.to_saved());
+        }
+        if self.followed_threads.take_changed() {
+            self.followed_threads_handler
+                .save(&self.followed_threads.to_saved());
+        }
+        if self.notifications.take_filters_changed() {
+            self.notification_filters.save(self.notifications.filters());
+        }
+        if self.pool.take_roles_changed() {
+            self.relay_roles.save(self.pool.all_roles());
+        }
+        if self.accounts.take_relay_groups_changed() {
+            self.relay_groups.save(self.accounts.relay_groups());
+        }
+        if self.sounds.take_settings_changed() {
+            self.sound_settings.save(&self.sounds.settings());
+        }
+        if self.os_notifications.take_settings_changed() {
+            self.os_notification_settings
+                .save(&self.os_notifications.settings());
+        }
+        if self.img_cache.disk_quota.take_settings_changed() {
+            self.cache_quota.save(&self.img_cache.disk_quota.settings());
+        }
+        if self.img_cache.link_previews.take_settings_changed() {
+            self.link_preview_settings
+                .save(&self.img_cache.link_previews.settings());
+        }
 
         if self.args.relay_debug {
             if self.pool.debug.is_none() {
@@ -125,6 +278,30 @@ impl eframe::App for Notedeck {
     }
 }
 
+/// Point everything at the demo relay, and keep demo data away from the
+/// real database, keys and settings
+fn start_demo(args: &mut Args) -> Option<Demo> {
+    let demo = match Demo::start() {
+        Ok(demo) => demo,
+        Err(err) => {
+            error!("couldn't start the demo relay: {err}");
+            return None;
+        }
+    };
+
+    args.relays = vec![demo.relay.url().to_owned()];
+    args.keys.insert(0, demo.account.clone());
+    args.use_keystore = false;
+    if args.datapath.is_none() {
+        // start fresh every time, old demo notes are from keys we threw away
+        let path = std::env::temp_dir().join("notedeck-demo");
+        let _ = std::fs::remove_dir_all(&path);
+        args.datapath = Some(path.to_string_lossy().into_owned());
+    }
+
+    Some(demo)
+}
+
 #[cfg(feature = "puffin")]
 fn setup_puffin() {
     info!("setting up puffin");
@@ -137,7 +314,13 @@ impl Notedeck {
         setup_puffin();
 
         // Skip the first argument, which is the program name.
-        let (parsed_args, unrecognized_args) = Args::parse(&args[1..]);
+        let (mut parsed_args, unrecognized_args) = Args::parse(&args[1..]);
+
+        let demo = if parsed_args.demo {
+            start_demo(&mut parsed_args)
+        } else {
+            None
+        };
 
         let data_path = parsed_args
             .datapath
@@ -177,6 +360,8 @@ impl Notedeck {
         };
 
         let mut accounts = Accounts::new(keystore, parsed_args.relays.clone());
+        let relay_groups = JsonSettings::new(&path, "relay_groups.json");
+        accounts.load_relay_groups(relay_groups.load());
 
         let num_keys = parsed_args.keys.len();
 
@@ -199,6 +384,12 @@ impl Notedeck {
 
         // AccountManager will setup the pool on first update
         let mut pool = RelayPool::new();
+        pool.user_agent(concat!("notedeck/", env!("CARGO_PKG_VERSION")));
+        let relay_roles = JsonSettings::new(&path, "relay_roles.json");
+        pool.load_roles(relay_roles.load());
+        if let Some(timeout) = parsed_args.connect_timeout {
+            pool.connect_timeout(timeout);
+        }
         {
             let ctx = ctx.clone();
             if let Err(err) = pool.add_multicast_relay(move || ctx.request_repaint()) {
@@ -206,30 +397,70 @@ impl Notedeck {
             }
         }
 
-        let img_cache = Images::new(img_cache_dir);
+        let mut img_cache = Images::new(img_cache_dir);
+        let animation_settings = JsonSettings::new(&path, "animation.json");
+        img_cache.gif_states.set_settings(animation_settings.load());
+        img_cache.gif_states.take_settings_changed();
+        let cache_quota = JsonSettings::new(&path, "cache_quota.json");
+        img_cache.disk_quota.set_settings(cache_quota.load());
+        img_cache.disk_quota.take_settings_changed();
+        let link_preview_settings = JsonSettings::new(&path, "link_previews.json");
+        img_cache
+            .link_previews
+            .set_settings(link_preview_settings.load());
+        img_cache.link_previews.take_settings_changed();
         let note_cache = NoteCache::default();
         let unknown_ids = UnknownIds::default();
         let zoom = ZoomHandler::new(&path);
+        let accessibility_settings = JsonSettings::new(&path, "accessibility.json");
+        let accessibility = Accessibility::new(accessibility_settings.load());
+        let quiet_hours_settings = JsonSettings::new(&path, "quiet_hours.json");
+        let quiet_hours = QuietHours::new(quiet_hours_settings.load());
+        let notification_filters = JsonSettings::new(&path, "notification_filters.json");
+        let notifications = NotificationWatcher::new(notification_filters.load());
+        let paid_orders = JsonSettings::new(&path, "paid_orders.json");
+        let paid_content = PaidContent::new(paid_orders.load());
+        let followed_threads_handler = JsonSettings::new(&path, "followed_threads.json");
+        let followed_threads = FollowedThreads::new(followed_threads_handler.load());
+        let sound_settings = JsonSettings::new(&path, "sounds.json");
+        let sounds = Sounds::new(&path, sound_settings.load());
+        let os_notification_settings = JsonSettings::new(&path, "os_notifications.json");
+        let os_notifications = OsNotifications::new(os_notification_settings.load());
         let app_size = AppSizeHandler::new(&path);
 
         if let Some(z) = zoom.get_zoom_factor() {
             ctx.set_zoom_factor(z);
         }
+        ctx.options_mut(|o| o.zoom_with_keyboard = false);
 
         // migrate
         if let Err(e) = img_cache.migrate_v0() {
             error!("error migrating image cache: {e}");
         }
 
+        let uri_inbox = if parsed_args.tests {
+            None
+        } else {
+            UriInbox::listen(&path, ctx)
+        };
+        let opened_uris = parsed_args
+            .open_uris
+            .iter()
+            .filter_map(|uri| NostrUri::parse(uri))
+            .collect();
+
         let global_wallet = GlobalWallet::new(&path);
         let zaps = Zaps::default();
         let job_pool = JobPool::default();
+        let wot = WebOfTrust::new(&path);
 
         Self {
             ndb,
             img_cache,
             unknown_ids,
             pool,
+            relay_roles,
+            relay_groups,
             note_cache,
             accounts,
             global_wallet,
@@ -239,11 +470,37 @@ impl Notedeck {
             app: None,
             zoom,
             app_size,
+            animation_settings,
+            cache_quota,
+            link_preview_settings,
             unrecognized_args,
             frame_history: FrameHistory::default(),
             clipboard: Clipboard::new(None),
             zaps,
             job_pool,
+            counts: Counts::default(),
+            polls: Polls::default(),
+            zap_goals: ZapGoals::default(),
+            paid_content,
+            paid_orders,
+            wot,
+            blossom: BlossomServers::default(),
+            accessibility,
+            accessibility_settings,
+            quiet_hours,
+            quiet_hours_settings,
+            notifications,
+            read_state: NotificationReadState::default(),
+            followed_threads,
+            followed_threads_handler,
+            notification_filters,
+            sounds,
+            sound_settings,
+            os_notifications,
+            os_notification_settings,
+            uri_inbox,
+            opened_uris,
+            _demo: demo,
         }
     }
 
@@ -268,6 +525,20 @@ impl Notedeck {
             zaps: &mut self.zaps,
             frame_history: &mut self.frame_history,
             job_pool: &mut self.job_pool,
+            counts: &mut self.counts,
+            polls: &mut self.polls,
+            zap_goals: &mut self.zap_goals,
+            paid_content: &mut self.paid_content,
+            wot: &mut self.wot,
+            blossom: &self.blossom,
+            accessibility: &mut self.accessibility,
+            quiet_hours: &mut self.quiet_hours,
+            sounds: &mut self.sounds,
+            notifications: &mut self.notifications,
+            read_state: &mut self.read_state,
+            followed_threads: &mut self.followed_threads,
+            os_notifications: &mut self.os_notifications,
+            opened_uris: &mut self.opened_uris,
         }
     }
 
@@ -0,0 +1,176 @@
+use enostr::{Keypair, Pubkey, SecretKey};
+use poll_promise::Promise;
+use tracing::{info, warn};
+
+use crate::ncryptsec::{decrypt_ncryptsec, encrypt_secret_key, NcryptsecError};
+
+enum LockJob {
+    Unlock {
+        passphrase: String,
+        promise: Promise<Result<Vec<SecretKey>, NcryptsecError>>,
+    },
+    Encrypt {
+        passphrase: String,
+        promise: Promise<Result<Vec<(Pubkey, String)>, NcryptsecError>>,
+    },
+}
+
+/// Finished lock work for [`crate::Accounts`] to apply
+pub(crate) enum LockUpdate {
+    /// Secret keys we decrypted with the passphrase
+    Unlocked(Vec<SecretKey>),
+
+    /// Secret keys encrypted with the passphrase, ready to be saved
+    Encrypted(Vec<(Pubkey, String)>),
+}
+
+/// Keeps account secret keys encrypted at rest with a passphrase or PIN.
+///
+/// With the lock on, secret keys are only saved as ncryptsecs (NIP-49)
+/// and we start up locked: we can browse, but nothing gets signed until
+/// the user unlocks with their passphrase.
+#[derive(Default)]
+pub struct AppLock {
+    /// Kept while unlocked so keys for accounts added later get
+    /// encrypted too
+    passphrase: Option<String>,
+
+    /// Secret keys that can't be saved until we know the passphrase
+    unsaved: Vec<SecretKey>,
+
+    jobs: Vec<LockJob>,
+    last_error: Option<NcryptsecError>,
+}
+
+impl AppLock {
+    /// Whether we're still running scrypt for an unlock or encryption
+    pub fn is_busy(&self) -> bool {
+        !self.jobs.is_empty()
+    }
+
+    /// Whether we know the passphrase, so new keys can be encrypted
+    pub fn has_passphrase(&self) -> bool {
+        self.passphrase.is_some()
+    }
+
+    pub fn last_error(&self) -> Option<&NcryptsecError> {
+        self.last_error.as_ref()
+    }
+
+    pub fn clear_error(&mut self) {
+        self.last_error = None;
+    }
+
+    pub(crate) fn unlock(&mut self, ncryptsecs: Vec<String>, passphrase: String) {
+        self.last_error = None;
+        let pass = passphrase.clone();
+        let promise = Promise::spawn_thread("app-unlock", move || decrypt_all(&ncryptsecs, &pass));
+        self.jobs.push(LockJob::Unlock {
+            passphrase,
+            promise,
+        });
+    }
+
+    pub(crate) fn encrypt(&mut self, secrets: Vec<SecretKey>, passphrase: String) {
+        self.last_error = None;
+        let pass = passphrase.clone();
+        let promise = Promise::spawn_thread("app-lock", move || {
+            secrets
+                .iter()
+                .map(|secret| {
+                    let pubkey = Keypair::from_secret(secret.clone()).pubkey;
+                    Ok::<_, NcryptsecError>((pubkey, encrypt_secret_key(secret, &pass)?))
+                })
+                .collect()
+        });
+        self.jobs.push(LockJob::Encrypt {
+            passphrase,
+            promise,
+        });
+    }
+
+    /// Encrypt a new account's key with the passphrase we unlocked with,
+    /// or hold on to it until the user unlocks. The login view asks for
+    /// the passphrase first, so only keys given on the command line end up
+    /// waiting, and they're gone if we quit before unlocking.
+    pub(crate) fn save(&mut self, secret: SecretKey) {
+        match self.passphrase.clone() {
+            Some(passphrase) => self.encrypt(vec![secret], passphrase),
+            None => {
+                warn!("app lock: new key won't be saved until we're unlocked");
+                self.unsaved.push(secret);
+            }
+        }
+    }
+
+    pub(crate) fn forget_passphrase(&mut self) {
+        self.passphrase = None;
+    }
+
+    pub(crate) fn poll(&mut self) -> Vec<LockUpdate> {
+        let mut updates = Vec::new();
+
+        for job in std::mem::take(&mut self.jobs) {
+            match job {
+                LockJob::Unlock {
+                    passphrase,
+                    promise,
+                } => match promise.try_take() {
+                    Err(promise) => self.jobs.push(LockJob::Unlock {
+                        passphrase,
+                        promise,
+                    }),
+                    Ok(Ok(keys)) => {
+                        info!("app lock: unlocked {} keys", keys.len());
+                        self.passphrase = Some(passphrase);
+                        updates.push(LockUpdate::Unlocked(keys));
+                    }
+                    Ok(Err(err)) => self.last_error = Some(err),
+                },
+
+                LockJob::Encrypt {
+                    passphrase,
+                    promise,
+                } => match promise.try_take() {
+                    Err(promise) => self.jobs.push(LockJob::Encrypt {
+                        passphrase,
+                        promise,
+                    }),
+                    Ok(Ok(encrypted)) => {
+                        self.passphrase = Some(passphrase);
+                        updates.push(LockUpdate::Encrypted(encrypted));
+                    }
+                    Ok(Err(err)) => self.last_error = Some(err),
+                },
+            }
+        }
+
+        if let Some(passphrase) = self.passphrase.clone() {
+            if !self.unsaved.is_empty() {
+                let unsaved = std::mem::take(&mut self.unsaved);
+                self.encrypt(unsaved, passphrase);
+            }
+        }
+
+        updates
+    }
+}
+
+/// Decrypt every key we can. One that was locked with some other
+/// passphrase shouldn't keep the rest locked.
+fn decrypt_all(ncryptsecs: &[String], passphrase: &str) -> Result<Vec<SecretKey>, NcryptsecError> {
+    let mut keys = Vec::new();
+    let mut last_err = None;
+
+    for ncryptsec in ncryptsecs {
+        match decrypt_ncryptsec(ncryptsec, passphrase) {
+            Ok(key) => keys.push(key),
+            Err(err) => last_err = Some(err),
+        }
+    }
+
+    match last_err {
+        Some(err) if keys.is_empty() => Err(err),
+        _ => Ok(keys),
+    }
+}
@@ -1,8 +1,11 @@
 use std::collections::BTreeSet;
+use std::time::Duration;
 
 use enostr::{Keypair, Pubkey, SecretKey};
 use tracing::error;
 
+use crate::nip19::NostrUri;
+
 pub struct Args {
     pub relays: Vec<String>,
     pub is_mobile: Option<bool>,
@@ -17,6 +20,16 @@ pub struct Args {
     pub use_keystore: bool,
     pub dbpath: Option<String>,
     pub datapath: Option<String>,
+
+    /// How long to wait on a relay connection before retrying
+    pub connect_timeout: Option<Duration>,
+
+    /// Run offline against a local relay full of demo content
+    pub demo: bool,
+
+    /// `nostr:` uris to open, like when notedeck is the system's handler
+    /// for them
+    pub open_uris: Vec<String>,
 }
 
 impl Args {
@@ -34,6 +47,9 @@ impl Args {
             use_keystore: true,
             dbpath: None,
             datapath: None,
+            connect_timeout: None,
+            demo: false,
+            open_uris: vec![],
         };
 
         let mut i = 0;
@@ -112,10 +128,36 @@ impl Args {
                     continue;
                 };
                 res.relays.push(relay.clone());
+            } else if arg == "--connect-timeout" {
+                i += 1;
+                let secs = if let Some(next_arg) = args.get(i) {
+                    next_arg
+                } else {
+                    error!("connect-timeout argument missing?");
+                    continue;
+                };
+
+                match secs.parse::<f32>() {
+                    Ok(secs) if secs > 0.0 => {
+                        res.connect_timeout = Some(Duration::from_secs_f32(secs))
+                    }
+                    _ => error!("--connect-timeout takes a number of seconds, got {secs}"),
+                }
+            } else if arg == "--demo" {
+                res.demo = true;
             } else if arg == "--no-keystore" {
                 res.use_keystore = false;
             } else if arg == "--relay-debug" {
                 res.relay_debug = true;
+            } else if ["nostr:", "web+nostr:", "nostr+notedeck:"]
+                .iter()
+                .any(|scheme| arg.starts_with(scheme))
+            {
+                if NostrUri::parse(arg).is_some() {
+                    res.open_uris.push(arg.clone());
+                } else {
+                    error!("can't open {arg}");
+                }
             } else {
                 unrecognized_args.insert(arg.clone());
             }
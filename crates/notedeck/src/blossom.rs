@@ -0,0 +1,206 @@
+use enostr::{Pubkey, RelayPool};
+use nostrdb::{Filter, Ndb, Note, Subscription, Transaction};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+/// BUD-03 user server list
+pub const BLOSSOM_SERVER_LIST_KIND: u64 = 10063;
+
+/// The selected account's Blossom servers (BUD-03), most preferred
+/// first. Uploads go to these, and media that's gone from one server is
+/// looked for on the others.
+#[derive(Default)]
+pub struct BlossomServers {
+    account: Option<Pubkey>,
+    servers: Vec<String>,
+
+    sub: Option<Subscription>,
+    remote_subid: Option<String>,
+}
+
+impl BlossomServers {
+    pub fn servers(&self) -> &[String] {
+        &self.servers
+    }
+
+    /// Follow the selected account's server list. Returns true when the
+    /// list changed.
+    pub fn update(
+        &mut self,
+        ndb: &mut Ndb,
+        pool: &mut RelayPool,
+        selected: Option<&Pubkey>,
+    ) -> bool {
+        let mut changed = false;
+
+        if self.account.as_ref() != selected {
+            self.deactivate(ndb, pool);
+            self.account = selected.copied();
+            changed = !self.servers.is_empty();
+            self.servers.clear();
+
+            if let Some(account) = self.account {
+                changed |= self.activate(ndb, pool, &account);
+            }
+        }
+
+        let Some(sub) = self.sub else {
+            return changed;
+        };
+
+        if ndb.poll_for_notes(sub, 10).is_empty() {
+            return changed;
+        }
+
+        let Some(account) = self.account else {
+            return changed;
+        };
+        let servers = newest_server_list(ndb, account.bytes());
+        if servers != self.servers {
+            debug!("blossom servers for {}: {:?}", account.hex(), servers);
+            self.servers = servers;
+            changed = true;
+        }
+
+        changed
+    }
+
+    fn activate(&mut self, ndb: &Ndb, pool: &mut RelayPool, account: &Pubkey) -> bool {
+        let filter = Filter::new()
+            .authors([account.bytes()])
+            .kinds([BLOSSOM_SERVER_LIST_KIND])
+            .limit(1)
+            .build();
+
+        match ndb.subscribe(&[filter.clone()]) {
+            Ok(sub) => self.sub = Some(sub),
+            Err(e) => error!("blossom server list subscription: {e}"),
+        }
+
+        let subid = format!("blossom-{}", Uuid::new_v4());
+        pool.subscribe(subid.clone(), vec![filter]);
+        self.remote_subid = Some(subid);
+
+        self.servers = newest_server_list(ndb, account.bytes());
+        !self.servers.is_empty()
+    }
+
+    fn deactivate(&mut self, ndb: &mut Ndb, pool: &mut RelayPool) {
+        if let Some(sub) = self.sub.take() {
+            if let Err(e) = ndb.unsubscribe(sub) {
+                error!("blossom server list unsubscribe: {e}");
+            }
+        }
+        if let Some(subid) = self.remote_subid.take() {
+            pool.unsubscribe(subid);
+        }
+    }
+
+    /// Whether an EOSE was for our server list. We keep the subscription
+    /// open for updates.
+    pub fn handle_eose(&self, subid: &str) -> bool {
+        self.remote_subid.as_deref() == Some(subid)
+    }
+}
+
+fn newest_server_list(ndb: &Ndb, pubkey: &[u8; 32]) -> Vec<String> {
+    let filter = Filter::new()
+        .authors([pubkey])
+        .kinds([BLOSSOM_SERVER_LIST_KIND])
+        .limit(1)
+        .build();
+
+    let Ok(txn) = Transaction::new(ndb) else {
+        return Vec::new();
+    };
+    let Ok(results) = ndb.query(&txn, &[filter], 1) else {
+        return Vec::new();
+    };
+
+    results
+        .first()
+        .map(|result| server_list(&result.note))
+        .unwrap_or_default()
+}
+
+/// The `server` tags of a kind 10063 list, in order
+pub fn server_list(note: &Note) -> Vec<String> {
+    let mut servers = Vec::new();
+    for tag in note.tags() {
+        if tag.count() < 2 || tag.get_str(0) != Some("server") {
+            continue;
+        }
+
+        let Some(server) = tag.get_str(1).map(|s| s.trim().trim_end_matches('/')) else {
+            continue;
+        };
+        if server.starts_with("http") && !servers.iter().any(|s| s == server) {
+            servers.push(server.to_owned());
+        }
+    }
+    servers
+}
+
+/// The sha256 and extension of a blob url, if its last path segment
+/// looks like one (BUD-01)
+pub fn blob_hash(url: &str) -> Option<(&str, Option<&str>)> {
+    let path = url.split(['?', '#']).next()?;
+    let last = path.rsplit('/').next()?;
+    let (hash, ext) = match last.split_once('.') {
+        Some((hash, ext)) => (hash, Some(ext)),
+        None => (last, None),
+    };
+
+    (hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())).then_some((hash, ext))
+}
+
+/// Where else to look for a blob when its url stops working. Empty if
+/// `url` isn't a blob url.
+pub fn fallback_urls(url: &str, servers: &[String]) -> Vec<String> {
+    let Some((hash, ext)) = blob_hash(url) else {
+        return Vec::new();
+    };
+
+    servers
+        .iter()
+        .filter(|server| !url.starts_with(server.as_str()))
+        .map(|server| match ext {
+            Some(ext) => format!("{server}/{hash}.{ext}"),
+            None => format!("{server}/{hash}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "b1674191a88ec5cdd733e4240a81803105dc412d6c6708d53ab94fc248f4f553";
+
+    #[test]
+    fn test_blob_hash() {
+        let url = format!("https://cdn.example.com/{HASH}.png");
+        assert_eq!(blob_hash(&url), Some((HASH, Some("png"))));
+
+        let url = format!("https://cdn.example.com/{HASH}?w=200");
+        assert_eq!(blob_hash(&url), Some((HASH, None)));
+
+        assert_eq!(blob_hash("https://nostr.build/i/abc.png"), None);
+    }
+
+    #[test]
+    fn test_fallback_urls() {
+        let servers = vec![
+            "https://cdn.example.com".to_owned(),
+            "https://backup.example.com".to_owned(),
+        ];
+
+        let url = format!("https://cdn.example.com/{HASH}.jpg");
+        assert_eq!(
+            fallback_urls(&url, &servers),
+            vec![format!("https://backup.example.com/{HASH}.jpg")]
+        );
+
+        assert!(fallback_urls("https://nostr.build/i/abc.png", &servers).is_empty());
+    }
+}
@@ -0,0 +1,387 @@
+use enostr::{Pubkey, RelayPool};
+use nostrdb::{Filter, Ndb, Note, Subscription, Transaction};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::lists::{note_tags, sign_list, ListError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactAction {
+    Follow(Pubkey),
+    Unfollow(Pubkey),
+}
+
+impl ContactAction {
+    pub fn pubkey(&self) -> &Pubkey {
+        match self {
+            ContactAction::Follow(pk) | ContactAction::Unfollow(pk) => pk,
+        }
+    }
+}
+
+/// Follows that were on a copy of our contact list we hadn't seen when
+/// we last edited it. Our edit replaced that copy, so they're gone
+/// unless we put them back.
+#[derive(Debug, Clone)]
+pub struct ContactConflict {
+    pub missing: Vec<Pubkey>,
+}
+
+/// What our last follow or unfollow was made on top of
+struct ContactEdit {
+    /// created_at of the contact list we edited, 0 if we had none
+    based_on: u64,
+    published_at: u64,
+    unfollowed: Option<Pubkey>,
+}
+
+/// An account's contact list (kind 3). We keep a subscription open
+/// while the account is selected so we always edit the freshest copy
+/// any relay has, and notice when someone else's copy crosses ours.
+pub struct AccountContactData {
+    filter: Filter,
+    subid: Option<String>,
+    sub: Option<Subscription>,
+
+    /// A relay has sent us everything it has for this account
+    synced: bool,
+
+    last_edit: Option<ContactEdit>,
+    conflict: Option<ContactConflict>,
+}
+
+impl AccountContactData {
+    pub fn new(pubkey: &[u8; 32]) -> Self {
+        let filter = Filter::new().authors([pubkey]).kinds([3]).limit(1).build();
+
+        AccountContactData {
+            filter,
+            subid: None,
+            sub: None,
+            synced: false,
+            last_edit: None,
+            conflict: None,
+        }
+    }
+
+    pub fn activate(&mut self, ndb: &Ndb, pool: &mut RelayPool) {
+        debug!("activating contacts sub {}", self.filter.json().unwrap());
+        assert_eq!(self.subid, None, "subid already exists");
+        assert_eq!(self.sub, None, "sub already exists");
+
+        let sub = ndb
+            .subscribe(&[self.filter.clone()])
+            .expect("ndb contacts subscription");
+
+        let subid = Uuid::new_v4().to_string();
+        pool.subscribe(subid.clone(), vec![self.filter.clone()]);
+
+        self.sub = Some(sub);
+        self.subid = Some(subid);
+    }
+
+    pub fn deactivate(&mut self, ndb: &mut Ndb, pool: &mut RelayPool) {
+        debug!("deactivating contacts sub {}", self.filter.json().unwrap());
+        assert_ne!(self.subid, None, "subid doesn't exist");
+        assert_ne!(self.sub, None, "sub doesn't exist");
+
+        pool.unsubscribe(self.subid.as_ref().unwrap().clone());
+        ndb.unsubscribe(self.sub.unwrap())
+            .expect("ndb contacts unsubscribe");
+
+        self.sub = None;
+        self.subid = None;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.sub.is_some()
+    }
+
+    pub fn subid(&self) -> Option<&str> {
+        self.subid.as_deref()
+    }
+
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.synced
+    }
+
+    pub fn handle_eose(&mut self, subid: &str) -> bool {
+        if self.subid.as_deref() != Some(subid) {
+            return false;
+        }
+
+        self.synced = true;
+        true
+    }
+
+    pub fn conflict(&self) -> Option<&ContactConflict> {
+        self.conflict.as_ref()
+    }
+
+    pub fn dismiss_conflict(&mut self) {
+        self.conflict = None;
+    }
+
+    /// Look for new copies of the contact list. Returns true if one
+    /// arrived, contact timelines will want to refresh.
+    pub fn poll(&mut self, ndb: &Ndb) -> bool {
+        let Some(sub) = self.sub else {
+            return false;
+        };
+
+        let nks = ndb.poll_for_notes(sub, 10);
+        if nks.is_empty() {
+            return false;
+        }
+
+        let txn = Transaction::new(ndb).expect("txn");
+        for nk in nks {
+            let Ok(note) = ndb.get_note_by_key(&txn, nk) else {
+                continue;
+            };
+            self.check_conflict(ndb, &txn, &note);
+        }
+
+        true
+    }
+
+    fn check_conflict(&mut self, ndb: &Ndb, txn: &Transaction, arrived: &Note) {
+        let Some(edit) = &self.last_edit else {
+            return;
+        };
+
+        // newer than our edit means someone edited after us, which is
+        // fine. Only copies we never saw but replaced are a problem.
+        let created_at = arrived.created_at();
+        if created_at <= edit.based_on || created_at >= edit.published_at {
+            return;
+        }
+
+        let ours = match newest_contact_list(ndb, txn, arrived.pubkey()) {
+            Some(note) => follows(&note),
+            None => vec![],
+        };
+
+        let missing = dropped_follows(&follows(arrived), &ours, edit.unfollowed.as_ref());
+        if missing.is_empty() {
+            return;
+        }
+
+        warn!(
+            "contact list from {} is newer than the one we edited, {} follows were dropped",
+            created_at,
+            missing.len()
+        );
+        self.conflict = Some(ContactConflict { missing });
+    }
+
+    fn record_edit(&mut self, based_on: u64, published_at: u64, action: &ContactAction) {
+        let unfollowed = match action {
+            ContactAction::Unfollow(pk) => Some(*pk),
+            ContactAction::Follow(_) => None,
+        };
+
+        self.last_edit = Some(ContactEdit {
+            based_on,
+            published_at,
+            unfollowed,
+        });
+        self.conflict = None;
+    }
+}
+
+/// Apply an action to the newest contact list we have for `pubkey` and
+/// build the replacement as json, signed with `seckey`. None if the
+/// action doesn't change anything. Refuses until a relay has sent its
+/// copy, since the one in ndb may be from an earlier session and older
+/// than what relays have.
+pub fn edit_contact_list(
+    ndb: &Ndb,
+    data: &mut AccountContactData,
+    pubkey: &Pubkey,
+    action: &ContactAction,
+    seckey: &[u8; 32],
+    now: u64,
+) -> Result<Option<String>, ListError> {
+    if !data.is_synced() {
+        return Err(ListError::NotSynced);
+    }
+
+    let txn = Transaction::new(ndb).map_err(|e| ListError::Build(e.to_string()))?;
+    let current = newest_contact_list(ndb, &txn, pubkey.bytes());
+
+    let (tags, content, based_on) = match &current {
+        Some(note) => (
+            note_tags(note),
+            note.content().to_owned(),
+            note.created_at(),
+        ),
+        None => (vec![], String::new(), 0),
+    };
+
+    let Some(tags) = edit_tags(tags, action) else {
+        return Ok(None);
+    };
+
+    // replaceable events are ordered by created_at, so make sure ours
+    // wins even if the clock is behind the copy we edited
+    let created_at = now.max(based_on + 1);
+
+    let json = sign_list(3, &content, &tags, seckey, created_at)?;
+
+    data.record_edit(based_on, created_at, action);
+
+    Ok(Some(json))
+}
+
+pub fn newest_contact_list<'a>(
+    ndb: &Ndb,
+    txn: &'a Transaction,
+    pubkey: &[u8; 32],
+) -> Option<Note<'a>> {
+    let filter = Filter::new().authors([pubkey]).kinds([3]).limit(1).build();
+    ndb.query(txn, &[filter], 1)
+        .ok()?
+        .into_iter()
+        .max_by_key(|result| result.note.created_at())
+        .map(|result| result.note)
+}
+
+fn follows(note: &Note) -> Vec<Pubkey> {
+    note_tags(note)
+        .iter()
+        .filter_map(|tag| match tag.as_slice() {
+            [p, pk, ..] if p == "p" => Pubkey::from_hex(pk).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+fn is_follow_of(tag: &[String], pubkey: &str) -> bool {
+    matches!(tag, [p, pk, ..] if p == "p" && pk == pubkey)
+}
+
+/// The contact list tags after an action, None if nothing changed.
+/// Everything else on the list (relay hints, petnames, hashtags) is
+/// kept as is.
+fn edit_tags(mut tags: Vec<Vec<String>>, action: &ContactAction) -> Option<Vec<Vec<String>>> {
+    let pubkey = action.pubkey().hex();
+
+    match action {
+        ContactAction::Follow(_) => {
+            if tags.iter().any(|tag| is_follow_of(tag, &pubkey)) {
+                return None;
+            }
+            tags.push(vec!["p".to_owned(), pubkey]);
+        }
+        ContactAction::Unfollow(_) => {
+            let before = tags.len();
+            tags.retain(|tag| !is_follow_of(tag, &pubkey));
+            if tags.len() == before {
+                return None;
+            }
+        }
+    }
+
+    Some(tags)
+}
+
+/// Follows on `theirs` that `ours` doesn't have, other than the one we
+/// meant to drop
+fn dropped_follows(theirs: &[Pubkey], ours: &[Pubkey], unfollowed: Option<&Pubkey>) -> Vec<Pubkey> {
+    theirs
+        .iter()
+        .filter(|pk| !ours.contains(pk) && Some(*pk) != unfollowed)
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enostr::FullKeypair;
+    use nostrdb::{Config, IngestMetadata};
+    use tempfile::TempDir;
+
+    fn pk(n: u8) -> Pubkey {
+        Pubkey::new([n; 32])
+    }
+
+    fn p_tag(pubkey: &Pubkey) -> Vec<String> {
+        vec!["p".to_owned(), pubkey.hex()]
+    }
+
+    #[test]
+    fn test_follow_adds_once() {
+        let tags = vec![p_tag(&pk(1)), vec!["t".to_owned(), "nostr".to_owned()]];
+
+        let followed = edit_tags(tags.clone(), &ContactAction::Follow(pk(2))).unwrap();
+        assert_eq!(followed.len(), 3);
+        assert_eq!(followed[2], p_tag(&pk(2)));
+
+        assert!(edit_tags(followed, &ContactAction::Follow(pk(2))).is_none());
+    }
+
+    #[test]
+    fn test_unfollow_keeps_other_tags() {
+        let mut with_hint = p_tag(&pk(1));
+        with_hint.push("wss://relay.damus.io".to_owned());
+        let tags = vec![
+            with_hint,
+            p_tag(&pk(2)),
+            vec!["t".to_owned(), "nostr".to_owned()],
+        ];
+
+        let unfollowed = edit_tags(tags, &ContactAction::Unfollow(pk(1))).unwrap();
+        assert_eq!(
+            unfollowed,
+            vec![p_tag(&pk(2)), vec!["t".to_owned(), "nostr".to_owned()]]
+        );
+
+        assert!(edit_tags(unfollowed, &ContactAction::Unfollow(pk(1))).is_none());
+    }
+
+    #[test]
+    fn test_dropped_follows() {
+        let theirs = [pk(1), pk(2), pk(3)];
+        let ours = [pk(1), pk(4)];
+
+        assert_eq!(dropped_follows(&theirs, &ours, Some(&pk(3))), vec![pk(2)]);
+        assert!(dropped_follows(&ours, &ours, None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_edit_before_eose_with_stale_list() {
+        let tmp_dir = TempDir::new().unwrap();
+        let ndb = Ndb::new(tmp_dir.path().to_str().unwrap(), &Config::new()).unwrap();
+        let account = FullKeypair::generate();
+        let seckey = account.secret_key.to_secret_bytes();
+
+        // a list left over from an earlier session
+        let stale = sign_list(3, "", &[p_tag(&pk(1))], &seckey, 1_700_000_000).unwrap();
+        let sub = ndb
+            .subscribe(&[Filter::new().authors([account.pubkey.bytes()]).build()])
+            .unwrap();
+        ndb.process_event_with(
+            &format!(r#"["EVENT","sub",{stale}]"#),
+            IngestMetadata::new(),
+        )
+        .unwrap();
+        ndb.wait_for_notes(sub, 1).await.unwrap();
+
+        let mut data = AccountContactData::new(account.pubkey.bytes());
+        let edit = edit_contact_list(
+            &ndb,
+            &mut data,
+            &account.pubkey,
+            &ContactAction::Follow(pk(2)),
+            &seckey,
+            1_700_000_100,
+        );
+        assert!(matches!(edit, Err(ListError::NotSynced)));
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{
-    frame_history::FrameHistory, wallet::GlobalWallet, zaps::Zaps, Accounts, Args, DataPath,
-    Images, JobPool, NoteCache, ThemeHandler, UnknownIds,
+    accessibility::Accessibility, frame_history::FrameHistory, nip19::NostrUri,
+    wallet::GlobalWallet, zaps::Zaps, Accounts, Args, BlossomServers, Counts, DataPath,
+    FollowedThreads, Images, JobPool, NoteCache, NotificationReadState, NotificationWatcher,
+    OsNotifications, PaidContent, Polls, QuietHours, Sounds, ThemeHandler, UnknownIds, WebOfTrust,
+    ZapGoals,
 };
 use egui_winit::clipboard::Clipboard;
 
@@ -24,4 +27,20 @@ pub struct AppContext<'a> {
     pub zaps: &'a mut Zaps,
     pub frame_history: &'a mut FrameHistory,
     pub job_pool: &'a mut JobPool,
+    pub counts: &'a mut Counts,
+    pub polls: &'a mut Polls,
+    pub zap_goals: &'a mut ZapGoals,
+    pub paid_content: &'a mut PaidContent,
+    pub wot: &'a mut WebOfTrust,
+    pub blossom: &'a BlossomServers,
+    pub accessibility: &'a mut Accessibility,
+    pub quiet_hours: &'a mut QuietHours,
+    pub sounds: &'a mut Sounds,
+    pub notifications: &'a mut NotificationWatcher,
+    pub read_state: &'a mut NotificationReadState,
+    pub followed_threads: &'a mut FollowedThreads,
+    pub os_notifications: &'a mut OsNotifications,
+
+    /// `nostr:` uris waiting for an app to open them
+    pub opened_uris: &'a mut Vec<NostrUri>,
 }
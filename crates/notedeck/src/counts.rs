@@ -0,0 +1,374 @@
+use enostr::{ClientMessage, Filter, NoteId, Pubkey, RelayPool};
+use nostrdb::{Ndb, Transaction};
+use poll_promise::Promise;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::debouncer::Debouncer;
+
+/// How long a local count is good for before we recount from nostrdb
+const LOCAL_REFRESH: Duration = Duration::from_secs(10);
+
+/// How long before we ask relays for a fresh count
+const REMOTE_REFRESH: Duration = Duration::from_secs(5 * 60);
+
+/// Upper bound for local counts. Past this we show the relay count, if
+/// we have one.
+const MAX_LOCAL_COUNT: i32 = 10_000;
+
+/// Only count things that were on screen this recently
+const SEEN_WINDOW: Duration = Duration::from_secs(5);
+
+/// Max counts and participant lists we look up locally per batch
+const MAX_LOCAL_PER_BATCH: usize = 100;
+
+/// Max COUNT requests we send per batch
+const MAX_REQUESTS_PER_BATCH: usize = 20;
+
+/// How many recent repliers we keep per note
+const MAX_PARTICIPANTS: usize = 3;
+
+/// How many of the latest replies we look through for them
+const PARTICIPANT_SCAN: i32 = 50;
+
+/// Something we can count
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CountKind {
+    Followers(Pubkey),
+    Replies(NoteId),
+    Reposts(NoteId),
+    Reactions(NoteId),
+    Zaps(NoteId),
+}
+
+impl CountKind {
+    pub fn filter(&self) -> Filter {
+        match self {
+            CountKind::Followers(pk) => Filter::new().kinds([3]).pubkeys([pk.bytes()]).build(),
+            CountKind::Replies(id) => Filter::new().kinds([1]).event(id.bytes()).build(),
+            CountKind::Reposts(id) => Filter::new().kinds([6, 16]).event(id.bytes()).build(),
+            CountKind::Reactions(id) => Filter::new().kinds([7]).event(id.bytes()).build(),
+            CountKind::Zaps(id) => Filter::new().kinds([9735]).event(id.bytes()).build(),
+        }
+    }
+}
+
+struct CountEntry {
+    local: u64,
+    /// When the local count was last started, None if it never was
+    local_updated: Option<Instant>,
+
+    /// The highest count any relay reported
+    remote: Option<u64>,
+    remote_requested: Option<Instant>,
+
+    last_seen: Instant,
+}
+
+impl CountEntry {
+    fn best(&self) -> u64 {
+        self.remote
+            .map_or(self.local, |remote| remote.max(self.local))
+    }
+
+    fn wants_local(&self) -> bool {
+        if self.last_seen.elapsed() > SEEN_WINDOW {
+            return false;
+        }
+
+        match self.local_updated {
+            None => true,
+            Some(updated) => updated.elapsed() > LOCAL_REFRESH,
+        }
+    }
+
+    fn wants_remote(&self) -> bool {
+        if self.last_seen.elapsed() > SEEN_WINDOW {
+            return false;
+        }
+
+        match self.remote_requested {
+            None => true,
+            Some(requested) => requested.elapsed() > REMOTE_REFRESH,
+        }
+    }
+}
+
+struct ParticipantsEntry {
+    author: [u8; 32],
+    pubkeys: Vec<Pubkey>,
+    updated: Option<Instant>,
+    last_seen: Instant,
+}
+
+impl ParticipantsEntry {
+    fn wants_local(&self) -> bool {
+        if self.last_seen.elapsed() > SEEN_WINDOW {
+            return false;
+        }
+
+        match self.updated {
+            None => true,
+            Some(updated) => updated.elapsed() > LOCAL_REFRESH,
+        }
+    }
+}
+
+/// What a batch of local lookups found
+#[derive(Default)]
+struct LocalResults {
+    counts: Vec<(CountKind, u64)>,
+    participants: Vec<(NoteId, Vec<Pubkey>)>,
+}
+
+/// Follower and interaction counts.
+///
+/// Relays that advertise NIP-45 COUNT are asked for counts in batches.
+/// We also count what we have in nostrdb, so relays that don't support
+/// COUNT (or haven't answered yet) still show something. Local counts
+/// run on their own thread, rendering only ever reads what's cached.
+pub struct Counts {
+    entries: HashMap<CountKind, CountEntry>,
+
+    /// Outstanding COUNT subscription ids
+    inflight: HashMap<String, CountKind>,
+
+    /// The latest people to reply to a note
+    participants: HashMap<NoteId, ParticipantsEntry>,
+
+    /// Local counts being looked up
+    counting: Option<Promise<LocalResults>>,
+
+    debouncer: Debouncer,
+}
+
+impl Default for Counts {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            inflight: HashMap::new(),
+            participants: HashMap::new(),
+            counting: None,
+            debouncer: Debouncer::new(Duration::from_secs(1)),
+        }
+    }
+}
+
+impl Counts {
+    /// Get the current count for something. Anything we haven't counted
+    /// yet is 0 until [`Counts::count_local`] or a relay gets to it.
+    pub fn get(&mut self, kind: CountKind) -> u64 {
+        let entry = self.entries.entry(kind).or_insert_with(|| CountEntry {
+            local: 0,
+            local_updated: None,
+            remote: None,
+            remote_requested: None,
+            last_seen: Instant::now(),
+        });
+
+        entry.last_seen = Instant::now();
+        entry.best()
+    }
+
+    /// Reply and repost counts for a note
+    pub fn note_counts(&mut self, note_id: &[u8; 32]) -> NoteCounts {
+        let id = NoteId::new(*note_id);
+        NoteCounts {
+            replies: self.get(CountKind::Replies(id)),
+            reposts: self.get(CountKind::Reposts(id)),
+        }
+    }
+
+    /// The most recent people to reply to a note, newest first, not
+    /// counting its author. Only replies we have locally are looked at.
+    pub fn reply_participants(&mut self, note_id: &[u8; 32], author: &[u8; 32]) -> &[Pubkey] {
+        let entry = self
+            .participants
+            .entry(NoteId::new(*note_id))
+            .or_insert_with(|| ParticipantsEntry {
+                author: *author,
+                pubkeys: Vec::new(),
+                updated: None,
+                last_seen: Instant::now(),
+            });
+
+        entry.last_seen = Instant::now();
+        &entry.pubkeys
+    }
+
+    /// Pick up finished local counts and start counting whatever was on
+    /// screen recently and is stale. Called once a frame.
+    pub fn count_local(&mut self, ndb: &Ndb) {
+        if let Some(counting) = self.counting.take() {
+            match counting.try_take() {
+                Ok(results) => self.apply(results),
+                Err(counting) => {
+                    self.counting = Some(counting);
+                    return;
+                }
+            }
+        }
+
+        let now = Instant::now();
+        let mut kinds = Vec::new();
+        for (kind, entry) in self.entries.iter_mut() {
+            if kinds.len() == MAX_LOCAL_PER_BATCH {
+                break;
+            }
+            if entry.wants_local() {
+                entry.local_updated = Some(now);
+                kinds.push(*kind);
+            }
+        }
+
+        let mut notes = Vec::new();
+        for (id, entry) in self.participants.iter_mut() {
+            if notes.len() == MAX_LOCAL_PER_BATCH {
+                break;
+            }
+            if entry.wants_local() {
+                entry.updated = Some(now);
+                notes.push((*id, entry.author));
+            }
+        }
+
+        if kinds.is_empty() && notes.is_empty() {
+            return;
+        }
+
+        let ndb = ndb.clone();
+        self.counting = Some(Promise::spawn_thread("counts", move || {
+            let Ok(txn) = Transaction::new(&ndb) else {
+                return LocalResults::default();
+            };
+
+            LocalResults {
+                counts: kinds
+                    .into_iter()
+                    .map(|kind| (kind, local_count(&ndb, &txn, &kind)))
+                    .collect(),
+                participants: notes
+                    .into_iter()
+                    .map(|(id, author)| (id, local_participants(&ndb, &txn, &id, &author)))
+                    .collect(),
+            }
+        }));
+    }
+
+    fn apply(&mut self, results: LocalResults) {
+        for (kind, count) in results.counts {
+            if let Some(entry) = self.entries.get_mut(&kind) {
+                entry.local = count;
+            }
+        }
+
+        for (id, pubkeys) in results.participants {
+            if let Some(entry) = self.participants.get_mut(&id) {
+                entry.pubkeys = pubkeys;
+            }
+        }
+    }
+
+    /// Send COUNT requests for anything that was looked at recently and
+    /// doesn't have a fresh relay count. The pool only sends them to
+    /// relays that list NIP-45 in their relay information.
+    pub fn send_requests(&mut self, pool: &mut RelayPool) {
+        if !self.debouncer.should_act() {
+            return;
+        }
+        self.debouncer.bounce();
+
+        let wanted: Vec<CountKind> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.wants_remote())
+            .map(|(kind, _)| *kind)
+            .take(MAX_REQUESTS_PER_BATCH)
+            .collect();
+
+        for kind in wanted {
+            let sub_id = Uuid::new_v4().to_string();
+            debug!("sending COUNT {} for {:?}", sub_id, kind);
+            pool.send(&ClientMessage::count(sub_id.clone(), vec![kind.filter()]));
+
+            // every relay answers with the same subid, so the previous one
+            // for this kind stays around until we replace it here
+            self.inflight.retain(|_, inflight| *inflight != kind);
+            self.inflight.insert(sub_id, kind);
+
+            if let Some(entry) = self.entries.get_mut(&kind) {
+                entry.remote_requested = Some(Instant::now());
+            }
+        }
+    }
+
+    /// Process a COUNT result from a relay. Returns false if it wasn't
+    /// one of ours.
+    pub fn handle_count(&mut self, sub_id: &str, count: u64) -> bool {
+        let Some(kind) = self.inflight.get(sub_id) else {
+            return false;
+        };
+
+        if let Some(entry) = self.entries.get_mut(kind) {
+            entry.remote = Some(entry.remote.map_or(count, |c| c.max(count)));
+        }
+
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoteCounts {
+    pub replies: u64,
+    pub reposts: u64,
+}
+
+fn local_participants(ndb: &Ndb, txn: &Transaction, id: &NoteId, author: &[u8; 32]) -> Vec<Pubkey> {
+    let filter = CountKind::Replies(*id).filter();
+    let mut replies = match ndb.query(txn, &[filter], PARTICIPANT_SCAN) {
+        Ok(results) => results,
+        Err(err) => {
+            error!("reply participant query failed for {}: {err}", id.hex());
+            return Vec::new();
+        }
+    };
+    replies.sort_by_key(|r| std::cmp::Reverse(r.note.created_at()));
+
+    let mut participants: Vec<Pubkey> = Vec::with_capacity(MAX_PARTICIPANTS);
+    for reply in &replies {
+        let pubkey = reply.note.pubkey();
+        if pubkey == author || participants.iter().any(|pk| pk.bytes() == pubkey) {
+            continue;
+        }
+        participants.push(Pubkey::new(*pubkey));
+        if participants.len() == MAX_PARTICIPANTS {
+            break;
+        }
+    }
+
+    participants
+}
+
+fn local_count(ndb: &Ndb, txn: &Transaction, kind: &CountKind) -> u64 {
+    match ndb.query(txn, &[kind.filter()], MAX_LOCAL_COUNT) {
+        Ok(results) => match kind {
+            // only count each author's contact list once
+            CountKind::Followers(_) => {
+                let mut authors: Vec<&[u8; 32]> = results.iter().map(|r| r.note.pubkey()).collect();
+                authors.sort();
+                authors.dedup();
+                authors.len() as u64
+            }
+            CountKind::Replies(_)
+            | CountKind::Reposts(_)
+            | CountKind::Reactions(_)
+            | CountKind::Zaps(_) => results.len() as u64,
+        },
+        Err(err) => {
+            error!("local count query failed for {:?}: {err}", kind);
+            0
+        }
+    }
+}
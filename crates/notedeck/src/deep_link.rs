@@ -0,0 +1,150 @@
+//! `nostr+notedeck:` links, for scripts, browsers and other apps to tell
+//! notedeck what to do:
+//!
+//! - `nostr+notedeck://profile/<npub, nprofile or hex>` opens a profile
+//! - `nostr+notedeck://compose?text=<text>` starts a note with some text
+//! - `nostr+notedeck://relay/add?url=<relay url>` adds a relay
+//! - `nostr+notedeck://deck?name=<name>&column=<route>&column=<route>`
+//!   adds a deck, with a column for each route in the same format decks
+//!   are saved in, like `contact:<hex pubkey>`
+//!
+//! Anything that changes the user's setup waits for them to agree to it.
+
+use enostr::Pubkey;
+use url::Url;
+
+use crate::nip19::NostrUri;
+
+pub const SCHEME: &str = "nostr+notedeck";
+
+/// Most columns one link can ask for
+const MAX_COLUMNS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLink {
+    Profile([u8; 32]),
+    Compose {
+        text: String,
+    },
+    AddRelay(String),
+    Deck {
+        name: String,
+        icon: Option<char>,
+        columns: Vec<String>,
+    },
+}
+
+impl DeepLink {
+    pub fn parse(uri: &str) -> Option<Self> {
+        let url = Url::parse(uri.trim()).ok()?;
+        if url.scheme() != SCHEME {
+            return None;
+        }
+
+        let query = |key: &str| query_values(&url, key).into_iter().next();
+        let mut path = url.path_segments().into_iter().flatten();
+
+        match url.host_str()? {
+            "profile" => {
+                let entity = path.next()?;
+                let pubkey = match NostrUri::parse(entity) {
+                    Some(NostrUri::Profile { pubkey, .. }) => pubkey,
+                    _ => *Pubkey::from_hex(entity).ok()?.bytes(),
+                };
+                Some(DeepLink::Profile(pubkey))
+            }
+
+            "compose" => Some(DeepLink::Compose {
+                text: query("text").unwrap_or_default(),
+            }),
+
+            "relay" if path.next() == Some("add") => {
+                let relay = query("url")?.trim().to_owned();
+                if !matches!(Url::parse(&relay).ok()?.scheme(), "ws" | "wss") {
+                    return None;
+                }
+                Some(DeepLink::AddRelay(relay))
+            }
+
+            "deck" => {
+                let mut columns = query_values(&url, "column");
+                if columns.is_empty() {
+                    return None;
+                }
+                columns.truncate(MAX_COLUMNS);
+                Some(DeepLink::Deck {
+                    name: query("name").unwrap_or_else(|| "Linked".to_owned()),
+                    icon: query("icon").and_then(|icon| icon.chars().next()),
+                    columns,
+                })
+            }
+
+            _ => None,
+        }
+    }
+
+    /// Whether following the link changes the user's setup, so they should
+    /// be asked first. Opening a profile or a draft doesn't.
+    pub fn needs_confirmation(&self) -> bool {
+        match self {
+            DeepLink::Profile(_) | DeepLink::Compose { .. } => false,
+            DeepLink::AddRelay(_) | DeepLink::Deck { .. } => true,
+        }
+    }
+}
+
+/// Every value `key` has in the link's query, decoded
+fn query_values(url: &Url, key: &str) -> Vec<String> {
+    url.query_pairs()
+        .filter(|(k, _)| k == key)
+        .map(|(_, value)| value.into_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_parse_into_commands() {
+        let hex = "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d";
+        let Some(DeepLink::Profile(pubkey)) =
+            DeepLink::parse(&format!("nostr+notedeck://profile/{hex}"))
+        else {
+            panic!("expected a profile");
+        };
+        assert_eq!(hex::encode(pubkey), hex);
+
+        assert_eq!(
+            DeepLink::parse("nostr+notedeck://compose?text=gm%20%23coffee"),
+            Some(DeepLink::Compose {
+                text: "gm #coffee".to_owned()
+            })
+        );
+
+        assert_eq!(
+            DeepLink::parse("nostr+notedeck://relay/add?url=wss%3A%2F%2Frelay.example.com"),
+            Some(DeepLink::AddRelay("wss://relay.example.com".to_owned()))
+        );
+        assert_eq!(
+            DeepLink::parse("nostr+notedeck://relay/add?url=https://example.com"),
+            None
+        );
+
+        let deck =
+            DeepLink::parse("nostr+notedeck://deck?name=Work&column=universe&column=hashtag:rust")
+                .unwrap();
+        assert!(deck.needs_confirmation());
+        assert_eq!(
+            deck,
+            DeepLink::Deck {
+                name: "Work".to_owned(),
+                icon: None,
+                columns: vec!["universe".to_owned(), "hashtag:rust".to_owned()],
+            }
+        );
+
+        assert_eq!(DeepLink::parse("nostr+notedeck://deck?name=Empty"), None);
+        assert_eq!(DeepLink::parse("nostr+notedeck://launch-missiles"), None);
+    }
+}
@@ -0,0 +1,156 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use enostr::{FullKeypair, Keypair, MockRelay};
+use nostrdb::{NoteBuildOptions, NoteBuilder};
+use tracing::info;
+
+/// A local relay full of made-up people and notes, so notedeck can be
+/// tried out (or shown off) without a network connection
+pub struct Demo {
+    pub relay: MockRelay,
+
+    /// Follows everyone on the demo relay and can post to it
+    pub account: Keypair,
+}
+
+const PEOPLE: &[(&str, &str)] = &[
+    (
+        "alice",
+        "Runs a relay in her closet. Mostly posts about gardening.",
+    ),
+    ("bob", "Lightning node operator, amateur baker."),
+    ("carol", "Writes clients for fun. Ask me about egui."),
+];
+
+const NOTES: &[(usize, &str)] = &[
+    (
+        0,
+        "The tomatoes finally came in. Pictures later, they're too good to rush.",
+    ),
+    (
+        1,
+        "Sourdough attempt number 14. This one actually rose! #baking",
+    ),
+    (
+        2,
+        "Working on column layouts today, multi-account decks are surprisingly fun.",
+    ),
+    (0, "Is it just me or are relays getting faster? #nostr"),
+    (
+        1,
+        "Opened a few more channels, zaps should route better now ⚡",
+    ),
+    (
+        2,
+        "Reminder that you can bind a column to a different account from its header.",
+    ),
+];
+
+impl Demo {
+    pub fn start() -> std::io::Result<Self> {
+        let account = FullKeypair::generate();
+        let people: Vec<FullKeypair> = PEOPLE.iter().map(|_| FullKeypair::generate()).collect();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        let mut events = vec![profile(&account, "you", "This is the demo account.", now)];
+        for (kp, (name, about)) in people.iter().zip(PEOPLE) {
+            events.push(profile(kp, name, about, now));
+        }
+        events.push(contact_list(&account, &people, now));
+
+        let mut first_note = None;
+        for (i, (author, content)) in NOTES.iter().enumerate() {
+            let created_at = now - 600 * (NOTES.len() - i) as u64;
+            let (json, id) = text_note(&people[*author], content, created_at, None);
+            first_note.get_or_insert((id, &people[*author]));
+            events.push(json);
+        }
+
+        // give the first note a reply so threads have something to show
+        if let Some((id, author)) = first_note {
+            let reply = "Save me one for the bread!";
+            let (json, _) = text_note(&people[1], reply, now - 60, Some((&id, author)));
+            events.push(json);
+        }
+
+        let relay = MockRelay::start(events)?;
+        info!("demo relay running at {}", relay.url());
+
+        Ok(Demo {
+            relay,
+            account: account.to_keypair(),
+        })
+    }
+}
+
+fn profile(kp: &FullKeypair, name: &str, about: &str, now: u64) -> String {
+    let sec = kp.secret_key.to_secret_bytes();
+    let content = serde_json::json!({"name": name, "display_name": name, "about": about});
+
+    NoteBuilder::new()
+        .kind(0)
+        .content(&content.to_string())
+        .created_at(now - 86400)
+        .options(NoteBuildOptions::default().created_at(false).sign(&sec))
+        .build()
+        .expect("demo profile")
+        .json()
+        .expect("demo profile json")
+}
+
+fn contact_list(kp: &FullKeypair, follows: &[FullKeypair], now: u64) -> String {
+    let sec = kp.secret_key.to_secret_bytes();
+    let follows: Vec<String> = follows.iter().map(|f| f.pubkey.hex()).collect();
+
+    let mut builder = NoteBuilder::new()
+        .kind(3)
+        .content("")
+        .created_at(now - 86400);
+    for pubkey in &follows {
+        builder = builder.start_tag().tag_str("p").tag_str(pubkey);
+    }
+
+    builder
+        .options(NoteBuildOptions::default().created_at(false).sign(&sec))
+        .build()
+        .expect("demo contact list")
+        .json()
+        .expect("demo contact list json")
+}
+
+/// A kind 1 note as json, and its id
+fn text_note(
+    kp: &FullKeypair,
+    content: &str,
+    created_at: u64,
+    reply_to: Option<(&[u8; 32], &FullKeypair)>,
+) -> (String, [u8; 32]) {
+    let sec = kp.secret_key.to_secret_bytes();
+    let reply_tags = reply_to.map(|(id, author)| (hex::encode(id), author.pubkey.hex()));
+
+    let mut builder = NoteBuilder::new()
+        .kind(1)
+        .content(content)
+        .created_at(created_at);
+
+    if let Some((id, pubkey)) = &reply_tags {
+        builder = builder
+            .start_tag()
+            .tag_str("e")
+            .tag_str(id)
+            .tag_str("")
+            .tag_str("root")
+            .start_tag()
+            .tag_str("p")
+            .tag_str(pubkey);
+    }
+
+    let note = builder
+        .options(NoteBuildOptions::default().created_at(false).sign(&sec))
+        .build()
+        .expect("demo note");
+
+    (note.json().expect("demo note json"), *note.id())
+}
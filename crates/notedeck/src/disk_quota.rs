@@ -0,0 +1,269 @@
+//! Keeps the media cache on disk under a size limit. Files are touched
+//! whenever they're read back (see [`crate::MediaCache::touch`]), so their
+//! modification time doubles as the last access and the least recently
+//! used ones go first.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use poll_promise::Promise;
+use tracing::{debug, info, warn};
+
+/// How often the cache is measured, and trimmed if it's over quota
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Trim a bit below the quota so we aren't evicting on every sweep
+const EVICT_TO: f64 = 0.9;
+
+const MB: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CacheQuotaSettings {
+    pub max_mb: u64,
+}
+
+impl Default for CacheQuotaSettings {
+    fn default() -> Self {
+        Self { max_mb: 1024 }
+    }
+}
+
+impl CacheQuotaSettings {
+    pub fn max_bytes(&self) -> u64 {
+        self.max_mb * MB
+    }
+}
+
+enum Job {
+    Sweep,
+    Clear,
+}
+
+pub struct DiskQuota {
+    dirs: Vec<PathBuf>,
+    settings: CacheQuotaSettings,
+    settings_changed: bool,
+
+    /// Bytes on disk as of the last sweep
+    usage: Option<u64>,
+    running: Option<Promise<u64>>,
+    queued: Option<Job>,
+    next_sweep: Instant,
+}
+
+impl DiskQuota {
+    pub fn new(dirs: Vec<PathBuf>) -> Self {
+        Self {
+            dirs,
+            settings: CacheQuotaSettings::default(),
+            settings_changed: false,
+            usage: None,
+            running: None,
+            queued: None,
+            next_sweep: Instant::now(),
+        }
+    }
+
+    pub fn settings(&self) -> CacheQuotaSettings {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: CacheQuotaSettings) {
+        if settings == self.settings {
+            return;
+        }
+        self.settings = settings;
+        self.settings_changed = true;
+        self.queue(Job::Sweep);
+    }
+
+    /// Whether the settings changed since the last call, so they can be
+    /// saved
+    pub fn take_settings_changed(&mut self) -> bool {
+        std::mem::take(&mut self.settings_changed)
+    }
+
+    /// Bytes the cache takes up on disk, once it's been measured
+    pub fn usage(&self) -> Option<u64> {
+        self.usage
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.running.is_some() || self.queued.is_some()
+    }
+
+    /// Delete everything in the cache. Images that are already loaded stay
+    /// on screen, they're fetched again next time.
+    pub fn clear(&mut self) {
+        self.queue(Job::Clear);
+    }
+
+    fn queue(&mut self, job: Job) {
+        // a clear makes a pending sweep pointless
+        if !matches!(self.queued, Some(Job::Clear)) {
+            self.queued = Some(job);
+        }
+    }
+
+    /// Pick up finished work and start what's due. Called once a frame.
+    pub fn update(&mut self) {
+        if let Some(running) = self.running.take() {
+            match running.try_take() {
+                Ok(usage) => self.usage = Some(usage),
+                Err(running) => {
+                    self.running = Some(running);
+                    return;
+                }
+            }
+        }
+
+        if self.queued.is_none() && Instant::now() >= self.next_sweep {
+            self.queued = Some(Job::Sweep);
+        }
+
+        let Some(job) = self.queued.take() else {
+            return;
+        };
+        self.next_sweep = Instant::now() + SWEEP_INTERVAL;
+
+        let dirs = self.dirs.clone();
+        let max_bytes = self.settings.max_bytes();
+        self.running = Some(match job {
+            Job::Sweep => Promise::spawn_thread("cache_sweep", move || sweep(&dirs, max_bytes)),
+            Job::Clear => Promise::spawn_thread("cache_clear", move || clear(&dirs)),
+        });
+    }
+}
+
+struct CachedFile {
+    path: PathBuf,
+    size: u64,
+    last_used: SystemTime,
+}
+
+fn cached_files(dir: &Path, files: &mut Vec<CachedFile>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            cached_files(&entry.path(), files);
+        } else if metadata.is_file() {
+            files.push(CachedFile {
+                path: entry.path(),
+                size: metadata.len(),
+                last_used: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            });
+        }
+    }
+}
+
+/// Measure the cache and evict from it if it's over `max_bytes`. Returns
+/// the bytes left.
+fn sweep(dirs: &[PathBuf], max_bytes: u64) -> u64 {
+    let mut files = Vec::new();
+    for dir in dirs {
+        cached_files(dir, &mut files);
+    }
+
+    let total: u64 = files.iter().map(|f| f.size).sum();
+    if total <= max_bytes {
+        debug!("media cache is {total} bytes, under {max_bytes}");
+        return total;
+    }
+
+    let target = (max_bytes as f64 * EVICT_TO) as u64;
+    let (evict, remaining) = least_recently_used(&mut files, target);
+    info!(
+        "media cache is {total} bytes, over {max_bytes}. evicting {} files",
+        evict.len()
+    );
+
+    let mut left = remaining;
+    for file in evict {
+        if let Err(e) = std::fs::remove_file(&file.path) {
+            warn!("couldn't evict {}: {e}", file.path.display());
+            left += file.size;
+        }
+    }
+
+    left
+}
+
+/// Split off the oldest files until what's left fits in `target` bytes.
+/// Returns the files to remove and the size of the rest.
+fn least_recently_used(files: &mut Vec<CachedFile>, target: u64) -> (Vec<CachedFile>, u64) {
+    files.sort_by_key(|f| f.last_used);
+
+    let mut total: u64 = files.iter().map(|f| f.size).sum();
+    let mut count = 0;
+    for file in files.iter() {
+        if total <= target {
+            break;
+        }
+        total -= file.size;
+        count += 1;
+    }
+
+    (files.drain(..count).collect(), total)
+}
+
+fn clear(dirs: &[PathBuf]) -> u64 {
+    let mut files = Vec::new();
+    for dir in dirs {
+        cached_files(dir, &mut files);
+    }
+
+    let mut left = 0;
+    for file in &files {
+        if let Err(e) = std::fs::remove_file(&file.path) {
+            warn!("couldn't remove {}: {e}", file.path.display());
+            left += file.size;
+        }
+    }
+    info!("cleared {} files from the media cache", files.len());
+
+    left
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, size: u64, secs: u64) -> CachedFile {
+        CachedFile {
+            path: PathBuf::from(name),
+            size,
+            last_used: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+        }
+    }
+
+    #[test]
+    fn test_least_recently_used() {
+        let mut files = vec![
+            file("new", 10, 300),
+            file("old", 10, 100),
+            file("middle", 10, 200),
+        ];
+
+        let (evict, remaining) = least_recently_used(&mut files, 15);
+        let evicted: Vec<_> = evict.iter().map(|f| f.path.to_str().unwrap()).collect();
+        assert_eq!(evicted, ["old", "middle"]);
+        assert_eq!(remaining, 10);
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn test_least_recently_used_under_target() {
+        let mut files = vec![file("a", 10, 1), file("b", 10, 2)];
+        let (evict, remaining) = least_recently_used(&mut files, 100);
+        assert!(evict.is_empty());
+        assert_eq!(remaining, 20);
+    }
+}
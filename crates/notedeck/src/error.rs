@@ -40,6 +40,8 @@ pub enum ZapError {
     Serialization(String),
     #[error("nwc error")]
     NWC(String),
+    #[error("remote signer error")]
+    Signer(String),
 }
 
 impl From<String> for Error {
@@ -167,7 +167,7 @@ pub struct FilteredTags {
 
 impl FilteredTags {
     pub fn into_follow_filter(self) -> Vec<Filter> {
-        self.into_filter([1], default_limit())
+        self.into_filter([1, crate::poll::POLL_KIND as u64], default_limit())
     }
 
     // TODO: make this more general
@@ -0,0 +1,185 @@
+//! Threads each account takes part in. Replying in a thread follows it, so
+//! later replies there come in as notifications even when they don't
+//! mention us. Our replies are picked up as relays hand them back, which
+//! catches the ones sent from other clients as well, and a thread stays
+//! followed until it's unfollowed.
+
+use std::collections::BTreeMap;
+
+use enostr::{Pubkey, RelayPool};
+use nostrdb::{Filter, FilterBuilder, Ndb, NoteReply, Subscription, Transaction};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::time::unix_now;
+
+/// Threads followed per account. Past this the ones followed longest ago
+/// are dropped.
+const MAX_FOLLOWED_THREADS: usize = 200;
+
+/// Followed threads as they're saved, thread roots by account, in hex
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SavedFollowedThreads(BTreeMap<String, Vec<String>>);
+
+#[derive(Default)]
+pub struct FollowedThreads {
+    /// Thread roots by account, followed longest ago first
+    threads: BTreeMap<[u8; 32], Vec<[u8; 32]>>,
+    changed: bool,
+
+    /// Watching for the selected account's replies
+    account: Option<Pubkey>,
+    sub: Option<Subscription>,
+    remote_subid: Option<String>,
+}
+
+impl FollowedThreads {
+    pub fn new(saved: SavedFollowedThreads) -> Self {
+        let threads = saved
+            .0
+            .into_iter()
+            .filter_map(|(account, roots)| {
+                let account = from_hex(&account)?;
+                let roots = roots.iter().filter_map(|root| from_hex(root)).collect();
+                Some((account, roots))
+            })
+            .collect();
+
+        Self {
+            threads,
+            ..Default::default()
+        }
+    }
+
+    pub fn to_saved(&self) -> SavedFollowedThreads {
+        SavedFollowedThreads(
+            self.threads
+                .iter()
+                .filter(|(_, roots)| !roots.is_empty())
+                .map(|(account, roots)| {
+                    (
+                        hex::encode(account),
+                        roots.iter().map(hex::encode).collect(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Whether the threads were changed since the last call, and should be
+    /// saved
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// The roots of the threads `account` follows
+    pub fn threads(&self, account: &[u8; 32]) -> &[[u8; 32]] {
+        self.threads.get(account).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn is_following(&self, account: &[u8; 32], root: &[u8; 32]) -> bool {
+        self.threads(account).contains(root)
+    }
+
+    pub fn follow(&mut self, account: &[u8; 32], root: [u8; 32]) {
+        let roots = self.threads.entry(*account).or_default();
+        if roots.contains(&root) {
+            return;
+        }
+        debug!("following thread {}", hex::encode(root));
+        roots.push(root);
+        if roots.len() > MAX_FOLLOWED_THREADS {
+            roots.remove(0);
+        }
+        self.changed = true;
+    }
+
+    pub fn unfollow(&mut self, account: &[u8; 32], root: &[u8; 32]) {
+        if let Some(roots) = self.threads.get_mut(account) {
+            let before = roots.len();
+            roots.retain(|r| r != root);
+            self.changed |= roots.len() != before;
+        }
+    }
+
+    /// Follow the threads the selected account replies in. Called every
+    /// frame.
+    pub fn update(&mut self, ndb: &mut Ndb, pool: &mut RelayPool, selected: Option<&Pubkey>) {
+        if self.account.as_ref() != selected {
+            self.stop(ndb, pool);
+            self.account = selected.copied();
+            if let Some(account) = self.account {
+                self.start(ndb, pool, &account);
+            }
+        }
+
+        let (Some(sub), Some(account)) = (self.sub, self.account) else {
+            return;
+        };
+        let keys = ndb.poll_for_notes(sub, 10);
+        if keys.is_empty() {
+            return;
+        }
+        let Ok(txn) = Transaction::new(ndb) else {
+            return;
+        };
+
+        for key in keys {
+            let Ok(note) = ndb.get_note_by_key(&txn, key) else {
+                continue;
+            };
+            if let Some(root) = NoteReply::new(note.tags()).root() {
+                self.follow(account.bytes(), *root.id);
+            }
+        }
+    }
+
+    fn start(&mut self, ndb: &mut Ndb, pool: &mut RelayPool, account: &Pubkey) {
+        let filter = Filter::new()
+            .authors([account.bytes()])
+            .kinds([1])
+            .since(unix_now())
+            .build();
+
+        match ndb.subscribe(&[filter.clone()]) {
+            Ok(sub) => self.sub = Some(sub),
+            Err(e) => {
+                error!("followed threads subscription: {e}");
+                return;
+            }
+        }
+
+        let subid = format!("our-replies-{}", Uuid::new_v4());
+        pool.subscribe(subid.clone(), vec![filter]);
+        self.remote_subid = Some(subid);
+    }
+
+    fn stop(&mut self, ndb: &mut Ndb, pool: &mut RelayPool) {
+        if let Some(sub) = self.sub.take() {
+            if let Err(e) = ndb.unsubscribe(sub) {
+                error!("followed threads unsubscribe: {e}");
+            }
+        }
+        if let Some(subid) = self.remote_subid.take() {
+            pool.unsubscribe(subid);
+        }
+    }
+
+    /// Whether an EOSE was for our replies. We keep the subscription open
+    /// for the ones still to come.
+    pub fn handle_eose(&self, subid: &str) -> bool {
+        self.remote_subid.as_deref() == Some(subid)
+    }
+}
+
+/// Replies in the threads with these `roots`
+pub fn thread_replies_filter(roots: &[[u8; 32]]) -> FilterBuilder {
+    Filter::new().kinds([1]).events(roots.iter())
+}
+
+fn from_hex(id: &str) -> Option<[u8; 32]> {
+    let mut bytes = [0u8; 32];
+    hex::decode_to_slice(id, &mut bytes).ok()?;
+    Some(bytes)
+}
@@ -1,4 +1,4 @@
-use crate::{ui, NotedeckTextStyle};
+use crate::{style_overrides, ui, NotedeckTextStyle};
 
 pub enum NamedFontFamily {
     Medium,
@@ -50,7 +50,9 @@ pub fn mobile_font_size(text_style: &NotedeckTextStyle) -> f32 {
 }
 
 pub fn get_font_size(ctx: &egui::Context, text_style: &NotedeckTextStyle) -> f32 {
-    if ui::is_narrow(ctx) {
+    if let Some(size) = style_overrides::font_size(ctx, text_style) {
+        size
+    } else if ui::is_narrow(ctx) {
         mobile_font_size(text_style)
     } else {
         desktop_font_size(text_style)
@@ -1,4 +1,7 @@
+use crate::disk_quota::DiskQuota;
+use crate::link_preview::LinkPreviews;
 use crate::urls::{UrlCache, UrlMimes};
+use crate::video::Videos;
 use crate::Result;
 use egui::TextureHandle;
 use image::{Delay, Frame};
@@ -6,7 +9,8 @@ use poll_promise::Promise;
 
 use egui::ColorImage;
 
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs::{create_dir_all, File};
 use std::sync::mpsc::Receiver;
 use std::time::{Duration, Instant, SystemTime};
@@ -15,7 +19,7 @@ use hex::ToHex;
 use sha2::Digest;
 use std::path::PathBuf;
 use std::path::{self, Path};
-use tracing::warn;
+use tracing::{debug, warn};
 
 #[derive(Default)]
 pub struct TexturesCache {
@@ -286,6 +290,18 @@ impl MediaCache {
         Ok(())
     }
 
+    /// Mark a cached file as just used, so the disk quota evicts it last
+    pub fn touch(path: &Path) {
+        let touched = File::options()
+            .append(true)
+            .open(path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+
+        if let Err(e) = touched {
+            debug!("couldn't touch {}: {e}", path.display());
+        }
+    }
+
     pub fn key(url: &str) -> String {
         let k: String = sha2::Sha256::digest(url.as_bytes()).encode_hex();
         PathBuf::from(&k[0..2])
@@ -353,17 +369,35 @@ pub struct Images {
     pub static_imgs: MediaCache,
     pub gifs: MediaCache,
     pub urls: UrlMimes,
+    pub link_previews: LinkPreviews,
     pub gif_states: GifStateMap,
+    pub videos: Videos,
+
+    /// Keeps the files behind all of the above under a size limit
+    pub disk_quota: DiskQuota,
+
+    /// Blossom servers to look for blobs on when their url 404s
+    pub blossom_servers: Vec<String>,
 }
 
 impl Images {
     /// path to directory to place [`MediaCache`]s
     pub fn new(path: path::PathBuf) -> Self {
+        let disk_quota = DiskQuota::new(vec![
+            path.join(MediaCache::rel_dir(MediaCacheType::Image)),
+            path.join(MediaCache::rel_dir(MediaCacheType::Gif)),
+            path.join(Videos::rel_dir()),
+        ]);
+
         Self {
             static_imgs: MediaCache::new(&path, MediaCacheType::Image),
             gifs: MediaCache::new(&path, MediaCacheType::Gif),
             urls: UrlMimes::new(UrlCache::new(path.join(UrlCache::rel_dir()))),
+            link_previews: LinkPreviews::new(path.join(LinkPreviews::rel_dir())),
             gif_states: Default::default(),
+            videos: Videos::new(path.join(Videos::rel_dir())),
+            disk_quota,
+            blossom_servers: Vec::new(),
         }
     }
 
@@ -387,7 +421,99 @@ impl Images {
     }
 }
 
-pub type GifStateMap = HashMap<String, GifState>;
+/// How animated images behave
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnimationSettings {
+    /// How many animations may play at once. The rest hold their current
+    /// frame until one stops being drawn.
+    pub max_playing: usize,
+
+    /// Hold animations that are outside of their column's view or in a
+    /// minimized window
+    pub pause_hidden: bool,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self {
+            max_playing: 6,
+            pause_hidden: true,
+        }
+    }
+}
+
+/// Playback state of every animated image, and which of them get to play
+#[derive(Default)]
+pub struct GifStateMap {
+    states: HashMap<String, GifState>,
+    settings: AnimationSettings,
+    settings_changed: bool,
+
+    /// Hold every animation still, for reduced motion
+    motion_reduced: bool,
+
+    /// Animations that played this frame
+    playing: HashSet<String>,
+}
+
+impl GifStateMap {
+    pub fn get(&self, url: &str) -> Option<&GifState> {
+        self.states.get(url)
+    }
+
+    pub fn insert(&mut self, url: String, state: GifState) {
+        self.states.insert(url, state);
+    }
+
+    pub fn settings(&self) -> AnimationSettings {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: AnimationSettings) {
+        if settings != self.settings {
+            self.settings = settings;
+            self.settings_changed = true;
+        }
+    }
+
+    /// Whether the settings were changed since the last call, and should
+    /// be saved
+    pub fn take_settings_changed(&mut self) -> bool {
+        std::mem::take(&mut self.settings_changed)
+    }
+
+    pub fn set_motion_reduced(&mut self, reduced: bool) {
+        self.motion_reduced = reduced;
+    }
+
+    /// Forget who played last frame. Called before anything is drawn.
+    pub fn begin_frame(&mut self) {
+        self.playing.clear();
+    }
+
+    /// Whether the animation at `url` may move on to its next frame
+    pub fn may_play(&mut self, url: &str, visible: bool) -> bool {
+        if self.motion_reduced {
+            return false;
+        }
+
+        if !visible && self.settings.pause_hidden {
+            return false;
+        }
+
+        if self.playing.contains(url) {
+            return true;
+        }
+
+        if self.playing.len() >= self.settings.max_playing {
+            return false;
+        }
+
+        self.playing.insert(url.to_owned());
+        true
+    }
+}
 
 pub struct GifState {
     pub last_frame_rendered: Instant,
@@ -395,3 +521,40 @@ pub struct GifState {
     pub next_frame_time: Option<SystemTime>,
     pub last_frame_index: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_animation_cap() {
+        let mut gifs = GifStateMap::default();
+        gifs.set_settings(AnimationSettings {
+            max_playing: 2,
+            pause_hidden: true,
+        });
+
+        assert!(gifs.may_play("a", true));
+        assert!(gifs.may_play("b", true));
+        assert!(!gifs.may_play("c", true));
+        // the same animation drawn twice only counts once
+        assert!(gifs.may_play("a", true));
+
+        gifs.begin_frame();
+        assert!(gifs.may_play("c", true));
+    }
+
+    #[test]
+    fn test_animation_pause_hidden() {
+        let mut gifs = GifStateMap::default();
+        assert!(!gifs.may_play("a", false));
+
+        gifs.set_settings(AnimationSettings {
+            pause_hidden: false,
+            ..Default::default()
+        });
+        assert!(gifs.may_play("a", false));
+        assert!(gifs.take_settings_changed());
+        assert!(!gifs.take_settings_changed());
+    }
+}
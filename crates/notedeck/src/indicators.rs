@@ -0,0 +1,90 @@
+//! Colors for status indicators like zap and relay state. Widgets ask for
+//! them here instead of hardcoding them, so a color-blind safe palette
+//! can be swapped in. Indicators shouldn't rely on color alone either,
+//! each state also gets its own shape or label.
+
+use egui::{Color32, Context, Id};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndicatorPalette {
+    #[default]
+    Standard,
+
+    /// Safe for deuteranopia and protanopia, from the Okabe-Ito palette
+    RedGreen,
+
+    /// Safe for tritanopia
+    BlueYellow,
+}
+
+impl IndicatorPalette {
+    pub const ALL: [IndicatorPalette; 3] = [
+        IndicatorPalette::Standard,
+        IndicatorPalette::RedGreen,
+        IndicatorPalette::BlueYellow,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            IndicatorPalette::Standard => "Standard",
+            IndicatorPalette::RedGreen => "Red-green safe",
+            IndicatorPalette::BlueYellow => "Blue-yellow safe",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndicatorColors {
+    pub zap: Color32,
+
+    /// Connected, sent, done
+    pub ok: Color32,
+
+    /// Still going, or half done
+    pub pending: Color32,
+    pub error: Color32,
+}
+
+impl IndicatorColors {
+    fn new(palette: IndicatorPalette, visuals: &egui::Visuals) -> Self {
+        match palette {
+            IndicatorPalette::Standard => Self {
+                zap: Color32::from_rgb(0xFF, 0xB7, 0x57),
+                ok: visuals.selection.bg_fill,
+                pending: visuals.warn_fg_color,
+                error: visuals.error_fg_color,
+            },
+            IndicatorPalette::RedGreen => Self {
+                zap: Color32::from_rgb(0xE6, 0x9F, 0x00),
+                ok: Color32::from_rgb(0x00, 0x72, 0xB2),
+                pending: Color32::from_rgb(0xE6, 0x9F, 0x00),
+                error: Color32::from_rgb(0xD5, 0x5E, 0x00),
+            },
+            IndicatorPalette::BlueYellow => Self {
+                zap: Color32::from_rgb(0xCC, 0x79, 0xA7),
+                ok: Color32::from_rgb(0x00, 0x9E, 0x73),
+                pending: Color32::from_rgb(0xCC, 0x79, 0xA7),
+                error: Color32::from_rgb(0xD5, 0x5E, 0x00),
+            },
+        }
+    }
+}
+
+fn palette_id() -> Id {
+    Id::new("indicator_palette")
+}
+
+/// Use `palette` for indicators from now on
+pub fn set_palette(ctx: &Context, palette: IndicatorPalette) {
+    ctx.data_mut(|d| d.insert_temp(palette_id(), palette));
+}
+
+pub fn palette(ctx: &Context) -> IndicatorPalette {
+    ctx.data(|d| d.get_temp(palette_id())).unwrap_or_default()
+}
+
+/// The indicator colors for the current palette and theme
+pub fn colors(ctx: &Context) -> IndicatorColors {
+    IndicatorColors::new(palette(ctx), &ctx.style().visuals)
+}
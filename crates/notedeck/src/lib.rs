@@ -1,74 +1,143 @@
 pub mod abbrev;
+mod accessibility;
+mod account_colors;
 mod accounts;
 mod app;
+mod app_lock;
 mod args;
+pub mod blossom;
+mod contacts;
 mod context;
+mod counts;
 pub mod debouncer;
+pub mod deep_link;
+mod demo;
+mod disk_quota;
 mod error;
 pub mod filter;
+pub mod followed_threads;
 pub mod fonts;
 mod frame_history;
 mod imgcache;
+pub mod indicators;
 mod job_pool;
+pub mod link_preview;
+mod lists;
 mod muted;
 pub mod name;
+mod ncryptsec;
+pub mod nip19;
+mod nip46;
+mod nip55;
 pub mod note;
 mod notecache;
+pub mod notifications;
+pub mod os_notify;
+pub mod paid;
 mod persist;
 pub mod platform;
+pub mod poll;
+pub mod pow;
 pub mod profile;
+pub mod quiet_hours;
+pub mod read_state;
 pub mod relay_debug;
+pub mod relay_groups;
 pub mod relayspec;
 mod result;
+mod signer;
+pub mod sound;
 pub mod storage;
 mod style;
+pub mod style_overrides;
 pub mod theme;
 mod time;
 mod timecache;
 mod timed_serializer;
+pub mod tip_jar;
+pub mod toast;
 pub mod ui;
 mod unknowns;
+pub mod uri_inbox;
 mod urls;
 mod user_account;
+pub mod video;
 mod wallet;
+mod wot;
+pub mod zap_goal;
 mod zaps;
 
+pub use accessibility::{Accessibility, AccessibilitySettings, MotionPreference};
+pub use account_colors::AccountColors;
 pub use accounts::{AccountData, Accounts, AccountsAction, AddAccountAction, SwitchAccountAction};
 pub use app::{App, AppAction, Notedeck};
+pub use app_lock::AppLock;
 pub use args::Args;
+pub use blossom::BlossomServers;
+pub use contacts::{ContactAction, ContactConflict};
 pub use context::AppContext;
+pub use counts::{CountKind, Counts, NoteCounts};
+pub use disk_quota::{CacheQuotaSettings, DiskQuota};
 pub use error::{show_one_error_message, Error, FilterError, ZapError};
 pub use filter::{FilterState, FilterStates, UnifiedSubscription};
+pub use followed_threads::{FollowedThreads, SavedFollowedThreads};
 pub use fonts::NamedFontFamily;
 pub use imgcache::{
-    Animation, GifState, GifStateMap, ImageFrame, Images, LoadableTextureState, MediaCache,
-    MediaCacheType, TextureFrame, TextureState, TexturedImage, TexturesCache,
+    Animation, AnimationSettings, GifState, GifStateMap, ImageFrame, Images, LoadableTextureState,
+    MediaCache, MediaCacheType, TextureFrame, TextureState, TexturedImage, TexturesCache,
 };
 pub use job_pool::JobPool;
-pub use muted::{MuteFun, Muted};
+pub use link_preview::{LinkPreview, LinkPreviewSettings, LinkPreviews};
+pub use lists::{pin_list_filter, ListError, PIN_LIST_KIND};
+pub use muted::{MuteAction, MuteFun, MuteItem, Muted};
 pub use name::NostrName;
+pub use ncryptsec::{
+    decrypt_in_background, decrypt_ncryptsec, encrypt_in_background, encrypt_secret_key,
+    is_ncryptsec, NcryptsecError, PassphraseStrength, MIN_PASSPHRASE_LEN, NCRYPTSEC_LOG_N,
+};
+pub use nip46::{
+    BunkerUri, RemoteSigner, SignPurpose, SignedEvent, SignerError, SignerStatus, UnsignedEvent,
+    NOSTRCONNECT_RELAYS,
+};
+pub use nip55::AmberSigner;
 pub use note::{
     BroadcastContext, ContextSelection, NoteAction, NoteContext, NoteContextSelection, NoteRef,
-    RootIdError, RootNoteId, RootNoteIdBuf, ZapAction,
+    RootIdError, RootNoteId, RootNoteIdBuf, ZapAction, DEFAULT_TRUNCATE_LINES,
 };
 pub use notecache::{CachedNote, NoteCache};
+pub use notifications::{
+    AuthorGroup, Notification, NotificationFilterSettings, NotificationKind, NotificationWatcher,
+};
+pub use os_notify::{OsNotificationSettings, OsNotifications};
+pub use paid::PaidContent;
 pub use persist::*;
+pub use poll::Polls;
 pub use profile::get_profile_url;
+pub use quiet_hours::{QuietHours, QuietHoursSettings};
+pub use read_state::NotificationReadState;
 pub use relay_debug::RelayDebugView;
+pub use relay_groups::{RelayGroup, RelayGroups};
 pub use relayspec::RelaySpec;
 pub use result::Result;
+pub use signer::AccountSigner;
+pub use sound::{Sound, SoundSettings, Sounds};
 pub use storage::{AccountStorage, DataPath, DataPathType, Directory};
 pub use style::NotedeckTextStyle;
+pub use style_overrides::StyleOverrides;
 pub use theme::ColorTheme;
 pub use time::time_ago_since;
 pub use timecache::TimeCached;
-pub use unknowns::{get_unknown_note_ids, NoteRefsUnkIdAction, SingleUnkIdAction, UnknownIds};
+pub use unknowns::{
+    get_unknown_note_ids, NoteRefsUnkIdAction, SingleUnkIdAction, UnknownIds, UnknownIdsBatch,
+};
 pub use urls::{supported_mime_hosted_at_url, SupportedMimeType, UrlMimes};
 pub use user_account::UserAccount;
 pub use wallet::{
     get_current_wallet, get_wallet_for_mut, GlobalWallet, Wallet, WalletError, WalletType,
     WalletUIState, ZapWallet,
 };
+pub use wot::{WebOfTrust, WotMode, WotSettings, WotVerdict, MAX_WOT_HOPS};
+pub use zap_goal::ZapGoals;
 pub use zaps::{
     get_current_default_msats, AnyZapState, DefaultZapError, DefaultZapMsats, NoteZapTarget,
     NoteZapTargetOwned, PendingDefaultZapState, ZapTarget, ZapTargetOwned, ZappingError,
@@ -0,0 +1,459 @@
+//! Previews for links in notes, made from the OpenGraph and Twitter card
+//! `<meta>` tags of the page they point to. Fetching a page tells its
+//! site our IP address, so it can be turned off.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use poll_promise::Promise;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::Error;
+
+const FILE_NAME: &str = "link_previews.bin";
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Pages change, so previews are fetched again after this long
+const PREVIEW_TTL: u64 = 60 * 60 * 24 * 7;
+
+/// Meta tags live in the head, there's no need to look further than this
+const MAX_PAGE_BYTES: usize = 512 * 1024;
+
+/// Pages fetched at once, the rest wait their turn
+const MAX_IN_FLIGHT: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkPreviewSettings {
+    pub enabled: bool,
+}
+
+impl Default for LinkPreviewSettings {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkPreview {
+    pub title: Option<String>,
+    pub description: Option<String>,
+
+    /// Absolute url of the page's thumbnail
+    pub image: Option<String>,
+    pub site_name: Option<String>,
+}
+
+impl LinkPreview {
+    /// Parse the preview out of a page's html. None if there's nothing
+    /// worth showing.
+    pub fn from_html(html: &str, page_url: &Url) -> Option<Self> {
+        let mut og = LinkPreview::default();
+        let mut twitter = LinkPreview::default();
+
+        for (key, content) in meta_tags(html) {
+            let content = Some(content);
+            match key.as_str() {
+                "og:title" => og.title = og.title.or(content),
+                "og:description" => og.description = og.description.or(content),
+                "og:image" | "og:image:url" => og.image = og.image.or(content),
+                "og:site_name" => og.site_name = og.site_name.or(content),
+                "twitter:title" => twitter.title = twitter.title.or(content),
+                "twitter:description" => twitter.description = twitter.description.or(content),
+                "twitter:image" | "twitter:image:src" => twitter.image = twitter.image.or(content),
+                "description" => twitter.description = twitter.description.or(content),
+                _ => {}
+            }
+        }
+
+        let image = og
+            .image
+            .or(twitter.image)
+            .and_then(|image| page_url.join(&image).ok())
+            .filter(|image| matches!(image.scheme(), "http" | "https"))
+            .map(String::from);
+
+        let preview = LinkPreview {
+            title: og.title.or(twitter.title).or_else(|| title_tag(html)),
+            description: og.description.or(twitter.description),
+            image,
+            site_name: og.site_name,
+        };
+
+        preview.title.is_some().then_some(preview)
+    }
+}
+
+/// `(property or name, content)` of each `<meta>` tag, keys lowercased
+fn meta_tags(html: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    let lower = html.to_ascii_lowercase();
+    let mut starts = Vec::new();
+    let mut from = 0;
+    while let Some(at) = lower[from..].find("<meta") {
+        starts.push(from + at);
+        from += at + 5;
+    }
+
+    starts.into_iter().filter_map(move |start| {
+        let end = html[start..].find('>')? + start;
+        let attrs = attributes(&html[start + 5..end]);
+
+        let key = attrs
+            .iter()
+            .find(|(name, _)| name == "property" || name == "name")?
+            .1
+            .to_ascii_lowercase();
+        let content = attrs.iter().find(|(name, _)| name == "content")?.1.trim();
+        (!content.is_empty()).then(|| (key, decode_entities(content)))
+    })
+}
+
+/// The attributes of a tag, names lowercased. Values can be double
+/// quoted, single quoted or bare.
+fn attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut rest = tag.trim_start();
+
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].trim_end_matches('/').to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        // an attribute without a value
+        let Some(after_eq) = rest.strip_prefix('=') else {
+            continue;
+        };
+        rest = after_eq.trim_start();
+
+        let (value, remaining) = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let inner = &rest[1..];
+                let close = inner.find(quote).unwrap_or(inner.len());
+                (&inner[..close], inner.get(close + 1..).unwrap_or(""))
+            }
+            _ => {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                (rest[..end].trim_end_matches('/'), &rest[end..])
+            }
+        };
+
+        attrs.push((name, value.to_owned()));
+        rest = remaining.trim_start();
+    }
+
+    attrs
+}
+
+fn title_tag(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = lower[open..].find('>')? + open + 1;
+    let end = lower[start..].find("</title")? + start;
+    let title = html[start..end].trim();
+    (!title.is_empty()).then(|| decode_entities(title))
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_owned();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let decoded = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => ' ',
+                _ => {
+                    let code = if let Some(hex) = entity.strip_prefix("#x") {
+                        u32::from_str_radix(hex, 16).ok()
+                    } else if let Some(hex) = entity.strip_prefix("#X") {
+                        u32::from_str_radix(hex, 16).ok()
+                    } else {
+                        entity.strip_prefix('#')?.parse().ok()
+                    };
+                    char::from_u32(code?)?
+                }
+            };
+            Some((c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedPreview {
+    /// None when the page had nothing to preview, so we don't keep asking
+    preview: Option<LinkPreview>,
+    fetched_at: u64,
+}
+
+impl CachedPreview {
+    fn is_stale(&self, now: u64) -> bool {
+        now.saturating_sub(self.fetched_at) > PREVIEW_TTL
+    }
+}
+
+type Previews = HashMap<String, CachedPreview>;
+
+/// Fetched link previews, kept on disk between runs
+pub struct LinkPreviews {
+    settings: LinkPreviewSettings,
+    settings_changed: bool,
+
+    previews: Previews,
+    in_flight: HashMap<String, Promise<Option<LinkPreview>>>,
+
+    path: PathBuf,
+    from_disk_promise: Option<Promise<Option<Previews>>>,
+    dirty: bool,
+    last_saved: SystemTime,
+}
+
+impl LinkPreviews {
+    pub fn rel_dir() -> &'static str {
+        FILE_NAME
+    }
+
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            settings: LinkPreviewSettings::default(),
+            settings_changed: false,
+            previews: HashMap::new(),
+            in_flight: HashMap::new(),
+            from_disk_promise: Some(read_from_disk(path.clone())),
+            path,
+            dirty: false,
+            last_saved: SystemTime::now(),
+        }
+    }
+
+    pub fn settings(&self) -> LinkPreviewSettings {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: LinkPreviewSettings) {
+        if settings == self.settings {
+            return;
+        }
+        self.settings = settings;
+        self.settings_changed = true;
+    }
+
+    /// Whether the settings changed since the last call, so they can be
+    /// saved
+    pub fn take_settings_changed(&mut self) -> bool {
+        std::mem::take(&mut self.settings_changed)
+    }
+
+    /// The preview for `url`, fetching it if we don't have it yet. None
+    /// while it's loading, if the page has none, or if previews are off.
+    pub fn get(&mut self, ctx: &egui::Context, url: &str) -> Option<&LinkPreview> {
+        if !self.settings.enabled {
+            return None;
+        }
+
+        let now = crate::time::unix_now();
+        if let Some(mut promise) = self.in_flight.remove(url) {
+            match promise.ready_mut() {
+                Some(preview) => {
+                    self.previews.insert(
+                        url.to_owned(),
+                        CachedPreview {
+                            preview: preview.take(),
+                            fetched_at: now,
+                        },
+                    );
+                    self.dirty = true;
+                }
+                None => {
+                    self.in_flight.insert(url.to_owned(), promise);
+                    return None;
+                }
+            }
+        }
+
+        let fresh = self
+            .previews
+            .get(url)
+            .is_some_and(|cached| !cached.is_stale(now));
+        if !fresh && self.in_flight.len() < MAX_IN_FLIGHT {
+            if let Ok(parsed) = Url::parse(url) {
+                if matches!(parsed.scheme(), "http" | "https") {
+                    self.in_flight
+                        .insert(url.to_owned(), fetch_preview(ctx, parsed));
+                }
+            }
+        }
+
+        self.previews.get(url)?.preview.as_ref()
+    }
+
+    /// Pick up the previews from the last run and save new ones now and
+    /// then. Called once a frame.
+    pub fn handle_io(&mut self) {
+        if let Some(promise) = &mut self.from_disk_promise {
+            if let Some(from_disk) = promise.ready_mut() {
+                if let Some(from_disk) = from_disk.take() {
+                    for (url, cached) in from_disk {
+                        self.previews.entry(url).or_insert(cached);
+                    }
+                }
+                self.from_disk_promise = None;
+            }
+        }
+
+        let due = SystemTime::now()
+            .duration_since(self.last_saved)
+            .is_ok_and(|elapsed| elapsed >= SAVE_INTERVAL);
+        if self.dirty && due {
+            let now = crate::time::unix_now();
+            self.previews.retain(|_, cached| !cached.is_stale(now));
+            save_to_disk(self.path.clone(), self.previews.clone());
+            self.dirty = false;
+            self.last_saved = SystemTime::now();
+        }
+    }
+}
+
+fn fetch_preview(ctx: &egui::Context, url: Url) -> Promise<Option<LinkPreview>> {
+    let (sender, promise) = Promise::new();
+    let ctx = ctx.clone();
+
+    let request = ehttp::Request {
+        headers: ehttp::Headers::new(&[("Accept", "text/html,application/xhtml+xml")]),
+        ..ehttp::Request::get(url.as_str())
+    };
+
+    ehttp::fetch(request, move |response| {
+        let preview = match response {
+            Ok(resp) if resp.ok => {
+                let is_html = resp
+                    .content_type()
+                    .is_some_and(|content_type| content_type.contains("html"));
+                if is_html {
+                    let bytes = &resp.bytes[..resp.bytes.len().min(MAX_PAGE_BYTES)];
+                    LinkPreview::from_html(&String::from_utf8_lossy(bytes), &url)
+                } else {
+                    None
+                }
+            }
+            Ok(resp) => {
+                tracing::debug!("no link preview for {url}: {}", resp.status);
+                None
+            }
+            Err(err) => {
+                tracing::debug!("no link preview for {url}: {err}");
+                None
+            }
+        };
+
+        sender.send(preview);
+        ctx.request_repaint();
+    });
+
+    promise
+}
+
+fn read_from_disk(path: PathBuf) -> Promise<Option<Previews>> {
+    let (sender, promise) = Promise::new();
+
+    std::thread::spawn(move || {
+        if !path.exists() {
+            sender.send(None);
+            return;
+        }
+
+        let result: Result<Previews, Error> =
+            std::fs::read(&path).map_err(Error::from).and_then(|bytes| {
+                bincode::deserialize(&bytes).map_err(|e| Error::Generic(e.to_string()))
+            });
+
+        match result {
+            Ok(previews) => sender.send(Some(previews)),
+            Err(e) => {
+                tracing::error!("problem reading link previews: {e}");
+                sender.send(None)
+            }
+        }
+    });
+
+    promise
+}
+
+fn save_to_disk(path: PathBuf, previews: Previews) {
+    std::thread::spawn(move || {
+        let result = bincode::serialize(&previews)
+            .map_err(|e| Error::Generic(e.to_string()))
+            .and_then(|encoded| std::fs::write(&path, encoded).map_err(Error::from));
+
+        match result {
+            Ok(_) => tracing::debug!("saved {} link previews", previews.len()),
+            Err(e) => tracing::error!("failed to save link previews: {e}"),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_html() {
+        let html = r#"<html><head>
+            <title>Fallback</title>
+            <meta property="og:title" content="Tom &amp; Jerry&#39;s">
+            <META name='twitter:description' content='Cat and mouse'/>
+            <meta content="/thumb.png" property=og:image>
+            <meta property="og:site_name" content="Example">
+            </head></html>"#;
+        let page = Url::parse("https://example.com/shows/1").unwrap();
+
+        let preview = LinkPreview::from_html(html, &page).unwrap();
+        assert_eq!(preview.title.as_deref(), Some("Tom & Jerry's"));
+        assert_eq!(preview.description.as_deref(), Some("Cat and mouse"));
+        assert_eq!(
+            preview.image.as_deref(),
+            Some("https://example.com/thumb.png")
+        );
+        assert_eq!(preview.site_name.as_deref(), Some("Example"));
+    }
+
+    #[test]
+    fn test_from_html_fallbacks() {
+        let page = Url::parse("https://example.com").unwrap();
+
+        let preview = LinkPreview::from_html("<title> Just a title </title>", &page).unwrap();
+        assert_eq!(preview.title.as_deref(), Some("Just a title"));
+        assert_eq!(preview.image, None);
+
+        assert!(LinkPreview::from_html("<p>nothing here</p>", &page).is_none());
+    }
+}
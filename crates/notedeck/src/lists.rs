@@ -0,0 +1,76 @@
+use enostr::Pubkey;
+use nostrdb::{Filter, Note, NoteBuildOptions, NoteBuilder};
+
+/// NIP-51 pinned notes, shown on top of a profile
+pub const PIN_LIST_KIND: u32 = 10001;
+
+/// Why we couldn't edit one of an account's replaceable lists (contacts,
+/// mutes)
+#[derive(Debug, thiserror::Error)]
+pub enum ListError {
+    /// Relays haven't sent us their copy of the list yet. Publishing now
+    /// could replace a newer one than we have.
+    #[error("still fetching this list from relays")]
+    NotSynced,
+
+    #[error("no way to sign for this account")]
+    CantSign,
+
+    #[error("couldn't build list: {0}")]
+    Build(String),
+}
+
+/// `account`'s pinned notes list
+pub fn pin_list_filter(account: &Pubkey) -> Filter {
+    Filter::new()
+        .authors([account.bytes()])
+        .kinds([PIN_LIST_KIND as u64])
+        .limit(1)
+        .build()
+}
+
+/// A note's tags as strings, ids hex encoded
+pub(crate) fn note_tags(note: &Note) -> Vec<Vec<String>> {
+    let mut tags = Vec::new();
+    for tag in note.tags() {
+        let values = (0..tag.count())
+            .filter_map(|i| {
+                let value = tag.get(i)?;
+                value
+                    .variant()
+                    .str()
+                    .map(str::to_owned)
+                    .or_else(|| value.variant().id().map(hex::encode))
+            })
+            .collect();
+        tags.push(values);
+    }
+    tags
+}
+
+/// Build and sign a replacement list as json
+pub(crate) fn sign_list(
+    kind: u32,
+    content: &str,
+    tags: &[Vec<String>],
+    seckey: &[u8; 32],
+    created_at: u64,
+) -> Result<String, ListError> {
+    let mut builder = NoteBuilder::new()
+        .kind(kind)
+        .content(content)
+        .created_at(created_at);
+    for tag in tags {
+        builder = builder.start_tag();
+        for value in tag {
+            builder = builder.tag_str(value);
+        }
+    }
+
+    builder
+        .options(NoteBuildOptions::default().created_at(false).sign(seckey))
+        .build()
+        .ok_or_else(|| ListError::Build("note build failed".to_owned()))?
+        .json()
+        .map_err(|e| ListError::Build(e.to_string()))
+}
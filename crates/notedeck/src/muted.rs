@@ -1,3 +1,4 @@
+use enostr::{NoteId, Pubkey};
 use nostrdb::Note;
 use std::collections::BTreeSet;
 
@@ -53,19 +54,17 @@ impl Muted {
             */
             return true;
         }
-        // FIXME - Implement hashtag muting here
-
-        // TODO - let's not add this for now, we will likely need to
-        // have an optimized data structure in nostrdb to properly
-        // mute words. this mutes substrings which is not ideal.
-        //
-        // let content = note.content().to_lowercase();
-        // for word in &self.words {
-        //     if content.contains(&word.to_lowercase()) {
-        //         debug!("{}: MUTED word: {}", hex::encode(note.id()), word);
-        //         return Some(format!("muted word {}", word));
-        //     }
-        // }
+
+        if !self.hashtags.is_empty() && self.has_muted_hashtag(note) {
+            return true;
+        }
+
+        if !self.words.is_empty() {
+            let content = note.content().to_lowercase();
+            if self.words.iter().any(|word| contains_word(&content, word)) {
+                return true;
+            }
+        }
 
         if self.threads.contains(thread) {
             /*
@@ -80,4 +79,157 @@ impl Muted {
 
         false
     }
+
+    fn has_muted_hashtag(&self, note: &Note) -> bool {
+        for tag in note.tags() {
+            if tag.get(0).and_then(|t| t.variant().str()) != Some("t") {
+                continue;
+            }
+
+            let Some(hashtag) = tag.get(1).and_then(|t| t.variant().str()) else {
+                continue;
+            };
+
+            if self.hashtags.contains(&hashtag.to_lowercase()) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn contains(&self, item: &MuteItem) -> bool {
+        match item {
+            MuteItem::Pubkey(pk) => self.pubkeys.contains(pk.bytes()),
+            MuteItem::Hashtag(hashtag) => self.hashtags.contains(hashtag),
+            MuteItem::Word(word) => self.words.contains(word),
+            MuteItem::Thread(id) => self.threads.contains(id.bytes()),
+        }
+    }
+}
+
+/// Something that can go on a NIP-51 mute list (kind 10000)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MuteItem {
+    Pubkey(Pubkey),
+    Hashtag(String),
+    Word(String),
+
+    /// The root note of the thread
+    Thread(NoteId),
+}
+
+impl MuteItem {
+    /// Words and hashtags are matched case insensitively, so they're
+    /// stored lowercase. Returns None for an empty word or hashtag.
+    pub fn hashtag(hashtag: &str) -> Option<Self> {
+        let hashtag = hashtag.trim().trim_start_matches('#').to_lowercase();
+        (!hashtag.is_empty()).then_some(MuteItem::Hashtag(hashtag))
+    }
+
+    pub fn word(word: &str) -> Option<Self> {
+        let word = word.trim().to_lowercase();
+        (!word.is_empty()).then_some(MuteItem::Word(word))
+    }
+
+    fn tag(&self) -> Vec<String> {
+        let (name, value) = match self {
+            MuteItem::Pubkey(pk) => ("p", pk.hex()),
+            MuteItem::Hashtag(hashtag) => ("t", hashtag.clone()),
+            MuteItem::Word(word) => ("word", word.clone()),
+            MuteItem::Thread(id) => ("e", id.hex()),
+        };
+        vec![name.to_owned(), value]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MuteAction {
+    Mute(MuteItem),
+    Unmute(MuteItem),
+}
+
+/// The mute list tags after an action, None if nothing changed. Tags
+/// we don't understand are left alone.
+pub(crate) fn edit_mute_tags(
+    mut tags: Vec<Vec<String>>,
+    action: &MuteAction,
+) -> Option<Vec<Vec<String>>> {
+    let (item, mute) = match action {
+        MuteAction::Mute(item) => (item, true),
+        MuteAction::Unmute(item) => (item, false),
+    };
+    let wanted = item.tag();
+    let matches = |tag: &Vec<String>| {
+        tag.len() >= 2 && tag[0] == wanted[0] && tag[1].eq_ignore_ascii_case(&wanted[1])
+    };
+
+    if mute {
+        if tags.iter().any(matches) {
+            return None;
+        }
+        tags.push(wanted);
+    } else {
+        let before = tags.len();
+        tags.retain(|tag| !matches(tag));
+        if tags.len() == before {
+            return None;
+        }
+    }
+
+    Some(tags)
+}
+
+/// Whole word match, so muting "art" doesn't hide notes about parties.
+/// Both sides are expected to be lowercase already.
+fn contains_word(content: &str, word: &str) -> bool {
+    let is_boundary = |c: Option<char>| !matches!(c, Some(c) if c.is_alphanumeric());
+
+    content.match_indices(word).any(|(start, _)| {
+        let before = content[..start].chars().next_back();
+        let after = content[start + word.len()..].chars().next();
+        is_boundary(before) && is_boundary(after)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str, value: &str) -> Vec<String> {
+        vec![name.to_owned(), value.to_owned()]
+    }
+
+    #[test]
+    fn test_contains_word_on_boundaries() {
+        assert!(contains_word("i love art", "art"));
+        assert!(contains_word("art, mostly", "art"));
+        assert!(contains_word("new #art drop", "art"));
+        assert!(!contains_word("party time", "art"));
+        assert!(!contains_word("artsy", "art"));
+        assert!(contains_word("no spoilers please", "no spoilers"));
+    }
+
+    #[test]
+    fn test_edit_mute_tags() {
+        let tags = vec![tag("p", "abcd"), tag("word", "spoilers")];
+
+        let muted = MuteItem::hashtag("#Politics").unwrap();
+        assert_eq!(muted, MuteItem::Hashtag("politics".to_owned()));
+
+        let added = edit_mute_tags(tags.clone(), &MuteAction::Mute(muted.clone())).unwrap();
+        assert_eq!(added.last(), Some(&tag("t", "politics")));
+        assert!(edit_mute_tags(added.clone(), &MuteAction::Mute(muted.clone())).is_none());
+
+        let removed = edit_mute_tags(added, &MuteAction::Unmute(muted)).unwrap();
+        assert_eq!(removed, tags);
+
+        let word = MuteItem::word("  Spoilers ").unwrap();
+        assert_eq!(
+            edit_mute_tags(tags, &MuteAction::Unmute(word)).unwrap(),
+            vec![tag("p", "abcd")]
+        );
+
+        assert!(MuteItem::word("   ").is_none());
+    }
 }
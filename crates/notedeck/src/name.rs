@@ -74,3 +74,22 @@ pub fn get_display_name<'a>(record: Option<&ProfileRecord<'a>>) -> NostrName<'a>
         nip05,
     }
 }
+
+/// A few of `names` out of `total` people, for lines like "A, B and 3
+/// others reposted"
+pub fn summarize_names(names: &[&str], total: usize) -> String {
+    match names {
+        [] => format!("{total} people"),
+        [one] if total <= 1 => (*one).to_owned(),
+        [a, b] if total == 2 => format!("{a} and {b}"),
+        [a, b, c, ..] if total == 3 => format!("{a}, {b} and {c}"),
+        [a] => match total.saturating_sub(1) {
+            1 => format!("{a} and 1 other"),
+            rest => format!("{a} and {rest} others"),
+        },
+        [a, b, ..] => match total.saturating_sub(2) {
+            1 => format!("{a}, {b} and 1 other"),
+            rest => format!("{a}, {b} and {rest} others"),
+        },
+    }
+}
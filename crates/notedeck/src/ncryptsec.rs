@@ -0,0 +1,202 @@
+use enostr::SecretKey;
+use nostr::nips::nip19::{FromBech32, ToBech32};
+use nostr::nips::nip49::{EncryptedSecretKey, KeySecurity};
+use poll_promise::Promise;
+
+/// The scrypt cost we encrypt with. NIP-49 puts 2^16 at about 64MiB and
+/// 100ms on a fast machine, slower phones take closer to a second.
+pub const NCRYPTSEC_LOG_N: u8 = 16;
+
+const NCRYPTSEC_PREFIX: &str = "ncryptsec1";
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum NcryptsecError {
+    #[error("not a valid ncryptsec")]
+    Invalid,
+
+    #[error("wrong passphrase")]
+    WrongPassphrase,
+
+    #[error("couldn't encrypt key: {0}")]
+    Encrypt(String),
+}
+
+pub fn is_ncryptsec(s: &str) -> bool {
+    s.trim().starts_with(NCRYPTSEC_PREFIX)
+}
+
+/// Encrypt a secret key with a passphrase into an `ncryptsec1...`
+/// string (NIP-49). Slow on purpose, see [`encrypt_in_background`].
+pub fn encrypt_secret_key(secret: &SecretKey, passphrase: &str) -> Result<String, NcryptsecError> {
+    // we don't know how the key was handled before it got to us
+    EncryptedSecretKey::new(secret, passphrase, NCRYPTSEC_LOG_N, KeySecurity::Unknown)
+        .map_err(|e| NcryptsecError::Encrypt(e.to_string()))?
+        .to_bech32()
+        .map_err(|e| NcryptsecError::Encrypt(e.to_string()))
+}
+
+pub fn decrypt_ncryptsec(ncryptsec: &str, passphrase: &str) -> Result<SecretKey, NcryptsecError> {
+    let encrypted =
+        EncryptedSecretKey::from_bech32(ncryptsec.trim()).map_err(|_| NcryptsecError::Invalid)?;
+
+    // decryption only fails if the passphrase (and so the derived key)
+    // doesn't match
+    encrypted
+        .to_secret_key(passphrase)
+        .map_err(|_| NcryptsecError::WrongPassphrase)
+}
+
+/// scrypt takes long enough to drop frames, so do it off the ui thread
+pub fn encrypt_in_background(
+    secret: SecretKey,
+    passphrase: String,
+) -> Promise<Result<String, NcryptsecError>> {
+    Promise::spawn_thread("ncryptsec", move || {
+        encrypt_secret_key(&secret, &passphrase)
+    })
+}
+
+pub fn decrypt_in_background(
+    ncryptsec: String,
+    passphrase: String,
+) -> Promise<Result<SecretKey, NcryptsecError>> {
+    Promise::spawn_thread("ncryptsec", move || {
+        decrypt_ncryptsec(&ncryptsec, &passphrase)
+    })
+}
+
+/// A rough guess at how hard a passphrase is to brute force
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PassphraseStrength {
+    TooShort,
+    Weak,
+    Fair,
+    Strong,
+    VeryStrong,
+}
+
+/// Shorter than this and we won't encrypt with it at all
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+impl PassphraseStrength {
+    pub fn of(passphrase: &str) -> Self {
+        let len = passphrase.chars().count();
+        if len < MIN_PASSPHRASE_LEN {
+            return PassphraseStrength::TooShort;
+        }
+
+        let bits = entropy_bits(passphrase);
+        if bits < 40.0 {
+            PassphraseStrength::Weak
+        } else if bits < 60.0 {
+            PassphraseStrength::Fair
+        } else if bits < 80.0 {
+            PassphraseStrength::Strong
+        } else {
+            PassphraseStrength::VeryStrong
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PassphraseStrength::TooShort => "Too short",
+            PassphraseStrength::Weak => "Weak",
+            PassphraseStrength::Fair => "Fair",
+            PassphraseStrength::Strong => "Strong",
+            PassphraseStrength::VeryStrong => "Very strong",
+        }
+    }
+
+    /// How full the strength meter should be, 0 to 1
+    pub fn fraction(&self) -> f32 {
+        match self {
+            PassphraseStrength::TooShort => 0.1,
+            PassphraseStrength::Weak => 0.3,
+            PassphraseStrength::Fair => 0.55,
+            PassphraseStrength::Strong => 0.8,
+            PassphraseStrength::VeryStrong => 1.0,
+        }
+    }
+
+    pub fn is_acceptable(&self) -> bool {
+        *self != PassphraseStrength::TooShort
+    }
+}
+
+/// Character pool size times length, with runs of the same character
+/// counted once so "aaaaaaaaaaaa" doesn't pass for strong
+fn entropy_bits(passphrase: &str) -> f64 {
+    let (mut lower, mut upper, mut digit, mut symbol, mut other) =
+        (false, false, false, false, false);
+    let mut effective_len = 0;
+    let mut prev = None;
+
+    for c in passphrase.chars() {
+        match c {
+            'a'..='z' => lower = true,
+            'A'..='Z' => upper = true,
+            '0'..='9' => digit = true,
+            c if c.is_ascii() => symbol = true,
+            _ => other = true,
+        }
+
+        if prev != Some(c) {
+            effective_len += 1;
+        }
+        prev = Some(c);
+    }
+
+    let pool = [
+        (lower, 26),
+        (upper, 26),
+        (digit, 10),
+        (symbol, 33),
+        (other, 100),
+    ]
+    .iter()
+    .filter(|(present, _)| *present)
+    .map(|(_, size)| size)
+    .sum::<u32>();
+
+    effective_len as f64 * (pool.max(1) as f64).log2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enostr::FullKeypair;
+
+    #[test]
+    fn test_ncryptsec_roundtrip() {
+        let kp = FullKeypair::generate();
+        let ncryptsec = encrypt_secret_key(&kp.secret_key, "correct horse").unwrap();
+        assert!(is_ncryptsec(&ncryptsec));
+
+        let decrypted = decrypt_ncryptsec(&ncryptsec, "correct horse").unwrap();
+        assert_eq!(decrypted.secret_bytes(), kp.secret_key.secret_bytes());
+
+        assert_eq!(
+            decrypt_ncryptsec(&ncryptsec, "battery staple"),
+            Err(NcryptsecError::WrongPassphrase)
+        );
+        assert_eq!(
+            decrypt_ncryptsec("ncryptsec1nope", "correct horse"),
+            Err(NcryptsecError::Invalid)
+        );
+    }
+
+    #[test]
+    fn test_passphrase_strength() {
+        assert_eq!(PassphraseStrength::of("abc"), PassphraseStrength::TooShort);
+        assert_eq!(
+            PassphraseStrength::of("aaaaaaaaaaaaaaaa"),
+            PassphraseStrength::Weak
+        );
+        assert_eq!(PassphraseStrength::of("password"), PassphraseStrength::Weak);
+        assert_eq!(
+            PassphraseStrength::of("sunshine1"),
+            PassphraseStrength::Fair
+        );
+        assert!(PassphraseStrength::of("Tr0ub4dor&3-correct-horse") >= PassphraseStrength::Strong);
+    }
+}
@@ -0,0 +1,338 @@
+//! Encoding notes and profiles as NIP-19 entities. The shareable ones
+//! (nprofile, nevent, naddr) can carry relay hints so whoever opens them
+//! knows where to look.
+
+use nostr::nips::nip19::{FromBech32, Nip19};
+use nostrdb::{Note, Transaction};
+
+use crate::deep_link::{self, DeepLink};
+
+static HRP_NPROFILE: bech32::Hrp = bech32::Hrp::parse_unchecked("nprofile");
+static HRP_NEVENT: bech32::Hrp = bech32::Hrp::parse_unchecked("nevent");
+static HRP_NADDR: bech32::Hrp = bech32::Hrp::parse_unchecked("naddr");
+
+const TLV_SPECIAL: u8 = 0;
+const TLV_RELAY: u8 = 1;
+const TLV_AUTHOR: u8 = 2;
+const TLV_KIND: u8 = 3;
+
+/// Enough for someone to find the note without making the link huge
+pub const MAX_RELAY_HINTS: usize = 3;
+
+/// The ways a note or its author can be copied
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Nip19Kind {
+    Npub,
+    Nprofile,
+    Note,
+    Nevent,
+    Naddr,
+}
+
+impl Nip19Kind {
+    /// Whether this encoding has room for relay hints
+    pub fn has_relays(&self) -> bool {
+        matches!(
+            self,
+            Nip19Kind::Nprofile | Nip19Kind::Nevent | Nip19Kind::Naddr
+        )
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Nip19Kind::Npub => "npub",
+            Nip19Kind::Nprofile => "nprofile",
+            Nip19Kind::Note => "note",
+            Nip19Kind::Nevent => "nevent",
+            Nip19Kind::Naddr => "naddr",
+        }
+    }
+}
+
+/// Replaceable and addressable kinds, the ones an naddr can point at
+pub fn is_addressable(kind: u32) -> bool {
+    matches!(kind, 0 | 3 | 10000..20000 | 30000..40000)
+}
+
+/// Relays we've seen `note` on, at most [`MAX_RELAY_HINTS`] of them.
+/// Notes we published ourselves or got over multicast may have none.
+pub fn relay_hints(note: &Note, txn: &Transaction) -> Vec<String> {
+    note.relays(txn)
+        .filter(|relay| relay.starts_with("wss://") || relay.starts_with("ws://"))
+        .take(MAX_RELAY_HINTS)
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Encode `note` (or its author) as `kind`. Returns None for an naddr of a
+/// note that isn't addressable.
+pub fn encode_note(note: &Note, kind: Nip19Kind, relays: &[String]) -> Option<String> {
+    match kind {
+        Nip19Kind::Npub => enostr::Pubkey::new(*note.pubkey()).npub(),
+        Nip19Kind::Nprofile => nprofile(note.pubkey(), relays),
+        Nip19Kind::Note => enostr::NoteId::new(*note.id()).to_bech(),
+        Nip19Kind::Nevent => nevent(note.id(), Some(note.pubkey()), Some(note.kind()), relays),
+        Nip19Kind::Naddr => {
+            if !is_addressable(note.kind()) {
+                return None;
+            }
+            naddr(
+                d_tag(note).unwrap_or(""),
+                note.pubkey(),
+                note.kind(),
+                relays,
+            )
+        }
+    }
+}
+
+/// The link to share `note` with: an naddr for addressable notes so it
+/// follows edits, an nevent otherwise, with relay hints either way
+pub fn note_link(note: &Note, txn: &Transaction) -> Option<String> {
+    let kind = if is_addressable(note.kind()) {
+        Nip19Kind::Naddr
+    } else {
+        Nip19Kind::Nevent
+    };
+    encode_note(note, kind, &relay_hints(note, txn))
+}
+
+/// A web page for `bech` that people without a nostr client can open
+pub fn njump_url(bech: &str) -> String {
+    format!("https://njump.me/{bech}")
+}
+
+fn d_tag<'a>(note: &Note<'a>) -> Option<&'a str> {
+    note.tags().into_iter().find_map(|tag| {
+        if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some("d") {
+            return None;
+        }
+        tag.get_unchecked(1).variant().str()
+    })
+}
+
+pub fn nprofile(pubkey: &[u8; 32], relays: &[String]) -> Option<String> {
+    let mut tlv = Vec::new();
+    push_tlv(&mut tlv, TLV_SPECIAL, pubkey);
+    push_relays(&mut tlv, relays);
+    bech32::encode::<bech32::Bech32>(HRP_NPROFILE, &tlv).ok()
+}
+
+pub fn nevent(
+    id: &[u8; 32],
+    author: Option<&[u8; 32]>,
+    kind: Option<u32>,
+    relays: &[String],
+) -> Option<String> {
+    let mut tlv = Vec::new();
+    push_tlv(&mut tlv, TLV_SPECIAL, id);
+    push_relays(&mut tlv, relays);
+    if let Some(author) = author {
+        push_tlv(&mut tlv, TLV_AUTHOR, author);
+    }
+    if let Some(kind) = kind {
+        push_tlv(&mut tlv, TLV_KIND, &kind.to_be_bytes());
+    }
+    bech32::encode::<bech32::Bech32>(HRP_NEVENT, &tlv).ok()
+}
+
+pub fn naddr(identifier: &str, pubkey: &[u8; 32], kind: u32, relays: &[String]) -> Option<String> {
+    let mut tlv = Vec::new();
+    push_tlv(&mut tlv, TLV_SPECIAL, identifier.as_bytes());
+    push_relays(&mut tlv, relays);
+    push_tlv(&mut tlv, TLV_AUTHOR, pubkey);
+    push_tlv(&mut tlv, TLV_KIND, &kind.to_be_bytes());
+    bech32::encode::<bech32::Bech32>(HRP_NADDR, &tlv).ok()
+}
+
+fn push_relays(tlv: &mut Vec<u8>, relays: &[String]) {
+    for relay in relays {
+        push_tlv(tlv, TLV_RELAY, relay.as_bytes());
+    }
+}
+
+/// What a `nostr:` uri points at, see NIP-21
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NostrUri {
+    /// A note1 or nevent
+    Note { id: [u8; 32], relays: Vec<String> },
+
+    /// An npub or nprofile
+    Profile {
+        pubkey: [u8; 32],
+        relays: Vec<String>,
+    },
+
+    /// An naddr
+    Address {
+        kind: u32,
+        pubkey: [u8; 32],
+        identifier: String,
+        relays: Vec<String>,
+    },
+
+    /// A `nostr+notedeck:` link telling us to do something
+    Notedeck(DeepLink),
+}
+
+impl NostrUri {
+    /// Parse a `nostr:` uri. The `web+nostr:` scheme browsers use and a
+    /// bare entity without any scheme work too. Secret keys are refused.
+    pub fn parse(uri: &str) -> Option<Self> {
+        let uri = uri.trim();
+        if uri.starts_with(deep_link::SCHEME) {
+            return DeepLink::parse(uri).map(NostrUri::Notedeck);
+        }
+        let bech = ["web+nostr:", "nostr:"]
+            .iter()
+            .find_map(|scheme| uri.strip_prefix(scheme))
+            .unwrap_or(uri)
+            .trim_start_matches("//");
+
+        match Nip19::from_bech32(bech).ok()? {
+            Nip19::EventId(id) => Some(NostrUri::Note {
+                id: id.to_bytes(),
+                relays: Vec::new(),
+            }),
+            Nip19::Event(event) => Some(NostrUri::Note {
+                id: event.event_id.to_bytes(),
+                relays: relays(&event.relays),
+            }),
+            Nip19::Pubkey(pubkey) => Some(NostrUri::Profile {
+                pubkey: pubkey.to_bytes(),
+                relays: Vec::new(),
+            }),
+            Nip19::Profile(profile) => Some(NostrUri::Profile {
+                pubkey: profile.public_key.to_bytes(),
+                relays: relays(&profile.relays),
+            }),
+            Nip19::Coordinate(coordinate) => Some(NostrUri::Address {
+                kind: coordinate.kind.as_u16() as u32,
+                pubkey: coordinate.public_key.to_bytes(),
+                identifier: coordinate.identifier.clone(),
+                relays: relays(&coordinate.relays),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn relays(&self) -> &[String] {
+        match self {
+            NostrUri::Note { relays, .. }
+            | NostrUri::Profile { relays, .. }
+            | NostrUri::Address { relays, .. } => relays,
+            NostrUri::Notedeck(_) => &[],
+        }
+    }
+}
+
+fn relays(relays: &[impl ToString]) -> Vec<String> {
+    relays.iter().map(ToString::to_string).collect()
+}
+
+/// Values longer than a TLV length byte allows are left out
+fn push_tlv(tlv: &mut Vec<u8>, typ: u8, value: &[u8]) {
+    let Ok(len) = u8::try_from(value.len()) else {
+        tracing::warn!("skipping nip19 tlv {typ}, it's {} bytes", value.len());
+        return;
+    };
+    tlv.push(typ);
+    tlv.push(len);
+    tlv.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pubkey() -> [u8; 32] {
+        let bytes = hex::decode("3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d")
+            .unwrap();
+        bytes.try_into().unwrap()
+    }
+
+    #[test]
+    fn test_nprofile_matches_spec() {
+        // the example from NIP-19
+        let relays = [
+            "wss://r.x.com".to_owned(),
+            "wss://djbas.sadkb.com".to_owned(),
+        ];
+        assert_eq!(
+            nprofile(&pubkey(), &relays).as_deref(),
+            Some("nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p")
+        );
+    }
+
+    #[test]
+    fn test_nevent_round_trip() {
+        let id = [7; 32];
+        let relays = ["wss://relay.damus.io".to_owned()];
+        let bech = nevent(&id, Some(&pubkey()), Some(1), &relays).unwrap();
+
+        let Ok(Nip19::Event(event)) = Nip19::from_bech32(&bech) else {
+            panic!("{bech} isn't an nevent");
+        };
+        assert_eq!(event.event_id.to_bytes(), id);
+        assert_eq!(event.author.map(|pk| pk.to_bytes()), Some(pubkey()));
+        assert_eq!(event.relays.len(), 1);
+    }
+
+    #[test]
+    fn test_naddr_round_trip() {
+        let bech = naddr("my-article", &pubkey(), 30023, &[]).unwrap();
+
+        let Ok(Nip19::Coordinate(coordinate)) = Nip19::from_bech32(&bech) else {
+            panic!("{bech} isn't an naddr");
+        };
+        assert_eq!(coordinate.identifier, "my-article");
+        assert_eq!(coordinate.public_key.to_bytes(), pubkey());
+        assert_eq!(coordinate.kind.as_u16(), 30023);
+    }
+
+    #[test]
+    fn test_is_addressable() {
+        assert!(is_addressable(0));
+        assert!(is_addressable(30023));
+        assert!(!is_addressable(1));
+        assert!(!is_addressable(40000));
+    }
+
+    #[test]
+    fn test_parse_nostr_uri() {
+        let id = [7; 32];
+        let relays = ["wss://relay.damus.io".to_owned()];
+        let bech = nevent(&id, None, None, &relays).unwrap();
+        let expected = NostrUri::Note {
+            id,
+            relays: relays.to_vec(),
+        };
+        assert_eq!(
+            NostrUri::parse(&format!("nostr:{bech}")),
+            Some(expected.clone())
+        );
+        assert_eq!(
+            NostrUri::parse(&format!("web+nostr://{bech}")),
+            Some(expected.clone())
+        );
+        assert_eq!(NostrUri::parse(&bech), Some(expected));
+
+        let npub = enostr::Pubkey::new(pubkey()).npub().unwrap();
+        assert_eq!(
+            NostrUri::parse(&format!("nostr:{npub}")),
+            Some(NostrUri::Profile {
+                pubkey: pubkey(),
+                relays: Vec::new(),
+            })
+        );
+
+        let bech = naddr("my-article", &pubkey(), 30023, &[]).unwrap();
+        assert!(matches!(
+            NostrUri::parse(&bech),
+            Some(NostrUri::Address { kind: 30023, .. })
+        ));
+
+        assert_eq!(NostrUri::parse("nostr:npub1nope"), None);
+        assert_eq!(NostrUri::parse("--debug"), None);
+    }
+}
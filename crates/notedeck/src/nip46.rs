@@ -0,0 +1,870 @@
+use enostr::{ClientMessage, Filter, FullKeypair, Keypair, NoteId, Pubkey, RelayPool};
+use nostr::nips::nip44;
+use nostr::JsonUtil;
+use nostrdb::{Note, NoteBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokenator::{ParseError, TokenParser, TokenSerializable, TokenWriter};
+use tracing::{debug, error, info, warn};
+use url::Url;
+use uuid::Uuid;
+
+use crate::accounts::AccountRelayData;
+use crate::time::unix_now;
+
+/// NIP-46 request and response events
+const NOSTR_CONNECT_KIND: u32 = 24133;
+
+/// The permissions we ask for when connecting
+const PERMS: &str = "sign_event:1,sign_event:6,sign_event:7,sign_event:9734,sign_event:22242";
+
+/// How long we wait for connect, get_public_key and ping responses
+/// before deciding the signer is unreachable
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Signing can need a human to approve it, so give it much longer
+const SIGN_TIMEOUT: Duration = Duration::from_secs(3 * 60);
+
+/// If a sign request has been waiting this long without us hearing
+/// anything from the signer, ping it to see if it's still there
+const PING_AFTER: Duration = Duration::from_secs(15);
+
+/// How often we try to reach a disconnected signer
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(30);
+
+const BUNKER_TOKEN: &str = "bunker";
+
+/// Where we ask signers to find us when logging in with a
+/// nostrconnect:// code
+pub const NOSTRCONNECT_RELAYS: &[&str] = &["wss://relay.nsec.app", "wss://relay.damus.io"];
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SignerError {
+    #[error("invalid signer uri: {0}")]
+    InvalidUri(String),
+
+    #[error("the signer rejected the request: {0}")]
+    Rejected(String),
+
+    #[error("the signer didn't respond in time")]
+    Timeout,
+
+    #[error("the signer is disconnected")]
+    Disconnected,
+
+    #[error("the request was cancelled")]
+    Cancelled,
+
+    #[error("bad response from signer: {0}")]
+    BadResponse(String),
+
+    #[error("this account can't sign")]
+    NoSigner,
+}
+
+/// A `bunker://<remote-signer-pubkey>?relay=...&secret=...` uri
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BunkerUri {
+    pub remote: Pubkey,
+    pub relays: Vec<String>,
+    pub secret: Option<String>,
+}
+
+impl BunkerUri {
+    pub fn parse(uri: &str) -> Result<Self, SignerError> {
+        let url = Url::parse(uri.trim()).map_err(|e| SignerError::InvalidUri(e.to_string()))?;
+
+        if url.scheme() != "bunker" {
+            return Err(SignerError::InvalidUri("expected bunker://".to_owned()));
+        }
+
+        let remote = url
+            .host_str()
+            .and_then(|host| Pubkey::from_hex(host).ok())
+            .ok_or_else(|| SignerError::InvalidUri("missing signer pubkey".to_owned()))?;
+
+        let mut relays = Vec::new();
+        let mut secret = None;
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "relay" => relays.push(AccountRelayData::canonicalize_url(&value)),
+                "secret" => secret = Some(value.into_owned()),
+                _ => {}
+            }
+        }
+
+        if relays.is_empty() {
+            return Err(SignerError::InvalidUri("no relays".to_owned()));
+        }
+
+        Ok(BunkerUri {
+            remote,
+            relays,
+            secret,
+        })
+    }
+}
+
+/// The event fields a signer needs for `sign_event`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UnsignedEvent {
+    pub kind: u32,
+    pub content: String,
+    pub tags: Vec<Vec<String>>,
+    pub created_at: u64,
+}
+
+impl UnsignedEvent {
+    /// Take the contents of a note built with the signer's local key.
+    /// The note's pubkey, id and signature are dropped, the signer fills
+    /// those in.
+    pub fn from_note(note: &Note) -> Result<Self, SignerError> {
+        let json = note
+            .json()
+            .map_err(|e| SignerError::BadResponse(e.to_string()))?;
+        let mut unsigned: UnsignedEvent =
+            serde_json::from_str(&json).map_err(|e| SignerError::BadResponse(e.to_string()))?;
+
+        if unsigned.created_at == 0 {
+            unsigned.created_at = unix_now();
+        }
+
+        Ok(unsigned)
+    }
+}
+
+/// An event the signer signed for us
+#[derive(Debug, Clone)]
+pub struct SignedEvent {
+    pub id: NoteId,
+    pub json: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignerStatus {
+    Connecting,
+    Connected,
+    Disconnected(String),
+}
+
+/// What to do with an event once the signer has signed it
+pub enum SignPurpose {
+    /// Send it to our relays
+    Publish,
+
+    /// Hand it back to whoever asked for it
+    Respond(poll_promise::Sender<Result<SignedEvent, SignerError>>),
+}
+
+impl SignPurpose {
+    pub fn fail(self, err: SignerError) {
+        match self {
+            SignPurpose::Publish => error!("nip46: couldn't sign note: {err}"),
+            SignPurpose::Respond(sender) => sender.send(Err(err)),
+        }
+    }
+}
+
+enum Method {
+    Connect,
+    GetPublicKey,
+    Ping,
+    SignEvent(SignPurpose),
+}
+
+impl Method {
+    fn name(&self) -> &'static str {
+        match self {
+            Method::Connect => "connect",
+            Method::GetPublicKey => "get_public_key",
+            Method::Ping => "ping",
+            Method::SignEvent(_) => "sign_event",
+        }
+    }
+
+    fn timeout(&self) -> Duration {
+        match self {
+            Method::SignEvent(_) => SIGN_TIMEOUT,
+            _ => CONNECT_TIMEOUT,
+        }
+    }
+}
+
+struct PendingRequest {
+    method: Method,
+    sent_at: Instant,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    id: &'a str,
+    method: &'a str,
+    params: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: String,
+    result: Option<String>,
+    error: Option<String>,
+}
+
+/// A NIP-46 remote signer ("bunker"). The user's secret key lives with
+/// the signer, we talk to it over relays with a local keypair that never
+/// signs anything on the user's behalf.
+pub struct RemoteSigner {
+    client: FullKeypair,
+
+    /// The signer's pubkey. We don't know it yet if we're waiting for
+    /// someone to scan our nostrconnect:// code.
+    remote: Option<Pubkey>,
+
+    relays: Vec<String>,
+    secret: Option<String>,
+
+    /// The pubkey the signer signs as. Only known after get_public_key.
+    user_pubkey: Option<Pubkey>,
+
+    /// Whether the signer already accepted our connect request, in which
+    /// case reconnecting is just a ping
+    authorized: bool,
+
+    /// The signer accepted us but we haven't asked who it signs as yet
+    wants_pubkey: bool,
+
+    sub_id: String,
+    subscribed: bool,
+    status: SignerStatus,
+    requests: HashMap<String, PendingRequest>,
+    last_heard: Option<Instant>,
+    last_attempt: Option<Instant>,
+
+    /// Set when the signer wants the user to approve something in a
+    /// browser before it answers
+    auth_url: Option<String>,
+    last_error: Option<SignerError>,
+
+    /// Signed events waiting to be published
+    signed: Vec<String>,
+}
+
+impl RemoteSigner {
+    fn new(remote: Option<Pubkey>, relays: Vec<String>, secret: Option<String>) -> Self {
+        Self {
+            client: FullKeypair::generate(),
+            remote,
+            relays,
+            secret,
+            user_pubkey: None,
+            authorized: false,
+            wants_pubkey: false,
+            sub_id: Uuid::new_v4().to_string(),
+            subscribed: false,
+            status: SignerStatus::Connecting,
+            requests: HashMap::new(),
+            last_heard: None,
+            last_attempt: None,
+            auth_url: None,
+            last_error: None,
+            signed: Vec::new(),
+        }
+    }
+
+    /// Connect to a signer from a bunker:// uri
+    pub fn bunker(uri: BunkerUri) -> Self {
+        Self::new(Some(uri.remote), uri.relays, uri.secret)
+    }
+
+    /// Wait for a signer to connect to us via our nostrconnect:// uri
+    pub fn nostrconnect(relays: Vec<String>) -> Self {
+        let relays = relays
+            .iter()
+            .map(|r| AccountRelayData::canonicalize_url(r))
+            .collect();
+        let secret = Uuid::new_v4().simple().to_string();
+        Self::new(None, relays, Some(secret))
+    }
+
+    /// The uri to show (as a QR code) for signers to connect to us
+    pub fn nostrconnect_uri(&self) -> String {
+        let mut uri = format!("nostrconnect://{}?", self.client.pubkey.hex());
+        {
+            let mut query = url::form_urlencoded::Serializer::new(&mut uri);
+            for relay in &self.relays {
+                query.append_pair("relay", relay);
+            }
+            if let Some(secret) = &self.secret {
+                query.append_pair("secret", secret);
+            }
+            query.append_pair("perms", PERMS);
+            query.append_pair("name", "Notedeck");
+        }
+        uri
+    }
+
+    /// Whether we're still waiting for a signer to scan our
+    /// nostrconnect:// code
+    pub fn awaiting_scan(&self) -> bool {
+        self.remote.is_none()
+    }
+
+    pub fn status(&self) -> &SignerStatus {
+        &self.status
+    }
+
+    pub fn user_pubkey(&self) -> Option<&Pubkey> {
+        self.user_pubkey.as_ref()
+    }
+
+    pub fn relays(&self) -> &[String] {
+        &self.relays
+    }
+
+    pub fn auth_url(&self) -> Option<&str> {
+        self.auth_url.as_deref()
+    }
+
+    pub fn last_error(&self) -> Option<&SignerError> {
+        self.last_error.as_ref()
+    }
+
+    pub fn clear_error(&mut self) {
+        self.last_error = None;
+    }
+
+    /// The local key to build notes with before handing them to
+    /// [`RemoteSigner::sign_event`]. Notes signed with this key are only
+    /// used for their contents.
+    pub fn local_key(&self) -> [u8; 32] {
+        self.client.secret_key.secret_bytes()
+    }
+
+    /// Sign requests still waiting on the signer
+    pub fn pending_signatures(&self) -> usize {
+        self.requests
+            .values()
+            .filter(|req| matches!(req.method, Method::SignEvent(_)))
+            .count()
+    }
+
+    /// Give up on every outstanding sign request
+    pub fn cancel_pending(&mut self) {
+        self.fail_requests(
+            |method| matches!(method, Method::SignEvent(_)),
+            SignerError::Cancelled,
+        );
+        self.auth_url = None;
+    }
+
+    pub fn sign_event(
+        &mut self,
+        pool: &mut RelayPool,
+        unsigned: &UnsignedEvent,
+        purpose: SignPurpose,
+    ) {
+        if self.user_pubkey.is_none() {
+            purpose.fail(SignerError::Disconnected);
+            return;
+        }
+
+        let json = match serde_json::to_string(unsigned) {
+            Ok(json) => json,
+            Err(err) => {
+                purpose.fail(SignerError::BadResponse(err.to_string()));
+                return;
+            }
+        };
+
+        self.send_request(pool, Method::SignEvent(purpose), vec![json]);
+    }
+
+    /// Resubscribe when one of our relays (re)connects
+    pub fn send_initial_filters(&mut self, pool: &mut RelayPool, relay_url: &str) {
+        if !self.relays.iter().any(|r| r == relay_url) {
+            return;
+        }
+
+        pool.send_to(&self.subscription(), relay_url);
+        if self.status != SignerStatus::Connected {
+            self.establish(pool);
+        }
+    }
+
+    /// Process a relay EVENT message. Returns false if it wasn't for us.
+    pub fn process_event(&mut self, sub_id: &str, msg: &str) -> bool {
+        if sub_id != self.sub_id {
+            return false;
+        }
+
+        let event = match serde_json::from_str::<(String, String, nostr::Event)>(msg) {
+            Ok((_, _, event)) => event,
+            Err(err) => {
+                warn!("nip46: couldn't parse event: {err}");
+                return true;
+            }
+        };
+
+        if event.verify().is_err() {
+            warn!("nip46: dropping event with bad signature");
+            return true;
+        }
+
+        let sender = Pubkey::new(event.pubkey.to_bytes());
+        if self.remote.is_some_and(|remote| remote != sender) {
+            return true;
+        }
+
+        let plaintext = match decrypt(&self.client, &sender, &event.content) {
+            Ok(plaintext) => plaintext,
+            Err(err) => {
+                warn!(
+                    "nip46: couldn't decrypt message from {}: {err}",
+                    sender.hex()
+                );
+                return true;
+            }
+        };
+
+        match serde_json::from_str::<Response>(&plaintext) {
+            Ok(resp) => self.handle_response(sender, resp),
+            Err(err) => warn!("nip46: bad response from {}: {err}", sender.hex()),
+        }
+
+        true
+    }
+
+    /// Called every frame. Subscribes, times out requests and reconnects
+    /// if the signer went away.
+    pub fn update(&mut self, pool: &mut RelayPool) {
+        if !self.subscribed {
+            let sub = self.subscription();
+            for relay in &self.relays {
+                pool.send_to(&sub, relay);
+            }
+            self.subscribed = true;
+            self.establish(pool);
+        }
+
+        if self.wants_pubkey {
+            self.wants_pubkey = false;
+            self.send_request(pool, Method::GetPublicKey, vec![]);
+        }
+
+        let expired: Vec<String> = self
+            .requests
+            .iter()
+            .filter(|(_, req)| req.sent_at.elapsed() > req.method.timeout())
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in expired {
+            let Some(req) = self.requests.remove(&id) else {
+                continue;
+            };
+
+            match req.method {
+                Method::SignEvent(purpose) => {
+                    self.last_error = Some(SignerError::Timeout);
+                    purpose.fail(SignerError::Timeout);
+                }
+                _ => self.disconnected("no response from signer"),
+            }
+        }
+
+        match self.status {
+            SignerStatus::Disconnected(_) => {
+                if elapsed_or_never(self.last_attempt, RECONNECT_INTERVAL) {
+                    self.establish(pool);
+                }
+            }
+
+            SignerStatus::Connected => {
+                // A sign request might be waiting on the user, or the
+                // signer might be gone. Ping it to find out which.
+                let quiet = elapsed_or_never(self.last_heard, PING_AFTER);
+                let stale_sign = self.requests.values().any(|req| {
+                    matches!(req.method, Method::SignEvent(_)) && req.sent_at.elapsed() > PING_AFTER
+                });
+                let pinging = self
+                    .requests
+                    .values()
+                    .any(|req| matches!(req.method, Method::Ping));
+
+                if quiet && stale_sign && !pinging {
+                    self.send_request(pool, Method::Ping, vec![]);
+                }
+            }
+
+            SignerStatus::Connecting => {}
+        }
+    }
+
+    /// Signed events to publish
+    pub fn take_signed(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.signed)
+    }
+
+    fn subscription(&self) -> ClientMessage {
+        let since = unix_now().saturating_sub(60);
+        let filter = Filter::new()
+            .kinds([NOSTR_CONNECT_KIND as u64])
+            .pubkeys([self.client.pubkey.bytes()])
+            .since(since)
+            .build();
+        ClientMessage::req(self.sub_id.clone(), vec![filter])
+    }
+
+    /// Connect, or ping if the signer already knows us
+    fn establish(&mut self, pool: &mut RelayPool) {
+        let Some(remote) = self.remote else {
+            // nostrconnect, the signer has to come to us
+            return;
+        };
+
+        let in_flight = self
+            .requests
+            .values()
+            .any(|req| matches!(req.method, Method::Connect | Method::Ping));
+        if in_flight {
+            return;
+        }
+
+        self.last_attempt = Some(Instant::now());
+
+        if self.authorized {
+            self.send_request(pool, Method::Ping, vec![]);
+        } else {
+            let mut params = vec![remote.hex()];
+            params.push(self.secret.clone().unwrap_or_default());
+            params.push(PERMS.to_owned());
+            self.send_request(pool, Method::Connect, params);
+        }
+    }
+
+    fn send_request(&mut self, pool: &mut RelayPool, method: Method, params: Vec<String>) {
+        let Some(remote) = self.remote else {
+            if let Method::SignEvent(purpose) = method {
+                purpose.fail(SignerError::Disconnected);
+            }
+            return;
+        };
+
+        let id = Uuid::new_v4().to_string();
+        let request = Request {
+            id: &id,
+            method: method.name(),
+            params,
+        };
+
+        let msg = serde_json::to_string(&request)
+            .map_err(|e| e.to_string())
+            .and_then(|json| encrypt(&self.client, &remote, &json))
+            .and_then(|content| {
+                let note = NoteBuilder::new()
+                    .kind(NOSTR_CONNECT_KIND)
+                    .content(&content)
+                    .start_tag()
+                    .tag_str("p")
+                    .tag_str(&remote.hex())
+                    .sign(&self.client.secret_key.secret_bytes())
+                    .build()
+                    .ok_or_else(|| "couldn't build request note".to_owned())?;
+                ClientMessage::event(&note).map_err(|e| e.to_string())
+            });
+
+        let msg = match msg {
+            Ok(msg) => msg,
+            Err(err) => {
+                error!("nip46: couldn't create {} request: {err}", method.name());
+                if let Method::SignEvent(purpose) = method {
+                    purpose.fail(SignerError::BadResponse(err));
+                }
+                return;
+            }
+        };
+
+        debug!("nip46: sending {} {}", method.name(), id);
+        for relay in &self.relays {
+            pool.send_to(&msg, relay);
+        }
+
+        self.requests.insert(
+            id,
+            PendingRequest {
+                method,
+                sent_at: Instant::now(),
+            },
+        );
+    }
+
+    fn handle_response(&mut self, sender: Pubkey, resp: Response) {
+        self.last_heard = Some(Instant::now());
+
+        let Some(req) = self.requests.remove(&resp.id) else {
+            // nostrconnect: the signer tells us about itself by echoing
+            // our secret back
+            if self.remote.is_none() && resp.result.is_some() && resp.result == self.secret {
+                info!("nip46: signer {} connected", sender.hex());
+                self.remote = Some(sender);
+                self.on_connected();
+            }
+            return;
+        };
+
+        if resp.result.as_deref() == Some("auth_url") {
+            // the signer wants the user to go approve this somewhere,
+            // keep waiting for the real response
+            self.auth_url = resp.error;
+            self.requests.insert(resp.id, req);
+            return;
+        }
+
+        let error = resp.error.filter(|e| !e.is_empty());
+
+        match req.method {
+            Method::Connect => match error {
+                Some(err) => self.disconnected(&err),
+                None => self.on_connected(),
+            },
+
+            Method::GetPublicKey => {
+                match resp.result.as_deref().map(Pubkey::from_hex) {
+                    Some(Ok(pk)) => {
+                        if self.user_pubkey.is_some_and(|cur| cur != pk) {
+                            // someone swapped the key out from under us
+                            self.disconnected("signer changed keys");
+                            return;
+                        }
+                        self.user_pubkey = Some(pk);
+                        self.status = SignerStatus::Connected;
+                    }
+                    _ => self.disconnected(error.as_deref().unwrap_or("bad pubkey")),
+                }
+            }
+
+            Method::Ping => {
+                if self.status != SignerStatus::Connected {
+                    self.on_connected();
+                }
+            }
+
+            Method::SignEvent(purpose) => {
+                self.auth_url = None;
+                let res = match (resp.result, error) {
+                    (_, Some(err)) => Err(SignerError::Rejected(err)),
+                    (Some(json), None) => verify_signed(json, self.user_pubkey),
+                    (None, None) => Err(SignerError::BadResponse("empty result".to_owned())),
+                };
+
+                match purpose {
+                    SignPurpose::Publish => match res {
+                        Ok(signed) => self.signed.push(signed.json),
+                        Err(err) => {
+                            error!("nip46: signing failed: {err}");
+                            self.last_error = Some(err);
+                        }
+                    },
+                    SignPurpose::Respond(sender) => sender.send(res),
+                }
+            }
+        }
+    }
+
+    fn on_connected(&mut self) {
+        self.authorized = true;
+        self.secret = None;
+        self.last_error = None;
+
+        if self.user_pubkey.is_some() {
+            self.status = SignerStatus::Connected;
+        } else {
+            // we still need the user's pubkey before we're useful, we ask
+            // for it on the next update
+            self.status = SignerStatus::Connecting;
+            self.wants_pubkey = true;
+        }
+    }
+
+    fn disconnected(&mut self, reason: &str) {
+        if !matches!(self.status, SignerStatus::Disconnected(_)) {
+            warn!("nip46: signer disconnected: {reason}");
+        }
+        self.status = SignerStatus::Disconnected(reason.to_owned());
+        self.auth_url = None;
+        self.fail_requests(|_| true, SignerError::Disconnected);
+    }
+
+    fn fail_requests(&mut self, which: impl Fn(&Method) -> bool, err: SignerError) {
+        let ids: Vec<String> = self
+            .requests
+            .iter()
+            .filter(|(_, req)| which(&req.method))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in ids {
+            if let Some(PendingRequest {
+                method: Method::SignEvent(purpose),
+                ..
+            }) = self.requests.remove(&id)
+            {
+                purpose.fail(err.clone());
+            }
+        }
+    }
+}
+
+/// Check that a signer handed back a valid event signed by the user
+pub(crate) fn verify_signed(
+    json: String,
+    user_pubkey: Option<Pubkey>,
+) -> Result<SignedEvent, SignerError> {
+    let event =
+        nostr::Event::from_json(&json).map_err(|e| SignerError::BadResponse(e.to_string()))?;
+
+    event
+        .verify()
+        .map_err(|e| SignerError::BadResponse(e.to_string()))?;
+
+    if user_pubkey.map(|pk| *pk.bytes()) != Some(event.pubkey.to_bytes()) {
+        return Err(SignerError::BadResponse(
+            "signed by the wrong key".to_owned(),
+        ));
+    }
+
+    Ok(SignedEvent {
+        id: NoteId::new(event.id.to_bytes()),
+        json,
+    })
+}
+
+fn elapsed_or_never(instant: Option<Instant>, duration: Duration) -> bool {
+    match instant {
+        None => true,
+        Some(instant) => instant.elapsed() > duration,
+    }
+}
+
+fn conversation_keys(
+    client: &FullKeypair,
+    other: &Pubkey,
+) -> Result<(nostr::SecretKey, nostr::PublicKey), String> {
+    let pk = nostr::PublicKey::from_slice(other.bytes()).map_err(|e| e.to_string())?;
+    Ok((client.secret_key.clone(), pk))
+}
+
+fn encrypt(client: &FullKeypair, to: &Pubkey, plaintext: &str) -> Result<String, String> {
+    let (sk, pk) = conversation_keys(client, to)?;
+    nip44::encrypt(&sk, &pk, plaintext, nip44::Version::V2).map_err(|e| e.to_string())
+}
+
+fn decrypt(client: &FullKeypair, from: &Pubkey, payload: &str) -> Result<String, String> {
+    let (sk, pk) = conversation_keys(client, from)?;
+    nip44::decrypt(&sk, &pk, payload).map_err(|e| e.to_string())
+}
+
+impl TokenSerializable for RemoteSigner {
+    fn parse_from_tokens<'a>(parser: &mut TokenParser<'a>) -> Result<Self, ParseError<'a>> {
+        parser.parse_all(|p| {
+            p.parse_token(BUNKER_TOKEN)?;
+            let remote =
+                Pubkey::from_hex(p.pull_token()?).map_err(|_| ParseError::HexDecodeFailed)?;
+            let user =
+                Pubkey::from_hex(p.pull_token()?).map_err(|_| ParseError::HexDecodeFailed)?;
+            let relays: Vec<String> = p.pull_token()?.split(',').map(str::to_owned).collect();
+            let client = Keypair::parse_from_tokens(p)?
+                .to_full()
+                .map(|kp| kp.to_full())
+                .ok_or(ParseError::DecodeFailed)?;
+
+            let mut signer = RemoteSigner::new(Some(remote), relays, None);
+            signer.client = client;
+            signer.user_pubkey = Some(user);
+            signer.authorized = true;
+            Ok(signer)
+        })
+    }
+
+    fn serialize_tokens(&self, writer: &mut TokenWriter) {
+        let (Some(remote), Some(user)) = (self.remote, self.user_pubkey) else {
+            // we only save signers that finished connecting
+            return;
+        };
+
+        writer.write_token(BUNKER_TOKEN);
+        writer.write_token(&remote.hex());
+        writer.write_token(&user.hex());
+        writer.write_token(&self.relays.join(","));
+        self.client.clone().to_keypair().serialize_tokens(writer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bunker_uri() {
+        let remote = Pubkey::new([7; 32]);
+        let uri = format!(
+            "bunker://{}?relay=wss%3A%2F%2Frelay.nsec.app&relay=wss://nos.lol&secret=abc",
+            remote.hex()
+        );
+
+        let parsed = BunkerUri::parse(&uri).unwrap();
+        assert_eq!(parsed.remote, remote);
+        assert_eq!(
+            parsed.relays,
+            vec![
+                "wss://relay.nsec.app/".to_owned(),
+                "wss://nos.lol/".to_owned()
+            ]
+        );
+        assert_eq!(parsed.secret.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_parse_bunker_uri_errors() {
+        assert!(BunkerUri::parse("nostrconnect://abc?relay=wss://nos.lol").is_err());
+        assert!(BunkerUri::parse("bunker://nothex?relay=wss://nos.lol").is_err());
+
+        let remote = Pubkey::new([7; 32]);
+        assert!(BunkerUri::parse(&format!("bunker://{}", remote.hex())).is_err());
+    }
+
+    #[test]
+    fn test_signer_tokens() {
+        let uri = BunkerUri {
+            remote: Pubkey::new([7; 32]),
+            relays: vec!["wss://relay.nsec.app/".to_owned()],
+            secret: Some("abc".to_owned()),
+        };
+        let mut signer = RemoteSigner::bunker(uri);
+        signer.user_pubkey = Some(Pubkey::new([9; 32]));
+
+        let mut writer = TokenWriter::new("\t");
+        signer.serialize_tokens(&mut writer);
+
+        let data = writer.str().split('\t').collect::<Vec<&str>>();
+        let mut parser = TokenParser::new(&data);
+        let parsed = RemoteSigner::parse_from_tokens(&mut parser).unwrap();
+
+        assert_eq!(parsed.remote, signer.remote);
+        assert_eq!(parsed.user_pubkey, signer.user_pubkey);
+        assert_eq!(parsed.relays, signer.relays);
+        assert_eq!(parsed.client, signer.client);
+        assert!(parsed.authorized);
+        // the connect secret is single use, it shouldn't be saved
+        assert!(parsed.secret.is_none());
+    }
+
+    #[test]
+    fn test_nip44_roundtrip() {
+        let a = FullKeypair::generate();
+        let b = FullKeypair::generate();
+
+        let payload = encrypt(&a, &b.pubkey, "{\"id\":\"1\"}").unwrap();
+        assert_eq!(decrypt(&b, &a.pubkey, &payload).unwrap(), "{\"id\":\"1\"}");
+    }
+}
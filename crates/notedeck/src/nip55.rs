@@ -0,0 +1,384 @@
+use enostr::{FullKeypair, Keypair, Pubkey};
+use poll_promise::Promise;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tokenator::{ParseError, TokenParser, TokenSerializable, TokenWriter};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::nip46::{verify_signed, SignPurpose, SignerError, SignerStatus, UnsignedEvent};
+use crate::platform::{self, ExternalSignerRequest, ExternalSignerResponse};
+
+/// The user has to switch to the signer app and back for every request,
+/// give them plenty of time
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(3 * 60);
+
+const AMBER_TOKEN: &str = "amber";
+
+enum Method {
+    GetPublicKey,
+    SignEvent(SignPurpose),
+    Encrypt(poll_promise::Sender<Result<String, SignerError>>),
+    Decrypt(poll_promise::Sender<Result<String, SignerError>>),
+}
+
+impl Method {
+    fn name(&self) -> &'static str {
+        match self {
+            Method::GetPublicKey => "get_public_key",
+            Method::SignEvent(_) => "sign_event",
+            Method::Encrypt(_) => "nip44_encrypt",
+            Method::Decrypt(_) => "nip44_decrypt",
+        }
+    }
+
+    fn fail(self, err: SignerError) {
+        match self {
+            Method::GetPublicKey => {}
+            Method::SignEvent(purpose) => purpose.fail(err),
+            Method::Encrypt(sender) | Method::Decrypt(sender) => sender.send(Err(err)),
+        }
+    }
+}
+
+struct Request {
+    id: String,
+    method: Method,
+    content: String,
+
+    /// The other party for encrypt and decrypt
+    pubkey: Option<String>,
+}
+
+/// A NIP-55 signer app on Android, like Amber. The user's secret key
+/// stays in the signer app, we send it requests as intents.
+///
+/// The signer takes over the screen for each request, so we only ever
+/// have one in flight and queue the rest.
+pub struct AmberSigner {
+    /// Only used to build notes before the signer signs them, see
+    /// [`AmberSigner::local_key`]
+    client: FullKeypair,
+
+    user_pubkey: Option<Pubkey>,
+
+    /// The signer app's package name, so we keep talking to the same app
+    package: Option<String>,
+
+    status: SignerStatus,
+    queue: VecDeque<Request>,
+    current: Option<(Request, Instant)>,
+    last_error: Option<SignerError>,
+
+    /// Signed events waiting to be published
+    signed: Vec<String>,
+}
+
+impl AmberSigner {
+    fn new(user_pubkey: Option<Pubkey>, package: Option<String>) -> Self {
+        Self {
+            client: FullKeypair::generate(),
+            user_pubkey,
+            package,
+            status: SignerStatus::Connecting,
+            queue: VecDeque::new(),
+            current: None,
+            last_error: None,
+            signed: Vec::new(),
+        }
+    }
+
+    /// Ask the signer app which account we're logging in as
+    pub fn login() -> Self {
+        let mut signer = Self::new(None, None);
+        signer.enqueue(Method::GetPublicKey, String::new(), None);
+        signer
+    }
+
+    pub fn status(&self) -> &SignerStatus {
+        &self.status
+    }
+
+    pub fn user_pubkey(&self) -> Option<&Pubkey> {
+        self.user_pubkey.as_ref()
+    }
+
+    pub fn last_error(&self) -> Option<&SignerError> {
+        self.last_error.as_ref()
+    }
+
+    pub fn clear_error(&mut self) {
+        self.last_error = None;
+    }
+
+    /// The local key to build notes with before handing them to
+    /// [`AmberSigner::sign_event`]
+    pub fn local_key(&self) -> [u8; 32] {
+        self.client.secret_key.secret_bytes()
+    }
+
+    /// Sign requests still waiting on the signer app
+    pub fn pending_signatures(&self) -> usize {
+        self.current
+            .iter()
+            .map(|(req, _)| req)
+            .chain(self.queue.iter())
+            .filter(|req| matches!(req.method, Method::SignEvent(_)))
+            .count()
+    }
+
+    /// Give up on every outstanding request. If the signer app is
+    /// showing one right now, its answer gets dropped.
+    pub fn cancel_pending(&mut self) {
+        let current = self.current.take().map(|(req, _)| req);
+        for req in current.into_iter().chain(self.queue.drain(..)) {
+            req.method.fail(SignerError::Cancelled);
+        }
+    }
+
+    pub fn sign_event(&mut self, unsigned: &UnsignedEvent, purpose: SignPurpose) {
+        let Some(pubkey) = self.user_pubkey else {
+            purpose.fail(SignerError::Disconnected);
+            return;
+        };
+
+        // the signer wants a whole event, pubkey included
+        let json = serde_json::to_value(unsigned).and_then(|mut value| {
+            value["pubkey"] = pubkey.hex().into();
+            serde_json::to_string(&value)
+        });
+
+        match json {
+            Ok(json) => self.enqueue(Method::SignEvent(purpose), json, None),
+            Err(err) => purpose.fail(SignerError::BadResponse(err.to_string())),
+        }
+    }
+
+    /// NIP-44 encrypt `plaintext` to `to` as the user
+    pub fn encrypt(
+        &mut self,
+        to: &Pubkey,
+        plaintext: &str,
+    ) -> Promise<Result<String, SignerError>> {
+        let (sender, promise) = Promise::new();
+        self.enqueue(
+            Method::Encrypt(sender),
+            plaintext.to_owned(),
+            Some(to.hex()),
+        );
+        promise
+    }
+
+    /// NIP-44 decrypt a payload `from` sent to the user
+    pub fn decrypt(
+        &mut self,
+        from: &Pubkey,
+        payload: &str,
+    ) -> Promise<Result<String, SignerError>> {
+        let (sender, promise) = Promise::new();
+        self.enqueue(
+            Method::Decrypt(sender),
+            payload.to_owned(),
+            Some(from.hex()),
+        );
+        promise
+    }
+
+    /// Called every frame. Picks up the signer app's response and
+    /// launches the next request.
+    pub fn update(&mut self) {
+        if let Some((req, sent_at)) = &self.current {
+            if let Some(resp) = platform::take_external_signer_response(&req.id) {
+                if let Some((req, _)) = self.current.take() {
+                    self.handle_response(req, resp);
+                }
+            } else if sent_at.elapsed() > REQUEST_TIMEOUT {
+                if let Some((req, _)) = self.current.take() {
+                    warn!("nip55: {} timed out", req.method.name());
+                    self.last_error = Some(SignerError::Timeout);
+                    req.method.fail(SignerError::Timeout);
+                }
+            }
+        }
+
+        if self.current.is_none() {
+            if let Some(req) = self.queue.pop_front() {
+                self.launch(req);
+            }
+        }
+    }
+
+    /// Signed events to publish
+    pub fn take_signed(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.signed)
+    }
+
+    fn enqueue(&mut self, method: Method, content: String, pubkey: Option<String>) {
+        self.queue.push_back(Request {
+            id: Uuid::new_v4().to_string(),
+            method,
+            content,
+            pubkey,
+        });
+    }
+
+    fn launch(&mut self, req: Request) {
+        let current_user = self.user_pubkey.and_then(|pk| pk.npub());
+        let launched = platform::launch_external_signer(&ExternalSignerRequest {
+            id: &req.id,
+            method: req.method.name(),
+            content: &req.content,
+            pubkey: req.pubkey.as_deref(),
+            current_user: current_user.as_deref(),
+            package: self.package.as_deref(),
+        });
+
+        match launched {
+            Ok(()) => {
+                debug!("nip55: launched {} {}", req.method.name(), req.id);
+                self.current = Some((req, Instant::now()));
+            }
+            Err(err) => {
+                error!("nip55: couldn't launch signer: {err}");
+                self.status = SignerStatus::Disconnected(err);
+                req.method.fail(SignerError::Disconnected);
+            }
+        }
+    }
+
+    fn handle_response(&mut self, req: Request, resp: ExternalSignerResponse) {
+        if resp.rejected {
+            let err = SignerError::Rejected(format!("{} was declined", req.method.name()));
+            if matches!(req.method, Method::GetPublicKey) {
+                self.status = SignerStatus::Disconnected("Login was declined".to_owned());
+            } else {
+                self.last_error = Some(err.clone());
+            }
+            req.method.fail(err);
+            return;
+        }
+
+        match req.method {
+            Method::GetPublicKey => {
+                // older signers answer with an npub, newer ones with hex
+                match resp.result.as_deref().map(Pubkey::parse) {
+                    Some(Ok(pk)) => {
+                        info!("nip55: signer {:?} signs for {}", resp.package, pk.hex());
+                        self.user_pubkey = Some(pk);
+                        self.package = resp.package.or(self.package.take());
+                        self.status = SignerStatus::Connected;
+                    }
+                    _ => {
+                        self.status =
+                            SignerStatus::Disconnected("signer sent a bad pubkey".to_owned());
+                    }
+                }
+            }
+
+            Method::SignEvent(purpose) => {
+                self.status = SignerStatus::Connected;
+                let res = resp
+                    .event
+                    .ok_or_else(|| SignerError::BadResponse("no signed event".to_owned()))
+                    .and_then(|json| verify_signed(json, self.user_pubkey));
+
+                match purpose {
+                    SignPurpose::Publish => match res {
+                        Ok(signed) => self.signed.push(signed.json),
+                        Err(err) => {
+                            error!("nip55: signing failed: {err}");
+                            self.last_error = Some(err);
+                        }
+                    },
+                    SignPurpose::Respond(sender) => sender.send(res),
+                }
+            }
+
+            Method::Encrypt(sender) | Method::Decrypt(sender) => {
+                sender.send(
+                    resp.result
+                        .ok_or_else(|| SignerError::BadResponse("empty result".to_owned())),
+                );
+            }
+        }
+    }
+}
+
+impl TokenSerializable for AmberSigner {
+    fn parse_from_tokens<'a>(parser: &mut TokenParser<'a>) -> Result<Self, ParseError<'a>> {
+        parser.parse_all(|p| {
+            p.parse_token(AMBER_TOKEN)?;
+            let user =
+                Pubkey::from_hex(p.pull_token()?).map_err(|_| ParseError::HexDecodeFailed)?;
+            let package = p.pull_token()?.to_owned();
+            let client = Keypair::parse_from_tokens(p)?
+                .to_full()
+                .map(|kp| kp.to_full())
+                .ok_or(ParseError::DecodeFailed)?;
+
+            let mut signer = AmberSigner::new(Some(user), Some(package));
+            signer.client = client;
+            signer.status = SignerStatus::Connected;
+            Ok(signer)
+        })
+    }
+
+    fn serialize_tokens(&self, writer: &mut TokenWriter) {
+        let (Some(user), Some(package)) = (self.user_pubkey, &self.package) else {
+            // we only save signers that told us who they are
+            return;
+        };
+
+        writer.write_token(AMBER_TOKEN);
+        writer.write_token(&user.hex());
+        writer.write_token(package);
+        self.client.clone().to_keypair().serialize_tokens(writer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amber_tokens() {
+        let mut signer = AmberSigner::new(
+            Some(Pubkey::new([9; 32])),
+            Some("com.greenart7c3.nostrsigner".to_owned()),
+        );
+        signer.status = SignerStatus::Connected;
+
+        let mut writer = TokenWriter::new("\t");
+        signer.serialize_tokens(&mut writer);
+
+        let data = writer.str().split('\t').collect::<Vec<&str>>();
+        let mut parser = TokenParser::new(&data);
+        let parsed = AmberSigner::parse_from_tokens(&mut parser).unwrap();
+
+        assert_eq!(parsed.user_pubkey, signer.user_pubkey);
+        assert_eq!(parsed.package, signer.package);
+        assert_eq!(parsed.client, signer.client);
+        assert_eq!(parsed.status, SignerStatus::Connected);
+    }
+
+    #[test]
+    fn test_unfinished_login_isnt_saved() {
+        let signer = AmberSigner::login();
+
+        let mut writer = TokenWriter::new("\t");
+        signer.serialize_tokens(&mut writer);
+
+        assert!(writer.str().is_empty());
+    }
+
+    #[test]
+    fn test_cancel_fails_queued_requests() {
+        let mut signer = AmberSigner::new(Some(Pubkey::new([9; 32])), None);
+        let promise = signer.encrypt(&Pubkey::new([1; 32]), "hi");
+        assert_eq!(signer.queue.len(), 1);
+
+        signer.cancel_pending();
+        assert!(signer.queue.is_empty());
+        assert_eq!(promise.block_and_take(), Err(SignerError::Cancelled));
+    }
+}
@@ -1,7 +1,9 @@
 use super::context::ContextSelection;
-use crate::{zaps::NoteZapTargetOwned, Images, MediaCacheType, TexturedImage};
+use crate::{zaps::NoteZapTargetOwned, ContactAction, Images, MediaCacheType, TexturedImage};
 use enostr::{NoteId, Pubkey};
 use poll_promise::Promise;
+use std::path::{Path, PathBuf};
+use tracing::error;
 
 #[derive(Debug)]
 pub enum NoteAction {
@@ -28,6 +30,12 @@ pub enum NoteAction {
 
     /// User clicked on media
     Media(MediaAction),
+
+    /// User followed or unfollowed someone from their hover card
+    Contact(ContactAction),
+
+    /// User voted on a poll, picking these option ids
+    Vote { poll: NoteId, options: Vec<String> },
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -53,6 +61,8 @@ pub enum MediaAction {
         url: String,
         cache_type: MediaCacheType,
     },
+    /// Download the original file into the user's downloads folder
+    Save { url: String },
 }
 
 impl std::fmt::Debug for MediaAction {
@@ -73,12 +83,13 @@ impl std::fmt::Debug for MediaAction {
                 .field("url", url)
                 .field("cache_type", cache_type)
                 .finish(),
+            Self::Save { url } => f.debug_struct("Save").field("url", url).finish(),
         }
     }
 }
 
 impl MediaAction {
-    pub fn process(self, images: &mut Images) {
+    pub fn process(self, ctx: &egui::Context, images: &mut Images) {
         match self {
             MediaAction::FetchImage {
                 url,
@@ -98,6 +109,95 @@ impl MediaAction {
 
                 cache.textures_cache.move_to_loaded(&url);
             }
+            MediaAction::Save { url } => save_media(ctx, url),
+        }
+    }
+}
+
+/// Fetch `url` again rather than copying it out of the cache, the cache
+/// only has our re-encoded copy
+fn save_media(ctx: &egui::Context, url: String) {
+    let Some(dir) = dirs::download_dir().or_else(dirs::home_dir) else {
+        crate::toast::error(ctx, "Couldn't find a downloads folder");
+        return;
+    };
+
+    let ctx = ctx.clone();
+    ehttp::fetch(ehttp::Request::get(&url), move |response| {
+        let result = response.and_then(|response| {
+            if !response.ok {
+                return Err(format!("{} {}", response.status, response.status_text));
+            }
+
+            let path = unused_path(&dir, &download_file_name(&url));
+            std::fs::write(&path, &response.bytes).map_err(|e| e.to_string())?;
+            Ok(path)
+        });
+
+        match result {
+            Ok(path) => crate::toast::info(&ctx, format!("Saved to {}", path.display())),
+            Err(e) => {
+                error!("couldn't save {url}: {e}");
+                crate::toast::error(&ctx, format!("Couldn't save image: {e}"));
+            }
         }
+    });
+}
+
+/// The last path segment of `url`, or a generic name when there isn't a
+/// usable one
+fn download_file_name(url: &str) -> String {
+    let name = url::Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            url.path_segments()
+                .and_then(|mut segments| segments.next_back().map(str::to_owned))
+        })
+        .unwrap_or_default();
+
+    let name: String = name
+        .chars()
+        .filter(|c| !matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+        .collect();
+
+    if name.is_empty() || name.starts_with('.') {
+        "image".to_owned()
+    } else {
+        name
+    }
+}
+
+/// `name` in `dir`, numbered if a file by that name is already there
+fn unused_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+
+    let name = Path::new(name);
+    let stem = name.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+    let ext = name.extension().and_then(|e| e.to_str());
+    (1..)
+        .map(|n| match ext {
+            Some(ext) => dir.join(format!("{stem}-{n}.{ext}")),
+            None => dir.join(format!("{stem}-{n}")),
+        })
+        .find(|path| !path.exists())
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_download_file_name() {
+        assert_eq!(
+            download_file_name("https://example.com/a/cat.jpg?size=large"),
+            "cat.jpg"
+        );
+        assert_eq!(download_file_name("https://example.com/"), "image");
+        assert_eq!(download_file_name("https://example.com/.hidden"), "image");
+        assert_eq!(download_file_name("not a url"), "image");
     }
 }
@@ -1,23 +1,47 @@
-use enostr::{ClientMessage, NoteId, Pubkey, RelayPool};
-use nostrdb::{Note, NoteKey};
+use enostr::{ClientMessage, RelayPool};
+use nostr::nips::nip19::{FromBech32, Nip19};
+use nostrdb::{Ndb, Note, NoteKey, Transaction};
 use tracing::error;
 
+use crate::name::get_display_name;
+use crate::nip19::{self, Nip19Kind};
+
 /// When broadcasting notes, this determines whether to broadcast
-/// over the local network via multicast, or globally
+/// over the local network via multicast, or to which relays
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum BroadcastContext {
     LocalNetwork,
     Everywhere,
+    /// The selected account's write relays. The app knows which those
+    /// are and turns this into [`BroadcastContext::Relays`].
+    WriteRelays,
+    Relays(Vec<String>),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 #[allow(clippy::enum_variant_names)]
 pub enum NoteContextSelection {
+    /// The content with profile mentions swapped for names
     CopyText,
-    CopyPubkey,
-    CopyNoteId,
+    /// The content exactly as it was published
+    CopyRawText,
+    /// The note, or its author, as a NIP-19 entity. With `relays`, the
+    /// relays we saw the note on go in as hints.
+    CopyBech32 {
+        kind: Nip19Kind,
+        relays: bool,
+    },
+    /// An nevent or naddr for the note, with relay hints
+    CopyNoteLink,
+    /// An njump.me page for the note, for people who aren't on nostr
+    CopyWebLink,
+    OpenWebLink,
     CopyNoteJSON,
     Broadcast(BroadcastContext),
+    MuteThread,
+    MuteUser,
+    /// Open the raw event, tags and relays for debugging
+    Inspect,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -27,7 +51,14 @@ pub struct ContextSelection {
 }
 
 impl NoteContextSelection {
-    pub fn process(&self, ui: &mut egui::Ui, note: &Note<'_>, pool: &mut RelayPool) {
+    pub fn process(
+        &self,
+        ui: &mut egui::Ui,
+        ndb: &Ndb,
+        txn: &Transaction,
+        note: &Note<'_>,
+        pool: &mut RelayPool,
+    ) {
         match self {
             NoteContextSelection::Broadcast(context) => {
                 tracing::info!("Broadcasting note {}", hex::encode(note.id()));
@@ -36,28 +67,159 @@ impl NoteContextSelection {
                         pool.send_to(&ClientMessage::event(note).unwrap(), "multicast");
                     }
 
-                    BroadcastContext::Everywhere => {
+                    // without an account to pick write relays from, every
+                    // relay we're connected to will do
+                    BroadcastContext::Everywhere | BroadcastContext::WriteRelays => {
                         pool.send(&ClientMessage::event(note).unwrap());
                     }
+
+                    BroadcastContext::Relays(relays) => {
+                        let msg = ClientMessage::event(note).unwrap();
+                        for relay in relays {
+                            pool.send_to_ignoring_roles(&msg, relay);
+                        }
+                        let message = match relays.len() {
+                            1 => "Sent to 1 relay".to_owned(),
+                            n => format!("Sent to {n} relays"),
+                        };
+                        crate::toast::info(ui.ctx(), message);
+                    }
                 }
             }
             NoteContextSelection::CopyText => {
-                ui.ctx().copy_text(note.content().to_string());
+                let text = resolve_mentions(note.content(), |pubkey| {
+                    let profile = ndb.get_profile_by_pubkey(txn, pubkey).ok();
+                    let name = get_display_name(profile.as_ref());
+                    name.display_name.or(name.username).map(str::to_owned)
+                });
+                ui.ctx().copy_text(text);
             }
-            NoteContextSelection::CopyPubkey => {
-                if let Some(bech) = Pubkey::new(*note.pubkey()).npub() {
-                    ui.ctx().copy_text(bech);
-                }
+            NoteContextSelection::CopyRawText => {
+                ui.ctx().copy_text(note.content().to_string());
             }
-            NoteContextSelection::CopyNoteId => {
-                if let Some(bech) = NoteId::new(*note.id()).to_bech() {
-                    ui.ctx().copy_text(bech);
+            NoteContextSelection::CopyBech32 { kind, relays } => {
+                let hints = if *relays {
+                    nip19::relay_hints(note, txn)
+                } else {
+                    Vec::new()
+                };
+                match nip19::encode_note(note, *kind, &hints) {
+                    Some(bech) => ui.ctx().copy_text(bech),
+                    None => error!("couldn't encode note as {}", kind.label()),
                 }
             }
+            NoteContextSelection::CopyNoteLink => match nip19::note_link(note, txn) {
+                Some(link) => ui.ctx().copy_text(link),
+                None => error!("couldn't make a link for note"),
+            },
+            NoteContextSelection::CopyWebLink => match nip19::note_link(note, txn) {
+                Some(link) => ui.ctx().copy_text(nip19::njump_url(&link)),
+                None => error!("couldn't make a link for note"),
+            },
+            NoteContextSelection::OpenWebLink => match nip19::note_link(note, txn) {
+                Some(link) => ui
+                    .ctx()
+                    .open_url(egui::OpenUrl::new_tab(nip19::njump_url(&link))),
+                None => error!("couldn't make a link for note"),
+            },
             NoteContextSelection::CopyNoteJSON => match note.json() {
                 Ok(json) => ui.ctx().copy_text(json),
                 Err(err) => error!("error copying note json: {err}"),
             },
+            // muting needs an account to publish the mute list with and
+            // inspecting opens a route, the app handles them before we
+            // get here
+            NoteContextSelection::MuteThread
+            | NoteContextSelection::MuteUser
+            | NoteContextSelection::Inspect => {}
+        }
+    }
+}
+
+const MENTION_PREFIX: &str = "nostr:";
+
+/// The next `nostr:` mention in `text`: where it starts, and the bech32
+/// entity after the prefix
+fn next_mention(text: &str) -> Option<(usize, &str)> {
+    let start = text.find(MENTION_PREFIX)?;
+    let after = &text[start + MENTION_PREFIX.len()..];
+    let len = after
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(after.len());
+    Some((start, &after[..len]))
+}
+
+/// The pubkey of an npub or nprofile
+fn mention_pubkey(bech: &str) -> Option<[u8; 32]> {
+    match Nip19::from_bech32(bech) {
+        Ok(Nip19::Pubkey(pubkey)) => Some(pubkey.to_bytes()),
+        Ok(Nip19::Profile(profile)) => Some(profile.public_key.to_bytes()),
+        _ => None,
+    }
+}
+
+/// Replace `nostr:npub…` and `nostr:nprofile…` mentions in `content` with
+/// `@name`. Mentions `name_of` doesn't know stay as they are.
+pub fn resolve_mentions(content: &str, name_of: impl Fn(&[u8; 32]) -> Option<String>) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some((start, bech)) = next_mention(rest) {
+        out.push_str(&rest[..start]);
+
+        match mention_pubkey(bech).and_then(|pk| name_of(&pk)) {
+            Some(name) => {
+                out.push('@');
+                out.push_str(&name);
+            }
+            None => {
+                out.push_str(MENTION_PREFIX);
+                out.push_str(bech);
+            }
         }
+
+        rest = &rest[start + MENTION_PREFIX.len() + bech.len()..];
+    }
+    out.push_str(rest);
+
+    out
+}
+
+/// Pubkeys of the profiles mentioned in `content`
+pub fn profile_mentions(content: &str) -> Vec<[u8; 32]> {
+    let mut pubkeys = Vec::new();
+    let mut rest = content;
+    while let Some((start, bech)) = next_mention(rest) {
+        pubkeys.extend(mention_pubkey(bech));
+        rest = &rest[start + MENTION_PREFIX.len() + bech.len()..];
+    }
+    pubkeys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enostr::Pubkey;
+
+    const JB55: &str = "npub1xtscya34g58tk0z605fvr788k263gsu6cy9x0mhnm87echrgufzsevkk5s";
+
+    #[test]
+    fn test_resolve_mentions() {
+        let content = format!("gm nostr:{JB55}, and nostr:npub1bogus. nostr:{JB55}");
+        let resolved = resolve_mentions(&content, |_| Some("jb55".to_owned()));
+        assert_eq!(resolved, "gm @jb55, and nostr:npub1bogus. @jb55");
+    }
+
+    #[test]
+    fn test_profile_mentions() {
+        let content = format!("gm nostr:{JB55} and nostr:note1bogus");
+        let pubkeys = profile_mentions(&content);
+        assert_eq!(pubkeys.len(), 1);
+        assert_eq!(Pubkey::new(pubkeys[0]).npub().as_deref(), Some(JB55));
+    }
+
+    #[test]
+    fn test_resolve_mentions_unknown_profile() {
+        let content = format!("hi nostr:{JB55}");
+        assert_eq!(resolve_mentions(&content, |_| None), content);
     }
 }
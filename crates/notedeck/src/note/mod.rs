@@ -2,16 +2,22 @@ mod action;
 mod context;
 
 pub use action::{MediaAction, NoteAction, ZapAction, ZapTargetAmount};
-pub use context::{BroadcastContext, ContextSelection, NoteContextSelection};
+pub use context::{profile_mentions, BroadcastContext, ContextSelection, NoteContextSelection};
 
 use crate::JobPool;
-use crate::{notecache::NoteCache, zaps::Zaps, Images};
-use enostr::{NoteId, RelayPool};
+use crate::{
+    notecache::NoteCache, zaps::Zaps, AccountColors, Counts, Images, PaidContent, Polls, ZapGoals,
+};
+use enostr::{NoteId, Pubkey, RelayPool};
 use nostrdb::{Ndb, Note, NoteKey, QueryResult, Transaction};
 use std::borrow::Borrow;
 use std::cmp::Ordering;
 use std::fmt;
 
+/// How many lines of a long note timelines show unless the user picks
+/// something else
+pub const DEFAULT_TRUNCATE_LINES: usize = 8;
+
 /// Aggregates dependencies to reduce the number of parameters
 /// passed to inner UI elements, minimizing prop drilling.
 pub struct NoteContext<'d> {
@@ -21,7 +27,19 @@ pub struct NoteContext<'d> {
     pub zaps: &'d mut Zaps,
     pub pool: &'d mut RelayPool,
     pub job_pool: &'d mut JobPool,
+    pub counts: &'d mut Counts,
+    pub polls: &'d mut Polls,
+    pub zap_goals: &'d mut ZapGoals,
+    pub paid_content: &'d mut PaidContent,
     pub current_account_has_wallet: bool,
+    pub current_account_can_sign: bool,
+
+    /// The account this column acts as, whose poll votes get marked
+    pub acting_pubkey: Option<Pubkey>,
+    pub account_colors: AccountColors,
+
+    /// Where notes that truncate get cut off, None to show them in full
+    pub truncate_lines: Option<usize>,
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
@@ -188,3 +206,64 @@ where
             |rnid| Ok(RootNoteId::new_unsafe(rnid.id)),
         )
 }
+
+/// The unix time a note asked to be dropped at, from its NIP-40
+/// `expiration` tag
+pub fn expiration(note: &Note) -> Option<u64> {
+    note.tags().into_iter().find_map(|tag| {
+        if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some("expiration") {
+            return None;
+        }
+        tag.get_unchecked(1).variant().str()?.parse().ok()
+    })
+}
+
+/// Whether a note's expiration has passed. Expired notes shouldn't be
+/// shown, relays are free to have dropped them already.
+pub fn is_expired(note: &Note) -> bool {
+    expiration(note).is_some_and(|at| at <= crate::time::unix_now())
+}
+
+/// Whether an `EVENT` message from a relay carries a note that has
+/// already expired. Relays should drop these but not all do, and nostrdb
+/// can't delete a note once it has ingested it, so we don't let them in.
+pub fn event_expired(msg: &str) -> bool {
+    // hardly anything expires, don't parse everything else
+    if !msg.contains("\"expiration\"") {
+        return false;
+    }
+
+    let Ok(serde_json::Value::Array(parts)) = serde_json::from_str(msg) else {
+        return false;
+    };
+    let Some(tags) = parts
+        .get(2)
+        .and_then(|event| event.get("tags"))
+        .and_then(|tags| tags.as_array())
+    else {
+        return false;
+    };
+
+    tags.iter()
+        .filter_map(|tag| tag.as_array())
+        .find(|tag| tag.first().and_then(|name| name.as_str()) == Some("expiration"))
+        .and_then(|tag| tag.get(1)?.as_str()?.parse::<u64>().ok())
+        .is_some_and(|at| at <= crate::time::unix_now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_expired() {
+        let event = |tags: &str| {
+            format!(r#"["EVENT","sub",{{"kind":1,"tags":{tags},"content":"expiration"}}]"#)
+        };
+
+        assert!(event_expired(&event(r#"[["expiration","1600000000"]]"#)));
+        assert!(!event_expired(&event(r#"[["expiration","99999999999"]]"#)));
+        assert!(!event_expired(&event(r#"[["t","expiration"]]"#)));
+        assert!(!event_expired(&event("[]")));
+    }
+}
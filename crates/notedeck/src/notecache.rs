@@ -1,4 +1,4 @@
-use crate::{time_ago_since, TimeCached};
+use crate::{note::profile_mentions, time_ago_since, TimeCached};
 use nostrdb::{Note, NoteKey, NoteReply, NoteReplyBuf};
 use std::collections::HashMap;
 use std::time::Duration;
@@ -34,6 +34,9 @@ impl NoteCache {
 pub struct CachedNote {
     reltime: TimeCached<String>,
     pub reply: NoteReplyBuf,
+
+    /// Everyone the note mentions, in `p` tags or its content
+    mentions: Vec<[u8; 32]>,
 }
 
 impl CachedNote {
@@ -44,7 +47,18 @@ impl CachedNote {
             Box::new(move || time_ago_since(created_at)),
         );
         let reply = NoteReply::new(note.tags()).to_owned();
-        CachedNote { reltime, reply }
+        let mentions = mentioned_pubkeys(note);
+        CachedNote {
+            reltime,
+            reply,
+            mentions,
+        }
+    }
+
+    /// Whether the note mentions `pubkey`. Kept per pubkey rather than per
+    /// account, so it stays right when switching accounts.
+    pub fn mentions(&self, pubkey: &[u8; 32]) -> bool {
+        self.mentions.contains(pubkey)
     }
 
     pub fn reltime_str_mut(&mut self) -> &str {
@@ -55,3 +69,19 @@ impl CachedNote {
         self.reltime.get().map(|x| x.as_str())
     }
 }
+
+fn mentioned_pubkeys(note: &Note) -> Vec<[u8; 32]> {
+    let mut pubkeys = profile_mentions(note.content());
+    for tag in note.tags() {
+        if tag.count() < 2 || tag.get_str(0) != Some("p") {
+            continue;
+        }
+        if let Some(pubkey) = tag.get_id(1) {
+            pubkeys.push(*pubkey);
+        }
+    }
+
+    pubkeys.sort_unstable();
+    pubkeys.dedup();
+    pubkeys
+}
@@ -0,0 +1,430 @@
+//! Notes that should get the selected account's attention as they come
+//! in: mentions, DMs and zaps. This only tells apps what arrived, the
+//! notifications timeline is still where they're read. The same filter
+//! settings decide what that timeline shows. Replies in threads the
+//! account follows count as mentions too.
+
+use enostr::{Pubkey, RelayPool};
+use nostrdb::{Filter, Ndb, Note, NoteKey, Subscription, Transaction};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::followed_threads::thread_replies_filter;
+use crate::time::unix_now;
+use crate::{MuteFun, WebOfTrust};
+
+const DM_KIND: u32 = 4;
+const REPOST_KIND: u32 = 6;
+const REACTION_KIND: u32 = 7;
+const ZAP_RECEIPT_KIND: u32 = 9735;
+
+/// What the notifications timeline subscribes to: mentions and replies,
+/// reposts, reactions and zaps
+pub const NOTIFICATION_TIMELINE_KINDS: [u64; 4] = [
+    1,
+    REPOST_KIND as u64,
+    REACTION_KIND as u64,
+    ZAP_RECEIPT_KIND as u64,
+];
+
+/// Relays can be a little behind, notes older than this when we started
+/// listening are catching up, not news
+const CATCH_UP_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationKind {
+    Mention,
+    DirectMessage,
+    Zap,
+}
+
+impl NotificationKind {
+    fn from_note_kind(kind: u32) -> Option<Self> {
+        match kind {
+            1 => Some(Self::Mention),
+            DM_KIND => Some(Self::DirectMessage),
+            ZAP_RECEIPT_KIND => Some(Self::Zap),
+            _ => None,
+        }
+    }
+}
+
+/// Who gets to notify us, by how far they are in the follow graph
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuthorGroup {
+    #[default]
+    Everyone,
+    Follows,
+    FollowsOfFollows,
+    Nobody,
+}
+
+impl AuthorGroup {
+    pub const ALL: [AuthorGroup; 4] = [
+        AuthorGroup::Everyone,
+        AuthorGroup::Follows,
+        AuthorGroup::FollowsOfFollows,
+        AuthorGroup::Nobody,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AuthorGroup::Everyone => "Everyone",
+            AuthorGroup::Follows => "People I follow",
+            AuthorGroup::FollowsOfFollows => "Follows of follows",
+            AuthorGroup::Nobody => "Nobody",
+        }
+    }
+
+    /// How far into the follow graph we need to see to tell
+    fn hops(self) -> u8 {
+        match self {
+            AuthorGroup::Follows => 1,
+            AuthorGroup::FollowsOfFollows => 2,
+            AuthorGroup::Everyone | AuthorGroup::Nobody => 0,
+        }
+    }
+
+    /// Whether someone `distance` follows away from us is in the group.
+    /// None is further than the graph reaches.
+    fn contains_distance(self, distance: Option<u8>) -> bool {
+        match self {
+            AuthorGroup::Everyone => true,
+            AuthorGroup::Nobody => false,
+            _ => distance.is_some_and(|d| d <= self.hops()),
+        }
+    }
+
+    pub fn contains(self, wot: &WebOfTrust, author: &[u8; 32]) -> bool {
+        self.contains_distance(wot.distance(author))
+    }
+}
+
+/// Who each kind of notification is allowed from, and what else to
+/// leave out
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationFilterSettings {
+    pub mention: AuthorGroup,
+    pub dm: AuthorGroup,
+    pub zap: AuthorGroup,
+
+    /// Zaps smaller than this don't notify. 0 lets every zap through.
+    pub min_zap_sats: u64,
+
+    /// Only in the notifications timeline, they never raise an alert
+    pub hide_reactions: bool,
+    pub hide_reposts: bool,
+
+    /// People whose notifications we never want, without muting them
+    /// everywhere else
+    pub muted: Vec<Pubkey>,
+}
+
+impl NotificationFilterSettings {
+    pub fn group(&self, kind: NotificationKind) -> AuthorGroup {
+        match kind {
+            NotificationKind::Mention => self.mention,
+            NotificationKind::DirectMessage => self.dm,
+            NotificationKind::Zap => self.zap,
+        }
+    }
+
+    /// Whether a notification from `from` about `kind` gets through.
+    /// `zap_msats` is how much a zap was for, if we could tell.
+    pub fn allows(
+        &self,
+        wot: &WebOfTrust,
+        kind: NotificationKind,
+        from: &Pubkey,
+        zap_msats: Option<u64>,
+    ) -> bool {
+        if self.muted.contains(from) || !self.group(kind).contains(wot, from.bytes()) {
+            return false;
+        }
+        if kind == NotificationKind::Zap && self.min_zap_sats > 0 {
+            return zap_msats.is_some_and(|msats| msats / 1000 >= self.min_zap_sats);
+        }
+        true
+    }
+
+    /// Whether the notifications timeline shows `note`. Reactions and
+    /// reposts go by who can mention us.
+    pub fn shows(&self, wot: &WebOfTrust, note: &Note) -> bool {
+        match note.kind() {
+            REACTION_KIND if self.hide_reactions => false,
+            REPOST_KIND if self.hide_reposts => false,
+            ZAP_RECEIPT_KIND => zap_sender(note).is_some_and(|from| {
+                self.allows(
+                    wot,
+                    NotificationKind::Zap,
+                    &Pubkey::new(from),
+                    zap_msats(note),
+                )
+            }),
+            kind => {
+                let kind =
+                    NotificationKind::from_note_kind(kind).unwrap_or(NotificationKind::Mention);
+                self.allows(wot, kind, &Pubkey::new(*note.pubkey()), None)
+            }
+        }
+    }
+
+    /// How much of the follow graph the filters need built
+    pub fn hops(&self) -> u8 {
+        [self.mention, self.dm, self.zap]
+            .into_iter()
+            .map(AuthorGroup::hops)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub kind: NotificationKind,
+
+    /// Who it's from. For zaps that's whoever asked for the zap, not the
+    /// wallet service that signed the receipt.
+    pub from: Pubkey,
+    pub note_key: NoteKey,
+
+    /// How much a zap was for, from its zap request
+    pub zap_msats: Option<u64>,
+}
+
+/// Watches for notifications addressed to the selected account
+#[derive(Default)]
+pub struct NotificationWatcher {
+    account: Option<Pubkey>,
+    since: u64,
+    sub: Option<Subscription>,
+    remote_subid: Option<String>,
+
+    /// Roots of the followed threads we're listening in
+    threads: Vec<[u8; 32]>,
+
+    filters: NotificationFilterSettings,
+    filters_changed: bool,
+}
+
+impl NotificationWatcher {
+    pub fn new(filters: NotificationFilterSettings) -> Self {
+        Self {
+            filters,
+            ..Default::default()
+        }
+    }
+
+    pub fn filters(&self) -> &NotificationFilterSettings {
+        &self.filters
+    }
+
+    pub fn set_filters(&mut self, filters: NotificationFilterSettings) {
+        if filters != self.filters {
+            self.filters = filters;
+            self.filters_changed = true;
+        }
+    }
+
+    /// Whether the filters were changed since the last call, and should
+    /// be saved
+    pub fn take_filters_changed(&mut self) -> bool {
+        std::mem::take(&mut self.filters_changed)
+    }
+
+    /// Follow the selected account, and the `threads` it follows, and
+    /// return what arrived for it since the last call. Called every frame.
+    pub fn update(
+        &mut self,
+        ndb: &mut Ndb,
+        pool: &mut RelayPool,
+        selected: Option<&Pubkey>,
+        threads: &[[u8; 32]],
+        is_muted: &MuteFun,
+        wot: &WebOfTrust,
+    ) -> Vec<Notification> {
+        if self.account.as_ref() != selected || self.threads != threads {
+            self.stop(ndb, pool);
+            self.account = selected.copied();
+            self.threads = threads.to_vec();
+            if let Some(account) = self.account {
+                self.start(ndb, pool, &account);
+            }
+        }
+
+        let (Some(sub), Some(account)) = (self.sub, self.account) else {
+            return vec![];
+        };
+
+        let keys = ndb.poll_for_notes(sub, 100);
+        if keys.is_empty() {
+            return vec![];
+        }
+        let Ok(txn) = Transaction::new(ndb) else {
+            return vec![];
+        };
+
+        let oldest = self.since.saturating_sub(CATCH_UP_SECS);
+        keys.into_iter()
+            .filter_map(|key| {
+                let note = ndb.get_note_by_key(&txn, key).ok()?;
+                notification(&note, &account, oldest, is_muted)
+            })
+            .filter(|n| self.filters.allows(wot, n.kind, &n.from, n.zap_msats))
+            .collect()
+    }
+
+    fn filters(account: &Pubkey, threads: &[[u8; 32]], since: u64) -> Vec<Filter> {
+        let mut filters = vec![Filter::new()
+            .kinds([1, DM_KIND as u64, ZAP_RECEIPT_KIND as u64])
+            .pubkeys([account.bytes()])
+            .since(since)
+            .build()];
+        if !threads.is_empty() {
+            filters.push(thread_replies_filter(threads).since(since).build());
+        }
+        filters
+    }
+
+    fn start(&mut self, ndb: &mut Ndb, pool: &mut RelayPool, account: &Pubkey) {
+        self.since = unix_now();
+        let filters = Self::filters(
+            account,
+            &self.threads,
+            self.since.saturating_sub(CATCH_UP_SECS),
+        );
+
+        match ndb.subscribe(&filters) {
+            Ok(sub) => self.sub = Some(sub),
+            Err(e) => {
+                error!("notification subscription: {e}");
+                return;
+            }
+        }
+
+        let subid = format!("notifications-{}", Uuid::new_v4());
+        debug!("watching for notifications in {subid}");
+        pool.subscribe(subid.clone(), filters);
+        self.remote_subid = Some(subid);
+    }
+
+    fn stop(&mut self, ndb: &mut Ndb, pool: &mut RelayPool) {
+        if let Some(sub) = self.sub.take() {
+            if let Err(e) = ndb.unsubscribe(sub) {
+                error!("notification unsubscribe: {e}");
+            }
+        }
+        if let Some(subid) = self.remote_subid.take() {
+            pool.unsubscribe(subid);
+        }
+    }
+}
+
+fn notification(
+    note: &Note,
+    account: &Pubkey,
+    oldest: u64,
+    is_muted: &MuteFun,
+) -> Option<Notification> {
+    let kind = NotificationKind::from_note_kind(note.kind())?;
+    if note.created_at() < oldest || note.pubkey() == account.bytes() {
+        return None;
+    }
+    if is_muted(note, note.id()) || crate::note::is_expired(note) {
+        return None;
+    }
+
+    let from = match kind {
+        NotificationKind::Zap => zap_sender(note)?,
+        _ => *note.pubkey(),
+    };
+    if &from == account.bytes() {
+        return None;
+    }
+
+    Some(Notification {
+        kind,
+        from: Pubkey::new(from),
+        note_key: note.key()?,
+        zap_msats: (kind == NotificationKind::Zap)
+            .then(|| zap_msats(note))
+            .flatten(),
+    })
+}
+
+/// Who sent a zap: the author of the zap request the receipt carries in
+/// its description tag
+pub fn zap_sender(receipt: &Note) -> Option<[u8; 32]> {
+    zap_request_author(zap_request(receipt)?)
+}
+
+/// How much a zap was for, from the `amount` tag of its zap request
+pub fn zap_msats(receipt: &Note) -> Option<u64> {
+    let request: serde_json::Value = serde_json::from_str(zap_request(receipt)?).ok()?;
+    zap_request_msats(&request)
+}
+
+pub(crate) fn zap_request_msats(request: &serde_json::Value) -> Option<u64> {
+    request
+        .get("tags")?
+        .as_array()?
+        .iter()
+        .find_map(|tag| match tag.as_array()?.as_slice() {
+            [name, amount, ..] if name.as_str() == Some("amount") => amount.as_str()?.parse().ok(),
+            _ => None,
+        })
+}
+
+/// The zap request json a zap receipt carries
+pub(crate) fn zap_request<'a>(receipt: &Note<'a>) -> Option<&'a str> {
+    receipt.tags().into_iter().find_map(|tag| {
+        if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some("description") {
+            return None;
+        }
+        tag.get_unchecked(1).variant().str()
+    })
+}
+
+fn zap_request_author(zap_request: &str) -> Option<[u8; 32]> {
+    let request: serde_json::Value = serde_json::from_str(zap_request).ok()?;
+    let pubkey = request.get("pubkey")?.as_str()?;
+    hex::decode(pubkey).ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zap_sender_comes_from_the_request() {
+        let pubkey = "32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245";
+        let request = format!(r#"{{"kind":9734,"pubkey":"{pubkey}","content":"","tags":[]}}"#);
+
+        let author = zap_request_author(&request).expect("author");
+        assert_eq!(hex::encode(author), pubkey);
+        assert_eq!(zap_request_author(r#"{"kind":9734}"#), None);
+        assert_eq!(zap_request_author("not json"), None);
+    }
+
+    #[test]
+    fn zap_amount_from_request() {
+        let request: serde_json::Value =
+            serde_json::from_str(r#"{"tags":[["p","ab"],["amount","21000"]]}"#).unwrap();
+        assert_eq!(zap_request_msats(&request), Some(21000));
+
+        let request: serde_json::Value = serde_json::from_str(r#"{"tags":[]}"#).unwrap();
+        assert_eq!(zap_request_msats(&request), None);
+    }
+
+    #[test]
+    fn author_groups_by_distance() {
+        assert!(AuthorGroup::Everyone.contains_distance(None));
+        assert!(!AuthorGroup::Nobody.contains_distance(Some(1)));
+        assert!(AuthorGroup::Follows.contains_distance(Some(1)));
+        assert!(!AuthorGroup::Follows.contains_distance(Some(2)));
+        assert!(AuthorGroup::FollowsOfFollows.contains_distance(Some(2)));
+        assert!(!AuthorGroup::FollowsOfFollows.contains_distance(None));
+    }
+}
@@ -0,0 +1,318 @@
+//! Desktop notifications for mentions, DMs and zaps, shown by the OS
+//! through whatever the platform already has for it, like `notify-send`.
+//! Where the platform can tell us the notification was clicked, the note
+//! or profile it's about is opened the same way a `nostr:` link is.
+
+use std::sync::mpsc;
+
+use nostrdb::{Ndb, Note, Transaction};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+
+use crate::name::get_display_name;
+use crate::nip19::NostrUri;
+use crate::notifications::{zap_request, Notification, NotificationKind};
+use crate::quiet_hours::{Alert, QuietHours};
+
+/// Longest note preview, in chars
+const PREVIEW_CHARS: usize = 140;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OsNotificationSettings {
+    pub enabled: bool,
+
+    /// Show what the note says, not just who it's from
+    pub previews: bool,
+}
+
+impl Default for OsNotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            previews: true,
+        }
+    }
+}
+
+/// What a notification says and what clicking it opens
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Popup {
+    title: String,
+    body: String,
+    open: NostrUri,
+}
+
+pub struct OsNotifications {
+    settings: OsNotificationSettings,
+    settings_changed: bool,
+
+    /// Notifications that were clicked, sent back from the threads
+    /// waiting on them
+    clicked_tx: mpsc::Sender<NostrUri>,
+    clicked_rx: mpsc::Receiver<NostrUri>,
+}
+
+impl OsNotifications {
+    pub fn new(settings: OsNotificationSettings) -> Self {
+        let (clicked_tx, clicked_rx) = mpsc::channel();
+        Self {
+            settings,
+            settings_changed: false,
+            clicked_tx,
+            clicked_rx,
+        }
+    }
+
+    pub fn settings(&self) -> OsNotificationSettings {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: OsNotificationSettings) {
+        if settings != self.settings {
+            self.settings = settings;
+            self.settings_changed = true;
+        }
+    }
+
+    /// Whether the settings were changed since the last call, and should
+    /// be saved
+    pub fn take_settings_changed(&mut self) -> bool {
+        std::mem::take(&mut self.settings_changed)
+    }
+
+    /// Show `notification` on the desktop, unless they're off or it's
+    /// quiet hours
+    pub fn notify(
+        &mut self,
+        ctx: &egui::Context,
+        ndb: &Ndb,
+        notification: &Notification,
+        quiet_hours: &QuietHours,
+    ) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        let alert = match notification.kind {
+            NotificationKind::DirectMessage => Alert::DirectMessage {
+                from: &notification.from,
+            },
+            _ => Alert::Other,
+        };
+        if !quiet_hours.allows(alert) {
+            return;
+        }
+
+        let Ok(txn) = Transaction::new(ndb) else {
+            return;
+        };
+        let Ok(note) = ndb.get_note_by_key(&txn, notification.note_key) else {
+            return;
+        };
+        let name = ndb
+            .get_profile_by_pubkey(&txn, notification.from.bytes())
+            .ok();
+        let name = get_display_name(name.as_ref()).name().to_owned();
+        let popup = popup(notification, &note, &name, self.settings.previews);
+
+        let clicked = self.clicked_tx.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            if show(&popup.title, &popup.body) {
+                debug!("notification clicked, opening {:?}", popup.open);
+                if clicked.send(popup.open).is_ok() {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                    ctx.request_repaint();
+                }
+            }
+        });
+    }
+
+    /// What clicked notifications asked to open since the last call
+    pub fn take_clicked(&self) -> Vec<NostrUri> {
+        self.clicked_rx.try_iter().collect()
+    }
+}
+
+fn popup(notification: &Notification, note: &Note, name: &str, previews: bool) -> Popup {
+    let profile = NostrUri::Profile {
+        pubkey: *notification.from.bytes(),
+        relays: vec![],
+    };
+
+    match notification.kind {
+        NotificationKind::Mention => {
+            let replied = note
+                .tags()
+                .into_iter()
+                .any(|tag| tag.count() >= 2 && tag.get_unchecked(0).variant().str() == Some("e"));
+            let title = if replied {
+                format!("{name} replied to you")
+            } else {
+                format!("{name} mentioned you")
+            };
+            Popup {
+                title,
+                body: if previews {
+                    preview(note.content())
+                } else {
+                    String::new()
+                },
+                open: NostrUri::Note {
+                    id: *note.id(),
+                    relays: vec![],
+                },
+            }
+        }
+
+        // the content is encrypted, there's nothing to preview
+        NotificationKind::DirectMessage => Popup {
+            title: format!("{name} sent you a message"),
+            body: String::new(),
+            open: profile,
+        },
+
+        NotificationKind::Zap => {
+            let request = zap_request(note)
+                .and_then(|request| serde_json::from_str::<serde_json::Value>(request).ok());
+            let title = match notification.zap_msats {
+                Some(msats) => format!("{name} zapped you {} sats", msats / 1000),
+                None => format!("{name} zapped you"),
+            };
+            let comment = request
+                .as_ref()
+                .and_then(|request| request.get("content")?.as_str())
+                .filter(|_| previews)
+                .map(preview)
+                .unwrap_or_default();
+            let open = zapped_note(note)
+                .map(|id| NostrUri::Note { id, relays: vec![] })
+                .unwrap_or(profile);
+            Popup {
+                title,
+                body: comment,
+                open,
+            }
+        }
+    }
+}
+
+/// The note a zap receipt is for, if it wasn't a zap to the profile
+fn zapped_note(receipt: &Note) -> Option<[u8; 32]> {
+    receipt.tags().into_iter().find_map(|tag| {
+        if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some("e") {
+            return None;
+        }
+        tag.get_unchecked(1).variant().id().copied()
+    })
+}
+
+/// The start of `content` on one line
+fn preview(content: &str) -> String {
+    let line = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= PREVIEW_CHARS {
+        return line;
+    }
+    let mut cut: String = line.chars().take(PREVIEW_CHARS - 1).collect();
+    cut.push('…');
+    cut
+}
+
+/// Show a notification and wait around for it to be dismissed. Returns
+/// whether it was clicked, as far as the platform lets us know.
+fn show(title: &str, body: &str) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        // --wait and --action need libnotify 0.7.9, older ones just
+        // show it and we never hear about clicks
+        let output = std::process::Command::new("notify-send")
+            .args(["--app-name=Notedeck", "--action=default=Open", "--wait"])
+            .arg(title)
+            .arg(body)
+            .output();
+        match output {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).trim() == "default",
+            Err(e) => {
+                error!("couldn't run notify-send: {e}");
+                false
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let script = format!(
+            "display notification {} with title {}",
+            applescript_string(body),
+            applescript_string(title)
+        );
+        if let Err(e) = std::process::Command::new("osascript")
+            .args(["-e", &script])
+            .status()
+        {
+            error!("couldn't run osascript: {e}");
+        }
+        false
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $n = New-Object System.Windows.Forms.NotifyIcon; \
+             $n.Icon = [System.Drawing.SystemIcons]::Information; \
+             $n.Visible = $true; \
+             Register-ObjectEvent $n BalloonTipClicked -SourceIdentifier clicked | Out-Null; \
+             $n.ShowBalloonTip(10000, {}, {}, 'None'); \
+             if (Wait-Event clicked -Timeout 15) {{ 'clicked' }}; \
+             $n.Dispose()",
+            powershell_string(title),
+            powershell_string(body)
+        );
+        let output = std::process::Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .output();
+        match output {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).trim() == "clicked",
+            Err(e) => {
+                error!("couldn't show notification: {e}");
+                false
+            }
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        debug!("no desktop notifications on this platform: {title}");
+        let _ = body;
+        false
+    }
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn applescript_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(target_os = "windows")]
+fn powershell_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previews_fit_on_one_line() {
+        assert_eq!(preview("gm\n\nnostr  friends"), "gm nostr friends");
+
+        let long = "a".repeat(PREVIEW_CHARS + 10);
+        let cut = preview(&long);
+        assert_eq!(cut.chars().count(), PREVIEW_CHARS);
+        assert!(cut.ends_with('…'));
+
+        assert_eq!(applescript_string(r#"say "hi""#), r#""say \"hi\"""#);
+    }
+}
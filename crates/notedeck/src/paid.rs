@@ -0,0 +1,410 @@
+//! Paid content. A note with a `paywall` tag is a teaser for something
+//! its author sells. There's no NIP for this, the tag is our own:
+//!
+//! `["paywall", "<price in msats>", "<unlock url>"]`
+//!
+//! - the price is a decimal integer of millisats, more than zero
+//! - the unlock url is http or https
+//! - only the first valid `paywall` tag counts, extra elements are
+//!   ignored, and a note with no valid one is an ordinary note
+//!
+//! Buying it is a zap of the price to the teaser. Once the zap goes
+//! through, the unlock url is fetched with a GET carrying a NIP-98
+//! authorization signed by the buyer. The server checks for the buyer's
+//! zap receipt and answers with the full content, either as
+//! `{"content": "..."}` or NIP-44 encrypted from the author to the buyer
+//! as `{"nip44": "..."}`. It should answer the same buyer every time they
+//! ask, since that's how a purchase is unlocked again after a restart.
+//!
+//! Paid orders are saved, so a teaser bought in an earlier session is
+//! fetched again rather than paid for twice.
+
+use std::collections::HashMap;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use enostr::{FullKeypair, NoteId, Pubkey};
+use nostr::nips::nip44;
+use nostrdb::{Note, NoteBuilder};
+use poll_promise::Promise;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use url::Url;
+
+use crate::zaps::{AnyZapState, NoteZapTargetOwned, ZapTarget, Zaps};
+use crate::Accounts;
+
+pub const PAYWALL_TAG: &str = "paywall";
+
+/// NIP-98 http auth
+const HTTP_AUTH_KIND: u32 = 27235;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paywall {
+    pub price_msats: u64,
+    pub unlock_url: String,
+}
+
+impl Paywall {
+    pub fn from_note(note: &Note) -> Option<Self> {
+        note.tags().into_iter().find_map(|tag| {
+            if tag.count() < 3 || tag.get_unchecked(0).variant().str() != Some(PAYWALL_TAG) {
+                return None;
+            }
+
+            let price_msats = tag.get_unchecked(1).variant().str()?.parse().ok()?;
+            let unlock_url = tag.get_unchecked(2).variant().str()?;
+            let parsed = Url::parse(unlock_url).ok()?;
+            if !matches!(parsed.scheme(), "http" | "https") || price_msats == 0 {
+                return None;
+            }
+
+            Some(Self {
+                price_msats,
+                unlock_url: unlock_url.to_owned(),
+            })
+        })
+    }
+}
+
+/// Where a purchase is at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unlock<'a> {
+    /// Waiting on the zap
+    Paying,
+    Fetching,
+    Unlocked(&'a str),
+    Failed(&'a str),
+}
+
+struct Order {
+    buyer: Pubkey,
+    target: NoteZapTargetOwned,
+    paywall: Paywall,
+}
+
+/// An order whose zap went through, as it's saved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PaidOrder {
+    note_id: NoteId,
+    author: Pubkey,
+    buyer: Pubkey,
+    price_msats: u64,
+    unlock_url: String,
+}
+
+impl From<&Order> for PaidOrder {
+    fn from(order: &Order) -> Self {
+        Self {
+            note_id: order.target.note_id,
+            author: order.target.zap_recipient,
+            buyer: order.buyer,
+            price_msats: order.paywall.price_msats,
+            unlock_url: order.paywall.unlock_url.clone(),
+        }
+    }
+}
+
+impl From<&PaidOrder> for Order {
+    fn from(paid: &PaidOrder) -> Self {
+        Self {
+            buyer: paid.buyer,
+            target: NoteZapTargetOwned {
+                note_id: paid.note_id,
+                zap_recipient: paid.author,
+            },
+            paywall: Paywall {
+                price_msats: paid.price_msats,
+                unlock_url: paid.unlock_url.clone(),
+            },
+        }
+    }
+}
+
+/// Paid orders as they're saved
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SavedPaidOrders(Vec<PaidOrder>);
+
+enum Purchase {
+    Paying(Order),
+    /// The zap went through, the content is next
+    Paid(Order),
+    Fetching(Order, Promise<Result<String, String>>),
+    Unlocked(String),
+    Failed {
+        error: String,
+        /// Set when the zap went through and it's the fetch that failed,
+        /// so trying again doesn't pay twice
+        paid: Option<Order>,
+    },
+}
+
+/// Teasers that are being bought or unlocked this session, their full
+/// content once it's been fetched, and every order that's been paid for
+#[derive(Default)]
+pub struct PaidContent {
+    purchases: HashMap<NoteId, Purchase>,
+    paid: Vec<PaidOrder>,
+    changed: bool,
+}
+
+impl PaidContent {
+    pub fn new(saved: SavedPaidOrders) -> Self {
+        Self {
+            paid: saved.0,
+            ..Default::default()
+        }
+    }
+
+    pub fn to_saved(&self) -> SavedPaidOrders {
+        SavedPaidOrders(self.paid.clone())
+    }
+
+    /// Whether orders were paid since the last call, and should be saved
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Whether `buyer` already paid for `note_id`
+    pub fn is_paid(&self, buyer: &Pubkey, note_id: &NoteId) -> bool {
+        self.paid_order(buyer, note_id).is_some()
+    }
+
+    fn paid_order(&self, buyer: &Pubkey, note_id: &NoteId) -> Option<&PaidOrder> {
+        self.paid
+            .iter()
+            .find(|paid| paid.buyer == *buyer && paid.note_id == *note_id)
+    }
+
+    /// Start buying `target`. Returns whether it needs paying for: if
+    /// so the zap is sent like any other and this waits for it to go
+    /// through, then fetches the content. An order that was already paid
+    /// for, in this session or an earlier one, is fetched straight away.
+    pub fn buy(&mut self, buyer: Pubkey, target: NoteZapTargetOwned, paywall: Paywall) -> bool {
+        let note_id = target.note_id;
+        if let Some(paid) = self.paid_order(&buyer, &note_id) {
+            let order = Order::from(paid);
+            self.purchases.insert(note_id, Purchase::Paid(order));
+            return false;
+        }
+
+        self.purchases.insert(
+            note_id,
+            Purchase::Paying(Order {
+                buyer,
+                target,
+                paywall,
+            }),
+        );
+        true
+    }
+
+    /// Try a failed purchase again. If it was the fetch that failed it's
+    /// fetched again, otherwise it's forgotten so it can be bought again.
+    pub fn retry(&mut self, note_id: &NoteId) {
+        match self.purchases.remove(note_id) {
+            Some(Purchase::Failed {
+                paid: Some(order), ..
+            }) => {
+                self.purchases.insert(*note_id, Purchase::Paid(order));
+            }
+            Some(Purchase::Failed { paid: None, .. }) | None => {}
+            Some(other) => {
+                self.purchases.insert(*note_id, other);
+            }
+        }
+    }
+
+    pub fn state(&self, note_id: &NoteId) -> Option<Unlock<'_>> {
+        Some(match self.purchases.get(note_id)? {
+            Purchase::Paying(_) => Unlock::Paying,
+            Purchase::Paid(_) | Purchase::Fetching(..) => Unlock::Fetching,
+            Purchase::Unlocked(content) => Unlock::Unlocked(content),
+            Purchase::Failed { error, .. } => Unlock::Failed(error),
+        })
+    }
+
+    /// Fetch the content of purchases whose zaps went through, and pick
+    /// up what's been fetched. Called once a frame.
+    pub fn update(&mut self, ctx: &egui::Context, accounts: &Accounts, zaps: &Zaps) {
+        for (id, purchase) in std::mem::take(&mut self.purchases) {
+            let purchase = advance(purchase, ctx, accounts, zaps);
+            if let Purchase::Paid(order) = &purchase {
+                if !self.is_paid(&order.buyer, &id) {
+                    self.paid.push(PaidOrder::from(order));
+                    self.changed = true;
+                }
+            }
+            self.purchases.insert(id, purchase);
+        }
+    }
+}
+
+fn advance(purchase: Purchase, ctx: &egui::Context, accounts: &Accounts, zaps: &Zaps) -> Purchase {
+    match purchase {
+        Purchase::Paying(order) => {
+            let target = ZapTarget::Note((&order.target).into());
+            match zaps.any_zap_state_for(order.buyer.bytes(), target) {
+                Ok(AnyZapState::LocalOnly | AnyZapState::Confirmed) => Purchase::Paid(order),
+                Ok(AnyZapState::None | AnyZapState::Pending) => Purchase::Paying(order),
+                Err(err) => Purchase::Failed {
+                    error: err.to_string(),
+                    paid: None,
+                },
+            }
+        }
+        Purchase::Paid(order) => match accounts.get_full(order.buyer.bytes()) {
+            Some(keypair) => {
+                let promise = fetch_content(ctx, &order, keypair.to_full());
+                Purchase::Fetching(order, promise)
+            }
+            None => Purchase::Failed {
+                error: "Unlocking needs this account's secret key".to_owned(),
+                paid: Some(order),
+            },
+        },
+        Purchase::Fetching(order, promise) => match promise.try_take() {
+            Ok(Ok(content)) => Purchase::Unlocked(content),
+            Ok(Err(error)) => Purchase::Failed {
+                error,
+                paid: Some(order),
+            },
+            Err(promise) => Purchase::Fetching(order, promise),
+        },
+        done @ (Purchase::Unlocked(_) | Purchase::Failed { .. }) => done,
+    }
+}
+
+fn fetch_content(
+    ctx: &egui::Context,
+    order: &Order,
+    buyer: FullKeypair,
+) -> Promise<Result<String, String>> {
+    let (sender, promise) = Promise::new();
+    let ctx = ctx.clone();
+    let paywall = &order.paywall;
+    let author = order.target.zap_recipient;
+
+    let auth = match http_auth(&buyer, &paywall.unlock_url) {
+        Ok(auth) => auth,
+        Err(err) => return Promise::from_ready(Err(err)),
+    };
+    let request = ehttp::Request {
+        headers: ehttp::Headers::new(&[
+            ("Accept", "application/json"),
+            ("Authorization", auth.as_str()),
+        ]),
+        ..ehttp::Request::get(&paywall.unlock_url)
+    };
+
+    debug!("unlocking paid content at {}", paywall.unlock_url);
+    ehttp::fetch(request, move |response| {
+        let content = match response {
+            Ok(resp) if resp.ok => match resp.text() {
+                Some(body) => parse_unlocked(body, |payload| {
+                    let author =
+                        nostr::PublicKey::from_slice(author.bytes()).map_err(|e| e.to_string())?;
+                    nip44::decrypt(&buyer.secret_key, &author, payload).map_err(|e| e.to_string())
+                }),
+                None => Err("The unlock server sent something that isn't text".to_owned()),
+            },
+            Ok(resp) => Err(format!("The unlock server said {}", resp.status)),
+            Err(err) => Err(err),
+        };
+
+        sender.send(content);
+        ctx.request_repaint();
+    });
+
+    promise
+}
+
+/// A NIP-98 `Authorization` header value for a GET of `url`
+fn http_auth(buyer: &FullKeypair, url: &str) -> Result<String, String> {
+    let note = NoteBuilder::new()
+        .kind(HTTP_AUTH_KIND)
+        .content("")
+        .start_tag()
+        .tag_str("u")
+        .tag_str(url)
+        .start_tag()
+        .tag_str("method")
+        .tag_str("GET")
+        .sign(&buyer.secret_key.secret_bytes())
+        .build()
+        .ok_or_else(|| "Couldn't sign the unlock request".to_owned())?;
+
+    let json = note.json().map_err(|e| e.to_string())?;
+    Ok(format!("Nostr {}", BASE64_STANDARD.encode(json)))
+}
+
+/// The full content out of the unlock server's response, decrypting it
+/// with `decrypt` when it's NIP-44 encrypted
+fn parse_unlocked(
+    body: &str,
+    decrypt: impl FnOnce(&str) -> Result<String, String>,
+) -> Result<String, String> {
+    let response: serde_json::Value =
+        serde_json::from_str(body).map_err(|_| "The unlock server didn't send json".to_owned())?;
+
+    if let Some(content) = response.get("content").and_then(|c| c.as_str()) {
+        return Ok(content.to_owned());
+    }
+    if let Some(payload) = response.get("nip44").and_then(|c| c.as_str()) {
+        return decrypt(payload);
+    }
+    Err("The unlock server didn't send any content".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paid_orders_are_not_paid_twice() {
+        let buyer = Pubkey::new([1; 32]);
+        let target = NoteZapTargetOwned {
+            note_id: NoteId::new([2; 32]),
+            zap_recipient: Pubkey::new([3; 32]),
+        };
+        let paywall = Paywall {
+            price_msats: 21_000,
+            unlock_url: "https://example.com/unlock/1".to_owned(),
+        };
+
+        let mut session = PaidContent::default();
+        session.paid.push(PaidOrder::from(&Order {
+            buyer,
+            target: target.clone(),
+            paywall: paywall.clone(),
+        }));
+        let json = serde_json::to_string(&session.to_saved()).unwrap();
+
+        let mut restarted = PaidContent::new(serde_json::from_str(&json).unwrap());
+        assert!(restarted.is_paid(&buyer, &target.note_id));
+        assert!(!restarted.buy(buyer, target.clone(), paywall.clone()));
+        assert_eq!(restarted.state(&target.note_id), Some(Unlock::Fetching));
+
+        // someone else still has to pay
+        let other = Pubkey::new([4; 32]);
+        assert!(restarted.buy(other, target.clone(), paywall));
+        assert_eq!(restarted.state(&target.note_id), Some(Unlock::Paying));
+    }
+
+    #[test]
+    fn unlocked_content_plain_or_encrypted() {
+        let no_decrypt = |_: &str| -> Result<String, String> { panic!("not encrypted") };
+        assert_eq!(
+            parse_unlocked(r#"{"content":"the whole story"}"#, no_decrypt),
+            Ok("the whole story".to_owned())
+        );
+
+        let decrypted = parse_unlocked(r#"{"nip44":"AgAb"}"#, |payload| {
+            assert_eq!(payload, "AgAb");
+            Ok("secret".to_owned())
+        });
+        assert_eq!(decrypted, Ok("secret".to_owned()));
+
+        assert!(parse_unlocked(r#"{"status":"paid"}"#, no_decrypt).is_err());
+        assert!(parse_unlocked("<html>", no_decrypt).is_err());
+    }
+}
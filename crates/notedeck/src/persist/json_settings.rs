@@ -0,0 +1,164 @@
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{error, info};
+
+use crate::{storage, DataPath, DataPathType, Directory};
+
+/// Settings files found unreadable this run, for telling the user about
+static UNREADABLE: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// The settings files that couldn't be parsed since the last call, and
+/// where each one was set aside
+pub fn take_unreadable_settings() -> Vec<String> {
+    match UNREADABLE.try_lock() {
+        Ok(mut unreadable) if !unreadable.is_empty() => std::mem::take(&mut *unreadable),
+        _ => Vec::new(),
+    }
+}
+
+/// A json file in the settings directory holding a single `T`
+pub struct JsonSettings<T> {
+    directory: Directory,
+    file_name: &'static str,
+
+    /// Set when the file couldn't be parsed or moved aside, so saving
+    /// doesn't replace what might still be recovered by hand
+    keep_file: AtomicBool,
+
+    item: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned + Default> JsonSettings<T> {
+    pub fn new(path: &DataPath, file_name: &'static str) -> Self {
+        let directory = Directory::new(path.path(DataPathType::Setting));
+        Self {
+            directory,
+            file_name,
+            keep_file: AtomicBool::new(false),
+            item: PhantomData,
+        }
+    }
+
+    /// The saved item, or the default if nothing was saved. A file that
+    /// can't be parsed is moved aside first so it isn't overwritten by
+    /// the next save.
+    pub fn load(&self) -> T {
+        let Ok(contents) = self.directory.get_file(self.file_name.to_owned()) else {
+            return T::default();
+        };
+
+        match serde_json::from_str(&contents) {
+            Ok(item) => item,
+            Err(e) => {
+                error!("could not parse {}: {e}", self.file_name);
+                self.set_aside();
+                T::default()
+            }
+        }
+    }
+
+    pub fn save(&self, item: &T) {
+        if self.keep_file.load(Ordering::Relaxed) {
+            error!(
+                "not saving {}, it couldn't be read or moved aside",
+                self.file_name
+            );
+            return;
+        }
+
+        let json = match serde_json::to_string(item) {
+            Ok(json) => json,
+            Err(e) => {
+                error!("could not serialize {}: {e}", self.file_name);
+                return;
+            }
+        };
+
+        match storage::write_file_atomic(
+            &self.directory.file_path,
+            self.file_name.to_owned(),
+            &json,
+        ) {
+            Ok(_) => info!("saved {}", self.file_name),
+            Err(e) => error!("could not save {}: {e}", self.file_name),
+        }
+    }
+
+    fn set_aside(&self) {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let backup = format!("{}.{secs}.bad", self.file_name);
+
+        let from = self.directory.file_path.join(self.file_name);
+        let to = self.directory.file_path.join(&backup);
+        let message = match std::fs::rename(&from, &to) {
+            Ok(_) => {
+                error!("moved unreadable {} to {backup}", self.file_name);
+                format!("Couldn't read {}, it was saved as {backup}", self.file_name)
+            }
+            Err(e) => {
+                error!("could not move {} aside: {e}", self.file_name);
+                self.keep_file.store(true, Ordering::Relaxed);
+                format!(
+                    "Couldn't read {}, changes to it won't be saved",
+                    self.file_name
+                )
+            }
+        };
+
+        if let Ok(mut unreadable) = UNREADABLE.lock() {
+            unreadable.push(message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_and_load() {
+        let tmp = TempDir::new().unwrap();
+        let path = DataPath::new(tmp.path());
+        let settings: JsonSettings<BTreeMap<String, u32>> = JsonSettings::new(&path, "test.json");
+
+        // nothing saved yet
+        assert!(settings.load().is_empty());
+
+        let item = BTreeMap::from([("a".to_owned(), 1), ("b".to_owned(), 2)]);
+        settings.save(&item);
+        assert_eq!(settings.load(), item);
+    }
+
+    #[test]
+    fn test_unparseable_file_is_kept() {
+        let tmp = TempDir::new().unwrap();
+        let path = DataPath::new(tmp.path());
+        let dir = path.path(DataPathType::Setting);
+        storage::write_file(&dir, "test.json".to_owned(), "not json").unwrap();
+
+        let settings: JsonSettings<Vec<u32>> = JsonSettings::new(&path, "test.json");
+        assert!(settings.load().is_empty());
+        settings.save(&vec![1]);
+
+        let backups: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|f| f.ok()?.file_name().into_string().ok())
+            .filter(|name| name.ends_with(".bad"))
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(dir.join(&backups[0])).unwrap(),
+            "not json"
+        );
+        assert_eq!(settings.load(), vec![1]);
+    }
+}
@@ -1,9 +1,11 @@
 mod app_size;
+mod json_settings;
 mod theme_handler;
 mod token_handler;
 mod zoom;
 
 pub use app_size::AppSizeHandler;
+pub use json_settings::{take_unreadable_settings, JsonSettings};
 pub use theme_handler::ThemeHandler;
 pub use token_handler::TokenHandler;
 pub use zoom::ZoomHandler;
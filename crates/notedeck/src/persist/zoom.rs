@@ -1,8 +1,13 @@
 use crate::{DataPath, DataPathType};
-use egui::Context;
+use egui::{Context, Id, Key, Modifiers};
 
 use crate::timed_serializer::TimedSerializer;
 
+/// What ctrl+plus and ctrl+minus step through, like a browser
+const ZOOM_LEVELS: [f32; 13] = [
+    0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0,
+];
+
 pub struct ZoomHandler {
     serializer: TimedSerializer<f32>,
 }
@@ -23,4 +28,89 @@ impl ZoomHandler {
     pub fn get_zoom_factor(&self) -> Option<f32> {
         self.serializer.get_item()
     }
+
+    /// Zoom the whole window with ctrl+plus, ctrl+minus and ctrl+0. This
+    /// replaces egui's own shortcuts, which step by a fixed 10%.
+    pub fn handle_shortcuts(&mut self, ctx: &Context) {
+        let toast_id = Id::new("zoom_toast");
+        if crate::toast::take_clicked(ctx, toast_id) {
+            ctx.set_zoom_factor(1.0);
+            return;
+        }
+
+        let current = ctx.zoom_factor();
+        let zoom = ctx.input_mut(|i| {
+            if i.consume_key(Modifiers::COMMAND, Key::Plus)
+                || i.consume_key(Modifiers::COMMAND, Key::Equals)
+            {
+                Some(step_zoom(current, true))
+            } else if i.consume_key(Modifiers::COMMAND, Key::Minus) {
+                Some(step_zoom(current, false))
+            } else if i.consume_key(Modifiers::COMMAND, Key::Num0) {
+                Some(1.0)
+            } else {
+                None
+            }
+        });
+
+        let Some(zoom) = zoom else {
+            return;
+        };
+        if zoom == current {
+            return;
+        }
+
+        ctx.set_zoom_factor(zoom);
+
+        // a fresh toast for each step instead of a stack of old levels
+        crate::toast::dismiss(ctx, toast_id);
+        let message = format!("Zoom {:.0}%", zoom * 100.0);
+        if zoom == 1.0 {
+            crate::toast::info(ctx, message);
+        } else {
+            crate::toast::with_action(ctx, message, "Reset", toast_id);
+        }
+    }
+}
+
+/// The next zoom level in or out from `current`, which may be between
+/// levels if it was set some other way
+fn step_zoom(current: f32, zoom_in: bool) -> f32 {
+    // a little slack so a level saved as 1.1000001 is still 1.1
+    const EPSILON: f32 = 0.001;
+
+    if zoom_in {
+        ZOOM_LEVELS
+            .iter()
+            .copied()
+            .find(|level| *level > current + EPSILON)
+            .unwrap_or(current.max(ZOOM_LEVELS[ZOOM_LEVELS.len() - 1]))
+    } else {
+        ZOOM_LEVELS
+            .iter()
+            .rev()
+            .copied()
+            .find(|level| *level < current - EPSILON)
+            .unwrap_or(current.min(ZOOM_LEVELS[0]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_zoom() {
+        assert_eq!(step_zoom(1.0, true), 1.1);
+        assert_eq!(step_zoom(1.0, false), 0.9);
+
+        // in between levels goes to the neighbouring one
+        assert_eq!(step_zoom(1.2, true), 1.25);
+        assert_eq!(step_zoom(1.2, false), 1.1);
+
+        // and it stops at the ends
+        assert_eq!(step_zoom(3.0, true), 3.0);
+        assert_eq!(step_zoom(0.5, false), 0.5);
+        assert_eq!(step_zoom(0.3, false), 0.3);
+    }
 }
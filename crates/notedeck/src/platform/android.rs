@@ -1,5 +1,11 @@
+use jni::objects::{GlobalRef, JClass, JObject, JString, JValue};
+use jni::sys::jboolean;
+use jni::{JNIEnv, JavaVM};
 use std::sync::atomic::{AtomicI32, Ordering};
-use tracing::debug;
+use std::sync::Mutex;
+use tracing::{debug, error};
+
+use super::{ExternalSignerRequest, ExternalSignerResponse};
 
 // Thread-safe static global
 static KEYBOARD_HEIGHT: AtomicI32 = AtomicI32::new(0);
@@ -24,3 +30,133 @@ pub extern "C" fn Java_com_damus_notedeck_KeyboardHeightHelper_nativeKeyboardHei
 pub fn virtual_keyboard_height() -> i32 {
     KEYBOARD_HEIGHT.load(Ordering::SeqCst)
 }
+
+/// The activity we launch external signer intents from, and the vm to
+/// call into it with
+static ACTIVITY: Mutex<Option<(JavaVM, GlobalRef)>> = Mutex::new(None);
+
+/// Responses from external signers that haven't been picked up yet
+static SIGNER_RESPONSES: Mutex<Vec<(String, ExternalSignerResponse)>> = Mutex::new(Vec::new());
+
+/// Called by `ExternalSigner` when our main activity is created, so we
+/// can launch signer intents from it later
+#[no_mangle]
+pub extern "C" fn Java_com_damus_notedeck_ExternalSigner_nativeAttach(
+    env: JNIEnv,
+    _class: JClass,
+    activity: JObject,
+) {
+    let attached = env
+        .get_java_vm()
+        .and_then(|vm| Ok((vm, env.new_global_ref(activity)?)));
+
+    match attached {
+        Ok(attached) => {
+            if let Ok(mut current) = ACTIVITY.lock() {
+                *current = Some(attached);
+            }
+        }
+        Err(e) => error!("could not attach to activity: {e}"),
+    }
+}
+
+/// Called by `ExternalSigner` when a signer app returns a result
+#[no_mangle]
+pub extern "C" fn Java_com_damus_notedeck_ExternalSigner_nativeOnResult(
+    mut env: JNIEnv,
+    _class: JClass,
+    id: JString,
+    result: JString,
+    event: JString,
+    package: JString,
+    rejected: jboolean,
+) {
+    let Some(id) = get_string(&mut env, id) else {
+        return;
+    };
+
+    let response = ExternalSignerResponse {
+        result: get_string(&mut env, result),
+        event: get_string(&mut env, event),
+        package: get_string(&mut env, package),
+        rejected: rejected != 0,
+    };
+
+    debug!(
+        "external signer response for {id}, rejected: {}",
+        response.rejected
+    );
+
+    if let Ok(mut responses) = SIGNER_RESPONSES.lock() {
+        responses.push((id, response));
+    }
+}
+
+fn get_string(env: &mut JNIEnv, s: JString) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+
+    env.get_string(&s).ok().map(Into::into)
+}
+
+/// Whether there's an app installed that handles `nostrsigner:` intents
+pub fn external_signer_available() -> bool {
+    with_activity(|env, activity| {
+        env.call_method(activity, "isExternalSignerInstalled", "()Z", &[])
+            .and_then(|v| v.z())
+            .map_err(|e| e.to_string())
+    })
+    .unwrap_or(false)
+}
+
+pub fn launch_external_signer(request: &ExternalSignerRequest) -> Result<(), String> {
+    with_activity(|env, activity| {
+        let mut args = Vec::new();
+        for arg in [
+            Some(request.id),
+            Some(request.method),
+            Some(request.content),
+            request.pubkey,
+            request.current_user,
+            request.package,
+        ] {
+            args.push(match arg {
+                Some(s) => JObject::from(env.new_string(s).map_err(|e| e.to_string())?),
+                None => JObject::null(),
+            });
+        }
+
+        let args: Vec<JValue> = args.iter().map(JValue::Object).collect();
+        let launched = env
+            .call_method(
+                activity,
+                "launchExternalSigner",
+                "(Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;)Z",
+                &args,
+            )
+            .and_then(|v| v.z())
+            .map_err(|e| e.to_string())?;
+
+        if launched {
+            Ok(())
+        } else {
+            Err("no signer app installed".to_owned())
+        }
+    })
+}
+
+pub fn take_external_signer_response(id: &str) -> Option<ExternalSignerResponse> {
+    let mut responses = SIGNER_RESPONSES.lock().ok()?;
+    let index = responses.iter().position(|(rid, _)| rid == id)?;
+    Some(responses.remove(index).1)
+}
+
+fn with_activity<T>(
+    f: impl FnOnce(&mut JNIEnv, &JObject) -> Result<T, String>,
+) -> Result<T, String> {
+    let current = ACTIVITY.lock().map_err(|e| e.to_string())?;
+    let (vm, activity) = current.as_ref().ok_or("no activity to launch from")?;
+    let mut env = vm.attach_current_thread().map_err(|e| e.to_string())?;
+    f(&mut env, activity.as_obj())
+}
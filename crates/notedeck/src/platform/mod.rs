@@ -1,6 +1,41 @@
 #[cfg(target_os = "android")]
 pub mod android;
 
+/// A NIP-55 request for an external signer app like Amber
+pub struct ExternalSignerRequest<'a> {
+    /// Our id for the request, the response comes back under it
+    pub id: &'a str,
+
+    /// `get_public_key`, `sign_event`, `nip44_encrypt`, ...
+    pub method: &'a str,
+
+    pub content: &'a str,
+
+    /// The other party for encrypt and decrypt requests
+    pub pubkey: Option<&'a str>,
+
+    /// The user we want the signer to act as
+    pub current_user: Option<&'a str>,
+
+    /// The signer's package name, once we know which app it is
+    pub package: Option<&'a str>,
+}
+
+/// What an external signer app sent back
+#[derive(Debug, Clone, Default)]
+pub struct ExternalSignerResponse {
+    pub result: Option<String>,
+
+    /// The signed event json, for `sign_event`
+    pub event: Option<String>,
+
+    /// The signer's package name, for `get_public_key`
+    pub package: Option<String>,
+
+    /// The user declined, or backed out of the signer
+    pub rejected: bool,
+}
+
 #[cfg(target_os = "android")]
 pub fn virtual_keyboard_height() -> i32 {
     android::virtual_keyboard_height()
@@ -10,3 +45,33 @@ pub fn virtual_keyboard_height() -> i32 {
 pub fn virtual_keyboard_height() -> i32 {
     0
 }
+
+#[cfg(target_os = "android")]
+pub fn external_signer_available() -> bool {
+    android::external_signer_available()
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn external_signer_available() -> bool {
+    false
+}
+
+#[cfg(target_os = "android")]
+pub fn launch_external_signer(request: &ExternalSignerRequest) -> Result<(), String> {
+    android::launch_external_signer(request)
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn launch_external_signer(_request: &ExternalSignerRequest) -> Result<(), String> {
+    Err("external signers are only supported on Android".to_owned())
+}
+
+#[cfg(target_os = "android")]
+pub fn take_external_signer_response(id: &str) -> Option<ExternalSignerResponse> {
+    android::take_external_signer_response(id)
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn take_external_signer_response(_id: &str) -> Option<ExternalSignerResponse> {
+    None
+}
@@ -0,0 +1,348 @@
+//! NIP-88 polls. A poll is a note listing its options, and a vote is a
+//! response note naming the options picked. Only the latest response
+//! from each pubkey counts.
+
+use enostr::{ClientMessage, Filter, NoteId, Pubkey, RelayPool};
+use nostrdb::{Ndb, Note, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::debouncer::Debouncer;
+
+pub const POLL_KIND: u32 = 1068;
+pub const POLL_RESPONSE_KIND: u32 = 1018;
+
+/// How long a tally is good for before we recount from nostrdb
+const LOCAL_REFRESH: Duration = Duration::from_secs(5);
+
+/// Only fetch votes for polls that were on screen this recently
+const SEEN_WINDOW: Duration = Duration::from_secs(5);
+
+/// Most responses we look at per poll
+const MAX_RESPONSES: i32 = 2000;
+
+/// Every vote request goes out under this id, so each one replaces the
+/// last instead of piling up on relays
+const VOTES_SUB_ID: &str = "poll-votes";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollOption {
+    pub id: String,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Poll {
+    pub options: Vec<PollOption>,
+    pub multiple_choice: bool,
+
+    /// Unix time voting closes at
+    pub ends_at: Option<u64>,
+}
+
+impl Poll {
+    pub fn from_note(note: &Note) -> Option<Self> {
+        if note.kind() != POLL_KIND {
+            return None;
+        }
+
+        let mut options = Vec::new();
+        let mut multiple_choice = false;
+        let mut ends_at = None;
+
+        for tag in note.tags() {
+            if tag.count() < 2 {
+                continue;
+            }
+            let value = |i| tag.get_unchecked(i).variant().str();
+
+            match value(0) {
+                Some("option") if tag.count() >= 3 => {
+                    if let (Some(id), Some(label)) = (value(1), value(2)) {
+                        options.push(PollOption {
+                            id: id.to_owned(),
+                            label: label.to_owned(),
+                        });
+                    }
+                }
+                Some("polltype") => multiple_choice = value(1) == Some("multiplechoice"),
+                Some("endsAt") => ends_at = value(1).and_then(|at| at.parse().ok()),
+                _ => {}
+            }
+        }
+
+        (!options.is_empty()).then_some(Self {
+            options,
+            multiple_choice,
+            ends_at,
+        })
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.ends_at.is_some_and(|at| at <= crate::time::unix_now())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PollTally {
+    /// Votes for each of the poll's options, in order
+    pub votes: Vec<u64>,
+
+    /// People who voted, some may have picked more than one option
+    pub voters: u64,
+
+    /// The option ids the current account picked
+    pub mine: Vec<String>,
+}
+
+/// One pubkey's answer to a poll
+#[derive(Debug, Clone)]
+struct Response {
+    pubkey: [u8; 32],
+    created_at: u64,
+    options: Vec<String>,
+}
+
+impl Response {
+    fn from_note(note: &Note) -> Self {
+        let options = note
+            .tags()
+            .into_iter()
+            .filter(|tag| {
+                tag.count() >= 2 && tag.get_unchecked(0).variant().str() == Some("response")
+            })
+            .filter_map(|tag| tag.get_unchecked(1).variant().str().map(str::to_owned))
+            .collect();
+
+        Self {
+            pubkey: *note.pubkey(),
+            created_at: note.created_at(),
+            options,
+        }
+    }
+}
+
+fn tally_responses(
+    poll: &Poll,
+    responses: impl IntoIterator<Item = Response>,
+    me: Option<&[u8; 32]>,
+) -> PollTally {
+    let mut latest: HashMap<[u8; 32], Response> = HashMap::new();
+    for response in responses {
+        if poll.ends_at.is_some_and(|at| response.created_at > at) {
+            continue;
+        }
+        match latest.get(&response.pubkey) {
+            Some(existing) if existing.created_at >= response.created_at => {}
+            _ => {
+                latest.insert(response.pubkey, response);
+            }
+        }
+    }
+
+    let mut tally = PollTally {
+        votes: vec![0; poll.options.len()],
+        ..Default::default()
+    };
+
+    for response in latest.values() {
+        let mut picked: Vec<usize> = Vec::new();
+        for id in &response.options {
+            let Some(index) = poll.options.iter().position(|o| &o.id == id) else {
+                continue;
+            };
+            if !picked.contains(&index) {
+                picked.push(index);
+            }
+            if !poll.multiple_choice {
+                break;
+            }
+        }
+
+        if picked.is_empty() {
+            continue;
+        }
+
+        tally.voters += 1;
+        for index in &picked {
+            tally.votes[*index] += 1;
+        }
+        if me == Some(&response.pubkey) {
+            tally.mine = picked.iter().map(|i| poll.options[*i].id.clone()).collect();
+        }
+    }
+
+    tally
+}
+
+struct PollEntry {
+    tally: PollTally,
+    counted: Instant,
+    counted_for: Option<Pubkey>,
+    last_seen: Instant,
+}
+
+/// Vote tallies for the polls on screen. Votes are counted from what's
+/// in nostrdb, and relays are asked for the votes of every poll that's
+/// been seen lately.
+pub struct Polls {
+    entries: HashMap<NoteId, PollEntry>,
+
+    /// The polls whose votes we last asked relays for
+    requested: HashSet<NoteId>,
+    debouncer: Debouncer,
+}
+
+impl Default for Polls {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            requested: HashSet::new(),
+            debouncer: Debouncer::new(Duration::from_secs(1)),
+        }
+    }
+}
+
+impl Polls {
+    /// The votes on `poll`, counting again if it's been a while
+    pub fn tally(
+        &mut self,
+        ndb: &Ndb,
+        txn: &Transaction,
+        poll_id: &[u8; 32],
+        poll: &Poll,
+        me: Option<&Pubkey>,
+    ) -> PollTally {
+        let id = NoteId::new(*poll_id);
+        let now = Instant::now();
+
+        let fresh = self.entries.get(&id).is_some_and(|entry| {
+            entry.counted_for.as_ref() == me && now.duration_since(entry.counted) < LOCAL_REFRESH
+        });
+        if !fresh {
+            let responses = local_responses(ndb, txn, poll_id);
+            let tally = tally_responses(poll, responses, me.map(|pk| pk.bytes()));
+            self.entries.insert(
+                id,
+                PollEntry {
+                    tally,
+                    counted: now,
+                    counted_for: me.copied(),
+                    last_seen: now,
+                },
+            );
+        }
+
+        let entry = self.entries.get_mut(&id).expect("just counted");
+        entry.last_seen = now;
+        entry.tally.clone()
+    }
+
+    /// Ask relays for the votes on polls that are on screen, when that
+    /// set changes
+    pub fn send_requests(&mut self, pool: &mut RelayPool) {
+        if !self.debouncer.should_act() {
+            return;
+        }
+        self.debouncer.bounce();
+
+        self.entries
+            .retain(|_, entry| entry.last_seen.elapsed() < SEEN_WINDOW * 12);
+        let seen: HashSet<NoteId> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.last_seen.elapsed() < SEEN_WINDOW)
+            .map(|(id, _)| *id)
+            .collect();
+
+        if seen == self.requested {
+            return;
+        }
+
+        if seen.is_empty() {
+            pool.send(&ClientMessage::close(VOTES_SUB_ID.to_owned()));
+        } else {
+            debug!("asking for votes on {} polls", seen.len());
+            let filter = Filter::new()
+                .kinds([POLL_RESPONSE_KIND as u64])
+                .events(seen.iter().map(|id| id.bytes()))
+                .limit(MAX_RESPONSES as u64)
+                .build();
+            pool.send(&ClientMessage::req(VOTES_SUB_ID.to_owned(), vec![filter]));
+        }
+        self.requested = seen;
+    }
+}
+
+fn local_responses(ndb: &Ndb, txn: &Transaction, poll_id: &[u8; 32]) -> Vec<Response> {
+    let filter = Filter::new()
+        .kinds([POLL_RESPONSE_KIND as u64])
+        .event(poll_id)
+        .limit(MAX_RESPONSES as u64)
+        .build();
+
+    ndb.query(txn, &[filter], MAX_RESPONSES)
+        .map(|results| {
+            results
+                .iter()
+                .map(|result| Response::from_note(&result.note))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll(multiple_choice: bool) -> Poll {
+        let option = |id: &str| PollOption {
+            id: id.to_owned(),
+            label: id.to_uppercase(),
+        };
+        Poll {
+            options: vec![option("a"), option("b"), option("c")],
+            multiple_choice,
+            ends_at: Some(100),
+        }
+    }
+
+    fn response(pubkey: u8, created_at: u64, options: &[&str]) -> Response {
+        Response {
+            pubkey: [pubkey; 32],
+            created_at,
+            options: options.iter().map(|o| o.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_tally_single_choice() {
+        let responses = vec![
+            response(1, 10, &["a"]),
+            // changed their mind, only the latest counts
+            response(1, 20, &["b"]),
+            // only the first pick counts on a single choice poll
+            response(2, 10, &["c", "a"]),
+            // too late
+            response(3, 101, &["a"]),
+            // not an option
+            response(4, 10, &["z"]),
+        ];
+
+        let tally = tally_responses(&poll(false), responses, Some(&[1; 32]));
+        assert_eq!(tally.votes, vec![0, 1, 1]);
+        assert_eq!(tally.voters, 2);
+        assert_eq!(tally.mine, vec!["b".to_owned()]);
+    }
+
+    #[test]
+    fn test_tally_multiple_choice() {
+        let responses = vec![response(1, 10, &["a", "c", "a"]), response(2, 10, &["c"])];
+
+        let tally = tally_responses(&poll(true), responses, Some(&[3; 32]));
+        assert_eq!(tally.votes, vec![1, 0, 2]);
+        assert_eq!(tally.voters, 2);
+        assert!(tally.mine.is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+//! NIP-13 proof of work. A note's difficulty is the number of leading
+//! zero bits in its id, and a `nonce` tag is varied until the id has
+//! enough of them.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use nostrdb::{Note, NoteBuildOptions, NoteBuilder};
+use sha2::{Digest, Sha256};
+
+use crate::UnsignedEvent;
+
+/// Past this, mining takes minutes on a desktop cpu
+pub const MAX_DIFFICULTY: u32 = 32;
+
+/// Check whether we should give up this often
+const CANCEL_CHECK_EVERY: u64 = 4096;
+
+/// Leading zero bits of a note id
+pub fn difficulty(id: &[u8; 32]) -> u32 {
+    let mut bits = 0;
+    for byte in id {
+        if *byte == 0 {
+            bits += 8;
+        } else {
+            bits += byte.leading_zeros();
+            break;
+        }
+    }
+    bits
+}
+
+/// Difficulty a note commits to with its nonce tag. A note that happens
+/// to have a low id without aiming for it doesn't count.
+pub fn committed_difficulty(note: &Note) -> u32 {
+    let target = note.tags().into_iter().find_map(|tag| {
+        if tag.count() < 3 || tag.get_unchecked(0).variant().str() != Some("nonce") {
+            return None;
+        }
+        tag.get_unchecked(2).variant().str()?.parse::<u32>().ok()
+    });
+
+    target.map_or(0, |target| target.min(difficulty(note.id())))
+}
+
+/// Add a nonce tag to `event` that gives it an id with `target` leading
+/// zero bits. `attempts` is bumped as it goes, for showing progress, and
+/// setting `cancel` gives up. Returns None if it was cancelled.
+pub fn mine(
+    pubkey: &[u8; 32],
+    mut event: UnsignedEvent,
+    target: u32,
+    attempts: &AtomicU64,
+    cancel: &AtomicBool,
+) -> Option<UnsignedEvent> {
+    event
+        .tags
+        .retain(|tag| tag.first().map(String::as_str) != Some("nonce"));
+
+    // everything but the nonce itself is fixed, so it's only hashed once
+    let (prefix, suffix) = serialize_around_nonce(pubkey, &event, target);
+    let mut hasher = Sha256::new();
+    hasher.update(prefix.as_bytes());
+
+    let mut nonce: u64 = 0;
+    loop {
+        if nonce % CANCEL_CHECK_EVERY == 0 {
+            attempts.fetch_add(CANCEL_CHECK_EVERY, Ordering::Relaxed);
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+        }
+
+        let mut attempt = hasher.clone();
+        attempt.update(nonce.to_string().as_bytes());
+        attempt.update(suffix.as_bytes());
+        let id: [u8; 32] = attempt.finalize().into();
+
+        if difficulty(&id) >= target {
+            event.tags.push(vec![
+                "nonce".to_owned(),
+                nonce.to_string(),
+                target.to_string(),
+            ]);
+            return Some(event);
+        }
+
+        nonce += 1;
+    }
+}
+
+/// The NIP-01 serialization of `event` split where the nonce goes. The
+/// nonce tag is last.
+fn serialize_around_nonce(
+    pubkey: &[u8; 32],
+    event: &UnsignedEvent,
+    target: u32,
+) -> (String, String) {
+    let tags = serde_json::to_string(&event.tags).unwrap_or_else(|_| "[]".to_owned());
+    let tags_open = &tags[..tags.len() - 1];
+    let separator = if event.tags.is_empty() { "" } else { "," };
+    let content = serde_json::to_string(&event.content).unwrap_or_else(|_| "\"\"".to_owned());
+
+    let prefix = format!(
+        "[0,\"{}\",{},{},{tags_open}{separator}[\"nonce\",\"",
+        hex::encode(pubkey),
+        event.created_at,
+        event.kind,
+    );
+    let suffix = format!("\",\"{target}\"]],{content}]");
+
+    (prefix, suffix)
+}
+
+/// Build and sign `event` as is, keeping its created_at and tags so a
+/// mined id stays the same
+pub fn sign_event<'a>(event: &'a UnsignedEvent, seckey: &[u8; 32]) -> Option<Note<'a>> {
+    let mut builder = NoteBuilder::new()
+        .kind(event.kind)
+        .content(&event.content)
+        .created_at(event.created_at);
+
+    for tag in &event.tags {
+        builder = builder.start_tag();
+        for elem in tag {
+            builder = builder.tag_str(elem);
+        }
+    }
+
+    // the builder stamps the current time unless told not to
+    builder
+        .options(NoteBuildOptions::default().created_at(false).sign(seckey))
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_difficulty() {
+        let mut id = [0xff; 32];
+        assert_eq!(difficulty(&id), 0);
+
+        id[0] = 0;
+        id[1] = 0x0f;
+        assert_eq!(difficulty(&id), 12);
+
+        assert_eq!(difficulty(&[0; 32]), 256);
+    }
+
+    #[test]
+    fn test_mine() {
+        let pubkey = [3; 32];
+        let event = UnsignedEvent {
+            kind: 1,
+            content: "it's \"proof\" of work\n".to_owned(),
+            tags: vec![vec!["t".to_owned(), "pow".to_owned()]],
+            created_at: 1_700_000_000,
+        };
+
+        let mined = mine(
+            &pubkey,
+            event,
+            8,
+            &AtomicU64::new(0),
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        let nonce = mined.tags.last().unwrap();
+        assert_eq!(nonce[0], "nonce");
+        assert_eq!(nonce[2], "8");
+
+        // hash the event the way NIP-01 says to, independently of mine()
+        let json = serde_json::to_string(&(
+            0,
+            hex::encode(pubkey),
+            mined.created_at,
+            mined.kind,
+            &mined.tags,
+            &mined.content,
+        ))
+        .unwrap();
+        let id: [u8; 32] = Sha256::digest(json.as_bytes()).into();
+        assert!(difficulty(&id) >= 8);
+    }
+
+    #[test]
+    fn test_signed_mined_event_keeps_its_work() {
+        let keypair = enostr::FullKeypair::generate();
+        let event = UnsignedEvent {
+            kind: 1,
+            content: "mined a while ago".to_owned(),
+            tags: Vec::new(),
+            created_at: 1_600_000_000,
+        };
+
+        let mined = mine(
+            keypair.pubkey.bytes(),
+            event,
+            8,
+            &AtomicU64::new(0),
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        let note = sign_event(&mined, &keypair.secret_key.to_secret_bytes()).unwrap();
+
+        assert_eq!(note.created_at(), 1_600_000_000);
+        assert!(difficulty(note.id()) >= 8);
+        assert_eq!(committed_difficulty(&note), difficulty(note.id()).min(8));
+    }
+
+    #[test]
+    fn test_mine_cancel() {
+        let event = UnsignedEvent {
+            kind: 1,
+            content: String::new(),
+            tags: Vec::new(),
+            created_at: 0,
+        };
+        let cancel = AtomicBool::new(true);
+        assert!(mine(&[0; 32], event, 255, &AtomicU64::new(0), &cancel).is_none());
+    }
+}
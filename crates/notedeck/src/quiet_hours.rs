@@ -0,0 +1,190 @@
+//! Times of the week when notifications keep quiet. Unread counts still
+//! go up, only sounds and OS notifications are held back. DMs from a few
+//! chosen people can still come through.
+
+use chrono::{Datelike, Local, Timelike};
+use enostr::Pubkey;
+use serde::{Deserialize, Serialize};
+
+/// Days of the week as bits, monday first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Weekdays(u8);
+
+impl Weekdays {
+    pub const NAMES: [&'static str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+    pub const ALL: Self = Self(0b111_1111);
+    pub const WORKDAYS: Self = Self(0b001_1111);
+
+    /// `day` counts from monday as 0
+    pub fn contains(self, day: u8) -> bool {
+        day < 7 && self.0 & (1 << day) != 0
+    }
+
+    pub fn set(&mut self, day: u8, on: bool) {
+        if day >= 7 {
+            return;
+        }
+        if on {
+            self.0 |= 1 << day;
+        } else {
+            self.0 &= !(1 << day);
+        }
+    }
+}
+
+/// A stretch of quiet, like 22:00 to 07:00. One that ends before it
+/// starts runs past midnight into the next day. The days are the ones it
+/// starts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietRange {
+    /// Minutes after midnight
+    pub start: u16,
+    pub end: u16,
+    pub days: Weekdays,
+}
+
+impl Default for QuietRange {
+    fn default() -> Self {
+        Self {
+            start: 22 * 60,
+            end: 7 * 60,
+            days: Weekdays::ALL,
+        }
+    }
+}
+
+impl QuietRange {
+    /// Whether `minute` after midnight on `day` (monday is 0) falls in this
+    /// range. A range that starts and ends at the same time is all day.
+    pub fn contains(&self, day: u8, minute: u16) -> bool {
+        let yesterday = (day + 6) % 7;
+        if self.start == self.end {
+            self.days.contains(day)
+        } else if self.start < self.end {
+            self.days.contains(day) && (self.start..self.end).contains(&minute)
+        } else {
+            (self.days.contains(day) && minute >= self.start)
+                || (self.days.contains(yesterday) && minute < self.end)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuietHoursSettings {
+    pub enabled: bool,
+    pub ranges: Vec<QuietRange>,
+
+    /// People whose DMs still make a sound during quiet hours
+    pub dm_exceptions: Vec<Pubkey>,
+}
+
+impl QuietHoursSettings {
+    /// Whether it's quiet at `minute` after midnight on `day`, monday
+    /// being 0
+    pub fn quiet_at(&self, day: u8, minute: u16) -> bool {
+        self.enabled && self.ranges.iter().any(|r| r.contains(day, minute))
+    }
+}
+
+/// What set off a notification, for deciding whether it can make noise
+pub enum Alert<'a> {
+    DirectMessage { from: &'a Pubkey },
+    Other,
+}
+
+pub struct QuietHours {
+    settings: QuietHoursSettings,
+    settings_changed: bool,
+}
+
+impl QuietHours {
+    pub fn new(settings: QuietHoursSettings) -> Self {
+        Self {
+            settings,
+            settings_changed: false,
+        }
+    }
+
+    pub fn settings(&self) -> &QuietHoursSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: QuietHoursSettings) {
+        if settings != self.settings {
+            self.settings = settings;
+            self.settings_changed = true;
+        }
+    }
+
+    /// Whether the settings were changed since the last call, and should
+    /// be saved
+    pub fn take_settings_changed(&mut self) -> bool {
+        std::mem::take(&mut self.settings_changed)
+    }
+
+    /// Whether it's quiet hours now, in local time
+    pub fn is_quiet(&self) -> bool {
+        let now = Local::now();
+        let day = now.weekday().num_days_from_monday() as u8;
+        let minute = (now.hour() * 60 + now.minute()) as u16;
+        self.settings.quiet_at(day, minute)
+    }
+
+    /// Whether `alert` may play a sound or show an OS notification now.
+    /// Badges and unread counts don't ask, they always update.
+    pub fn allows(&self, alert: Alert) -> bool {
+        if let Alert::DirectMessage { from } = alert {
+            if self.settings.dm_exceptions.contains(from) {
+                return true;
+            }
+        }
+        !self.is_quiet()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MON: u8 = 0;
+    const TUE: u8 = 1;
+    const SAT: u8 = 5;
+
+    #[test]
+    fn overnight_range_spills_into_next_day() {
+        let range = QuietRange {
+            start: 22 * 60,
+            end: 7 * 60,
+            days: Weekdays::WORKDAYS,
+        };
+
+        assert!(range.contains(MON, 23 * 60));
+        assert!(range.contains(TUE, 6 * 60));
+        assert!(!range.contains(TUE, 7 * 60));
+        assert!(!range.contains(MON, 12 * 60));
+        // friday night runs into saturday morning, but saturday night is off
+        assert!(range.contains(SAT, 60));
+        assert!(!range.contains(SAT, 23 * 60));
+        // nothing started on sunday night
+        assert!(!range.contains(MON, 60));
+    }
+
+    #[test]
+    fn disabled_settings_are_never_quiet() {
+        let mut settings = QuietHoursSettings {
+            enabled: false,
+            ranges: vec![QuietRange {
+                start: 0,
+                end: 0,
+                days: Weekdays::ALL,
+            }],
+            dm_exceptions: vec![],
+        };
+        assert!(!settings.quiet_at(MON, 12 * 60));
+
+        settings.enabled = true;
+        assert!(settings.quiet_at(MON, 12 * 60));
+    }
+}
@@ -0,0 +1,256 @@
+//! How far the selected account has read its notifications, kept in sync
+//! across devices so clearing them on one clears the badge on the others.
+//! The marker is the created_at of the newest notification read, kept in
+//! NIP-78 app data encrypted to the account itself (NIP-44).
+//!
+//! Markers only ever move forward: a device merges in what the others
+//! published by taking the newest. Since they're notification timestamps
+//! rather than device clocks, a device with its clock off doesn't throw
+//! the others off, except for markers further in the future than any
+//! notification could reasonably be, which are held back to now. Our
+//! events go out newer than the last one we saw, so a relay keeps ours
+//! even if our clock is behind the device that published before.
+//!
+//! Only accounts with their key on this device take part, a remote signer
+//! would have to be asked for every decryption.
+
+use std::time::{Duration, Instant};
+
+use enostr::{ClientMessage, FilledKeypair, Pubkey, RelayPool};
+use nostr::nips::nip44;
+use nostrdb::{Filter, IngestMetadata, Ndb, NoteBuilder, Subscription, Transaction};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::time::unix_now;
+use crate::Accounts;
+
+/// NIP-78 arbitrary app data
+pub const APP_DATA_KIND: u64 = 30078;
+
+const READ_STATE_D_TAG: &str = "notedeck/notifications/read";
+
+/// Reading through notifications moves the marker a lot, it goes out once
+/// it's been still this long
+const PUBLISH_DELAY: Duration = Duration::from_secs(5);
+
+/// How far ahead of our clock a marker can be before it's held back
+const MAX_CLOCK_SKEW: u64 = 10 * 60;
+
+#[derive(Serialize, Deserialize)]
+struct ReadMarker {
+    last_read: u64,
+}
+
+#[derive(Default)]
+pub struct NotificationReadState {
+    account: Option<Pubkey>,
+    last_read: u64,
+
+    /// created_at of the newest marker event seen, ours go out after it
+    newest_event: u64,
+
+    /// When the marker last moved on this device, until it's published
+    changed_at: Option<Instant>,
+
+    sub: Option<Subscription>,
+    remote_subid: Option<String>,
+}
+
+impl NotificationReadState {
+    /// created_at of the newest notification read on any device
+    pub fn last_read(&self) -> u64 {
+        self.last_read
+    }
+
+    /// The user read notifications up to `created_at` here
+    pub fn mark_read(&mut self, created_at: u64) {
+        let created_at = created_at.min(unix_now() + MAX_CLOCK_SKEW);
+        if created_at > self.last_read {
+            self.last_read = created_at;
+            self.changed_at = Some(Instant::now());
+        }
+    }
+
+    /// Follow the selected account's marker, and publish ours once it
+    /// settles. Returns true when another device moved it.
+    pub fn update(&mut self, ndb: &mut Ndb, pool: &mut RelayPool, accounts: &Accounts) -> bool {
+        let selected = accounts.selected_account_pubkey();
+        let switched = self.account.as_ref() != selected;
+        if switched {
+            self.deactivate(ndb, pool);
+            self.account = selected.copied();
+            self.last_read = 0;
+            self.newest_event = 0;
+            self.changed_at = None;
+
+            if let Some(account) = self.account {
+                if accounts.get_full(account.bytes()).is_some() {
+                    self.activate(ndb, pool, &account);
+                }
+            }
+        }
+
+        let Some(account) = self.account else {
+            return false;
+        };
+        let Some(keypair) = accounts.get_full(account.bytes()) else {
+            return false;
+        };
+
+        // the marker we had from last time is already in nostrdb
+        let polled = self
+            .sub
+            .is_some_and(|sub| !ndb.poll_for_notes(sub, 10).is_empty());
+        let changed = (switched || polled) && self.load(ndb, &keypair);
+
+        if self
+            .changed_at
+            .is_some_and(|at| at.elapsed() >= PUBLISH_DELAY)
+        {
+            self.changed_at = None;
+            self.publish(ndb, pool, &keypair);
+        }
+
+        changed
+    }
+
+    fn activate(&mut self, ndb: &Ndb, pool: &mut RelayPool, account: &Pubkey) {
+        let filter = read_state_filter(account);
+
+        match ndb.subscribe(&[filter.clone()]) {
+            Ok(sub) => self.sub = Some(sub),
+            Err(e) => error!("notification read state subscription: {e}"),
+        }
+
+        let subid = format!("read-state-{}", Uuid::new_v4());
+        pool.subscribe(subid.clone(), vec![filter]);
+        self.remote_subid = Some(subid);
+    }
+
+    fn deactivate(&mut self, ndb: &mut Ndb, pool: &mut RelayPool) {
+        if let Some(sub) = self.sub.take() {
+            if let Err(e) = ndb.unsubscribe(sub) {
+                error!("notification read state unsubscribe: {e}");
+            }
+        }
+        if let Some(subid) = self.remote_subid.take() {
+            pool.unsubscribe(subid);
+        }
+    }
+
+    /// Merge in the newest marker in ndb. Returns whether it moved ours.
+    fn load(&mut self, ndb: &Ndb, keypair: &FilledKeypair) -> bool {
+        let Ok(txn) = Transaction::new(ndb) else {
+            return false;
+        };
+        let filter = read_state_filter(keypair.pubkey);
+        let Ok(results) = ndb.query(&txn, &[filter], 1) else {
+            return false;
+        };
+        let Some(note) = results.first().map(|result| &result.note) else {
+            return false;
+        };
+        if note.created_at() <= self.newest_event {
+            return false;
+        }
+        self.newest_event = note.created_at();
+
+        let marker = decrypt(keypair, note.content())
+            .and_then(|json| serde_json::from_str::<ReadMarker>(&json).map_err(|e| e.to_string()));
+        let remote = match marker {
+            Ok(marker) => marker.last_read.min(unix_now() + MAX_CLOCK_SKEW),
+            Err(e) => {
+                error!("couldn't read notification read state: {e}");
+                return false;
+            }
+        };
+
+        if remote <= self.last_read {
+            // we're ahead, make sure the others hear about it
+            if remote < self.last_read && self.changed_at.is_none() {
+                self.changed_at = Some(Instant::now());
+            }
+            return false;
+        }
+
+        debug!("notifications read up to {remote} on another device");
+        self.last_read = remote;
+        true
+    }
+
+    fn publish(&mut self, ndb: &Ndb, pool: &mut RelayPool, keypair: &FilledKeypair) {
+        let marker = ReadMarker {
+            last_read: self.last_read,
+        };
+        let json = serde_json::to_string(&marker).expect("read marker serializes");
+        let content = match encrypt(keypair, &json) {
+            Ok(content) => content,
+            Err(e) => {
+                error!("couldn't encrypt notification read state: {e}");
+                return;
+            }
+        };
+
+        let created_at = unix_now().max(self.newest_event + 1);
+        let Some(note) = NoteBuilder::new()
+            .kind(APP_DATA_KIND as u32)
+            .content(&content)
+            .created_at(created_at)
+            .start_tag()
+            .tag_str("d")
+            .tag_str(READ_STATE_D_TAG)
+            .sign(&keypair.secret_key.secret_bytes())
+            .build()
+        else {
+            error!("couldn't sign notification read state");
+            return;
+        };
+        let Ok(json) = note.json() else {
+            return;
+        };
+        self.newest_event = created_at;
+
+        if let Err(err) = ndb.process_event_with(
+            &format!("[\"EVENT\",{json}]"),
+            IngestMetadata::new().client(true),
+        ) {
+            error!("couldn't ingest our notification read state: {err}");
+        }
+        match ClientMessage::event_json(json) {
+            Ok(msg) => pool.send(&msg),
+            Err(e) => error!("couldn't send notification read state: {e}"),
+        }
+    }
+
+    /// Whether an EOSE was for our read state. We keep the subscription
+    /// open for other devices' updates.
+    pub fn handle_eose(&self, subid: &str) -> bool {
+        self.remote_subid.as_deref() == Some(subid)
+    }
+}
+
+fn read_state_filter(account: &Pubkey) -> Filter {
+    Filter::new()
+        .authors([account.bytes()])
+        .kinds([APP_DATA_KIND])
+        .tags([READ_STATE_D_TAG], 'd')
+        .limit(1)
+        .build()
+}
+
+fn self_conversation(keypair: &FilledKeypair) -> Result<nostr::PublicKey, String> {
+    nostr::PublicKey::from_slice(keypair.pubkey.bytes()).map_err(|e| e.to_string())
+}
+
+fn encrypt(keypair: &FilledKeypair, plaintext: &str) -> Result<String, String> {
+    let pk = self_conversation(keypair)?;
+    nip44::encrypt(keypair.secret_key, &pk, plaintext, nip44::Version::V2)
+        .map_err(|e| e.to_string())
+}
+
+fn decrypt(keypair: &FilledKeypair, payload: &str) -> Result<String, String> {
+    let pk = self_conversation(keypair)?;
+    nip44::decrypt(keypair.secret_key, &pk, payload).map_err(|e| e.to_string())
+}
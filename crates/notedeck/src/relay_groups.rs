@@ -0,0 +1,94 @@
+//! Named sets of relays to switch between, like "home", "privacy" or
+//! "work". While one is active the pool connects to its relays instead of
+//! the account's own.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayGroup {
+    pub name: String,
+    pub relays: Vec<String>,
+
+    /// Publish the group as the account's NIP-65 relay list when switching
+    /// to it, so others find the account there too
+    #[serde(default)]
+    pub publish: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayGroups {
+    pub groups: Vec<RelayGroup>,
+
+    /// The group in use, None uses the account's relays
+    pub active: Option<String>,
+}
+
+impl RelayGroups {
+    pub fn get(&self, name: &str) -> Option<&RelayGroup> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut RelayGroup> {
+        self.groups.iter_mut().find(|group| group.name == name)
+    }
+
+    pub fn active_group(&self) -> Option<&RelayGroup> {
+        self.get(self.active.as_deref()?)
+    }
+
+    pub fn active_group_mut(&mut self) -> Option<&mut RelayGroup> {
+        let name = self.active.clone()?;
+        self.get_mut(&name)
+    }
+
+    /// Add a group, replacing one with the same name
+    pub fn insert(&mut self, group: RelayGroup) {
+        match self.get_mut(&group.name) {
+            Some(existing) => *existing = group,
+            None => self.groups.push(group),
+        }
+    }
+
+    /// Remove a group, going back to the account's relays if it was in
+    /// use. Returns whether it was.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.groups.retain(|group| group.name != name);
+        if self.active.as_deref() == Some(name) {
+            self.active = None;
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(name: &str, relay: &str) -> RelayGroup {
+        RelayGroup {
+            name: name.to_owned(),
+            relays: vec![relay.to_owned()],
+            publish: false,
+        }
+    }
+
+    #[test]
+    fn groups_are_replaced_by_name_and_removing_the_active_one_clears_it() {
+        let mut groups = RelayGroups::default();
+        groups.insert(group("home", "wss://a/"));
+        groups.insert(group("work", "wss://b/"));
+        groups.insert(group("home", "wss://c/"));
+        assert_eq!(groups.groups.len(), 2);
+        assert_eq!(groups.get("home").unwrap().relays, vec!["wss://c/"]);
+
+        groups.active = Some("work".to_owned());
+        assert_eq!(groups.active_group().unwrap().relays, vec!["wss://b/"]);
+
+        assert!(!groups.remove("home"));
+        assert!(groups.remove("work"));
+        assert_eq!(groups.active, None);
+        assert!(groups.groups.is_empty());
+    }
+}
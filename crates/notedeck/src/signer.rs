@@ -0,0 +1,153 @@
+use enostr::{Pubkey, RelayPool};
+use tokenator::{ParseError, TokenParser, TokenSerializable, TokenWriter};
+
+use crate::nip46::{RemoteSigner, SignPurpose, SignerError, SignerStatus, UnsignedEvent};
+use crate::nip55::AmberSigner;
+
+/// Something that holds an account's secret key and signs for us
+pub enum AccountSigner {
+    /// A NIP-46 bunker we talk to over relays
+    Remote(RemoteSigner),
+
+    /// A NIP-55 signer app on the same Android device
+    Amber(AmberSigner),
+}
+
+impl AccountSigner {
+    pub fn status(&self) -> &SignerStatus {
+        match self {
+            AccountSigner::Remote(signer) => signer.status(),
+            AccountSigner::Amber(signer) => signer.status(),
+        }
+    }
+
+    pub fn user_pubkey(&self) -> Option<&Pubkey> {
+        match self {
+            AccountSigner::Remote(signer) => signer.user_pubkey(),
+            AccountSigner::Amber(signer) => signer.user_pubkey(),
+        }
+    }
+
+    /// Relays we need to stay connected to for this signer
+    pub fn relays(&self) -> &[String] {
+        match self {
+            AccountSigner::Remote(signer) => signer.relays(),
+            AccountSigner::Amber(_) => &[],
+        }
+    }
+
+    pub fn auth_url(&self) -> Option<&str> {
+        match self {
+            AccountSigner::Remote(signer) => signer.auth_url(),
+            AccountSigner::Amber(_) => None,
+        }
+    }
+
+    pub fn last_error(&self) -> Option<&SignerError> {
+        match self {
+            AccountSigner::Remote(signer) => signer.last_error(),
+            AccountSigner::Amber(signer) => signer.last_error(),
+        }
+    }
+
+    pub fn clear_error(&mut self) {
+        match self {
+            AccountSigner::Remote(signer) => signer.clear_error(),
+            AccountSigner::Amber(signer) => signer.clear_error(),
+        }
+    }
+
+    /// The local key to build notes with before they're signed
+    pub fn local_key(&self) -> [u8; 32] {
+        match self {
+            AccountSigner::Remote(signer) => signer.local_key(),
+            AccountSigner::Amber(signer) => signer.local_key(),
+        }
+    }
+
+    pub fn pending_signatures(&self) -> usize {
+        match self {
+            AccountSigner::Remote(signer) => signer.pending_signatures(),
+            AccountSigner::Amber(signer) => signer.pending_signatures(),
+        }
+    }
+
+    pub fn cancel_pending(&mut self) {
+        match self {
+            AccountSigner::Remote(signer) => signer.cancel_pending(),
+            AccountSigner::Amber(signer) => signer.cancel_pending(),
+        }
+    }
+
+    pub fn sign_event(
+        &mut self,
+        pool: &mut RelayPool,
+        unsigned: &UnsignedEvent,
+        purpose: SignPurpose,
+    ) {
+        match self {
+            AccountSigner::Remote(signer) => signer.sign_event(pool, unsigned, purpose),
+            AccountSigner::Amber(signer) => signer.sign_event(unsigned, purpose),
+        }
+    }
+
+    pub fn send_initial_filters(&mut self, pool: &mut RelayPool, relay_url: &str) {
+        if let AccountSigner::Remote(signer) = self {
+            signer.send_initial_filters(pool, relay_url);
+        }
+    }
+
+    /// Process a relay EVENT message. Returns false if it wasn't for us.
+    pub fn process_event(&mut self, sub_id: &str, msg: &str) -> bool {
+        match self {
+            AccountSigner::Remote(signer) => signer.process_event(sub_id, msg),
+            AccountSigner::Amber(_) => false,
+        }
+    }
+
+    pub fn update(&mut self, pool: &mut RelayPool) {
+        match self {
+            AccountSigner::Remote(signer) => signer.update(pool),
+            AccountSigner::Amber(signer) => signer.update(),
+        }
+    }
+
+    /// Signed events to publish
+    pub fn take_signed(&mut self) -> Vec<String> {
+        match self {
+            AccountSigner::Remote(signer) => signer.take_signed(),
+            AccountSigner::Amber(signer) => signer.take_signed(),
+        }
+    }
+}
+
+impl From<RemoteSigner> for AccountSigner {
+    fn from(signer: RemoteSigner) -> Self {
+        AccountSigner::Remote(signer)
+    }
+}
+
+impl From<AmberSigner> for AccountSigner {
+    fn from(signer: AmberSigner) -> Self {
+        AccountSigner::Amber(signer)
+    }
+}
+
+impl TokenSerializable for AccountSigner {
+    fn parse_from_tokens<'a>(parser: &mut TokenParser<'a>) -> Result<Self, ParseError<'a>> {
+        TokenParser::alt(
+            parser,
+            &[
+                |p| Ok(AccountSigner::Remote(RemoteSigner::parse_from_tokens(p)?)),
+                |p| Ok(AccountSigner::Amber(AmberSigner::parse_from_tokens(p)?)),
+            ],
+        )
+    }
+
+    fn serialize_tokens(&self, writer: &mut TokenWriter) {
+        match self {
+            AccountSigner::Remote(signer) => signer.serialize_tokens(writer),
+            AccountSigner::Amber(signer) => signer.serialize_tokens(writer),
+        }
+    }
+}
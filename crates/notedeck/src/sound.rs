@@ -0,0 +1,265 @@
+//! Short sounds for notifications. They're synthesized, written to the
+//! cache as wav files and handed to whatever plays audio on the
+//! platform, so there's no audio stack to carry around.
+
+use std::f32::consts::TAU;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+
+use crate::notifications::{Notification, NotificationKind};
+use crate::quiet_hours::{Alert, QuietHours};
+use crate::{DataPath, DataPathType};
+
+const SAMPLE_RATE: u32 = 22_050;
+
+/// A burst of notes shouldn't turn into a burst of sounds
+const MIN_GAP: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Sound {
+    #[default]
+    Silent,
+    Chime,
+    Pop,
+    Ping,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 4] = [Sound::Silent, Sound::Chime, Sound::Pop, Sound::Ping];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Sound::Silent => "Silent",
+            Sound::Chime => "Chime",
+            Sound::Pop => "Pop",
+            Sound::Ping => "Ping",
+        }
+    }
+
+    /// Frequencies and lengths in milliseconds, played one after another
+    fn tones(self) -> &'static [(f32, u32)] {
+        match self {
+            Sound::Silent => &[],
+            Sound::Chime => &[(1318.5, 110), (1760.0, 220)],
+            Sound::Pop => &[(520.0, 60)],
+            Sound::Ping => &[(1568.0, 260)],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoundSettings {
+    pub enabled: bool,
+    pub mention: Sound,
+    pub dm: Sound,
+    pub zap: Sound,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mention: Sound::Pop,
+            dm: Sound::Chime,
+            zap: Sound::Ping,
+        }
+    }
+}
+
+impl SoundSettings {
+    pub fn sound(&self, kind: NotificationKind) -> Sound {
+        match kind {
+            NotificationKind::Mention => self.mention,
+            NotificationKind::DirectMessage => self.dm,
+            NotificationKind::Zap => self.zap,
+        }
+    }
+}
+
+pub struct Sounds {
+    settings: SoundSettings,
+    settings_changed: bool,
+
+    /// Where the wav files go
+    dir: PathBuf,
+    last_played: Option<Instant>,
+}
+
+impl Sounds {
+    pub fn new(path: &DataPath, settings: SoundSettings) -> Self {
+        Self {
+            settings,
+            settings_changed: false,
+            dir: path.path(DataPathType::Cache).join("sounds"),
+            last_played: None,
+        }
+    }
+
+    pub fn settings(&self) -> SoundSettings {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: SoundSettings) {
+        if settings != self.settings {
+            self.settings = settings;
+            self.settings_changed = true;
+        }
+    }
+
+    /// Whether the settings were changed since the last call, and should
+    /// be saved
+    pub fn take_settings_changed(&mut self) -> bool {
+        std::mem::take(&mut self.settings_changed)
+    }
+
+    /// Play the sound for `notification`, unless sounds are off or it's
+    /// quiet hours
+    pub fn notify(&mut self, notification: &Notification, quiet_hours: &QuietHours) {
+        if !self.settings.enabled {
+            return;
+        }
+        let sound = self.settings.sound(notification.kind);
+        if sound == Sound::Silent {
+            return;
+        }
+
+        let alert = match notification.kind {
+            NotificationKind::DirectMessage => Alert::DirectMessage {
+                from: &notification.from,
+            },
+            _ => Alert::Other,
+        };
+        if !quiet_hours.allows(alert) {
+            return;
+        }
+
+        if self
+            .last_played
+            .is_some_and(|last| last.elapsed() < MIN_GAP)
+        {
+            return;
+        }
+        self.play(sound);
+    }
+
+    /// Play `sound` now, for trying them out
+    pub fn play(&mut self, sound: Sound) {
+        if sound == Sound::Silent {
+            return;
+        }
+        self.last_played = Some(Instant::now());
+
+        let file = self
+            .dir
+            .join(format!("{}.wav", sound.label().to_lowercase()));
+        let dir = self.dir.clone();
+        std::thread::spawn(move || {
+            if !file.exists() {
+                let written =
+                    std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&file, wav(sound)));
+                if let Err(e) = written {
+                    error!("couldn't write {}: {e}", file.display());
+                    return;
+                }
+            }
+            play_file(&file);
+        });
+    }
+}
+
+/// 16 bit mono wav of `sound`, each tone fading out so it doesn't click
+fn wav(sound: Sound) -> Vec<u8> {
+    let samples: Vec<i16> = sound
+        .tones()
+        .iter()
+        .flat_map(|&(freq, ms)| {
+            let len = SAMPLE_RATE * ms / 1000;
+            (0..len).map(move |i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                let fade = 1.0 - i as f32 / len as f32;
+                let attack = (i as f32 / 64.0).min(1.0);
+                ((TAU * freq * t).sin() * fade * fade * attack * 0.4 * i16::MAX as f32) as i16
+            })
+        })
+        .collect();
+
+    let data_len = samples.len() as u32 * 2;
+    let mut out = Vec::with_capacity(44 + data_len as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes()); // pcm
+    out.extend_from_slice(&1u16.to_le_bytes()); // mono
+    out.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    out.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    out.extend_from_slice(&2u16.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        out.extend_from_slice(&sample.to_le_bytes());
+    }
+    out
+}
+
+fn play_file(file: &std::path::Path) {
+    #[cfg(target_os = "linux")]
+    let players: &[(&str, &[&str])] = &[("paplay", &[]), ("pw-play", &[]), ("aplay", &["-q"])];
+
+    #[cfg(target_os = "macos")]
+    let players: &[(&str, &[&str])] = &[("afplay", &[])];
+
+    #[cfg(target_os = "windows")]
+    {
+        let script = format!(
+            "(New-Object Media.SoundPlayer '{}').PlaySync()",
+            file.display()
+        );
+        let played = std::process::Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .status();
+        if let Err(e) = played {
+            debug!("couldn't play sound: {e}");
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    {
+        let played = players.iter().any(|(program, args)| {
+            std::process::Command::new(program)
+                .args(*args)
+                .arg(file)
+                .status()
+                .is_ok_and(|status| status.success())
+        });
+        if !played {
+            debug!("no player could play {}", file.display());
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    debug!(
+        "no sound playback on this platform, not playing {}",
+        file.display()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wav_header_matches_samples() {
+        let bytes = wav(Sound::Chime);
+        let data_len = u32::from_le_bytes(bytes[40..44].try_into().unwrap());
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(bytes.len(), 44 + data_len as usize);
+        // 110ms and 220ms at 22050Hz, two bytes a sample
+        assert_eq!(data_len, (2425 + 4851) * 2);
+    }
+}
@@ -1,7 +1,7 @@
 use std::{
     collections::{HashMap, VecDeque},
     fs::{self, File},
-    io::{self, BufRead},
+    io::{self, BufRead, Write},
     path::{Path, PathBuf},
     time::SystemTime,
 };
@@ -191,6 +191,23 @@ pub fn write_file(directory: &Path, file_name: String, data: &str) -> Result<()>
     Ok(())
 }
 
+/// Write the file to the directory by way of a temporary file, so a crash
+/// partway through leaves the old contents instead of half of the new
+pub fn write_file_atomic(directory: &Path, file_name: String, data: &str) -> Result<()> {
+    if !directory.exists() {
+        fs::create_dir_all(directory)?
+    }
+
+    let tmp = directory.join(format!("{file_name}.tmp"));
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(data.as_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, directory.join(file_name))?;
+    Ok(())
+}
+
 pub fn delete_file(directory: &Path, file_name: String) -> Result<()> {
     let file_to_delete = directory.join(file_name.clone());
     if file_to_delete.exists() && file_to_delete.is_file() {
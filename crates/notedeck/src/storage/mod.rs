@@ -2,4 +2,6 @@ mod account_storage;
 mod file_storage;
 
 pub use account_storage::AccountStorage;
-pub use file_storage::{delete_file, write_file, DataPath, DataPathType, Directory};
+pub use file_storage::{
+    delete_file, write_file, write_file_atomic, DataPath, DataPathType, Directory,
+};
@@ -0,0 +1,382 @@
+//! Style overrides for people who want something other than the bundled
+//! themes, usually something denser. `style.toml` in the settings directory
+//! can change font sizes, spacing, corner radii and colors:
+//!
+//! ```toml
+//! [text]
+//! body = 13.0
+//! small = 11.0
+//!
+//! [spacing]
+//! item = [6.0, 2.0]
+//! button_padding = [4.0, 1.0]
+//! window_margin = 4
+//!
+//! [corners]
+//! widgets = 2
+//! windows = 4
+//!
+//! [colors.dark]
+//! panel = "#101010"
+//! hyperlink = "#7aa2f7"
+//! ```
+//!
+//! Anything left out keeps the theme's value. Debug builds pick up edits
+//! to the file while running.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+use egui::{Color32, CornerRadius, FontId, Margin, TextStyle, Theme, Vec2, Visuals};
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+use tracing::{error, info};
+
+use crate::{DataPath, DataPathType, NotedeckTextStyle};
+
+const STYLE_FILE: &str = "style.toml";
+
+/// How often debug builds look for changes to the file
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StyleOverrideFile {
+    pub text: TextSizes,
+    pub spacing: SpacingOverrides,
+    pub corners: CornerOverrides,
+    pub colors: ColorOverrides,
+}
+
+/// Font sizes in points, by text style
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TextSizes {
+    pub heading: Option<f32>,
+    pub heading2: Option<f32>,
+    pub heading3: Option<f32>,
+    pub heading4: Option<f32>,
+    pub body: Option<f32>,
+    pub monospace: Option<f32>,
+    pub button: Option<f32>,
+    pub small: Option<f32>,
+    pub tiny: Option<f32>,
+}
+
+impl TextSizes {
+    pub fn get(&self, text_style: &NotedeckTextStyle) -> Option<f32> {
+        match text_style {
+            NotedeckTextStyle::Heading => self.heading,
+            NotedeckTextStyle::Heading2 => self.heading2,
+            NotedeckTextStyle::Heading3 => self.heading3,
+            NotedeckTextStyle::Heading4 => self.heading4,
+            NotedeckTextStyle::Body => self.body,
+            NotedeckTextStyle::Monospace => self.monospace,
+            NotedeckTextStyle::Button => self.button,
+            NotedeckTextStyle::Small => self.small,
+            NotedeckTextStyle::Tiny => self.tiny,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpacingOverrides {
+    /// Between widgets, horizontally and vertically
+    pub item: Option<[f32; 2]>,
+
+    /// Around the text of buttons
+    pub button_padding: Option<[f32; 2]>,
+
+    /// The smallest a clickable widget gets
+    pub interact_size: Option<[f32; 2]>,
+    pub indent: Option<f32>,
+    pub window_margin: Option<i8>,
+    pub menu_margin: Option<i8>,
+    pub scroll_bar_width: Option<f32>,
+}
+
+/// Corner radii in points
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CornerOverrides {
+    pub widgets: Option<u8>,
+    pub windows: Option<u8>,
+    pub menus: Option<u8>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorOverrides {
+    pub dark: Palette,
+    pub light: Palette,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Palette {
+    pub panel: Option<HexColor>,
+    pub window: Option<HexColor>,
+    pub text: Option<HexColor>,
+    pub hyperlink: Option<HexColor>,
+    pub selection: Option<HexColor>,
+    pub faint_bg: Option<HexColor>,
+    pub extreme_bg: Option<HexColor>,
+    pub error: Option<HexColor>,
+    pub warning: Option<HexColor>,
+    pub widget_bg: Option<HexColor>,
+}
+
+/// A color written as `#rrggbb` or `#rrggbbaa`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct HexColor(pub Color32);
+
+impl TryFrom<String> for HexColor {
+    type Error = String;
+
+    fn try_from(hex: String) -> Result<Self, Self::Error> {
+        Color32::from_hex(&hex)
+            .map(HexColor)
+            .map_err(|_| format!("expected a color like #rrggbb, got {hex:?}"))
+    }
+}
+
+impl StyleOverrideFile {
+    pub fn parse(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
+
+    fn apply_to(&self, theme: Theme, style: &mut egui::Style) {
+        for text_style in NotedeckTextStyle::iter() {
+            if let Some(size) = self.text.get(&text_style) {
+                style.text_styles.insert(
+                    text_style.text_style(),
+                    FontId::new(size, text_style.font_family()),
+                );
+            }
+        }
+
+        let spacing = &self.spacing;
+        let ours = &mut style.spacing;
+        if let Some([x, y]) = spacing.item {
+            ours.item_spacing = Vec2::new(x, y);
+        }
+        if let Some([x, y]) = spacing.button_padding {
+            ours.button_padding = Vec2::new(x, y);
+        }
+        if let Some([x, y]) = spacing.interact_size {
+            ours.interact_size = Vec2::new(x, y);
+        }
+        if let Some(indent) = spacing.indent {
+            ours.indent = indent;
+        }
+        if let Some(margin) = spacing.window_margin {
+            ours.window_margin = Margin::same(margin);
+        }
+        if let Some(margin) = spacing.menu_margin {
+            ours.menu_margin = Margin::same(margin);
+        }
+        if let Some(width) = spacing.scroll_bar_width {
+            ours.scroll.bar_width = width;
+        }
+
+        let visuals = &mut style.visuals;
+        if let Some(radius) = self.corners.widgets {
+            let radius = CornerRadius::same(radius);
+            let widgets = &mut visuals.widgets;
+            for widget in [
+                &mut widgets.noninteractive,
+                &mut widgets.inactive,
+                &mut widgets.hovered,
+                &mut widgets.active,
+                &mut widgets.open,
+            ] {
+                widget.corner_radius = radius;
+            }
+        }
+        if let Some(radius) = self.corners.windows {
+            visuals.window_corner_radius = CornerRadius::same(radius);
+        }
+        if let Some(radius) = self.corners.menus {
+            visuals.menu_corner_radius = CornerRadius::same(radius);
+        }
+
+        let palette = match theme {
+            Theme::Dark => &self.colors.dark,
+            Theme::Light => &self.colors.light,
+        };
+        palette.apply_to(visuals);
+    }
+}
+
+impl Palette {
+    fn apply_to(&self, visuals: &mut Visuals) {
+        let set = |color: Option<HexColor>, field: &mut Color32| {
+            if let Some(HexColor(color)) = color {
+                *field = color;
+            }
+        };
+
+        set(self.panel, &mut visuals.panel_fill);
+        set(self.window, &mut visuals.window_fill);
+        set(self.hyperlink, &mut visuals.hyperlink_color);
+        set(self.faint_bg, &mut visuals.faint_bg_color);
+        set(self.extreme_bg, &mut visuals.extreme_bg_color);
+        set(self.error, &mut visuals.error_fg_color);
+        set(self.warning, &mut visuals.warn_fg_color);
+        set(self.widget_bg, &mut visuals.widgets.inactive.bg_fill);
+        set(self.widget_bg, &mut visuals.widgets.inactive.weak_bg_fill);
+        if let Some(HexColor(color)) = self.text {
+            visuals.override_text_color = Some(color);
+        }
+        if let Some(HexColor(color)) = self.selection {
+            visuals.selection.bg_fill = color;
+            visuals.selection.stroke.color = color;
+        }
+    }
+}
+
+/// The parts of a theme's style the overrides can touch, as they were
+/// before any were applied, so an override taken out of the file goes
+/// back to the theme's value
+struct Base {
+    text_styles: BTreeMap<TextStyle, FontId>,
+    spacing: egui::style::Spacing,
+    visuals: Visuals,
+}
+
+impl Base {
+    fn of(ctx: &egui::Context, theme: Theme) -> Self {
+        let style = ctx.style_of(theme);
+        Self {
+            text_styles: style.text_styles.clone(),
+            spacing: style.spacing.clone(),
+            visuals: style.visuals.clone(),
+        }
+    }
+}
+
+/// The overrides in `style.toml`, and what they were applied on top of
+pub struct StyleOverrides {
+    path: PathBuf,
+    overrides: StyleOverrideFile,
+    base: Option<[Base; 2]>,
+
+    modified: Option<SystemTime>,
+    last_checked: Instant,
+}
+
+impl StyleOverrides {
+    pub fn new(path: &DataPath) -> Self {
+        let path = path.path(DataPathType::Setting).join(STYLE_FILE);
+        let mut overrides = Self {
+            path,
+            overrides: StyleOverrideFile::default(),
+            base: None,
+            modified: None,
+            last_checked: Instant::now(),
+        };
+        overrides.modified = overrides.modified_at();
+        if overrides.modified.is_some() {
+            if let Some(file) = overrides.read() {
+                overrides.overrides = file;
+            }
+        }
+        overrides
+    }
+
+    /// Put the overrides on top of the current styles. The first call
+    /// takes the styles as they are as the theme's.
+    pub fn apply(&mut self, ctx: &egui::Context) {
+        let base = self
+            .base
+            .get_or_insert_with(|| [Base::of(ctx, Theme::Dark), Base::of(ctx, Theme::Light)]);
+
+        for (theme, base) in [Theme::Dark, Theme::Light].into_iter().zip(base.iter()) {
+            ctx.style_mut_of(theme, |style| {
+                style.text_styles = base.text_styles.clone();
+                style.spacing = base.spacing.clone();
+                style.visuals = base.visuals.clone();
+                self.overrides.apply_to(theme, style);
+            });
+        }
+
+        let text = self.overrides.text.clone();
+        ctx.data_mut(|d| d.insert_temp(text_sizes_id(), text));
+    }
+
+    /// The theme's visuals were just replaced, like when high contrast was
+    /// turned on. Take them as the new base and put the overrides back.
+    pub fn visuals_changed(&mut self, ctx: &egui::Context) {
+        if let Some([dark, light]) = &mut self.base {
+            dark.visuals = ctx.style_of(Theme::Dark).visuals.clone();
+            light.visuals = ctx.style_of(Theme::Light).visuals.clone();
+        }
+        self.apply(ctx);
+    }
+
+    /// Check whether the file was edited every so often and apply it again
+    /// if it was. Meant for debug builds, where tweaking a style while
+    /// looking at it is worth a stat a second.
+    pub fn reload_if_changed(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        if now.duration_since(self.last_checked) < RELOAD_INTERVAL {
+            return;
+        }
+        self.last_checked = now;
+        ctx.request_repaint_after(RELOAD_INTERVAL);
+
+        let modified = self.modified_at();
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+
+        if modified.is_none() {
+            info!("{STYLE_FILE} was removed, going back to the theme");
+            self.overrides = StyleOverrideFile::default();
+        } else if let Some(file) = self.read() {
+            info!("reloaded {STYLE_FILE}");
+            self.overrides = file;
+        } else {
+            // keep what was working while the file is mid-edit
+            return;
+        }
+        self.apply(ctx);
+    }
+
+    fn modified_at(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    }
+
+    fn read(&self) -> Option<StyleOverrideFile> {
+        let toml = match std::fs::read_to_string(&self.path) {
+            Ok(toml) => toml,
+            Err(e) => {
+                error!("could not read {}: {e}", self.path.display());
+                return None;
+            }
+        };
+        match StyleOverrideFile::parse(&toml) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                error!("could not parse {STYLE_FILE}: {e}");
+                None
+            }
+        }
+    }
+}
+
+fn text_sizes_id() -> egui::Id {
+    egui::Id::new("style-override-text-sizes")
+}
+
+/// The font size `style.toml` sets for `text_style`, if it sets one
+pub fn font_size(ctx: &egui::Context, text_style: &NotedeckTextStyle) -> Option<f32> {
+    ctx.data_mut(|d| d.get_temp::<TextSizes>(text_sizes_id()))
+        .and_then(|sizes| sizes.get(text_style))
+}
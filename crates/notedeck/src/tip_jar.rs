@@ -0,0 +1,140 @@
+//! Tip jars. A tip jar is a note someone pins to their profile asking for
+//! tips, written from a template so it reads fine in any client. The
+//! lightning address and suggested amounts also go in a tag so we can
+//! show a QR and one tap zap buttons under it:
+//!
+//! `["tipjar", "<lightning address>", "<sats>", "<sats>", ...]`
+
+use nostrdb::Note;
+
+pub const TIP_JAR_TAG: &str = "tipjar";
+
+/// Most suggested amounts a tip jar shows
+pub const MAX_AMOUNTS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TipJar {
+    pub lud16: String,
+    pub amounts_sats: Vec<u64>,
+}
+
+impl TipJar {
+    pub fn from_note(note: &Note) -> Option<Self> {
+        note.tags().into_iter().find_map(|tag| {
+            if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some(TIP_JAR_TAG) {
+                return None;
+            }
+
+            let lud16 = tag.get_unchecked(1).variant().str()?;
+            if !is_lightning_address(lud16) {
+                return None;
+            }
+
+            let amounts_sats = (2..tag.count())
+                .filter_map(|i| tag.get_unchecked(i).variant().str()?.parse().ok())
+                .filter(|sats| *sats > 0)
+                .take(MAX_AMOUNTS)
+                .collect();
+
+            Some(Self {
+                lud16: lud16.to_owned(),
+                amounts_sats,
+            })
+        })
+    }
+
+    pub fn tag(&self) -> Vec<String> {
+        let mut tag = vec![TIP_JAR_TAG.to_owned(), self.lud16.clone()];
+        tag.extend(self.amounts_sats.iter().map(u64::to_string));
+        tag
+    }
+
+    /// What the note says, `message` first and then how to tip
+    pub fn content(&self, message: &str) -> String {
+        let mut content = String::new();
+        let message = message.trim();
+        if !message.is_empty() {
+            content.push_str(message);
+            content.push_str("\n\n");
+        }
+
+        content.push_str(&format!("⚡ Tips go to {}", self.lud16));
+        if !self.amounts_sats.is_empty() {
+            let amounts: Vec<String> = self
+                .amounts_sats
+                .iter()
+                .map(|sats| format!("{sats} sats"))
+                .collect();
+            content.push_str(&format!("\nSuggested: {}", amounts.join(", ")));
+        }
+        content.push_str(&format!("\n\nlightning:{}", self.lud16));
+
+        content
+    }
+
+    /// For the QR, wallets open it straight to paying the address
+    pub fn uri(&self) -> String {
+        format!("lightning:{}", self.lud16)
+    }
+}
+
+/// `name@domain`, what a lightning address looks like
+pub fn is_lightning_address(address: &str) -> bool {
+    match address.split_once('@') {
+        Some((name, domain)) => {
+            !name.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !address.contains(char::is_whitespace)
+                && !domain.contains('@')
+        }
+        None => false,
+    }
+}
+
+/// Suggested amounts as typed, like "1000, 5000 21000". None if any of
+/// them isn't a number of sats.
+pub fn parse_amounts(text: &str) -> Option<Vec<u64>> {
+    let mut amounts = Vec::new();
+    for part in text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+    {
+        let sats: u64 = part.parse().ok()?;
+        if sats == 0 {
+            return None;
+        }
+        if !amounts.contains(&sats) {
+            amounts.push(sats);
+        }
+    }
+    amounts.truncate(MAX_AMOUNTS);
+    Some(amounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_as_typed() {
+        assert_eq!(
+            parse_amounts("1000, 5000 21000"),
+            Some(vec![1000, 5000, 21000])
+        );
+        assert_eq!(parse_amounts("  "), Some(vec![]));
+        assert_eq!(parse_amounts("21,21"), Some(vec![21]));
+        assert_eq!(parse_amounts("1000, lots"), None);
+        assert_eq!(parse_amounts("0"), None);
+        assert_eq!(
+            parse_amounts("1 2 3 4 5 6").map(|a| a.len()),
+            Some(MAX_AMOUNTS)
+        );
+
+        assert!(is_lightning_address("satoshi@example.com"));
+        assert!(!is_lightning_address("satoshi"));
+        assert!(!is_lightning_address("@example.com"));
+        assert!(!is_lightning_address("satoshi@localhost"));
+    }
+}
@@ -0,0 +1,182 @@
+//! Short messages at the bottom of the window for things the user did
+//! that didn't work out, or might want to take back. Anything with an
+//! [`egui::Context`] can queue one, they're drawn on top of whatever app
+//! is running.
+
+use egui::{Align2, Color32, Context, Id, RichText};
+
+const TOAST_SECONDS: f64 = 4.0;
+/// Long enough to notice and reach for the button
+const ACTION_TOAST_SECONDS: f64 = 8.0;
+const MAX_TOASTS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToastKind {
+    Info,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+struct Toast {
+    kind: ToastKind,
+    message: String,
+    expires_at: f64,
+
+    /// A button label and the id reported by [`take_clicked`]
+    action: Option<(String, Id)>,
+}
+
+fn toasts_id() -> Id {
+    Id::new("notedeck_toasts")
+}
+
+fn clicked_id() -> Id {
+    Id::new("notedeck_toast_clicks")
+}
+
+pub fn info(ctx: &Context, message: impl Into<String>) {
+    push(ctx, ToastKind::Info, message.into(), None, TOAST_SECONDS);
+}
+
+pub fn error(ctx: &Context, message: impl Into<String>) {
+    push(ctx, ToastKind::Error, message.into(), None, TOAST_SECONDS);
+}
+
+/// A toast with a button. Clicking it dismisses the toast, and
+/// [`take_clicked`] returns true for `id` once.
+pub fn with_action(ctx: &Context, message: impl Into<String>, label: impl Into<String>, id: Id) {
+    with_action_for(ctx, message, label, id, ACTION_TOAST_SECONDS);
+}
+
+/// [`with_action`] for an action that only applies for `seconds`
+pub fn with_action_for(
+    ctx: &Context,
+    message: impl Into<String>,
+    label: impl Into<String>,
+    id: Id,
+    seconds: f64,
+) {
+    push(
+        ctx,
+        ToastKind::Info,
+        message.into(),
+        Some((label.into(), id)),
+        seconds,
+    );
+}
+
+/// Whether the button of toast `id` was clicked since the last call
+pub fn take_clicked(ctx: &Context, id: Id) -> bool {
+    ctx.data_mut(|d| {
+        let clicked = d.get_temp_mut_or_default::<Vec<Id>>(clicked_id());
+        let before = clicked.len();
+        clicked.retain(|c| *c != id);
+        clicked.len() != before
+    })
+}
+
+/// Take down the toast with button `id`, its action no longer applies
+pub fn dismiss(ctx: &Context, id: Id) {
+    ctx.data_mut(|d| {
+        d.get_temp_mut_or_default::<Vec<Toast>>(toasts_id())
+            .retain(|t| !matches!(&t.action, Some((_, action)) if *action == id));
+    });
+}
+
+fn push(
+    ctx: &Context,
+    kind: ToastKind,
+    message: String,
+    action: Option<(String, Id)>,
+    seconds: f64,
+) {
+    let expires_at = ctx.input(|i| i.time) + seconds;
+
+    ctx.data_mut(|d| {
+        let toasts = d.get_temp_mut_or_default::<Vec<Toast>>(toasts_id());
+
+        // the same failure twice in a row only needs to be said once
+        if let Some(existing) = toasts.iter_mut().find(|t| t.message == message) {
+            existing.expires_at = expires_at;
+            existing.action = action;
+            return;
+        }
+
+        toasts.push(Toast {
+            kind,
+            message,
+            expires_at,
+            action,
+        });
+        if toasts.len() > MAX_TOASTS {
+            toasts.remove(0);
+        }
+    });
+
+    ctx.request_repaint();
+}
+
+/// Draw the current toasts and drop expired ones. Called once a frame.
+pub fn show(ctx: &Context) {
+    let now = ctx.input(|i| i.time);
+    let toasts = ctx.data_mut(|d| {
+        let toasts = d.get_temp_mut_or_default::<Vec<Toast>>(toasts_id());
+        toasts.retain(|t| t.expires_at > now);
+        toasts.clone()
+    });
+
+    let Some(next_expiry) = toasts.iter().map(|t| t.expires_at).reduce(f64::min) else {
+        return;
+    };
+
+    let interactable = toasts.iter().any(|t| t.action.is_some());
+    let mut clicked = None;
+
+    egui::Area::new(toasts_id())
+        .order(egui::Order::Foreground)
+        .anchor(Align2::CENTER_BOTTOM, egui::vec2(0.0, -24.0))
+        .interactable(interactable)
+        .show(ctx, |ui| {
+            for toast in &toasts {
+                if toast_ui(ui, toast) {
+                    clicked = toast.action.as_ref().map(|(_, id)| *id);
+                }
+                ui.add_space(6.0);
+            }
+        });
+
+    if let Some(id) = clicked {
+        dismiss(ctx, id);
+        ctx.data_mut(|d| d.get_temp_mut_or_default::<Vec<Id>>(clicked_id()).push(id));
+    }
+
+    ctx.request_repaint_after_secs((next_expiry - now) as f32);
+}
+
+/// Returns true if the toast's button was clicked
+fn toast_ui(ui: &mut egui::Ui, toast: &Toast) -> bool {
+    let (fill, text) = match toast.kind {
+        ToastKind::Info => (ui.visuals().extreme_bg_color, ui.visuals().text_color()),
+        ToastKind::Error => (ui.visuals().error_fg_color, Color32::WHITE),
+    };
+
+    egui::Frame::new()
+        .fill(fill)
+        .corner_radius(8.0)
+        .inner_margin(egui::Margin::symmetric(14, 10))
+        .shadow(ui.visuals().popup_shadow)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(&toast.message).color(text));
+
+                let Some((label, _)) = &toast.action else {
+                    return false;
+                };
+                ui.add_space(8.0);
+                ui.add(egui::Button::new(RichText::new(label).strong().color(text)).frame(false))
+                    .clicked()
+            })
+            .inner
+        })
+        .inner
+}
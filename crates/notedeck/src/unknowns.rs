@@ -9,7 +9,26 @@ use nostr::RelayUrl;
 use nostrdb::{BlockType, Mention, Ndb, Note, NoteKey, Transaction};
 use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
-use tracing::error;
+use tracing::{debug, error};
+
+/// How long unknown ids collect before they're asked for together
+const BATCH_WINDOW: Duration = Duration::from_millis(500);
+
+/// Most ids asked for at once, the rest go in the next batch
+const MAX_BATCH: usize = 500;
+
+/// Relays hinted for ids that get a query of their own, the ones hinted
+/// for the most ids first
+const MAX_HINTED_RELAYS: usize = 3;
+
+/// How long an id has to turn up before we ask again. Doubles every time
+/// it doesn't, up to [`MAX_RETRY_AFTER`].
+const RETRY_AFTER: Duration = Duration::from_secs(15);
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(15 * 60);
+
+/// Ids that weren't needed again for this long are forgotten, they've
+/// turned up or scrolled away
+const FORGET_AFTER: Duration = Duration::from_secs(60 * 60);
 
 #[must_use = "process_action should be used on this result"]
 pub enum SingleUnkIdAction {
@@ -79,34 +98,141 @@ impl SingleUnkIdAction {
     }
 }
 
-/// Unknown Id searcher
+/// An id we asked relays for
+#[derive(Debug, Clone, Copy)]
+struct Requested {
+    sent_at: Instant,
+    attempts: u32,
+}
+
+impl Requested {
+    /// When to ask again if it still hasn't turned up
+    fn retry_at(&self) -> Instant {
+        let doublings = self.attempts.saturating_sub(1).min(16);
+        let wait = RETRY_AFTER
+            .saturating_mul(1u32 << doublings)
+            .min(MAX_RETRY_AFTER);
+        self.sent_at + wait
+    }
+}
+
+/// The queries for one batch of unknown ids
+pub struct UnknownIdsBatch {
+    /// How many ids are in the batch
+    pub len: usize,
+
+    /// Every id in the batch, for relays we pick
+    pub filters: Vec<Filter>,
+
+    /// The ids relays were hinted for, by relay
+    pub hinted: Vec<(String, Vec<Filter>)>,
+}
+
+/// Unknown Id searcher. Ids collect for a moment and are asked for in one
+/// batch. Ids already asked for aren't asked again until they've had time
+/// to turn up, and wait longer every time they don't.
 #[derive(Default, Debug)]
 pub struct UnknownIds {
     ids: HashMap<UnknownId, HashSet<RelayUrl>>,
-    first_updated: Option<Instant>,
-    last_updated: Option<Instant>,
+
+    /// When the oldest id waiting in `ids` was added
+    batch_started: Option<Instant>,
+
+    /// Ids in flight or waiting to be retried
+    requested: HashMap<UnknownId, Requested>,
 }
 
 impl UnknownIds {
-    /// Simple debouncer
+    /// Whether the current batch has had long enough to fill up, or is full
     pub fn ready_to_send(&self) -> bool {
-        if self.ids.is_empty() {
-            return false;
-        }
+        self.ready_at(Instant::now())
+    }
 
-        // we trigger on first set
-        if self.first_updated == self.last_updated {
-            return true;
+    fn ready_at(&self, now: Instant) -> bool {
+        match self.batch_started {
+            Some(_) if self.ids.len() >= MAX_BATCH => true,
+            Some(started) => now.saturating_duration_since(started) >= BATCH_WINDOW,
+            None => false,
         }
+    }
 
-        let last_updated = if let Some(last) = self.last_updated {
-            last
-        } else {
-            // if we've
-            return true;
+    /// How long until the current batch is ready to send, to wake up for
+    pub fn send_in(&self) -> Option<Duration> {
+        let started = self.batch_started?;
+        Some(BATCH_WINDOW.saturating_sub(started.elapsed()))
+    }
+
+    /// Take the next batch of ids to ask for, and remember we asked
+    pub fn take_batch(&mut self) -> Option<UnknownIdsBatch> {
+        self.take_batch_at(Instant::now())
+    }
+
+    fn take_batch_at(&mut self, now: Instant) -> Option<UnknownIdsBatch> {
+        self.drop_requested(now);
+        self.requested
+            .retain(|_, requested| now.saturating_duration_since(requested.sent_at) < FORGET_AFTER);
+
+        let batch: Vec<(UnknownId, HashSet<RelayUrl>)> = {
+            let ids: Vec<UnknownId> = self.ids.keys().take(MAX_BATCH).copied().collect();
+            ids.into_iter()
+                .filter_map(|id| self.ids.remove_entry(&id))
+                .collect()
         };
+        self.batch_started = (!self.ids.is_empty()).then_some(now);
+        if batch.is_empty() {
+            return None;
+        }
+
+        for (id, _) in &batch {
+            let attempts = self.requested.get(id).map_or(0, |r| r.attempts) + 1;
+            self.requested.insert(
+                *id,
+                Requested {
+                    sent_at: now,
+                    attempts,
+                },
+            );
+        }
+
+        let mut by_relay: HashMap<&RelayUrl, Vec<&UnknownId>> = HashMap::new();
+        for (id, relays) in &batch {
+            for relay in relays {
+                by_relay.entry(relay).or_default().push(id);
+            }
+        }
+        let mut by_relay: Vec<(&RelayUrl, Vec<&UnknownId>)> = by_relay.into_iter().collect();
+        by_relay.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+        let hinted = by_relay
+            .into_iter()
+            .take(MAX_HINTED_RELAYS)
+            .filter_map(|(relay, ids)| Some((relay.to_string(), get_unknown_ids_filter(&ids)?)))
+            .collect();
+
+        let ids: Vec<&UnknownId> = batch.iter().map(|(id, _)| id).collect();
+        Some(UnknownIdsBatch {
+            len: ids.len(),
+            filters: get_unknown_ids_filter(&ids)?,
+            hinted,
+        })
+    }
 
-        Instant::now() - last_updated >= Duration::from_secs(2)
+    /// Forget ids that were already asked for and haven't been given long
+    /// enough to turn up
+    fn drop_requested(&mut self, now: Instant) {
+        let requested = &self.requested;
+        let before = self.ids.len();
+        self.ids.retain(|id, _| match requested.get(id) {
+            Some(requested) => requested.retry_at() <= now,
+            None => true,
+        });
+
+        let dropped = before - self.ids.len();
+        if dropped > 0 {
+            debug!("{dropped} unknown ids are already being looked for");
+        }
+        if self.ids.is_empty() {
+            self.batch_started = None;
+        }
     }
 
     pub fn ids_iter(&self) -> impl ExactSizeIterator<Item = &UnknownId> {
@@ -119,6 +245,7 @@ impl UnknownIds {
 
     pub fn clear(&mut self) {
         self.ids = HashMap::default();
+        self.batch_started = None;
     }
 
     pub fn filter(&self) -> Option<Vec<Filter>> {
@@ -126,13 +253,17 @@ impl UnknownIds {
         get_unknown_ids_filter(&ids)
     }
 
-    /// We've updated some unknown ids, update the last_updated time to now
+    /// We've added some unknown ids, start a batch for them unless
+    /// they're all being looked for already
     pub fn mark_updated(&mut self) {
-        let now = Instant::now();
-        if self.first_updated.is_none() {
-            self.first_updated = Some(now);
+        self.mark_updated_at(Instant::now());
+    }
+
+    fn mark_updated_at(&mut self, now: Instant) {
+        self.drop_requested(now);
+        if !self.ids.is_empty() && self.batch_started.is_none() {
+            self.batch_started = Some(now);
         }
-        self.last_updated = Some(now);
     }
 
     pub fn update_from_note_key(
@@ -375,3 +506,51 @@ fn get_unknown_ids_filter(ids: &[&UnknownId]) -> Option<Vec<Filter>> {
 
     Some(filters)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(unknown_ids: &mut UnknownIds, id: UnknownId, now: Instant) {
+        unknown_ids.ids_mut().entry(id).or_default();
+        unknown_ids.mark_updated_at(now);
+    }
+
+    #[test]
+    fn ids_are_batched_and_retried_with_backoff() {
+        let start = Instant::now();
+        let a = UnknownId::Pubkey(Pubkey::new([1; 32]));
+        let b = UnknownId::Id(NoteId::new([2; 32]));
+        let mut unknown_ids = UnknownIds::default();
+
+        add(&mut unknown_ids, a, start);
+        add(&mut unknown_ids, b, start + BATCH_WINDOW / 2);
+        assert!(!unknown_ids.ready_at(start + BATCH_WINDOW / 2));
+        assert!(unknown_ids.ready_at(start + BATCH_WINDOW));
+
+        let sent = start + BATCH_WINDOW;
+        let batch = unknown_ids.take_batch_at(sent).expect("batch");
+        assert_eq!(batch.len, 2);
+        assert_eq!(batch.filters.len(), 2);
+        assert!(!unknown_ids.ready_at(sent));
+
+        // still in flight, seeing it again doesn't ask again
+        add(&mut unknown_ids, a, sent + RETRY_AFTER / 2);
+        assert_eq!(unknown_ids.ids_iter().len(), 0);
+        assert!(!unknown_ids.ready_at(sent + RETRY_AFTER));
+
+        // never turned up, so it's asked for again, then waits twice as long
+        let retried = sent + RETRY_AFTER;
+        add(&mut unknown_ids, a, retried);
+        let batch = unknown_ids
+            .take_batch_at(retried + BATCH_WINDOW)
+            .expect("retry");
+        assert_eq!(batch.len, 1);
+
+        let resent = retried + BATCH_WINDOW;
+        add(&mut unknown_ids, a, resent + RETRY_AFTER);
+        assert_eq!(unknown_ids.ids_iter().len(), 0);
+        add(&mut unknown_ids, a, resent + RETRY_AFTER * 2);
+        assert_eq!(unknown_ids.ids_iter().len(), 1);
+    }
+}
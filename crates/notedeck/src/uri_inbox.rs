@@ -0,0 +1,164 @@
+//! Hands `nostr:` uris to a notedeck that's already running, so opening a
+//! link doesn't start a second copy. The running app listens on a
+//! loopback port and leaves the port and a token in the data dir. A new
+//! launch with uris sends them there and quits if they're taken.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+use crate::nip19::NostrUri;
+use crate::{DataPath, DataPathType};
+
+const INBOX_FILE: &str = "uri_inbox";
+
+/// A uri longer than this isn't one of ours
+const MAX_MESSAGE_LEN: u64 = 8192;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+const ACK: &str = "ok";
+
+/// Where uris sent by other launches show up
+pub struct UriInbox {
+    receiver: mpsc::Receiver<NostrUri>,
+    file: PathBuf,
+    contents: String,
+}
+
+impl UriInbox {
+    /// Start listening for uris. None if the port couldn't be opened,
+    /// other launches will start their own window then.
+    pub fn listen(path: &DataPath, ctx: &egui::Context) -> Option<Self> {
+        let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, 0)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("couldn't listen for nostr uris: {e}");
+                return None;
+            }
+        };
+        let port = listener.local_addr().ok()?.port();
+        let token = Uuid::new_v4().to_string();
+
+        let dir = path.path(DataPathType::Setting);
+        let _ = std::fs::create_dir_all(&dir);
+        let file = dir.join(INBOX_FILE);
+        let contents = format!("{port} {token}");
+        if let Err(e) = std::fs::write(&file, &contents) {
+            error!("couldn't write {INBOX_FILE}: {e}");
+            return None;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let Some(uri) = receive(stream, &token) else {
+                    continue;
+                };
+                info!("opening {uri:?} from another launch");
+                if sender.send(uri).is_err() {
+                    // the app is gone
+                    return;
+                }
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                ctx.request_repaint();
+            }
+        });
+
+        Some(Self {
+            receiver,
+            file,
+            contents,
+        })
+    }
+
+    /// Uris that came in since the last call
+    pub fn take(&self) -> Vec<NostrUri> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+impl Drop for UriInbox {
+    fn drop(&mut self) {
+        // a launch after ours may have taken the file over
+        let ours = std::fs::read_to_string(&self.file).is_ok_and(|c| c == self.contents);
+        if ours {
+            let _ = std::fs::remove_file(&self.file);
+        }
+    }
+}
+
+/// Read `<token>\n<uri>\n` off a connection, and tell the sender we've
+/// got it
+fn receive(stream: TcpStream, token: &str) -> Option<NostrUri> {
+    let _ = stream.set_read_timeout(Some(REPLY_TIMEOUT));
+    let mut reader = BufReader::new(stream.try_clone().ok()?.take(MAX_MESSAGE_LEN));
+
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    if line.trim_end() != token {
+        debug!("ignoring uri inbox connection without our token");
+        return None;
+    }
+
+    line.clear();
+    reader.read_line(&mut line).ok()?;
+    let uri = NostrUri::parse(&line)?;
+
+    let _ = (&stream).write_all(format!("{ACK}\n").as_bytes());
+    Some(uri)
+}
+
+/// Send `uris` to the notedeck that's running with the data at `path`.
+/// False if there isn't one or it didn't take all of them, the caller
+/// should open them itself.
+pub fn forward(path: &DataPath, uris: &[String]) -> bool {
+    if uris.is_empty() {
+        return false;
+    }
+
+    let file = path.path(DataPathType::Setting).join(INBOX_FILE);
+    let Ok(contents) = std::fs::read_to_string(file) else {
+        return false;
+    };
+    let Some((port, token)) = contents
+        .split_once(' ')
+        .and_then(|(port, token)| Some((port.parse::<u16>().ok()?, token)))
+    else {
+        return false;
+    };
+
+    uris.iter().all(|uri| match send(port, token, uri) {
+        Ok(()) => true,
+        Err(e) => {
+            // probably left over from a notedeck that crashed
+            debug!("couldn't hand {uri} to a running notedeck: {e}");
+            false
+        }
+    })
+}
+
+fn send(port: u16, token: &str, uri: &str) -> std::io::Result<()> {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let mut stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+    stream.write_all(format!("{token}\n{uri}\n").as_bytes())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    if reply.trim_end() == ACK {
+        Ok(())
+    } else {
+        Err(std::io::Error::other("no reply"))
+    }
+}
@@ -1,16 +1,44 @@
 use enostr::{Keypair, KeypairUnowned};
 use tokenator::{ParseError, TokenParser, TokenSerializable};
 
+const LOCKED_TOKEN: &str = "locked";
+
+use crate::signer::AccountSigner;
 use crate::wallet::ZapWallet;
 
 pub struct UserAccount {
     pub key: Keypair,
     pub wallet: Option<ZapWallet>,
+
+    /// A NIP-46 bunker or NIP-55 signer app holding the secret key for
+    /// this account
+    pub signer: Option<AccountSigner>,
+
+    /// The secret key encrypted with the app lock passphrase. While
+    /// we're locked, `key` only has the pubkey.
+    pub locked_key: Option<String>,
 }
 
 impl UserAccount {
     pub fn new(key: Keypair) -> Self {
-        Self { key, wallet: None }
+        Self {
+            key,
+            wallet: None,
+            signer: None,
+            locked_key: None,
+        }
+    }
+
+    /// Whether the secret key is locked away until the user enters the
+    /// app lock passphrase
+    pub fn is_locked(&self) -> bool {
+        self.locked_key.is_some() && self.key.secret_key.is_none()
+    }
+
+    /// Whether we can sign notes for this account, either locally or
+    /// with a remote signer
+    pub fn can_sign(&self) -> bool {
+        self.key.secret_key.is_some() || self.signer.is_some()
     }
 
     pub fn keypair(&self) -> KeypairUnowned {
@@ -24,11 +52,25 @@ impl UserAccount {
         self.wallet = Some(wallet);
         self
     }
+
+    pub fn with_signer(mut self, signer: impl Into<AccountSigner>) -> Self {
+        self.signer = Some(signer.into());
+        self
+    }
 }
 
 enum UserAccountRoute {
     Key(Keypair),
     Wallet(ZapWallet),
+    Signer(AccountSigner),
+    Locked(String),
+}
+
+fn parse_locked_key<'a>(parser: &mut TokenParser<'a>) -> Result<String, ParseError<'a>> {
+    parser.parse_all(|p| {
+        p.parse_token(LOCKED_TOKEN)?;
+        Ok(p.pull_token()?.to_owned())
+    })
 }
 
 impl TokenSerializable for UserAccount {
@@ -37,6 +79,8 @@ impl TokenSerializable for UserAccount {
     ) -> Result<Self, tokenator::ParseError<'a>> {
         let mut m_key = None;
         let mut m_wallet = None;
+        let mut m_signer = None;
+        let mut m_locked = None;
 
         loop {
             let res = TokenParser::alt(
@@ -44,17 +88,25 @@ impl TokenSerializable for UserAccount {
                 &[
                     |p| Ok(UserAccountRoute::Key(Keypair::parse_from_tokens(p)?)),
                     |p| Ok(UserAccountRoute::Wallet(ZapWallet::parse_from_tokens(p)?)),
+                    |p| {
+                        Ok(UserAccountRoute::Signer(AccountSigner::parse_from_tokens(
+                            p,
+                        )?))
+                    },
+                    |p| Ok(UserAccountRoute::Locked(parse_locked_key(p)?)),
                 ],
             );
 
             match res {
                 Ok(UserAccountRoute::Key(key)) => m_key = Some(key),
                 Ok(UserAccountRoute::Wallet(wallet)) => m_wallet = Some(wallet),
+                Ok(UserAccountRoute::Signer(signer)) => m_signer = Some(signer),
+                Ok(UserAccountRoute::Locked(locked)) => m_locked = Some(locked),
                 Err(ParseError::AltAllFailed) => break,
                 Err(_) => {}
             }
 
-            if m_key.is_some() && m_wallet.is_some() {
+            if m_key.is_some() && m_wallet.is_some() && m_signer.is_some() {
                 break;
             }
         }
@@ -69,17 +121,34 @@ impl TokenSerializable for UserAccount {
             user_acc = user_acc.with_wallet(wallet);
         };
 
+        if let Some(signer) = m_signer {
+            if signer.user_pubkey() == Some(&user_acc.key.pubkey) {
+                user_acc = user_acc.with_signer(signer);
+            }
+        }
+
+        user_acc.locked_key = m_locked;
+
         Ok(user_acc)
     }
 
     fn serialize_tokens(&self, writer: &mut tokenator::TokenWriter) {
-        self.key.serialize_tokens(writer);
+        if let Some(locked) = &self.locked_key {
+            // the secret key never goes to disk without the passphrase
+            self.key.pubkey.serialize_tokens(writer);
+            writer.write_token(LOCKED_TOKEN);
+            writer.write_token(locked);
+        } else {
+            self.key.serialize_tokens(writer);
+        }
 
-        let Some(wallet) = &self.wallet else {
-            return;
-        };
+        if let Some(wallet) = &self.wallet {
+            wallet.serialize_tokens(writer);
+        }
 
-        wallet.serialize_tokens(writer);
+        if let Some(signer) = &self.signer {
+            signer.serialize_tokens(writer);
+        }
     }
 }
 
@@ -120,4 +189,25 @@ mod tests {
 
         assert_eq!(wallet.wallet.uri, URI);
     }
+
+    #[test]
+    fn test_locked_account_doesnt_save_secret() {
+        let kp = FullKeypair::generate();
+        let mut acc = UserAccount::new(kp.to_keypair());
+        acc.locked_key = Some("ncryptsec1locked".to_owned());
+
+        let mut writer = TokenWriter::new("\t");
+        acc.serialize_tokens(&mut writer);
+
+        let serialized = writer.str();
+        assert!(!serialized.contains("eseckey"));
+
+        let data = &serialized.split("\t").collect::<Vec<&str>>();
+        let mut parser = TokenParser::new(data);
+        let new_acc = UserAccount::parse_from_tokens(&mut parser).unwrap();
+
+        assert_eq!(new_acc.key.pubkey, kp.pubkey);
+        assert!(new_acc.is_locked());
+        assert_eq!(new_acc.locked_key.as_deref(), Some("ncryptsec1locked"));
+    }
 }
@@ -0,0 +1,387 @@
+//! Inline video. There's no video decoder in the tree, so this leans on an
+//! `ffmpeg` binary: one run grabs a poster frame, which is cached next to
+//! the other media, and another streams raw frames while a video plays.
+//! Where ffmpeg isn't installed videos fail to load and the UI falls back
+//! to a link.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Instant;
+
+use egui::{ColorImage, TextureHandle, TextureOptions};
+use poll_promise::Promise;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::imgcache::{MediaCache, TextureState, TexturedImage, TexturesCache};
+use crate::Result;
+
+const VIDEO_EXTENSIONS: [&str; 4] = ["mp4", "webm", "mov", "m4v"];
+
+/// Frames are scaled and letterboxed to this size while decoding, so the
+/// reader always knows how many bytes make a frame
+pub const FRAME_WIDTH: usize = 640;
+pub const FRAME_HEIGHT: usize = 360;
+
+/// Whether `url` points at a video we can try to play
+pub fn is_video_url(url: &str) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+
+    Path::new(url.path())
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| VIDEO_EXTENSIONS.iter().any(|v| ext.eq_ignore_ascii_case(v)))
+}
+
+/// Poster frames and players for the videos in notes
+pub struct Videos {
+    poster_dir: PathBuf,
+    posters: TexturesCache,
+    players: HashMap<String, VideoPlayer>,
+}
+
+impl Videos {
+    pub fn new(poster_dir: PathBuf) -> Self {
+        Self {
+            poster_dir,
+            posters: TexturesCache::default(),
+            players: HashMap::new(),
+        }
+    }
+
+    pub fn rel_dir() -> &'static str {
+        "video"
+    }
+
+    /// The poster frame for `url`, from disk or extracted by ffmpeg
+    pub fn poster(&mut self, ctx: &egui::Context, url: &str) -> TextureState {
+        let poster_dir = &self.poster_dir;
+        self.posters
+            .handle_and_get_or_insert(url, || fetch_poster(ctx, poster_dir, url))
+    }
+
+    pub fn player(&self, url: &str) -> Option<&VideoPlayer> {
+        self.players.get(url)
+    }
+
+    pub fn player_mut(&mut self, url: &str) -> &mut VideoPlayer {
+        self.players
+            .entry(url.to_owned())
+            .or_insert_with(|| VideoPlayer::new(url))
+    }
+
+    /// Pick up new frames for `url` if it has a player
+    pub fn update_player(&mut self, ctx: &egui::Context, url: &str) {
+        if let Some(player) = self.players.get_mut(url) {
+            player.update(ctx);
+        }
+    }
+
+    /// Start playing `url`. Only one video plays at a time.
+    pub fn play(&mut self, ctx: &egui::Context, url: &str) {
+        for (other, player) in self.players.iter_mut() {
+            if other != url {
+                player.pause();
+            }
+        }
+        self.player_mut(url).play(ctx);
+    }
+}
+
+fn fetch_poster(
+    ctx: &egui::Context,
+    poster_dir: &Path,
+    url: &str,
+) -> Promise<Option<Result<TexturedImage>>> {
+    let ctx = ctx.clone();
+    let poster_dir = poster_dir.to_owned();
+    let url = url.to_owned();
+
+    Promise::spawn_thread("video_poster", move || {
+        let poster = load_poster(&poster_dir, &url).map(|image| {
+            TexturedImage::Static(ctx.load_texture(&url, image, TextureOptions::default()))
+        });
+        ctx.request_repaint();
+        Some(poster)
+    })
+}
+
+fn load_poster(poster_dir: &Path, url: &str) -> Result<ColorImage> {
+    let cached = poster_dir.join(MediaCache::key(url));
+    if let Ok(bytes) = std::fs::read(&cached) {
+        MediaCache::touch(&cached);
+        return decode_image(&bytes);
+    }
+
+    // a second in usually skips the fade from black, but short clips
+    // don't have one
+    let png = match extract_frame(url, Some("1")) {
+        Ok(png) => png,
+        Err(_) => extract_frame(url, None)?,
+    };
+    let image = decode_image(&png)?;
+
+    if let Err(e) = MediaCache::write(poster_dir, url, image.clone()) {
+        warn!("couldn't cache poster for {url}: {e}");
+    }
+
+    Ok(image)
+}
+
+/// One frame of `url` as png bytes
+fn extract_frame(url: &str, seek: Option<&str>) -> Result<Vec<u8>> {
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(["-v", "error"]);
+    if let Some(seek) = seek {
+        cmd.args(["-ss", seek]);
+    }
+    let output = cmd
+        .args(["-i", url, "-frames:v", "1", "-f", "image2pipe"])
+        .args(["-vcodec", "png", "-"])
+        .stdin(Stdio::null())
+        .output()?;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(crate::Error::Generic(format!(
+            "ffmpeg couldn't get a frame: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(output.stdout)
+}
+
+fn decode_image(bytes: &[u8]) -> Result<ColorImage> {
+    let image = image::load_from_memory(bytes)?.to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    Ok(ColorImage::from_rgba_unmultiplied(size, image.as_raw()))
+}
+
+/// Length of the video in seconds, if ffprobe can tell
+fn probe_duration(url: &str) -> Option<f64> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format=duration"])
+        .args(["-of", "default=noprint_wrappers=1:nokey=1", url])
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+/// A running ffmpeg that writes raw rgba frames to us in real time
+struct FrameStream {
+    child: Child,
+    frames: Receiver<ColorImage>,
+}
+
+impl FrameStream {
+    fn spawn(url: &str, start: f64) -> std::io::Result<Self> {
+        let filter = format!(
+            "scale={FRAME_WIDTH}:{FRAME_HEIGHT}:force_original_aspect_ratio=decrease,\
+             pad={FRAME_WIDTH}:{FRAME_HEIGHT}:(ow-iw)/2:(oh-ih)/2"
+        );
+
+        let mut child = Command::new("ffmpeg")
+            .args([
+                "-v",
+                "error",
+                "-re",
+                "-ss",
+                &format!("{start:.2}"),
+                "-i",
+                url,
+            ])
+            .args([
+                "-an", "-vf", &filter, "-f", "rawvideo", "-pix_fmt", "rgba", "-",
+            ])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let Some(mut stdout) = child.stdout.take() else {
+            let _ = child.kill();
+            return Err(std::io::Error::other("no stdout from ffmpeg"));
+        };
+
+        // a small buffer so a video that isn't being drawn stalls ffmpeg
+        // instead of piling up frames
+        let (sender, frames) = mpsc::sync_channel(2);
+        std::thread::spawn(move || {
+            let mut buf = vec![0; FRAME_WIDTH * FRAME_HEIGHT * 4];
+            while stdout.read_exact(&mut buf).is_ok() {
+                let frame = ColorImage::from_rgba_unmultiplied([FRAME_WIDTH, FRAME_HEIGHT], &buf);
+                if sender.send(frame).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self { child, frames })
+    }
+}
+
+impl Drop for FrameStream {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub struct VideoPlayer {
+    url: String,
+    frame: Option<TextureHandle>,
+    stream: Option<FrameStream>,
+    duration: Promise<Option<f64>>,
+    error: Option<String>,
+
+    /// Where playback last (re)started, and when
+    start_pos: f64,
+    started_at: Option<Instant>,
+}
+
+impl VideoPlayer {
+    fn new(url: &str) -> Self {
+        let probe_url = url.to_owned();
+        Self {
+            url: url.to_owned(),
+            frame: None,
+            stream: None,
+            duration: Promise::spawn_thread("video_duration", move || probe_duration(&probe_url)),
+            error: None,
+            start_pos: 0.0,
+            started_at: None,
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// The latest decoded frame. None until playback has started.
+    pub fn frame(&self) -> Option<&TextureHandle> {
+        self.frame.as_ref()
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn duration(&self) -> Option<f64> {
+        self.duration.ready().copied().flatten()
+    }
+
+    /// Seconds into the video
+    pub fn position(&self) -> f64 {
+        let pos = match self.started_at {
+            Some(started_at) => self.start_pos + started_at.elapsed().as_secs_f64(),
+            None => self.start_pos,
+        };
+
+        match self.duration() {
+            Some(duration) => pos.min(duration),
+            None => pos,
+        }
+    }
+
+    pub fn play(&mut self, ctx: &egui::Context) {
+        if self.is_playing() {
+            return;
+        }
+
+        match FrameStream::spawn(&self.url, self.start_pos) {
+            Ok(stream) => {
+                self.stream = Some(stream);
+                self.started_at = Some(Instant::now());
+                self.error = None;
+                ctx.request_repaint();
+            }
+            Err(e) => {
+                self.error = Some(format!("Couldn't start ffmpeg: {e}"));
+            }
+        }
+    }
+
+    pub fn pause(&mut self) {
+        if self.stream.take().is_some() {
+            self.start_pos = self.position();
+            self.started_at = None;
+        }
+    }
+
+    pub fn seek(&mut self, ctx: &egui::Context, pos: f64) {
+        let playing = self.is_playing();
+        self.stream = None;
+        self.started_at = None;
+        self.start_pos = pos.max(0.0);
+        if playing {
+            self.play(ctx);
+        }
+    }
+
+    /// Pick up decoded frames. Call once a frame while the player is
+    /// visible.
+    pub fn update(&mut self, ctx: &egui::Context) {
+        let Some(stream) = &self.stream else {
+            return;
+        };
+
+        let mut latest = None;
+        let mut ended = false;
+        loop {
+            match stream.frames.try_recv() {
+                Ok(frame) => latest = Some(frame),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    ended = true;
+                    break;
+                }
+            }
+        }
+
+        if let Some(image) = latest {
+            match &mut self.frame {
+                Some(texture) => texture.set(image, TextureOptions::LINEAR),
+                None => {
+                    self.frame = Some(ctx.load_texture(
+                        format!("video:{}", self.url),
+                        image,
+                        TextureOptions::LINEAR,
+                    ))
+                }
+            }
+        }
+
+        if ended {
+            debug!("video ended: {}", self.url);
+            if self.frame.is_none() {
+                self.error = Some("Couldn't play this video".to_owned());
+            }
+            self.stream = None;
+            self.started_at = None;
+            self.start_pos = 0.0;
+        } else {
+            ctx.request_repaint();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_video_url() {
+        assert!(is_video_url("https://example.com/clip.mp4"));
+        assert!(is_video_url("https://example.com/a/b/clip.WEBM?x=1"));
+        assert!(!is_video_url("https://example.com/image.png"));
+        assert!(!is_video_url("https://example.com/mp4"));
+        assert!(!is_video_url("not a url.mp4"));
+    }
+}
@@ -0,0 +1,477 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use enostr::{Pubkey, RelayPool};
+use nostrdb::{Filter, Ndb, Note, Subscription, Transaction};
+use poll_promise::Promise;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::{DataPath, JsonSettings};
+
+const WOT_FILE: &str = "wot.json";
+
+pub const MAX_WOT_HOPS: u8 = 3;
+
+/// Stop growing the graph past this many authors, three hops out from
+/// a big follow list is most of nostr
+const MAX_GRAPH_SIZE: usize = 100_000;
+
+/// Contact lists we ask relays for at most, closest authors first
+const MAX_FETCHED_LISTS: usize = 2_000;
+
+const AUTHORS_PER_FILTER: usize = 250;
+
+/// New contact lists trickle in one at a time, don't rebuild for each
+const REBUILD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// What to do with notes from outside the web of trust
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WotMode {
+    Hide,
+    Collapse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WotSettings {
+    pub enabled: bool,
+
+    /// 1 is only people we follow, 2 adds who they follow, and so on
+    pub max_hops: u8,
+    pub mode: WotMode,
+}
+
+impl Default for WotSettings {
+    fn default() -> Self {
+        WotSettings {
+            enabled: false,
+            max_hops: 2,
+            mode: WotMode::Collapse,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WotVerdict {
+    Show,
+    Collapse,
+    Hide,
+}
+
+/// How far authors are from the selected account by following contact
+/// lists (kind 3) in nostrdb. Used to tuck away notes from strangers in
+/// replies, hashtags and search, where spam tends to land.
+pub struct WebOfTrust {
+    settings: WotSettings,
+    file: JsonSettings<WotSettings>,
+
+    root: Option<Pubkey>,
+    distances: HashMap<[u8; 32], u8>,
+
+    /// A rebuild running off the UI thread, for the root it started with
+    building: Option<Promise<(Pubkey, Option<HashMap<[u8; 32], u8>>)>>,
+
+    /// Hops other features need the graph for, even with the filter off
+    required_hops: u8,
+
+    /// Every contact list that gets ingested, to notice graph changes
+    sub: Option<Subscription>,
+    /// Contact lists of the graph we asked relays for
+    remote_subid: Option<String>,
+    /// How many authors that subscription covers
+    fetched: usize,
+
+    dirty: bool,
+    last_rebuild: Option<Instant>,
+}
+
+impl WebOfTrust {
+    pub fn new(path: &DataPath) -> Self {
+        let file = JsonSettings::new(path, WOT_FILE);
+        let settings = file.load();
+
+        WebOfTrust {
+            settings,
+            file,
+            root: None,
+            distances: HashMap::new(),
+            building: None,
+            required_hops: 0,
+            sub: None,
+            remote_subid: None,
+            fetched: 0,
+            dirty: true,
+            last_rebuild: None,
+        }
+    }
+
+    pub fn settings(&self) -> WotSettings {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: WotSettings) {
+        if settings == self.settings {
+            return;
+        }
+
+        self.settings = WotSettings {
+            max_hops: settings.max_hops.clamp(1, MAX_WOT_HOPS),
+            ..settings
+        };
+        self.dirty = true;
+        self.last_rebuild = None;
+        self.file.save(&self.settings);
+    }
+
+    /// Keep the graph built out to `hops`, for things like notification
+    /// filters that need it whether or not the filter here is on
+    pub fn require_hops(&mut self, hops: u8) {
+        let hops = hops.min(MAX_WOT_HOPS);
+        if hops == self.required_hops {
+            return;
+        }
+        if hops > self.graph_hops() {
+            self.dirty = true;
+            self.last_rebuild = None;
+        }
+        self.required_hops = hops;
+    }
+
+    /// How far out the graph goes, 0 when nothing needs it
+    fn graph_hops(&self) -> u8 {
+        let filter_hops = if self.settings.enabled {
+            self.settings.max_hops
+        } else {
+            0
+        };
+        filter_hops.max(self.required_hops)
+    }
+
+    /// Follow distance from the selected account, None if they're
+    /// further than the graph reaches or we haven't built it
+    pub fn distance(&self, pubkey: &[u8; 32]) -> Option<u8> {
+        self.distances.get(pubkey).copied()
+    }
+
+    pub fn verdict(&self, author: &[u8; 32]) -> WotVerdict {
+        // an empty graph means we're still loading or the account
+        // follows nobody, hiding everything wouldn't help either way
+        if !self.settings.enabled || self.distances.len() <= 1 {
+            return WotVerdict::Show;
+        }
+
+        if self
+            .distance(author)
+            .is_some_and(|d| d <= self.settings.max_hops)
+        {
+            return WotVerdict::Show;
+        }
+
+        match self.settings.mode {
+            WotMode::Hide => WotVerdict::Hide,
+            WotMode::Collapse => WotVerdict::Collapse,
+        }
+    }
+
+    /// Keep the graph in step with the selected account. Called every
+    /// frame, so this is cheap unless something changed. The graph is
+    /// built on its own thread and swapped in when it's done.
+    pub fn update(&mut self, ndb: &mut Ndb, pool: &mut RelayPool, selected: Option<&Pubkey>) {
+        let max_hops = self.graph_hops();
+        if max_hops == 0 {
+            if self.sub.is_some() {
+                self.deactivate(ndb, pool);
+                self.distances.clear();
+            }
+            return;
+        }
+
+        if self.root.as_ref() != selected {
+            self.root = selected.copied();
+            self.distances.clear();
+            // whatever it finds is for the old account
+            self.building = None;
+            self.dirty = true;
+            self.last_rebuild = None;
+            self.stop_fetching(pool);
+        }
+
+        let Some(root) = self.root else {
+            return;
+        };
+
+        if self.sub.is_none() {
+            match ndb.subscribe(&[Filter::new().kinds([3]).build()]) {
+                Ok(sub) => self.sub = Some(sub),
+                Err(e) => error!("wot contact list subscription: {e}"),
+            }
+        }
+
+        self.poll(ndb);
+
+        if let Some(building) = self.building.take() {
+            match building.try_take() {
+                Ok((built_for, Some(distances))) => {
+                    if built_for == root {
+                        self.distances = distances;
+                        self.fetch_contact_lists(pool);
+                    }
+                }
+                Ok((_, None)) => {}
+                Err(building) => {
+                    self.building = Some(building);
+                    return;
+                }
+            }
+        }
+
+        let waiting = matches!(self.last_rebuild, Some(last) if last.elapsed() < REBUILD_INTERVAL);
+        if !self.dirty || waiting {
+            return;
+        }
+
+        let ndb = ndb.clone();
+        self.building = Some(Promise::spawn_thread("wot", move || {
+            let started = Instant::now();
+            let txn = match Transaction::new(&ndb) {
+                Ok(txn) => txn,
+                Err(e) => {
+                    error!("wot transaction: {e}");
+                    return (root, None);
+                }
+            };
+
+            let distances = follow_distances(max_hops, root.bytes(), |authors| {
+                newest_follows(&ndb, &txn, authors)
+            });
+            debug!(
+                "wot: {} authors within {} hops, built in {:?}",
+                distances.len(),
+                max_hops,
+                started.elapsed()
+            );
+            (root, Some(distances))
+        }));
+
+        self.dirty = false;
+        self.last_rebuild = Some(Instant::now());
+    }
+
+    /// A contact list in our graph changed, the graph needs rebuilding
+    fn poll(&mut self, ndb: &Ndb) {
+        let Some(sub) = self.sub else {
+            return;
+        };
+
+        let nks = ndb.poll_for_notes(sub, 100);
+        if nks.is_empty() {
+            return;
+        }
+
+        let Ok(txn) = Transaction::new(ndb) else {
+            return;
+        };
+
+        let max_hops = self.graph_hops();
+        let relevant = nks.into_iter().any(|nk| {
+            let Ok(note) = ndb.get_note_by_key(&txn, nk) else {
+                return false;
+            };
+            // follows of authors on the edge are out of range anyway
+            matches!(self.distances.get(note.pubkey()), Some(d) if *d < max_hops)
+                || self.distances.is_empty()
+        });
+
+        if relevant {
+            self.dirty = true;
+        }
+    }
+
+    /// Ask relays for the contact lists we need to see past the first
+    /// hop. Each one we get can pull more authors into range, so this
+    /// grows along with the graph until it's complete or at the cap.
+    fn fetch_contact_lists(&mut self, pool: &mut RelayPool) {
+        let mut authors: Vec<(&[u8; 32], u8)> = self
+            .distances
+            .iter()
+            .filter(|(_, d)| **d < self.graph_hops())
+            .map(|(pk, d)| (pk, *d))
+            .collect();
+        authors.sort_by_key(|(_, d)| *d);
+        authors.truncate(MAX_FETCHED_LISTS);
+
+        // the root is always there, and the subscription we have
+        // already covers anything we knew about back then
+        if authors.len() <= 1.max(self.fetched) {
+            return;
+        }
+
+        let filters: Vec<Filter> = authors
+            .chunks(AUTHORS_PER_FILTER)
+            .map(|chunk| {
+                Filter::new()
+                    .authors(chunk.iter().map(|(pk, _)| *pk))
+                    .kinds([3])
+                    .limit(chunk.len() as u64)
+                    .build()
+            })
+            .collect();
+
+        let subid = format!("wot-{}", Uuid::new_v4());
+        debug!("wot: fetching {} contact lists in {}", authors.len(), subid);
+        let fetched = authors.len();
+        self.stop_fetching(pool);
+        pool.subscribe(subid.clone(), filters);
+        self.remote_subid = Some(subid);
+        self.fetched = fetched;
+    }
+
+    fn stop_fetching(&mut self, pool: &mut RelayPool) {
+        if let Some(subid) = self.remote_subid.take() {
+            pool.unsubscribe(subid);
+        }
+        self.fetched = 0;
+    }
+
+    fn deactivate(&mut self, ndb: &mut Ndb, pool: &mut RelayPool) {
+        if let Some(sub) = self.sub.take() {
+            if let Err(e) = ndb.unsubscribe(sub) {
+                error!("wot unsubscribe: {e}");
+            }
+        }
+        self.stop_fetching(pool);
+        self.building = None;
+        self.root = None;
+    }
+
+    /// Whether an EOSE was for our contact list fetch. We keep the
+    /// subscription open for updates.
+    pub fn handle_eose(&self, subid: &str) -> bool {
+        self.remote_subid.as_deref() == Some(subid)
+    }
+}
+
+/// Breadth first over the follow graph. `follows_of` returns the
+/// follows of each author it's given that we have a contact list for.
+fn follow_distances(
+    max_hops: u8,
+    root: &[u8; 32],
+    mut follows_of: impl FnMut(&[[u8; 32]]) -> Vec<Vec<[u8; 32]>>,
+) -> HashMap<[u8; 32], u8> {
+    let mut distances = HashMap::from([(*root, 0)]);
+    let mut frontier = vec![*root];
+
+    for hop in 1..=max_hops {
+        let mut next = Vec::new();
+
+        for follows in follows_of(&frontier) {
+            for pk in follows {
+                if distances.len() >= MAX_GRAPH_SIZE {
+                    return distances;
+                }
+                if let Entry::Vacant(entry) = distances.entry(pk) {
+                    entry.insert(hop);
+                    next.push(pk);
+                }
+            }
+        }
+
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+
+    distances
+}
+
+/// The follows on the newest contact list of each author
+fn newest_follows(ndb: &Ndb, txn: &Transaction, authors: &[[u8; 32]]) -> Vec<Vec<[u8; 32]>> {
+    let mut newest: HashMap<&[u8; 32], Note> = HashMap::new();
+
+    for chunk in authors.chunks(AUTHORS_PER_FILTER) {
+        // replaced lists can still be in the db, leave room for them
+        let filter = Filter::new()
+            .authors(chunk)
+            .kinds([3])
+            .limit(chunk.len() as u64 * 2)
+            .build();
+        let Ok(results) = ndb.query(txn, &[filter], chunk.len() as i32 * 2) else {
+            continue;
+        };
+
+        for result in results {
+            let note = result.note;
+            match newest.entry(note.pubkey()) {
+                Entry::Occupied(mut entry) => {
+                    if note.created_at() > entry.get().created_at() {
+                        entry.insert(note);
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(note);
+                }
+            }
+        }
+    }
+
+    newest.values().map(follows).collect()
+}
+
+fn follows(note: &Note) -> Vec<[u8; 32]> {
+    let mut follows = Vec::new();
+
+    for tag in note.tags() {
+        if tag.count() < 2 {
+            continue;
+        }
+
+        if tag.get_unchecked(0).variant().str() != Some("p") {
+            continue;
+        }
+
+        if let Some(pk) = tag.get_unchecked(1).variant().id() {
+            follows.push(*pk);
+        }
+    }
+
+    follows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pk(n: u8) -> [u8; 32] {
+        [n; 32]
+    }
+
+    #[test]
+    fn test_follow_distances() {
+        // 1 follows 2 and 3, 2 follows 4, 4 follows 5, 3 follows 1 back
+        let graph: HashMap<[u8; 32], Vec<[u8; 32]>> = HashMap::from([
+            (pk(1), vec![pk(2), pk(3)]),
+            (pk(2), vec![pk(4)]),
+            (pk(3), vec![pk(1)]),
+            (pk(4), vec![pk(5)]),
+        ]);
+        let follows_of = |authors: &[[u8; 32]]| {
+            authors
+                .iter()
+                .filter_map(|a| graph.get(a).cloned())
+                .collect::<Vec<_>>()
+        };
+
+        let distances = follow_distances(2, &pk(1), follows_of);
+        assert_eq!(distances.get(&pk(1)), Some(&0));
+        assert_eq!(distances.get(&pk(2)), Some(&1));
+        assert_eq!(distances.get(&pk(3)), Some(&1));
+        assert_eq!(distances.get(&pk(4)), Some(&2));
+        assert_eq!(distances.get(&pk(5)), None);
+
+        let distances = follow_distances(3, &pk(1), follows_of);
+        assert_eq!(distances.get(&pk(5)), Some(&3));
+    }
+}
@@ -0,0 +1,289 @@
+//! NIP-75 zap goals. A goal is a note asking for some amount of sats,
+//! and the zap receipts that reference it count toward it. Zaps that
+//! land after the goal closes don't count.
+
+use enostr::{ClientMessage, Filter, NoteId, RelayPool};
+use lightning_invoice::Bolt11Invoice;
+use nostrdb::{Ndb, Note, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::debouncer::Debouncer;
+
+pub const ZAP_GOAL_KIND: u32 = 9041;
+const ZAP_RECEIPT_KIND: u32 = 9735;
+
+/// How long progress is good for before we add it up again from nostrdb
+const LOCAL_REFRESH: Duration = Duration::from_secs(5);
+
+/// Only fetch receipts for goals that were on screen this recently
+const SEEN_WINDOW: Duration = Duration::from_secs(5);
+
+/// Most receipts we look at per goal
+const MAX_RECEIPTS: i32 = 5000;
+
+/// Every receipt request goes out under this id, so each one replaces
+/// the last instead of piling up on relays
+const RECEIPTS_SUB_ID: &str = "zap-goal-receipts";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZapGoal {
+    /// What the goal is for, from the note's content
+    pub description: String,
+    pub summary: Option<String>,
+    pub image: Option<String>,
+    pub target_msats: u64,
+
+    /// Relays the receipts are meant to be sent to
+    pub relays: Vec<String>,
+
+    /// Unix time after which zaps stop counting
+    pub closed_at: Option<u64>,
+}
+
+impl ZapGoal {
+    pub fn from_note(note: &Note) -> Option<Self> {
+        if note.kind() != ZAP_GOAL_KIND {
+            return None;
+        }
+
+        let mut target_msats = None;
+        let mut relays = Vec::new();
+        let mut closed_at = None;
+        let mut summary = None;
+        let mut image = None;
+
+        for tag in note.tags() {
+            if tag.count() < 2 {
+                continue;
+            }
+            let value = |i| tag.get_unchecked(i).variant().str();
+
+            match value(0) {
+                Some("amount") => target_msats = value(1).and_then(|a| a.parse().ok()),
+                Some("relays") => {
+                    relays = (1..tag.count())
+                        .filter_map(|i| value(i).map(str::to_owned))
+                        .collect()
+                }
+                Some("closed_at") => closed_at = value(1).and_then(|at| at.parse().ok()),
+                Some("summary") => summary = value(1).map(str::to_owned),
+                Some("image") => image = value(1).map(str::to_owned),
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            description: note.content().to_owned(),
+            summary,
+            image,
+            target_msats: target_msats.filter(|a| *a > 0)?,
+            relays,
+            closed_at,
+        })
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed_at
+            .is_some_and(|at| at <= crate::time::unix_now())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZapGoalProgress {
+    pub raised_msats: u64,
+    pub zaps: u64,
+}
+
+impl ZapGoalProgress {
+    /// How far along the goal is, 1.0 or more once it's reached
+    pub fn fraction(&self, goal: &ZapGoal) -> f32 {
+        self.raised_msats as f32 / goal.target_msats as f32
+    }
+}
+
+/// A receipt as far as a goal cares
+struct Receipt {
+    created_at: u64,
+    msats: u64,
+}
+
+impl Receipt {
+    fn from_note(note: &Note) -> Option<Self> {
+        let bolt11 = note.tags().into_iter().find_map(|tag| {
+            if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some("bolt11") {
+                return None;
+            }
+            tag.get_unchecked(1).variant().str()
+        })?;
+
+        let msats = bolt11
+            .parse::<Bolt11Invoice>()
+            .ok()?
+            .amount_milli_satoshis()?;
+
+        Some(Self {
+            created_at: note.created_at(),
+            msats,
+        })
+    }
+}
+
+fn add_up(goal: &ZapGoal, receipts: impl IntoIterator<Item = Receipt>) -> ZapGoalProgress {
+    receipts
+        .into_iter()
+        .filter(|r| !goal.closed_at.is_some_and(|at| r.created_at > at))
+        .fold(ZapGoalProgress::default(), |mut progress, receipt| {
+            progress.raised_msats += receipt.msats;
+            progress.zaps += 1;
+            progress
+        })
+}
+
+struct GoalEntry {
+    progress: ZapGoalProgress,
+    counted: Instant,
+    last_seen: Instant,
+}
+
+/// Progress of the zap goals on screen. Receipts are added up from
+/// what's in nostrdb, and relays are asked for the receipts of every
+/// goal that's been seen lately.
+pub struct ZapGoals {
+    entries: HashMap<NoteId, GoalEntry>,
+
+    /// The goals whose receipts we last asked relays for
+    requested: HashSet<NoteId>,
+    debouncer: Debouncer,
+}
+
+impl Default for ZapGoals {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            requested: HashSet::new(),
+            debouncer: Debouncer::new(Duration::from_secs(1)),
+        }
+    }
+}
+
+impl ZapGoals {
+    /// How much has been zapped toward `goal`, adding it up again if
+    /// it's been a while
+    pub fn progress(
+        &mut self,
+        ndb: &Ndb,
+        txn: &Transaction,
+        goal_id: &[u8; 32],
+        goal: &ZapGoal,
+    ) -> ZapGoalProgress {
+        let id = NoteId::new(*goal_id);
+        let now = Instant::now();
+
+        let fresh = self
+            .entries
+            .get(&id)
+            .is_some_and(|entry| now.duration_since(entry.counted) < LOCAL_REFRESH);
+        if !fresh {
+            let progress = add_up(goal, local_receipts(ndb, txn, goal_id));
+            self.entries.insert(
+                id,
+                GoalEntry {
+                    progress,
+                    counted: now,
+                    last_seen: now,
+                },
+            );
+        }
+
+        let entry = self.entries.get_mut(&id).expect("just counted");
+        entry.last_seen = now;
+        entry.progress
+    }
+
+    /// Ask relays for the receipts of goals that are on screen, when that
+    /// set changes
+    pub fn send_requests(&mut self, pool: &mut RelayPool) {
+        if !self.debouncer.should_act() {
+            return;
+        }
+        self.debouncer.bounce();
+
+        self.entries
+            .retain(|_, entry| entry.last_seen.elapsed() < SEEN_WINDOW * 12);
+        let seen: HashSet<NoteId> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.last_seen.elapsed() < SEEN_WINDOW)
+            .map(|(id, _)| *id)
+            .collect();
+
+        if seen == self.requested {
+            return;
+        }
+
+        if seen.is_empty() {
+            pool.send(&ClientMessage::close(RECEIPTS_SUB_ID.to_owned()));
+        } else {
+            debug!("asking for zap receipts of {} goals", seen.len());
+            let filter = Filter::new()
+                .kinds([ZAP_RECEIPT_KIND as u64])
+                .events(seen.iter().map(|id| id.bytes()))
+                .limit(MAX_RECEIPTS as u64)
+                .build();
+            pool.send(&ClientMessage::req(
+                RECEIPTS_SUB_ID.to_owned(),
+                vec![filter],
+            ));
+        }
+        self.requested = seen;
+    }
+}
+
+fn local_receipts(ndb: &Ndb, txn: &Transaction, goal_id: &[u8; 32]) -> Vec<Receipt> {
+    let filter = Filter::new()
+        .kinds([ZAP_RECEIPT_KIND as u64])
+        .event(goal_id)
+        .limit(MAX_RECEIPTS as u64)
+        .build();
+
+    ndb.query(txn, &[filter], MAX_RECEIPTS)
+        .map(|results| {
+            results
+                .iter()
+                .filter_map(|result| Receipt::from_note(&result.note))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn late_zaps_dont_count() {
+        let goal = ZapGoal {
+            description: "new mic".to_owned(),
+            summary: None,
+            image: None,
+            target_msats: 100_000,
+            relays: vec![],
+            closed_at: Some(100),
+        };
+        let receipt = |created_at, msats| Receipt { created_at, msats };
+
+        let progress = add_up(
+            &goal,
+            [
+                receipt(10, 21_000),
+                receipt(100, 4_000),
+                receipt(101, 50_000),
+            ],
+        );
+        assert_eq!(progress.raised_msats, 25_000);
+        assert_eq!(progress.zaps, 2);
+        assert_eq!(progress.fraction(&goal), 0.25);
+    }
+}
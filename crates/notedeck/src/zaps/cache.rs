@@ -1,13 +1,19 @@
-use enostr::{NoteId, Pubkey};
+use enostr::{NoteId, Pubkey, RelayPool};
 use nostrdb::{Ndb, Transaction};
 use nwc::nostr::nips::nip47::PayInvoiceResponse;
 use poll_promise::Promise;
 use tokio::task::JoinError;
 
-use crate::{get_wallet_for_mut, Accounts, GlobalWallet, ZapError};
+use crate::{
+    get_wallet_for_mut, Accounts, GlobalWallet, SignPurpose, SignedEvent, SignerError,
+    UnsignedEvent, ZapError,
+};
 
 use super::{
-    networking::{fetch_invoice_lnurl, fetch_invoice_lud16, FetchedInvoice, FetchingInvoice},
+    networking::{
+        fetch_invoice_lnurl, fetch_invoice_lud16, lud16_to_lnurl, make_kind_9734, FetchedInvoice,
+        FetchingInvoice, ZapRequestSigner,
+    },
     zap::Zap,
 };
 
@@ -30,6 +36,7 @@ fn process_event(
     event: ZapEvent,
     accounts: &mut Accounts,
     global_wallet: &mut GlobalWallet,
+    pool: &mut RelayPool,
     ndb: &Ndb,
     txn: &Transaction,
 ) -> NextState {
@@ -37,7 +44,7 @@ fn process_event(
         ZapEvent::FetchInvoice {
             zap_ctx,
             sender_relays,
-        } => process_new_zap_event(zap_ctx, accounts, ndb, txn, sender_relays),
+        } => process_new_zap_event(zap_ctx, accounts, pool, ndb, txn, sender_relays),
         ZapEvent::SendNWC {
             zap_ctx,
             req_noteid,
@@ -76,71 +83,110 @@ fn process_event(
 
 fn process_new_zap_event(
     zap_ctx: ZapCtx,
-    accounts: &Accounts,
+    accounts: &mut Accounts,
+    pool: &mut RelayPool,
     ndb: &Ndb,
     txn: &Transaction,
     sender_relays: Vec<String>,
 ) -> NextState {
-    let Some(full_kp) = accounts
-        .get_selected_account()
-        .or_else(|| accounts.find_account(zap_ctx.key.sender.bytes()))
-        .and_then(|u| u.key.to_full())
+    let Some(account) = accounts
+        .find_account(zap_ctx.key.sender.bytes())
+        .or_else(|| accounts.get_selected_account())
+        .filter(|u| u.can_sign())
     else {
         return NextState::Event(EventResponse {
             id: zap_ctx.id,
             event: Err(ZappingError::InvalidAccount),
         });
     };
+    let sender = account.key.pubkey;
+    let local_nsec = account.key.secret_key.as_ref().map(|sk| sk.secret_bytes());
 
-    // TODO(kernelkind): support ZapTarget::Profile
-    let ZapTargetOwned::Note(note_target) = zap_ctx.key.target.clone() else {
-        return NextState::Event(EventResponse {
-            id: zap_ctx.id,
-            event: Err(ZappingError::UnsupportedOperation),
-        });
-    };
-
+    let target = zap_ctx.key.target.clone();
     let id = zap_ctx.id;
-    let promise = send_note_zap(
-        ndb,
-        txn,
-        note_target,
-        zap_ctx.msats,
-        &full_kp.secret_key.secret_bytes(),
-        sender_relays,
-    )
-    .map(|promise| ZapPromise::FetchingInvoice {
-        ctx: zap_ctx,
-        promise,
-    });
-    let Some(promise) = promise else {
+    let Some(address) = get_users_zap_endpoint(txn, ndb, target.pubkey()) else {
         return NextState::Event(EventResponse {
             id,
             event: Err(ZappingError::InvalidZapAddress),
         });
     };
 
-    NextState::Transition(promise)
+    let signer = match local_nsec {
+        Some(nsec) => ZapRequestSigner::Local(nsec),
+        None => {
+            let remote = sign_zap_request_remotely(
+                accounts,
+                pool,
+                &sender,
+                &address,
+                zap_ctx.msats,
+                &sender_relays,
+                target.clone(),
+            );
+
+            match remote {
+                Some(promise) => ZapRequestSigner::Remote(promise),
+                None => {
+                    return NextState::Event(EventResponse {
+                        id,
+                        event: Err(ZappingError::InvalidZapAddress),
+                    })
+                }
+            }
+        }
+    };
+
+    let promise = send_note_zap(address, zap_ctx.msats, signer, target, sender_relays);
+
+    NextState::Transition(ZapPromise::FetchingInvoice {
+        ctx: zap_ctx,
+        promise,
+    })
 }
 
 fn send_note_zap(
-    ndb: &Ndb,
-    txn: &Transaction,
-    note_target: NoteZapTargetOwned,
+    address: ZapAddress,
     msats: u64,
-    nsec: &[u8; 32],
+    signer: ZapRequestSigner,
+    target: ZapTargetOwned,
     relays: Vec<String>,
-) -> Option<FetchingInvoice> {
-    let address = get_users_zap_endpoint(txn, ndb, &note_target.zap_recipient)?;
+) -> FetchingInvoice {
+    match address {
+        ZapAddress::Lud16(s) => fetch_invoice_lud16(s, msats, signer, target, relays),
+        ZapAddress::Lud06(s) => fetch_invoice_lnurl(s, msats, signer, target, relays),
+    }
+}
 
-    let promise = match address {
-        ZapAddress::Lud16(s) => {
-            fetch_invoice_lud16(s, msats, *nsec, ZapTargetOwned::Note(note_target), relays)
-        }
-        ZapAddress::Lud06(s) => {
-            fetch_invoice_lnurl(s, msats, *nsec, ZapTargetOwned::Note(note_target), relays)
-        }
+/// Build the zap request and hand it to the sender's remote signer. The
+/// lnurl has to be in the request, so we work it out up front instead of
+/// after fetching the pay request like we do for local keys.
+fn sign_zap_request_remotely(
+    accounts: &mut Accounts,
+    pool: &mut RelayPool,
+    sender: &Pubkey,
+    address: &ZapAddress,
+    msats: u64,
+    relays: &[String],
+    target: ZapTargetOwned,
+) -> Option<Promise<Result<SignedEvent, SignerError>>> {
+    let session_key = accounts.signer_session_key(sender.bytes())?;
+
+    let lnurl = match address {
+        ZapAddress::Lud16(lud16) => lud16_to_lnurl(lud16).ok()?,
+        ZapAddress::Lud06(lnurl) => lnurl.clone(),
     };
+
+    let note = make_kind_9734(&lnurl, msats, "", &session_key, relays.to_vec(), target);
+    let unsigned = UnsignedEvent::from_note(&note).ok()?;
+
+    let (promise_sender, promise) = Promise::new();
+    accounts.sign_remote(
+        pool,
+        sender.bytes(),
+        &unsigned,
+        SignPurpose::Respond(promise_sender),
+    );
+
     Some(promise)
 }
 
@@ -270,6 +316,7 @@ impl Zaps {
         &mut self,
         accounts: &mut Accounts,
         global_wallet: &mut GlobalWallet,
+        pool: &mut RelayPool,
         ndb: &Ndb,
     ) {
         for i in (0..self.in_flight.len()).rev() {
@@ -291,7 +338,15 @@ impl Zaps {
             };
 
             let txn = nostrdb::Transaction::new(ndb).expect("txn");
-            match process_event(event_resp.id, event, accounts, global_wallet, ndb, &txn) {
+            match process_event(
+                event_resp.id,
+                event,
+                accounts,
+                global_wallet,
+                pool,
+                ndb,
+                &txn,
+            ) {
                 NextState::Event(event_resp) => {
                     self.zaps
                         .insert(event_resp.id, ZapState::Pending(event_resp.event));
@@ -462,7 +517,7 @@ pub enum ZapEvent {
 pub enum ZappingError {
     InvoiceFetchFailed(ZapError),
     InvalidAccount,
-    UnsupportedOperation, // TODO(kernelkind): support profile zaps
+    UnsupportedOperation,
     InvalidZapAddress,
     SenderNoWallet,
     InvalidNWCResponse(String),
@@ -558,7 +613,6 @@ pub enum ZapTarget<'a> {
 }
 
 impl ZapTargetOwned {
-    #[allow(dead_code)]
     pub fn pubkey(&self) -> &Pubkey {
         match &self {
             ZapTargetOwned::Profile(pubkey) => pubkey,
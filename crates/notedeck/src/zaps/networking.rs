@@ -1,4 +1,4 @@
-use crate::{zaps::ZapTargetOwned, ZapError};
+use crate::{zaps::ZapTargetOwned, SignedEvent, SignerError, ZapError};
 use enostr::NoteId;
 use nostrdb::NoteBuilder;
 use poll_promise::Promise;
@@ -13,6 +13,16 @@ pub struct FetchedInvoice {
 
 pub type FetchingInvoice = Promise<Result<Result<FetchedInvoice, ZapError>, JoinError>>;
 
+/// Who signs the zap request (kind 9734)
+pub enum ZapRequestSigner {
+    /// We have the sender's secret key
+    Local([u8; 32]),
+
+    /// The sender's remote signer is signing a zap request we already
+    /// built, wait for it
+    Remote(Promise<Result<SignedEvent, SignerError>>),
+}
+
 async fn fetch_pay_req_async(url: &Url) -> Result<LNUrlPayRequest, ZapError> {
     let (sender, promise) = Promise::new();
 
@@ -47,7 +57,7 @@ async fn fetch_pay_req_from_lud16(lud16: &str) -> Result<LNUrlPayRequest, ZapErr
 
 static HRP_LNURL: bech32::Hrp = bech32::Hrp::parse_unchecked("lnurl");
 
-fn lud16_to_lnurl(lud16: &str) -> Result<String, ZapError> {
+pub(super) fn lud16_to_lnurl(lud16: &str) -> Result<String, ZapError> {
     let endpoint_url = generate_endpoint_url(lud16)?;
 
     let url_str = endpoint_url.to_string();
@@ -56,7 +66,7 @@ fn lud16_to_lnurl(lud16: &str) -> Result<String, ZapError> {
     bech32::encode::<bech32::Bech32>(HRP_LNURL, data).map_err(|e| ZapError::Bech(e.to_string()))
 }
 
-fn make_kind_9734<'a>(
+pub(super) fn make_kind_9734<'a>(
     lnurl: &str,
     msats: u64,
     comment: &str,
@@ -131,36 +141,32 @@ fn endpoint_query_for_invoice<'a>(
     endpoint_base_url: &'a mut Url,
     msats: u64,
     lnurl: &str,
-    note: nostrdb::Note,
-) -> Result<&'a Url, ZapError> {
-    let nostr = note
-        .json()
-        .map_err(|e| ZapError::Serialization(format!("failed note to json: {e}")))?;
-
-    Ok(endpoint_base_url
+    nostr: &str,
+) -> &'a Url {
+    endpoint_base_url
         .query_pairs_mut()
         .append_pair("amount", &msats.to_string())
         .append_pair("lnurl", lnurl)
-        .append_pair("nostr", &nostr)
-        .finish())
+        .append_pair("nostr", nostr)
+        .finish()
 }
 
 pub fn fetch_invoice_lud16(
     lud16: String,
     msats: u64,
-    sender_nsec: [u8; 32],
+    signer: ZapRequestSigner,
     target: ZapTargetOwned,
     relays: Vec<String>,
 ) -> FetchingInvoice {
     Promise::spawn_async(tokio::spawn(async move {
-        fetch_invoice_lud16_async(&lud16, msats, &sender_nsec, target, relays).await
+        fetch_invoice_lud16_async(&lud16, msats, signer, target, relays).await
     }))
 }
 
 pub fn fetch_invoice_lnurl(
     lnurl: String,
     msats: u64,
-    sender_nsec: [u8; 32],
+    signer: ZapRequestSigner,
     target: ZapTargetOwned,
     relays: Vec<String>,
 ) -> FetchingInvoice {
@@ -170,7 +176,7 @@ pub fn fetch_invoice_lnurl(
             Err(e) => return Err(e),
         };
 
-        fetch_invoice_lnurl_async(&lnurl, &pay_req, msats, &sender_nsec, relays, target).await
+        fetch_invoice_lnurl_async(&lnurl, &pay_req, msats, signer, relays, target).await
     }))
 }
 
@@ -197,7 +203,7 @@ async fn fetch_invoice_lnurl_async(
     lnurl: &str,
     pay_req: &LNUrlPayRequest,
     msats: u64,
-    sender_nsec: &[u8; 32],
+    signer: ZapRequestSigner,
     relays: Vec<String>,
     target: ZapTargetOwned,
 ) -> Result<FetchedInvoice, ZapError> {
@@ -207,14 +213,25 @@ async fn fetch_invoice_lnurl_async(
     let mut base_url = Url::parse(&pay_req.callback_url)
         .map_err(|e| ZapError::EndpointError(format!("invalid callback url from endpoint: {e}")))?;
 
-    let (query, noteid) = {
-        let comment: &str = "";
-        let note = make_kind_9734(lnurl, msats, comment, sender_nsec, relays, target);
-        let noteid = NoteId::new(*note.id());
-        let query = endpoint_query_for_invoice(&mut base_url, msats, lnurl, note)?;
-        (query, noteid)
+    let (nostr, noteid) = match signer {
+        ZapRequestSigner::Local(sender_nsec) => {
+            let comment: &str = "";
+            let note = make_kind_9734(lnurl, msats, comment, &sender_nsec, relays, target);
+            let json = note
+                .json()
+                .map_err(|e| ZapError::Serialization(format!("failed note to json: {e}")))?;
+            (json, NoteId::new(*note.id()))
+        }
+
+        ZapRequestSigner::Remote(promise) => {
+            let signed = tokio::task::block_in_place(|| promise.block_and_take())
+                .map_err(|e| ZapError::Signer(e.to_string()))?;
+            (signed.json, signed.id)
+        }
     };
 
+    let query = endpoint_query_for_invoice(&mut base_url, msats, lnurl, &nostr);
+
     let res = fetch_invoice(query).await;
     res.map(|i| FetchedInvoice {
         invoice: i.invoice,
@@ -225,7 +242,7 @@ async fn fetch_invoice_lnurl_async(
 async fn fetch_invoice_lud16_async(
     lud16: &str,
     msats: u64,
-    sender_nsec: &[u8; 32],
+    signer: ZapRequestSigner,
     target: ZapTargetOwned,
     relays: Vec<String>,
 ) -> Result<FetchedInvoice, ZapError> {
@@ -233,7 +250,7 @@ async fn fetch_invoice_lud16_async(
 
     let lnurl = lud16_to_lnurl(lud16)?;
 
-    fetch_invoice_lnurl_async(&lnurl, &pay_req, msats, sender_nsec, relays, target).await
+    fetch_invoice_lnurl_async(&lnurl, &pay_req, msats, signer, relays, target).await
 }
 
 async fn fetch_invoice(req: &Url) -> Result<LNInvoice, ZapError> {
@@ -301,6 +318,7 @@ mod tests {
 
     use super::{
         fetch_invoice_lnurl, fetch_invoice_lud16, fetch_pay_req_from_lud16, lud16_to_lnurl,
+        ZapRequestSigner,
     };
 
     #[ignore] // don't run this test automatically since it sends real http
@@ -348,7 +366,7 @@ mod tests {
             fetch_invoice_lud16(
                 "jb55@sendsats.lol".to_owned(),
                 1000,
-                FullKeypair::generate().secret_key.to_secret_bytes(),
+                ZapRequestSigner::Local(FullKeypair::generate().secret_key.to_secret_bytes()),
                 crate::zaps::ZapTargetOwned::Note(crate::NoteZapTargetOwned {
                     note_id: NoteId::new([0; 32]),
                     zap_recipient: kp.pubkey,
@@ -389,7 +407,7 @@ mod tests {
             fetch_invoice_lnurl(
                 lnurl.to_owned(),
                 1000,
-                kp.secret_key.to_secret_bytes(),
+                ZapRequestSigner::Local(kp.secret_key.to_secret_bytes()),
                 crate::zaps::ZapTargetOwned::Note(crate::NoteZapTargetOwned {
                     note_id: NoteId::new([0; 32]),
                     zap_recipient: kp.pubkey,
@@ -0,0 +1,102 @@
+package com.damus.notedeck;
+
+import android.app.Activity;
+import android.content.ActivityNotFoundException;
+import android.content.Intent;
+import android.net.Uri;
+import android.util.Log;
+
+import java.util.HashMap;
+import java.util.Map;
+
+/**
+ * Talks to NIP-55 signer apps like Amber. Requests go out as
+ * nostrsigner: intents and the results are handed back to notedeck
+ * by id.
+ */
+public class ExternalSigner {
+    private static final String TAG = "ExternalSigner";
+    private static final int FIRST_REQUEST_CODE = 0x5500;
+
+    private final Activity activity;
+    private final Map<Integer, String> pending = new HashMap<>();
+    private int nextRequestCode = FIRST_REQUEST_CODE;
+
+    private static native void nativeAttach(Activity activity);
+    private static native void nativeOnResult(String id, String result, String event, String pkg, boolean rejected);
+
+    public ExternalSigner(Activity activity) {
+        this.activity = activity;
+        nativeAttach(activity);
+    }
+
+    public boolean isInstalled() {
+        Intent intent = new Intent(Intent.ACTION_VIEW, Uri.parse("nostrsigner:"));
+        return !activity.getPackageManager().queryIntentActivities(intent, 0).isEmpty();
+    }
+
+    public boolean launch(String id, String type, String content, String pubkey, String currentUser, String pkg) {
+        if (!isInstalled()) {
+            return false;
+        }
+
+        Intent intent = new Intent(Intent.ACTION_VIEW, Uri.parse("nostrsigner:" + Uri.encode(content)));
+        if (pkg != null) {
+            intent.setPackage(pkg);
+        }
+        intent.putExtra("type", type);
+        intent.putExtra("id", id);
+        if (pubkey != null) {
+            intent.putExtra("pubkey", pubkey);
+        }
+        if (currentUser != null) {
+            intent.putExtra("current_user", currentUser);
+        }
+        intent.addFlags(Intent.FLAG_ACTIVITY_SINGLE_TOP | Intent.FLAG_ACTIVITY_CLEAR_TOP);
+
+        final int requestCode;
+        synchronized (pending) {
+            requestCode = nextRequestCode++;
+            pending.put(requestCode, id);
+        }
+
+        activity.runOnUiThread(() -> {
+            try {
+                activity.startActivityForResult(intent, requestCode);
+            } catch (ActivityNotFoundException e) {
+                Log.e(TAG, "no signer app for " + type, e);
+                synchronized (pending) {
+                    pending.remove(requestCode);
+                }
+                nativeOnResult(id, null, null, null, true);
+            }
+        });
+
+        return true;
+    }
+
+    /** Returns false if the result wasn't for one of our requests */
+    public boolean onActivityResult(int requestCode, int resultCode, Intent data) {
+        String id;
+        synchronized (pending) {
+            id = pending.remove(requestCode);
+        }
+        if (id == null) {
+            return false;
+        }
+
+        if (resultCode != Activity.RESULT_OK || data == null) {
+            nativeOnResult(id, null, null, null, true);
+            return true;
+        }
+
+        nativeOnResult(
+            id,
+            data.getStringExtra("result"),
+            data.getStringExtra("event"),
+            data.getStringExtra("package"),
+            false
+        );
+        return true;
+    }
+}
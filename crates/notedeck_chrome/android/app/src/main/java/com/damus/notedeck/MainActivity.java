@@ -1,5 +1,6 @@
 package com.damus.notedeck;
 
+import android.content.Intent;
 import android.os.Bundle;
 import android.util.Log;
 import android.view.MotionEvent;
@@ -22,6 +23,7 @@ public class MainActivity extends GameActivity {
 
   private native void nativeOnKeyboardHeightChanged(int height);
   private KeyboardHeightHelper keyboardHelper;
+  private ExternalSigner externalSigner;
   
   @Override
   protected void onCreate(Bundle savedInstanceState) {
@@ -30,6 +32,7 @@ public class MainActivity extends GameActivity {
       setupInsets();
       //setupFullscreen()
       keyboardHelper = new KeyboardHeightHelper(this);
+      externalSigner = new ExternalSigner(this);
       
       super.onCreate(savedInstanceState);
   }
@@ -78,6 +81,22 @@ public class MainActivity extends GameActivity {
       WindowCompat.setDecorFitsSystemWindows(getWindow(), true);
   }
   
+  // called from rust, see platform/android.rs
+  public boolean isExternalSignerInstalled() {
+      return externalSigner.isInstalled();
+  }
+
+  public boolean launchExternalSigner(String id, String type, String content, String pubkey, String currentUser, String pkg) {
+      return externalSigner.launch(id, type, content, pubkey, currentUser, pkg);
+  }
+
+  @Override
+  protected void onActivityResult(int requestCode, int resultCode, Intent data) {
+      if (!externalSigner.onActivityResult(requestCode, resultCode, data)) {
+          super.onActivityResult(requestCode, resultCode, data);
+      }
+  }
+
   @Override
   public void onResume() {
       super.onResume();
@@ -6,8 +6,8 @@ use egui::{vec2, Button, Label, Layout, Rect, RichText, ThemePreference, Widget}
 use egui_extras::{Size, StripBuilder};
 use nostrdb::{ProfileRecord, Transaction};
 use notedeck::{
-    profile::get_profile_url, App, AppAction, AppContext, NotedeckTextStyle, UserAccount,
-    WalletType,
+    profile::get_profile_url, AccountColors, App, AppAction, AppContext, NotedeckTextStyle,
+    StyleOverrides, UserAccount, WalletType,
 };
 use notedeck_columns::{timeline::kind::ListKind, timeline::TimelineKind, Damus};
 
@@ -22,6 +22,13 @@ pub struct Chrome {
     open: bool,
     tab_selected: i32,
     apps: Vec<NotedeckApp>,
+
+    /// Whether the high contrast visuals are in place, None until they've
+    /// been set from the accessibility settings
+    high_contrast: Option<bool>,
+
+    /// The user's `style.toml`, loaded on the first frame
+    style_overrides: Option<StyleOverrides>,
 }
 
 impl Default for Chrome {
@@ -31,6 +38,8 @@ impl Default for Chrome {
             tab_selected: 0,
             open: true,
             apps: vec![],
+            high_contrast: None,
+            style_overrides: None,
         }
     }
 }
@@ -128,7 +137,7 @@ impl ChromePanelAction {
             }
 
             Self::Settings => {
-                Self::columns_navigate(ctx, chrome, notedeck_columns::Route::Relays);
+                Self::columns_navigate(ctx, chrome, notedeck_columns::Route::Settings);
             }
 
             Self::Wallet => {
@@ -403,6 +412,22 @@ impl Chrome {
 
 impl notedeck::App for Chrome {
     fn update(&mut self, ctx: &mut notedeck::AppContext, ui: &mut egui::Ui) -> Option<AppAction> {
+        let style_overrides = self.style_overrides.get_or_insert_with(|| {
+            let mut overrides = StyleOverrides::new(ctx.path);
+            overrides.apply(ui.ctx());
+            overrides
+        });
+
+        let high_contrast = ctx.accessibility.settings().high_contrast;
+        if self.high_contrast != Some(high_contrast) {
+            crate::theme::apply_visuals(ui.ctx(), notedeck::ui::is_oled(), high_contrast);
+            style_overrides.visuals_changed(ui.ctx());
+            self.high_contrast = Some(high_contrast);
+        }
+
+        #[cfg(debug_assertions)]
+        style_overrides.reload_if_changed(ui.ctx());
+
         if let Some(action) = self.show(ctx, ui) {
             action.process(ctx, self, ui);
         }
@@ -619,8 +644,10 @@ fn chrome_handle_app_action(
                 .decks_cache
                 .active_columns_mut(ctx.accounts)
                 .unwrap();
-            let m_action = notedeck_columns::actionbar::execute_and_process_note_action(
+            let result = notedeck_columns::actionbar::execute_and_process_note_action(
                 note_action,
+                &mut columns.note_action_middleware,
+                &mut columns.undo,
                 ctx.ndb,
                 cols,
                 0,
@@ -636,10 +663,17 @@ fn chrome_handle_app_action(
                 ui,
             );
 
-            if let Some(action) = m_action {
-                let col = cols.column_mut(0);
+            match result {
+                Ok(Some(action)) => {
+                    let col = cols.column_mut(0);
 
-                action.process(&mut col.router, &mut col.sheet_router);
+                    action.process(&mut col.router, &mut col.sheet_router);
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    tracing::error!("note action failed: {err}");
+                    notedeck::toast::error(ui.ctx(), err.to_string());
+                }
             }
         }
     }
@@ -654,8 +688,15 @@ fn pfp_button(ctx: &mut AppContext, ui: &mut egui::Ui) -> egui::Response {
 
     let txn = Transaction::new(ctx.ndb).expect("should be able to create txn");
     let profile_url = get_account_url(&txn, ctx.ndb, ctx.accounts.get_selected_account());
+    let account_color = ctx
+        .accounts
+        .selected_account_pubkey_bytes()
+        .and_then(|pk| AccountColors::new(ctx.accounts).get(pk));
 
     let mut widget = ProfilePic::new(ctx.img_cache, profile_url).size(cur_pfp_size);
+    if let Some(color) = account_color {
+        widget = widget.border(egui::Stroke::new(2.0, color));
+    }
 
     ui.put(helper.get_animation_rect(), &mut widget);
 
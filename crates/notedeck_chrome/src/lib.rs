@@ -8,5 +8,8 @@ mod android;
 mod app;
 mod chrome;
 
+#[cfg(test)]
+mod snapshot;
+
 pub use app::NotedeckApp;
 pub use chrome::Chrome;
@@ -1,7 +1,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 // hide console window on Windows in release
 
-use notedeck::{DataPath, DataPathType, Notedeck};
+use notedeck::{uri_inbox, Args, DataPath, DataPathType, Notedeck};
 use notedeck_chrome::{
     setup::{generate_native_options, setup_chrome},
     Chrome, NotedeckApp,
@@ -72,6 +72,13 @@ async fn main() {
     // This guard must be scoped for the duration of the entire program so all logs will be written
     let _guard = setup_logging(&path);
 
+    // a notedeck that's already running opens the links instead
+    let (args, _) = Args::parse(&std::env::args().skip(1).collect::<Vec<_>>());
+    let inbox_path = args.datapath.as_ref().map_or(path.clone(), DataPath::new);
+    if uri_inbox::forward(&inbox_path, &args.open_uris) {
+        return;
+    }
+
     let _res = eframe::run_native(
         "Damus Notedeck",
         generate_native_options(path),
@@ -26,8 +26,7 @@ pub fn setup_chrome(ctx: &egui::Context, args: &notedeck::Args, theme: ThemePref
         info!("Loaded theme {:?} from disk", theme);
         o.theme_preference = theme;
     });
-    ctx.set_visuals_of(egui::Theme::Dark, theme::dark_mode(is_oled));
-    ctx.set_visuals_of(egui::Theme::Light, theme::light_mode());
+    theme::apply_visuals(ctx, is_oled, false);
     setup_cc(ctx, is_mobile);
 }
 
@@ -0,0 +1,454 @@
+//! Headless layout snapshots of note rendering.
+//!
+//! [`Snapshot`] renders notes out of a throwaway nostrdb with the app's
+//! fonts and dark theme, in a fixed size window, without a GPU. What egui
+//! drew is flattened into a plain text layout tree: every row of text,
+//! every image and every filled shape, with positions rounded to whole
+//! points. The tree is compared against `snapshots/<name>.txt`, so a
+//! change in how notes wrap, truncate, lay out mentions or size their
+//! media fails a test with a diff.
+//!
+//! Snapshots live in `snapshots/` and are committed with the tests. A
+//! missing one is recorded the first time its test runs locally, and
+//! should be committed along with it. On CI (`CI` is set) a missing
+//! snapshot fails like a changed one, so a baseline that was never
+//! committed can't pass there. To accept an intentional layout change,
+//! rerun the tests with `UPDATE_SNAPSHOTS=1` and commit the rewritten
+//! files.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use egui::epaint::{ClippedShape, Shape};
+use egui::{Color32, Rect, TextureId, ThemePreference};
+use enostr::{FullKeypair, Keypair, RelayPool, SecretKey};
+use nostrdb::{Config, IngestMetadata, Ndb, NoteBuildOptions, NoteBuilder, NoteKey, Transaction};
+use notedeck::{
+    Counts, Images, JobPool, NoteCache, NoteContext, PaidContent, Polls, ZapGoals, Zaps,
+};
+use notedeck_ui::jobs::JobsCache;
+use notedeck_ui::{NoteOptions, NoteView};
+use tempfile::TempDir;
+
+use crate::{setup, theme};
+
+/// Frames to run before capturing. Fonts only take effect on the frame
+/// after they're set, and bundled icons finish decoding on the next one.
+const WARMUP_FRAMES: usize = 3;
+
+pub struct Snapshot {
+    ctx: egui::Context,
+    size: egui::Vec2,
+    ndb: Ndb,
+    img_cache: Images,
+    note_cache: NoteCache,
+    zaps: Zaps,
+    pool: RelayPool,
+    job_pool: JobPool,
+    counts: Counts,
+    polls: Polls,
+    zap_goals: ZapGoals,
+    paid_content: PaidContent,
+    jobs: JobsCache,
+    _dir: TempDir,
+}
+
+impl Snapshot {
+    pub fn new() -> Self {
+        let dir = TempDir::new().expect("snapshot dir");
+        let ndb =
+            Ndb::new(dir.path().to_str().expect("snapshot dir"), &Config::new()).expect("ndb");
+        let img_cache = Images::new(dir.path().join("cache"));
+
+        let ctx = egui::Context::default();
+        ctx.options_mut(|o| o.theme_preference = ThemePreference::Dark);
+        ctx.set_visuals_of(egui::Theme::Dark, theme::dark_mode(false));
+        setup::setup_cc(&ctx, false);
+
+        Self {
+            ctx,
+            size: egui::vec2(600.0, 1000.0),
+            ndb,
+            img_cache,
+            note_cache: NoteCache::default(),
+            zaps: Zaps::default(),
+            pool: RelayPool::new(),
+            job_pool: JobPool::default(),
+            counts: Counts::default(),
+            polls: Polls::default(),
+            zap_goals: ZapGoals::default(),
+            paid_content: PaidContent::default(),
+            jobs: JobsCache::default(),
+            _dir: dir,
+        }
+    }
+
+    /// Window size in points. Anything under 550 wide gets the mobile
+    /// layout.
+    pub fn size(mut self, size: egui::Vec2) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Add a signed event to the database and wait for it to be written
+    pub fn ingest(&self, json: &str) -> NoteKey {
+        let id = event_id(json);
+        self.ndb
+            .process_event_with(
+                &format!("[\"EVENT\",{json}]"),
+                IngestMetadata::new().client(true),
+            )
+            .expect("ingest");
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let txn = Transaction::new(&self.ndb).expect("txn");
+            if let Ok(key) = self.ndb.get_notekey_by_id(&txn, &id) {
+                return key;
+            }
+
+            assert!(Instant::now() < deadline, "event never made it into ndb");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Render a single note and return its layout tree
+    pub fn note(&mut self, key: NoteKey, options: NoteOptions) -> String {
+        self.render(|ui, note_context, jobs| {
+            let ndb = note_context.ndb;
+            let txn = Transaction::new(ndb).expect("txn");
+            let note = ndb.get_note_by_key(&txn, key).expect("snapshot note");
+            NoteView::new(note_context, None, &note, options, jobs).show(ui);
+        })
+    }
+
+    /// Render notes one after the other like a timeline does
+    pub fn timeline(&mut self, keys: &[NoteKey], options: NoteOptions) -> String {
+        self.render(|ui, note_context, jobs| {
+            let ndb = note_context.ndb;
+            let txn = Transaction::new(ndb).expect("txn");
+            for key in keys {
+                let note = ndb.get_note_by_key(&txn, *key).expect("snapshot note");
+                NoteView::new(note_context, None, &note, options, jobs).show(ui);
+                notedeck_ui::hline(ui);
+            }
+        })
+    }
+
+    fn render(
+        &mut self,
+        mut add_contents: impl FnMut(&mut egui::Ui, &mut NoteContext, &mut JobsCache),
+    ) -> String {
+        let input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(egui::Pos2::ZERO, self.size)),
+            time: Some(0.0),
+            ..Default::default()
+        };
+
+        let mut shapes = Vec::new();
+        for _ in 0..WARMUP_FRAMES {
+            let output = self.ctx.run(input.clone(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let mut note_context = NoteContext {
+                        ndb: &self.ndb,
+                        img_cache: &mut self.img_cache,
+                        note_cache: &mut self.note_cache,
+                        zaps: &mut self.zaps,
+                        pool: &mut self.pool,
+                        job_pool: &mut self.job_pool,
+                        counts: &mut self.counts,
+                        polls: &mut self.polls,
+                        zap_goals: &mut self.zap_goals,
+                        paid_content: &mut self.paid_content,
+                        current_account_has_wallet: false,
+                        current_account_can_sign: false,
+                        acting_pubkey: None,
+                        account_colors: Default::default(),
+                        truncate_lines: Some(notedeck::DEFAULT_TRUNCATE_LINES),
+                    };
+                    add_contents(ui, &mut note_context, &mut self.jobs);
+                });
+            });
+            shapes = output.shapes;
+        }
+
+        layout_tree(&shapes)
+    }
+}
+
+/// Flatten what egui painted into one line per text row, image and
+/// filled shape, in paint order
+pub fn layout_tree(shapes: &[ClippedShape]) -> String {
+    let mut out = String::new();
+    for clipped in shapes {
+        describe(&clipped.shape, clipped.clip_rect, &mut out);
+    }
+    out
+}
+
+fn describe(shape: &Shape, clip: Rect, out: &mut String) {
+    if !clip.intersects(shape.visual_bounding_rect()) {
+        return;
+    }
+
+    match shape {
+        Shape::Vec(shapes) => {
+            for shape in shapes {
+                describe(shape, clip, out);
+            }
+        }
+        Shape::Text(text) => {
+            for row in &text.galley.rows {
+                let line: String = row.glyphs.iter().map(|glyph| glyph.chr).collect();
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let rect = row.rect.translate(text.pos.to_vec2());
+                let _ = writeln!(out, "text {line:?} {}", rounded(rect));
+            }
+        }
+        _ if shape.texture_id() != TextureId::default() => {
+            let _ = writeln!(out, "image {}", rounded(shape.visual_bounding_rect()));
+        }
+        Shape::Circle(circle) if circle.fill != Color32::TRANSPARENT => {
+            let _ = writeln!(out, "circle {}", rounded(shape.visual_bounding_rect()));
+        }
+        Shape::Rect(rect) if rect.fill != Color32::TRANSPARENT => {
+            let _ = writeln!(out, "rect {}", rounded(rect.rect));
+        }
+        _ => {}
+    }
+}
+
+fn rounded(rect: Rect) -> String {
+    format!(
+        "[{} {} {}x{}]",
+        rect.min.x.round() as i32,
+        rect.min.y.round() as i32,
+        rect.width().round() as i32,
+        rect.height().round() as i32,
+    )
+}
+
+fn snapshot_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("snapshots")
+        .join(format!("{name}.txt"))
+}
+
+/// Compare a layout tree against its recorded snapshot. On a mismatch
+/// the new tree is written next to it as `<name>.new.txt`. A missing
+/// snapshot is recorded, except on CI. With `UPDATE_SNAPSHOTS=1` the
+/// snapshot is always rewritten.
+pub fn assert_snapshot(name: &str, tree: &str) {
+    let path = snapshot_path(name);
+    let expected = std::fs::read_to_string(&path).ok();
+
+    let update = std::env::var("UPDATE_SNAPSHOTS").as_deref() == Ok("1");
+    let record = expected.is_none() && std::env::var_os("CI").is_none();
+    if update || record {
+        std::fs::create_dir_all(path.parent().expect("snapshot dir")).expect("snapshot dir");
+        std::fs::write(&path, tree).expect("write snapshot");
+        if record {
+            eprintln!(
+                "recorded snapshot '{name}' at {}, commit it with the test",
+                path.display()
+            );
+        }
+        return;
+    }
+
+    if expected.as_deref() == Some(tree) {
+        return;
+    }
+
+    let new_path = path.with_extension("new.txt");
+    let _ = std::fs::create_dir_all(path.parent().expect("snapshot dir"));
+    let _ = std::fs::write(&new_path, tree);
+
+    match expected {
+        Some(expected) => panic!(
+            "snapshot '{name}' changed, new layout written to {}\n{}",
+            new_path.display(),
+            diff(&expected, tree)
+        ),
+        None => panic!(
+            "no snapshot committed for '{name}' at {}, layout written to {}. \
+             Run the test locally to record it and commit the file.",
+            path.display(),
+            new_path.display()
+        ),
+    }
+}
+
+/// Lines that differ between two trees, up to a screenful
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    let mut out = String::new();
+    let mut shown = 0;
+    for i in 0..expected.len().max(actual.len()) {
+        let (old, new) = (expected.get(i), actual.get(i));
+        if old == new {
+            continue;
+        }
+
+        if let Some(old) = old {
+            let _ = writeln!(out, "{:>4} - {old}", i + 1);
+        }
+        if let Some(new) = new {
+            let _ = writeln!(out, "{:>4} + {new}", i + 1);
+        }
+
+        shown += 1;
+        if shown == 20 {
+            out.push_str("     ...\n");
+            break;
+        }
+    }
+    out
+}
+
+fn event_id(json: &str) -> [u8; 32] {
+    let value: serde_json::Value = serde_json::from_str(json).expect("event json");
+    let id = value["id"].as_str().expect("event id");
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&id[i * 2..i * 2 + 2], 16).expect("event id hex");
+    }
+    bytes
+}
+
+/// The same keypair every run, so usernames and npubs don't move
+/// between snapshots
+pub fn seeded_keypair(seed: u8) -> FullKeypair {
+    let secret_key = SecretKey::from_slice(&[seed; 32]).expect("seeded secret key");
+    let kp = Keypair::from_secret(secret_key.clone());
+    FullKeypair::new(kp.pubkey, secret_key)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+pub fn profile_json(kp: &FullKeypair, name: &str) -> String {
+    let sec = kp.secret_key.to_secret_bytes();
+    let content = serde_json::json!({"name": name, "display_name": name});
+
+    NoteBuilder::new()
+        .kind(0)
+        .content(&content.to_string())
+        .created_at(now() - 86400)
+        .options(NoteBuildOptions::default().created_at(false).sign(&sec))
+        .build()
+        .expect("snapshot profile")
+        .json()
+        .expect("snapshot profile json")
+}
+
+/// A kind 1 note posted `age` seconds ago. Relative timestamps are
+/// rendered, so keep ages well inside a unit ("2h", not "59m").
+pub fn text_note_json(kp: &FullKeypair, content: &str, age: u64) -> String {
+    let sec = kp.secret_key.to_secret_bytes();
+
+    NoteBuilder::new()
+        .kind(1)
+        .content(content)
+        .created_at(now() - age)
+        .options(NoteBuildOptions::default().created_at(false).sign(&sec))
+        .build()
+        .expect("snapshot note")
+        .json()
+        .expect("snapshot note json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_HOURS: u64 = 2 * 60 * 60 + 30 * 60;
+
+    #[test]
+    fn snapshot_long_note_truncates() {
+        let mut snapshot = Snapshot::new();
+        let author = seeded_keypair(1);
+        snapshot.ingest(&profile_json(&author, "alice"));
+
+        let content = "The tomatoes finally came in and they are too good to rush. ".repeat(40);
+        let note = snapshot.ingest(&text_note_json(&author, &content, TWO_HOURS));
+
+        let tree = snapshot.note(note, NoteOptions::default());
+        assert_snapshot("long_note_truncated", &tree);
+
+        let mut full = NoteOptions::default();
+        full.set_truncate(false);
+        let untruncated = snapshot.note(note, full);
+        assert_ne!(tree, untruncated);
+    }
+
+    #[test]
+    fn snapshot_mentions() {
+        let mut snapshot = Snapshot::new();
+        let alice = seeded_keypair(1);
+        let bob = seeded_keypair(2);
+        snapshot.ingest(&profile_json(&alice, "alice"));
+        snapshot.ingest(&profile_json(&bob, "bob"));
+
+        let npub = alice.pubkey.npub().expect("npub");
+        let content = format!("gm nostr:{npub}, the bread rose this time! #baking");
+        let note = snapshot.ingest(&text_note_json(&bob, &content, TWO_HOURS));
+
+        let tree = snapshot.note(note, NoteOptions::default());
+        assert!(tree.contains("@alice"), "mention not rendered:\n{tree}");
+        assert_snapshot("mention", &tree);
+    }
+
+    #[test]
+    fn snapshot_media_sizing() {
+        let mut snapshot = Snapshot::new();
+        let author = seeded_keypair(3);
+        snapshot.ingest(&profile_json(&author, "carol"));
+
+        let content = "look at this https://example.com/cat.png";
+        let note = snapshot.ingest(&text_note_json(&author, content, TWO_HOURS));
+
+        assert_snapshot(
+            "media_desktop",
+            &snapshot.note(note, NoteOptions::default()),
+        );
+
+        let mut snapshot = snapshot.size(egui::vec2(400.0, 800.0));
+        assert_snapshot("media_mobile", &snapshot.note(note, NoteOptions::default()));
+    }
+
+    #[test]
+    fn snapshot_timeline() {
+        let mut snapshot = Snapshot::new();
+        let people: Vec<FullKeypair> = (1..=3).map(seeded_keypair).collect();
+        for (kp, name) in people.iter().zip(["alice", "bob", "carol"]) {
+            snapshot.ingest(&profile_json(kp, name));
+        }
+
+        let notes: Vec<NoteKey> = people
+            .iter()
+            .enumerate()
+            .map(|(i, kp)| {
+                let content = format!("note number {i} from the seeded timeline");
+                snapshot.ingest(&text_note_json(kp, &content, TWO_HOURS))
+            })
+            .collect();
+
+        let tree = snapshot.timeline(&notes, NoteOptions::default());
+        assert_snapshot("timeline", &tree);
+    }
+
+    #[test]
+    fn test_diff_shows_changed_lines() {
+        let diff = diff("a\nb\nc\n", "a\nx\nc\nd\n");
+        assert_eq!(diff, "   2 - b\n   2 + x\n   4 + d\n");
+    }
+}
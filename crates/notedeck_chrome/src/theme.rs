@@ -10,6 +10,11 @@ const BLACK: Color32 = Color32::from_rgb(0x00, 0x00, 0x00);
 const RED_700: Color32 = Color32::from_rgb(0xC7, 0x37, 0x5A);
 const ORANGE_700: Color32 = Color32::from_rgb(0xF6, 0xB1, 0x4A);
 
+// HIGH CONTRAST
+const HIGH_CONTRAST_LINK_LIGHT: Color32 = Color32::from_rgb(0x6A, 0x0D, 0x83);
+const HIGH_CONTRAST_LINK_DARK: Color32 = Color32::from_rgb(0xF0, 0x9C, 0xFF);
+const HIGH_CONTRAST_ERR_DARK: Color32 = Color32::from_rgb(0xFF, 0x6B, 0x81);
+
 // BACKGROUNDS
 const SEMI_DARKER_BG: Color32 = Color32::from_rgb(0x39, 0x39, 0x39);
 const DARKER_BG: Color32 = Color32::from_rgb(0x1F, 0x1F, 0x1F);
@@ -86,6 +91,46 @@ pub fn light_color_theme() -> ColorTheme {
     }
 }
 
+/// Black on white with solid borders, for people who find the regular
+/// light theme hard to read
+pub fn high_contrast_light_color_theme() -> ColorTheme {
+    ColorTheme {
+        extreme_bg_color: Color32::WHITE,
+        hyperlink_color: HIGH_CONTRAST_LINK_LIGHT,
+        selection_color: HIGH_CONTRAST_LINK_LIGHT,
+        window_stroke_color: BLACK,
+        noninteractive_weak_bg_fill: Color32::WHITE,
+        noninteractive_bg_stroke_color: BLACK,
+        noninteractive_fg_stroke_color: BLACK,
+        inactive_bg_stroke_color: BLACK,
+        inactive_bg_fill: Color32::WHITE,
+        inactive_weak_bg_fill: Color32::WHITE,
+        ..light_color_theme()
+    }
+}
+
+/// White on black with solid borders
+pub fn high_contrast_dark_color_theme() -> ColorTheme {
+    ColorTheme {
+        panel_fill: Color32::BLACK,
+        extreme_bg_color: Color32::BLACK,
+        err_fg_color: HIGH_CONTRAST_ERR_DARK,
+        warn_fg_color: Color32::YELLOW,
+        hyperlink_color: HIGH_CONTRAST_LINK_DARK,
+        selection_color: PURPLE_ALT,
+        window_fill: Color32::BLACK,
+        window_stroke_color: Color32::WHITE,
+        noninteractive_bg_fill: Color32::BLACK,
+        noninteractive_weak_bg_fill: Color32::BLACK,
+        noninteractive_bg_stroke_color: Color32::WHITE,
+        noninteractive_fg_stroke_color: Color32::WHITE,
+        inactive_bg_stroke_color: Color32::WHITE,
+        inactive_bg_fill: Color32::BLACK,
+        inactive_weak_bg_fill: Color32::BLACK,
+        ..desktop_dark_color_theme()
+    }
+}
+
 pub fn light_mode() -> Visuals {
     notedeck::theme::create_themed_visuals(light_color_theme(), Visuals::light())
 }
@@ -101,6 +146,29 @@ pub fn dark_mode(is_oled: bool) -> Visuals {
     )
 }
 
+/// Set the light and dark visuals, in their high contrast variants or not
+pub fn apply_visuals(ctx: &egui::Context, is_oled: bool, high_contrast: bool) {
+    if high_contrast {
+        ctx.set_visuals_of(
+            egui::Theme::Dark,
+            notedeck::theme::create_themed_visuals(
+                high_contrast_dark_color_theme(),
+                Visuals::dark(),
+            ),
+        );
+        ctx.set_visuals_of(
+            egui::Theme::Light,
+            notedeck::theme::create_themed_visuals(
+                high_contrast_light_color_theme(),
+                Visuals::light(),
+            ),
+        );
+    } else {
+        ctx.set_visuals_of(egui::Theme::Dark, dark_mode(is_oled));
+        ctx.set_visuals_of(egui::Theme::Light, light_mode());
+    }
+}
+
 /// Create custom text sizes for any FontSizes
 pub fn add_custom_style(is_mobile: bool, style: &mut Style) {
     let font_size = if is_mobile {
@@ -0,0 +1,305 @@
+//! Benchmarks for the paths we hit every frame or every time a batch of
+//! notes comes in. Run with `cargo bench -p notedeck_columns`.
+
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use enostr::FullKeypair;
+use nostrdb::{
+    BlockType, Config, Filter, IngestMetadata, Ndb, NoteBuildOptions, NoteBuilder, NoteKey,
+    Transaction,
+};
+use notedeck::{filter, FilterState, NoteCache, UnknownIds};
+use notedeck_columns::timeline::{Timeline, TimelineKind, TimelineTab};
+use tempfile::TempDir;
+
+const CONTENTS: &[&str] = &[
+    "gm nostr",
+    "Sourdough attempt number 14. This one actually rose! #baking #bread",
+    "check this out https://example.com/some/long/path?with=query and https://damus.io",
+    "nostr:npub1xtscya34g58tk0z605fvr788k263gsu6cy9x0mhnm87echrgufzsevkk5s said it first",
+    "a longer note that goes on for a while, the kind that gets truncated in the timeline \
+     because someone had a lot to say about relays, clients, and keys. #nostr #longform",
+];
+
+/// An ndb in a temp dir with a batch of notes already ingested
+struct Fixture {
+    _dir: TempDir,
+    ndb: Ndb,
+    notes: Vec<NoteKey>,
+    contact_list: NoteKey,
+}
+
+impl Fixture {
+    fn new(num_notes: usize, num_follows: usize) -> Self {
+        let dir = TempDir::new().expect("bench dir");
+        let ndb = Ndb::new(dir.path().to_str().expect("bench dir"), &Config::new()).expect("ndb");
+
+        let notes_sub = ndb
+            .subscribe(&[Filter::new().kinds([1]).build()])
+            .expect("notes sub");
+        let contacts_sub = ndb
+            .subscribe(&[Filter::new().kinds([3]).build()])
+            .expect("contacts sub");
+
+        let authors: Vec<FullKeypair> = (0..num_follows).map(|_| FullKeypair::generate()).collect();
+        let owner = FullKeypair::generate();
+
+        ingest(&ndb, &contact_list_json(&owner, &authors));
+        for i in 0..num_notes {
+            let author = &authors[i % authors.len()];
+            let content = CONTENTS[i % CONTENTS.len()];
+            ingest(&ndb, &text_note_json(author, content, i as u64));
+        }
+
+        let notes = wait_for(&ndb, notes_sub, num_notes);
+        let contact_list = wait_for(&ndb, contacts_sub, 1)[0];
+
+        Fixture {
+            _dir: dir,
+            ndb,
+            notes,
+            contact_list,
+        }
+    }
+}
+
+fn ingest(ndb: &Ndb, json: &str) {
+    ndb.process_event_with(
+        &format!("[\"EVENT\",{json}]"),
+        IngestMetadata::new().client(true),
+    )
+    .expect("ingest");
+}
+
+/// Ingestion happens on nostrdb's writer threads, so wait for it to
+/// catch up before measuring anything
+fn wait_for(ndb: &Ndb, sub: nostrdb::Subscription, count: usize) -> Vec<NoteKey> {
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let mut keys = Vec::with_capacity(count);
+
+    while keys.len() < count {
+        assert!(Instant::now() < deadline, "timed out ingesting bench notes");
+        keys.extend(ndb.poll_for_notes(sub, (count - keys.len()) as u32));
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    keys
+}
+
+fn text_note_json(kp: &FullKeypair, content: &str, created_at: u64) -> String {
+    let sec = kp.secret_key.to_secret_bytes();
+
+    NoteBuilder::new()
+        .kind(1)
+        .content(content)
+        .created_at(1_700_000_000 + created_at)
+        .options(NoteBuildOptions::default().created_at(false).sign(&sec))
+        .build()
+        .expect("bench note")
+        .json()
+        .expect("bench note json")
+}
+
+fn contact_list_json(kp: &FullKeypair, follows: &[FullKeypair]) -> String {
+    let sec = kp.secret_key.to_secret_bytes();
+    let follows: Vec<String> = follows.iter().map(|f| f.pubkey.hex()).collect();
+
+    let mut builder = NoteBuilder::new()
+        .kind(3)
+        .content("")
+        .created_at(1_700_000_000);
+    for pubkey in &follows {
+        builder = builder.start_tag().tag_str("p").tag_str(pubkey);
+    }
+    builder = builder.start_tag().tag_str("t").tag_str("nostr");
+
+    builder
+        .options(NoteBuildOptions::default().created_at(false).sign(&sec))
+        .build()
+        .expect("bench contact list")
+        .json()
+        .expect("bench contact list json")
+}
+
+fn empty_timeline() -> Timeline {
+    Timeline::new(
+        TimelineKind::Universe,
+        FilterState::ready(vec![]),
+        TimelineTab::full_tabs(),
+    )
+}
+
+fn timeline_insert(c: &mut Criterion) {
+    let fixture = Fixture::new(5000, 200);
+    let txn = Transaction::new(&fixture.ndb).expect("txn");
+    let mut group = c.benchmark_group("timeline_insert");
+
+    for size in [100, 1000, 5000] {
+        let keys = &fixture.notes[..size];
+        group.throughput(Throughput::Elements(size as u64));
+
+        // a fresh column getting its first batch from nostrdb
+        group.bench_with_input(BenchmarkId::new("empty", size), keys, |b, keys| {
+            b.iter_batched(
+                || {
+                    (
+                        empty_timeline(),
+                        NoteCache::default(),
+                        UnknownIds::default(),
+                    )
+                },
+                |(mut timeline, mut note_cache, mut unknown_ids)| {
+                    timeline
+                        .insert(
+                            keys,
+                            &fixture.ndb,
+                            &txn,
+                            &mut unknown_ids,
+                            &mut note_cache,
+                            false,
+                        )
+                        .expect("insert");
+                    timeline
+                },
+                BatchSize::LargeInput,
+            )
+        });
+
+        // new notes merging into a column that's already full, the
+        // common case once the app has been running for a while
+        let (older, newer) = keys.split_at(size / 2);
+        group.bench_with_input(
+            BenchmarkId::new("merge", size),
+            &(older, newer),
+            |b, (older, newer)| {
+                b.iter_batched(
+                    || {
+                        let mut timeline = empty_timeline();
+                        let mut note_cache = NoteCache::default();
+                        let mut unknown_ids = UnknownIds::default();
+                        timeline
+                            .insert(
+                                older,
+                                &fixture.ndb,
+                                &txn,
+                                &mut unknown_ids,
+                                &mut note_cache,
+                                false,
+                            )
+                            .expect("insert");
+                        (timeline, note_cache, unknown_ids)
+                    },
+                    |(mut timeline, mut note_cache, mut unknown_ids)| {
+                        timeline
+                            .insert(
+                                newer,
+                                &fixture.ndb,
+                                &txn,
+                                &mut unknown_ids,
+                                &mut note_cache,
+                                false,
+                            )
+                            .expect("insert");
+                        timeline
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn content_parsing(c: &mut Criterion) {
+    let fixture = Fixture::new(1000, 50);
+    let txn = Transaction::new(&fixture.ndb).expect("txn");
+    let mut group = c.benchmark_group("content");
+    group.throughput(Throughput::Elements(fixture.notes.len() as u64));
+
+    // what note rendering does before laying anything out
+    group.bench_function("blocks", |b| {
+        b.iter(|| {
+            let mut counts = [0usize; 4];
+            for key in &fixture.notes {
+                let Ok(blocks) = fixture.ndb.get_blocks_by_key(&txn, *key) else {
+                    continue;
+                };
+                let Ok(note) = fixture.ndb.get_note_by_key(&txn, *key) else {
+                    continue;
+                };
+                for block in blocks.iter(&note) {
+                    let slot = match block.blocktype() {
+                        BlockType::MentionBech32 => 0,
+                        BlockType::Url => 1,
+                        BlockType::Hashtag => 2,
+                        _ => 3,
+                    };
+                    counts[slot] += 1;
+                }
+            }
+            counts
+        })
+    });
+
+    group.bench_function("note_cache", |b| {
+        b.iter_batched(
+            NoteCache::default,
+            |mut note_cache| {
+                for key in &fixture.notes {
+                    if let Ok(note) = fixture.ndb.get_note_by_key(&txn, *key) {
+                        note_cache.cached_note_or_insert(*key, &note);
+                    }
+                }
+                note_cache
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+fn filter_construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("filters");
+
+    for follows in [100, 1000] {
+        let fixture = Fixture::new(0, follows);
+        let txn = Transaction::new(&fixture.ndb).expect("txn");
+        let contact_list = fixture
+            .ndb
+            .get_note_by_key(&txn, fixture.contact_list)
+            .expect("contact list");
+
+        group.bench_with_input(
+            BenchmarkId::new("contact_list", follows),
+            &contact_list,
+            |b, contact_list| {
+                b.iter(|| {
+                    filter::filter_from_tags(contact_list, None, true)
+                        .expect("filter")
+                        .into_follow_filter()
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("last_per_pubkey", follows),
+            &contact_list,
+            |b, contact_list| {
+                b.iter(|| filter::last_n_per_pubkey_from_tags(contact_list, 1, 1).expect("filter"))
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    timeline_insert,
+    content_parsing,
+    filter_construction
+);
+criterion_main!(benches);
@@ -2,7 +2,8 @@ use enostr::FullKeypair;
 use nostrdb::Ndb;
 
 use notedeck::{
-    Accounts, AccountsAction, AddAccountAction, Images, SingleUnkIdAction, SwitchAccountAction,
+    Accounts, AccountsAction, AddAccountAction, AmberSigner, Images, RemoteSigner,
+    SingleUnkIdAction, SwitchAccountAction, NOSTRCONNECT_RELAYS,
 };
 
 use crate::app::get_active_columns_mut;
@@ -13,6 +14,8 @@ use crate::{
     ui::{
         account_login_view::{AccountLoginResponse, AccountLoginView},
         accounts::{AccountsView, AccountsViewResponse},
+        app_lock::{AppLockResponse, AppLockState, AppLockView, UnlockResponse, UnlockView},
+        export_key::{ExportKeyResponse, ExportKeyState, ExportKeyView},
     },
 };
 use tracing::info;
@@ -31,6 +34,8 @@ pub fn render_accounts_route(
     accounts: &mut Accounts,
     decks: &mut DecksCache,
     login_state: &mut AcquireKeyState,
+    export_state: &mut ExportKeyState,
+    lock_state: &mut AppLockState,
     route: AccountsRoute,
 ) -> AddAccountAction {
     let resp = match route {
@@ -39,10 +44,36 @@ pub fn render_accounts_route(
             .inner
             .map(AccountsRouteResponse::Accounts),
 
+        AccountsRoute::AddAccount if !accounts.can_save_secret_keys() => {
+            // a key added now would only live in memory until unlocking
+            let resp = UnlockView::new(lock_state, accounts.app_lock()).ui(ui);
+            if let Some(UnlockResponse::Unlock(passphrase)) = resp.inner {
+                accounts.unlock(passphrase);
+            }
+            None
+        }
+
         AccountsRoute::AddAccount => AccountLoginView::new(login_state)
+            .signer(accounts.pending_signer())
             .ui(ui)
             .inner
             .map(AccountsRouteResponse::AddAccount),
+
+        AccountsRoute::ExportKey => {
+            let pubkey = accounts
+                .get_selected_account()
+                .filter(|acc| acc.key.secret_key.is_some())
+                .map(|acc| &acc.key.pubkey);
+            ExportKeyView::new(export_state, pubkey)
+                .ui(ui)
+                .inner
+                .map(AccountsRouteResponse::ExportKey)
+        }
+
+        AccountsRoute::AppLock => AppLockView::new(lock_state, accounts)
+            .ui(ui)
+            .inner
+            .map(AccountsRouteResponse::AppLock),
     };
 
     if let Some(resp) = resp {
@@ -55,7 +86,12 @@ pub fn render_accounts_route(
                 }
             }
             AccountsRouteResponse::AddAccount(response) => {
+                let complete = response.is_complete();
                 let action = process_login_view_response(accounts, decks, response);
+                if !complete {
+                    return action;
+                }
+
                 *login_state = Default::default();
                 let router = get_active_columns_mut(accounts, decks)
                     .column_mut(col)
@@ -63,6 +99,44 @@ pub fn render_accounts_route(
                 router.go_back();
                 action
             }
+            AccountsRouteResponse::ExportKey(response) => {
+                match response {
+                    ExportKeyResponse::Encrypt(passphrase) => {
+                        let export = accounts
+                            .selected_account_pubkey_bytes()
+                            .and_then(|pk| accounts.export_ncryptsec(pk, passphrase));
+                        if let Some(export) = export {
+                            export_state.set_export(export);
+                        }
+                    }
+                    ExportKeyResponse::Done => {
+                        export_state.clear();
+                        get_active_columns_mut(accounts, decks)
+                            .column_mut(col)
+                            .router_mut()
+                            .go_back();
+                    }
+                }
+                AddAccountAction {
+                    accounts_action: None,
+                    unk_id_action: SingleUnkIdAction::no_action(),
+                }
+            }
+            AccountsRouteResponse::AppLock(response) => {
+                match response {
+                    AppLockResponse::Enable(passphrase) => accounts.enable_lock(passphrase),
+                    AppLockResponse::Unlock(passphrase) => accounts.unlock(passphrase),
+                    AppLockResponse::Lock => accounts.lock(),
+                    AppLockResponse::Disable => {
+                        accounts.disable_lock();
+                    }
+                }
+                lock_state.clear_passphrase();
+                AddAccountAction {
+                    accounts_action: None,
+                    unk_id_action: SingleUnkIdAction::no_action(),
+                }
+            }
         }
     } else {
         AddAccountAction {
@@ -96,6 +170,12 @@ pub fn process_accounts_view_response(
         AccountsViewResponse::RouteToLogin => {
             router.route_to(Route::add_account());
         }
+        AccountsViewResponse::RouteToExportKey => {
+            router.route_to(Route::export_key());
+        }
+        AccountsViewResponse::RouteToAppLock => {
+            router.route_to(Route::app_lock());
+        }
     }
     accounts.needs_relay_config();
     selection
@@ -106,7 +186,41 @@ pub fn process_login_view_response(
     decks: &mut DecksCache,
     response: AccountLoginResponse,
 ) -> AddAccountAction {
+    let no_action = || AddAccountAction {
+        accounts_action: None,
+        unk_id_action: SingleUnkIdAction::no_action(),
+    };
+
     let (r, pubkey) = match response {
+        AccountLoginResponse::ConnectBunker(uri) => {
+            manager.start_signer_login(RemoteSigner::bunker(uri));
+            return no_action();
+        }
+        AccountLoginResponse::ConnectQr => {
+            let relays = NOSTRCONNECT_RELAYS.iter().map(|r| r.to_string()).collect();
+            manager.start_signer_login(RemoteSigner::nostrconnect(relays));
+            return no_action();
+        }
+        AccountLoginResponse::ConnectAmber => {
+            manager.start_signer_login(AmberSigner::login());
+            return no_action();
+        }
+        AccountLoginResponse::CancelSigner => {
+            manager.cancel_signer_login();
+            return no_action();
+        }
+        AccountLoginResponse::SignerReady => {
+            let Some(pubkey) = manager
+                .pending_signer()
+                .and_then(|s| s.user_pubkey().copied())
+            else {
+                return no_action();
+            };
+            let Some(action) = manager.finish_signer_login() else {
+                return no_action();
+            };
+            (action, pubkey)
+        }
         AccountLoginResponse::CreateNew => {
             let kp = FullKeypair::generate().to_keypair();
             let pubkey = kp.pubkey;
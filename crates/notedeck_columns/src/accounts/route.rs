@@ -1,16 +1,20 @@
-use super::{AccountLoginResponse, AccountsViewResponse};
+use super::{AccountLoginResponse, AccountsViewResponse, AppLockResponse, ExportKeyResponse};
 use serde::{Deserialize, Serialize};
 use tokenator::{ParseError, TokenParser, TokenSerializable, TokenWriter};
 
 pub enum AccountsRouteResponse {
     Accounts(AccountsViewResponse),
     AddAccount(AccountLoginResponse),
+    ExportKey(ExportKeyResponse),
+    AppLock(AppLockResponse),
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum AccountsRoute {
     Accounts,
     AddAccount,
+    ExportKey,
+    AppLock,
 }
 
 impl AccountsRoute {
@@ -19,6 +23,8 @@ impl AccountsRoute {
         match self {
             Self::Accounts => &["accounts", "show"],
             Self::AddAccount => &["accounts", "new"],
+            Self::ExportKey => &["accounts", "export"],
+            Self::AppLock => &["accounts", "lock"],
         }
     }
 }
@@ -38,6 +44,8 @@ impl TokenSerializable for AccountsRoute {
             &[
                 |p| parse_accounts_route(p, AccountsRoute::Accounts),
                 |p| parse_accounts_route(p, AccountsRoute::AddAccount),
+                |p| parse_accounts_route(p, AccountsRoute::ExportKey),
+                |p| parse_accounts_route(p, AccountsRoute::AppLock),
             ],
         )
     }
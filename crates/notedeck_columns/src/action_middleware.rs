@@ -0,0 +1,239 @@
+//! Hooks that run around every [`NoteAction`] the columns app executes.
+//!
+//! Things that care about *all* actions, like counting them, asking for
+//! confirmation or recording them for undo, go here as a middleware
+//! instead of another arm in `execute_note_action`.
+
+use std::collections::HashMap;
+
+use notedeck::{NoteAction, ZapAction};
+
+use crate::{actionbar::NoteActionError, nav::RouterAction};
+
+/// The result of running a note action, as seen by middleware
+pub type NoteActionResult = Result<Option<RouterAction>, NoteActionError>;
+
+/// What sort of action this is, without its payload. Actions aren't
+/// `Clone`, so this is what [`NoteActionMiddleware::after`] gets to see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NoteActionKind {
+    Reply,
+    Quote,
+    Hashtag,
+    Profile,
+    Note,
+    Context,
+    Zap,
+    ZapCustomize,
+    ZapClearError,
+    Media,
+    Contact,
+    Vote,
+}
+
+impl NoteActionKind {
+    pub fn of(action: &NoteAction) -> Self {
+        match action {
+            NoteAction::Reply(_) => Self::Reply,
+            NoteAction::Quote(_) => Self::Quote,
+            NoteAction::Hashtag(_) => Self::Hashtag,
+            NoteAction::Profile(_) => Self::Profile,
+            NoteAction::Note(_) => Self::Note,
+            NoteAction::Context(_) => Self::Context,
+            NoteAction::Zap(ZapAction::Send(_)) => Self::Zap,
+            NoteAction::Zap(ZapAction::CustomizeAmount(_)) => Self::ZapCustomize,
+            NoteAction::Zap(ZapAction::ClearError(_)) => Self::ZapClearError,
+            NoteAction::Media(_) => Self::Media,
+            NoteAction::Contact(_) => Self::Contact,
+            NoteAction::Vote { .. } => Self::Vote,
+        }
+    }
+}
+
+/// Whether an action should go ahead after [`NoteActionMiddleware::before`]
+pub enum MiddlewareFlow {
+    /// Pass the action on to the next middleware, then execute it
+    Continue(NoteAction),
+
+    /// Drop the action. Later middleware and the executor never see it.
+    Stop,
+}
+
+pub trait NoteActionMiddleware {
+    /// Shows up in logs when this middleware stops an action
+    fn name(&self) -> &'static str;
+
+    /// Look at, rewrite or swallow an action before it runs
+    fn before(&mut self, action: NoteAction, _ui: &mut egui::Ui) -> MiddlewareFlow {
+        MiddlewareFlow::Continue(action)
+    }
+
+    /// Called once the action has run, whether it succeeded or not
+    fn after(&mut self, _kind: NoteActionKind, _result: &NoteActionResult) {}
+}
+
+/// The middleware registered with the app, run in the order they were
+/// pushed for `before` and in reverse for `after`.
+#[derive(Default)]
+pub struct NoteActionMiddlewares {
+    layers: Vec<Box<dyn NoteActionMiddleware>>,
+}
+
+impl NoteActionMiddlewares {
+    pub fn push(&mut self, middleware: impl NoteActionMiddleware + 'static) {
+        self.layers.push(Box::new(middleware));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Run `action` through every layer's `before`. Returns `None` if one
+    /// of them stopped it.
+    pub fn before(&mut self, mut action: NoteAction, ui: &mut egui::Ui) -> Option<NoteAction> {
+        for layer in &mut self.layers {
+            match layer.before(action, ui) {
+                MiddlewareFlow::Continue(next) => action = next,
+                MiddlewareFlow::Stop => {
+                    tracing::debug!("note action stopped by {}", layer.name());
+                    return None;
+                }
+            }
+        }
+
+        Some(action)
+    }
+
+    pub fn after(&mut self, kind: NoteActionKind, result: &NoteActionResult) {
+        for layer in self.layers.iter_mut().rev() {
+            layer.after(kind, result);
+        }
+    }
+}
+
+/// Counts executed actions by kind, split by whether they failed
+#[derive(Default, Debug)]
+pub struct ActionCounter {
+    ok: HashMap<NoteActionKind, u64>,
+    failed: HashMap<NoteActionKind, u64>,
+}
+
+impl ActionCounter {
+    pub fn succeeded(&self, kind: NoteActionKind) -> u64 {
+        self.ok.get(&kind).copied().unwrap_or(0)
+    }
+
+    pub fn failed(&self, kind: NoteActionKind) -> u64 {
+        self.failed.get(&kind).copied().unwrap_or(0)
+    }
+}
+
+impl NoteActionMiddleware for ActionCounter {
+    fn name(&self) -> &'static str {
+        "action-counter"
+    }
+
+    fn after(&mut self, kind: NoteActionKind, result: &NoteActionResult) {
+        let counts = if result.is_ok() {
+            &mut self.ok
+        } else {
+            &mut self.failed
+        };
+        *counts.entry(kind).or_default() += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enostr::NoteId;
+    use std::{cell::RefCell, rc::Rc};
+
+    struct Log {
+        name: &'static str,
+        stop: bool,
+        seen: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl NoteActionMiddleware for Log {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn before(&mut self, action: NoteAction, _ui: &mut egui::Ui) -> MiddlewareFlow {
+            self.seen.borrow_mut().push(format!("before {}", self.name));
+            if self.stop {
+                MiddlewareFlow::Stop
+            } else {
+                MiddlewareFlow::Continue(action)
+            }
+        }
+
+        fn after(&mut self, _kind: NoteActionKind, _result: &NoteActionResult) {
+            self.seen.borrow_mut().push(format!("after {}", self.name));
+        }
+    }
+
+    fn run(middlewares: &mut NoteActionMiddlewares) -> Option<NoteAction> {
+        let ctx = egui::Context::default();
+        let mut out = None;
+        let _ = ctx.run(Default::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                out = middlewares.before(NoteAction::Reply(NoteId::new([0; 32])), ui);
+            });
+        });
+        out
+    }
+
+    #[test]
+    fn test_middleware_order() {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let mut middlewares = NoteActionMiddlewares::default();
+        for name in ["a", "b"] {
+            middlewares.push(Log {
+                name,
+                stop: false,
+                seen: seen.clone(),
+            });
+        }
+
+        assert!(run(&mut middlewares).is_some());
+        middlewares.after(NoteActionKind::Reply, &Ok(None));
+
+        assert_eq!(
+            *seen.borrow(),
+            vec!["before a", "before b", "after b", "after a"]
+        );
+    }
+
+    #[test]
+    fn test_middleware_stop() {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let mut middlewares = NoteActionMiddlewares::default();
+        middlewares.push(Log {
+            name: "a",
+            stop: true,
+            seen: seen.clone(),
+        });
+        middlewares.push(Log {
+            name: "b",
+            stop: false,
+            seen: seen.clone(),
+        });
+
+        assert!(run(&mut middlewares).is_none());
+        assert_eq!(*seen.borrow(), vec!["before a"]);
+    }
+
+    #[test]
+    fn test_action_counter() {
+        let mut counter = ActionCounter::default();
+        counter.after(NoteActionKind::Zap, &Ok(None));
+        counter.after(NoteActionKind::Zap, &Err(NoteActionError::WalletMissing));
+        counter.after(NoteActionKind::Zap, &Err(NoteActionError::NoAccount));
+
+        assert_eq!(counter.succeeded(NoteActionKind::Zap), 1);
+        assert_eq!(counter.failed(NoteActionKind::Zap), 2);
+        assert_eq!(counter.failed(NoteActionKind::Reply), 0);
+    }
+}
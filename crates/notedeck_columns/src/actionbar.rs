@@ -1,18 +1,51 @@
 use crate::{
+    action_middleware::{NoteActionKind, NoteActionMiddlewares},
     column::Columns,
     nav::{RouterAction, RouterType},
     route::Route,
+    scheduled::unix_now,
     timeline::{ThreadSelection, TimelineCache, TimelineKind},
+    undo::{UndoCommand, UndoStack},
 };
 
-use enostr::{Pubkey, RelayPool};
+use enostr::{NoteId, Pubkey, RelayPool};
 use nostrdb::{Ndb, NoteKey, Transaction};
 use notedeck::{
-    get_wallet_for_mut, note::ZapTargetAmount, Accounts, GlobalWallet, Images, NoteAction,
-    NoteCache, NoteZapTargetOwned, UnknownIds, ZapAction, ZapTarget, ZappingError, Zaps,
+    get_wallet_for_mut,
+    note::{root_note_id_from_selected_id, ZapTargetAmount},
+    poll::POLL_RESPONSE_KIND,
+    Accounts, BroadcastContext, GlobalWallet, Images, ListError, MuteAction, MuteFun, MuteItem,
+    NoteAction, NoteCache, NoteContextSelection, NoteZapTargetOwned, RootIdError, UnknownIds,
+    UnsignedEvent, ZapAction, ZapTarget, ZappingError, Zaps,
 };
 use tracing::error;
 
+/// Why a note action couldn't be carried out. These end up in front of
+/// the user, so the messages are written for them.
+#[derive(Debug, thiserror::Error)]
+pub enum NoteActionError {
+    #[error("Couldn't find the thread this note belongs to")]
+    ThreadResolution(RootIdError),
+
+    #[error("Log in to do that")]
+    NoAccount,
+
+    #[error("Set up a wallet to send zaps")]
+    WalletMissing,
+
+    #[error("Couldn't load the note: {0}")]
+    Ndb(#[from] nostrdb::Error),
+
+    #[error("Couldn't update your mute list: {0}")]
+    Mute(#[from] ListError),
+
+    #[error("Couldn't update your follows: {0}")]
+    Contacts(ListError),
+
+    #[error("Couldn't send your vote: {0}")]
+    Vote(ListError),
+}
+
 pub struct NewNotes {
     pub id: TimelineKind,
     pub notes: Vec<NoteKey>,
@@ -27,10 +60,13 @@ struct NoteActionResponse {
     router_action: Option<RouterAction>,
 }
 
-/// The note action executor for notedeck_columns
+/// The note action executor for notedeck_columns. `acting` is the account
+/// of the column the action came from.
 #[allow(clippy::too_many_arguments)]
 fn execute_note_action(
     action: NoteAction,
+    acting: Option<Pubkey>,
+    undo: &mut UndoStack,
     ndb: &Ndb,
     timeline_cache: &mut TimelineCache,
     note_cache: &mut NoteCache,
@@ -42,7 +78,7 @@ fn execute_note_action(
     images: &mut Images,
     router_type: RouterType,
     ui: &mut egui::Ui,
-) -> NoteActionResponse {
+) -> Result<NoteActionResponse, NoteActionError> {
     let mut timeline_res = None;
     let mut router_action = None;
 
@@ -55,12 +91,9 @@ fn execute_note_action(
             router_action = Some(RouterAction::route_to(Route::Timeline(kind.clone())));
             timeline_res = timeline_cache.open(ndb, note_cache, txn, pool, &kind);
         }
-        NoteAction::Note(note_id) => 'ex: {
-            let Ok(thread_selection) = ThreadSelection::from_note_id(ndb, note_cache, txn, note_id)
-            else {
-                tracing::error!("No thread selection for {}?", hex::encode(note_id.bytes()));
-                break 'ex;
-            };
+        NoteAction::Note(note_id) => {
+            let thread_selection = ThreadSelection::from_note_id(ndb, note_cache, txn, note_id)
+                .map_err(NoteActionError::ThreadResolution)?;
 
             let kind = TimelineKind::Thread(thread_selection);
             router_action = Some(RouterAction::route_to(Route::Timeline(kind.clone())));
@@ -76,15 +109,11 @@ fn execute_note_action(
         NoteAction::Quote(note_id) => {
             router_action = Some(RouterAction::route_to(Route::quote(note_id)));
         }
-        NoteAction::Zap(zap_action) => 's: {
-            let Some(cur_acc) = accounts.get_selected_account_mut() else {
-                break 's;
-            };
-
-            let sender = cur_acc.key.pubkey;
+        NoteAction::Zap(zap_action) => {
+            let sender = acting.ok_or(NoteActionError::NoAccount)?;
 
             match &zap_action {
-                ZapAction::Send(target) => 'a: {
+                ZapAction::Send(target) => {
                     let Some(wallet) = get_wallet_for_mut(accounts, global_wallet, sender.bytes())
                     else {
                         zaps.send_error(
@@ -92,7 +121,7 @@ fn execute_note_action(
                             ZapTarget::Note((&target.target).into()),
                             ZappingError::SenderNoWallet,
                         );
-                        break 'a;
+                        return Err(NoteActionError::WalletMissing);
                     };
 
                     if let RouterType::Sheet = router_type {
@@ -114,27 +143,65 @@ fn execute_note_action(
                 }
             }
         }
-        NoteAction::Context(context) => match ndb.get_note_by_key(txn, context.note_key) {
-            Err(err) => tracing::error!("{err}"),
-            Ok(note) => {
-                context.action.process(ui, &note, pool);
+        NoteAction::Context(context) => {
+            let note = ndb.get_note_by_key(txn, context.note_key)?;
+            match context.action {
+                NoteContextSelection::MuteThread => {
+                    let root = root_note_id_from_selected_id(ndb, note_cache, txn, note.id())
+                        .map_err(NoteActionError::ThreadResolution)?;
+                    let item = MuteItem::Thread(NoteId::new(*root.bytes()));
+                    undo.push(mute(ndb, pool, accounts, item)?);
+                }
+                NoteContextSelection::MuteUser => {
+                    let item = MuteItem::Pubkey(Pubkey::new(*note.pubkey()));
+                    undo.push(mute(ndb, pool, accounts, item)?);
+                }
+                NoteContextSelection::Broadcast(BroadcastContext::WriteRelays) => {
+                    let relays = accounts
+                        .write_relays()
+                        .into_iter()
+                        .filter(|relay| pool.roles(relay).write)
+                        .collect();
+                    NoteContextSelection::Broadcast(BroadcastContext::Relays(relays))
+                        .process(ui, ndb, txn, &note, pool);
+                }
+                NoteContextSelection::Inspect => {
+                    let route = Route::Inspect(NoteId::new(*note.id()));
+                    router_action = Some(RouterAction::route_to(route));
+                }
+                _ => context.action.process(ui, ndb, txn, &note, pool),
             }
-        },
+        }
         NoteAction::Media(media_action) => {
-            media_action.process(images);
+            media_action.process(ui.ctx(), images);
+        }
+        NoteAction::Contact(contact_action) => {
+            let acting = acting.ok_or(NoteActionError::NoAccount)?;
+            accounts
+                .apply_contact_action(ndb, pool, &acting, contact_action)
+                .map_err(NoteActionError::Contacts)?;
+        }
+        NoteAction::Vote { poll, options } => {
+            let voter = acting.ok_or(NoteActionError::NoAccount)?;
+            accounts
+                .publish_as(ndb, pool, &voter, &poll_response(&poll, &options))
+                .map_err(NoteActionError::Vote)?;
         }
     }
 
-    NoteActionResponse {
+    Ok(NoteActionResponse {
         timeline_res,
         router_action,
-    }
+    })
 }
 
-/// Execute a NoteAction and process the result
+/// Execute a NoteAction and process the result. The action goes through
+/// `middleware` first, which may stop it.
 #[allow(clippy::too_many_arguments)]
 pub fn execute_and_process_note_action(
     action: NoteAction,
+    middleware: &mut NoteActionMiddlewares,
+    undo: &mut UndoStack,
     ndb: &Ndb,
     columns: &mut Columns,
     col: usize,
@@ -148,7 +215,52 @@ pub fn execute_and_process_note_action(
     zaps: &mut Zaps,
     images: &mut Images,
     ui: &mut egui::Ui,
-) -> Option<RouterAction> {
+) -> Result<Option<RouterAction>, NoteActionError> {
+    let Some(action) = middleware.before(action, ui) else {
+        return Ok(None);
+    };
+    let kind = NoteActionKind::of(&action);
+
+    let result = process_note_action(
+        action,
+        undo,
+        ndb,
+        columns,
+        col,
+        timeline_cache,
+        note_cache,
+        pool,
+        txn,
+        unknown_ids,
+        accounts,
+        global_wallet,
+        zaps,
+        images,
+        ui,
+    );
+
+    middleware.after(kind, &result);
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_note_action(
+    action: NoteAction,
+    undo: &mut UndoStack,
+    ndb: &Ndb,
+    columns: &mut Columns,
+    col: usize,
+    timeline_cache: &mut TimelineCache,
+    note_cache: &mut NoteCache,
+    pool: &mut RelayPool,
+    txn: &Transaction,
+    unknown_ids: &mut UnknownIds,
+    accounts: &mut Accounts,
+    global_wallet: &mut GlobalWallet,
+    zaps: &mut Zaps,
+    images: &mut Images,
+    ui: &mut egui::Ui,
+) -> Result<Option<RouterAction>, NoteActionError> {
     let router_type = {
         let sheet_router = &mut columns.column_mut(col).sheet_router;
 
@@ -159,8 +271,36 @@ pub fn execute_and_process_note_action(
         }
     };
 
+    let acting = columns.column(col).acting_pubkey(accounts);
+
+    let from_notifications = matches!(router_type, RouterType::Stack)
+        && matches!(
+            columns.column(col).router().routes().last(),
+            Some(Route::Timeline(TimelineKind::Notifications(_)))
+        );
+
+    // a thread that's already up in another column is shown there
+    if from_notifications {
+        if let NoteAction::Note(note_id) = &action {
+            if let Ok(selection) = ThreadSelection::from_note_id(ndb, note_cache, txn, *note_id) {
+                let showing = columns.find_showing(col, |route| match route {
+                    Route::Timeline(TimelineKind::Thread(shown)) => {
+                        shown.root_id == selection.root_id
+                    }
+                    _ => false,
+                });
+                if let Some(showing) = showing {
+                    columns.select_column(showing as i32);
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
     let resp = execute_note_action(
         action,
+        acting,
+        undo,
         ndb,
         timeline_cache,
         note_cache,
@@ -172,13 +312,51 @@ pub fn execute_and_process_note_action(
         images,
         router_type,
         ui,
-    );
+    )?;
 
     if let Some(br) = resp.timeline_res {
-        br.process(ndb, note_cache, txn, timeline_cache, unknown_ids);
+        br.process(
+            ndb,
+            note_cache,
+            txn,
+            timeline_cache,
+            unknown_ids,
+            &accounts.mutefun(),
+        );
     }
 
-    resp.router_action
+    let peek = ui.input(|i| i.modifiers.command);
+    if opens_ephemeral(resp.router_action.as_ref(), from_notifications, peek) {
+        if let Some(RouterAction::RouteTo(route, _)) = resp.router_action {
+            for kind in columns.open_ephemeral(col, route) {
+                if let Some(br) = timeline_cache.open(ndb, note_cache, txn, pool, &kind) {
+                    br.process(
+                        ndb,
+                        note_cache,
+                        txn,
+                        timeline_cache,
+                        unknown_ids,
+                        &accounts.mutefun(),
+                    );
+                }
+            }
+            return Ok(None);
+        }
+    }
+
+    Ok(resp.router_action)
+}
+
+/// Rather than taking over the notifications column, what was tapped
+/// there opens in a temporary column of its own. ctrl+click does the same
+/// for a profile, thread or hashtag from any column, to peek at it
+/// without losing your place.
+fn opens_ephemeral(action: Option<&RouterAction>, from_notifications: bool, peek: bool) -> bool {
+    (from_notifications || peek)
+        && matches!(
+            action,
+            Some(RouterAction::RouteTo(Route::Timeline(_), RouterType::Stack))
+        )
 }
 
 fn send_zap(
@@ -196,6 +374,41 @@ fn send_zap(
     zaps.send_zap(sender.bytes(), sender_relays, zap_target, msats);
 }
 
+/// Mutes apply to the selected account, so that's whose mute list the
+/// item goes on. Returns how to take it back.
+fn mute(
+    ndb: &Ndb,
+    pool: &mut RelayPool,
+    accounts: &mut Accounts,
+    item: MuteItem,
+) -> Result<UndoCommand, NoteActionError> {
+    let account = accounts
+        .selected_account_pubkey()
+        .copied()
+        .ok_or(NoteActionError::NoAccount)?;
+
+    accounts.apply_mute_action(ndb, pool, &account, &MuteAction::Mute(item.clone()))?;
+
+    Ok(UndoCommand::Mute { account, item })
+}
+
+/// A NIP-88 response picking `options` on `poll`
+fn poll_response(poll: &NoteId, options: &[String]) -> UnsignedEvent {
+    let mut tags = vec![vec!["e".to_owned(), poll.hex()]];
+    tags.extend(
+        options
+            .iter()
+            .map(|option| vec!["response".to_owned(), option.clone()]),
+    );
+
+    UnsignedEvent {
+        kind: POLL_RESPONSE_KIND,
+        content: String::new(),
+        tags,
+        created_at: unix_now(),
+    }
+}
+
 fn clear_zap_error(sender: &Pubkey, zaps: &mut Zaps, target: &NoteZapTargetOwned) {
     zaps.clear_error_for(sender.bytes(), ZapTarget::Note(target.into()));
 }
@@ -212,11 +425,12 @@ impl TimelineOpenResult {
         txn: &Transaction,
         storage: &mut TimelineCache,
         unknown_ids: &mut UnknownIds,
+        is_muted: &MuteFun,
     ) {
         match self {
             // update the thread for next render if we have new notes
             TimelineOpenResult::NewNotes(new_notes) => {
-                new_notes.process(storage, ndb, txn, unknown_ids, note_cache);
+                new_notes.process(storage, ndb, txn, unknown_ids, note_cache, is_muted);
             }
         }
     }
@@ -228,7 +442,8 @@ impl NewNotes {
     }
 
     /// Simple helper for processing a NewThreadNotes result. It simply
-    /// inserts/merges the notes into the corresponding timeline cache
+    /// inserts/merges the notes into the corresponding timeline cache,
+    /// minus anything the selected account has muted
     pub fn process(
         &self,
         timeline_cache: &mut TimelineCache,
@@ -236,6 +451,7 @@ impl NewNotes {
         txn: &Transaction,
         unknown_ids: &mut UnknownIds,
         note_cache: &mut NoteCache,
+        is_muted: &MuteFun,
     ) {
         let reversed = matches!(&self.id, TimelineKind::Thread(_));
 
@@ -246,9 +462,62 @@ impl NewNotes {
             return;
         };
 
-        if let Err(err) = timeline.insert(&self.notes, ndb, txn, unknown_ids, note_cache, reversed)
-        {
+        let notes: Vec<NoteKey> = self
+            .notes
+            .iter()
+            .copied()
+            .filter(|key| !is_muted_key(ndb, note_cache, txn, is_muted, *key))
+            .collect();
+
+        if let Err(err) = timeline.insert(&notes, ndb, txn, unknown_ids, note_cache, reversed) {
             error!("error inserting notes into profile timeline: {err}")
         }
     }
 }
+
+fn is_muted_key(
+    ndb: &Ndb,
+    note_cache: &mut NoteCache,
+    txn: &Transaction,
+    is_muted: &MuteFun,
+    key: NoteKey,
+) -> bool {
+    let Ok(note) = ndb.get_note_by_key(txn, key) else {
+        return false;
+    };
+
+    match root_note_id_from_selected_id(ndb, note_cache, txn, note.id()) {
+        Ok(root) => is_muted(&note, root.bytes()),
+        Err(_) => is_muted(&note, note.id()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peek_routing() {
+        let profile =
+            RouterAction::route_to(Route::Timeline(TimelineKind::Profile(Pubkey::new([1; 32]))));
+        let hashtag =
+            RouterAction::route_to(Route::Timeline(TimelineKind::Hashtag("nostr".to_owned())));
+        let reply = RouterAction::route_to(Route::reply(NoteId::new([2; 32])));
+        let sheet = RouterAction::route_to_sheet(Route::Timeline(TimelineKind::Profile(
+            Pubkey::new([1; 32]),
+        )));
+
+        // a plain click routes in place
+        assert!(!opens_ephemeral(Some(&profile), false, false));
+
+        // ctrl+click peeks from any column, and notifications always do
+        assert!(opens_ephemeral(Some(&profile), false, true));
+        assert!(opens_ephemeral(Some(&hashtag), false, true));
+        assert!(opens_ephemeral(Some(&profile), true, false));
+
+        // composers and sheets stay where they are
+        assert!(!opens_ephemeral(Some(&reply), true, true));
+        assert!(!opens_ephemeral(Some(&sheet), true, true));
+        assert!(!opens_ephemeral(None, true, true));
+    }
+}
@@ -1,19 +1,36 @@
 use crate::{
+    action_middleware::NoteActionMiddlewares,
     args::ColumnsArgs,
-    column::Columns,
+    column::{self, ColumnDrag, Columns, ColumnsAction},
     decks::{Decks, DecksCache, FALLBACK_PUBKEY},
     draft::Drafts,
+    emoji::EmojiFetcher,
+    media_upload::MediaServer,
     nav::{self, ProcessNavResult},
+    negentropy,
+    open_uri::PendingUri,
+    post_as::PostAsGuard,
+    pow::ProofOfWork,
+    reading_size::ReadingSizes,
+    recurring_zaps::RecurringZaps,
+    relay_test::RelayTests,
+    reply_tags::ReplyTags,
     route::Route,
+    scheduled::ScheduledPosts,
     storage,
     subscriptions::{SubKind, Subscriptions},
     support::Support,
-    timeline::{self, TimelineCache},
+    timeline::{self, TimelineCache, TimelineKind},
+    truncate::Truncation,
     ui::{self, DesktopSidePanel},
+    undo::UndoStack,
+    undo_send::UndoSend,
     view_state::ViewState,
+    whats_new::WhatsNew,
     Result,
 };
 
+use notedeck::deep_link::DeepLink;
 use notedeck::{Accounts, AppAction, AppContext, DataPath, DataPathType, FilterState, UnknownIds};
 use notedeck_ui::{jobs::JobsCache, NoteOptions};
 
@@ -26,7 +43,7 @@ use nostrdb::{Ndb, Transaction};
 
 use std::collections::{BTreeSet, HashMap};
 use std::path::Path;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, trace, warn};
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -45,6 +62,35 @@ pub struct Damus {
     pub subscriptions: Subscriptions,
     pub support: Support,
     pub jobs: JobsCache,
+    pub media_server: MediaServer,
+    pub post_as: PostAsGuard,
+    pub truncation: Truncation,
+    pub reading_sizes: ReadingSizes,
+    pub scheduled: ScheduledPosts,
+    pub recurring_zaps: RecurringZaps,
+    pub relay_tests: RelayTests,
+    pub undo_send: UndoSend,
+    pub pow: ProofOfWork,
+    pub reply_tags: ReplyTags,
+    pub whats_new: WhatsNew,
+
+    /// Custom emoji lists for the composer, see [`crate::emoji`]
+    pub emoji: EmojiFetcher,
+
+    /// Local changes that can still be taken back, see [`crate::undo`]
+    pub undo: UndoStack,
+
+    /// A `nostr:` uri that's waiting on relays for its note
+    pub pending_uri: Option<PendingUri>,
+
+    /// A `nostr+notedeck:` link waiting for the user to confirm it
+    pub pending_link: Option<DeepLink>,
+
+    /// When a column was last scrolled, the deck is saved once it settles
+    pub scroll_changed_at: Option<Instant>,
+
+    /// Runs around every note action, see [`crate::action_middleware`]
+    pub note_action_middleware: NoteActionMiddlewares,
 
     //frame_history: crate::frame_history::FrameHistory,
 
@@ -58,39 +104,11 @@ pub struct Damus {
     pub unrecognized_args: BTreeSet<String>,
 }
 
-fn handle_key_events(input: &egui::InputState, columns: &mut Columns) {
-    for event in &input.raw.events {
-        if let egui::Event::Key {
-            key, pressed: true, ..
-        } = event
-        {
-            match key {
-                egui::Key::J => {
-                    columns.select_down();
-                }
-                egui::Key::K => {
-                    columns.select_up();
-                }
-                egui::Key::H => {
-                    columns.select_left();
-                }
-                egui::Key::L => {
-                    columns.select_left();
-                }
-                _ => {}
-            }
-        }
-    }
-}
-
 fn try_process_event(
     damus: &mut Damus,
     app_ctx: &mut AppContext<'_>,
     ctx: &egui::Context,
 ) -> Result<()> {
-    let current_columns = get_active_columns_mut(app_ctx.accounts, &mut damus.decks_cache);
-    ctx.input(|i| handle_key_events(i, current_columns));
-
     let ctx2 = ctx.clone();
     let wakeup = move || {
         ctx2.request_repaint();
@@ -108,6 +126,8 @@ fn try_process_event(
         };
 
         match (&ev.event).into() {
+            // temporary relays only get the queries that sent us there
+            RelayEvent::Opened if app_ctx.pool.is_temporary(&ev.relay) => {}
             RelayEvent::Opened => {
                 app_ctx
                     .accounts
@@ -123,8 +143,14 @@ fn try_process_event(
                 );
             }
             // TODO: handle reconnects
-            RelayEvent::Closed => warn!("{} connection closed", &ev.relay),
-            RelayEvent::Error(e) => error!("{}: {}", &ev.relay, e),
+            RelayEvent::Closed => {
+                warn!("{} connection closed", &ev.relay);
+                damus.subscriptions.negentropy.relay_disconnected(&ev.relay);
+            }
+            RelayEvent::Error(e) => {
+                error!("{}: {}", &ev.relay, e);
+                damus.subscriptions.negentropy.relay_disconnected(&ev.relay);
+            }
             RelayEvent::Other(msg) => trace!("other event {:?}", &msg),
             RelayEvent::Message(msg) => {
                 process_message(damus, app_ctx, &ev.relay, &msg);
@@ -132,6 +158,21 @@ fn try_process_event(
         }
     }
 
+    for pubkey in app_ctx.accounts.take_contact_updates() {
+        for timeline in damus.timeline_cache.timelines.values_mut() {
+            if timeline.kind.contact_list_owner() == Some(&pubkey) {
+                timeline::refresh_contact_timeline(
+                    app_ctx.ndb,
+                    app_ctx.pool,
+                    app_ctx.note_cache,
+                    timeline,
+                );
+            }
+        }
+    }
+
+    follow_threads_in_notifications(damus, app_ctx);
+
     for (_kind, timeline) in damus.timeline_cache.timelines.iter_mut() {
         let is_ready =
             timeline::is_timeline_ready(app_ctx.ndb, app_ctx.pool, app_ctx.note_cache, timeline);
@@ -150,40 +191,162 @@ fn try_process_event(
             ) {
                 error!("poll_notes_into_view: {err}");
             }
+
+            timeline::load_older_notes(
+                timeline,
+                app_ctx.ndb,
+                &txn,
+                app_ctx.unknown_ids,
+                app_ctx.note_cache,
+                &mut damus.subscriptions,
+                app_ctx.pool,
+            );
+
+            timeline.check_gaps();
+            timeline::fill_gap(
+                timeline,
+                app_ctx.ndb,
+                &txn,
+                app_ctx.unknown_ids,
+                app_ctx.note_cache,
+                &mut damus.subscriptions,
+                app_ctx.pool,
+            );
+            timeline::load_branches(timeline, &mut damus.subscriptions, app_ctx.pool);
         } else {
             // TODO: show loading?
         }
     }
 
+    sync_notification_read_state(damus, app_ctx);
+
     if app_ctx.unknown_ids.ready_to_send() {
-        unknown_id_send(app_ctx.unknown_ids, app_ctx.pool);
+        unknown_id_send(
+            &mut damus.subscriptions,
+            app_ctx.unknown_ids,
+            app_ctx.pool,
+            ctx,
+        );
+    } else if let Some(send_in) = app_ctx.unknown_ids.send_in() {
+        ctx.request_repaint_after(send_in);
     }
 
     Ok(())
 }
 
-fn unknown_id_send(unknown_ids: &mut UnknownIds, pool: &mut RelayPool) {
-    debug!("unknown_id_send called on: {:?}", &unknown_ids);
-    let filter = unknown_ids.filter().expect("filter");
+/// Replies in the threads the selected account follows show up in its
+/// notifications
+fn follow_threads_in_notifications(damus: &mut Damus, app_ctx: &mut AppContext<'_>) {
+    let Some(selected) = app_ctx.accounts.selected_account_pubkey().copied() else {
+        return;
+    };
+    let threads = app_ctx.followed_threads.threads(selected.bytes());
+
+    for timeline in damus.timeline_cache.timelines.values_mut() {
+        if timeline.kind != TimelineKind::Notifications(selected)
+            || timeline.subscription.is_none()
+            || timeline.followed_threads == threads
+        {
+            continue;
+        }
+        timeline::refresh_notifications_timeline(
+            app_ctx.ndb,
+            app_ctx.pool,
+            app_ctx.note_cache,
+            timeline,
+            threads,
+        );
+    }
+}
+
+/// Notifications read on another device are read here too, and ones read
+/// here are passed on to the others
+fn sync_notification_read_state(damus: &mut Damus, app_ctx: &mut AppContext<'_>) {
+    let Some(selected) = app_ctx.accounts.selected_account_pubkey().copied() else {
+        return;
+    };
+
+    for timeline in damus.timeline_cache.timelines.values_mut() {
+        if timeline.kind != TimelineKind::Notifications(selected) {
+            continue;
+        }
+
+        let synced = app_ctx.read_state.last_read();
+        if timeline.last_read > synced {
+            app_ctx.read_state.mark_read(timeline.last_read);
+        }
+        timeline.last_read = timeline.last_read.max(synced);
+    }
+}
+
+/// How long a column has to sit still before its scroll position is saved
+const SCROLL_SAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// Whether a column was scrolled and has since settled, so the deck
+/// should be saved with its new position
+fn scroll_settled(app: &mut Damus, ctx: &egui::Context) -> bool {
+    let Some(changed_at) = app.scroll_changed_at else {
+        return false;
+    };
+
+    let still_for = changed_at.elapsed();
+    if still_for < SCROLL_SAVE_DELAY {
+        ctx.request_repaint_after(SCROLL_SAVE_DELAY - still_for);
+        return false;
+    }
+
+    app.scroll_changed_at = None;
+    true
+}
+
+/// How many relays we ask for unknown ids at first. We ask more if none
+/// of them have what we're looking for.
+const UNKNOWN_IDS_FANOUT: usize = 2;
+
+/// Ask for the next batch of unknown ids: all of them from the best
+/// relays, and the ones relays were hinted for from those relays too
+fn unknown_id_send(
+    subscriptions: &mut Subscriptions,
+    unknown_ids: &mut UnknownIds,
+    pool: &mut RelayPool,
+    ctx: &egui::Context,
+) {
+    let Some(batch) = unknown_ids.take_batch() else {
+        return;
+    };
     debug!(
-        "Getting {} unknown ids from relays",
-        unknown_ids.ids_iter().len()
+        "Getting {} unknown ids from relays, {} hinted relays",
+        batch.len,
+        batch.hinted.len()
     );
-    let msg = ClientMessage::req("unknownids".to_string(), filter);
-    unknown_ids.clear();
-    pool.send(&msg);
+
+    // a sub of their own each, so a batch doesn't replace one still out
+    let sub_id = crate::subscriptions::new_sub_id();
+    subscriptions.subs.insert(sub_id.clone(), SubKind::OneShot);
+    pool.subscribe_routed(sub_id, batch.filters, UNKNOWN_IDS_FANOUT);
+
+    for (relay, filters) in batch.hinted {
+        let sub_id = crate::subscriptions::new_sub_id();
+        subscriptions.subs.insert(sub_id.clone(), SubKind::OneShot);
+        pool.subscribe_temporary(
+            relay,
+            sub_id,
+            filters,
+            crate::relay_pool_manager::create_wakeup(ctx),
+        );
+    }
 }
 
 fn update_damus(damus: &mut Damus, app_ctx: &mut AppContext<'_>, ctx: &egui::Context) {
     app_ctx.img_cache.urls.cache.handle_io();
+    app_ctx.img_cache.link_previews.handle_io();
+    damus
+        .media_server
+        .set_blossom_servers(app_ctx.blossom.servers());
 
     match damus.state {
         DamusState::Initializing => {
             damus.state = DamusState::Initialized;
-            // this lets our eose handler know to close unknownids right away
-            damus
-                .subscriptions()
-                .insert("unknownids".to_string(), SubKind::OneShot);
             if let Err(err) = timeline::setup_initial_nostrdb_subs(
                 app_ctx.ndb,
                 app_ctx.note_cache,
@@ -191,6 +354,7 @@ fn update_damus(damus: &mut Damus, app_ctx: &mut AppContext<'_>, ctx: &egui::Con
             ) {
                 warn!("update_damus init: {err}");
             }
+            crate::whats_new::show_if_updated(damus, app_ctx);
         }
 
         DamusState::Initialized => (),
@@ -199,6 +363,9 @@ fn update_damus(damus: &mut Damus, app_ctx: &mut AppContext<'_>, ctx: &egui::Con
     if let Err(err) = try_process_event(damus, app_ctx, ctx) {
         error!("error processing event: {}", err);
     }
+
+    crate::undo::process_undo(damus, app_ctx, ctx);
+    crate::open_uri::process_opened_uris(damus, app_ctx, ctx);
 }
 
 fn handle_eose(
@@ -208,6 +375,15 @@ fn handle_eose(
     subid: &str,
     relay_url: &str,
 ) -> Result<()> {
+    if ctx.accounts.handle_eose(subid)
+        || ctx.wot.handle_eose(subid)
+        || ctx.blossom.handle_eose(subid)
+        || ctx.read_state.handle_eose(subid)
+        || ctx.followed_threads.handle_eose(subid)
+    {
+        return Ok(());
+    }
+
     let sub_kind = if let Some(sub_kind) = subscriptions.subs.get(subid) {
         sub_kind
     } else {
@@ -244,6 +420,23 @@ fn handle_eose(
             ctx.pool.send_to(&msg, relay_url);
         }
 
+        // the first relay to finish is enough to stop showing we're loading
+        SubKind::Backfill(timeline_kind) => {
+            let msg = ClientMessage::close(subid.to_string());
+            ctx.pool.send_to(&msg, relay_url);
+            if let Some(timeline) = timeline_cache.timelines.get_mut(timeline_kind) {
+                timeline.paging.done(subid);
+            }
+        }
+
+        SubKind::FillGap(timeline_kind) => {
+            let msg = ClientMessage::close(subid.to_string());
+            ctx.pool.send_to(&msg, relay_url);
+            if let Some(timeline) = timeline_cache.timelines.get_mut(timeline_kind) {
+                timeline.gaps.filled(subid);
+            }
+        }
+
         SubKind::FetchingContactList(timeline_uid) => {
             let timeline = if let Some(tl) = timeline_cache.timelines.get_mut(timeline_uid) {
                 tl
@@ -291,7 +484,21 @@ fn handle_eose(
 
 fn process_message(damus: &mut Damus, ctx: &mut AppContext<'_>, relay: &str, msg: &RelayMessage) {
     match msg {
-        RelayMessage::Event(_subid, ev) => {
+        RelayMessage::Event(subid, ev) => {
+            // remote signer responses are ephemeral and only mean
+            // something to the signer, keep them out of nostrdb
+            if ctx.accounts.process_signer_event(subid, ev) {
+                return;
+            }
+
+            if damus.relay_tests.handle_query(relay, subid, true, ctx.pool) {
+                return;
+            }
+
+            if notedeck::note::event_expired(ev) {
+                return;
+            }
+
             let relay = if let Some(relay) = ctx.pool.relays.iter().find(|r| r.url() == relay) {
                 relay
             } else {
@@ -324,9 +531,39 @@ fn process_message(damus: &mut Damus, ctx: &mut AppContext<'_>, relay: &str, msg
                 }
             }
         }
-        RelayMessage::Notice(msg) => warn!("Notice from {}: {}", relay, msg),
-        RelayMessage::OK(cr) => info!("OK {:?}", cr),
+        RelayMessage::Notice(msg) => {
+            warn!("Notice from {}: {}", relay, msg);
+            negentropy::handle_notice(&mut damus.subscriptions, ctx.pool, relay, msg);
+        }
+        RelayMessage::Count(sid, count) => {
+            if !ctx.counts.handle_count(sid, *count) {
+                warn!("got COUNT for unknown subid {} from {}", sid, relay);
+            }
+        }
+        RelayMessage::NegMsg(sid, msg) => {
+            negentropy::handle_neg_msg(&mut damus.subscriptions, ctx.pool, relay, sid, msg);
+        }
+        RelayMessage::NegErr(sid, reason) => {
+            negentropy::handle_neg_err(&mut damus.subscriptions, ctx.pool, relay, sid, reason);
+        }
+        RelayMessage::Closed(sid, reason) => {
+            warn!("{relay} closed {sid}: {reason}");
+        }
+        RelayMessage::OK(cr) => {
+            info!("OK {:?}", cr);
+            damus.relay_tests.handle_ok(
+                relay,
+                cr.event_id(),
+                cr.accepted(),
+                cr.message(),
+                ctx.pool,
+            );
+        }
         RelayMessage::Eose(sid) => {
+            if damus.relay_tests.handle_query(relay, sid, false, ctx.pool) {
+                return;
+            }
+
             if let Err(err) = handle_eose(
                 &damus.subscriptions,
                 &mut damus.timeline_cache,
@@ -345,18 +582,61 @@ fn render_damus(
     app_ctx: &mut AppContext<'_>,
     ui: &mut egui::Ui,
 ) -> Option<AppAction> {
+    if app_ctx.accounts.is_locked() && !damus.view_state.app_lock.browsing_locked() {
+        render_unlock_screen(damus, app_ctx, ui);
+        return None;
+    }
+
+    crate::shortcuts::process_shortcuts(damus, app_ctx, ui);
+    damus
+        .note_options
+        .set_summary(app_ctx.accessibility.settings().note_summaries);
+
     let app_action = if notedeck::ui::is_narrow(ui.ctx()) {
         render_damus_mobile(damus, app_ctx, ui)
     } else {
         render_damus_desktop(damus, app_ctx, ui)
     };
 
+    ui::signer::pending_signer_overlay(ui.ctx(), app_ctx.accounts);
+    crate::post_as::confirm_pending_post(damus, app_ctx, ui.ctx());
+    crate::open_uri::confirm_pending_link(damus, app_ctx, ui.ctx());
+    crate::undo_send::process_held_posts(damus, app_ctx, ui.ctx());
+    if damus.pow.process_mined(app_ctx.pool, app_ctx.accounts) {
+        ui::pow::mining_overlay(ui.ctx(), &mut damus.pow);
+        ui.ctx().request_repaint_after(Duration::from_millis(250));
+    }
+
     // We use this for keeping timestamps and things up to date
     ui.ctx().request_repaint_after(Duration::from_secs(1));
 
     app_action
 }
 
+/// Keys are encrypted at rest, ask for the passphrase before showing
+/// the decks
+fn render_unlock_screen(damus: &mut Damus, app_ctx: &mut AppContext<'_>, ui: &mut egui::Ui) {
+    let state = &mut damus.view_state.app_lock;
+    let resp = ui
+        .vertical_centered(|ui| {
+            ui.add_space(ui.available_height() / 4.0);
+            ui.set_max_width(400.0);
+            ui::app_lock::UnlockView::new(state, app_ctx.accounts.app_lock())
+                .browsable(true)
+                .ui(ui)
+                .inner
+        })
+        .inner;
+
+    match resp {
+        Some(ui::app_lock::UnlockResponse::Unlock(passphrase)) => {
+            app_ctx.accounts.unlock(passphrase)
+        }
+        Some(ui::app_lock::UnlockResponse::Browse) => state.browse_locked(),
+        None => {}
+    }
+}
+
 /*
 fn determine_key_storage_type() -> KeyStorageType {
     #[cfg(target_os = "macos")]
@@ -408,6 +688,7 @@ impl Damus {
                         &txn,
                         &mut timeline_cache,
                         ctx.unknown_ids,
+                        &ctx.accounts.mutefun(),
                     );
                 }
             }
@@ -447,7 +728,7 @@ impl Damus {
             subscriptions: Subscriptions::default(),
             since_optimize: parsed_args.since_optimize,
             timeline_cache,
-            drafts: Drafts::default(),
+            drafts: storage::load_drafts(ctx.path),
             state: DamusState::Initializing,
             note_options,
             //frame_history: FrameHistory::default(),
@@ -458,6 +739,23 @@ impl Damus {
             debug,
             unrecognized_args,
             jobs,
+            media_server: MediaServer::new(ctx.path),
+            post_as: PostAsGuard::new(ctx.path),
+            truncation: Truncation::new(ctx.path),
+            reading_sizes: ReadingSizes::new(ctx.path),
+            scheduled: ScheduledPosts::new(ctx.path),
+            recurring_zaps: RecurringZaps::new(ctx.path),
+            relay_tests: RelayTests::default(),
+            undo_send: UndoSend::new(ctx.path),
+            pow: ProofOfWork::new(ctx.path),
+            reply_tags: ReplyTags::new(ctx.path),
+            whats_new: WhatsNew::new(ctx.path),
+            emoji: EmojiFetcher::default(),
+            undo: UndoStack::default(),
+            pending_uri: None,
+            pending_link: None,
+            scroll_changed_at: None,
+            note_action_middleware: NoteActionMiddlewares::default(),
         }
     }
 
@@ -502,6 +800,23 @@ impl Damus {
             decks_cache,
             unrecognized_args: BTreeSet::default(),
             jobs: JobsCache::default(),
+            media_server: MediaServer::new(&path),
+            post_as: PostAsGuard::new(&path),
+            truncation: Truncation::new(&path),
+            reading_sizes: ReadingSizes::new(&path),
+            scheduled: ScheduledPosts::new(&path),
+            recurring_zaps: RecurringZaps::new(&path),
+            relay_tests: RelayTests::default(),
+            undo_send: UndoSend::new(&path),
+            pow: ProofOfWork::new(&path),
+            reply_tags: ReplyTags::new(&path),
+            whats_new: WhatsNew::new(&path),
+            emoji: EmojiFetcher::default(),
+            undo: UndoStack::default(),
+            pending_uri: None,
+            pending_link: None,
+            scroll_changed_at: None,
+            note_action_middleware: NoteActionMiddlewares::default(),
         }
     }
 
@@ -559,6 +874,10 @@ fn render_damus_mobile(
         }
     }
 
+    if scroll_settled(app, ui.ctx()) && !app.tmp_columns {
+        storage::save_decks_cache(app_ctx.path, &app.decks_cache);
+    }
+
     rect.min.x = rect.max.x - 100.0;
     rect.min.y = rect.max.y - 100.0;
 
@@ -587,111 +906,234 @@ fn render_damus_desktop(
     app_ctx: &mut AppContext<'_>,
     ui: &mut egui::Ui,
 ) -> Option<AppAction> {
-    let screen_size = ui.ctx().screen_rect().width();
-    let calc_panel_width = (screen_size
-        / get_active_columns(app_ctx.accounts, &app.decks_cache).num_columns() as f32)
-        - 30.0;
-    let min_width = 320.0;
-    let need_scroll = calc_panel_width < min_width;
-    let panel_sizes = if need_scroll {
-        Size::exact(min_width)
-    } else {
-        Size::remainder()
-    };
+    let available = ui.ctx().screen_rect().width() - ui::side_panel::SIDE_PANEL_WIDTH;
+    let (panel_sizes, need_scroll) = column_sizes(
+        get_active_columns(app_ctx.accounts, &app.decks_cache),
+        available,
+    );
 
     ui.spacing_mut().item_spacing.x = 0.0;
 
     if need_scroll {
         egui::ScrollArea::horizontal()
-            .show(ui, |ui| timelines_view(ui, panel_sizes, app, app_ctx))
+            .show(ui, |ui| timelines_view(ui, &panel_sizes, app, app_ctx))
             .inner
     } else {
-        timelines_view(ui, panel_sizes, app, app_ctx)
+        timelines_view(ui, &panel_sizes, app, app_ctx)
     }
 }
 
+/// How wide each column is laid out. Resized columns keep their width
+/// and the rest share what's left, unless that would squeeze them, in
+/// which case the deck scrolls sideways.
+fn column_sizes(columns: &Columns, available: f32) -> (Vec<Size>, bool) {
+    let widths: Vec<Option<f32>> = columns.columns().iter().map(|col| col.width).collect();
+    let fixed: f32 = widths.iter().flatten().sum();
+    let shared = widths.iter().filter(|width| width.is_none()).count();
+
+    let need_scroll = if shared == 0 {
+        fixed > available
+    } else {
+        (available - fixed) / (shared as f32) < column::AUTO_COLUMN_MIN_WIDTH
+    };
+
+    let sizes = widths
+        .into_iter()
+        .map(|width| match width {
+            Some(width) => Size::exact(width),
+            None if need_scroll => Size::exact(column::AUTO_COLUMN_MIN_WIDTH),
+            None => Size::remainder(),
+        })
+        .collect();
+
+    (sizes, need_scroll)
+}
+
+/// The divider on the right of each column. Dragging it sets the
+/// column's width, double clicking it lets the column share the room
+/// again. Returns whether a width was settled on and should be saved.
+fn column_dividers_ui(ui: &egui::Ui, columns: &mut Columns, column_rects: &[egui::Rect]) -> bool {
+    // leave the dividers alone while a column is being moved
+    if egui::DragAndDrop::has_payload_of_type::<ColumnDrag>(ui.ctx()) {
+        return false;
+    }
+
+    let mut changed = false;
+    for (ind, rect) in column_rects.iter().enumerate() {
+        let grab =
+            egui::Rect::from_x_y_ranges(rect.right() - 3.0..=rect.right() + 3.0, rect.y_range());
+        let resp = ui.interact(
+            grab,
+            egui::Id::new(("column_divider", ind)),
+            egui::Sense::click_and_drag(),
+        );
+
+        if resp.hovered() || resp.dragged() {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeColumn);
+            ui.painter().vline(
+                rect.right(),
+                rect.y_range(),
+                egui::Stroke::new(2.0, ui.visuals().selection.stroke.color),
+            );
+        }
+
+        let column = columns.column_mut(ind);
+        if resp.double_clicked() {
+            changed |= column.width.take().is_some();
+        } else if resp.dragged() {
+            if let Some(pointer) = resp.interact_pointer_pos() {
+                column.width = Some(column::clamp_width(pointer.x - rect.left()));
+            }
+        }
+        changed |= resp.drag_stopped();
+    }
+
+    changed
+}
+
+/// While a column is dragged by its header, shade it and mark the gap it
+/// would drop into. Returns the move once it's dropped.
+fn column_drag_ui(ui: &egui::Ui, column_rects: &[egui::Rect]) -> Option<ColumnsAction> {
+    let ColumnDrag(from) = *egui::DragAndDrop::payload::<ColumnDrag>(ui.ctx())?;
+    let source = *column_rects.get(from)?;
+    let pointer = ui.ctx().pointer_interact_pos()?;
+
+    let gap = column_rects
+        .iter()
+        .position(|rect| pointer.x < rect.center().x)
+        .unwrap_or(column_rects.len());
+    let to = column::drop_index(from, gap);
+
+    ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+    let painter = ui.ctx().layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("column_drag"),
+    ));
+    let selection = ui.visuals().selection;
+    painter.rect_filled(source, 0.0, selection.bg_fill.gamma_multiply(0.15));
+    if to != from {
+        // past the last column, the line goes at its right edge
+        let x = match column_rects.get(gap) {
+            Some(rect) => rect.left(),
+            None => column_rects
+                .last()
+                .map_or(source.right(), |rect| rect.right()),
+        };
+        painter.vline(
+            x,
+            source.y_range(),
+            egui::Stroke::new(3.0, selection.stroke.color),
+        );
+    }
+
+    if !ui.input(|i| i.pointer.any_released()) {
+        return None;
+    }
+    egui::DragAndDrop::clear_payload(ui.ctx());
+    (to != from).then_some(ColumnsAction::Switch(from, to))
+}
+
 fn timelines_view(
     ui: &mut egui::Ui,
-    sizes: Size,
+    sizes: &[Size],
     app: &mut Damus,
     ctx: &mut AppContext<'_>,
 ) -> Option<AppAction> {
     let num_cols = get_active_columns(ctx.accounts, &app.decks_cache).num_columns();
     let mut side_panel_action: Option<nav::SwitchingAction> = None;
     let mut responses = Vec::with_capacity(num_cols);
+    let mut column_rects = Vec::with_capacity(num_cols);
 
-    StripBuilder::new(ui)
-        .size(Size::exact(ui::side_panel::SIDE_PANEL_WIDTH))
-        .sizes(sizes, num_cols)
-        .clip(true)
-        .horizontal(|mut strip| {
-            strip.cell(|ui| {
-                let rect = ui.available_rect_before_wrap();
-                let side_panel =
-                    DesktopSidePanel::new(ctx.accounts.get_selected_account(), &app.decks_cache)
-                        .show(ui);
-
-                if let Some(side_panel) = side_panel {
-                    if side_panel.response.clicked() || side_panel.response.secondary_clicked() {
-                        if let Some(action) = DesktopSidePanel::perform_action(
-                            &mut app.decks_cache,
-                            ctx.accounts,
-                            side_panel.action,
-                        ) {
-                            side_panel_action = Some(action);
-                        }
+    let strip = sizes.iter().fold(
+        StripBuilder::new(ui).size(Size::exact(ui::side_panel::SIDE_PANEL_WIDTH)),
+        |strip, size| strip.size(*size),
+    );
+    strip.clip(true).horizontal(|mut strip| {
+        strip.cell(|ui| {
+            let rect = ui.available_rect_before_wrap();
+            let side_panel =
+                DesktopSidePanel::new(ctx.accounts.get_selected_account(), &app.decks_cache)
+                    .show(ui);
+
+            if let Some(side_panel) = side_panel {
+                if side_panel.response.clicked() || side_panel.response.secondary_clicked() {
+                    if let Some(action) = DesktopSidePanel::perform_action(
+                        &mut app.decks_cache,
+                        ctx.accounts,
+                        side_panel.action,
+                    ) {
+                        side_panel_action = Some(action);
                     }
                 }
+            }
 
-                // debug
-                /*
-                ui.painter().rect(
-                    rect,
-                    0,
-                    egui::Color32::RED,
-                    egui::Stroke::new(1.0, egui::Color32::BLUE),
-                    egui::StrokeKind::Inside,
-                );
-                */
+            // debug
+            /*
+            ui.painter().rect(
+                rect,
+                0,
+                egui::Color32::RED,
+                egui::Stroke::new(1.0, egui::Color32::BLUE),
+                egui::StrokeKind::Inside,
+            );
+            */
 
-                // vertical sidebar line
-                ui.painter().vline(
-                    rect.right(),
-                    rect.y_range(),
-                    ui.visuals().widgets.noninteractive.bg_stroke,
-                );
-            });
+            // vertical sidebar line
+            ui.painter().vline(
+                rect.right(),
+                rect.y_range(),
+                ui.visuals().widgets.noninteractive.bg_stroke,
+            );
+        });
 
-            for col_index in 0..num_cols {
-                strip.cell(|ui| {
-                    let rect = ui.available_rect_before_wrap();
-                    let v_line_stroke = ui.visuals().widgets.noninteractive.bg_stroke;
-                    let inner_rect = {
-                        let mut inner = rect;
-                        inner.set_right(rect.right() - v_line_stroke.width);
-                        inner
-                    };
-                    responses.push(nav::render_nav(col_index, inner_rect, app, ctx, ui));
+        for col_index in 0..num_cols {
+            strip.cell(|ui| {
+                let rect = ui.available_rect_before_wrap();
+                column_rects.push(rect);
+                let v_line_stroke = ui.visuals().widgets.noninteractive.bg_stroke;
+                let inner_rect = {
+                    let mut inner = rect;
+                    inner.set_right(rect.right() - v_line_stroke.width);
+                    inner
+                };
+                responses.push(nav::render_nav(col_index, inner_rect, app, ctx, ui));
+
+                // shortcuts act on the column last clicked in
+                if ui.rect_contains_pointer(rect) && ui.input(|i| i.pointer.any_pressed()) {
+                    app.columns_mut(ctx.accounts)
+                        .select_column(col_index as i32);
+                }
 
-                    // vertical line
-                    ui.painter()
-                        .vline(rect.right(), rect.y_range(), v_line_stroke);
+                // vertical line
+                ui.painter()
+                    .vline(rect.right(), rect.y_range(), v_line_stroke);
 
-                    // we need borrow ui context for processing, so proces
-                    // responses in the last cell
+                // we need borrow ui context for processing, so proces
+                // responses in the last cell
 
-                    if col_index == num_cols - 1 {}
-                });
+                if col_index == num_cols - 1 {}
+            });
 
-                //strip.cell(|ui| timeline::timeline_view(ui, app, timeline_ind));
-            }
-        });
+            //strip.cell(|ui| timeline::timeline_view(ui, app, timeline_ind));
+        }
+    });
+
+    if let Some(action) = column_drag_ui(ui, &column_rects) {
+        side_panel_action = Some(nav::SwitchingAction::Columns(action));
+    }
+
+    let mut save_cols = column_dividers_ui(ui, app.columns_mut(ctx.accounts), &column_rects);
 
     // process the side panel action after so we don't change the number of columns during
     // StripBuilder rendering
-    let mut save_cols = false;
     if let Some(action) = side_panel_action {
-        save_cols = save_cols || action.process(&mut app.timeline_cache, &mut app.decks_cache, ctx);
+        save_cols = save_cols
+            || action.process(
+                &mut app.timeline_cache,
+                &mut app.decks_cache,
+                &mut app.undo,
+                ctx,
+            );
     }
 
     let mut app_action: Option<AppAction> = None;
@@ -710,6 +1152,8 @@ fn timelines_view(
         }
     }
 
+    save_cols = scroll_settled(app, ui.ctx()) || save_cols;
+
     if app.tmp_columns {
         save_cols = false;
     }
@@ -730,7 +1174,28 @@ impl notedeck::App for Damus {
         */
 
         update_damus(self, ctx, ui.ctx());
-        render_damus(self, ctx, ui)
+        let action = render_damus(self, ctx, ui);
+        storage::autosave_drafts(ctx.path, &mut self.drafts, &self.undo_send);
+        self.timeline_cache.sweep_expired(ctx.ndb);
+        if let Some(next) = self.scheduled.publish_due(
+            ctx.ndb,
+            ctx.pool,
+            ctx.accounts,
+            &mut self.pow,
+            self.reply_tags.scheme(),
+        ) {
+            ui.ctx().request_repaint_after(next);
+        }
+        if let Some(next) = self
+            .recurring_zaps
+            .run_due(ctx.zaps, ctx.pool, ctx.accounts)
+        {
+            ui.ctx().request_repaint_after(next);
+        }
+        if let Some(next) = self.relay_tests.check_timeouts(ctx.pool) {
+            ui.ctx().request_repaint_after(next);
+        }
+        action
     }
 }
 
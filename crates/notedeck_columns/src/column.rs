@@ -1,18 +1,50 @@
 use crate::{
     actionbar::TimelineOpenResult,
     route::{Route, Router, SingletonRouter},
-    timeline::{Timeline, TimelineCache, TimelineKind},
+    timeline::{ScrollAnchor, Timeline, TimelineCache, TimelineKind},
 };
-use enostr::RelayPool;
+use enostr::{Pubkey, RelayPool};
 use nostrdb::{Ndb, Transaction};
-use notedeck::NoteCache;
+use notedeck::{Accounts, NoteCache, UserAccount};
 use std::iter::Iterator;
-use tracing::warn;
 
 #[derive(Clone, Debug)]
 pub struct Column {
     pub router: Router<Route>,
     pub sheet_router: SingletonRouter<Route>,
+
+    /// The account this column posts, zaps and reacts as. None means
+    /// whichever account is selected.
+    pub account: Option<Pubkey>,
+
+    /// Notes in its timeline need at least this much proof of work to be
+    /// shown, 0 shows everything
+    pub min_pow: u32,
+
+    /// Opened for a quick look, at a notification or from a ctrl+click.
+    /// Its first route is borrowed from the column it was opened from,
+    /// going back to it closes the column, and it's never saved.
+    pub ephemeral: bool,
+
+    /// Set by dragging the divider on its right, None shares the room
+    /// with the other columns that don't have one
+    pub width: Option<f32>,
+
+    /// How far down its timeline it was scrolled, saved with the deck so
+    /// it opens there again. None at the top.
+    pub scroll_anchor: Option<ScrollAnchor>,
+}
+
+/// Narrowest a column can be dragged to
+pub const MIN_COLUMN_WIDTH: f32 = 280.0;
+pub const MAX_COLUMN_WIDTH: f32 = 1200.0;
+
+/// Columns without a width don't get squeezed narrower than this, the
+/// deck scrolls instead
+pub const AUTO_COLUMN_MIN_WIDTH: f32 = 320.0;
+
+pub fn clamp_width(width: f32) -> f32 {
+    width.clamp(MIN_COLUMN_WIDTH, MAX_COLUMN_WIDTH)
 }
 
 impl Column {
@@ -21,9 +53,27 @@ impl Column {
         Column {
             router,
             sheet_router: SingletonRouter::default(),
+            account: None,
+            min_pow: 0,
+            ephemeral: false,
+            width: None,
+            scroll_anchor: None,
         }
     }
 
+    /// The pubkey of the account acting in this column
+    pub fn acting_pubkey(&self, accounts: &Accounts) -> Option<Pubkey> {
+        self.account
+            .or_else(|| accounts.selected_account_pubkey().copied())
+    }
+
+    /// The account acting in this column. None if the column is bound to
+    /// an account that has since been removed, we don't want to quietly
+    /// act as someone else.
+    pub fn acting_account<'a>(&self, accounts: &'a Accounts) -> Option<&'a UserAccount> {
+        accounts.find_account(self.acting_pubkey(accounts)?.bytes())
+    }
+
     pub fn router(&self) -> &Router<Route> {
         &self.router
     }
@@ -167,14 +217,6 @@ impl Columns {
         &mut self.columns[ind]
     }
 
-    pub fn select_down(&mut self) {
-        warn!("todo: implement select_down");
-    }
-
-    pub fn select_up(&mut self) {
-        warn!("todo: implement select_up");
-    }
-
     pub fn select_left(&mut self) {
         if self.selected - 1 < 0 {
             return;
@@ -189,6 +231,53 @@ impl Columns {
         self.selected += 1;
     }
 
+    /// The column, other than `except`, whose current route satisfies
+    /// `shows`
+    pub fn find_showing(&self, except: usize, shows: impl Fn(&Route) -> bool) -> Option<usize> {
+        self.columns
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != except)
+            .find(|(_, col)| col.router().routes().last().is_some_and(&shows))
+            .map(|(i, _)| i)
+    }
+
+    /// Show `route` in a temporary column next to `from`, and select it.
+    /// Any timeline in `route` must already be opened. The column starts
+    /// on the route `from` is showing, and holds its own reference to
+    /// that timeline so closing `from` first doesn't pull it out from
+    /// under it. The returned timelines are the ones it borrowed.
+    #[must_use = "you must call timeline_cache.open() for each returned value"]
+    pub fn open_ephemeral(&mut self, from: usize, route: Route) -> Vec<TimelineKind> {
+        let source = self.column(from);
+        let mut routes: Vec<Route> = source
+            .router()
+            .routes()
+            .last()
+            .cloned()
+            .into_iter()
+            .collect();
+
+        let borrowed = routes
+            .iter()
+            .filter_map(|route| match route {
+                Route::Timeline(kind) => Some(kind.clone()),
+                _ => None,
+            })
+            .collect();
+        routes.push(route);
+
+        let mut column = Column::new(routes);
+        column.account = source.account;
+        column.ephemeral = true;
+
+        let index = (from + 1).min(self.columns.len());
+        self.columns.insert(index, column);
+        self.select_column(index as i32);
+
+        borrowed
+    }
+
     #[must_use = "you must call timeline_cache.pop() for each returned value"]
     pub fn delete_column(&mut self, index: usize) -> Vec<TimelineKind> {
         let mut kinds_to_pop: Vec<TimelineKind> = vec![];
@@ -203,10 +292,16 @@ impl Columns {
         if self.columns.is_empty() {
             self.new_column_picker();
         }
+        if self.selected >= self.columns.len() as i32 {
+            self.selected = self.columns.len() as i32 - 1;
+        }
 
         kinds_to_pop
     }
 
+    /// Move the column at `from_index` so it ends up at `to_index`,
+    /// shifting the ones in between over. The selection stays with the
+    /// column it was on.
     pub fn move_col(&mut self, from_index: usize, to_index: usize) {
         if from_index == to_index
             || from_index >= self.columns.len()
@@ -215,7 +310,33 @@ impl Columns {
             return;
         }
 
-        self.columns.swap(from_index, to_index);
+        let column = self.columns.remove(from_index);
+        self.columns.insert(to_index, column);
+
+        let selected = self.selected as usize;
+        self.selected = if selected == from_index {
+            to_index
+        } else if from_index < selected && selected <= to_index {
+            selected - 1
+        } else if to_index <= selected && selected < from_index {
+            selected + 1
+        } else {
+            selected
+        } as i32;
+    }
+}
+
+/// The column being dragged by its header, as a drag and drop payload
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnDrag(pub usize);
+
+/// Where a column dragged from `from` ends up when dropped in `gap`, the
+/// gaps being numbered from 0 before the first column
+pub fn drop_index(from: usize, gap: usize) -> usize {
+    if gap > from {
+        gap - 1
+    } else {
+        gap
     }
 }
 
@@ -227,4 +348,80 @@ pub enum IntermediaryRoute {
 pub enum ColumnsAction {
     Switch(usize, usize), // from Switch.0 to Switch.1,
     Remove(usize),
+
+    /// Have a column act as an account, or as the selected account again
+    BindAccount(usize, Option<Pubkey>),
+
+    /// Only show notes with at least this much proof of work
+    MinPow(usize, u32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(routes: &[Route]) -> Columns {
+        let mut columns = Columns::new();
+        for route in routes {
+            columns.add_column(Column::new(vec![route.clone()]));
+        }
+        columns
+    }
+
+    fn order(columns: &Columns) -> Vec<Route> {
+        columns
+            .columns()
+            .iter()
+            .map(|c| c.router().top().clone())
+            .collect()
+    }
+
+    #[test]
+    fn moving_a_column_shifts_the_rest() {
+        let mut cols = columns(&[Route::Relays, Route::Settings, Route::Search]);
+        cols.select_column(1);
+
+        cols.move_col(0, 2);
+        assert_eq!(
+            order(&cols),
+            vec![Route::Settings, Route::Search, Route::Relays]
+        );
+        // settings is still selected
+        assert_eq!(cols.selected, 0);
+
+        cols.move_col(2, 0);
+        assert_eq!(
+            order(&cols),
+            vec![Route::Relays, Route::Settings, Route::Search]
+        );
+        assert_eq!(cols.selected, 1);
+    }
+
+    #[test]
+    fn closing_the_source_before_its_peek() {
+        let notifications = TimelineKind::Notifications(Pubkey::new([1; 32]));
+        let profile = TimelineKind::Profile(Pubkey::new([2; 32]));
+        let mut cols = columns(&[Route::Timeline(notifications.clone())]);
+
+        // the peek takes its own reference to what it borrowed
+        let borrowed = cols.open_ephemeral(0, Route::Timeline(profile.clone()));
+        assert_eq!(borrowed, vec![notifications.clone()]);
+        assert_eq!(cols.selected, 1);
+
+        assert_eq!(cols.delete_column(0), vec![notifications.clone()]);
+
+        // and gives it back along with its own when it closes, so every
+        // open is matched by a pop
+        assert!(cols.columns()[0].ephemeral);
+        assert_eq!(cols.delete_column(0), vec![notifications, profile]);
+    }
+
+    #[test]
+    fn dropping_in_a_gap() {
+        // dropped either side of itself, nothing moves
+        assert_eq!(drop_index(1, 1), 1);
+        assert_eq!(drop_index(1, 2), 1);
+        assert_eq!(drop_index(0, 3), 2);
+        assert_eq!(drop_index(2, 0), 0);
+    }
 }
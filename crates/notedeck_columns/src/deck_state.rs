@@ -7,6 +7,10 @@ pub struct DeckState {
     pub selecting_glyph: bool,
     pub warn_no_title: bool,
     pub warn_no_icon: bool,
+
+    /// A shared deck pasted in to import, and why it couldn't be
+    pub import_json: String,
+    pub import_error: Option<String>,
     glyph_options: Option<Vec<char>>,
 }
 
@@ -44,6 +48,8 @@ impl Default for DeckState {
             selecting_glyph: true,
             warn_no_icon: Default::default(),
             warn_no_title: Default::default(),
+            import_json: Default::default(),
+            import_error: Default::default(),
             glyph_options: Default::default(),
         }
     }
@@ -4,6 +4,7 @@ use enostr::Pubkey;
 use nostrdb::Transaction;
 use notedeck::AppContext;
 use tracing::{error, info};
+use uuid::Uuid;
 
 use crate::{
     accounts::AccountsRoute,
@@ -283,6 +284,11 @@ pub struct Deck {
     pub icon: char,
     pub name: String,
     columns: Columns,
+
+    /// Stays the same across renames and restarts. Scroll positions and
+    /// navigation state are kept under it, so each deck has its own even
+    /// where their columns line up.
+    id: Uuid,
 }
 
 impl Default for Deck {
@@ -293,6 +299,7 @@ impl Default for Deck {
             icon: '🇩',
             name: String::from("Default Deck"),
             columns,
+            id: Uuid::new_v4(),
         }
     }
 }
@@ -305,6 +312,7 @@ impl Deck {
             icon,
             name,
             columns,
+            id: Uuid::new_v4(),
         }
     }
 
@@ -313,9 +321,25 @@ impl Deck {
             icon,
             name,
             columns,
+            id: Uuid::new_v4(),
         }
     }
 
+    /// The deck as it was saved, keeping the id it had
+    pub fn with_id(mut self, id: Uuid) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// What a column's scroll and navigation state is kept under
+    pub fn column_id(&self, col: usize) -> egui::Id {
+        egui::Id::new(("deck_column", self.id, col))
+    }
+
     pub fn columns(&self) -> &Columns {
         &self.columns
     }
@@ -359,6 +383,7 @@ pub fn demo_decks(
                 &txn,
                 timeline_cache,
                 ctx.unknown_ids,
+                &ctx.accounts.mutefun(),
             );
         }
 
@@ -368,6 +393,7 @@ pub fn demo_decks(
             icon: '🇩',
             name: String::from("Demo Deck"),
             columns,
+            id: Uuid::new_v4(),
         }
     };
 
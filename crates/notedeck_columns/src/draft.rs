@@ -1,7 +1,12 @@
 use egui::text::LayoutJob;
-use poll_promise::Promise;
 
-use crate::{media_upload::Nip94Event, post::PostBuffer, ui::note::PostType, Error};
+use crate::{
+    emoji::CustomEmoji,
+    media_upload::{Nip94Event, PendingUpload},
+    post::{CompletionKind, NewPoll, PostBuffer},
+    ui::note::PostType,
+};
+use enostr::{NoteId, Pubkey};
 use std::collections::HashMap;
 
 #[derive(Default)]
@@ -9,15 +14,111 @@ pub struct Draft {
     pub buffer: PostBuffer,
     pub cur_layout: Option<(String, LayoutJob)>, // `PostBuffer::text_buffer` to current `LayoutJob`
     pub cur_mention_hint: Option<MentionHint>,
+    pub cur_completion: Option<CompletionHint>,
     pub uploaded_media: Vec<Nip94Event>, // media uploads to include
-    pub uploading_media: Vec<Promise<Result<Nip94Event, Error>>>, // promises that aren't ready yet
+    pub uploading_media: Vec<PendingUpload>, // uploads that aren't done yet
     pub upload_errors: Vec<String>,      // media upload errors to show the user
+
+    /// Attach a NIP-36 content warning, with this reason if it isn't empty
+    pub content_warning: Option<String>,
+
+    /// Have the note expire this many seconds after it's posted
+    pub expires_in: Option<u64>,
+
+    /// Post it as a NIP-88 poll with these options
+    pub poll: Option<PollDraft>,
+
+    /// Custom emoji picked from completions, tagged if they're still in
+    /// the text when posting
+    pub emojis: Vec<CustomEmoji>,
+
+    /// For replies, people the note being replied to tags that were
+    /// unticked, so they won't be notified
+    pub untagged: Vec<Pubkey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PollDraft {
+    pub options: Vec<String>,
+    pub multiple_choice: bool,
+
+    /// Close voting this many seconds after it's posted
+    pub ends_in: Option<u64>,
+}
+
+impl Default for PollDraft {
+    fn default() -> Self {
+        Self {
+            options: vec![String::new(), String::new()],
+            multiple_choice: false,
+            ends_in: Some(24 * 3600),
+        }
+    }
+}
+
+impl PollDraft {
+    /// At least two options filled in
+    pub fn is_ready(&self) -> bool {
+        self.options
+            .iter()
+            .filter(|option| !option.trim().is_empty())
+            .count()
+            >= 2
+    }
+
+    /// The poll to post, closing relative to now
+    pub fn to_poll(&self) -> NewPoll {
+        NewPoll {
+            options: self
+                .options
+                .iter()
+                .map(|option| option.trim())
+                .filter(|option| !option.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            multiple_choice: self.multiple_choice,
+            ends_at: self.ends_in.map(|secs| crate::scheduled::unix_now() + secs),
+        }
+    }
 }
 
 pub struct MentionHint {
     pub index: usize,
     pub pos: egui::Pos2,
     pub text: String,
+
+    /// Profiles matching `text`, best first
+    pub results: Vec<[u8; 32]>,
+
+    /// The result arrow keys have moved to
+    pub selected: usize,
+}
+
+/// Suggestions for the hashtag or emoji shortcode being typed
+pub struct CompletionHint {
+    pub kind: CompletionKind,
+
+    /// Char index of the `#` or `:` the suggestion replaces from
+    pub start: usize,
+    pub text: String,
+    pub pos: egui::Pos2,
+    pub items: Vec<CompletionItem>,
+    pub selected: usize,
+}
+
+pub enum CompletionItem {
+    Hashtag(String),
+    Emoji(CustomEmoji),
+}
+
+impl CompletionItem {
+    /// What goes in the text in place of what was typed
+    pub fn replacement(&self) -> String {
+        match self {
+            CompletionItem::Hashtag(tag) => format!("#{tag} "),
+            CompletionItem::Emoji(emoji) => format!(":{}: ", emoji.shortcode),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -25,6 +126,10 @@ pub struct Drafts {
     replies: HashMap<[u8; 32], Draft>,
     quotes: HashMap<[u8; 32], Draft>,
     compose: Draft,
+
+    /// What was last written to disk, so unchanged drafts aren't written
+    /// again. See [`crate::storage::autosave_drafts`].
+    pub(crate) saved: Option<String>,
 }
 
 impl Drafts {
@@ -47,6 +152,42 @@ impl Drafts {
     pub fn quote_mut(&mut self, id: &[u8; 32]) -> &mut Draft {
         self.quotes.entry(*id).or_default()
     }
+
+    /// Drafts with something in them: the new note first, then replies,
+    /// then quotes
+    pub fn saved(&self) -> Vec<(PostType, &Draft)> {
+        let mut replies: Vec<_> = self.replies.iter().filter(|(_, d)| !d.is_empty()).collect();
+        replies.sort_by_key(|(id, _)| **id);
+        let mut quotes: Vec<_> = self.quotes.iter().filter(|(_, d)| !d.is_empty()).collect();
+        quotes.sort_by_key(|(id, _)| **id);
+
+        let compose = (!self.compose.is_empty()).then_some((PostType::New, &self.compose));
+        compose
+            .into_iter()
+            .chain(
+                replies
+                    .into_iter()
+                    .map(|(id, d)| (PostType::Reply(NoteId::new(*id)), d)),
+            )
+            .chain(
+                quotes
+                    .into_iter()
+                    .map(|(id, d)| (PostType::Quote(NoteId::new(*id)), d)),
+            )
+            .collect()
+    }
+
+    pub fn discard(&mut self, post_type: &PostType) {
+        match post_type {
+            PostType::New => self.compose.clear(),
+            PostType::Reply(id) => {
+                self.replies.remove(id.bytes());
+            }
+            PostType::Quote(id) => {
+                self.quotes.remove(id.bytes());
+            }
+        }
+    }
 }
 
 impl Draft {
@@ -54,10 +195,21 @@ impl Draft {
         Draft::default()
     }
 
+    /// Nothing written and nothing attached
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty() && self.uploaded_media.is_empty()
+    }
+
     pub fn clear(&mut self) {
         self.buffer = PostBuffer::default();
         self.upload_errors = Vec::new();
         self.uploaded_media = Vec::new();
         self.uploading_media = Vec::new();
+        self.content_warning = None;
+        self.expires_in = None;
+        self.poll = None;
+        self.cur_completion = None;
+        self.emojis = Vec::new();
+        self.untagged = Vec::new();
     }
 }
@@ -0,0 +1,208 @@
+//! NIP-30 custom emoji for the composer. They come from the user's emoji
+//! list (kind 10030), both inline and from the emoji sets (kind 30030) it
+//! points at.
+
+use std::collections::{HashMap, HashSet};
+
+use enostr::{ClientMessage, RelayPool};
+use nostrdb::{Filter, Ndb, Note, Transaction};
+
+use crate::subscriptions::{self, SubKind};
+
+const EMOJI_LIST_KIND: u32 = 10030;
+const EMOJI_SET_KIND: u32 = 30030;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CustomEmoji {
+    pub shortcode: String,
+    pub url: String,
+}
+
+/// The `30030:<pubkey>:<identifier>` an emoji list uses to point at a set
+#[derive(Debug, PartialEq, Eq)]
+struct SetAddress {
+    pubkey: [u8; 32],
+    identifier: String,
+}
+
+impl SetAddress {
+    fn parse(addr: &str) -> Option<Self> {
+        let mut parts = addr.splitn(3, ':');
+        if parts.next()? != EMOJI_SET_KIND.to_string() {
+            return None;
+        }
+        let pubkey = hex::decode(parts.next()?).ok()?.try_into().ok()?;
+        let identifier = parts.next()?.to_owned();
+        Some(SetAddress { pubkey, identifier })
+    }
+
+    fn filter(&self) -> Filter {
+        Filter::new()
+            .kinds([EMOJI_SET_KIND as u64])
+            .authors([&self.pubkey])
+            .tags([self.identifier.as_str()], 'd')
+            .limit(1)
+            .build()
+    }
+}
+
+fn list_filter(pubkey: &[u8; 32]) -> Filter {
+    Filter::new()
+        .kinds([EMOJI_LIST_KIND as u64])
+        .authors([pubkey])
+        .limit(1)
+        .build()
+}
+
+/// Asks relays for emoji lists and sets we haven't asked for yet, so they
+/// show up in the composer. Sets can only be requested once the list that
+/// points at them has arrived, so this is called every frame a composer
+/// is open.
+#[derive(Default)]
+pub struct EmojiFetcher {
+    requested: HashSet<String>,
+}
+
+impl EmojiFetcher {
+    pub fn fetch(
+        &mut self,
+        ndb: &Ndb,
+        subs: &mut HashMap<String, SubKind>,
+        pool: &mut RelayPool,
+        pubkey: &[u8; 32],
+    ) {
+        let Ok(txn) = Transaction::new(ndb) else {
+            return;
+        };
+
+        let mut filters = Vec::new();
+        if self
+            .requested
+            .insert(format!("{EMOJI_LIST_KIND}:{}", hex::encode(pubkey)))
+        {
+            filters.push(list_filter(pubkey));
+        }
+
+        if let Some(list) = query_one(ndb, &txn, list_filter(pubkey)) {
+            for addr in set_addresses(&list) {
+                if self.requested.insert(addr.to_owned()) {
+                    if let Some(set) = SetAddress::parse(addr) {
+                        filters.push(set.filter());
+                    }
+                }
+            }
+        }
+
+        if filters.is_empty() {
+            return;
+        }
+
+        let sub_id = subscriptions::new_sub_id();
+        subs.insert(sub_id.clone(), SubKind::OneShot);
+        pool.send(&ClientMessage::req(sub_id, filters));
+    }
+}
+
+/// Every emoji `pubkey` can use, from their list first and then from its
+/// sets in order. The first emoji with a given shortcode wins.
+pub fn user_emojis(ndb: &Ndb, txn: &Transaction, pubkey: &[u8; 32]) -> Vec<CustomEmoji> {
+    let Some(list) = query_one(ndb, txn, list_filter(pubkey)) else {
+        return Vec::new();
+    };
+
+    let mut emojis = Vec::new();
+    let mut seen = HashSet::new();
+    let mut add = |note: &Note| {
+        for emoji in emoji_tags(note) {
+            if seen.insert(emoji.shortcode.clone()) {
+                emojis.push(emoji);
+            }
+        }
+    };
+
+    add(&list);
+    for addr in set_addresses(&list) {
+        let Some(set) = SetAddress::parse(addr) else {
+            continue;
+        };
+        if let Some(set) = query_one(ndb, txn, set.filter()) {
+            add(&set);
+        }
+    }
+
+    emojis
+}
+
+fn query_one<'a>(ndb: &Ndb, txn: &'a Transaction, filter: Filter) -> Option<Note<'a>> {
+    ndb.query(txn, &[filter], 1)
+        .ok()?
+        .into_iter()
+        .next()
+        .map(|res| res.note)
+}
+
+fn set_addresses<'a>(list: &'a Note<'a>) -> impl Iterator<Item = &'a str> + 'a {
+    list.tags().into_iter().filter_map(|tag| {
+        if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some("a") {
+            return None;
+        }
+        tag.get_unchecked(1)
+            .variant()
+            .str()
+            .filter(|addr| addr.starts_with("30030:"))
+    })
+}
+
+fn emoji_tags(note: &Note) -> Vec<CustomEmoji> {
+    let mut emojis = Vec::new();
+    for tag in note.tags() {
+        if tag.count() < 3 || tag.get_unchecked(0).variant().str() != Some("emoji") {
+            continue;
+        }
+        let (Some(shortcode), Some(url)) = (
+            tag.get_unchecked(1).variant().str(),
+            tag.get_unchecked(2).variant().str(),
+        ) else {
+            continue;
+        };
+        if is_shortcode(shortcode) && !url.is_empty() {
+            emojis.push(CustomEmoji {
+                shortcode: shortcode.to_owned(),
+                url: url.to_owned(),
+            });
+        }
+    }
+    emojis
+}
+
+/// NIP-30 shortcodes are alphanumeric, with underscores and hyphens
+pub fn is_shortcode(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_address() {
+        let pk = "32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245";
+        let addr = SetAddress::parse(&format!("30030:{pk}:blobcats")).unwrap();
+        assert_eq!(hex::encode(addr.pubkey), pk);
+        assert_eq!(addr.identifier, "blobcats");
+
+        assert!(SetAddress::parse(&format!("30023:{pk}:blobcats")).is_none());
+        assert!(SetAddress::parse("30030:nothex:blobcats").is_none());
+    }
+
+    #[test]
+    fn test_is_shortcode() {
+        assert!(is_shortcode("soapbox"));
+        assert!(is_shortcode("blob_cat-2"));
+        assert!(!is_shortcode(""));
+        assert!(!is_shortcode("two words"));
+        assert!(!is_shortcode("colon:"));
+    }
+}
@@ -12,6 +12,8 @@ use tracing::error;
 pub enum AcquireKeyError {
     InvalidKey,
     Nip05Failed(String),
+    InvalidBunker(String),
+    Ncryptsec(String),
 }
 
 impl std::fmt::Display for AcquireKeyError {
@@ -21,6 +23,8 @@ impl std::fmt::Display for AcquireKeyError {
             AcquireKeyError::Nip05Failed(e) => {
                 write!(f, "Failed to get pubkey from Nip05 address: {e}")
             }
+            AcquireKeyError::InvalidBunker(e) => write!(f, "Invalid bunker uri: {e}"),
+            AcquireKeyError::Ncryptsec(e) => write!(f, "Couldn't decrypt key: {e}"),
         }
     }
 }
@@ -148,6 +152,21 @@ pub fn perform_key_retrieval(key: &str) -> Promise<Result<Keypair, AcquireKeyErr
     }
 }
 
+/// Decrypt an ncryptsec (NIP-49). This runs scrypt so it happens on
+/// another thread.
+pub fn perform_ncryptsec_retrieval(
+    ncryptsec: &str,
+    passphrase: &str,
+) -> Promise<Result<Keypair, AcquireKeyError>> {
+    let ncryptsec = ncryptsec.to_owned();
+    let passphrase = passphrase.to_owned();
+    Promise::spawn_thread("ncryptsec", move || {
+        notedeck::decrypt_ncryptsec(&ncryptsec, &passphrase)
+            .map(Keypair::from_secret)
+            .map_err(|e| AcquireKeyError::Ncryptsec(e.to_string()))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4,6 +4,7 @@ mod error;
 //mod note;
 //mod block;
 pub mod accounts;
+pub mod action_middleware;
 pub mod actionbar;
 pub mod app_creation;
 mod app_style;
@@ -12,24 +13,39 @@ mod column;
 mod deck_state;
 mod decks;
 mod draft;
+mod emoji;
 mod key_parsing;
 pub mod login_manager;
 mod media_upload;
 mod multi_subscriber;
 mod nav;
+mod negentropy;
+mod open_uri;
 mod post;
+mod post_as;
+mod pow;
 mod profile;
 mod profile_state;
+mod reading_size;
+mod recurring_zaps;
 pub mod relay_pool_manager;
+mod relay_test;
+mod reply_tags;
 mod route;
+mod scheduled;
 mod search;
+mod shortcuts;
 mod subscriptions;
 mod support;
 mod test_data;
 pub mod timeline;
+mod truncate;
 pub mod ui;
+mod undo;
+mod undo_send;
 mod unknowns;
 mod view_state;
+mod whats_new;
 
 #[cfg(test)]
 #[macro_use]
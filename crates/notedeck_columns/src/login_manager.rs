@@ -1,18 +1,29 @@
-use crate::key_parsing::perform_key_retrieval;
 use crate::key_parsing::AcquireKeyError;
+use crate::key_parsing::{perform_key_retrieval, perform_ncryptsec_retrieval};
 use egui::{TextBuffer, TextEdit};
 use enostr::Keypair;
+use notedeck::BunkerUri;
 use poll_promise::Promise;
 
 /// The state data for acquiring a nostr key
 #[derive(Default)]
 pub struct AcquireKeyState {
     desired_key: String,
+
+    /// For decrypting the key if it's an ncryptsec
+    passphrase: String,
     promise_query: Option<(String, Promise<Result<Keypair, AcquireKeyError>>)>,
     error: Option<AcquireKeyError>,
     key_on_error: Option<String>,
     should_create_new: bool,
     show_password: bool,
+    bunker: Option<BunkerUri>,
+    should_connect_qr: bool,
+    should_connect_amber: bool,
+
+    /// Whether a NIP-55 signer app is installed, checked once per visit
+    /// to the login screen
+    external_signer: Option<bool>,
 }
 
 impl<'a> AcquireKeyState {
@@ -28,8 +39,47 @@ impl<'a> AcquireKeyState {
         textedit_closure(&mut self.desired_key)
     }
 
+    pub fn get_passphrase_textedit(
+        &'a mut self,
+        textedit_closure: fn(&'a mut dyn TextBuffer) -> TextEdit<'a>,
+    ) -> TextEdit<'a> {
+        textedit_closure(&mut self.passphrase)
+    }
+
+    /// Whether the key entered is an ncryptsec we need a passphrase for
+    pub fn needs_passphrase(&self) -> bool {
+        notedeck::is_ncryptsec(&self.desired_key)
+    }
+
     /// User pressed the 'acquire' button
     pub fn apply_acquire(&'a mut self) {
+        if self.needs_passphrase() {
+            if self.passphrase.is_empty() {
+                self.error = Some(AcquireKeyError::Ncryptsec(
+                    "enter the passphrase for this key".to_owned(),
+                ));
+                self.key_on_error = Some(self.desired_key.clone());
+                return;
+            }
+
+            // always retry, the passphrase might have changed
+            self.error = None;
+            let promise = perform_ncryptsec_retrieval(&self.desired_key, &self.passphrase);
+            self.promise_query = Some((self.desired_key.clone(), promise));
+            return;
+        }
+
+        if self.desired_key.trim().starts_with("bunker://") {
+            match BunkerUri::parse(&self.desired_key) {
+                Ok(uri) => self.bunker = Some(uri),
+                Err(e) => {
+                    self.error = Some(AcquireKeyError::InvalidBunker(e.to_string()));
+                    self.key_on_error = Some(self.desired_key.clone());
+                }
+            }
+            return;
+        }
+
         let new_promise = match &self.promise_query {
             Some((query, _)) => {
                 if query != &self.desired_key {
@@ -101,6 +151,33 @@ impl<'a> AcquireKeyState {
         self.should_create_new
     }
 
+    /// The bunker:// uri the user entered, if they just submitted one
+    pub fn take_bunker_uri(&mut self) -> Option<BunkerUri> {
+        self.bunker.take()
+    }
+
+    pub fn should_connect_qr(&mut self) {
+        self.should_connect_qr = true;
+    }
+
+    pub fn take_connect_qr(&mut self) -> bool {
+        std::mem::take(&mut self.should_connect_qr)
+    }
+
+    pub fn should_connect_amber(&mut self) {
+        self.should_connect_amber = true;
+    }
+
+    pub fn take_connect_amber(&mut self) -> bool {
+        std::mem::take(&mut self.should_connect_amber)
+    }
+
+    pub fn has_external_signer(&mut self) -> bool {
+        *self
+            .external_signer
+            .get_or_insert_with(notedeck::platform::external_signer_available)
+    }
+
     pub fn loading_and_error_ui(&mut self, ui: &mut egui::Ui) {
         ui.add_space(8.0);
 
@@ -132,7 +209,9 @@ fn show_error(ui: &mut egui::Ui, err: &AcquireKeyError) {
             AcquireKeyError::InvalidKey => egui::Label::new(
                 egui::RichText::new("Invalid key.").color(ui.visuals().error_fg_color),
             ),
-            AcquireKeyError::Nip05Failed(e) => {
+            AcquireKeyError::Nip05Failed(e)
+            | AcquireKeyError::InvalidBunker(e)
+            | AcquireKeyError::Ncryptsec(e) => {
                 egui::Label::new(egui::RichText::new(e).color(ui.visuals().error_fg_color))
             }
         };
@@ -1,11 +1,15 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use base64::{prelude::BASE64_URL_SAFE, Engine};
+use base64::{
+    prelude::{BASE64_STANDARD, BASE64_URL_SAFE},
+    Engine,
+};
 use ehttp::Request;
 use nostrdb::{Note, NoteBuilder};
-use notedeck::SupportedMimeType;
+use notedeck::{storage, DataPath, DataPathType, Directory, SupportedMimeType};
 use poll_promise::Promise;
 use sha2::{Digest, Sha256};
+use tracing::{error, info};
 use url::Url;
 
 use crate::Error;
@@ -13,6 +17,233 @@ use notedeck_ui::images::fetch_binary_from_disk;
 
 pub const NOSTR_BUILD_URL: fn() -> Url = || Url::parse("http://nostr.build").unwrap();
 const NIP96_WELL_KNOWN: &str = ".well-known/nostr/nip96.json";
+const MEDIA_SERVER_FILE: &str = "media_server.json";
+
+/// BUD-01 authorization event
+const BLOSSOM_AUTH_KIND: u32 = 24242;
+const BLOSSOM_AUTH_SECS: u64 = 5 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum MediaServerKind {
+    #[default]
+    Nip96,
+    Blossom,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MediaServerConfig {
+    #[serde(default)]
+    kind: MediaServerKind,
+    url: String,
+}
+
+/// Where the composer uploads to. Either a NIP-96 server, or Blossom
+/// servers: the account's own server list if it has one, otherwise the
+/// configured url. Defaults to nostr.build over NIP-96 and is saved
+/// whenever it's changed.
+pub struct MediaServer {
+    directory: Directory,
+    kind: MediaServerKind,
+    url: Url,
+
+    /// The selected account's kind 10063 list
+    blossom_servers: Vec<String>,
+}
+
+impl MediaServer {
+    pub fn new(path: &DataPath) -> Self {
+        let directory = Directory::new(path.path(DataPathType::Setting));
+        let config = directory
+            .get_file(MEDIA_SERVER_FILE.to_owned())
+            .ok()
+            .and_then(|json| {
+                serde_json::from_str::<MediaServerConfig>(&json)
+                    .ok()
+                    // this used to be just the url
+                    .or_else(|| {
+                        serde_json::from_str::<String>(&json)
+                            .ok()
+                            .map(|url| MediaServerConfig {
+                                kind: MediaServerKind::Nip96,
+                                url,
+                            })
+                    })
+            });
+
+        let (kind, url) = config
+            .and_then(|c| Some((c.kind, Url::parse(&c.url).ok()?)))
+            .unwrap_or_else(|| (MediaServerKind::Nip96, NOSTR_BUILD_URL()));
+
+        MediaServer {
+            directory,
+            kind,
+            url,
+            blossom_servers: Vec::new(),
+        }
+    }
+
+    pub fn kind(&self) -> MediaServerKind {
+        self.kind
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn blossom_servers(&self) -> &[String] {
+        &self.blossom_servers
+    }
+
+    pub fn set_blossom_servers(&mut self, servers: &[String]) {
+        if self.blossom_servers != servers {
+            self.blossom_servers = servers.to_vec();
+        }
+    }
+
+    pub fn set(&mut self, kind: MediaServerKind, url: Url) {
+        if kind == self.kind && url == self.url {
+            return;
+        }
+        self.kind = kind;
+        self.url = url;
+
+        let config = MediaServerConfig {
+            kind,
+            url: self.url.to_string(),
+        };
+        let json = match serde_json::to_string(&config) {
+            Ok(json) => json,
+            Err(e) => {
+                error!("could not serialize media server: {e}");
+                return;
+            }
+        };
+
+        match storage::write_file(
+            &self.directory.file_path,
+            MEDIA_SERVER_FILE.to_owned(),
+            &json,
+        ) {
+            Ok(_) => info!("saved media server {:?} {}", self.kind, self.url),
+            Err(e) => error!("could not save media server: {e}"),
+        }
+    }
+
+    /// The host uploads will go to first, for showing the user
+    pub fn host(&self) -> String {
+        match self.kind {
+            MediaServerKind::Blossom if !self.blossom_servers.is_empty() => {
+                Url::parse(&self.blossom_servers[0])
+                    .ok()
+                    .and_then(|url| url.host_str().map(str::to_owned))
+                    .unwrap_or_else(|| self.blossom_servers[0].clone())
+            }
+            _ => self.url.host_str().unwrap_or_default().to_owned(),
+        }
+    }
+
+    pub fn upload(
+        &self,
+        seckey: [u8; 32],
+        media_path: MediaPath,
+    ) -> Promise<Result<Nip94Event, Error>> {
+        let local_path = media_path.full_path.clone();
+        let upload = match self.kind {
+            MediaServerKind::Nip96 => provider_nip96_upload(self.url.clone(), seckey, media_path),
+            MediaServerKind::Blossom => {
+                let servers = if self.blossom_servers.is_empty() {
+                    vec![self.url.as_str().trim_end_matches('/').to_owned()]
+                } else {
+                    self.blossom_servers.clone()
+                };
+                blossom_upload(servers, seckey, media_path)
+            }
+        };
+
+        Promise::spawn_thread("fill_image_meta", move || {
+            let mut media = upload.block_and_take()?;
+            fill_image_meta(&mut media, &local_path);
+            Ok(media)
+        })
+    }
+}
+
+/// Servers don't always tell us an image's size and blurhash. Work them
+/// out from the local file so readers can lay out the image before it
+/// loads.
+fn fill_image_meta(media: &mut Nip94Event, local_path: &Path) {
+    if media.dimensions.is_some() && media.blurhash.is_some() {
+        return;
+    }
+
+    // not an image, or not one we can decode. the post goes out without them
+    let Ok(image) = image::open(local_path) else {
+        return;
+    };
+
+    if media.dimensions.is_none() {
+        media.dimensions = Some((image.width(), image.height()));
+    }
+
+    if media.blurhash.is_none() {
+        // a blurhash only has a handful of components, a small thumbnail
+        // gives the same result for a fraction of the work
+        let thumb = image.thumbnail(64, 64).to_rgba8();
+        match blurhash::encode(4, 3, thumb.width(), thumb.height(), thumb.as_raw()) {
+            Ok(hash) => media.blurhash = Some(hash),
+            Err(e) => error!(
+                "could not compute blurhash for {}: {e}",
+                local_path.display()
+            ),
+        }
+    }
+}
+
+/// Parse what the user typed as a server. A bare domain is assumed to
+/// be https.
+pub fn parse_media_server(input: &str) -> Option<Url> {
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+
+    let url = if input.contains("://") {
+        Url::parse(input).ok()?
+    } else {
+        Url::parse(&format!("https://{input}")).ok()?
+    };
+
+    (matches!(url.scheme(), "http" | "https") && url.host_str().is_some()).then_some(url)
+}
+
+/// Files pasted into the composer come through as their paths, or
+/// `file://` urls from most file managers. Returns them if every line of
+/// the paste is a file we can upload.
+pub fn pasted_media_paths(text: &str) -> Option<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let path = if line.starts_with("file://") {
+            Url::parse(line).ok()?.to_file_path().ok()?
+        } else {
+            PathBuf::from(line)
+        };
+
+        let ext = path.extension().and_then(|e| e.to_str())?;
+        if SupportedMimeType::from_extension(ext).is_err() || !path.is_file() {
+            return None;
+        }
+        paths.push(path);
+    }
+
+    (!paths.is_empty()).then_some(paths)
+}
+
+/// An upload the composer is waiting on
+pub struct PendingUpload {
+    pub file_name: String,
+    pub promise: Promise<Result<Nip94Event, Error>>,
+}
 
 fn get_upload_url(nip96_url: Url) -> Promise<Result<String, Error>> {
     let request = Request::get(nip96_url);
@@ -151,14 +382,25 @@ pub fn nip96_upload(
 pub fn nostrbuild_nip96_upload(
     seckey: [u8; 32],
     media_path: MediaPath,
+) -> Promise<Result<Nip94Event, Error>> {
+    provider_nip96_upload(NOSTR_BUILD_URL(), seckey, media_path)
+}
+
+/// Look up `provider`'s upload url from its NIP-96 well-known document,
+/// then upload to it
+pub fn provider_nip96_upload(
+    provider: Url,
+    seckey: [u8; 32],
+    media_path: MediaPath,
 ) -> Promise<Result<Nip94Event, Error>> {
     let (sender, promise) = Promise::new();
     std::thread::spawn(move || {
-        let upload_url = match get_nostr_build_upload_url().block_and_take() {
+        let host = provider.host_str().unwrap_or_default().to_owned();
+        let upload_url = match get_upload_url_from_provider(provider).block_and_take() {
             Ok(url) => url,
             Err(e) => {
                 sender.send(Err(Error::Generic(format!(
-                    "could not get nostrbuild upload url: {e}"
+                    "could not get upload url from {host}: {e}"
                 ))));
                 return;
             }
@@ -230,6 +472,193 @@ fn find_nip94_ev_in_json(json: String) -> Result<Nip94Event, Error> {
     }
 }
 
+fn create_blossom_auth(seckey: &[u8; 32], sha256: &str, content: &str) -> Result<String, Error> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let note = NoteBuilder::new()
+        .kind(BLOSSOM_AUTH_KIND)
+        .content(content)
+        .start_tag()
+        .tag_str("t")
+        .tag_str("upload")
+        .start_tag()
+        .tag_str("x")
+        .tag_str(sha256)
+        .start_tag()
+        .tag_str("expiration")
+        .tag_str(&(now + BLOSSOM_AUTH_SECS).to_string())
+        .sign(seckey)
+        .build()
+        .ok_or_else(|| Error::Generic("could not build blossom auth".to_owned()))?;
+
+    let json = note.json().map_err(|e| Error::Generic(e.to_string()))?;
+    Ok(format!("Nostr {}", BASE64_STANDARD.encode(json)))
+}
+
+/// Upload to the first of `servers` that takes the blob (BUD-02), then
+/// ask the rest to mirror it (BUD-04) so it survives that server going
+/// away.
+pub fn blossom_upload(
+    servers: Vec<String>,
+    seckey: [u8; 32],
+    media_path: MediaPath,
+) -> Promise<Result<Nip94Event, Error>> {
+    let (sender, promise) = Promise::new();
+    std::thread::spawn(move || {
+        let bytes = match fetch_binary_from_disk(media_path.full_path.clone()) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                sender.send(Err(Error::Generic(format!(
+                    "could not read contents of file to upload: {e}"
+                ))));
+                return;
+            }
+        };
+
+        let sha256 = sha256_hex(&bytes);
+        let auth = match create_blossom_auth(
+            &seckey,
+            &sha256,
+            &format!("Upload {}", media_path.file_name),
+        ) {
+            Ok(auth) => auth,
+            Err(e) => {
+                sender.send(Err(e));
+                return;
+            }
+        };
+
+        let mime = media_path.media_type.to_mime();
+        let mut errors = Vec::new();
+        let mut uploaded = None;
+        for (i, server) in servers.iter().enumerate() {
+            match blossom_put(server, bytes.clone(), mime, &auth, &sha256) {
+                Ok(media) => {
+                    uploaded = Some((i, media));
+                    break;
+                }
+                Err(e) => {
+                    error!("blossom upload to {server} failed: {e}");
+                    errors.push(format!("{server}: {e}"));
+                }
+            }
+        }
+
+        let Some((primary, media)) = uploaded else {
+            sender.send(Err(Error::Generic(format!(
+                "could not upload to any Blossom server ({})",
+                errors.join(", ")
+            ))));
+            return;
+        };
+
+        let url = media.url.clone();
+        sender.send(Ok(media));
+
+        for server in servers.iter().skip(primary + 1) {
+            match blossom_mirror(server, &url, &auth) {
+                Ok(()) => info!("mirrored {url} to {server}"),
+                Err(e) => error!("could not mirror {url} to {server}: {e}"),
+            }
+        }
+    });
+
+    promise
+}
+
+fn blossom_put(
+    server: &str,
+    body: Vec<u8>,
+    mime: &str,
+    auth: &str,
+    sha256: &str,
+) -> Result<Nip94Event, Error> {
+    let request = Request {
+        method: "PUT".to_owned(),
+        url: format!("{server}/upload"),
+        headers: ehttp::Headers::new(&[("Content-Type", mime), ("Authorization", auth)]),
+        body,
+    };
+
+    let response = ehttp::fetch_blocking(&request).map_err(Error::Generic)?;
+    if !response.ok {
+        return Err(blossom_response_error(&response));
+    }
+
+    let text = response
+        .text()
+        .ok_or_else(|| Error::Generic("blob descriptor is not text".to_owned()))?;
+    blob_descriptor_to_nip94(text, sha256)
+}
+
+fn blossom_mirror(server: &str, url: &str, auth: &str) -> Result<(), Error> {
+    let body = serde_json::json!({ "url": url }).to_string().into_bytes();
+    let request = Request {
+        method: "PUT".to_owned(),
+        url: format!("{server}/mirror"),
+        headers: ehttp::Headers::new(&[
+            ("Content-Type", "application/json"),
+            ("Authorization", auth),
+        ]),
+        body,
+    };
+
+    let response = ehttp::fetch_blocking(&request).map_err(Error::Generic)?;
+    if response.ok {
+        Ok(())
+    } else {
+        Err(blossom_response_error(&response))
+    }
+}
+
+/// Blossom servers explain rejections in the X-Reason header
+fn blossom_response_error(response: &ehttp::Response) -> Error {
+    let reason = response
+        .headers
+        .get("x-reason")
+        .unwrap_or(&response.status_text);
+    Error::Generic(format!("{} {reason}", response.status))
+}
+
+/// Servers that support BUD-08 send the NIP-94 tags along with the
+/// descriptor, otherwise we make do with what the descriptor has
+fn blob_descriptor_to_nip94(json: &str, sha256: &str) -> Result<Nip94Event, Error> {
+    let v: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| Error::Generic(e.to_string()))?;
+
+    if v["sha256"].as_str() != Some(sha256) {
+        return Err(Error::Generic(
+            "server stored a different blob than we sent".to_owned(),
+        ));
+    }
+
+    let url = v["url"]
+        .as_str()
+        .ok_or_else(|| Error::Generic("blob descriptor has no url".to_owned()))?;
+
+    let from_tags = serde_json::from_value::<Vec<Vec<String>>>(v["nip94"].clone())
+        .ok()
+        .and_then(|tags| Nip94Event::from_tags_and_content(tags, String::new()).ok());
+
+    Ok(match from_tags {
+        Some(media) => media,
+        None => Nip94Event {
+            url: url.to_owned(),
+            ox: Some(sha256.to_owned()),
+            x: Some(sha256.to_owned()),
+            media_type: v["type"].as_str().map(str::to_owned),
+            dimensions: None,
+            blurhash: None,
+            thumb: None,
+            alt: None,
+            content: String::new(),
+        },
+    })
+}
+
 #[derive(Debug)]
 pub struct MediaPath {
     full_path: PathBuf,
@@ -238,6 +667,10 @@ pub struct MediaPath {
 }
 
 impl MediaPath {
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
     pub fn new(path: PathBuf) -> Result<Self, Error> {
         if let Some(ex) = path.extension().and_then(|f| f.to_str()) {
             let media_type = SupportedMimeType::from_extension(ex)?;
@@ -261,7 +694,7 @@ impl MediaPath {
     }
 }
 
-#[derive(Clone, Debug, serde::Deserialize)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Nip94Event {
     pub url: String,
     pub ox: Option<String>,
@@ -270,6 +703,11 @@ pub struct Nip94Event {
     pub dimensions: Option<(u32, u32)>,
     pub blurhash: Option<String>,
     pub thumb: Option<String>,
+
+    /// Description of the media for people who can't see it, written in
+    /// the composer
+    #[serde(default)]
+    pub alt: Option<String>,
     pub content: String,
 }
 
@@ -283,6 +721,7 @@ impl Nip94Event {
             dimensions: Some((width, height)),
             blurhash: None,
             thumb: None,
+            alt: None,
             content: String::new(),
         }
     }
@@ -336,6 +775,7 @@ impl Nip94Event {
             dimensions,
             blurhash,
             thumb,
+            alt: None,
             content,
         })
     }
@@ -348,11 +788,41 @@ mod tests {
     use enostr::FullKeypair;
 
     use crate::media_upload::{
-        get_upload_url_from_provider, nostrbuild_nip96_upload, MediaPath, NOSTR_BUILD_URL,
+        blob_descriptor_to_nip94, get_upload_url_from_provider, nostrbuild_nip96_upload,
+        parse_media_server, MediaPath, NOSTR_BUILD_URL,
     };
 
     use super::internal_nip96_upload;
 
+    #[test]
+    fn test_blob_descriptor() {
+        let sha = "b1674191a88ec5cdd733e4240a81803105dc412d6c6708d53ab94fc248f4f553";
+        let json = format!(
+            r#"{{"url":"https://cdn.example.com/{sha}.png","sha256":"{sha}","size":184292,"type":"image/png","uploaded":1725105921}}"#
+        );
+
+        let media = blob_descriptor_to_nip94(&json, sha).unwrap();
+        assert_eq!(media.url, format!("https://cdn.example.com/{sha}.png"));
+        assert_eq!(media.x.as_deref(), Some(sha));
+        assert_eq!(media.media_type.as_deref(), Some("image/png"));
+
+        assert!(blob_descriptor_to_nip94(&json, "00").is_err());
+    }
+
+    #[test]
+    fn test_parse_media_server() {
+        assert_eq!(
+            parse_media_server("nostr.build").map(|u| u.to_string()),
+            Some("https://nostr.build/".to_owned())
+        );
+        assert_eq!(
+            parse_media_server(" http://localhost:3000 ").map(|u| u.to_string()),
+            Some("http://localhost:3000/".to_owned())
+        );
+        assert_eq!(parse_media_server(""), None);
+        assert_eq!(parse_media_server("ftp://nostr.build"), None);
+    }
+
     #[test]
     fn test_nostrbuild_upload_url() {
         let promise = get_upload_url_from_provider(NOSTR_BUILD_URL());
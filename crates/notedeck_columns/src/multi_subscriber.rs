@@ -1,13 +1,19 @@
 use enostr::{Filter, RelayPool};
 use nostrdb::{Ndb, Subscription};
+use serde_json::Value;
 use tracing::{error, info};
 use uuid::Uuid;
 
+use crate::scheduled::unix_now;
+
 #[derive(Debug)]
 pub struct MultiSubscriber {
     pub filters: Vec<Filter>,
     pub local_subid: Option<Subscription>,
+    /// The live tail, which can share a REQ with other subscriptions
     pub remote_subid: Option<String>,
+    /// Notes the relays already have, closed on each once it's sent them
+    backfill_subid: Option<String>,
     local_subscribers: u32,
     remote_subscribers: u32,
 }
@@ -26,6 +32,7 @@ impl MultiSubscriber {
             filters,
             local_subid: None,
             remote_subid: None,
+            backfill_subid: None,
             local_subscribers: 0,
             remote_subscribers: 0,
         }
@@ -40,10 +47,38 @@ impl MultiSubscriber {
         };
 
         pool.unsubscribe(remote_subid.clone());
+        if let Some(backfill_subid) = self.backfill_subid.take() {
+            pool.unsubscribe(backfill_subid);
+        }
 
         self.remote_subid = None;
     }
 
+    /// Send the remote subscriptions, or send them again under the same
+    /// ids after the filters changed. Limited filters go out twice: as a
+    /// backfill that's closed once relays have sent what they have, and
+    /// as a live tail with no limit starting now. Only the tail is shared
+    /// with other subscriptions, see [`RelayPool::subscribe_shared`].
+    pub fn send_remote(&mut self, pool: &mut RelayPool) {
+        let (backfill, tail) = split_live_tail(&self.filters, unix_now());
+
+        if backfill.is_empty() {
+            if let Some(backfill_subid) = self.backfill_subid.take() {
+                pool.unsubscribe(backfill_subid);
+            }
+        } else {
+            let backfill_subid = self
+                .backfill_subid
+                .get_or_insert_with(|| Uuid::new_v4().to_string());
+            pool.subscribe_backfill(backfill_subid.clone(), backfill);
+        }
+
+        let remote_subid = self
+            .remote_subid
+            .get_or_insert_with(|| Uuid::new_v4().to_string());
+        pool.subscribe_shared(remote_subid.clone(), tail);
+    }
+
     /// Locally unsubscribe if we have one
     fn unsubscribe_local(&mut self, ndb: &mut Ndb) {
         let local_sub = if let Some(local_sub) = self.local_subid {
@@ -117,10 +152,8 @@ impl MultiSubscriber {
                 );
                 return;
             } else {
-                let subid = Uuid::new_v4().to_string();
-                pool.subscribe(subid.clone(), self.filters.clone());
+                self.send_remote(pool);
                 self.info_log(ndb, "First remote subscription");
-                self.remote_subid = Some(subid);
             }
         }
 
@@ -143,3 +176,66 @@ impl MultiSubscriber {
         }
     }
 }
+
+/// Split `filters` into the ones for notes relays already have and the
+/// ones for notes from `now` on. A filter with a limit is in both: as it
+/// is, and without the limit since `now`. Filters without a limit are
+/// already live and only go in the tail.
+fn split_live_tail(filters: &[Filter], now: u64) -> (Vec<Filter>, Vec<Filter>) {
+    let mut backfill = Vec::new();
+    let mut tail = Vec::new();
+    for filter in filters {
+        match live_tail(filter, now) {
+            Some(live) => {
+                backfill.push(filter.clone());
+                tail.push(live);
+            }
+            None => tail.push(filter.clone()),
+        }
+    }
+    (backfill, tail)
+}
+
+/// `filter` without its limit, starting at `now`. None if it has no limit.
+fn live_tail(filter: &Filter, now: u64) -> Option<Filter> {
+    let json = filter.json().ok()?;
+    let Value::Object(mut fields) = serde_json::from_str(&json).ok()? else {
+        return None;
+    };
+    fields.remove("limit")?;
+
+    let since = fields
+        .get("since")
+        .and_then(Value::as_u64)
+        .unwrap_or_default()
+        .max(now);
+    fields.insert("since".to_owned(), since.into());
+
+    Filter::from_json(&Value::Object(fields).to_string()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limited_filters_get_a_live_tail() {
+        let profile = Filter::new()
+            .authors([&[1; 32]])
+            .kinds([1, 6])
+            .limit(500)
+            .build();
+        let live = Filter::new().kinds([1]).since(10).build();
+
+        let (backfill, tail) = split_live_tail(&[profile.clone(), live.clone()], 1_000);
+        assert_eq!(backfill.len(), 1);
+        assert_eq!(backfill[0].limit(), Some(500));
+
+        // the tail is what profile columns can share a REQ with
+        assert_eq!(tail.len(), 2);
+        let json: Value = serde_json::from_str(&tail[0].json().unwrap()).unwrap();
+        assert_eq!(json.get("limit"), None);
+        assert_eq!(json["since"], 1_000);
+        assert_eq!(tail[1].json().unwrap(), live.json().unwrap());
+    }
+}
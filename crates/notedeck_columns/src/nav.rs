@@ -1,37 +1,44 @@
 use crate::{
     accounts::render_accounts_route,
-    app::{get_active_columns_mut, get_decks_mut},
+    app::{get_active_columns, get_active_columns_mut, get_decks, get_decks_mut},
     column::ColumnsAction,
     deck_state::DeckState,
     decks::{Deck, DecksAction, DecksCache},
     profile::{ProfileAction, SaveProfileChanges},
     profile_state::ProfileState,
+    recurring_zaps::NewRecurringZap,
     relay_pool_manager::RelayPoolManager,
     route::{Route, Router, SingletonRouter},
-    timeline::{route::render_timeline_route, TimelineCache},
+    storage,
+    timeline::{route::render_timeline_route, TimelineCache, TimelineKind},
     ui::{
         self,
         add_column::render_add_column_routes,
+        app_lock::{AppLockState, UnlockResponse, UnlockView},
         column::NavTitle,
         configure_deck::ConfigureDeckView,
         edit_deck::{EditDeckResponse, EditDeckView},
-        note::{custom_zap::CustomZapView, NewPostAction, PostAction, PostType},
+        import_deck::ImportDeckView,
+        note::{custom_zap::CustomZapView, NewPostAction, PostAction, PostType, ScheduleAction},
         profile::EditProfileView,
         search::{FocusState, SearchView},
         support::SupportView,
         wallet::{get_default_zap_state, WalletAction, WalletState, WalletView},
-        RelayView,
+        DraftsAction, DraftsView, InspectView, MutedView, MutedViewAction, RecurringZapsAction,
+        RecurringZapsView, RelayView, RelayViewAction, ScheduledAction, ScheduledView,
+        SettingsAction, SettingsView, TipJarState, TipJarView, WhatsNewView,
     },
+    undo::{UndoCommand, UndoStack},
     Damus,
 };
 
 use egui_nav::{Nav, NavAction, NavResponse, NavUiType, Percent, PopupResponse, PopupSheet};
+use enostr::{KeypairUnowned, Pubkey};
 use nostrdb::Transaction;
 use notedeck::{
-    get_current_default_msats, get_current_wallet, AccountsAction, AppContext, NoteAction,
-    NoteContext,
+    get_current_default_msats, get_current_wallet, get_wallet_for_mut, name::get_display_name,
+    AccountColors, Accounts, AccountsAction, AppContext, MuteAction, NoteAction, NoteContext,
 };
-use notedeck_ui::View;
 use tracing::error;
 
 /// The result of processing a nav response
@@ -57,6 +64,10 @@ pub enum RenderNavAction {
     ProfileAction(ProfileAction),
     SwitchingAction(SwitchingAction),
     WalletAction(WalletAction),
+    MuteAction(MuteAction),
+    DiscardDraft(PostType),
+    Schedule(ScheduleAction),
+    RecurringZap(NewRecurringZap),
 }
 
 pub enum SwitchingAction {
@@ -71,6 +82,7 @@ impl SwitchingAction {
         &self,
         timeline_cache: &mut TimelineCache,
         decks_cache: &mut DecksCache,
+        undo: &mut UndoStack,
         ctx: &mut AppContext<'_>,
     ) -> bool {
         match &self {
@@ -89,8 +101,10 @@ impl SwitchingAction {
             },
             SwitchingAction::Columns(columns_action) => match *columns_action {
                 ColumnsAction::Remove(index) => {
-                    let kinds_to_pop =
-                        get_active_columns_mut(ctx.accounts, decks_cache).delete_column(index);
+                    let columns = get_active_columns_mut(ctx.accounts, decks_cache);
+                    let column = columns.column(index).clone();
+                    let kinds_to_pop = columns.delete_column(index);
+                    undo.push(UndoCommand::CloseColumn { index, column });
                     for kind in &kinds_to_pop {
                         if let Err(err) = timeline_cache.pop(kind, ctx.ndb, ctx.pool) {
                             error!("error popping timeline: {err}");
@@ -101,6 +115,18 @@ impl SwitchingAction {
                 ColumnsAction::Switch(from, to) => {
                     get_active_columns_mut(ctx.accounts, decks_cache).move_col(from, to);
                 }
+
+                ColumnsAction::BindAccount(index, account) => {
+                    get_active_columns_mut(ctx.accounts, decks_cache)
+                        .column_mut(index)
+                        .account = account;
+                }
+
+                ColumnsAction::MinPow(index, bits) => {
+                    get_active_columns_mut(ctx.accounts, decks_cache)
+                        .column_mut(index)
+                        .min_pow = bits;
+                }
             },
             SwitchingAction::Decks(decks_action) => match *decks_action {
                 DecksAction::Switch(index) => {
@@ -120,6 +146,8 @@ impl From<PostAction> for RenderNavAction {
         match post_action {
             PostAction::QuotedNoteAction(note_action) => Self::NoteAction(note_action),
             PostAction::NewPostAction(new_post) => Self::PostAction(new_post),
+            PostAction::Discard(post_type) => Self::DiscardDraft(post_type),
+            PostAction::Schedule(schedule) => Self::Schedule(schedule),
         }
     }
 }
@@ -211,11 +239,18 @@ fn process_nav_resp(
     if let Some(action) = response.action {
         match action {
             NavAction::Returned => {
-                let r = app
-                    .columns_mut(ctx.accounts)
-                    .column_mut(col)
-                    .router_mut()
-                    .pop();
+                let columns = app.columns_mut(ctx.accounts);
+                let r = columns.column_mut(col).router_mut().pop();
+
+                // back at the route it borrowed, there's nothing left of it
+                let column = columns.column(col);
+                if column.ephemeral && column.router().routes().len() == 1 {
+                    for kind in &columns.delete_column(col) {
+                        if let Err(err) = app.timeline_cache.pop(kind, ctx.ndb, ctx.pool) {
+                            error!("error popping timeline: {err}");
+                        }
+                    }
+                }
 
                 if let Some(Route::Timeline(kind)) = &r {
                     if let Err(err) = app.timeline_cache.pop(kind, ctx.ndb, ctx.pool) {
@@ -301,7 +336,7 @@ impl RouterAction {
     }
 }
 
-fn process_render_nav_action(
+pub fn process_render_nav_action(
     app: &mut Damus,
     ctx: &mut AppContext<'_>,
     ui: &mut egui::Ui,
@@ -313,7 +348,13 @@ fn process_render_nav_action(
         RenderNavAction::PfpClicked => Some(RouterAction::PfpClicked),
 
         RenderNavAction::RemoveColumn => {
-            let kinds_to_pop = app.columns_mut(ctx.accounts).delete_column(col);
+            let columns = app.columns_mut(ctx.accounts);
+            let column = columns.column(col).clone();
+            let kinds_to_pop = columns.delete_column(col);
+            if !column.ephemeral {
+                app.undo
+                    .push(UndoCommand::CloseColumn { index: col, column });
+            }
 
             for kind in &kinds_to_pop {
                 if let Err(err) = app.timeline_cache.pop(kind, ctx.ndb, ctx.pool) {
@@ -325,8 +366,12 @@ fn process_render_nav_action(
         }
 
         RenderNavAction::PostAction(new_post_action) => {
-            let txn = Transaction::new(ctx.ndb).expect("txn");
-            match new_post_action.execute(ctx.ndb, &txn, ctx.pool, &mut app.drafts) {
+            if app.post_as.needs_confirmation(new_post_action.poster()) {
+                app.post_as.hold(col, new_post_action);
+                return None;
+            }
+
+            match crate::undo_send::send_post(app, ctx, col, new_post_action) {
                 Err(err) => tracing::error!("Error executing post action: {err}"),
                 Ok(_) => tracing::debug!("Post action executed"),
             }
@@ -337,8 +382,10 @@ fn process_render_nav_action(
         RenderNavAction::NoteAction(note_action) => {
             let txn = Transaction::new(ctx.ndb).expect("txn");
 
-            crate::actionbar::execute_and_process_note_action(
+            let result = crate::actionbar::execute_and_process_note_action(
                 note_action,
+                &mut app.note_action_middleware,
+                &mut app.undo,
                 ctx.ndb,
                 get_active_columns_mut(ctx.accounts, &mut app.decks_cache),
                 col,
@@ -352,11 +399,25 @@ fn process_render_nav_action(
                 ctx.zaps,
                 ctx.img_cache,
                 ui,
-            )
+            );
+
+            match result {
+                Ok(router_action) => router_action,
+                Err(err) => {
+                    tracing::error!("note action failed: {err}");
+                    notedeck::toast::error(ui.ctx(), err.to_string());
+                    None
+                }
+            }
         }
 
         RenderNavAction::SwitchingAction(switching_action) => {
-            if switching_action.process(&mut app.timeline_cache, &mut app.decks_cache, ctx) {
+            if switching_action.process(
+                &mut app.timeline_cache,
+                &mut app.decks_cache,
+                &mut app.undo,
+                ctx,
+            ) {
                 return Some(ProcessNavResult::SwitchOccurred);
             } else {
                 return None;
@@ -366,10 +427,54 @@ fn process_render_nav_action(
             &mut app.view_state.pubkey_to_profile_state,
             ctx.ndb,
             ctx.pool,
+            ctx.accounts,
         ),
         RenderNavAction::WalletAction(wallet_action) => {
             wallet_action.process(ctx.accounts, ctx.global_wallet)
         }
+        RenderNavAction::DiscardDraft(post_type) => {
+            let draft = std::mem::take(app.drafts.get_from_post_type(&post_type));
+            app.undo
+                .push(UndoCommand::DiscardDraft { post_type, draft });
+            None
+        }
+        RenderNavAction::Schedule(schedule) => {
+            let draft = std::mem::take(app.drafts.get_from_post_type(&schedule.post_type));
+            app.scheduled.schedule(
+                schedule.poster,
+                schedule.post_type,
+                draft,
+                schedule.publish_at,
+            );
+            notedeck::toast::info(
+                ui.ctx(),
+                "Scheduled. It'll be posted while Notedeck is open.",
+            );
+            Some(RouterAction::GoBack)
+        }
+        RenderNavAction::RecurringZap(new) => {
+            app.recurring_zaps.add(new);
+            notedeck::toast::info(
+                ui.ctx(),
+                format!(
+                    "Zapping {} sats {}, while Notedeck is open.",
+                    new.msats / 1000,
+                    new.interval.label()
+                ),
+            );
+            None
+        }
+        RenderNavAction::MuteAction(mute_action) => {
+            if let Some(account) = ctx.accounts.selected_account_pubkey().copied() {
+                if let Err(err) =
+                    ctx.accounts
+                        .apply_mute_action(ctx.ndb, ctx.pool, &account, &mute_action)
+                {
+                    error!("couldn't update mute list: {err}");
+                }
+            }
+            None
+        }
     };
 
     if let Some(action) = router_action {
@@ -383,6 +488,50 @@ fn process_render_nav_action(
     }
 }
 
+/// Who replies and quotes in a column are posted as. A column bound to an
+/// account only ever posts as that account.
+fn column_poster<'a>(
+    accounts: &'a Accounts,
+    column_account: Option<&Pubkey>,
+) -> Option<KeypairUnowned<'a>> {
+    match column_account {
+        Some(pubkey) => accounts
+            .find_account(pubkey.bytes())
+            .filter(|acc| acc.can_sign())
+            .map(|acc| acc.keypair()),
+        None => accounts.selected_or_first_signer(),
+    }
+}
+
+/// The logged in accounts, named for the settings screen
+fn account_names(ctx: &AppContext<'_>) -> Vec<(Pubkey, String)> {
+    let pubkeys: Vec<Pubkey> = ctx
+        .accounts
+        .get_accounts()
+        .iter()
+        .map(|acc| acc.key.pubkey)
+        .collect();
+    pubkey_names(ctx, &pubkeys)
+}
+
+fn pubkey_names(ctx: &AppContext<'_>, pubkeys: &[Pubkey]) -> Vec<(Pubkey, String)> {
+    let txn = Transaction::new(ctx.ndb).expect("txn");
+    pubkeys
+        .iter()
+        .map(|&pubkey| {
+            let profile = ctx.ndb.get_profile_by_pubkey(&txn, pubkey.bytes()).ok();
+            let name = get_display_name(profile.as_ref());
+            let name = name
+                .display_name
+                .or(name.username)
+                .map(str::to_owned)
+                .or_else(|| pubkey.npub())
+                .unwrap_or_default();
+            (pubkey, name)
+        })
+        .collect()
+}
+
 fn render_nav_body(
     ui: &mut egui::Ui,
     app: &mut Damus,
@@ -392,6 +541,26 @@ fn render_nav_body(
     col: usize,
     inner_rect: egui::Rect,
 ) -> Option<RenderNavAction> {
+    let column = get_active_columns(ctx.accounts, &app.decks_cache).column(col);
+    let column_account = column.account;
+    let min_pow = column.min_pow;
+    let acting = column.acting_pubkey(ctx.accounts);
+    let current_account_has_wallet = match &acting {
+        Some(pubkey) => {
+            get_wallet_for_mut(ctx.accounts, ctx.global_wallet, pubkey.bytes()).is_some()
+        }
+        None => get_current_wallet(ctx.accounts, ctx.global_wallet).is_some(),
+    };
+
+    if let (Route::ComposeNote | Route::Reply(_) | Route::Quote(_), Some(pubkey)) = (top, acting) {
+        app.emoji.fetch(
+            ctx.ndb,
+            &mut app.subscriptions.subs,
+            ctx.pool,
+            pubkey.bytes(),
+        );
+    }
+
     let mut note_context = NoteContext {
         ndb: ctx.ndb,
         img_cache: ctx.img_cache,
@@ -399,21 +568,45 @@ fn render_nav_body(
         zaps: ctx.zaps,
         pool: ctx.pool,
         job_pool: ctx.job_pool,
-        current_account_has_wallet: get_current_wallet(ctx.accounts, ctx.global_wallet).is_some(),
+        counts: ctx.counts,
+        polls: ctx.polls,
+        zap_goals: ctx.zap_goals,
+        paid_content: ctx.paid_content,
+        current_account_has_wallet,
+        current_account_can_sign: acting
+            .and_then(|pubkey| ctx.accounts.find_account(pubkey.bytes()))
+            .is_some_and(|acc| acc.can_sign()),
+        acting_pubkey: acting,
+        account_colors: AccountColors::new(ctx.accounts),
+        truncate_lines: app.truncation.settings().lines(),
     };
     match top {
-        Route::Timeline(kind) => render_timeline_route(
-            ctx.unknown_ids,
-            &mut app.timeline_cache,
-            ctx.accounts,
-            kind,
-            col,
-            app.note_options,
-            depth,
-            ui,
-            &mut note_context,
-            &mut app.jobs,
-        ),
+        Route::Timeline(kind) => {
+            let action = render_timeline_route(
+                ctx.unknown_ids,
+                &mut app.timeline_cache,
+                &mut app.reading_sizes,
+                ctx.accounts,
+                ctx.wot,
+                ctx.notifications.filters(),
+                ctx.followed_threads,
+                acting,
+                min_pow,
+                kind,
+                get_decks(ctx.accounts, &app.decks_cache)
+                    .active()
+                    .column_id(col),
+                app.note_options,
+                depth,
+                ui,
+                &mut note_context,
+                &mut app.jobs,
+            );
+            if depth == 1 {
+                remember_scroll(app, ctx.accounts, col, kind);
+            }
+            action
+        }
         Route::Accounts(amr) => {
             let mut action = render_accounts_route(
                 ui,
@@ -423,6 +616,8 @@ fn render_nav_body(
                 ctx.accounts,
                 &mut app.decks_cache,
                 &mut app.view_state.login,
+                &mut app.view_state.export_key,
+                &mut app.view_state.app_lock,
                 *amr,
             );
             let txn = Transaction::new(ctx.ndb).expect("txn");
@@ -433,10 +628,271 @@ fn render_nav_body(
         }
         Route::Relays => {
             let manager = RelayPoolManager::new(ctx.pool);
-            RelayView::new(ctx.accounts, manager, &mut app.view_state.id_string_map).ui(ui);
+            let action = RelayView::new(
+                ctx.accounts,
+                manager,
+                &mut app.view_state.id_string_map,
+                &app.relay_tests,
+            )
+            .ui(ui);
+            match action {
+                Some(RelayViewAction::OpenMuted) => {
+                    get_active_columns_mut(ctx.accounts, &mut app.decks_cache)
+                        .column_mut(col)
+                        .router_mut()
+                        .route_to(Route::Muted);
+                }
+                Some(RelayViewAction::Removed(url)) => {
+                    app.undo.push(UndoCommand::RemoveRelay(url));
+                }
+                Some(RelayViewAction::Test(url)) => {
+                    match ctx.accounts.selected_or_first_nsec().map(|kp| kp.to_full()) {
+                        Some(kp) => app.relay_tests.start(&url, &kp, ctx.pool),
+                        None => notedeck::toast::error(
+                            ui.ctx(),
+                            "Testing a relay needs an account logged in with its secret key",
+                        ),
+                    }
+                }
+                None => {}
+            }
+            None
+        }
+        Route::Settings => {
+            let accounts = account_names(ctx);
+            let dm_exceptions = pubkey_names(ctx, &ctx.quiet_hours.settings().dm_exceptions);
+            let notification_mutes = pubkey_names(ctx, &ctx.notifications.filters().muted);
+            let action = SettingsView::new(
+                ctx.img_cache.gif_states.settings(),
+                ctx.accessibility.settings(),
+                ctx.img_cache.link_previews.settings(),
+                ctx.accessibility.os_reduce_motion(),
+                &ctx.img_cache.disk_quota,
+                app.post_as.settings(),
+                app.truncation.settings(),
+                app.undo_send.settings(),
+                app.pow.settings(),
+                app.reply_tags.settings(),
+                ctx.quiet_hours.settings(),
+                ctx.sounds.settings(),
+                ctx.os_notifications.settings(),
+                ctx.notifications.filters(),
+                &accounts,
+                &dm_exceptions,
+                &notification_mutes,
+            )
+            .ui(ui)?;
+
+            let route = match action {
+                SettingsAction::OpenRelays => Route::Relays,
+                SettingsAction::OpenMuted => Route::Muted,
+                SettingsAction::OpenDrafts => Route::Drafts,
+                SettingsAction::OpenScheduled => Route::Scheduled,
+                SettingsAction::OpenRecurringZaps => Route::RecurringZaps,
+                SettingsAction::OpenTipJar => Route::TipJar,
+                SettingsAction::OpenWhatsNew => Route::WhatsNew,
+                SettingsAction::Animation(settings) => {
+                    ctx.img_cache.gif_states.set_settings(settings);
+                    return None;
+                }
+                SettingsAction::Accessibility(settings) => {
+                    ctx.accessibility.set_settings(settings);
+                    return None;
+                }
+                SettingsAction::PostAs(settings) => {
+                    app.post_as.set_settings(settings);
+                    return None;
+                }
+                SettingsAction::Truncate(settings) => {
+                    app.truncation.set_settings(settings);
+                    return None;
+                }
+                SettingsAction::UndoSend(settings) => {
+                    app.undo_send.set_settings(settings);
+                    return None;
+                }
+                SettingsAction::Pow(settings) => {
+                    app.pow.set_settings(settings);
+                    return None;
+                }
+                SettingsAction::ReplyTags(settings) => {
+                    app.reply_tags.set_settings(settings);
+                    return None;
+                }
+                SettingsAction::LinkPreviews(settings) => {
+                    ctx.img_cache.link_previews.set_settings(settings);
+                    return None;
+                }
+                SettingsAction::QuietHours(settings) => {
+                    ctx.quiet_hours.set_settings(settings);
+                    return None;
+                }
+                SettingsAction::Sounds(settings) => {
+                    ctx.sounds.set_settings(settings);
+                    return None;
+                }
+                SettingsAction::PreviewSound(sound) => {
+                    ctx.sounds.play(sound);
+                    return None;
+                }
+                SettingsAction::OsNotifications(settings) => {
+                    ctx.os_notifications.set_settings(settings);
+                    return None;
+                }
+                SettingsAction::NotificationFilters(settings) => {
+                    ctx.notifications.set_filters(settings);
+                    return None;
+                }
+                SettingsAction::CacheQuota(settings) => {
+                    ctx.img_cache.disk_quota.set_settings(settings);
+                    return None;
+                }
+                SettingsAction::ClearMediaCache => {
+                    ctx.img_cache.disk_quota.clear();
+                    return None;
+                }
+            };
+
+            get_active_columns_mut(ctx.accounts, &mut app.decks_cache)
+                .column_mut(col)
+                .router_mut()
+                .route_to(route);
+            None
+        }
+        Route::Muted => {
+            let Some(account) = ctx.accounts.selected_account_pubkey().copied() else {
+                ui.label("Log in to manage what you've muted");
+                return None;
+            };
+            let muted = ctx.accounts.muted(&account)?;
+            let synced = ctx.accounts.mutes_synced(&account);
+
+            let action = MutedView::new(
+                ctx.ndb,
+                muted,
+                ctx.wot.settings(),
+                synced,
+                &mut app.view_state.id_string_map,
+            )
+            .ui(ui)?;
+
+            match action {
+                MutedViewAction::Mute(action) => Some(RenderNavAction::MuteAction(action)),
+                MutedViewAction::Wot(settings) => {
+                    ctx.wot.set_settings(settings);
+                    None
+                }
+            }
+        }
+        Route::Drafts => {
+            let action = DraftsView::new(ctx.ndb, &app.drafts).ui(ui)?;
+            match action {
+                DraftsAction::Resume(post_type) => {
+                    let route = match post_type {
+                        PostType::New => Route::ComposeNote,
+                        PostType::Reply(id) => Route::Reply(id),
+                        PostType::Quote(id) => Route::Quote(id),
+                    };
+                    get_active_columns_mut(ctx.accounts, &mut app.decks_cache)
+                        .column_mut(col)
+                        .router_mut()
+                        .route_to(route);
+                }
+                DraftsAction::Discard(post_type) => app.drafts.discard(&post_type),
+            }
+            None
+        }
+        Route::Scheduled => {
+            let action = ScheduledView::new(&app.scheduled).ui(ui)?;
+            match action {
+                ScheduledAction::Edit(id) => {
+                    let post = app.scheduled.cancel(id)?;
+                    let route = match post.post_type {
+                        PostType::New => Route::ComposeNote,
+                        PostType::Reply(id) => Route::Reply(id),
+                        PostType::Quote(id) => Route::Quote(id),
+                    };
+                    // whatever was being written there goes to undo rather
+                    // than being overwritten
+                    let replaced = std::mem::replace(
+                        app.drafts.get_from_post_type(&post.post_type),
+                        post.draft,
+                    );
+                    if !replaced.is_empty() {
+                        app.undo.push(UndoCommand::DiscardDraft {
+                            post_type: post.post_type,
+                            draft: replaced,
+                        });
+                    }
+                    get_active_columns_mut(ctx.accounts, &mut app.decks_cache)
+                        .column_mut(col)
+                        .router_mut()
+                        .route_to(route);
+                }
+                ScheduledAction::Cancel(id) => {
+                    app.scheduled.cancel(id);
+                }
+            }
             None
         }
+        Route::RecurringZaps => {
+            let pubkeys: Vec<Pubkey> = app
+                .recurring_zaps
+                .zaps()
+                .iter()
+                .flat_map(|zap| [zap.sender, zap.recipient])
+                .collect();
+            let names = pubkey_names(ctx, &pubkeys);
+            match RecurringZapsView::new(&app.recurring_zaps, &names).ui(ui)? {
+                RecurringZapsAction::Cancel(id) => app.recurring_zaps.cancel(id),
+            }
+            None
+        }
+        Route::TipJar => {
+            let Some(kp) = acting.and_then(|pk| ctx.accounts.get_full(pk.bytes())) else {
+                ui.label(
+                    "A tip jar is posted from an account you're logged in to with its secret key.",
+                );
+                return None;
+            };
+            let kp = kp.to_full();
+
+            let state = app.view_state.tip_jars.entry(kp.pubkey).or_insert_with(|| {
+                // pinning keeps what's pinned already, so have it on hand
+                ctx.pool.send(&enostr::ClientMessage::req(
+                    "tip-jar-pins".to_owned(),
+                    vec![notedeck::pin_list_filter(&kp.pubkey)],
+                ));
+
+                let txn = Transaction::new(ctx.ndb).expect("txn");
+                let lud16 = ctx
+                    .ndb
+                    .get_profile_by_pubkey(&txn, kp.pubkey.bytes())
+                    .ok()
+                    .and_then(|p| p.record().profile()?.lud16().map(str::to_owned))
+                    .unwrap_or_default();
+                TipJarState::new(lud16)
+            });
+
+            let action = TipJarView::new(state).ui(ui)?;
+            let pubkey = kp.pubkey;
+            match action.process(ctx.ndb, ctx.pool, ctx.accounts, kp) {
+                Ok(()) => {
+                    app.view_state.tip_jars.remove(&pubkey);
+                    notedeck::toast::info(ui.ctx(), "Your tip jar is pinned to your profile.");
+                    Some(RenderNavAction::Back)
+                }
+                Err(err) => {
+                    notedeck::toast::error(ui.ctx(), err);
+                    None
+                }
+            }
+        }
         Route::Reply(id) => {
+            if render_unlock_prompt(ui, ctx.accounts, &mut app.view_state.app_lock) {
+                return None;
+            }
+
             let txn = if let Ok(txn) = Transaction::new(ctx.ndb) {
                 txn
             } else {
@@ -452,7 +908,7 @@ fn render_nav_body(
             };
 
             let id = egui::Id::new(("post", col, note.key().unwrap()));
-            let poster = ctx.accounts.selected_or_first_nsec()?;
+            let poster = column_poster(ctx.accounts, column_account.as_ref())?;
 
             let action = {
                 let draft = app.drafts.reply_mut(note.id());
@@ -469,6 +925,7 @@ fn render_nav_body(
                             &mut app.jobs,
                         )
                         .id_source(id)
+                        .media_server(Some(&mut app.media_server))
                         .show(ui)
                     })
                     .inner;
@@ -479,6 +936,10 @@ fn render_nav_body(
             action.map(Into::into)
         }
         Route::Quote(id) => {
+            if render_unlock_prompt(ui, ctx.accounts, &mut app.view_state.app_lock) {
+                return None;
+            }
+
             let txn = Transaction::new(ctx.ndb).expect("txn");
 
             let note = if let Ok(note) = ctx.ndb.get_note_by_id(&txn, id.bytes()) {
@@ -490,7 +951,7 @@ fn render_nav_body(
 
             let id = egui::Id::new(("post", col, note.key().unwrap()));
 
-            let poster = ctx.accounts.selected_or_first_nsec()?;
+            let poster = column_poster(ctx.accounts, column_account.as_ref())?;
             let draft = app.drafts.quote_mut(note.id());
 
             let response = egui::ScrollArea::vertical()
@@ -505,6 +966,7 @@ fn render_nav_body(
                         &mut app.jobs,
                     )
                     .id_source(id)
+                    .media_server(Some(&mut app.media_server))
                     .show(ui)
                 })
                 .inner;
@@ -512,7 +974,14 @@ fn render_nav_body(
             response.action.map(Into::into)
         }
         Route::ComposeNote => {
-            let kp = ctx.accounts.get_selected_account()?.key.to_full()?;
+            if render_unlock_prompt(ui, ctx.accounts, &mut app.view_state.app_lock) {
+                return None;
+            }
+
+            let kp = acting
+                .and_then(|pubkey| ctx.accounts.find_account(pubkey.bytes()))
+                .filter(|acc| acc.can_sign())?
+                .keypair();
             let draft = app.drafts.compose_mut();
 
             let txn = Transaction::new(ctx.ndb).expect("txn");
@@ -525,6 +994,7 @@ fn render_nav_body(
                 app.note_options,
                 &mut app.jobs,
             )
+            .media_server(Some(&mut app.media_server))
             .ui(&txn, ui);
 
             post_response.action.map(Into::into)
@@ -538,6 +1008,14 @@ fn render_nav_body(
             SupportView::new(&mut app.support).show(ui);
             None
         }
+        Route::WhatsNew => {
+            let feature = WhatsNewView::new(&app.whats_new).ui(ui)?;
+            get_active_columns_mut(ctx.accounts, &mut app.decks_cache)
+                .column_mut(col)
+                .router_mut()
+                .route_to(feature.route());
+            None
+        }
         Route::Search => {
             let id = ui.id().with(("search", depth, col));
             let navigating = get_active_columns_mut(ctx.accounts, &mut app.decks_cache)
@@ -565,17 +1043,30 @@ fn render_nav_body(
                 &ctx.accounts.get_selected_account().map(|a| (&a.key).into()),
                 &mut app.jobs,
             )
+            .wot(Some(&*ctx.wot))
             .show(ui, ctx.clipboard)
             .map(RenderNavAction::NoteAction)
         }
         Route::NewDeck => {
             let id = ui.id().with("new-deck");
             let new_deck_state = app.view_state.id_to_deck_state.entry(id).or_default();
+            let cur_acc = ctx.accounts.selected_account_pubkey().copied();
             let mut resp = None;
-            if let Some(config_resp) = ConfigureDeckView::new(new_deck_state).ui(ui) {
-                if let Some(cur_acc) = ctx.accounts.selected_account_pubkey() {
-                    app.decks_cache
-                        .add_deck(*cur_acc, Deck::new(config_resp.icon, config_resp.name));
+
+            let mut new_deck = ConfigureDeckView::new(new_deck_state)
+                .ui(ui)
+                .map(|config_resp| Deck::new(config_resp.icon, config_resp.name));
+            if let Some(json) = ImportDeckView::new(new_deck_state).ui(ui) {
+                let deck_user = cur_acc.unwrap_or(*app.decks_cache.get_fallback_pubkey());
+                match storage::import_deck(&json, ctx, &mut app.timeline_cache, &deck_user) {
+                    Ok(deck) => new_deck = Some(deck),
+                    Err(err) => new_deck_state.import_error = Some(err),
+                }
+            }
+
+            if let Some(deck) = new_deck {
+                if let Some(cur_acc) = cur_acc {
+                    app.decks_cache.add_deck(cur_acc, deck);
 
                     // set new deck as active
                     let cur_index = get_decks_mut(ctx.accounts, &mut app.decks_cache)
@@ -618,6 +1109,14 @@ fn render_nav_body(
                             DecksAction::Removing(*index),
                         )));
                     }
+                    EditDeckResponse::Export => {
+                        ui.ctx().copy_text(storage::export_deck(cur_deck));
+                        notedeck::toast::info(
+                            ui.ctx(),
+                            "Deck copied. Paste it under New deck on another machine to import it.",
+                        );
+                        return None;
+                    }
                 }
                 get_active_columns_mut(ctx.accounts, &mut app.decks_cache)
                     .get_first_router()
@@ -641,7 +1140,10 @@ fn render_nav_body(
                             ProfileState::default()
                         }
                     });
-                if EditProfileView::new(state, ctx.img_cache).ui(ui) {
+                if EditProfileView::new(state, ctx.img_cache)
+                    .upload_key(kp.secret_key.secret_bytes())
+                    .ui(ui)
+                {
                     if let Some(taken_state) =
                         app.view_state.pubkey_to_profile_state.remove(kp.pubkey)
                     {
@@ -734,9 +1236,73 @@ fn render_nav_body(
                 )))
             })
         }
+        Route::Inspect(note_id) => {
+            InspectView::new(ctx.ndb, note_id).ui(ui);
+            None
+        }
     }
 }
 
+/// Posting as a locked account needs the app lock passphrase first.
+/// Returns true if we're showing the prompt instead of the composer.
+fn render_unlock_prompt(
+    ui: &mut egui::Ui,
+    accounts: &mut Accounts,
+    state: &mut AppLockState,
+) -> bool {
+    if !accounts
+        .get_selected_account()
+        .is_some_and(|acc| acc.is_locked())
+    {
+        return false;
+    }
+
+    if let Some(UnlockResponse::Unlock(passphrase)) =
+        UnlockView::new(state, accounts.app_lock()).ui(ui).inner
+    {
+        accounts.unlock(passphrase);
+    }
+
+    true
+}
+
+/// Keep where a column's timeline is scrolled to on the column, so it's
+/// saved with the deck
+fn remember_scroll(app: &mut Damus, accounts: &Accounts, col: usize, kind: &TimelineKind) {
+    let Some(timeline) = app.timeline_cache.timelines.get(kind) else {
+        return;
+    };
+    let view = timeline.current_view();
+    if view.restore_scroll.get().is_some() {
+        // still on its way back to the saved position
+        return;
+    }
+
+    let anchor = view.scroll_anchor();
+    let column = get_active_columns_mut(accounts, &mut app.decks_cache).column_mut(col);
+    if column.scroll_anchor != anchor {
+        column.scroll_anchor = anchor;
+        app.scroll_changed_at = Some(std::time::Instant::now());
+    }
+}
+
+/// Unread notes in the timeline a column starts from, for its header
+fn column_unread(app: &Damus, ctx: &AppContext<'_>, col: usize) -> usize {
+    let Some(Route::Timeline(kind)) = app
+        .columns(ctx.accounts)
+        .column(col)
+        .router()
+        .routes()
+        .first()
+    else {
+        return 0;
+    };
+    app.timeline_cache
+        .timelines
+        .get(kind)
+        .map_or(0, |timeline| timeline.unread())
+}
+
 #[must_use = "RenderNavResponse must be handled by calling .process_render_nav_response(..)"]
 pub fn render_nav(
     col: usize,
@@ -745,6 +1311,11 @@ pub fn render_nav(
     ctx: &mut AppContext<'_>,
     ui: &mut egui::Ui,
 ) -> RenderNavResponse {
+    let column_id = get_decks(ctx.accounts, &app.decks_cache)
+        .active()
+        .column_id(col);
+    let unread = column_unread(app, ctx, col);
+
     if let Some(sheet_route) = app
         .columns(ctx.accounts)
         .column(col)
@@ -767,7 +1338,7 @@ pub fn render_nav(
             .cloned();
         if let Some(bg_route) = bg_route {
             let resp = PopupSheet::new(&bg_route, &sheet_route)
-                .id_source(egui::Id::new(("nav", col)))
+                .id_source(column_id.with("nav"))
                 .navigating(navigating)
                 .returning(returning)
                 .with_split_percent_from_top(Percent::new(35).expect("35 <= 100"))
@@ -775,10 +1346,12 @@ pub fn render_nav(
                     NavUiType::Title => NavTitle::new(
                         ctx.ndb,
                         ctx.img_cache,
+                        ctx.accounts,
                         get_active_columns_mut(ctx.accounts, &mut app.decks_cache),
                         &[route.clone()],
                         col,
                     )
+                    .unread(unread)
                     .show(ui),
                     NavUiType::Body => render_nav_body(ui, app, ctx, route, 1, col, inner_rect),
                 });
@@ -806,15 +1379,17 @@ pub fn render_nav(
             .router_mut()
             .returning,
     )
-    .id_source(egui::Id::new(("nav", col)))
+    .id_source(column_id.with("nav"))
     .show_mut(ui, |ui, render_type, nav| match render_type {
         NavUiType::Title => NavTitle::new(
             ctx.ndb,
             ctx.img_cache,
+            ctx.accounts,
             get_active_columns_mut(ctx.accounts, &mut app.decks_cache),
             nav.routes(),
             col,
         )
+        .unread(unread)
         .show(ui),
         NavUiType::Body => {
             if let Some(top) = nav.routes().last() {
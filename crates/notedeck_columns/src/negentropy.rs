@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet};
+
+use enostr::{ClientMessage, Filter, Negentropy, NegentropyItem, PoolRelay, RelayPool};
+use nostrdb::{Ndb, Transaction};
+use tracing::{debug, error, info, warn};
+
+use crate::subscriptions::{self, SubKind, Subscriptions};
+use crate::timeline::TimelineKind;
+
+/// How many ids we ask for in a single REQ when fetching the notes a
+/// reconciliation says we're missing
+const FETCH_CHUNK: usize = 250;
+
+/// A NIP-77 reconciliation in flight with a single relay, for a single
+/// timeline filter
+struct NegentropySession {
+    relay: String,
+    kind: TimelineKind,
+
+    /// The filter we would have sent if the relay didn't speak
+    /// negentropy. We use this if it turns out that it doesn't.
+    fallback: Filter,
+
+    neg: Negentropy,
+}
+
+/// Tracks negentropy sessions and which relays we know don't support
+/// them. Relays are assumed to support negentropy until they tell us
+/// otherwise.
+#[derive(Default)]
+pub struct NegentropySyncs {
+    sessions: HashMap<String, NegentropySession>,
+    unsupported: HashSet<String>,
+}
+
+impl NegentropySyncs {
+    pub fn is_supported(&self, pool: &RelayPool, relay_url: &str) -> bool {
+        !self.unsupported.contains(relay_url)
+            && !pool.is_temporary(relay_url)
+            && pool
+                .relays
+                .iter()
+                .any(|relay| relay.url() == relay_url && matches!(relay, PoolRelay::Websocket(_)))
+    }
+
+    /// Start reconciling `filter` against the notes we already have for
+    /// it. Returns false if we couldn't, in which case the caller should
+    /// backfill the usual way.
+    pub fn open(
+        &mut self,
+        ndb: &Ndb,
+        pool: &mut RelayPool,
+        relay_url: &str,
+        kind: &TimelineKind,
+        filter: &Filter,
+    ) -> bool {
+        if !self.is_supported(pool, relay_url) {
+            return false;
+        }
+
+        let items = match local_items(ndb, filter) {
+            Some(items) => items,
+            None => return false,
+        };
+
+        let neg = Negentropy::new(items);
+        let sub_id = subscriptions::new_sub_id();
+        let msg = ClientMessage::neg_open(sub_id.clone(), filter.clone(), neg.initiate());
+
+        if !pool.send_to(&msg, relay_url) {
+            debug!("negentropy: NEG-OPEN not sent to {relay_url}");
+            return false;
+        }
+
+        debug!("negentropy: opened {sub_id} for {kind:?} on {relay_url}");
+
+        self.sessions.insert(
+            sub_id,
+            NegentropySession {
+                relay: relay_url.to_owned(),
+                kind: kind.clone(),
+                fallback: filter.clone(),
+                neg,
+            },
+        );
+
+        true
+    }
+
+    fn mark_unsupported(&mut self, relay: &str) {
+        if self.unsupported.insert(relay.to_owned()) {
+            info!("negentropy: {relay} doesn't support NIP-77, using regular filters");
+        }
+    }
+
+    /// Forget the sessions on a relay we lost the connection to. The
+    /// relay has dropped them too, and they're opened again along with
+    /// the rest of the timeline filters when it reconnects.
+    pub fn relay_disconnected(&mut self, relay: &str) {
+        let dropped = self.drain_relay(relay).len();
+        if dropped > 0 {
+            debug!("negentropy: dropped {dropped} sessions on disconnected {relay}");
+        }
+    }
+
+    /// Remove every session on this relay, returning their fallback filters
+    fn drain_relay(&mut self, relay: &str) -> Vec<Filter> {
+        let subids: Vec<String> = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| session.relay == relay)
+            .map(|(subid, _)| subid.clone())
+            .collect();
+
+        subids
+            .into_iter()
+            .filter_map(|subid| self.sessions.remove(&subid))
+            .map(|session| session.fallback)
+            .collect()
+    }
+}
+
+/// Handle a NEG-MSG from a relay. We fetch any ids we learned we're
+/// missing and continue the reconciliation until it's done.
+pub fn handle_neg_msg(
+    subs: &mut Subscriptions,
+    pool: &mut RelayPool,
+    relay: &str,
+    subid: &str,
+    msg: &str,
+) {
+    let session = if let Some(session) = subs.negentropy.sessions.get_mut(subid) {
+        session
+    } else {
+        warn!("negentropy: got NEG-MSG for unknown subid {subid} from {relay}");
+        return;
+    };
+
+    let next = session.neg.reconcile(msg);
+    let need = session.neg.take_need();
+
+    if !need.is_empty() {
+        debug!(
+            "negentropy: fetching {} missing notes for {:?} from {}",
+            need.len(),
+            session.kind,
+            relay
+        );
+        fetch_ids(&mut subs.subs, pool, relay, &need);
+    }
+
+    match next {
+        Ok(Some(next)) => {
+            pool.send_to(&ClientMessage::neg_msg(subid.to_owned(), next), relay);
+        }
+
+        Ok(None) => {
+            pool.send_to(&ClientMessage::neg_close(subid.to_owned()), relay);
+            subs.negentropy.sessions.remove(subid);
+        }
+
+        Err(err) => {
+            error!("negentropy: reconcile failed on {relay}: {err}");
+            pool.send_to(&ClientMessage::neg_close(subid.to_owned()), relay);
+            if let Some(session) = subs.negentropy.sessions.remove(subid) {
+                fallback_subscribe(&mut subs.subs, pool, relay, vec![session.fallback]);
+            }
+        }
+    }
+}
+
+/// Handle a NEG-ERR. The relay either doesn't support this query or
+/// closed it on us, either way we fall back to a regular backfill.
+pub fn handle_neg_err(
+    subs: &mut Subscriptions,
+    pool: &mut RelayPool,
+    relay: &str,
+    subid: &str,
+    reason: &str,
+) {
+    warn!("negentropy: NEG-ERR from {relay}: {reason}");
+
+    let Some(session) = subs.negentropy.sessions.remove(subid) else {
+        return;
+    };
+
+    // "blocked" and "closed" are per-query. Anything else and we assume
+    // the relay isn't going to be useful for reconciliation.
+    if !(reason.starts_with("blocked:") || reason.starts_with("closed:")) {
+        subs.negentropy.mark_unsupported(relay);
+    }
+
+    fallback_subscribe(&mut subs.subs, pool, relay, vec![session.fallback]);
+}
+
+/// Relays that don't know about NIP-77 typically reply to NEG-OPEN with a
+/// notice instead of a NEG-ERR. If we see one that names NEG-OPEN while
+/// sessions are pending on that relay, treat the relay as unsupported.
+/// Other notices say nothing about negentropy and are left alone.
+pub fn handle_notice(subs: &mut Subscriptions, pool: &mut RelayPool, relay: &str, notice: &str) {
+    if !is_neg_open_notice(notice) {
+        return;
+    }
+
+    let fallbacks = subs.negentropy.drain_relay(relay);
+    if fallbacks.is_empty() {
+        return;
+    }
+
+    subs.negentropy.mark_unsupported(relay);
+    fallback_subscribe(&mut subs.subs, pool, relay, fallbacks);
+}
+
+fn is_neg_open_notice(notice: &str) -> bool {
+    notice.to_lowercase().contains("neg-open")
+}
+
+fn fetch_ids(
+    subs: &mut HashMap<String, SubKind>,
+    pool: &mut RelayPool,
+    relay: &str,
+    ids: &[[u8; 32]],
+) {
+    for chunk in ids.chunks(FETCH_CHUNK) {
+        let sub_id = subscriptions::new_sub_id();
+        subs.insert(sub_id.clone(), SubKind::OneShot);
+        let filter = Filter::new()
+            .ids(chunk.iter())
+            .limit(chunk.len() as u64)
+            .build();
+        pool.send_to(&ClientMessage::req(sub_id, vec![filter]), relay);
+    }
+}
+
+fn fallback_subscribe(
+    subs: &mut HashMap<String, SubKind>,
+    pool: &mut RelayPool,
+    relay: &str,
+    filters: Vec<Filter>,
+) {
+    let sub_id = subscriptions::new_sub_id();
+    subs.insert(sub_id.clone(), SubKind::OneShot);
+    pool.send_to(&ClientMessage::req(sub_id, filters), relay);
+}
+
+/// The (created_at, id) pairs we have locally for a filter
+fn local_items(ndb: &Ndb, filter: &Filter) -> Option<Vec<NegentropyItem>> {
+    let txn = Transaction::new(ndb).ok()?;
+    let limit = filter.limit().unwrap_or(notedeck::filter::default_limit()) as i32;
+
+    let results = match ndb.query(&txn, &[filter.clone()], limit) {
+        Ok(results) => results,
+        Err(err) => {
+            error!("negentropy: local query failed: {err}");
+            return None;
+        }
+    };
+
+    Some(
+        results
+            .iter()
+            .map(|r| NegentropyItem::new(r.note.created_at(), *r.note.id()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_neg_open_notices_disable_negentropy() {
+        assert!(is_neg_open_notice("ERROR: unknown message type NEG-OPEN"));
+        assert!(is_neg_open_notice("bad msg: neg-open not supported"));
+
+        assert!(!is_neg_open_notice("unknown subscription"));
+        assert!(!is_neg_open_notice("rate-limited: slow down"));
+        assert!(!is_neg_open_notice("ERROR: unknown cmd"));
+    }
+}
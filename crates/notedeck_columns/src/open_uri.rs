@@ -0,0 +1,332 @@
+//! Opening `nostr:` uris, from the command line or handed over by a
+//! later launch. Profiles open right away. Notes we don't have are asked
+//! for first and opened when they arrive. Relays hinted in the uri are
+//! asked too, connecting to them just for that if we don't use them.
+//!
+//! `nostr+notedeck:` links come in the same way. The ones that add
+//! something to the user's setup wait for them to confirm it.
+
+use egui::{RichText, TextBuffer};
+use enostr::{ClientMessage, NoteId, Pubkey};
+use nostrdb::{Filter, Ndb, Transaction};
+use notedeck::deep_link::DeepLink;
+use notedeck::nip19::NostrUri;
+use notedeck::{AppContext, NotedeckTextStyle};
+use tracing::{error, info, warn};
+
+use crate::app::get_decks_mut;
+use crate::relay_pool_manager::create_wakeup;
+use crate::storage;
+use crate::subscriptions::{self, SubKind};
+use crate::timeline::{ThreadSelection, TimelineKind};
+use crate::{route::Route, Damus};
+
+/// Give up on a note relays don't come up with by then
+const FIND_TIMEOUT_SECS: f64 = 15.0;
+
+/// Most relay hints we follow from one uri
+const MAX_HINTS: usize = 3;
+
+/// Notes to fetch from the relays hinted in an nprofile
+const PROFILE_HINT_NOTES: u64 = 50;
+
+/// What decks added by a link get for an icon if it doesn't say
+const LINKED_DECK_ICON: char = '🔗';
+
+/// The uri we're waiting on relays for. A newer one replaces it.
+pub struct PendingUri {
+    uri: NostrUri,
+    since: f64,
+    requested: bool,
+}
+
+/// Open the uris that came in, or the one still waiting on relays, in
+/// the selected column
+pub fn process_opened_uris(app: &mut Damus, ctx: &mut AppContext<'_>, egui_ctx: &egui::Context) {
+    let now = egui_ctx.input(|i| i.time);
+    let opened = ctx.opened_uris.drain(..).last();
+    if let Some(uri) = opened.and_then(|uri| follow_link(app, ctx, uri)) {
+        info!("opening {uri:?}");
+        follow_hints(ctx, egui_ctx, &uri);
+        app.pending_uri = Some(PendingUri {
+            uri,
+            since: now,
+            requested: false,
+        });
+    }
+
+    let Some(pending) = &mut app.pending_uri else {
+        return;
+    };
+    let Ok(txn) = Transaction::new(ctx.ndb) else {
+        return;
+    };
+
+    let kind = match &pending.uri {
+        NostrUri::Profile { pubkey, .. } => Some(TimelineKind::Profile(Pubkey::new(*pubkey))),
+        uri => find_note(ctx.ndb, &txn, uri).and_then(|id| {
+            ThreadSelection::from_note_id(ctx.ndb, ctx.note_cache, &txn, id)
+                .ok()
+                .map(TimelineKind::Thread)
+        }),
+    };
+
+    let Some(kind) = kind else {
+        if !pending.requested {
+            pending.requested = true;
+            if let Some(filter) = note_filter(&pending.uri) {
+                let sub_id = subscriptions::new_sub_id();
+                app.subscriptions
+                    .subs
+                    .insert(sub_id.clone(), SubKind::OneShot);
+                ctx.pool.send(&ClientMessage::req(sub_id, vec![filter]));
+            }
+        } else if now - pending.since > FIND_TIMEOUT_SECS {
+            warn!("couldn't find {:?}", pending.uri);
+            notedeck::toast::error(egui_ctx, "Couldn't find that note on your relays");
+            app.pending_uri = None;
+        } else {
+            egui_ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        }
+        return;
+    };
+    app.pending_uri = None;
+
+    let open_result = app
+        .timeline_cache
+        .open(ctx.ndb, ctx.note_cache, &txn, ctx.pool, &kind);
+    if let Some(result) = open_result {
+        result.process(
+            ctx.ndb,
+            ctx.note_cache,
+            &txn,
+            &mut app.timeline_cache,
+            ctx.unknown_ids,
+            &ctx.accounts.mutefun(),
+        );
+    }
+
+    let columns = app.columns_mut(ctx.accounts);
+    if columns.columns().is_empty() {
+        return;
+    }
+    columns
+        .selected()
+        .router_mut()
+        .route_to(Route::Timeline(kind));
+}
+
+/// Do what a `nostr+notedeck:` link says, or hold it for the user to
+/// confirm. Returns the uris that open like any other.
+fn follow_link(app: &mut Damus, ctx: &mut AppContext<'_>, uri: NostrUri) -> Option<NostrUri> {
+    let NostrUri::Notedeck(link) = uri else {
+        return Some(uri);
+    };
+    info!("following {link:?}");
+
+    if link.needs_confirmation() {
+        app.pending_link = Some(link);
+        return None;
+    }
+
+    match link {
+        DeepLink::Profile(pubkey) => Some(NostrUri::Profile {
+            pubkey,
+            relays: vec![],
+        }),
+
+        // added to whatever's already in the composer, nothing gets lost
+        DeepLink::Compose { text } => {
+            let buffer = &mut app.drafts.compose_mut().buffer;
+            let end = buffer.as_str().chars().count();
+            let text = if end == 0 { text } else { format!("\n{text}") };
+            buffer.insert_text(&text, end);
+
+            let columns = app.columns_mut(ctx.accounts);
+            if !columns.columns().is_empty() {
+                columns.selected().router_mut().route_to(Route::ComposeNote);
+            }
+            None
+        }
+
+        DeepLink::AddRelay(_) | DeepLink::Deck { .. } => None,
+    }
+}
+
+/// Ask about a link held for confirmation, and follow it if the user
+/// agrees
+pub fn confirm_pending_link(app: &mut Damus, ctx: &mut AppContext<'_>, egui_ctx: &egui::Context) {
+    let Some(link) = &app.pending_link else {
+        return;
+    };
+    let (title, detail) = match link {
+        DeepLink::AddRelay(relay) => (
+            "Add this relay?".to_owned(),
+            format!("A link asked to add {relay} to your relays."),
+        ),
+        DeepLink::Deck { name, columns, .. } => (
+            format!("Add the deck \"{name}\"?"),
+            format!("A link asked to add a deck with {} columns.", columns.len()),
+        ),
+        DeepLink::Profile(_) | DeepLink::Compose { .. } => {
+            app.pending_link = None;
+            return;
+        }
+    };
+
+    let Some(confirmed) = confirmation_ui(egui_ctx, &title, &detail) else {
+        return;
+    };
+    let Some(link) = app.pending_link.take() else {
+        return;
+    };
+    if !confirmed {
+        return;
+    }
+
+    match link {
+        DeepLink::AddRelay(relay) => {
+            ctx.accounts.add_advertised_relay(&relay, ctx.pool);
+            notedeck::toast::info(egui_ctx, format!("Added {relay}"));
+        }
+
+        DeepLink::Deck {
+            name,
+            icon,
+            columns,
+        } => {
+            let deck_user = ctx
+                .accounts
+                .selected_account_pubkey()
+                .copied()
+                .unwrap_or(*app.decks_cache.get_fallback_pubkey());
+            let columns = columns.into_iter().map(|route| vec![route]).collect();
+            let deck = match storage::deck_from_columns(
+                icon.unwrap_or(LINKED_DECK_ICON),
+                name,
+                columns,
+                ctx,
+                &mut app.timeline_cache,
+                &deck_user,
+            ) {
+                Ok(deck) => deck,
+                Err(err) => {
+                    error!("couldn't add linked deck: {err}");
+                    notedeck::toast::error(egui_ctx, err);
+                    return;
+                }
+            };
+
+            let decks = get_decks_mut(ctx.accounts, &mut app.decks_cache);
+            decks.add_deck(deck);
+            decks.set_active(decks.decks().len() - 1);
+            if !app.tmp_columns {
+                storage::save_decks_cache(ctx.path, &app.decks_cache);
+            }
+        }
+
+        DeepLink::Profile(_) | DeepLink::Compose { .. } => {}
+    }
+}
+
+/// Some(true) once the user agrees, Some(false) if they don't
+fn confirmation_ui(ctx: &egui::Context, title: &str, detail: &str) -> Option<bool> {
+    let mut confirmed = None;
+
+    let modal = egui::Modal::new(egui::Id::new("deep_link_confirmation")).show(ctx, |ui| {
+        ui.set_max_width(360.0);
+        ui.label(RichText::new(title).text_style(NotedeckTextStyle::Heading3.text_style()));
+        ui.add_space(4.0);
+        ui.label(detail);
+        ui.add_space(12.0);
+
+        ui.horizontal(|ui| {
+            if ui.button("Add").clicked() {
+                confirmed = Some(true);
+            }
+            if ui.button("Cancel").clicked() {
+                confirmed = Some(false);
+            }
+        });
+    });
+
+    if confirmed.is_none() && modal.should_close() {
+        confirmed = Some(false);
+    }
+
+    confirmed
+}
+
+/// Ask the relays `uri` hints at for what it points to. Whatever they
+/// send lands in ndb like anything else, where the pending uri finds it.
+fn follow_hints(ctx: &mut AppContext<'_>, egui_ctx: &egui::Context, uri: &NostrUri) {
+    let (relays, filters) = match uri {
+        NostrUri::Note { relays, .. } | NostrUri::Address { relays, .. } => {
+            let Some(filter) = note_filter(uri) else {
+                return;
+            };
+            (relays, vec![filter])
+        }
+        NostrUri::Notedeck(_) => return,
+        NostrUri::Profile { pubkey, relays } => (
+            relays,
+            vec![
+                Filter::new().authors([pubkey]).kinds([0]).limit(1).build(),
+                Filter::new()
+                    .authors([pubkey])
+                    .kinds([1])
+                    .limit(PROFILE_HINT_NOTES)
+                    .build(),
+            ],
+        ),
+    };
+
+    for relay in relays.iter().take(MAX_HINTS) {
+        let sub_id = subscriptions::new_sub_id();
+        if !ctx.pool.subscribe_temporary(
+            relay.clone(),
+            sub_id,
+            filters.clone(),
+            create_wakeup(egui_ctx),
+        ) {
+            warn!("not following relay hint {relay}, too many temporary relays");
+        }
+    }
+}
+
+/// The id of the note `uri` points at, if we have it
+fn find_note(ndb: &Ndb, txn: &Transaction, uri: &NostrUri) -> Option<NoteId> {
+    match uri {
+        NostrUri::Note { id, .. } => ndb
+            .get_note_by_id(txn, id)
+            .ok()
+            .map(|note| NoteId::new(*note.id())),
+        NostrUri::Address { .. } => {
+            let filter = note_filter(uri)?;
+            let results = ndb.query(txn, &[filter], 1).ok()?;
+            let note = &results.first()?.note;
+            Some(NoteId::new(*note.id()))
+        }
+        NostrUri::Profile { .. } | NostrUri::Notedeck(_) => None,
+    }
+}
+
+fn note_filter(uri: &NostrUri) -> Option<Filter> {
+    match uri {
+        NostrUri::Note { id, .. } => Some(Filter::new().ids([id]).limit(1).build()),
+        NostrUri::Address {
+            kind,
+            pubkey,
+            identifier,
+            ..
+        } => Some(
+            Filter::new()
+                .kinds([*kind as u64])
+                .authors([pubkey])
+                .tags([identifier.as_str()], 'd')
+                .limit(1)
+                .build(),
+        ),
+        NostrUri::Profile { .. } | NostrUri::Notedeck(_) => None,
+    }
+}
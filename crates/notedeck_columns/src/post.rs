@@ -1,6 +1,7 @@
 use egui::{text::LayoutJob, TextBuffer, TextFormat};
-use enostr::{FullKeypair, Pubkey};
-use nostrdb::{Note, NoteBuilder, NoteReply};
+use enostr::{Keypair, Pubkey};
+use nostrdb::{Ndb, Note, NoteBuilder, NoteReply, Transaction};
+use notedeck::poll::POLL_KIND;
 use std::{
     any::TypeId,
     collections::{BTreeMap, HashMap, HashSet},
@@ -9,13 +10,40 @@ use std::{
 };
 use tracing::error;
 
+use crate::emoji::{is_shortcode, CustomEmoji};
 use crate::media_upload::Nip94Event;
+use crate::reply_tags::ReplyTagScheme;
 
 pub struct NewPost {
     pub content: String,
-    pub account: FullKeypair,
+    pub account: Keypair,
     pub media: Vec<Nip94Event>,
     pub mentions: Vec<Pubkey>,
+
+    /// NIP-36 content warning, with an optional reason
+    pub content_warning: Option<String>,
+
+    /// NIP-30 custom emoji picked while writing. Only the ones still in
+    /// the content get tagged.
+    pub emojis: Vec<CustomEmoji>,
+
+    /// Unix time to ask relays to drop it at, NIP-40
+    pub expiration: Option<u64>,
+
+    /// Post it as a NIP-88 poll instead of a text note
+    pub poll: Option<NewPoll>,
+
+    /// For replies, people the note being replied to tags that this one
+    /// shouldn't
+    pub untagged: Vec<Pubkey>,
+}
+
+pub struct NewPoll {
+    pub options: Vec<String>,
+    pub multiple_choice: bool,
+
+    /// Unix time voting closes at
+    pub ends_at: Option<u64>,
 }
 
 fn add_client_tag(builder: NoteBuilder<'_>) -> NoteBuilder<'_> {
@@ -28,7 +56,7 @@ fn add_client_tag(builder: NoteBuilder<'_>) -> NoteBuilder<'_> {
 impl NewPost {
     pub fn new(
         content: String,
-        account: enostr::FullKeypair,
+        account: Keypair,
         media: Vec<Nip94Event>,
         mentions: Vec<Pubkey>,
     ) -> Self {
@@ -37,14 +65,47 @@ impl NewPost {
             account,
             media,
             mentions,
+            content_warning: None,
+            emojis: Vec::new(),
+            expiration: None,
+            poll: None,
+            untagged: Vec::new(),
         }
     }
 
+    pub fn content_warning(mut self, reason: Option<String>) -> Self {
+        self.content_warning = reason;
+        self
+    }
+
+    pub fn emojis(mut self, emojis: Vec<CustomEmoji>) -> Self {
+        self.emojis = emojis;
+        self
+    }
+
+    pub fn expiration(mut self, at: Option<u64>) -> Self {
+        self.expiration = at;
+        self
+    }
+
+    pub fn poll(mut self, poll: Option<NewPoll>) -> Self {
+        self.poll = poll;
+        self
+    }
+
+    pub fn untagged(mut self, untagged: Vec<Pubkey>) -> Self {
+        self.untagged = untagged;
+        self
+    }
+
     pub fn to_note(&self, seckey: &[u8; 32]) -> Note {
         let mut content = self.content.clone();
         append_urls(&mut content, &self.media);
 
-        let mut builder = add_client_tag(NoteBuilder::new()).kind(1).content(&content);
+        let kind = if self.poll.is_some() { POLL_KIND } else { 1 };
+        let mut builder = add_client_tag(NoteBuilder::new())
+            .kind(kind)
+            .content(&content);
 
         for hashtag in Self::extract_hashtags(&self.content) {
             builder = builder.start_tag().tag_str("t").tag_str(&hashtag);
@@ -58,73 +119,43 @@ impl NewPost {
             builder = add_mention_tags(builder, &self.mentions);
         }
 
+        builder = add_content_warning_tag(builder, self.content_warning.as_deref());
+        builder = add_emoji_tags(builder, &self.content, &self.emojis);
+        builder = add_expiration_tag(builder, self.expiration);
+        if let Some(poll) = &self.poll {
+            builder = add_poll_tags(builder, poll);
+        }
+
         builder.sign(seckey).build().expect("note should be ok")
     }
 
-    pub fn to_reply(&self, seckey: &[u8; 32], replying_to: &Note) -> Note {
+    /// A reply to `replying_to`, which is in `thread`. Its e tags follow
+    /// `scheme`.
+    pub fn to_reply(
+        &self,
+        seckey: &[u8; 32],
+        replying_to: &Note,
+        thread: &ReplyThread,
+        scheme: ReplyTagScheme,
+    ) -> Note {
         let mut content = self.content.clone();
         append_urls(&mut content, &self.media);
 
-        let builder = add_client_tag(NoteBuilder::new()).kind(1).content(&content);
-
-        let nip10 = NoteReply::new(replying_to.tags());
-
-        let mut builder = if let Some(root) = nip10.root() {
-            builder
-                .start_tag()
-                .tag_str("e")
-                .tag_str(&hex::encode(root.id))
-                .tag_str("")
-                .tag_str("root")
-                .start_tag()
-                .tag_str("e")
-                .tag_str(&hex::encode(replying_to.id()))
-                .tag_str("")
-                .tag_str("reply")
-                .sign(seckey)
-        } else {
-            // we're replying to a post that isn't in a thread,
-            // just add a single reply-to-root tag
-            builder
-                .start_tag()
-                .tag_str("e")
-                .tag_str(&hex::encode(replying_to.id()))
-                .tag_str("")
-                .tag_str("root")
-                .sign(seckey)
-        };
-
-        let mut seen_p: HashSet<&[u8; 32]> = HashSet::new();
-
-        builder = builder
-            .start_tag()
-            .tag_str("p")
-            .tag_str(&hex::encode(replying_to.pubkey()));
-
-        seen_p.insert(replying_to.pubkey());
-
-        for tag in replying_to.tags() {
-            if tag.count() < 2 {
-                continue;
-            }
+        let mut builder = add_client_tag(NoteBuilder::new()).kind(1).content(&content);
 
-            if tag.get_unchecked(0).variant().str() != Some("p") {
-                continue;
+        for tag in thread.e_tags(scheme) {
+            builder = builder.start_tag();
+            for entry in &tag {
+                builder = builder.tag_str(entry);
             }
+        }
 
-            let id = if let Some(id) = tag.get_unchecked(1).variant().id() {
-                id
-            } else {
-                continue;
-            };
-
-            if seen_p.contains(id) {
+        // the author is always tagged, the rest can be left out
+        for (ind, pk) in reply_audience(replying_to).iter().enumerate() {
+            if ind > 0 && self.untagged.contains(pk) {
                 continue;
             }
-
-            seen_p.insert(id);
-
-            builder = builder.start_tag().tag_str("p").tag_str(&hex::encode(id));
+            builder = builder.start_tag().tag_str("p").tag_str(&pk.hex());
         }
 
         if !self.media.is_empty() {
@@ -135,22 +166,36 @@ impl NewPost {
             builder = add_mention_tags(builder, &self.mentions);
         }
 
+        builder = add_content_warning_tag(builder, self.content_warning.as_deref());
+        builder = add_emoji_tags(builder, &self.content, &self.emojis);
+        builder = add_expiration_tag(builder, self.expiration);
+
         builder
             .sign(seckey)
             .build()
             .expect("expected build to work")
     }
 
-    pub fn to_quote(&self, seckey: &[u8; 32], quoting: &Note) -> Note {
-        let mut new_content = format!(
-            "{}\nnostr:{}",
-            self.content,
-            enostr::NoteId::new(*quoting.id()).to_bech().unwrap()
-        );
-
+    /// A NIP-18 quote of `quoting`, which was seen on `relays`. The quote
+    /// goes at the end, after any media, as an nevent so clients know
+    /// where to find it.
+    pub fn to_quote(&self, seckey: &[u8; 32], quoting: &Note, relays: &[String]) -> Note {
+        let mut new_content = self.content.clone();
         append_urls(&mut new_content, &self.media);
 
-        let mut builder = NoteBuilder::new().kind(1).content(&new_content);
+        let mention = notedeck::nip19::nevent(
+            quoting.id(),
+            Some(quoting.pubkey()),
+            Some(quoting.kind()),
+            relays,
+        )
+        .or_else(|| enostr::NoteId::new(*quoting.id()).to_bech())
+        .expect("note ids encode");
+        new_content.push_str(&format!("\nnostr:{mention}"));
+
+        let mut builder = add_client_tag(NoteBuilder::new())
+            .kind(1)
+            .content(&new_content);
 
         for hashtag in Self::extract_hashtags(&self.content) {
             builder = builder.start_tag().tag_str("t").tag_str(&hashtag);
@@ -164,10 +209,16 @@ impl NewPost {
             builder = add_mention_tags(builder, &self.mentions);
         }
 
+        builder = add_content_warning_tag(builder, self.content_warning.as_deref());
+        builder = add_emoji_tags(builder, &self.content, &self.emojis);
+        builder = add_expiration_tag(builder, self.expiration);
+
         builder
             .start_tag()
             .tag_str("q")
             .tag_str(&hex::encode(quoting.id()))
+            .tag_str(relays.first().map_or("", String::as_str))
+            .tag_str(&hex::encode(quoting.pubkey()))
             .start_tag()
             .tag_str("p")
             .tag_str(&hex::encode(quoting.pubkey()))
@@ -199,6 +250,138 @@ fn append_urls(content: &mut String, media: &Vec<Nip94Event>) {
     }
 }
 
+/// A note a reply points at, and where to find it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadRef {
+    pub id: [u8; 32],
+    pub relay: Option<String>,
+    pub author: Option<Pubkey>,
+}
+
+/// The note a reply is to, and the root of the thread it's in. There's no
+/// root when the note being replied to starts the thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplyThread {
+    pub root: Option<ThreadRef>,
+    pub parent: ThreadRef,
+}
+
+impl ReplyThread {
+    /// The thread `replying_to` is in. Whatever its tags don't say about
+    /// the root is filled in from the root itself, if we have it.
+    pub fn new(ndb: &Ndb, txn: &Transaction, replying_to: &Note) -> Self {
+        let relays = notedeck::nip19::relay_hints(replying_to, txn);
+        let mut thread = Self::from_note(replying_to, &relays);
+
+        if let Some(root) = &mut thread.root {
+            if root.relay.is_none() || root.author.is_none() {
+                if let Ok(note) = ndb.get_note_by_id(txn, &root.id) {
+                    root.relay = root
+                        .relay
+                        .take()
+                        .or_else(|| notedeck::nip19::relay_hints(&note, txn).into_iter().next());
+                    root.author = root.author.or(Some(Pubkey::new(*note.pubkey())));
+                }
+            }
+        }
+
+        thread
+    }
+
+    /// The thread `replying_to` is in, going by its tags. It was seen on
+    /// `relays`.
+    pub fn from_note(replying_to: &Note, relays: &[String]) -> Self {
+        let parent = ThreadRef {
+            id: *replying_to.id(),
+            relay: relays.first().cloned(),
+            author: Some(Pubkey::new(*replying_to.pubkey())),
+        };
+
+        // its root is our root, even when it's replying deep in the thread
+        let root = NoteReply::new(replying_to.tags()).root().map(|root| {
+            let (relay, author) = e_tag_hints(replying_to, root.id);
+            ThreadRef {
+                id: *root.id,
+                relay,
+                author,
+            }
+        });
+
+        ReplyThread { root, parent }
+    }
+
+    /// The e tags for a reply in this thread
+    pub fn e_tags(&self, scheme: ReplyTagScheme) -> Vec<Vec<String>> {
+        let tag = |note: &ThreadRef, marker: &str| {
+            let mut tag = vec![
+                "e".to_owned(),
+                hex::encode(note.id),
+                note.relay.clone().unwrap_or_default(),
+            ];
+            if scheme == ReplyTagScheme::Markers {
+                tag.push(marker.to_owned());
+                if let Some(author) = &note.author {
+                    tag.push(author.hex());
+                }
+            }
+            tag
+        };
+
+        match &self.root {
+            Some(root) => vec![tag(root, "root"), tag(&self.parent, "reply")],
+
+            // replying to the note that starts the thread
+            None => vec![tag(&self.parent, "root")],
+        }
+    }
+}
+
+/// The relay hint and author `note` gave in its e tag for `id`
+fn e_tag_hints(note: &Note, id: &[u8; 32]) -> (Option<String>, Option<Pubkey>) {
+    for tag in note.tags() {
+        if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some("e") {
+            continue;
+        }
+        if tag.get_unchecked(1).variant().id() != Some(id) {
+            continue;
+        }
+
+        let relay = tag
+            .get(2)
+            .and_then(|relay| relay.variant().str())
+            .filter(|relay| relay.starts_with("wss://") || relay.starts_with("ws://"))
+            .map(str::to_owned);
+        let author = tag.get(4).and_then(|author| match author.variant().id() {
+            Some(id) => Some(Pubkey::new(*id)),
+            None => Pubkey::from_hex(author.variant().str()?).ok(),
+        });
+        return (relay, author);
+    }
+
+    (None, None)
+}
+
+/// Everyone a reply to `replying_to` notifies: its author first, then the
+/// people it tags
+pub fn reply_audience(replying_to: &Note) -> Vec<Pubkey> {
+    let mut audience = vec![Pubkey::new(*replying_to.pubkey())];
+
+    for tag in replying_to.tags() {
+        if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some("p") {
+            continue;
+        }
+        let Some(id) = tag.get_unchecked(1).variant().id() else {
+            continue;
+        };
+        let pk = Pubkey::new(*id);
+        if !audience.contains(&pk) {
+            audience.push(pk);
+        }
+    }
+
+    audience
+}
+
 fn add_mention_tags<'a>(builder: NoteBuilder<'a>, mentions: &Vec<Pubkey>) -> NoteBuilder<'a> {
     let mut builder = builder;
 
@@ -209,6 +392,78 @@ fn add_mention_tags<'a>(builder: NoteBuilder<'a>, mentions: &Vec<Pubkey>) -> Not
     builder
 }
 
+fn add_content_warning_tag<'a>(builder: NoteBuilder<'a>, reason: Option<&str>) -> NoteBuilder<'a> {
+    let Some(reason) = reason else {
+        return builder;
+    };
+
+    let builder = builder.start_tag().tag_str("content-warning");
+    let reason = reason.trim();
+    if reason.is_empty() {
+        builder
+    } else {
+        builder.tag_str(reason)
+    }
+}
+
+fn add_expiration_tag(builder: NoteBuilder<'_>, at: Option<u64>) -> NoteBuilder<'_> {
+    match at {
+        Some(at) => builder
+            .start_tag()
+            .tag_str("expiration")
+            .tag_str(&at.to_string()),
+        None => builder,
+    }
+}
+
+/// Options are numbered in order, that's all their ids need to be
+fn add_poll_tags<'a>(builder: NoteBuilder<'a>, poll: &NewPoll) -> NoteBuilder<'a> {
+    let mut builder = builder;
+    for (i, option) in poll.options.iter().enumerate() {
+        builder = builder
+            .start_tag()
+            .tag_str("option")
+            .tag_str(&i.to_string())
+            .tag_str(option);
+    }
+
+    let polltype = if poll.multiple_choice {
+        "multiplechoice"
+    } else {
+        "singlechoice"
+    };
+    builder = builder.start_tag().tag_str("polltype").tag_str(polltype);
+
+    if let Some(at) = poll.ends_at {
+        builder = builder
+            .start_tag()
+            .tag_str("endsAt")
+            .tag_str(&at.to_string());
+    }
+
+    builder
+}
+
+fn add_emoji_tags<'a>(
+    builder: NoteBuilder<'a>,
+    content: &str,
+    emojis: &[CustomEmoji],
+) -> NoteBuilder<'a> {
+    let mut builder = builder;
+    let mut seen = HashSet::new();
+    for emoji in emojis {
+        if !seen.insert(&emoji.shortcode) || !content.contains(&format!(":{}:", emoji.shortcode)) {
+            continue;
+        }
+        builder = builder
+            .start_tag()
+            .tag_str("emoji")
+            .tag_str(&emoji.shortcode)
+            .tag_str(&emoji.url);
+    }
+    builder
+}
+
 fn add_imeta_tags<'a>(builder: NoteBuilder<'a>, media: &Vec<Nip94Event>) -> NoteBuilder<'a> {
     let mut builder = builder;
     for item in media {
@@ -235,6 +490,9 @@ fn add_imeta_tags<'a>(builder: NoteBuilder<'a>, media: &Vec<Nip94Event>) -> Note
         if let Some(thumb) = &item.thumb {
             builder = builder.tag_str(&format!("thumb {thumb}"));
         }
+        if let Some(alt) = item.alt.as_deref().map(str::trim).filter(|a| !a.is_empty()) {
+            builder = builder.tag_str(&format!("alt {alt}"));
+        }
     }
     builder
 }
@@ -342,13 +600,58 @@ impl PostBuffer {
         self.text_buffer.is_empty()
     }
 
+    /// The char range and pubkey of each mention that's been picked, in
+    /// order. Together with the text this is all it takes to rebuild the
+    /// buffer with [`PostBuffer::restore`].
+    pub fn finalized_mentions(&self) -> Vec<(Range<usize>, Pubkey)> {
+        self.mention_starts
+            .values()
+            .filter_map(|key| {
+                let info = self.mentions.get(key)?;
+                match info.mention_type {
+                    MentionType::Finalized(pk) => Some((info.start_index..info.end_index, pk)),
+                    MentionType::Pending => None,
+                }
+            })
+            .collect()
+    }
+
+    /// A buffer holding `text` with the given mentions already picked.
+    /// Mentions that don't fit the text are left out.
+    pub fn restore(text: String, mentions: Vec<(Range<usize>, Pubkey)>) -> Self {
+        let num_chars = text.chars().count();
+        let mut buffer = PostBuffer {
+            text_buffer: text,
+            ..Default::default()
+        };
+
+        for (range, pk) in mentions {
+            if range.is_empty() || range.end > num_chars {
+                continue;
+            }
+            let key = buffer.get_new_mentions_key();
+            buffer.mentions.insert(
+                key,
+                MentionInfo {
+                    start_index: range.start,
+                    end_index: range.end,
+                    mention_type: MentionType::Finalized(pk),
+                },
+            );
+            buffer.mention_starts.insert(range.start, key);
+            buffer.mention_ends.insert(range.end, key);
+        }
+
+        buffer
+    }
+
     pub fn output(&self) -> PostOutput {
         let mut out = self.text_buffer.clone();
         let mut mentions = Vec::new();
         for (cur_end_ind, mention_ind) in self.mention_ends.iter().rev() {
             if let Some(info) = self.mentions.get(mention_ind) {
                 if let MentionType::Finalized(pk) = info.mention_type {
-                    if let Some(bech) = pk.npub() {
+                    if let Some(bech) = notedeck::nip19::nprofile(pk.bytes(), &[]) {
                         if let Some(byte_range) =
                             char_indices_to_byte(&out, info.start_index..*cur_end_ind)
                         {
@@ -719,6 +1022,115 @@ pub struct MentionInfo {
     pub mention_type: MentionType,
 }
 
+/// Order profile search results for the mention list: people the poster
+/// follows, then people they've interacted with recently (most recent
+/// first), then everyone else in search order.
+pub fn rank_mentions(
+    results: &[&[u8; 32]],
+    follows: &HashSet<[u8; 32]>,
+    recent: &[[u8; 32]],
+    limit: usize,
+) -> Vec<[u8; 32]> {
+    let mut ranked: Vec<[u8; 32]> = results.iter().map(|pk| **pk).collect();
+    ranked.sort_by_key(|pk| {
+        if follows.contains(pk) {
+            (0, 0)
+        } else if let Some(pos) = recent.iter().position(|r| r == pk) {
+            (1, pos)
+        } else {
+            (2, 0)
+        }
+    });
+    ranked.truncate(limit);
+    ranked
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Hashtag,
+    Emoji,
+}
+
+/// A `#hashtag` or `:shortcode` being typed, up to the cursor
+#[derive(Debug, PartialEq, Eq)]
+pub struct CompletionQuery<'a> {
+    pub kind: CompletionKind,
+
+    /// Char index of the `#` or `:`
+    pub start: usize,
+
+    /// What's been typed after it
+    pub text: &'a str,
+}
+
+/// The hashtag or emoji shortcode that ends at `cursor` (a char index), if
+/// there is one
+pub fn completion_at(text: &str, cursor: usize) -> Option<CompletionQuery<'_>> {
+    let end = text
+        .char_indices()
+        .nth(cursor)
+        .map_or(text.len(), |(i, _)| i);
+    let before = &text[..end];
+    let word_start = before.rfind(char::is_whitespace).map_or(0, |i| {
+        i + before[i..].chars().next().map_or(1, char::len_utf8)
+    });
+    let word = &before[word_start..];
+
+    let mut chars = word.chars();
+    let kind = match chars.next()? {
+        '#' => CompletionKind::Hashtag,
+        ':' => CompletionKind::Emoji,
+        _ => return None,
+    };
+    let query = chars.as_str();
+
+    let valid = match kind {
+        CompletionKind::Hashtag => {
+            !query.is_empty()
+                && !query.contains(|c: char| c == '#' || (c.is_ascii_punctuation() && c != '_'))
+        }
+        CompletionKind::Emoji => is_shortcode(query),
+    };
+    if !valid {
+        return None;
+    }
+
+    Some(CompletionQuery {
+        kind,
+        start: text[..word_start].chars().count(),
+        text: query,
+    })
+}
+
+/// Candidates (given best first) matching `query`, ignoring case. Ones that
+/// start with it come before ones that only contain it.
+pub fn rank_completions<'a>(
+    query: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    limit: usize,
+) -> Vec<&'a str> {
+    let query = query.to_lowercase();
+    let mut seen = HashSet::new();
+    let mut prefixed = Vec::new();
+    let mut containing = Vec::new();
+
+    for candidate in candidates {
+        let lower = candidate.to_lowercase();
+        if !seen.insert(lower.clone()) {
+            continue;
+        }
+        if lower.starts_with(&query) {
+            prefixed.push(candidate);
+        } else if lower.contains(&query) {
+            containing.push(candidate);
+        }
+    }
+
+    prefixed.extend(containing);
+    prefixed.truncate(limit);
+    prefixed
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1247,7 +1659,7 @@ mod tests {
         assert!(tags_iter.next().is_none());
         assert_eq!(
             note.content(),
-            "nostr:npub1xtscya34g58tk0z605fvr788k263gsu6cy9x0mhnm87echrgufzsevkk5s"
+            "nostr:nprofile1qqsr9cvzwc652r4m83d86ykplrnm9dg5gwdvzzn8ameanlvut35wy3g4h5cp7"
         );
     }
 
@@ -1282,7 +1694,7 @@ mod tests {
 
         assert!(tags_iter.next().is_none());
 
-        assert_eq!(note.content(), "nostr:npub1xtscya34g58tk0z605fvr788k263gsu6cy9x0mhnm87echrgufzsevkk5s test nostr:npub1fgz3pungsr2quse0fpjuk4c5m8fuyqx2d6a3ddqc4ek92h6hf9ns0mjeck test");
+        assert_eq!(note.content(), "nostr:nprofile1qqsr9cvzwc652r4m83d86ykplrnm9dg5gwdvzzn8ameanlvut35wy3g4h5cp7 test nostr:nprofile1qqsy5pgs7f5gp4qwgvh5sewt2u2dn57zqr9xawckksv2umz4tat5jecvjrvtj test");
     }
 
     #[test]
@@ -1302,4 +1714,254 @@ mod tests {
         assert!(tags_iter.next().is_none());
         assert_eq!(note.content(), "test @jb55 test");
     }
+
+    #[test]
+    fn test_rank_mentions() {
+        let (a, b, c, d) = ([1; 32], [2; 32], [3; 32], [4; 32]);
+        let follows = HashSet::from([c]);
+        let recent = [d, b];
+
+        let ranked = rank_mentions(&[&a, &b, &c, &d], &follows, &recent, 3);
+        assert_eq!(ranked, vec![c, d, b]);
+    }
+
+    #[test]
+    fn test_restore_mentions() {
+        let mut buf = PostBuffer::default();
+        buf.insert_text("@jb55", 0);
+        buf.select_full_mention(0, JB55());
+        buf.insert_text(" test ", 5);
+        buf.insert_text("@Kernel", 11);
+        let saved = buf.finalized_mentions();
+        assert_eq!(saved, vec![(0..5, JB55())]);
+
+        let restored = PostBuffer::restore(buf.as_str().to_owned(), saved);
+        assert_eq!(restored.output().text, buf.output().text);
+        assert_eq!(restored.output().mentions, vec![JB55()]);
+
+        // a range past the end of the text is dropped
+        let restored = PostBuffer::restore("hi".to_owned(), vec![(0..10, KK())]);
+        assert!(restored.mentions.is_empty());
+    }
+
+    #[test]
+    fn test_completion_at() {
+        let text = "gm #nost";
+        assert_eq!(
+            completion_at(text, 8),
+            Some(CompletionQuery {
+                kind: CompletionKind::Hashtag,
+                start: 3,
+                text: "nost",
+            })
+        );
+        assert_eq!(completion_at(text, 5).map(|q| q.text), Some("n"));
+        assert_eq!(completion_at(text, 2), None);
+
+        let text = "🍌 :blob_c";
+        assert_eq!(
+            completion_at(text, 9),
+            Some(CompletionQuery {
+                kind: CompletionKind::Emoji,
+                start: 2,
+                text: "blob_c",
+            })
+        );
+
+        // finished shortcodes, times and lone triggers aren't completed
+        assert_eq!(completion_at(":blob:", 6), None);
+        assert_eq!(completion_at("at 12:30", 8), None);
+        assert_eq!(completion_at("#", 1), None);
+    }
+
+    #[test]
+    fn test_rank_completions() {
+        let candidates = ["bitcoin", "nostr", "Nostrdev", "asknostr", "nostr"];
+        assert_eq!(
+            rank_completions("NOST", candidates, 10),
+            vec!["nostr", "Nostrdev", "asknostr"]
+        );
+        assert_eq!(rank_completions("nost", candidates, 1), vec!["nostr"]);
+    }
+
+    #[test]
+    fn note_emoji_tags() {
+        let kp = FullKeypair::generate();
+        let emoji = |code: &str| CustomEmoji {
+            shortcode: code.to_owned(),
+            url: format!("https://example.com/{code}.png"),
+        };
+        let post = NewPost::new(
+            "gm :soapbox:".to_owned(),
+            kp.clone(),
+            Vec::new(),
+            Vec::new(),
+        )
+        .emojis(vec![emoji("soapbox"), emoji("removed")]);
+        let note = post.to_note(&kp.pubkey);
+
+        let mut tags_iter = note.tags().iter();
+        tags_iter.next(); //ignore the first one, the client tag
+        let tag = tags_iter.next().unwrap();
+        assert_eq!(tag.count(), 3);
+        assert_eq!(tag.get(0).unwrap().str().unwrap(), "emoji");
+        assert_eq!(tag.get(1).unwrap().str().unwrap(), "soapbox");
+        assert!(tags_iter.next().is_none());
+    }
+
+    #[test]
+    fn note_expiration_tag() {
+        let kp = FullKeypair::generate();
+        let post = NewPost::new("gone soon".to_owned(), kp.clone(), Vec::new(), Vec::new())
+            .expiration(Some(1_700_000_000));
+        let note = post.to_note(&kp.pubkey);
+        assert_eq!(notedeck::note::expiration(&note), Some(1_700_000_000));
+
+        let post = NewPost::new(
+            "here to stay".to_owned(),
+            kp.clone(),
+            Vec::new(),
+            Vec::new(),
+        );
+        let note = post.to_note(&kp.pubkey);
+        assert_eq!(notedeck::note::expiration(&note), None);
+    }
+
+    #[test]
+    fn note_poll_tags() {
+        let kp = FullKeypair::generate();
+        let post = NewPost::new(
+            "tabs or spaces?".to_owned(),
+            kp.clone(),
+            Vec::new(),
+            Vec::new(),
+        )
+        .poll(Some(NewPoll {
+            options: vec!["tabs".to_owned(), "spaces".to_owned()],
+            multiple_choice: false,
+            ends_at: Some(1_700_000_000),
+        }));
+        let note = post.to_note(&kp.pubkey);
+        assert_eq!(note.kind(), POLL_KIND);
+
+        let poll = notedeck::poll::Poll::from_note(&note).unwrap();
+        let labels: Vec<&str> = poll.options.iter().map(|o| o.label.as_str()).collect();
+        assert_eq!(labels, vec!["tabs", "spaces"]);
+        assert!(!poll.multiple_choice);
+        assert_eq!(poll.ends_at, Some(1_700_000_000));
+    }
+
+    const RELAY: &str = "wss://relay.damus.io";
+
+    /// The e tags on `note`, ids in hex
+    fn e_tags(note: &Note) -> Vec<Vec<String>> {
+        note.tags()
+            .iter()
+            .filter(|tag| tag.get(0).and_then(|name| name.str()) == Some("e"))
+            .map(|tag| {
+                (0..tag.count())
+                    .map(|ind| {
+                        let entry = tag.get(ind).unwrap();
+                        match entry.id() {
+                            Some(id) => hex::encode(id),
+                            None => entry.str().unwrap().to_owned(),
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn e(parts: &[&str]) -> Vec<String> {
+        std::iter::once("e")
+            .chain(parts.iter().copied())
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn deep_replies_mark_root_and_parent() {
+        let kp = FullKeypair::generate();
+        let relays = [RELAY.to_owned()];
+        let post =
+            |content: &str| NewPost::new(content.to_owned(), kp.clone(), Vec::new(), Vec::new());
+        let (root, first, second, third) = (post("root"), post("1"), post("2"), post("3"));
+        let scheme = ReplyTagScheme::Markers;
+
+        let root = root.to_note(&kp.pubkey);
+        let thread = ReplyThread::from_note(&root, &relays);
+        let first = first.to_reply(&kp.pubkey, &root, &thread, scheme);
+        let thread = ReplyThread::from_note(&first, &relays);
+        let second = second.to_reply(&kp.pubkey, &first, &thread, scheme);
+        let thread = ReplyThread::from_note(&second, &relays);
+        let third = third.to_reply(&kp.pubkey, &second, &thread, scheme);
+
+        let author = hex::encode(root.pubkey());
+        let id = |note: &Note| hex::encode(note.id());
+        let root_tag = e(&[&id(&root), RELAY, "root", &author]);
+
+        assert_eq!(e_tags(&first), vec![root_tag.clone()]);
+        assert_eq!(
+            e_tags(&second),
+            vec![root_tag.clone(), e(&[&id(&first), RELAY, "reply", &author])]
+        );
+        assert_eq!(
+            e_tags(&third),
+            vec![root_tag, e(&[&id(&second), RELAY, "reply", &author])]
+        );
+    }
+
+    #[test]
+    fn deep_replies_in_positional_order() {
+        let kp = FullKeypair::generate();
+        let relays = [RELAY.to_owned()];
+        let post =
+            |content: &str| NewPost::new(content.to_owned(), kp.clone(), Vec::new(), Vec::new());
+        let (root, first, second, third) = (post("root"), post("1"), post("2"), post("3"));
+        let scheme = ReplyTagScheme::Positional;
+
+        let root = root.to_note(&kp.pubkey);
+        let thread = ReplyThread::from_note(&root, &relays);
+        let first = first.to_reply(&kp.pubkey, &root, &thread, scheme);
+        let thread = ReplyThread::from_note(&first, &relays);
+        let second = second.to_reply(&kp.pubkey, &first, &thread, scheme);
+        let thread = ReplyThread::from_note(&second, &relays);
+        let third = third.to_reply(&kp.pubkey, &second, &thread, scheme);
+
+        let id = |note: &Note| hex::encode(note.id());
+        let root_tag = e(&[&id(&root), RELAY]);
+
+        assert_eq!(e_tags(&first), vec![root_tag.clone()]);
+        assert_eq!(
+            e_tags(&second),
+            vec![root_tag.clone(), e(&[&id(&first), RELAY])]
+        );
+        assert_eq!(e_tags(&third), vec![root_tag, e(&[&id(&second), RELAY])]);
+    }
+
+    #[test]
+    fn marked_reply_to_positional_thread() {
+        let kp = FullKeypair::generate();
+        let relays = [RELAY.to_owned()];
+        let post =
+            |content: &str| NewPost::new(content.to_owned(), kp.clone(), Vec::new(), Vec::new());
+        let (root, first, second) = (post("root"), post("1"), post("2"));
+
+        let root = root.to_note(&kp.pubkey);
+        let thread = ReplyThread::from_note(&root, &relays);
+        let first = first.to_reply(&kp.pubkey, &root, &thread, ReplyTagScheme::Positional);
+        let thread = ReplyThread::from_note(&first, &relays);
+        let second = second.to_reply(&kp.pubkey, &first, &thread, ReplyTagScheme::Markers);
+
+        // a positional tag has no author for the root, and we don't have
+        // the root note here to look it up
+        let id = |note: &Note| hex::encode(note.id());
+        assert_eq!(
+            e_tags(&second),
+            vec![
+                e(&[&id(&root), RELAY, "root"]),
+                e(&[&id(&first), RELAY, "reply", &hex::encode(first.pubkey())]),
+            ]
+        );
+    }
 }
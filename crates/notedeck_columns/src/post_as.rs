@@ -0,0 +1,157 @@
+//! An optional check before posting from a secondary account. Anyone
+//! keeping a pseudonymous account next to their main one can ask to
+//! confirm every post that isn't going out as the main account.
+
+use egui::RichText;
+use enostr::Pubkey;
+use nostrdb::Transaction;
+use notedeck::{name::get_display_name, AppContext, DataPath, JsonSettings, NotedeckTextStyle};
+use tracing::error;
+
+use crate::{app::get_active_columns_mut, route::Route, ui::note::NewPostAction, Damus};
+
+const POST_AS_FILE: &str = "post_as.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PostAsSettings {
+    /// Ask before posting as anyone other than `primary`
+    #[serde(default)]
+    pub confirm_secondary: bool,
+    #[serde(default)]
+    pub primary: Option<Pubkey>,
+}
+
+/// A post held back until the user confirms the account it's from
+struct PendingPost {
+    col: usize,
+    action: NewPostAction,
+}
+
+pub struct PostAsGuard {
+    file: JsonSettings<PostAsSettings>,
+    settings: PostAsSettings,
+    pending: Option<PendingPost>,
+}
+
+impl PostAsGuard {
+    pub fn new(path: &DataPath) -> Self {
+        let file = JsonSettings::new(path, POST_AS_FILE);
+        let settings = file.load();
+
+        Self {
+            file,
+            settings,
+            pending: None,
+        }
+    }
+
+    pub fn settings(&self) -> &PostAsSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: PostAsSettings) {
+        if settings == self.settings {
+            return;
+        }
+        self.settings = settings;
+
+        self.file.save(&self.settings);
+    }
+
+    pub fn needs_confirmation(&self, poster: &Pubkey) -> bool {
+        self.settings.confirm_secondary
+            && self
+                .settings
+                .primary
+                .is_some_and(|primary| primary != *poster)
+    }
+
+    /// Keep `action` from column `col` until the user says whether to send
+    /// it. A newer post replaces one that's still waiting.
+    pub fn hold(&mut self, col: usize, action: NewPostAction) {
+        self.pending = Some(PendingPost { col, action });
+    }
+}
+
+enum Confirmation {
+    Post,
+    Cancel,
+}
+
+/// Ask about a held post, and send it if the user agrees. Cancelling
+/// leaves the draft as it was.
+pub fn confirm_pending_post(app: &mut Damus, ctx: &mut AppContext<'_>, egui_ctx: &egui::Context) {
+    let Some(pending) = &app.post_as.pending else {
+        return;
+    };
+
+    let poster = *pending.action.poster();
+    let name = Transaction::new(ctx.ndb)
+        .ok()
+        .and_then(|txn| {
+            let profile = ctx.ndb.get_profile_by_pubkey(&txn, poster.bytes()).ok();
+            let name = get_display_name(profile.as_ref());
+            name.display_name.or(name.username).map(str::to_owned)
+        })
+        .or_else(|| poster.npub())
+        .unwrap_or_default();
+
+    let Some(confirmation) = confirmation_ui(egui_ctx, &name) else {
+        return;
+    };
+
+    let Some(pending) = app.post_as.pending.take() else {
+        return;
+    };
+    if matches!(confirmation, Confirmation::Cancel) {
+        return;
+    }
+
+    if let Err(err) = crate::undo_send::send_post(app, ctx, pending.col, pending.action) {
+        error!("Error executing post action: {err}");
+        notedeck::toast::error(egui_ctx, format!("Couldn't post: {err}"));
+        return;
+    }
+
+    // close the composer, if it's still what the column is showing
+    let columns = get_active_columns_mut(ctx.accounts, &mut app.decks_cache);
+    if pending.col < columns.num_columns() {
+        let router = columns.column_mut(pending.col).router_mut();
+        if matches!(
+            router.top(),
+            Route::ComposeNote | Route::Reply(_) | Route::Quote(_)
+        ) {
+            router.go_back();
+        }
+    }
+}
+
+fn confirmation_ui(ctx: &egui::Context, name: &str) -> Option<Confirmation> {
+    let mut confirmation = None;
+
+    let modal = egui::Modal::new(egui::Id::new("post_as_confirmation")).show(ctx, |ui| {
+        ui.set_max_width(320.0);
+        ui.label(
+            RichText::new(format!("Post as {name}?"))
+                .text_style(NotedeckTextStyle::Heading3.text_style()),
+        );
+        ui.add_space(4.0);
+        ui.label("This isn't your primary account.");
+        ui.add_space(12.0);
+
+        ui.horizontal(|ui| {
+            if ui.button("Post").clicked() {
+                confirmation = Some(Confirmation::Post);
+            }
+            if ui.button("Cancel").clicked() {
+                confirmation = Some(Confirmation::Cancel);
+            }
+        });
+    });
+
+    if confirmation.is_none() && modal.should_close() {
+        confirmation = Some(Confirmation::Cancel);
+    }
+
+    confirmation
+}
@@ -0,0 +1,199 @@
+//! Proof of work on outgoing notes. Mining runs on its own thread per
+//! note, and the note is signed and sent once it has a nonce.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use enostr::{ClientMessage, Pubkey, RelayPool};
+use notedeck::pow::{mine, sign_event, MAX_DIFFICULTY};
+use notedeck::{Accounts, DataPath, JsonSettings, SignPurpose, UnsignedEvent};
+use poll_promise::Promise;
+use tracing::error;
+
+const POW_FILE: &str = "pow.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PowSettings {
+    pub enabled: bool,
+
+    /// Leading zero bits to mine for
+    pub difficulty: u32,
+}
+
+impl Default for PowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            difficulty: 16,
+        }
+    }
+}
+
+impl PowSettings {
+    /// Difficulty to mine outgoing notes to, None if they go out as is
+    pub fn target(&self) -> Option<u32> {
+        (self.enabled && self.difficulty > 0).then(|| self.difficulty.min(MAX_DIFFICULTY))
+    }
+}
+
+/// A note being mined
+struct MiningJob {
+    pubkey: Pubkey,
+
+    /// Signed with this when it's done, or by the account's remote signer
+    /// if there's no local key
+    seckey: Option<[u8; 32]>,
+    target: u32,
+
+    /// The note as it was written, in case it's sent without waiting
+    original: UnsignedEvent,
+    attempts: Arc<AtomicU64>,
+    cancel: Arc<AtomicBool>,
+    skip: bool,
+    promise: Promise<Option<UnsignedEvent>>,
+}
+
+impl MiningJob {
+    /// A rough guess at how far along it is. Each attempt has a 1 in
+    /// 2^target chance so this is only an expectation.
+    fn progress(&self) -> f32 {
+        let expected = 2f64.powi(self.target as i32);
+        let attempts = self.attempts.load(Ordering::Relaxed) as f64;
+        (attempts / expected).min(0.99) as f32
+    }
+}
+
+pub struct ProofOfWork {
+    file: JsonSettings<PowSettings>,
+    settings: PowSettings,
+    jobs: Vec<MiningJob>,
+}
+
+impl ProofOfWork {
+    pub fn new(path: &DataPath) -> Self {
+        let file = JsonSettings::new(path, POW_FILE);
+        let settings = file.load();
+
+        Self {
+            file,
+            settings,
+            jobs: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> PowSettings {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: PowSettings) {
+        if settings == self.settings {
+            return;
+        }
+        self.settings = settings;
+
+        self.file.save(&self.settings);
+    }
+
+    /// Start mining `event` for `pubkey` in the background
+    pub fn start(
+        &mut self,
+        pubkey: Pubkey,
+        seckey: Option<[u8; 32]>,
+        event: UnsignedEvent,
+        target: u32,
+    ) {
+        let attempts = Arc::new(AtomicU64::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let original = event.clone();
+
+        let (sender, promise) = Promise::new();
+        {
+            let attempts = attempts.clone();
+            let cancel = cancel.clone();
+            let pubkey = *pubkey.bytes();
+            std::thread::spawn(move || {
+                sender.send(mine(&pubkey, event, target, &attempts, &cancel));
+            });
+        }
+
+        self.jobs.push(MiningJob {
+            pubkey,
+            seckey,
+            target,
+            original,
+            attempts,
+            cancel,
+            skip: false,
+            promise,
+        });
+    }
+
+    /// How many notes are being mined and how far along the furthest
+    /// behind one is
+    pub fn progress(&self) -> Option<(usize, f32)> {
+        let least = self
+            .jobs
+            .iter()
+            .filter(|job| !job.skip)
+            .map(MiningJob::progress)
+            .min_by(f32::total_cmp)?;
+        Some((self.jobs.iter().filter(|job| !job.skip).count(), least))
+    }
+
+    /// Stop mining and send everything as it is
+    pub fn skip_all(&mut self) {
+        for job in &mut self.jobs {
+            job.skip = true;
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Sign and send notes that are done mining. Returns true while there
+    /// are still some going.
+    pub fn process_mined(&mut self, pool: &mut RelayPool, accounts: &mut Accounts) -> bool {
+        let mut i = 0;
+        while i < self.jobs.len() {
+            if self.jobs[i].promise.ready().is_none() {
+                i += 1;
+                continue;
+            }
+
+            let job = self.jobs.remove(i);
+            let Ok(mined) = job.promise.try_take() else {
+                continue;
+            };
+            let event = match mined {
+                Some(mined) => mined,
+                None if job.skip => job.original,
+                None => continue,
+            };
+            send(pool, accounts, &job.pubkey, job.seckey.as_ref(), &event);
+        }
+
+        !self.jobs.is_empty()
+    }
+}
+
+fn send(
+    pool: &mut RelayPool,
+    accounts: &mut Accounts,
+    pubkey: &Pubkey,
+    seckey: Option<&[u8; 32]>,
+    event: &UnsignedEvent,
+) {
+    let Some(seckey) = seckey else {
+        accounts.sign_remote(pool, pubkey.bytes(), event, SignPurpose::Publish);
+        return;
+    };
+
+    let Some(note) = sign_event(event, seckey) else {
+        error!("could not sign mined note");
+        return;
+    };
+
+    match ClientMessage::event(&note) {
+        Ok(msg) => pool.send(&msg),
+        Err(e) => error!("could not send mined note: {e}"),
+    }
+}
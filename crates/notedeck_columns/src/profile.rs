@@ -3,7 +3,8 @@ use std::collections::HashMap;
 use enostr::{FullKeypair, Pubkey, RelayPool};
 use nostrdb::{Ndb, Note, NoteBuildOptions, NoteBuilder};
 
-use tracing::info;
+use notedeck::{Accounts, ContactAction};
+use tracing::{error, info};
 
 use crate::{nav::RouterAction, profile_state::ProfileState, route::Route};
 
@@ -37,6 +38,10 @@ fn add_client_tag(builder: NoteBuilder<'_>) -> NoteBuilder<'_> {
 pub enum ProfileAction {
     Edit(FullKeypair),
     SaveChanges(SaveProfileChanges),
+
+    /// Follow or unfollow on behalf of an account
+    Contact(Pubkey, ContactAction),
+    DismissContactConflict(Pubkey),
 }
 
 impl ProfileAction {
@@ -45,6 +50,7 @@ impl ProfileAction {
         state_map: &mut HashMap<Pubkey, ProfileState>,
         ndb: &Ndb,
         pool: &mut RelayPool,
+        accounts: &mut Accounts,
     ) -> Option<RouterAction> {
         match self {
             ProfileAction::Edit(kp) => Some(RouterAction::route_to(Route::EditProfile(kp.pubkey))),
@@ -62,6 +68,16 @@ impl ProfileAction {
 
                 Some(RouterAction::GoBack)
             }
+            ProfileAction::Contact(account, action) => {
+                if let Err(err) = accounts.apply_contact_action(ndb, pool, account, *action) {
+                    error!("couldn't update contact list: {err}");
+                }
+                None
+            }
+            ProfileAction::DismissContactConflict(account) => {
+                accounts.dismiss_contact_conflict(account);
+                None
+            }
         }
     }
 }
@@ -1,6 +1,17 @@
 use nostrdb::{NdbProfile, ProfileRecord};
+use poll_promise::Promise;
+use serde_json::{Map, Value};
 
-#[derive(Default, Debug)]
+use crate::{media_upload::Nip94Event, Error};
+
+/// The profile images we can upload a new file for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileImage {
+    Picture,
+    Banner,
+}
+
+#[derive(Default)]
 pub struct ProfileState {
     pub display_name: String,
     pub name: String,
@@ -10,6 +21,9 @@ pub struct ProfileState {
     pub website: String,
     pub lud16: String,
     pub nip05: String,
+
+    uploads: Vec<(ProfileImage, Promise<Result<Nip94Event, Error>>)>,
+    pub upload_error: Option<String>,
 }
 
 impl ProfileState {
@@ -32,38 +46,64 @@ impl ProfileState {
             website,
             lud16,
             nip05,
+            ..Default::default()
         }
     }
 
-    pub fn to_json(&self) -> String {
-        let mut fields = Vec::new();
-
-        if !self.display_name.is_empty() {
-            fields.push(format!(r#""display_name":"{}""#, self.display_name));
-        }
-        if !self.name.is_empty() {
-            fields.push(format!(r#""name":"{}""#, self.name));
-        }
-        if !self.picture.is_empty() {
-            fields.push(format!(r#""picture":"{}""#, self.picture));
+    pub fn image_url_mut(&mut self, image: ProfileImage) -> &mut String {
+        match image {
+            ProfileImage::Picture => &mut self.picture,
+            ProfileImage::Banner => &mut self.banner,
         }
-        if !self.banner.is_empty() {
-            fields.push(format!(r#""banner":"{}""#, self.banner));
-        }
-        if !self.about.is_empty() {
-            fields.push(format!(r#""about":"{}""#, self.about));
-        }
-        if !self.website.is_empty() {
-            fields.push(format!(r#""website":"{}""#, self.website));
-        }
-        if !self.lud16.is_empty() {
-            fields.push(format!(r#""lud16":"{}""#, self.lud16));
-        }
-        if !self.nip05.is_empty() {
-            fields.push(format!(r#""nip05":"{}""#, self.nip05));
+    }
+
+    pub fn upload(&mut self, image: ProfileImage, promise: Promise<Result<Nip94Event, Error>>) {
+        self.upload_error = None;
+        self.uploads.retain(|(img, _)| *img != image);
+        self.uploads.push((image, promise));
+    }
+
+    pub fn is_uploading(&self, image: ProfileImage) -> bool {
+        self.uploads.iter().any(|(img, _)| *img == image)
+    }
+
+    pub fn is_any_uploading(&self) -> bool {
+        !self.uploads.is_empty()
+    }
+
+    /// Fill in the urls of uploads that finished
+    pub fn poll_uploads(&mut self) {
+        for (image, promise) in std::mem::take(&mut self.uploads) {
+            match promise.try_take() {
+                Err(promise) => self.uploads.push((image, promise)),
+                Ok(Ok(media)) => *self.image_url_mut(image) = media.url,
+                Ok(Err(err)) => {
+                    tracing::error!("profile image upload failed: {err}");
+                    self.upload_error = Some(err.to_string());
+                }
+            }
         }
+    }
+
+    pub fn to_json(&self) -> String {
+        let fields = [
+            ("display_name", &self.display_name),
+            ("name", &self.name),
+            ("picture", &self.picture),
+            ("banner", &self.banner),
+            ("about", &self.about),
+            ("website", &self.website),
+            ("lud16", &self.lud16),
+            ("nip05", &self.nip05),
+        ];
+
+        let metadata: Map<String, Value> = fields
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| (key.to_owned(), Value::String(value.clone())))
+            .collect();
 
-        format!("{{{}}}", fields.join(","))
+        Value::Object(metadata).to_string()
     }
 }
 
@@ -77,3 +117,22 @@ fn get_item<'a>(
         .and_then(item_retriever)
         .map_or_else(String::new, ToString::to_string)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_json_escapes_fields() {
+        let state = ProfileState {
+            name: "jb55".to_owned(),
+            about: "says \"hi\"\nand bye".to_owned(),
+            ..Default::default()
+        };
+
+        let json: Value = serde_json::from_str(&state.to_json()).unwrap();
+        assert_eq!(json["name"], "jb55");
+        assert_eq!(json["about"], "says \"hi\"\nand bye");
+        assert!(json.get("picture").is_none());
+    }
+}
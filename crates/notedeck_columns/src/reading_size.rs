@@ -0,0 +1,48 @@
+//! Text size for views meant for reading, like threads. It's on top of
+//! the app's zoom and only changes text, and each kind of view keeps its
+//! own.
+
+use std::collections::HashMap;
+
+use notedeck::{DataPath, JsonSettings};
+
+const READING_SIZE_FILE: &str = "reading_size.json";
+
+pub const MIN_TEXT_SCALE: f32 = 0.8;
+pub const MAX_TEXT_SCALE: f32 = 2.0;
+pub const TEXT_SCALE_STEP: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ReadingView {
+    Thread,
+}
+
+pub struct ReadingSizes {
+    file: JsonSettings<HashMap<ReadingView, f32>>,
+    scales: HashMap<ReadingView, f32>,
+}
+
+impl ReadingSizes {
+    pub fn new(path: &DataPath) -> Self {
+        let file = JsonSettings::new(path, READING_SIZE_FILE);
+        let scales = file.load();
+
+        Self { file, scales }
+    }
+
+    /// How much bigger than normal text is in `view`
+    pub fn scale(&self, view: ReadingView) -> f32 {
+        self.scales.get(&view).copied().unwrap_or(1.0)
+    }
+
+    pub fn set_scale(&mut self, view: ReadingView, scale: f32) {
+        // snapped to a step so repeated nudges don't drift
+        let scale = ((scale / TEXT_SCALE_STEP).round() * TEXT_SCALE_STEP)
+            .clamp(MIN_TEXT_SCALE, MAX_TEXT_SCALE);
+        if (scale - self.scale(view)).abs() < f32::EPSILON {
+            return;
+        }
+        self.scales.insert(view, scale);
+        self.file.save(&self.scales);
+    }
+}
@@ -0,0 +1,247 @@
+//! Zaps that repeat, like 1000 sats to someone every week. They're sent
+//! through the sender's wallet while the app is running, so a zap that
+//! comes due while it's closed goes out the next time it starts. Only
+//! once though, however many were missed.
+
+use std::time::Duration;
+
+use enostr::{Pubkey, RelayPool};
+use notedeck::{Accounts, AnyZapState, DataPath, JsonSettings, ZapTarget, Zaps};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::scheduled::unix_now;
+
+const RECURRING_FILE: &str = "recurring_zaps.json";
+
+/// Runs remembered per zap, older ones are dropped
+const MAX_HISTORY: usize = 20;
+
+const DAY: u64 = 60 * 60 * 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ZapInterval {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl ZapInterval {
+    pub const ALL: [ZapInterval; 3] = [
+        ZapInterval::Daily,
+        ZapInterval::Weekly,
+        ZapInterval::Monthly,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ZapInterval::Daily => "every day",
+            ZapInterval::Weekly => "every week",
+            ZapInterval::Monthly => "every month",
+        }
+    }
+
+    fn secs(self) -> u64 {
+        match self {
+            ZapInterval::Daily => DAY,
+            ZapInterval::Weekly => 7 * DAY,
+            ZapInterval::Monthly => 30 * DAY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZapRun {
+    /// Unix time it was sent
+    pub at: u64,
+
+    /// Why it didn't go through, None if it did
+    pub error: Option<String>,
+}
+
+/// What the profile page asks for when setting one up
+#[derive(Debug, Clone, Copy)]
+pub struct NewRecurringZap {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub msats: u64,
+    pub interval: ZapInterval,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurringZap {
+    pub id: u64,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub msats: u64,
+    pub interval: ZapInterval,
+
+    /// Unix time of the next run
+    pub next_at: u64,
+
+    /// Oldest first
+    #[serde(default)]
+    pub history: Vec<ZapRun>,
+
+    /// When the run that's being paid right now was sent
+    #[serde(skip)]
+    in_flight: Option<u64>,
+}
+
+impl RecurringZap {
+    pub fn is_paying(&self) -> bool {
+        self.in_flight.is_some()
+    }
+
+    fn record(&mut self, at: u64, error: Option<String>) {
+        match &error {
+            Some(e) => error!("recurring zap {} failed: {e}", self.id),
+            None => info!("recurring zap {} went through", self.id),
+        }
+        self.history.push(ZapRun { at, error });
+        let extra = self.history.len().saturating_sub(MAX_HISTORY);
+        self.history.drain(..extra);
+    }
+
+    fn target(&self) -> ZapTarget<'_> {
+        ZapTarget::Profile(self.recipient.bytes())
+    }
+}
+
+/// When a zap that was due at `due` comes due again. Runs missed while
+/// the app was closed aren't made up, it's the first one after `now`.
+fn next_due(due: u64, interval: ZapInterval, now: u64) -> u64 {
+    let secs = interval.secs();
+    let missed = now.saturating_sub(due) / secs;
+    due + (missed + 1) * secs
+}
+
+pub struct RecurringZaps {
+    file: JsonSettings<Vec<RecurringZap>>,
+    zaps: Vec<RecurringZap>,
+}
+
+impl RecurringZaps {
+    pub fn new(path: &DataPath) -> Self {
+        let file = JsonSettings::new(path, RECURRING_FILE);
+        let zaps = file.load();
+
+        Self { file, zaps }
+    }
+
+    pub fn zaps(&self) -> &[RecurringZap] {
+        &self.zaps
+    }
+
+    /// Set up a new recurring zap. The first one goes out right away.
+    pub fn add(&mut self, new: NewRecurringZap) {
+        let id = self.zaps.iter().map(|z| z.id + 1).max().unwrap_or(0);
+        self.zaps.push(RecurringZap {
+            id,
+            sender: new.sender,
+            recipient: new.recipient,
+            msats: new.msats,
+            interval: new.interval,
+            next_at: unix_now(),
+            history: vec![],
+            in_flight: None,
+        });
+        self.save();
+    }
+
+    pub fn cancel(&mut self, id: u64) {
+        self.zaps.retain(|z| z.id != id);
+        self.save();
+    }
+
+    /// Send the zaps that have come due and note how the ones in flight
+    /// went. Returns how long until there's something to do again.
+    pub fn run_due(
+        &mut self,
+        zaps: &mut Zaps,
+        pool: &RelayPool,
+        accounts: &Accounts,
+    ) -> Option<Duration> {
+        let now = unix_now();
+        let mut changed = false;
+
+        for recurring in &mut self.zaps {
+            if let Some(sent_at) = recurring.in_flight {
+                let outcome =
+                    match zaps.any_zap_state_for(recurring.sender.bytes(), recurring.target()) {
+                        Ok(AnyZapState::LocalOnly | AnyZapState::Confirmed) => Some(None),
+                        Ok(AnyZapState::None | AnyZapState::Pending) => None,
+                        Err(e) => Some(Some(e.to_string())),
+                    };
+                if let Some(error) = outcome {
+                    recurring.in_flight = None;
+                    recurring.record(sent_at, error);
+                    changed = true;
+                }
+                continue;
+            }
+
+            if recurring.next_at > now {
+                continue;
+            }
+
+            // a locked account's zaps wait for the passphrase
+            match accounts.find_account(recurring.sender.bytes()) {
+                Some(account) if account.is_locked() => continue,
+                Some(_) => {
+                    // an earlier failure would read as this one failing
+                    zaps.clear_error_for(recurring.sender.bytes(), recurring.target());
+                    let relays = pool.relays.iter().map(|r| r.url().to_string()).collect();
+                    zaps.send_zap(
+                        recurring.sender.bytes(),
+                        relays,
+                        recurring.target(),
+                        recurring.msats,
+                    );
+                    recurring.in_flight = Some(now);
+                }
+                None => recurring.record(
+                    now,
+                    Some("the account it zaps from is logged out".to_owned()),
+                ),
+            }
+            recurring.next_at = next_due(recurring.next_at, recurring.interval, now);
+            changed = true;
+        }
+
+        if changed {
+            self.save();
+        }
+
+        if self.zaps.iter().any(RecurringZap::is_paying) {
+            return Some(Duration::from_secs(1));
+        }
+        self.zaps
+            .iter()
+            .map(|z| Duration::from_secs(z.next_at.saturating_sub(now)))
+            .min()
+    }
+
+    fn save(&self) {
+        self.file.save(&self.zaps);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missed_runs_arent_made_up() {
+        let week = ZapInterval::Weekly.secs();
+
+        // on time
+        assert_eq!(next_due(1000, ZapInterval::Weekly, 1000), 1000 + week);
+        // a little late, still keeps to the schedule
+        assert_eq!(next_due(1000, ZapInterval::Weekly, 1500), 1000 + week);
+        // closed for two and a half weeks, the next one is the one after now
+        let now = 1000 + week * 5 / 2;
+        assert_eq!(next_due(1000, ZapInterval::Weekly, now), 1000 + 3 * week);
+        assert!(next_due(1000, ZapInterval::Daily, now) > now);
+    }
+}
@@ -1,5 +1,6 @@
-use enostr::RelayPool;
 pub use enostr::RelayStatus;
+use enostr::{Compression, PoolRelay, RelayInformation, RelayPool, RelayRoles};
+use std::time::Duration;
 
 /// The interface to a RelayPool for UI components.
 /// Represents all user-facing operations that can be performed for a user's relays
@@ -10,6 +11,23 @@ pub struct RelayPoolManager<'a> {
 pub struct RelayInfo<'a> {
     pub relay_url: &'a str,
     pub status: RelayStatus,
+
+    /// Rolling round trip time, once we've measured one
+    pub rtt: Option<Duration>,
+
+    /// EVENTs received from the relay, and how many of them we already had
+    pub events: u64,
+    pub duplicates: u64,
+
+    /// From the relay's NIP-11 document, if it has one
+    pub info: Option<&'a RelayInformation>,
+
+    /// What we use the relay for, None for non-websocket relays
+    pub roles: Option<RelayRoles>,
+
+    /// What the connection negotiated, None for non-websocket relays and
+    /// ones we aren't connected to
+    pub compression: Option<Compression>,
 }
 
 impl<'a> RelayPoolManager<'a> {
@@ -21,9 +39,24 @@ impl<'a> RelayPoolManager<'a> {
         self.pool
             .relays
             .iter()
-            .map(|relay| RelayInfo {
-                relay_url: relay.url(),
-                status: relay.status(),
+            .map(|relay| {
+                let stats = relay.stats();
+                RelayInfo {
+                    relay_url: relay.url(),
+                    status: relay.status(),
+                    rtt: stats.and_then(|stats| stats.rtt()),
+                    events: stats.map_or(0, |stats| stats.events()),
+                    duplicates: stats.map_or(0, |stats| stats.duplicates()),
+                    info: relay.info(),
+                    roles: match relay {
+                        PoolRelay::Websocket(_) => Some(self.pool.roles(relay.url())),
+                        PoolRelay::Multicast(_) => None,
+                    },
+                    compression: match relay.status() {
+                        RelayStatus::Connected => relay.compression(),
+                        _ => None,
+                    },
+                }
             })
             .collect()
     }
@@ -0,0 +1,239 @@
+//! Checking whether a relay takes our writes. A throwaway event is sent
+//! to just that relay, and once it says OK we ask it for the event back.
+//! The event is NIP-78 app data with a NIP-40 expiration, so it doesn't
+//! show up in anyone's feed and relays can forget it soon after. Each test
+//! replaces the last one.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use enostr::{ClientMessage, Filter, FullKeypair, RelayPool};
+use nostrdb::{NoteBuildOptions, NoteBuilder};
+use tracing::{debug, error};
+
+use crate::scheduled::unix_now;
+
+/// NIP-78 arbitrary app data
+const TEST_KIND: u32 = 30078;
+const TEST_D_TAG: &str = "notedeck-relay-test";
+
+/// How long relays should keep the test event around
+const EXPIRES_AFTER: u64 = 10 * 60;
+
+/// How long we wait on each step before calling it
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+const SUB_PREFIX: &str = "relay-test-";
+
+#[derive(Debug, Clone)]
+pub struct RelayTestResult {
+    pub accepted: bool,
+
+    /// What the relay said with its OK, like why it refused the event
+    pub message: String,
+
+    /// From sending the event to the relay's OK
+    pub latency: Duration,
+
+    /// Whether asking for the event back found it. None when it wasn't
+    /// accepted, so there was nothing to look for.
+    pub queryable: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub enum RelayTest {
+    /// Waiting on the relay's OK
+    Publishing {
+        event_id: [u8; 32],
+        sent_at: Instant,
+    },
+
+    /// Accepted, waiting to get it back
+    Querying {
+        result: RelayTestResult,
+        sub_id: String,
+        sent_at: Instant,
+    },
+    Done(RelayTestResult),
+
+    /// The relay never answered
+    TimedOut,
+}
+
+impl RelayTest {
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self,
+            RelayTest::Publishing { .. } | RelayTest::Querying { .. }
+        )
+    }
+}
+
+/// The write tests run from the relay settings, by relay url
+#[derive(Default)]
+pub struct RelayTests {
+    tests: HashMap<String, RelayTest>,
+}
+
+impl RelayTests {
+    pub fn get(&self, relay: &str) -> Option<&RelayTest> {
+        self.tests.get(relay)
+    }
+
+    /// Send a test event to `relay` signed by `kp`
+    pub fn start(&mut self, relay: &str, kp: &FullKeypair, pool: &mut RelayPool) {
+        let now = unix_now();
+        let expiration = (now + EXPIRES_AFTER).to_string();
+        let Some(note) = NoteBuilder::new()
+            .kind(TEST_KIND)
+            .content("Notedeck relay write test, safe to ignore")
+            .start_tag()
+            .tag_str("d")
+            .tag_str(TEST_D_TAG)
+            .start_tag()
+            .tag_str("expiration")
+            .tag_str(&expiration)
+            .options(
+                NoteBuildOptions::default()
+                    .created_at(true)
+                    .sign(&kp.secret_key.to_secret_bytes()),
+            )
+            .build()
+        else {
+            error!("couldn't build relay test event");
+            return;
+        };
+
+        let msg = match note
+            .json()
+            .map_err(|e| e.to_string())
+            .and_then(|json| ClientMessage::event_json(json).map_err(|e| e.to_string()))
+        {
+            Ok(msg) => msg,
+            Err(err) => {
+                error!("couldn't serialize relay test event: {err}");
+                return;
+            }
+        };
+
+        debug!("testing writes to {relay}");
+        pool.send_to_ignoring_roles(&msg, relay);
+        self.tests.insert(
+            relay.to_owned(),
+            RelayTest::Publishing {
+                event_id: *note.id(),
+                sent_at: Instant::now(),
+            },
+        );
+    }
+
+    /// A relay answered an EVENT we sent
+    pub fn handle_ok(
+        &mut self,
+        relay: &str,
+        event_id: &str,
+        accepted: bool,
+        message: &str,
+        pool: &mut RelayPool,
+    ) {
+        let Some(RelayTest::Publishing {
+            event_id: sent_id,
+            sent_at,
+        }) = self.tests.get(relay)
+        else {
+            return;
+        };
+        if hex::encode(sent_id) != event_id {
+            return;
+        }
+        let sent_id = *sent_id;
+
+        let result = RelayTestResult {
+            accepted,
+            message: message.to_owned(),
+            latency: sent_at.elapsed(),
+            queryable: None,
+        };
+        if !accepted {
+            self.tests.insert(relay.to_owned(), RelayTest::Done(result));
+            return;
+        }
+
+        let sub_id = format!("{SUB_PREFIX}{}", &event_id[..16]);
+        let filter = Filter::new().ids([&sent_id]).limit(1).build();
+        pool.send_to_ignoring_roles(&ClientMessage::req(sub_id.clone(), vec![filter]), relay);
+
+        self.tests.insert(
+            relay.to_owned(),
+            RelayTest::Querying {
+                result,
+                sub_id,
+                sent_at: Instant::now(),
+            },
+        );
+    }
+
+    /// An EVENT or EOSE on one of our subscriptions. Returns whether it
+    /// was ours, so the caller can leave it alone.
+    pub fn handle_query(
+        &mut self,
+        relay: &str,
+        sub_id: &str,
+        found: bool,
+        pool: &mut RelayPool,
+    ) -> bool {
+        if !sub_id.starts_with(SUB_PREFIX) {
+            return false;
+        }
+
+        if let Some(RelayTest::Querying {
+            result,
+            sub_id: ours,
+            ..
+        }) = self.tests.get(relay)
+        {
+            if ours == sub_id {
+                let mut result = result.clone();
+                result.queryable = Some(found);
+                self.tests.insert(relay.to_owned(), RelayTest::Done(result));
+                pool.send_to(&ClientMessage::close(sub_id.to_owned()), relay);
+            }
+        }
+
+        true
+    }
+
+    /// Give up on relays that have gone quiet. Returns how soon to check
+    /// again while there are tests running.
+    pub fn check_timeouts(&mut self, pool: &mut RelayPool) -> Option<Duration> {
+        let mut next: Option<Duration> = None;
+
+        for (relay, test) in self.tests.iter_mut() {
+            let sent_at = match test {
+                RelayTest::Publishing { sent_at, .. } | RelayTest::Querying { sent_at, .. } => {
+                    *sent_at
+                }
+                RelayTest::Done(_) | RelayTest::TimedOut => continue,
+            };
+
+            let elapsed = sent_at.elapsed();
+            if elapsed < TIMEOUT {
+                let left = TIMEOUT - elapsed;
+                next = Some(next.map_or(left, |n| n.min(left)));
+                continue;
+            }
+
+            *test = match test {
+                RelayTest::Querying { result, sub_id, .. } => {
+                    pool.send_to(&ClientMessage::close(sub_id.clone()), relay);
+                    let mut result = result.clone();
+                    result.queryable = Some(false);
+                    RelayTest::Done(result)
+                }
+                _ => RelayTest::TimedOut,
+            };
+        }
+
+        next
+    }
+}
@@ -0,0 +1,67 @@
+//! How replies point at the thread they're in. NIP-10 marks which e tag is
+//! the root and which is the note being replied to, but some older clients
+//! only read e tags by position and mis-thread marked ones, so the old
+//! scheme can be turned back on.
+
+use notedeck::{DataPath, JsonSettings};
+
+const REPLY_TAGS_FILE: &str = "reply_tags.json";
+
+/// The ways a reply's e tags can say what it replies to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReplyTagScheme {
+    /// "root" and "reply" markers, with relay hints and the authors
+    #[default]
+    Markers,
+
+    /// The deprecated positional scheme: the first e tag is the root and
+    /// the last is the note being replied to, unmarked
+    Positional,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ReplyTagSettings {
+    pub positional: bool,
+}
+
+impl ReplyTagSettings {
+    pub fn scheme(&self) -> ReplyTagScheme {
+        if self.positional {
+            ReplyTagScheme::Positional
+        } else {
+            ReplyTagScheme::Markers
+        }
+    }
+}
+
+pub struct ReplyTags {
+    file: JsonSettings<ReplyTagSettings>,
+    settings: ReplyTagSettings,
+}
+
+impl ReplyTags {
+    pub fn new(path: &DataPath) -> Self {
+        let file = JsonSettings::new(path, REPLY_TAGS_FILE);
+        let settings = file.load();
+
+        Self { file, settings }
+    }
+
+    pub fn settings(&self) -> ReplyTagSettings {
+        self.settings
+    }
+
+    pub fn scheme(&self) -> ReplyTagScheme {
+        self.settings.scheme()
+    }
+
+    pub fn set_settings(&mut self, settings: ReplyTagSettings) {
+        if settings == self.settings {
+            return;
+        }
+        self.settings = settings;
+
+        self.file.save(&self.settings);
+    }
+}
@@ -21,6 +21,13 @@ pub enum Route {
     Reply(NoteId),
     Quote(NoteId),
     Relays,
+    Muted,
+    Drafts,
+    Scheduled,
+    RecurringZaps,
+    TipJar,
+    Settings,
+    WhatsNew,
     ComposeNote,
     AddColumn(AddColumnRoute),
     EditProfile(Pubkey),
@@ -30,6 +37,8 @@ pub enum Route {
     EditDeck(usize),
     Wallet(WalletType),
     CustomizeZapAmount(NoteZapTargetOwned),
+    /// The raw event behind a note
+    Inspect(NoteId),
 }
 
 impl Route {
@@ -73,6 +82,14 @@ impl Route {
         Route::Accounts(AccountsRoute::AddAccount)
     }
 
+    pub fn export_key() -> Self {
+        Route::Accounts(AccountsRoute::ExportKey)
+    }
+
+    pub fn app_lock() -> Self {
+        Route::Accounts(AccountsRoute::AppLock)
+    }
+
     pub fn serialize_tokens(&self, writer: &mut TokenWriter) {
         match self {
             Route::Timeline(timeline_kind) => timeline_kind.serialize_tokens(writer),
@@ -100,6 +117,27 @@ impl Route {
             Route::Relays => {
                 writer.write_token("relay");
             }
+            Route::Muted => {
+                writer.write_token("muted");
+            }
+            Route::Drafts => {
+                writer.write_token("drafts");
+            }
+            Route::Scheduled => {
+                writer.write_token("scheduled");
+            }
+            Route::RecurringZaps => {
+                writer.write_token("recurring_zaps");
+            }
+            Route::TipJar => {
+                writer.write_token("tip_jar");
+            }
+            Route::Settings => {
+                writer.write_token("settings");
+            }
+            Route::WhatsNew => {
+                writer.write_token("whats_new");
+            }
             Route::ComposeNote => {
                 writer.write_token("compose");
             }
@@ -114,6 +152,10 @@ impl Route {
                 writer.write_token("wallet");
             }
             Route::CustomizeZapAmount(_) => writer.write_token("customize zap amount"),
+            Route::Inspect(note_id) => {
+                writer.write_token("inspect");
+                writer.write_token(&note_id.hex());
+            }
         }
     }
 
@@ -159,6 +201,48 @@ impl Route {
                         Ok(Route::Relays)
                     })
                 },
+                |p| {
+                    p.parse_all(|p| {
+                        p.parse_token("muted")?;
+                        Ok(Route::Muted)
+                    })
+                },
+                |p| {
+                    p.parse_all(|p| {
+                        p.parse_token("drafts")?;
+                        Ok(Route::Drafts)
+                    })
+                },
+                |p| {
+                    p.parse_all(|p| {
+                        p.parse_token("scheduled")?;
+                        Ok(Route::Scheduled)
+                    })
+                },
+                |p| {
+                    p.parse_all(|p| {
+                        p.parse_token("recurring_zaps")?;
+                        Ok(Route::RecurringZaps)
+                    })
+                },
+                |p| {
+                    p.parse_all(|p| {
+                        p.parse_token("tip_jar")?;
+                        Ok(Route::TipJar)
+                    })
+                },
+                |p| {
+                    p.parse_all(|p| {
+                        p.parse_token("settings")?;
+                        Ok(Route::Settings)
+                    })
+                },
+                |p| {
+                    p.parse_all(|p| {
+                        p.parse_token("whats_new")?;
+                        Ok(Route::WhatsNew)
+                    })
+                },
                 |p| {
                     p.parse_all(|p| {
                         p.parse_token("quote")?;
@@ -171,6 +255,12 @@ impl Route {
                         Ok(Route::Reply(NoteId::new(tokenator::parse_hex_id(p)?)))
                     })
                 },
+                |p| {
+                    p.parse_all(|p| {
+                        p.parse_token("inspect")?;
+                        Ok(Route::Inspect(NoteId::new(tokenator::parse_hex_id(p)?)))
+                    })
+                },
                 |p| {
                     p.parse_all(|p| {
                         p.parse_token("compose")?;
@@ -206,9 +296,18 @@ impl Route {
             Route::Reply(_id) => ColumnTitle::simple("Reply"),
             Route::Quote(_id) => ColumnTitle::simple("Quote"),
             Route::Relays => ColumnTitle::simple("Relays"),
+            Route::Muted => ColumnTitle::simple("Muted"),
+            Route::Drafts => ColumnTitle::simple("Drafts"),
+            Route::Scheduled => ColumnTitle::simple("Scheduled posts"),
+            Route::RecurringZaps => ColumnTitle::simple("Recurring zaps"),
+            Route::TipJar => ColumnTitle::simple("Tip jar"),
+            Route::Settings => ColumnTitle::simple("Settings"),
+            Route::WhatsNew => ColumnTitle::simple("What's new"),
             Route::Accounts(amr) => match amr {
                 AccountsRoute::Accounts => ColumnTitle::simple("Accounts"),
                 AccountsRoute::AddAccount => ColumnTitle::simple("Add Account"),
+                AccountsRoute::ExportKey => ColumnTitle::simple("Export Key"),
+                AccountsRoute::AppLock => ColumnTitle::simple("App Lock"),
             },
             Route::ComposeNote => ColumnTitle::simple("Compose Note"),
             Route::AddColumn(c) => match c {
@@ -224,6 +323,7 @@ impl Route {
                     ColumnTitle::simple("Add External Notifications Column")
                 }
                 AddColumnRoute::Hashtag => ColumnTitle::simple("Add Hashtag Column"),
+                AddColumnRoute::Watchlist => ColumnTitle::simple("Add Watchlist Column"),
                 AddColumnRoute::UndecidedIndividual => {
                     ColumnTitle::simple("Subscribe to someone's notes")
                 }
@@ -238,6 +338,7 @@ impl Route {
             Route::Search => ColumnTitle::simple("Search"),
             Route::Wallet(_) => ColumnTitle::simple("Wallet"),
             Route::CustomizeZapAmount(_) => ColumnTitle::simple("Customize Zap Amount"),
+            Route::Inspect(_) => ColumnTitle::simple("Event"),
         }
     }
 }
@@ -341,13 +442,25 @@ impl fmt::Display for Route {
                 TimelineKind::Hashtag(ht) => write!(f, "Hashtag ({})", ht),
                 TimelineKind::Thread(_id) => write!(f, "Thread"),
                 TimelineKind::Profile(_id) => write!(f, "Profile"),
+                TimelineKind::ProfileMedia(_id) => write!(f, "Profile Media"),
+                TimelineKind::ProfileZaps(_id) => write!(f, "Profile Zaps"),
+                TimelineKind::Watchlist(pks) => write!(f, "Watchlist ({})", pks.len()),
             },
             Route::Reply(_id) => write!(f, "Reply"),
             Route::Quote(_id) => write!(f, "Quote"),
             Route::Relays => write!(f, "Relays"),
+            Route::Muted => write!(f, "Muted"),
+            Route::Drafts => write!(f, "Drafts"),
+            Route::Scheduled => write!(f, "Scheduled posts"),
+            Route::RecurringZaps => write!(f, "Recurring zaps"),
+            Route::TipJar => write!(f, "Tip jar"),
+            Route::Settings => write!(f, "Settings"),
+            Route::WhatsNew => write!(f, "What's new"),
             Route::Accounts(amr) => match amr {
                 AccountsRoute::Accounts => write!(f, "Accounts"),
                 AccountsRoute::AddAccount => write!(f, "Add Account"),
+                AccountsRoute::ExportKey => write!(f, "Export Key"),
+                AccountsRoute::AppLock => write!(f, "App Lock"),
             },
             Route::ComposeNote => write!(f, "Compose Note"),
             Route::AddColumn(_) => write!(f, "Add Column"),
@@ -358,6 +471,7 @@ impl fmt::Display for Route {
             Route::Search => write!(f, "Search"),
             Route::Wallet(_) => write!(f, "Wallet"),
             Route::CustomizeZapAmount(_) => write!(f, "Customize Zap Amount"),
+            Route::Inspect(_) => write!(f, "Event"),
         }
     }
 }
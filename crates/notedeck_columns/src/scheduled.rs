@@ -0,0 +1,219 @@
+//! Posts held back until a time picked in the composer. They go out while
+//! the app is running, so one that comes due while it's closed is posted
+//! the next time it starts.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use enostr::{Pubkey, RelayPool};
+use nostrdb::{Ndb, Transaction};
+use notedeck::{Accounts, DataPath, JsonSettings};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::{
+    draft::{Draft, PollDraft},
+    post::NewPost,
+    pow::ProofOfWork,
+    reply_tags::ReplyTagScheme,
+    storage::SerializableDraft,
+    ui::note::{NewPostAction, PostType},
+};
+
+const SCHEDULED_FILE: &str = "scheduled.json";
+
+pub struct ScheduledPost {
+    pub id: u64,
+
+    /// Unix time to publish at
+    pub publish_at: u64,
+    pub poster: Pubkey,
+    pub post_type: PostType,
+    pub draft: Draft,
+
+    /// Why it couldn't be posted when it came due. It's left alone until
+    /// it's edited or cancelled.
+    pub error: Option<String>,
+}
+
+impl ScheduledPost {
+    fn publish(
+        &self,
+        ndb: &Ndb,
+        pool: &mut RelayPool,
+        accounts: &mut Accounts,
+        pow: &mut ProofOfWork,
+        reply_tags: ReplyTagScheme,
+    ) -> Result<(), String> {
+        let account = accounts
+            .find_account(self.poster.bytes())
+            .ok_or("the account it was scheduled from is logged out")?;
+
+        let output = self.draft.buffer.output();
+        let post = NewPost::new(
+            output.text,
+            account.key.clone(),
+            self.draft.uploaded_media.clone(),
+            output.mentions,
+        )
+        .content_warning(self.draft.content_warning.clone())
+        .emojis(self.draft.emojis.clone())
+        .expiration(self.draft.expires_in.map(|secs| unix_now() + secs))
+        .poll(self.draft.poll.as_ref().map(PollDraft::to_poll))
+        .untagged(self.draft.untagged.clone());
+
+        let txn = Transaction::new(ndb).expect("txn");
+        NewPostAction::new(self.post_type.clone(), post)
+            .publish(ndb, &txn, pool, accounts, pow, reply_tags)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializableScheduled {
+    id: u64,
+    publish_at: u64,
+    poster: Pubkey,
+    #[serde(flatten)]
+    draft: SerializableDraft,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+pub struct ScheduledPosts {
+    file: JsonSettings<Vec<SerializableScheduled>>,
+
+    /// Soonest first
+    posts: Vec<ScheduledPost>,
+}
+
+impl ScheduledPosts {
+    pub fn new(path: &DataPath) -> Self {
+        let file = JsonSettings::new(path, SCHEDULED_FILE);
+        let posts = file
+            .load()
+            .into_iter()
+            .map(|saved| {
+                let (post_type, draft) = saved.draft.into_draft();
+                ScheduledPost {
+                    id: saved.id,
+                    publish_at: saved.publish_at,
+                    poster: saved.poster,
+                    post_type,
+                    draft,
+                    error: saved.error,
+                }
+            })
+            .collect();
+
+        let mut scheduled = Self { file, posts };
+        scheduled.sort();
+        scheduled
+    }
+
+    pub fn posts(&self) -> &[ScheduledPost] {
+        &self.posts
+    }
+
+    pub fn schedule(&mut self, poster: Pubkey, post_type: PostType, draft: Draft, publish_at: u64) {
+        let id = self.posts.iter().map(|p| p.id + 1).max().unwrap_or(0);
+        self.posts.push(ScheduledPost {
+            id,
+            publish_at,
+            poster,
+            post_type,
+            draft,
+            error: None,
+        });
+        self.sort();
+        self.save();
+    }
+
+    /// Take a post out of the schedule, to edit or throw away
+    pub fn cancel(&mut self, id: u64) -> Option<ScheduledPost> {
+        let index = self.posts.iter().position(|p| p.id == id)?;
+        let post = self.posts.remove(index);
+        self.save();
+        Some(post)
+    }
+
+    /// Publish whatever has come due. Returns how long until the next one
+    /// is, so the caller can wake up for it.
+    pub fn publish_due(
+        &mut self,
+        ndb: &Ndb,
+        pool: &mut RelayPool,
+        accounts: &mut Accounts,
+        pow: &mut ProofOfWork,
+        reply_tags: ReplyTagScheme,
+    ) -> Option<Duration> {
+        let now = unix_now();
+        let mut changed = false;
+
+        // a locked account's posts wait for the passphrase
+        let held = |post: &ScheduledPost, accounts: &Accounts| {
+            post.error.is_some()
+                || accounts
+                    .find_account(post.poster.bytes())
+                    .is_some_and(|acc| acc.is_locked())
+        };
+
+        let mut i = 0;
+        while i < self.posts.len() {
+            let post = &mut self.posts[i];
+            if post.publish_at > now || held(post, accounts) {
+                i += 1;
+                continue;
+            }
+
+            changed = true;
+            match post.publish(ndb, pool, accounts, pow, reply_tags) {
+                Ok(()) => {
+                    info!("published scheduled post {}", post.id);
+                    self.posts.remove(i);
+                }
+                Err(e) => {
+                    error!("could not publish scheduled post {}: {e}", post.id);
+                    post.error = Some(e);
+                    i += 1;
+                }
+            }
+        }
+
+        if changed {
+            self.save();
+        }
+
+        self.posts
+            .iter()
+            .filter(|p| p.publish_at > now)
+            .map(|p| Duration::from_secs(p.publish_at.saturating_sub(now)))
+            .min()
+    }
+
+    fn sort(&mut self) {
+        self.posts.sort_by_key(|p| (p.publish_at, p.id));
+    }
+
+    fn save(&self) {
+        let saved: Vec<SerializableScheduled> = self
+            .posts
+            .iter()
+            .map(|post| SerializableScheduled {
+                id: post.id,
+                publish_at: post.publish_at,
+                poster: post.poster,
+                draft: SerializableDraft::new(&post.post_type, &post.draft),
+                error: post.error.clone(),
+            })
+            .collect();
+
+        self.file.save(&saved);
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,226 @@
+//! Keyboard shortcuts. j/k move focus through the selected column's
+//! notes, and the note actions act on the focused one. Nothing here
+//! fires while a text field has the keyboard.
+
+use egui::{Key, Modifiers};
+use enostr::{NoteId, Pubkey};
+use nostrdb::Transaction;
+use notedeck::note::ZapTargetAmount;
+use notedeck::{AppContext, NoteAction, NoteZapTargetOwned, ZapAction};
+
+use crate::nav::{self, RenderNavAction};
+use crate::route::Route;
+use crate::timeline::TimelineKind;
+use crate::Damus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shortcut {
+    FocusDown,
+    FocusUp,
+    ColumnLeft,
+    ColumnRight,
+    /// Counting from 0
+    Column(usize),
+    Reply,
+    Quote,
+    Zap,
+    NewPost,
+    Search,
+    Back,
+}
+
+impl Shortcut {
+    pub fn from_key(key: Key, modifiers: Modifiers) -> Option<Self> {
+        // leave chords to egui and the OS. shift is fine, some layouts
+        // need it for '/'
+        if modifiers.ctrl || modifiers.alt || modifiers.command || modifiers.mac_cmd {
+            return None;
+        }
+
+        Some(match key {
+            Key::J | Key::ArrowDown => Shortcut::FocusDown,
+            Key::K | Key::ArrowUp => Shortcut::FocusUp,
+            Key::H | Key::ArrowLeft => Shortcut::ColumnLeft,
+            Key::L | Key::ArrowRight => Shortcut::ColumnRight,
+            Key::R => Shortcut::Reply,
+            Key::Q => Shortcut::Quote,
+            Key::Z => Shortcut::Zap,
+            Key::N => Shortcut::NewPost,
+            Key::Slash => Shortcut::Search,
+            Key::Escape => Shortcut::Back,
+            key => Shortcut::Column(column_number(key)?),
+        })
+    }
+}
+
+fn column_number(key: Key) -> Option<usize> {
+    let keys = [
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+        Key::Num7,
+        Key::Num8,
+        Key::Num9,
+    ];
+    keys.iter().position(|k| *k == key)
+}
+
+/// The shortcuts pressed this frame
+fn read(ctx: &egui::Context) -> Vec<Shortcut> {
+    if ctx.wants_keyboard_input() {
+        return vec![];
+    }
+
+    ctx.input(|i| {
+        i.events
+            .iter()
+            .filter_map(|event| match event {
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => Shortcut::from_key(*key, *modifiers),
+                _ => None,
+            })
+            .collect()
+    })
+}
+
+pub fn process_shortcuts(app: &mut Damus, ctx: &mut AppContext<'_>, ui: &mut egui::Ui) {
+    for shortcut in read(ui.ctx()) {
+        process(app, ctx, ui, shortcut);
+    }
+}
+
+fn process(app: &mut Damus, ctx: &mut AppContext<'_>, ui: &mut egui::Ui, shortcut: Shortcut) {
+    let columns = app.columns_mut(ctx.accounts);
+    if columns.columns().is_empty() {
+        return;
+    }
+
+    match shortcut {
+        Shortcut::FocusDown => move_focus(app, ctx, 1),
+        Shortcut::FocusUp => move_focus(app, ctx, -1),
+        Shortcut::ColumnLeft => {
+            columns.select_left();
+            move_focus(app, ctx, 0);
+        }
+        Shortcut::ColumnRight => {
+            columns.select_right();
+            move_focus(app, ctx, 0);
+        }
+        Shortcut::Column(ind) => {
+            columns.select_column(ind as i32);
+            move_focus(app, ctx, 0);
+        }
+        Shortcut::NewPost => route_to(app, ctx, Route::ComposeNote),
+        Shortcut::Search => route_to(app, ctx, Route::Search),
+        Shortcut::Back => {
+            // escape closes the popup first
+            if ui.ctx().memory(|m| m.any_popup_open()) {
+                return;
+            }
+            let col = columns.selected as usize;
+            nav::process_render_nav_action(app, ctx, ui, col, RenderNavAction::Back);
+        }
+        Shortcut::Reply | Shortcut::Quote | Shortcut::Zap => {
+            let col = columns.selected as usize;
+            let Some((note_id, author)) = focused_note(app, ctx) else {
+                return;
+            };
+
+            let action = match shortcut {
+                Shortcut::Reply => NoteAction::Reply(note_id),
+                Shortcut::Quote => NoteAction::Quote(note_id),
+                _ => NoteAction::Zap(ZapAction::Send(ZapTargetAmount {
+                    target: NoteZapTargetOwned {
+                        note_id,
+                        zap_recipient: author,
+                    },
+                    specified_msats: None,
+                })),
+            };
+            nav::process_render_nav_action(app, ctx, ui, col, RenderNavAction::NoteAction(action));
+        }
+    }
+}
+
+/// The timeline the selected column is showing
+fn selected_timeline(app: &mut Damus, ctx: &AppContext<'_>) -> Option<TimelineKind> {
+    let columns = app.columns_mut(ctx.accounts);
+    let col = columns.selected as usize;
+    columns.column(col).router().top().timeline_id().cloned()
+}
+
+fn move_focus(app: &mut Damus, ctx: &AppContext<'_>, step: isize) {
+    let Some(kind) = selected_timeline(app, ctx) else {
+        return;
+    };
+    let Some(timeline) = app.timeline_cache.timelines.get_mut(&kind) else {
+        return;
+    };
+
+    // threads run oldest first
+    let step = if matches!(kind, TimelineKind::Thread(_)) {
+        -step
+    } else {
+        step
+    };
+    timeline.current_view_mut().move_focus(step);
+}
+
+fn focused_note(app: &mut Damus, ctx: &AppContext<'_>) -> Option<(NoteId, Pubkey)> {
+    let kind = selected_timeline(app, ctx)?;
+    let key = app
+        .timeline_cache
+        .timelines
+        .get(&kind)?
+        .current_view()
+        .focused?;
+
+    let txn = Transaction::new(ctx.ndb).ok()?;
+    let note = ctx.ndb.get_note_by_key(&txn, key).ok()?;
+    Some((NoteId::new(*note.id()), Pubkey::new(*note.pubkey())))
+}
+
+fn route_to(app: &mut Damus, ctx: &AppContext<'_>, route: Route) {
+    let router = app.columns_mut(ctx.accounts).selected().router_mut();
+    if router.top() != &route {
+        router.route_to(route);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_map_to_shortcuts() {
+        let none = Modifiers::NONE;
+        assert_eq!(Shortcut::from_key(Key::J, none), Some(Shortcut::FocusDown));
+        assert_eq!(
+            Shortcut::from_key(Key::ArrowRight, none),
+            Some(Shortcut::ColumnRight)
+        );
+        assert_eq!(
+            Shortcut::from_key(Key::Num1, none),
+            Some(Shortcut::Column(0))
+        );
+        assert_eq!(
+            Shortcut::from_key(Key::Num9, none),
+            Some(Shortcut::Column(8))
+        );
+        assert_eq!(Shortcut::from_key(Key::Num0, none), None);
+        assert_eq!(
+            Shortcut::from_key(Key::Slash, Modifiers::SHIFT),
+            Some(Shortcut::Search)
+        );
+        // ctrl+r and friends aren't ours
+        assert_eq!(Shortcut::from_key(Key::R, Modifiers::CTRL), None);
+        assert_eq!(Shortcut::from_key(Key::Z, Modifiers::COMMAND), None);
+    }
+}
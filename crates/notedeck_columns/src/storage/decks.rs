@@ -1,23 +1,38 @@
 use std::{collections::HashMap, fmt, str::FromStr};
 
 use enostr::Pubkey;
-use nostrdb::{Ndb, Transaction};
+use nostrdb::{Ndb, NoteKey, Transaction};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error};
+use uuid::Uuid;
 
 use crate::{
-    column::{Columns, IntermediaryRoute},
+    column::{self, Column, Columns, IntermediaryRoute},
     decks::{Deck, Decks, DecksCache},
     route::Route,
-    timeline::{TimelineCache, TimelineKind},
+    timeline::{ScrollAnchor, TimelineCache, TimelineKind},
     Error,
 };
 
-use notedeck::{storage, DataPath, DataPathType, Directory};
+use notedeck::{storage, AppContext, DataPath, DataPathType, Directory};
 use tokenator::{ParseError, TokenParser, TokenWriter};
 
 pub static DECKS_CACHE_FILE: &str = "decks_cache.json";
 
+/// Columns bound to an account get an extra `account:<pubkey>` entry
+/// before their routes
+const COLUMN_ACCOUNT: &str = "account";
+
+/// Same for columns filtering by proof of work, with `min_pow:<bits>`
+const COLUMN_MIN_POW: &str = "min_pow";
+
+/// And columns that were resized, with `width:<points>`
+const COLUMN_WIDTH: &str = "width";
+
+/// And columns scrolled down their timeline, with
+/// `scroll:<note key>:<offset>`
+const COLUMN_SCROLL: &str = "scroll";
+
 pub fn load_decks_cache(
     path: &DataPath,
     ndb: &Ndb,
@@ -177,12 +192,14 @@ struct SerializableDeck {
 enum MetadataKeyword {
     Icon,
     Name,
+    Id,
 }
 
 impl MetadataKeyword {
     const MAPPING: &'static [(&'static str, MetadataKeyword)] = &[
         ("icon", MetadataKeyword::Icon),
         ("name", MetadataKeyword::Name),
+        ("id", MetadataKeyword::Id),
     ];
 }
 impl fmt::Display for MetadataKeyword {
@@ -261,6 +278,7 @@ impl SerializableDeck {
         let metadata = serialize_metadata(vec![
             MetadataPayload::new(MetadataKeyword::Icon, deck.icon.to_string()),
             MetadataPayload::new(MetadataKeyword::Name, deck.name.clone()),
+            MetadataPayload::new(MetadataKeyword::Id, deck.id().to_string()),
         ]);
 
         SerializableDeck { metadata, columns }
@@ -285,21 +303,47 @@ impl SerializableDeck {
             .find(|p| p.keyword == MetadataKeyword::Name)
             .map_or_else(|| "Deck", |f| &f.value)
             .to_string();
+        // decks saved before they had ids get a new one
+        let id = deserialized_metadata
+            .iter()
+            .find(|p| p.keyword == MetadataKeyword::Id)
+            .and_then(|p| Uuid::parse_str(&p.value).ok());
 
-        Ok(Deck::new_with_columns(
+        let deck = Deck::new_with_columns(
             icon.parse::<char>()
                 .map_err(|_| Error::Generic("could not convert String -> char".to_owned()))?,
             name,
             columns,
-        ))
+        );
+        Ok(match id {
+            Some(id) => deck.with_id(id),
+            None => deck,
+        })
     }
 }
 
 fn serialize_columns(columns: &Columns) -> Vec<Vec<String>> {
     let mut cols_serialized: Vec<Vec<String>> = Vec::new();
 
-    for column in columns.columns() {
+    for column in columns.columns().iter().filter(|col| !col.ephemeral) {
         let mut column_routes = Vec::new();
+        if let Some(account) = &column.account {
+            column_routes.push(format!("{COLUMN_ACCOUNT}:{}", account.hex()));
+        }
+        if column.min_pow > 0 {
+            column_routes.push(format!("{COLUMN_MIN_POW}:{}", column.min_pow));
+        }
+        if let Some(width) = column.width {
+            column_routes.push(format!("{COLUMN_WIDTH}:{}", width.round()));
+        }
+        if let Some(anchor) = column.scroll_anchor {
+            column_routes.push(format!(
+                "{COLUMN_SCROLL}:{}:{}",
+                anchor.note.as_u64(),
+                anchor.offset.round()
+            ));
+        }
+
         for route in column.router().routes() {
             let mut writer = TokenWriter::default();
             route.serialize_tokens(&mut writer);
@@ -311,40 +355,219 @@ fn serialize_columns(columns: &Columns) -> Vec<Vec<String>> {
     cols_serialized
 }
 
-fn deserialize_columns(
-    ndb: &Ndb,
-    timeline_cache: &mut TimelineCache,
-    deck_user: &Pubkey,
-    columns: Vec<Vec<String>>,
-) -> Columns {
-    let mut cols = Columns::new();
-    for column in columns {
-        let mut cur_routes = Vec::new();
+/// A saved column, before its timelines are loaded
+struct ParsedColumn {
+    account: Option<Pubkey>,
+    min_pow: u32,
+    width: Option<f32>,
+    scroll_anchor: Option<ScrollAnchor>,
+    routes: Vec<CleanIntermediaryRoute>,
+}
+
+impl ParsedColumn {
+    fn parse(column: Vec<String>, deck_user: &Pubkey) -> Self {
+        let mut parsed = ParsedColumn {
+            account: None,
+            min_pow: 0,
+            width: None,
+            scroll_anchor: None,
+            routes: Vec::new(),
+        };
 
         for route in column {
             let tokens: Vec<&str> = route.split(":").collect();
+            if let [COLUMN_ACCOUNT, pubkey] = tokens.as_slice() {
+                parsed.account = Pubkey::from_hex(pubkey).ok();
+                continue;
+            }
+            if let [COLUMN_MIN_POW, bits] = tokens.as_slice() {
+                parsed.min_pow = bits.parse().unwrap_or(0);
+                continue;
+            }
+            if let [COLUMN_WIDTH, points] = tokens.as_slice() {
+                parsed.width = points.parse().ok().map(column::clamp_width);
+                continue;
+            }
+            if let [COLUMN_SCROLL, key, offset] = tokens.as_slice() {
+                parsed.scroll_anchor =
+                    key.parse()
+                        .ok()
+                        .zip(offset.parse().ok())
+                        .map(|(key, offset)| ScrollAnchor {
+                            note: NoteKey::new(key),
+                            offset,
+                        });
+                continue;
+            }
+
             let mut parser = TokenParser::new(&tokens);
 
             match CleanIntermediaryRoute::parse(&mut parser, deck_user) {
-                Ok(route_intermediary) => {
-                    if let Some(ir) = route_intermediary.into_intermediary_route(ndb) {
-                        cur_routes.push(ir);
-                    }
-                }
+                Ok(route_intermediary) => parsed.routes.push(route_intermediary),
                 Err(err) => {
                     error!("could not turn tokens to RouteIntermediary: {:?}", err);
                 }
             }
         }
 
+        parsed
+    }
+
+    fn apply_to(&self, column: &mut Column, timeline_cache: &TimelineCache) {
+        column.account = self.account;
+        column.min_pow = self.min_pow;
+        column.width = self.width;
+        column.scroll_anchor = self.scroll_anchor;
+
+        // the timeline scrolls there once it's laid the note out
+        if let Some(Route::Timeline(kind)) = column.router().routes().first() {
+            if let Some(timeline) = timeline_cache.timelines.get(kind) {
+                timeline
+                    .current_view()
+                    .restore_scroll
+                    .set(self.scroll_anchor);
+            }
+        }
+    }
+}
+
+fn deserialize_columns(
+    ndb: &Ndb,
+    timeline_cache: &mut TimelineCache,
+    deck_user: &Pubkey,
+    columns: Vec<Vec<String>>,
+) -> Columns {
+    let mut cols = Columns::new();
+    for column in columns {
+        let mut parsed = ParsedColumn::parse(column, deck_user);
+        let cur_routes: Vec<IntermediaryRoute> = std::mem::take(&mut parsed.routes)
+            .into_iter()
+            .filter_map(|route| route.into_intermediary_route(ndb))
+            .collect();
+
         if !cur_routes.is_empty() {
             cols.insert_intermediary_routes(timeline_cache, cur_routes);
+            if let Some(column) = cols.columns_mut().last_mut() {
+                parsed.apply_to(column, timeline_cache);
+            }
         }
     }
 
     cols
 }
 
+/// Marks json as a shared deck, and which version of the format it's in
+const SHARED_DECK_VERSION: u32 = 1;
+
+/// A deck as it's shared with other machines. The columns are in the
+/// same format as in the decks cache.
+#[derive(Serialize, Deserialize)]
+struct SharedDeck {
+    notedeck_deck: u32,
+    name: String,
+    icon: char,
+    columns: Vec<Vec<String>>,
+}
+
+/// The deck's columns as json someone can paste into Import deck
+pub fn export_deck(deck: &Deck) -> String {
+    let columns = serialize_columns(deck.columns())
+        .into_iter()
+        .map(|column| {
+            // whoever imports it likely isn't logged in as the same
+            // accounts, and their note keys point at other notes
+            column
+                .into_iter()
+                .filter(|token| !token.starts_with(&format!("{COLUMN_ACCOUNT}:")))
+                .filter(|token| !token.starts_with(&format!("{COLUMN_SCROLL}:")))
+                .collect()
+        })
+        .collect();
+
+    let shared = SharedDeck {
+        notedeck_deck: SHARED_DECK_VERSION,
+        name: deck.name.clone(),
+        icon: deck.icon,
+        columns,
+    };
+    serde_json::to_string_pretty(&shared).expect("shared deck serializes")
+}
+
+/// Recreate a deck from `export_deck` json. Its timelines are opened
+/// right away, like columns that are added by hand.
+pub fn import_deck(
+    json: &str,
+    ctx: &mut AppContext,
+    timeline_cache: &mut TimelineCache,
+    deck_user: &Pubkey,
+) -> Result<Deck, String> {
+    let shared: SharedDeck = serde_json::from_str(json.trim())
+        .map_err(|_| "That isn't a deck copied from Notedeck".to_owned())?;
+    if shared.notedeck_deck > SHARED_DECK_VERSION {
+        return Err("This deck is from a newer Notedeck, update to import it".to_owned());
+    }
+
+    deck_from_columns(
+        shared.icon,
+        shared.name,
+        shared.columns,
+        ctx,
+        timeline_cache,
+        deck_user,
+    )
+}
+
+/// A deck with `columns` in the format they're saved in, its timelines
+/// opened right away
+pub fn deck_from_columns(
+    icon: char,
+    name: String,
+    saved_columns: Vec<Vec<String>>,
+    ctx: &mut AppContext,
+    timeline_cache: &mut TimelineCache,
+    deck_user: &Pubkey,
+) -> Result<Deck, String> {
+    let txn = Transaction::new(ctx.ndb).map_err(|e| e.to_string())?;
+    let mut columns = Columns::new();
+    for column in saved_columns {
+        let parsed = ParsedColumn::parse(column, deck_user);
+        let mut routes = Vec::new();
+
+        for route in &parsed.routes {
+            match route {
+                CleanIntermediaryRoute::ToTimeline(kind) => {
+                    if let Some(results) =
+                        timeline_cache.open(ctx.ndb, ctx.note_cache, &txn, ctx.pool, kind)
+                    {
+                        results.process(
+                            ctx.ndb,
+                            ctx.note_cache,
+                            &txn,
+                            timeline_cache,
+                            ctx.unknown_ids,
+                            &ctx.accounts.mutefun(),
+                        );
+                    }
+                    routes.push(Route::timeline(kind.clone()));
+                }
+                CleanIntermediaryRoute::ToRoute(route) => routes.push(route.clone()),
+            }
+        }
+
+        if !routes.is_empty() {
+            let mut column = Column::new(routes);
+            parsed.apply_to(&mut column, timeline_cache);
+            columns.add_column(column);
+        }
+    }
+
+    if columns.num_columns() == 0 {
+        return Err("That deck doesn't have any columns".to_owned());
+    }
+
+    Ok(Deck::new_with_columns(icon, name, columns))
+}
+
 enum CleanIntermediaryRoute {
     ToTimeline(TimelineKind),
     ToRoute(Route),
@@ -0,0 +1,183 @@
+use std::ops::Range;
+
+use enostr::{NoteId, Pubkey};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+
+use crate::{
+    draft::{Draft, Drafts, PollDraft},
+    emoji::CustomEmoji,
+    media_upload::Nip94Event,
+    post::PostBuffer,
+    ui::note::PostType,
+    undo_send::UndoSend,
+};
+
+use notedeck::{storage, DataPath, DataPathType, Directory};
+
+pub static DRAFTS_FILE: &str = "drafts.json";
+
+/// Load the drafts that were autosaved, so a crash or restart doesn't lose
+/// what was being written
+pub fn load_drafts(path: &DataPath) -> Drafts {
+    let mut drafts = Drafts::default();
+
+    let data_path = path.path(DataPathType::Setting);
+    let Ok(json) = Directory::new(data_path).get_file(DRAFTS_FILE.to_owned()) else {
+        return drafts;
+    };
+
+    let saved = match serde_json::from_str::<Vec<SerializableDraft>>(&json) {
+        Ok(saved) => saved,
+        Err(e) => {
+            error!("Could not parse drafts from {DRAFTS_FILE}: {e}");
+            return drafts;
+        }
+    };
+
+    for saved in saved {
+        let (post_type, draft) = saved.into_draft();
+        *drafts.get_from_post_type(&post_type) = draft;
+    }
+    drafts.saved = Some(json);
+
+    drafts
+}
+
+/// Write the drafts to disk if they changed since they were last written.
+/// Called every frame. Uploads that haven't finished aren't saved.
+///
+/// Posts held back by undo send are saved too, unless something new has
+/// been started in their composer, so quitting before they go out
+/// doesn't lose them.
+pub fn autosave_drafts(path: &DataPath, drafts: &mut Drafts, undo_send: &UndoSend) {
+    let mut pending = drafts.saved();
+    for (post_type, draft) in undo_send.held_drafts() {
+        if !pending.iter().any(|(saved, _)| saved == post_type) {
+            pending.push((post_type.clone(), draft));
+        }
+    }
+
+    let saved: Vec<SerializableDraft> = pending
+        .into_iter()
+        .map(|(post_type, draft)| SerializableDraft::new(&post_type, draft))
+        .collect();
+
+    let json = match serde_json::to_string(&saved) {
+        Ok(json) => json,
+        Err(e) => {
+            error!("Could not serialize drafts: {e}");
+            return;
+        }
+    };
+
+    if drafts.saved.as_ref() == Some(&json) {
+        return;
+    }
+
+    let data_path = path.path(DataPathType::Setting);
+    if let Err(e) = storage::write_file(&data_path, DRAFTS_FILE.to_owned(), &json) {
+        error!("Could not write drafts to {DRAFTS_FILE}: {e}");
+    } else {
+        debug!("saved {} drafts", saved.len());
+    }
+
+    // even on failure, so we don't retry every frame
+    drafts.saved = Some(json);
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum SerializablePostType {
+    New,
+    Reply { id: NoteId },
+    Quote { id: NoteId },
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializableMention {
+    start: usize,
+    end: usize,
+    pubkey: Pubkey,
+}
+
+/// A draft as it's kept on disk, also used for scheduled posts
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SerializableDraft {
+    post_type: SerializablePostType,
+    text: String,
+    #[serde(default)]
+    mentions: Vec<SerializableMention>,
+    #[serde(default)]
+    media: Vec<Nip94Event>,
+    #[serde(default)]
+    content_warning: Option<String>,
+    #[serde(default)]
+    expires_in: Option<u64>,
+    #[serde(default)]
+    emojis: Vec<CustomEmoji>,
+    #[serde(default)]
+    poll: Option<PollDraft>,
+    #[serde(default)]
+    untagged: Vec<Pubkey>,
+}
+
+impl SerializableDraft {
+    pub(crate) fn new(post_type: &PostType, draft: &Draft) -> Self {
+        let post_type = match post_type {
+            PostType::New => SerializablePostType::New,
+            PostType::Reply(id) => SerializablePostType::Reply { id: *id },
+            PostType::Quote(id) => SerializablePostType::Quote { id: *id },
+        };
+
+        let mentions = draft
+            .buffer
+            .finalized_mentions()
+            .into_iter()
+            .map(|(range, pubkey)| SerializableMention {
+                start: range.start,
+                end: range.end,
+                pubkey,
+            })
+            .collect();
+
+        SerializableDraft {
+            post_type,
+            text: draft.buffer.text_buffer.clone(),
+            mentions,
+            media: draft.uploaded_media.clone(),
+            content_warning: draft.content_warning.clone(),
+            expires_in: draft.expires_in,
+            emojis: draft.emojis.clone(),
+            poll: draft.poll.clone(),
+            untagged: draft.untagged.clone(),
+        }
+    }
+
+    pub(crate) fn into_draft(self) -> (PostType, Draft) {
+        let post_type = match self.post_type {
+            SerializablePostType::New => PostType::New,
+            SerializablePostType::Reply { id } => PostType::Reply(id),
+            SerializablePostType::Quote { id } => PostType::Quote(id),
+        };
+
+        let mentions: Vec<(Range<usize>, Pubkey)> = self
+            .mentions
+            .into_iter()
+            .map(|m| (m.start..m.end, m.pubkey))
+            .collect();
+
+        let draft = Draft {
+            buffer: PostBuffer::restore(self.text, mentions),
+            uploaded_media: self.media,
+            content_warning: self.content_warning,
+            expires_in: self.expires_in,
+            emojis: self.emojis,
+            poll: self.poll,
+            untagged: self.untagged,
+            ..Default::default()
+        };
+
+        (post_type, draft)
+    }
+}
@@ -1,3 +1,9 @@
 mod decks;
+mod drafts;
 
-pub use decks::{load_decks_cache, save_decks_cache, DECKS_CACHE_FILE};
+pub use decks::{
+    deck_from_columns, export_deck, import_deck, load_decks_cache, save_decks_cache,
+    DECKS_CACHE_FILE,
+};
+pub(crate) use drafts::SerializableDraft;
+pub use drafts::{autosave_drafts, load_drafts, DRAFTS_FILE};
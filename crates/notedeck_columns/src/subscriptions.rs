@@ -1,3 +1,4 @@
+use crate::negentropy::NegentropySyncs;
 use crate::timeline::TimelineKind;
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -13,6 +14,13 @@ pub enum SubKind {
 
     Timeline(TimelineKind),
 
+    /// A page of older notes for a timeline scrolled to the bottom. Closed
+    /// after EOSE like a one shot.
+    Backfill(TimelineKind),
+
+    /// Notes missing from a gap in a timeline, closed after EOSE
+    FillGap(TimelineKind),
+
     /// We are fetching a contact list so that we can use it for our follows
     /// Filter.
     // TODO: generalize this to any list?
@@ -25,6 +33,9 @@ pub enum SubKind {
 #[derive(Default)]
 pub struct Subscriptions {
     pub subs: HashMap<String, SubKind>,
+
+    /// NIP-77 reconciliations used to backfill timelines
+    pub negentropy: NegentropySyncs,
 }
 
 pub fn new_sub_id() -> String {
@@ -11,11 +11,18 @@ use notedeck::{filter, FilterState, NoteCache, NoteRef};
 use enostr::RelayPool;
 use nostrdb::{Filter, Ndb, Transaction};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
+/// How often loaded timelines are swept for expired notes
+const EXPIRY_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(Default)]
 pub struct TimelineCache {
     pub timelines: HashMap<TimelineKind, Timeline>,
+
+    /// When expired notes were last swept out
+    expiry_swept: Option<Instant>,
 }
 
 pub enum Vitality<'a, M> {
@@ -63,6 +70,17 @@ impl TimelineCache {
                     id
                 );
                 self.timelines.remove(id);
+
+                // the profile's other tabs were opened along with it
+                if let TimelineKind::Profile(pk) = id {
+                    for tab in TimelineKind::profile_tabs(pk) {
+                        if self.timelines.contains_key(&tab) {
+                            if let Err(err) = self.pop(&tab, ndb, pool) {
+                                error!("popping {tab:?}: {err}");
+                            }
+                        }
+                    }
+                }
             }
 
             Ok(())
@@ -99,6 +117,40 @@ impl TimelineCache {
         self.timelines.insert(id, timeline);
     }
 
+    /// Drop notes that have expired since they were loaded, every so
+    /// often. The views hide them as soon as they expire, this keeps
+    /// them from piling up in long running sessions.
+    ///
+    /// The notes themselves stay in nostrdb, which can't delete a note.
+    /// Notes that arrive already expired are never ingested, and the
+    /// rest are filtered out wherever we load notes: timeline inserts,
+    /// search, notifications, and `NoteView` for everything looked up
+    /// by id.
+    pub fn sweep_expired(&mut self, ndb: &Ndb) {
+        let now = Instant::now();
+        if self
+            .expiry_swept
+            .is_some_and(|swept| now.duration_since(swept) < EXPIRY_SWEEP_INTERVAL)
+        {
+            return;
+        }
+        self.expiry_swept = Some(now);
+
+        let Ok(txn) = Transaction::new(ndb) else {
+            return;
+        };
+        let removed: usize = self
+            .timelines
+            .values_mut()
+            .flat_map(|timeline| timeline.views.iter_mut())
+            .map(|view| view.remove_expired(ndb, &txn))
+            .sum();
+
+        if removed > 0 {
+            info!("swept {removed} expired notes from timelines");
+        }
+    }
+
     /// Get and/or update the notes associated with this timeline
     pub fn notes<'a>(
         &'a mut self,
@@ -169,9 +221,9 @@ impl TimelineCache {
                     Some(TimelineOpenResult::new_notes(new_notes, id.clone()))
                 };
 
-                // we can't insert and update the VirtualList now, because we
-                // are already borrowing it mutably. Let's pass it as a
-                // result instead
+                // we can't insert into the timeline now, because we are
+                // already borrowing it mutably. Let's pass it as a result
+                // instead
                 //
                 // holder.get_view().insert(&notes); <-- no
                 (open_result, timeline)
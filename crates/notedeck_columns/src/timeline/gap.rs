@@ -0,0 +1,222 @@
+//! Gaps left in a timeline by time spent offline. Once back, relays are
+//! asked for what's new since the newest note we have, but they only send
+//! so many. When more than that came out, the oldest new notes we get are
+//! still well past the newest we had, and everything in between is missing.
+//! The gap is shown where it is, and filled on request a REQ's worth at a
+//! time, from the newest missing notes down.
+
+use std::time::{Duration, Instant};
+
+use notedeck::NoteRef;
+
+/// After a relay finishes filling, how long its notes get to land before
+/// we look at whether they closed the gap
+const FILL_SETTLE: Duration = Duration::from_secs(1);
+
+/// Relays were asked for notes since `newest`, up to `limit` of them
+#[derive(Debug, Clone, Copy)]
+struct Watch {
+    newest: u64,
+
+    /// When they were asked. Anything newer came in live, not as part of
+    /// the reply.
+    asked_at: u64,
+    limit: u64,
+}
+
+#[derive(Debug)]
+pub struct Gap {
+    /// Notes are missing from after this...
+    pub since: u64,
+
+    /// ...up to this, which is when the oldest note above the gap is from
+    pub until: u64,
+
+    limit: u64,
+    wanted: bool,
+
+    /// The relay subscription filling it
+    loading: Option<String>,
+
+    /// When a relay finished filling it
+    filled_at: Option<Instant>,
+}
+
+impl Gap {
+    pub fn is_filling(&self) -> bool {
+        self.loading.is_some() || self.filled_at.is_some()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Gaps {
+    watch: Option<Watch>,
+    gap: Option<Gap>,
+}
+
+impl Gaps {
+    /// Relays are being asked for notes since `newest`, the newest we
+    /// have, and send at most `limit`. The first ask is the one that
+    /// matters, until its gap is filled.
+    pub fn watch(&mut self, newest: u64, asked_at: u64, limit: u64) {
+        if self.watch.is_some() || self.gap.is_some() {
+            return;
+        }
+        self.watch = Some(Watch {
+            newest,
+            asked_at,
+            limit,
+        });
+    }
+
+    pub fn gap(&self) -> Option<&Gap> {
+        self.gap.as_ref()
+    }
+
+    /// Look at the timeline's `notes`, newest first, for a gap opening up
+    /// or for one that was filled
+    pub fn check(&mut self, notes: &[NoteRef]) {
+        if let Some(watch) = self.watch {
+            if let Some(until) = truncated(notes, watch.newest, watch.asked_at, watch.limit) {
+                self.watch = None;
+                self.gap = Some(Gap {
+                    since: watch.newest,
+                    until,
+                    limit: watch.limit,
+                    wanted: false,
+                    loading: None,
+                    filled_at: None,
+                });
+            }
+        }
+
+        let Some(gap) = &mut self.gap else {
+            return;
+        };
+        if !gap.filled_at.is_some_and(|at| at.elapsed() >= FILL_SETTLE) {
+            return;
+        }
+
+        // a full REQ's worth came in, so there could be more past it
+        match truncated(notes, gap.since, gap.until.saturating_sub(1), gap.limit) {
+            Some(until) => {
+                gap.until = until;
+                gap.filled_at = None;
+            }
+            None => self.gap = None,
+        }
+    }
+
+    /// The user asked for the gap to be filled. Returns whether it will be.
+    pub fn want_fill(&mut self) -> bool {
+        match &mut self.gap {
+            Some(gap) if !gap.is_filling() => {
+                gap.wanted = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The gap to fill now, as `(since, until, limit)`
+    pub fn take_wanted(&mut self) -> Option<(u64, u64, u64)> {
+        let gap = self.gap.as_mut()?;
+        if !std::mem::take(&mut gap.wanted) {
+            return None;
+        }
+        Some((gap.since, gap.until, gap.limit))
+    }
+
+    /// Relays were asked to fill the gap under `subid`
+    pub fn filling(&mut self, subid: String) {
+        if let Some(gap) = &mut self.gap {
+            gap.loading = Some(subid);
+        }
+    }
+
+    /// A relay is done filling the gap under `subid`
+    pub fn filled(&mut self, subid: &str) {
+        if let Some(gap) = &mut self.gap {
+            if gap.loading.as_deref() == Some(subid) {
+                gap.loading = None;
+                gap.filled_at = Some(Instant::now());
+            }
+        }
+    }
+}
+
+/// If there are at least `limit` notes from after `since` up to `until`,
+/// the reply that brought them was probably cut short. Returns when the
+/// oldest of them is from, where the notes that didn't make it start.
+fn truncated(notes: &[NoteRef], since: u64, until: u64, limit: u64) -> Option<u64> {
+    let mut count = 0;
+    let mut oldest = None;
+    for note in notes.iter().filter(|note| note.created_at <= until) {
+        if note.created_at <= since {
+            break;
+        }
+        count += 1;
+        oldest = Some(note.created_at);
+    }
+
+    if count >= limit {
+        oldest
+    } else {
+        None
+    }
+}
+
+/// The note in `notes` the gap opens up under, if the gap falls between
+/// two of them
+pub fn note_above(notes: &[NoteRef], since: u64) -> Option<&NoteRef> {
+    let below = notes.partition_point(|note| note.created_at > since);
+    if below == 0 || below == notes.len() {
+        return None;
+    }
+    notes.get(below - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostrdb::NoteKey;
+
+    fn notes(created_at: &[u64]) -> Vec<NoteRef> {
+        created_at
+            .iter()
+            .enumerate()
+            .map(|(i, created_at)| NoteRef {
+                key: NoteKey::new(i as u64 + 1),
+                created_at: *created_at,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_full_reply_past_the_cache_leaves_a_gap() {
+        let mut gaps = Gaps::default();
+        gaps.watch(100, 500, 3);
+
+        // a live note, and two from the reply: the relay had everything
+        gaps.check(&notes(&[600, 400, 300, 100, 90]));
+        assert!(gaps.gap().is_none());
+
+        // the reply filled up before getting back to what we had
+        let timeline = notes(&[600, 400, 300, 200, 100, 90]);
+        gaps.check(&timeline);
+        let gap = gaps.gap().unwrap();
+        assert_eq!((gap.since, gap.until), (100, 200));
+        assert_eq!(note_above(&timeline, gap.since).unwrap().created_at, 200);
+
+        assert!(gaps.want_fill());
+        assert_eq!(gaps.take_wanted(), Some((100, 200, 3)));
+        gaps.filling("fill".to_owned());
+        assert!(!gaps.want_fill());
+
+        // the fill only brought one, so that's all there was
+        gaps.filled("fill");
+        gaps.gap.as_mut().unwrap().filled_at = Instant::now().checked_sub(FILL_SETTLE);
+        gaps.check(&notes(&[600, 400, 300, 200, 150, 100, 90]));
+        assert!(gaps.gap().is_none());
+    }
+}
@@ -0,0 +1,172 @@
+//! Reposts of the same note, and reactions to it, folded into one entry as
+//! they're inserted. The newest of them is the one kept in the timeline,
+//! standing in for the rest, so a note reposted again comes back up to the
+//! top instead of showing up twice.
+
+use std::collections::{HashMap, HashSet};
+
+use nostrdb::{Note, NoteKey};
+use notedeck::NoteRef;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GroupKind {
+    Repost,
+    Reaction,
+}
+
+/// What a group's notes have in common
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GroupTarget {
+    pub kind: GroupKind,
+    pub note_id: [u8; 32],
+}
+
+impl GroupTarget {
+    /// The note `note` reposts or reacts to, None for anything else
+    pub fn of(note: &Note) -> Option<Self> {
+        let kind = match note.kind() {
+            6 => GroupKind::Repost,
+            7 => GroupKind::Reaction,
+            _ => return None,
+        };
+
+        let mut e_tags = note
+            .tags()
+            .into_iter()
+            .filter(|tag| tag.count() >= 2 && tag.get_unchecked(0).variant().str() == Some("e"))
+            .filter_map(|tag| tag.get_unchecked(1).variant().id().copied());
+
+        // a repost's first e tag is what it reposts, NIP-25 puts the note
+        // being reacted to last
+        let note_id = match kind {
+            GroupKind::Repost => e_tags.next(),
+            GroupKind::Reaction => e_tags.last(),
+        }?;
+
+        Some(GroupTarget { kind, note_id })
+    }
+}
+
+/// Which group a note being inserted goes in, and who it's from
+pub type GroupMembers = HashMap<NoteKey, (GroupTarget, [u8; 32])>;
+
+#[derive(Debug)]
+pub struct NoteGroup {
+    pub target: GroupTarget,
+
+    /// Who reposted or reacted, newest first, with when
+    authors: Vec<([u8; 32], u64)>,
+}
+
+impl NoteGroup {
+    pub fn authors(&self) -> impl Iterator<Item = &[u8; 32]> {
+        self.authors.iter().map(|(author, _)| author)
+    }
+
+    pub fn len(&self) -> usize {
+        self.authors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.authors.is_empty()
+    }
+
+    fn add(&mut self, author: [u8; 32], created_at: u64) {
+        // reposting twice doesn't count twice, the newest one does
+        if let Some(ind) = self.authors.iter().position(|(a, _)| *a == author) {
+            if self.authors[ind].1 >= created_at {
+                return;
+            }
+            self.authors.remove(ind);
+        }
+        let ind = self.authors.partition_point(|(_, at)| *at >= created_at);
+        self.authors.insert(ind, (author, created_at));
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NoteGroups {
+    /// By the note shown for the group
+    groups: HashMap<NoteKey, NoteGroup>,
+
+    /// The note shown for each group
+    shown: HashMap<GroupTarget, NoteRef>,
+}
+
+impl NoteGroups {
+    /// The group `note` is shown for, if it stands in for any others
+    pub fn get(&self, note: NoteKey) -> Option<&NoteGroup> {
+        self.groups.get(&note).filter(|group| group.len() > 1)
+    }
+
+    /// Fold `new_refs` into the groups they're `members` of. Returns the
+    /// ones to insert, and the notes already in the timeline that a newer
+    /// one took the place of.
+    pub fn fold(
+        &mut self,
+        new_refs: &[NoteRef],
+        members: &GroupMembers,
+    ) -> (Vec<NoteRef>, Vec<NoteKey>) {
+        let batch: HashSet<NoteKey> = new_refs.iter().map(|note| note.key).collect();
+        let mut dropped = HashSet::new();
+        let mut replaced = Vec::new();
+
+        // oldest first, so the newest of each group is the one left shown
+        let mut sorted = new_refs.to_vec();
+        sorted.sort_by_key(|note| note.created_at);
+
+        for note in sorted {
+            let Some((target, author)) = members.get(&note.key).copied() else {
+                continue;
+            };
+
+            let Some(shown) = self.shown.get(&target).copied() else {
+                let mut group = NoteGroup {
+                    target,
+                    authors: Vec::new(),
+                };
+                group.add(author, note.created_at);
+                self.groups.insert(note.key, group);
+                self.shown.insert(target, note);
+                continue;
+            };
+
+            if shown.key == note.key {
+                continue;
+            }
+
+            if note.created_at <= shown.created_at {
+                if let Some(group) = self.groups.get_mut(&shown.key) {
+                    group.add(author, note.created_at);
+                }
+                dropped.insert(note.key);
+                continue;
+            }
+
+            let Some(mut group) = self.groups.remove(&shown.key) else {
+                continue;
+            };
+            group.add(author, note.created_at);
+            self.groups.insert(note.key, group);
+            self.shown.insert(target, note);
+
+            if batch.contains(&shown.key) {
+                dropped.insert(shown.key);
+            } else {
+                replaced.push(shown.key);
+            }
+        }
+
+        let keep = new_refs
+            .iter()
+            .filter(|note| !dropped.contains(&note.key))
+            .copied()
+            .collect();
+        (keep, replaced)
+    }
+
+    pub fn clear(&mut self) {
+        self.groups.clear();
+        self.shown.clear();
+    }
+}
@@ -0,0 +1,298 @@
+//! Note heights for laying out long timelines. Only the notes near the
+//! viewport get laid out each frame; everything else takes up the height it
+//! was measured at last time it was on screen, or an estimate until it has
+//! been. Measurements are kept by note so they survive notes arriving and
+//! leaving around them.
+//!
+//! The heights sit in a Fenwick tree, so finding the note at a scroll
+//! offset, or the offset of a note, is O(log n) however long the timeline
+//! gets. Only changes to the notes themselves cost a full pass.
+
+use std::collections::HashMap;
+
+use nostrdb::NoteKey;
+use notedeck::NoteRef;
+
+/// What a note is assumed to take up before anything's been measured
+const DEFAULT_ESTIMATE: f32 = 120.0;
+
+/// Notes reflow when the column width changes, more than this and the
+/// measurements are thrown out
+const WIDTH_SLOP: f32 = 1.0;
+
+#[derive(Debug)]
+pub struct NoteHeights {
+    /// Heights notes were laid out at, by note
+    measured: HashMap<NoteKey, f32>,
+    measured_total: f64,
+
+    /// The estimate to use while nothing's been measured, carried over
+    /// from before a resize
+    fallback: f32,
+
+    /// The width the measurements were taken at
+    width: f32,
+
+    /// Where each note is in the timeline
+    index: HashMap<NoteKey, usize>,
+
+    /// The height of each note in timeline order, measured or estimated
+    heights: Vec<f32>,
+
+    /// Fenwick tree over `heights`, 1-based
+    tree: Vec<f64>,
+}
+
+impl Default for NoteHeights {
+    fn default() -> Self {
+        Self {
+            measured: HashMap::new(),
+            measured_total: 0.0,
+            fallback: DEFAULT_ESTIMATE,
+            width: 0.0,
+            index: HashMap::new(),
+            heights: Vec::new(),
+            tree: vec![0.0],
+        }
+    }
+}
+
+impl NoteHeights {
+    pub fn len(&self) -> usize {
+        self.heights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heights.is_empty()
+    }
+
+    /// The height of all the notes together
+    pub fn total(&self) -> f32 {
+        self.prefix(self.len()) as f32
+    }
+
+    /// What an unmeasured note is assumed to take up: the average of the
+    /// ones measured so far
+    pub fn estimate(&self) -> f32 {
+        if self.measured.is_empty() {
+            self.fallback
+        } else {
+            (self.measured_total / self.measured.len() as f64) as f32
+        }
+    }
+
+    /// Catch up with the timeline's notes after they changed. Measurements
+    /// of notes that are gone are dropped.
+    pub fn sync(&mut self, notes: &[NoteRef]) {
+        let estimate = self.estimate();
+        let mut measured = HashMap::with_capacity(self.measured.len().min(notes.len()));
+        self.index.clear();
+        self.heights.clear();
+        self.heights.reserve(notes.len());
+
+        for (ind, note) in notes.iter().enumerate() {
+            self.index.insert(note.key, ind);
+            let height = match self.measured.get(&note.key) {
+                Some(height) => {
+                    measured.insert(note.key, *height);
+                    *height
+                }
+                None => estimate,
+            };
+            self.heights.push(height);
+        }
+
+        self.measured_total = measured.values().map(|h| *h as f64).sum();
+        self.measured = measured;
+        self.build_tree();
+    }
+
+    /// Check the heights still fit the notes and the width they're laid out
+    /// at, and start over if they don't
+    pub fn fit(&mut self, width: f32, notes: &[NoteRef]) {
+        if (width - self.width).abs() > WIDTH_SLOP {
+            self.fallback = self.estimate();
+            self.measured.clear();
+            self.measured_total = 0.0;
+            self.width = width;
+            self.sync(notes);
+        } else if self.len() != notes.len() {
+            self.sync(notes);
+        }
+    }
+
+    /// Record the height `key` was laid out at. Returns how much it grew
+    /// by, so notes after it moved down that much.
+    pub fn measure(&mut self, key: NoteKey, height: f32) -> f32 {
+        let old_measured = self.measured.insert(key, height);
+        self.measured_total += height as f64 - old_measured.unwrap_or(0.0) as f64;
+
+        let Some(&ind) = self.index.get(&key) else {
+            return 0.0;
+        };
+        let delta = height - self.heights[ind];
+        if delta != 0.0 {
+            self.heights[ind] = height;
+            self.add(ind, delta as f64);
+        }
+        delta
+    }
+
+    /// Where `key` is in the timeline
+    pub fn index_of(&self, key: NoteKey) -> Option<usize> {
+        self.index.get(&key).copied()
+    }
+
+    /// How far down the list the note at `ind` starts. Reversed lists show
+    /// the last note first.
+    pub fn top_of(&self, ind: usize, reversed: bool) -> f32 {
+        if reversed {
+            (self.prefix(self.len()) - self.prefix(ind + 1)) as f32
+        } else {
+            self.prefix(ind) as f32
+        }
+    }
+
+    /// How far down the list the note at `ind` ends
+    pub fn bottom_of(&self, ind: usize, reversed: bool) -> f32 {
+        self.top_of(ind, reversed) + self.heights[ind]
+    }
+
+    /// The note `y` down the list falls on, clamped to the first and last
+    /// notes. None for an empty list.
+    pub fn index_at(&self, y: f32, reversed: bool) -> Option<usize> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+
+        if reversed {
+            let from_end = self.prefix(len) - y as f64;
+            Some(self.count_below(from_end, true).min(len - 1))
+        } else {
+            Some(self.count_below(y as f64, false).min(len - 1))
+        }
+    }
+
+    fn build_tree(&mut self) {
+        let len = self.heights.len();
+        self.tree.clear();
+        self.tree.resize(len + 1, 0.0);
+        for (ind, height) in self.heights.iter().enumerate() {
+            self.tree[ind + 1] += *height as f64;
+            let parent = (ind + 1) + lowest_bit(ind + 1);
+            if parent <= len {
+                self.tree[parent] += self.tree[ind + 1];
+            }
+        }
+    }
+
+    fn add(&mut self, ind: usize, delta: f64) {
+        let mut i = ind + 1;
+        while i < self.tree.len() {
+            self.tree[i] += delta;
+            i += lowest_bit(i);
+        }
+    }
+
+    /// The sum of the first `count` heights
+    fn prefix(&self, count: usize) -> f64 {
+        let mut sum = 0.0;
+        let mut i = count;
+        while i > 0 {
+            sum += self.tree[i];
+            i -= lowest_bit(i);
+        }
+        sum
+    }
+
+    /// The most notes from the start that fit above `y`. With `strict`,
+    /// ones ending right at `y` don't count.
+    fn count_below(&self, y: f64, strict: bool) -> usize {
+        let len = self.len();
+        let mut pos = 0;
+        let mut left = y;
+        let mut step = len.next_power_of_two();
+        while step > 0 {
+            let next = pos + step;
+            if next <= len {
+                let fits = if strict {
+                    self.tree[next] < left
+                } else {
+                    self.tree[next] <= left
+                };
+                if fits {
+                    pos = next;
+                    left -= self.tree[next];
+                }
+            }
+            step /= 2;
+        }
+        pos
+    }
+}
+
+fn lowest_bit(i: usize) -> usize {
+    i & i.wrapping_neg()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes(n: u64) -> Vec<NoteRef> {
+        (0..n)
+            .map(|i| NoteRef {
+                key: NoteKey::new(i + 1),
+                created_at: 1000 - i,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn notes_are_found_by_offset_either_way_up() {
+        let notes = notes(4);
+        let mut heights = NoteHeights::default();
+        heights.fit(300.0, &notes);
+        for (note, height) in notes.iter().zip([10.0, 20.0, 30.0, 40.0]) {
+            heights.measure(note.key, height);
+        }
+        assert_eq!(heights.total(), 100.0);
+
+        assert_eq!(heights.top_of(2, false), 30.0);
+        assert_eq!(heights.index_at(0.0, false), Some(0));
+        assert_eq!(heights.index_at(29.9, false), Some(1));
+        assert_eq!(heights.index_at(30.0, false), Some(2));
+        assert_eq!(heights.index_at(500.0, false), Some(3));
+
+        // the last note is on top
+        assert_eq!(heights.top_of(3, true), 0.0);
+        assert_eq!(heights.bottom_of(2, true), 70.0);
+        assert_eq!(heights.index_at(0.0, true), Some(3));
+        assert_eq!(heights.index_at(39.9, true), Some(3));
+        assert_eq!(heights.index_at(40.0, true), Some(2));
+        assert_eq!(heights.index_at(500.0, true), Some(0));
+    }
+
+    #[test]
+    fn measurements_follow_their_notes() {
+        let all = notes(3);
+        let mut heights = NoteHeights::default();
+        heights.fit(300.0, &all[1..]);
+        heights.measure(all[1].key, 50.0);
+        heights.measure(all[2].key, 70.0);
+
+        // a newer note lands on top, estimated at the average so far
+        heights.sync(&all);
+        assert_eq!(heights.index_of(all[1].key), Some(1));
+        assert_eq!(heights.top_of(1, false), 60.0);
+        assert_eq!(heights.total(), 180.0);
+
+        assert_eq!(heights.measure(all[0].key, 20.0), -40.0);
+        assert_eq!(heights.top_of(2, false), 70.0);
+
+        // resizing starts over from the average
+        heights.fit(200.0, &all);
+        assert!((heights.total() - 140.0).abs() < 0.01);
+    }
+}
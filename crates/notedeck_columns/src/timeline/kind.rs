@@ -1,10 +1,13 @@
 use crate::error::Error;
 use crate::search::SearchQuery;
-use crate::timeline::{Timeline, TimelineTab};
+use crate::timeline::{media::MEDIA_KINDS, Timeline, TimelineTab, ViewFilter};
 use enostr::{Filter, NoteId, Pubkey};
-use nostrdb::{Ndb, Transaction};
+use nostrdb::{FilterBuilder, Ndb, Transaction};
 use notedeck::{
     filter::{self, default_limit},
+    notifications::NOTIFICATION_TIMELINE_KINDS,
+    poll::POLL_KIND,
+    zap_goal::ZAP_GOAL_KIND,
     FilterError, FilterState, NoteCache, RootIdError, RootNoteIdBuf,
 };
 use notedeck_ui::contacts::contacts_filter;
@@ -208,6 +211,12 @@ pub enum TimelineKind {
 
     Profile(Pubkey),
 
+    /// The pictures in a profile's notes, for its media tab
+    ProfileMedia(Pubkey),
+
+    /// Zaps a profile got and sent, for its zaps tab
+    ProfileZaps(Pubkey),
+
     Thread(ThreadSelection),
 
     Universe,
@@ -216,10 +225,19 @@ pub enum TimelineKind {
     Generic(u64),
 
     Hashtag(String),
+
+    /// Notes from a hand-picked set of pubkeys. This is only stored in
+    /// the column, we never publish it as a list.
+    Watchlist(Vec<Pubkey>),
 }
 
 const NOTIFS_TOKEN_DEPRECATED: &str = "notifs";
 const NOTIFS_TOKEN: &str = "notifications";
+const WATCHLIST_TOKEN: &str = "watchlist";
+const PROFILE_MEDIA_TOKEN: &str = "profile_media";
+const PROFILE_ZAPS_TOKEN: &str = "profile_zaps";
+
+const ZAP_RECEIPT_KIND: u64 = 9735;
 
 /// Hardcoded algo timelines
 #[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
@@ -264,10 +282,13 @@ impl Display for TimelineKind {
             TimelineKind::Generic(_) => f.write_str("Timeline"),
             TimelineKind::Notifications(_) => f.write_str("Notifications"),
             TimelineKind::Profile(_) => f.write_str("Profile"),
+            TimelineKind::ProfileMedia(_) => f.write_str("Media"),
+            TimelineKind::ProfileZaps(_) => f.write_str("Zaps"),
             TimelineKind::Universe => f.write_str("Universe"),
             TimelineKind::Hashtag(_) => f.write_str("Hashtag"),
             TimelineKind::Thread(_) => f.write_str("Thread"),
             TimelineKind::Search(_) => f.write_str("Search"),
+            TimelineKind::Watchlist(_) => f.write_str("Watchlist"),
         }
     }
 }
@@ -279,11 +300,14 @@ impl TimelineKind {
             TimelineKind::Algo(AlgoTimeline::LastPerPubkey(list_kind)) => list_kind.pubkey(),
             TimelineKind::Notifications(pk) => Some(pk),
             TimelineKind::Profile(pk) => Some(pk),
+            TimelineKind::ProfileMedia(pk) => Some(pk),
+            TimelineKind::ProfileZaps(pk) => Some(pk),
             TimelineKind::Universe => None,
             TimelineKind::Generic(_) => None,
             TimelineKind::Hashtag(_ht) => None,
             TimelineKind::Thread(_ht) => None,
             TimelineKind::Search(query) => query.author(),
+            TimelineKind::Watchlist(_pks) => None,
         }
     }
 
@@ -295,11 +319,36 @@ impl TimelineKind {
             TimelineKind::List(_list_kind) => true,
             TimelineKind::Notifications(_pk_src) => true,
             TimelineKind::Profile(_pk_src) => true,
+            TimelineKind::ProfileMedia(_pk) => true,
+            TimelineKind::ProfileZaps(_pk) => true,
             TimelineKind::Universe => true,
             TimelineKind::Generic(_) => true,
             TimelineKind::Hashtag(_ht) => true,
             TimelineKind::Thread(_ht) => true,
             TimelineKind::Search(_q) => true,
+            TimelineKind::Watchlist(_pks) => true,
+        }
+    }
+
+    /// Can we backfill this timeline with negentropy (NIP-77) instead of
+    /// refetching notes we already have?
+    pub fn can_negentropy_sync(&self) -> bool {
+        match self {
+            TimelineKind::Profile(_pk) => true,
+            TimelineKind::ProfileMedia(_pk) => true,
+            TimelineKind::ProfileZaps(_pk) => true,
+            TimelineKind::Hashtag(_ht) => true,
+            TimelineKind::Notifications(_pk) => true,
+            TimelineKind::List(_list_kind) => true,
+            TimelineKind::Watchlist(_pks) => true,
+
+            // these have relay specific semantics (search) or per-author
+            // limits (last per pubkey) that don't map to a single range
+            TimelineKind::Algo(_algo) => false,
+            TimelineKind::Search(_q) => false,
+            TimelineKind::Thread(_ts) => false,
+            TimelineKind::Universe => false,
+            TimelineKind::Generic(_) => false,
         }
     }
 
@@ -321,6 +370,14 @@ impl TimelineKind {
                 writer.write_token("profile");
                 PubkeySource::pubkey(*pk).serialize_tokens(writer);
             }
+            TimelineKind::ProfileMedia(pk) => {
+                writer.write_token(PROFILE_MEDIA_TOKEN);
+                PubkeySource::pubkey(*pk).serialize_tokens(writer);
+            }
+            TimelineKind::ProfileZaps(pk) => {
+                writer.write_token(PROFILE_ZAPS_TOKEN);
+                PubkeySource::pubkey(*pk).serialize_tokens(writer);
+            }
             TimelineKind::Thread(root_note_id) => {
                 writer.write_token("thread");
                 writer.write_token(&root_note_id.root_id.hex());
@@ -336,6 +393,11 @@ impl TimelineKind {
                 writer.write_token("hashtag");
                 writer.write_token(ht);
             }
+            TimelineKind::Watchlist(pks) => {
+                writer.write_token(WATCHLIST_TOKEN);
+                let hexes: Vec<String> = pks.iter().map(|pk| pk.hex()).collect();
+                writer.write_token(&hexes.join(","));
+            }
         }
     }
 
@@ -352,6 +414,19 @@ impl TimelineKind {
             return profile;
         }
 
+        let profile_tab = parser.try_parse(|p| {
+            let token = p.parse_any_token(&[PROFILE_MEDIA_TOKEN, PROFILE_ZAPS_TOKEN])?;
+            let pk = *PubkeySource::parse_from_tokens(p)?.as_pubkey(deck_author);
+            Ok(if token == PROFILE_MEDIA_TOKEN {
+                TimelineKind::ProfileMedia(pk)
+            } else {
+                TimelineKind::ProfileZaps(pk)
+            })
+        });
+        if profile_tab.is_ok() {
+            return profile_tab;
+        }
+
         let notifications = parser.try_parse(|p| {
             // still handle deprecated form (notifs)
             p.parse_any_token(&[NOTIFS_TOKEN, NOTIFS_TOKEN_DEPRECATED])?;
@@ -396,6 +471,16 @@ impl TimelineKind {
                     p.parse_token("hashtag")?;
                     Ok(TimelineKind::Hashtag(p.pull_token()?.to_string()))
                 },
+                |p| {
+                    p.parse_token(WATCHLIST_TOKEN)?;
+                    let pks = p
+                        .pull_token()?
+                        .split(',')
+                        .map(Pubkey::from_hex)
+                        .collect::<Result<Vec<Pubkey>, _>>()
+                        .map_err(|_| ParseError::HexDecodeFailed)?;
+                    Ok(TimelineKind::watchlist(pks))
+                },
                 |p| {
                     p.parse_token("search")?;
                     let search_query = SearchQuery::parse_from_tokens(p)?;
@@ -413,6 +498,14 @@ impl TimelineKind {
         TimelineKind::List(ListKind::contact_list(pk))
     }
 
+    /// A watchlist column. The pubkeys are sorted and deduped so the same
+    /// set always maps to the same timeline.
+    pub fn watchlist(mut pks: Vec<Pubkey>) -> Self {
+        pks.sort();
+        pks.dedup();
+        TimelineKind::Watchlist(pks)
+    }
+
     pub fn search(s: String) -> Self {
         TimelineKind::Search(SearchQuery::new(s))
     }
@@ -425,6 +518,14 @@ impl TimelineKind {
         TimelineKind::Profile(pk)
     }
 
+    /// The timelines behind a profile's tabs, past its notes
+    pub fn profile_tabs(pk: &Pubkey) -> [Self; 2] {
+        [
+            TimelineKind::ProfileMedia(*pk),
+            TimelineKind::ProfileZaps(*pk),
+        ]
+    }
+
     pub fn thread(selected_note: ThreadSelection) -> Self {
         TimelineKind::Thread(selected_note)
     }
@@ -438,6 +539,15 @@ impl TimelineKind {
     }
 
     // TODO: probably should set default limit here
+    /// Whose contact list this timeline is built from, if any
+    pub fn contact_list_owner(&self) -> Option<&Pubkey> {
+        match self {
+            TimelineKind::List(ListKind::Contact(pk))
+            | TimelineKind::Algo(AlgoTimeline::LastPerPubkey(ListKind::Contact(pk))) => Some(pk),
+            _ => None,
+        }
+    }
+
     pub fn filters(&self, txn: &Transaction, ndb: &Ndb) -> FilterState {
         match self {
             TimelineKind::Search(s) => FilterState::ready(search_filter(s)),
@@ -448,10 +558,9 @@ impl TimelineKind {
                 ListKind::Contact(pubkey) => contact_filter_state(txn, ndb, pubkey),
             },
 
-            // TODO: still need to update this to fetch likes, zaps, etc
             TimelineKind::Notifications(pubkey) => FilterState::ready(vec![Filter::new()
                 .pubkeys([pubkey.bytes()])
-                .kinds([1])
+                .kinds(NOTIFICATION_TIMELINE_KINDS)
                 .limit(default_limit())
                 .build()]),
 
@@ -487,9 +596,59 @@ impl TimelineKind {
 
             TimelineKind::Profile(pk) => FilterState::ready(vec![Filter::new()
                 .authors([pk.bytes()])
-                .kinds([1])
+                .kinds([1, POLL_KIND as u64, ZAP_GOAL_KIND as u64])
                 .limit(default_limit())
                 .build()]),
+
+            TimelineKind::ProfileMedia(pk) => {
+                FilterState::ready(profile_media_filter(pk, None, default_limit()))
+            }
+
+            TimelineKind::ProfileZaps(pk) => {
+                FilterState::ready(profile_zaps_filter(pk, None, default_limit()))
+            }
+
+            TimelineKind::Watchlist(pks) => FilterState::ready(watchlist_filter(pks)),
+        }
+    }
+
+    /// Does scrolling to the bottom of this timeline load older notes?
+    pub fn can_load_older(&self) -> bool {
+        matches!(
+            self,
+            TimelineKind::Profile(_)
+                | TimelineKind::ProfileMedia(_)
+                | TimelineKind::ProfileZaps(_)
+                | TimelineKind::Hashtag(_)
+        )
+    }
+
+    /// Filters for up to `limit` notes from `until` back, for timelines that
+    /// load older notes as they're scrolled down
+    pub fn older_filters(&self, until: u64, limit: u64) -> Option<Vec<Filter>> {
+        match self {
+            TimelineKind::Profile(pk) => Some(vec![Filter::new()
+                .authors([pk.bytes()])
+                .kinds([1, POLL_KIND as u64, ZAP_GOAL_KIND as u64])
+                .until(until)
+                .limit(limit)
+                .build()]),
+
+            TimelineKind::ProfileMedia(pk) => Some(profile_media_filter(pk, Some(until), limit)),
+
+            TimelineKind::ProfileZaps(pk) => Some(profile_zaps_filter(pk, Some(until), limit)),
+
+            TimelineKind::Hashtag(hashtag) => {
+                let hashtag: &str = &hashtag.to_lowercase();
+                Some(vec![Filter::new()
+                    .kinds([1])
+                    .tags([hashtag], 't')
+                    .until(until)
+                    .limit(limit)
+                    .build()])
+            }
+
+            _ => None,
         }
     }
 
@@ -561,7 +720,7 @@ impl TimelineKind {
             TimelineKind::Profile(pk) => {
                 let filter = Filter::new()
                     .authors([pk.bytes()])
-                    .kinds([1])
+                    .kinds([1, POLL_KIND as u64, ZAP_GOAL_KIND as u64])
                     .limit(default_limit())
                     .build();
 
@@ -572,10 +731,22 @@ impl TimelineKind {
                 ))
             }
 
+            TimelineKind::ProfileMedia(pk) => Some(Timeline::new(
+                TimelineKind::ProfileMedia(pk),
+                FilterState::ready(profile_media_filter(&pk, None, default_limit())),
+                vec![TimelineTab::new(ViewFilter::Media)],
+            )),
+
+            TimelineKind::ProfileZaps(pk) => Some(Timeline::new(
+                TimelineKind::ProfileZaps(pk),
+                FilterState::ready(profile_zaps_filter(&pk, None, default_limit())),
+                TimelineTab::only_notes_and_replies(),
+            )),
+
             TimelineKind::Notifications(pk) => {
                 let notifications_filter = Filter::new()
                     .pubkeys([pk.bytes()])
-                    .kinds([1])
+                    .kinds(NOTIFICATION_TIMELINE_KINDS)
                     .limit(default_limit())
                     .build();
 
@@ -588,6 +759,15 @@ impl TimelineKind {
 
             TimelineKind::Hashtag(hashtag) => Some(Timeline::hashtag(hashtag)),
 
+            TimelineKind::Watchlist(pks) => {
+                let filter = FilterState::ready(watchlist_filter(&pks));
+                Some(Timeline::new(
+                    TimelineKind::Watchlist(pks),
+                    filter,
+                    TimelineTab::full_tabs(),
+                ))
+            }
+
             TimelineKind::List(ListKind::Contact(pk)) => Some(Timeline::new(
                 TimelineKind::contact_list(pk),
                 contact_filter_state(txn, ndb, &pk),
@@ -609,10 +789,15 @@ impl TimelineKind {
             },
             TimelineKind::Notifications(_pubkey_source) => ColumnTitle::simple("Notifications"),
             TimelineKind::Profile(_pubkey_source) => ColumnTitle::needs_db(self),
+            TimelineKind::ProfileMedia(_pk) => ColumnTitle::simple("Media"),
+            TimelineKind::ProfileZaps(_pk) => ColumnTitle::simple("Zaps"),
             TimelineKind::Thread(_root_id) => ColumnTitle::simple("Thread"),
             TimelineKind::Universe => ColumnTitle::simple("Universe"),
             TimelineKind::Generic(_) => ColumnTitle::simple("Custom"),
             TimelineKind::Hashtag(hashtag) => ColumnTitle::formatted(hashtag.to_string()),
+            TimelineKind::Watchlist(pks) => {
+                ColumnTitle::formatted(format!("Watchlist ({})", pks.len()))
+            }
         }
     }
 }
@@ -722,6 +907,79 @@ fn search_filter(s: &SearchQuery) -> Vec<Filter> {
     vec![s.filter().limit(default_limit()).build()]
 }
 
+fn watchlist_filter(pks: &[Pubkey]) -> Vec<Filter> {
+    vec![Filter::new()
+        .authors(pks.iter().map(|pk| pk.bytes()))
+        .kinds([1])
+        .limit(default_limit())
+        .build()]
+}
+
+/// Notes by `pk` that can have pictures in them, the `limit` newest from
+/// `until` back
+fn profile_media_filter(pk: &Pubkey, until: Option<u64>, limit: u64) -> Vec<Filter> {
+    let filter = Filter::new().authors([pk.bytes()]).kinds(MEDIA_KINDS);
+    vec![until_limit(filter, until, limit)]
+}
+
+/// Receipts for the zaps `pk` got, and for the ones they sent. Those
+/// carry the sender in an uppercase P tag.
+fn profile_zaps_filter(pk: &Pubkey, until: Option<u64>, limit: u64) -> Vec<Filter> {
+    let received = Filter::new()
+        .kinds([ZAP_RECEIPT_KIND])
+        .pubkeys([pk.bytes()]);
+    let mut filters = vec![until_limit(received, until, limit)];
+
+    match zaps_sent_filter(pk) {
+        Ok(sent) => filters.push(until_limit(sent.kinds([ZAP_RECEIPT_KIND]), until, limit)),
+        Err(err) => error!("zaps sent filter for {}: {err}", pk.hex()),
+    }
+
+    filters
+}
+
+fn zaps_sent_filter(pk: &Pubkey) -> Result<FilterBuilder, nostrdb::Error> {
+    let mut filter = Filter::new();
+    filter.start_tags_field('P')?;
+    filter.add_id_element(pk.bytes())?;
+    filter.end_field();
+    Ok(filter)
+}
+
+fn until_limit(filter: FilterBuilder, until: Option<u64>, limit: u64) -> Filter {
+    match until {
+        Some(until) => filter.until(until).limit(limit).build(),
+        None => filter.limit(limit).build(),
+    }
+}
+
 fn universe_filter() -> Vec<Filter> {
-    vec![Filter::new().kinds([1]).limit(default_limit()).build()]
+    vec![Filter::new()
+        .kinds([1, POLL_KIND as u64])
+        .limit(default_limit())
+        .build()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchlist_tokens() {
+        let a = Pubkey::new([1; 32]);
+        let b = Pubkey::new([2; 32]);
+        let kind = TimelineKind::watchlist(vec![b, a, b]);
+        assert_eq!(kind, TimelineKind::Watchlist(vec![a, b]));
+
+        let mut writer = TokenWriter::default();
+        kind.serialize_tokens(&mut writer);
+        let serialized = writer.str().to_owned();
+
+        let data = &serialized.split(":").collect::<Vec<&str>>();
+        let mut parser = TokenParser::new(data);
+        let deck_author = Pubkey::new([0; 32]);
+        let parsed = TimelineKind::parse(&mut parser, &deck_author).unwrap();
+
+        assert_eq!(parsed, kind);
+    }
 }
@@ -0,0 +1,92 @@
+//! The pictures in a note, for the profile's media grid. Notes say what
+//! they've attached in imeta tags, but plenty of clients only paste the
+//! link into the content, so image links there count too.
+
+use nostrdb::Note;
+
+const IMAGE_EXTENSIONS: [&str; 7] = ["jpg", "jpeg", "png", "gif", "webp", "avif", "bmp"];
+
+/// Kinds that can have pictures in them: text notes, and NIP-68 pictures
+pub const MEDIA_KINDS: [u64; 2] = [1, 20];
+
+/// The pictures in `note`, the ones in imeta tags first
+pub fn image_urls<'a>(note: &Note<'a>) -> Vec<&'a str> {
+    let mut urls: Vec<&'a str> = Vec::new();
+
+    for tag in note.tags() {
+        if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some("imeta") {
+            continue;
+        }
+
+        let mut url = None;
+        let mut mime = None;
+        for ind in 1..tag.count() {
+            let Some(entry) = tag.get_unchecked(ind).variant().str() else {
+                continue;
+            };
+            if let Some(value) = entry.strip_prefix("url ") {
+                url = Some(value.trim());
+            } else if let Some(value) = entry.strip_prefix("m ") {
+                mime = Some(value.trim());
+            }
+        }
+
+        let Some(url) = url else {
+            continue;
+        };
+        let is_image = match mime {
+            Some(mime) => mime.starts_with("image/"),
+            None => is_image_url(url),
+        };
+        if is_image && !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+
+    for url in content_image_urls(note.content()) {
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+
+    urls
+}
+
+pub fn has_images(note: &Note) -> bool {
+    !image_urls(note).is_empty()
+}
+
+/// Links to pictures in a note's `content`
+fn content_image_urls(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .split_whitespace()
+        .filter(|word| word.starts_with("https://") || word.starts_with("http://"))
+        .filter(|url| is_image_url(url))
+}
+
+/// Whether the path in `url` ends in an image file extension
+fn is_image_url(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let Some((_, ext)) = path.rsplit_once('.') else {
+        return false;
+    };
+    !ext.contains('/')
+        && IMAGE_EXTENSIONS
+            .iter()
+            .any(|image| ext.eq_ignore_ascii_case(image))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_links_to_pictures_count() {
+        let content = "look https://example.com/cat.JPG?w=400 and \
+                       https://example.com/page https://example.com/v.mp4 \
+                       https://example.com/dir.png/file nothttps://example.com/x.png";
+
+        let urls: Vec<&str> = content_image_urls(content).collect();
+        assert_eq!(urls, vec!["https://example.com/cat.JPG?w=400"]);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     error::Error,
     multi_subscriber::MultiSubscriber,
+    scheduled::unix_now,
     subscriptions::{self, SubKind, Subscriptions},
     timeline::kind::ListKind,
     Result,
@@ -10,20 +11,30 @@ use notedeck::{
     filter, CachedNote, FilterError, FilterState, FilterStates, NoteCache, NoteRef, UnknownIds,
 };
 
-use egui_virtual_list::VirtualList;
-use enostr::{PoolRelay, Pubkey, RelayPool};
+use enostr::{Pubkey, RelayPool};
 use nostrdb::{Filter, Ndb, Note, NoteKey, Transaction};
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 
 use tracing::{debug, error, info, warn};
 
 pub mod cache;
+pub mod gap;
+pub mod grouping;
+pub mod heights;
 pub mod kind;
+pub mod media;
+pub mod paging;
 pub mod route;
+pub mod thread_tree;
 
 pub use cache::TimelineCache;
+pub use gap::Gaps;
+pub use grouping::{GroupMembers, GroupTarget, NoteGroups};
+pub use heights::NoteHeights;
 pub use kind::{ColumnTitle, PubkeySource, ThreadSelection, TimelineKind};
+pub use paging::Paging;
+pub use thread_tree::ThreadTree;
 
 //#[derive(Debug, Hash, Clone, Eq, PartialEq)]
 //pub type TimelineId = TimelineKind;
@@ -57,6 +68,9 @@ pub enum ViewFilter {
 
     #[default]
     NotesAndReplies,
+
+    /// Notes with pictures in them
+    Media,
 }
 
 impl ViewFilter {
@@ -64,6 +78,7 @@ impl ViewFilter {
         match self {
             ViewFilter::Notes => "Notes",
             ViewFilter::NotesAndReplies => "Notes & Replies",
+            ViewFilter::Media => "Media",
         }
     }
 
@@ -71,6 +86,10 @@ impl ViewFilter {
         !cache.reply.borrow(note.tags()).is_reply()
     }
 
+    fn filter_media(_cache: &CachedNote, note: &Note) -> bool {
+        media::has_images(note)
+    }
+
     fn identity(_cache: &CachedNote, _note: &Note) -> bool {
         true
     }
@@ -79,6 +98,7 @@ impl ViewFilter {
         match self {
             ViewFilter::Notes => ViewFilter::filter_notes,
             ViewFilter::NotesAndReplies => ViewFilter::identity,
+            ViewFilter::Media => ViewFilter::filter_media,
         }
     }
 }
@@ -90,9 +110,36 @@ impl ViewFilter {
 #[derive(Default, Debug)]
 pub struct TimelineTab {
     pub notes: Vec<NoteRef>,
-    pub selection: i32,
     pub filter: ViewFilter,
-    pub list: Rc<RefCell<VirtualList>>,
+    pub heights: RefCell<NoteHeights>,
+
+    /// How far notes arriving above the view pushed it down since it was
+    /// last shown, for the view to scroll by so it stays put
+    pub pending_shift: Cell<f32>,
+
+    /// The note keyboard shortcuts act on, see [`crate::shortcuts`]
+    pub focused: Option<NoteKey>,
+
+    /// Set when focus moves, until the focused note is scrolled into view
+    pub scroll_to_focus: Cell<bool>,
+
+    /// The topmost note on screen when last shown, where focus starts
+    pub first_visible: Cell<Option<ScrollAnchor>>,
+
+    /// Where to scroll back to once the note is loaded, from a column
+    /// saved scrolled down
+    pub restore_scroll: Cell<Option<ScrollAnchor>>,
+
+    /// Reposts of the same note, and reactions to it, shown as one
+    pub groups: NoteGroups,
+}
+
+/// A scroll position that survives notes coming and going above it: a
+/// note, and how far the top of the view is past the top of that note
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollAnchor {
+    pub note: NoteKey,
+    pub offset: f32,
 }
 
 impl TimelineTab {
@@ -116,73 +163,138 @@ impl TimelineTab {
     }
 
     pub fn new_with_capacity(filter: ViewFilter, cap: usize) -> Self {
-        let selection = 0i32;
-        let mut list = VirtualList::new();
-        list.hide_on_resize(None);
-        list.over_scan(50.0);
-        let list = Rc::new(RefCell::new(list));
         let notes: Vec<NoteRef> = Vec::with_capacity(cap);
 
         TimelineTab {
             notes,
-            selection,
             filter,
-            list,
+            heights: RefCell::new(NoteHeights::default()),
+            pending_shift: Cell::new(0.0),
+            focused: None,
+            scroll_to_focus: Cell::new(false),
+            first_visible: Cell::new(None),
+            restore_scroll: Cell::new(None),
+            groups: NoteGroups::default(),
         }
     }
 
-    fn insert(&mut self, new_refs: &[NoteRef], reversed: bool) {
+    fn insert(&mut self, new_refs: &[NoteRef], members: &GroupMembers, reversed: bool) {
+        let (new_refs, replaced) = self.groups.fold(new_refs, members);
         if new_refs.is_empty() {
             return;
         }
+        // keep whatever's on screen there while notes land above it. at
+        // the top the new notes should show, so there's nothing to keep.
+        let anchor = self.scroll_anchor();
+        let old_top = anchor.and_then(|anchor| {
+            let heights = self.heights.get_mut();
+            if heights.len() != self.notes.len() {
+                return None;
+            }
+            let ind = heights.index_of(anchor.note)?;
+            Some((anchor.note, heights.top_of(ind, reversed)))
+        });
+
+        // a newer repost takes the place of the one shown for the note
+        if !replaced.is_empty() {
+            self.notes.retain(|note| !replaced.contains(&note.key));
+        }
+
         let num_prev_items = self.notes.len();
-        let (notes, merge_kind) = crate::timeline::merge_sorted_vecs(&self.notes, new_refs);
+        let (notes, merge_kind) = crate::timeline::merge_sorted_vecs(&self.notes, &new_refs);
 
         self.notes = notes;
         let new_items = self.notes.len() - num_prev_items;
+        if new_items == 0 {
+            return;
+        }
 
-        // TODO: technically items could have been added inbetween
-        if new_items > 0 {
-            let mut list = self.list.borrow_mut();
-
-            match merge_kind {
-                // TODO: update egui_virtual_list to support spliced inserts
-                MergeKind::Spliced => {
-                    debug!(
-                        "spliced when inserting {} new notes, resetting virtual list",
-                        new_refs.len()
-                    );
-                    list.reset();
-                }
-                MergeKind::FrontInsert => {
-                    // only run this logic if we're reverse-chronological
-                    // reversed in this case means chronological, since the
-                    // default is reverse-chronological. yeah it's confusing.
-                    if !reversed {
-                        debug!("inserting {} new notes at start", new_refs.len());
-                        list.items_inserted_at_start(new_items);
-                    }
-                }
+        if let MergeKind::Spliced = merge_kind {
+            debug!("spliced when inserting {} new notes", new_refs.len());
+        }
+
+        let heights = self.heights.get_mut();
+        heights.sync(&self.notes);
+
+        if let Some((note, old_top)) = old_top {
+            if let Some(ind) = heights.index_of(note) {
+                let shift = heights.top_of(ind, reversed) - old_top;
+                self.pending_shift.set(self.pending_shift.get() + shift);
             }
         }
     }
 
-    pub fn select_down(&mut self) {
-        debug!("select_down {}", self.selection + 1);
-        if self.selection + 1 > self.notes.len() as i32 {
-            return;
+    /// Drop notes whose NIP-40 expiration has passed. Returns how many
+    /// were dropped.
+    pub fn remove_expired(&mut self, ndb: &Ndb, txn: &Transaction) -> usize {
+        let before = self.notes.len();
+        self.notes.retain(|note_ref| {
+            !ndb.get_note_by_key(txn, note_ref.key)
+                .is_ok_and(|note| notedeck::note::is_expired(&note))
+        });
+
+        let removed = before - self.notes.len();
+        if removed > 0 {
+            self.heights.get_mut().sync(&self.notes);
         }
+        removed
+    }
+
+    /// Drop every note, used when the timeline's filter changes under it
+    pub fn clear(&mut self) {
+        self.notes.clear();
+        self.groups.clear();
+        self.focused = None;
+        self.heights.get_mut().sync(&self.notes);
+        self.pending_shift.set(0.0);
+    }
 
-        self.selection += 1;
+    /// Where the view is scrolled to, None when it's at the top
+    pub fn scroll_anchor(&self) -> Option<ScrollAnchor> {
+        let anchor = self.first_visible.get()?;
+        let at_top =
+            self.notes.first().map(|note| note.key) == Some(anchor.note) && anchor.offset <= 0.0;
+        (!at_top).then_some(anchor)
     }
 
-    pub fn select_up(&mut self) {
-        debug!("select_up {}", self.selection - 1);
-        if self.selection - 1 < 0 {
+    /// Move focus `step` notes along, in the order they're stored. With
+    /// nothing focused yet this focuses the topmost note on screen.
+    pub fn move_focus(&mut self, step: isize) {
+        let first_visible = self.first_visible.get().map(|anchor| anchor.note);
+        let Some(ind) = step_focus(&self.notes, self.focused, first_visible, step) else {
             return;
-        }
+        };
+
+        debug!("focusing note {ind} of {}", self.notes.len());
+        self.focused = Some(self.notes[ind].key);
+        self.scroll_to_focus.set(true);
+    }
+}
+
+/// Notes newer than `last_read`, which are all at the front
+fn count_unread(notes: &[NoteRef], last_read: u64) -> usize {
+    notes
+        .iter()
+        .take_while(|note| note.created_at > last_read)
+        .count()
+}
+
+/// Where focus lands moving `step` notes from `focused`. Focus that isn't
+/// in `notes` anymore starts over from `first_visible`, or the first note.
+fn step_focus(
+    notes: &[NoteRef],
+    focused: Option<NoteKey>,
+    first_visible: Option<NoteKey>,
+    step: isize,
+) -> Option<usize> {
+    if notes.is_empty() {
+        return None;
+    }
+    let position = |key: Option<NoteKey>| key.and_then(|k| notes.iter().position(|n| n.key == k));
 
-        self.selection -= 1;
+    match position(focused) {
+        Some(ind) => Some(ind.saturating_add_signed(step).min(notes.len() - 1)),
+        None => Some(position(first_visible).unwrap_or(0)),
     }
 }
 
@@ -197,6 +309,23 @@ pub struct Timeline {
     pub selected_view: usize,
 
     pub subscription: Option<MultiSubscriber>,
+
+    /// created_at of the newest note seen at the top of the timeline.
+    /// Anything newer came in while the user was scrolled away.
+    pub last_read: u64,
+
+    /// Older notes loaded as the timeline is scrolled down
+    pub paging: Paging,
+
+    /// Notes missed while offline, between the cached ones and the new
+    pub gaps: Gaps,
+
+    /// For notifications, the roots of the followed threads whose replies
+    /// the filter takes in
+    pub followed_threads: Vec<[u8; 32]>,
+
+    /// For threads, the replies laid out as a tree
+    pub tree: ThreadTree,
 }
 
 impl Timeline {
@@ -278,6 +407,49 @@ impl Timeline {
             views,
             subscription,
             selected_view,
+            last_read: 0,
+            paging: Paging::default(),
+            gaps: Gaps::default(),
+            followed_threads: Vec::new(),
+            tree: ThreadTree::default(),
+        }
+    }
+
+    /// Notes in the current view that came in since the user was last
+    /// at the top
+    pub fn unread(&self) -> usize {
+        count_unread(&self.current_view().notes, self.last_read)
+    }
+
+    /// The user is looking at the newest notes
+    pub fn mark_read(&mut self) {
+        if let Some(newest) = self.all_or_any_notes().first() {
+            self.last_read = self.last_read.max(newest.created_at);
+        }
+    }
+
+    /// Look for a gap opening up in the notes, or closing as it's filled
+    pub fn check_gaps(&mut self) {
+        let mut gaps = std::mem::take(&mut self.gaps);
+        gaps.check(self.all_or_any_notes());
+        self.gaps = gaps;
+    }
+
+    /// created_at of the oldest note loaded, where the next page of older
+    /// notes starts
+    pub fn oldest(&self) -> Option<u64> {
+        self.all_or_any_notes().last().map(|note| note.created_at)
+    }
+
+    /// The timeline was scrolled near the bottom. Returns whether there
+    /// might be older notes to load.
+    pub fn want_older(&mut self) -> bool {
+        if !self.kind.can_load_older() {
+            return false;
+        }
+        match self.oldest() {
+            Some(oldest) => self.paging.want(oldest),
+            None => false,
         }
     }
 
@@ -292,10 +464,9 @@ impl Timeline {
     /// Get the note refs for NotesAndReplies. If we only have Notes, then
     /// just return that instead
     pub fn all_or_any_notes(&self) -> &[NoteRef] {
-        self.notes(ViewFilter::NotesAndReplies).unwrap_or_else(|| {
-            self.notes(ViewFilter::Notes)
-                .expect("should have at least notes")
-        })
+        self.notes(ViewFilter::NotesAndReplies)
+            .or_else(|| self.notes(ViewFilter::Notes))
+            .unwrap_or_else(|| &self.views.first().expect("should have a view").notes)
     }
 
     pub fn notes(&self, view: ViewFilter) -> Option<&[NoteRef]> {
@@ -312,6 +483,21 @@ impl Timeline {
 
     /// Initial insert of notes into a timeline. Subsequent inserts should
     /// just use the insert function
+    /// Whether a note the filter matched belongs in the timeline. Our own
+    /// replies come in with the threads we follow, but they aren't
+    /// notifications.
+    fn shows(&self, note: &Note) -> bool {
+        // nostrdb still has notes that expired after we got them
+        if notedeck::note::is_expired(note) {
+            return false;
+        }
+
+        match &self.kind {
+            TimelineKind::Notifications(pk) => note.pubkey() != pk.bytes(),
+            _ => true,
+        }
+    }
+
     pub fn insert_new(
         &mut self,
         txn: &Transaction,
@@ -326,18 +512,35 @@ impl Timeline {
             filters
         };
 
+        let grouped = !matches!(self.kind, TimelineKind::Thread(_));
+        let mut members = GroupMembers::new();
+        let mut added: Vec<Vec<NoteRef>> = vec![Vec::new(); filters.len()];
+
         for note_ref in notes {
+            let Ok(note) = ndb.get_note_by_key(txn, note_ref.key) else {
+                continue;
+            };
+            if !self.shows(&note) {
+                continue;
+            }
+            if let Some(target) = GroupTarget::of(&note).filter(|_| grouped) {
+                members.insert(note_ref.key, (target, *note.pubkey()));
+            }
             for (view, filter) in filters.iter().enumerate() {
-                if let Ok(note) = ndb.get_note_by_key(txn, note_ref.key) {
-                    if filter(
-                        note_cache.cached_note_or_insert_mut(note_ref.key, &note),
-                        &note,
-                    ) {
-                        self.views[view].notes.push(*note_ref)
-                    }
+                if filter(
+                    note_cache.cached_note_or_insert_mut(note_ref.key, &note),
+                    &note,
+                ) {
+                    added[view].push(*note_ref)
                 }
             }
         }
+
+        for (view, added) in self.views.iter_mut().zip(added) {
+            let (added, replaced) = view.groups.fold(&added, &members);
+            view.notes.retain(|note| !replaced.contains(&note.key));
+            view.notes.extend(added);
+        }
     }
 
     /// The main function used for inserting notes into timelines. Handles
@@ -361,6 +564,9 @@ impl Timeline {
                 error!("hit race condition in poll_notes_into_view: https://github.com/damus-io/nostrdb/issues/35 note {:?} was not added to timeline", key);
                 continue;
             };
+            if !self.shows(&note) {
+                continue;
+            }
 
             // Ensure that unknown ids are captured when inserting notes
             // into the timeline
@@ -376,25 +582,38 @@ impl Timeline {
             ));
         }
 
+        // threads read top down, everything in them stays where it is
+        let members: GroupMembers = if reversed {
+            GroupMembers::new()
+        } else {
+            new_refs
+                .iter()
+                .filter_map(|(note, nr)| {
+                    GroupTarget::of(note).map(|target| (nr.key, (target, *note.pubkey())))
+                })
+                .collect()
+        };
+
         for view in &mut self.views {
             match view.filter {
                 ViewFilter::NotesAndReplies => {
                     let refs: Vec<NoteRef> = new_refs.iter().map(|(_note, nr)| *nr).collect();
 
-                    view.insert(&refs, reversed);
+                    view.insert(&refs, &members, reversed);
                 }
 
-                ViewFilter::Notes => {
+                ViewFilter::Notes | ViewFilter::Media => {
+                    let keep = view.filter.filter();
                     let mut filtered_refs = Vec::with_capacity(new_refs.len());
                     for (note, nr) in &new_refs {
                         let cached_note = note_cache.cached_note_or_insert(nr.key, note);
 
-                        if ViewFilter::filter_notes(cached_note, note) {
+                        if keep(cached_note, note) {
                             filtered_refs.push(*nr);
                         }
                     }
 
-                    view.insert(&filtered_refs, reversed);
+                    view.insert(&filtered_refs, &members, reversed);
                 }
             }
         }
@@ -469,6 +688,149 @@ pub fn merge_sorted_vecs<T: Ord + Copy>(vec1: &[T], vec2: &[T]) -> (Vec<T>, Merg
     (merged, result.unwrap_or(MergeKind::FrontInsert))
 }
 
+/// Load the page of notes older than the oldest in `timeline`, if it was
+/// scrolled down far enough to want them. nostrdb's are inserted right
+/// away, and relays are asked for theirs, which come in through the
+/// timeline's subscription.
+#[allow(clippy::too_many_arguments)]
+pub fn load_older_notes(
+    timeline: &mut Timeline,
+    ndb: &Ndb,
+    txn: &Transaction,
+    unknown_ids: &mut UnknownIds,
+    note_cache: &mut NoteCache,
+    subs: &mut Subscriptions,
+    pool: &mut RelayPool,
+) {
+    let Some(oldest) = timeline.oldest() else {
+        return;
+    };
+    if !timeline.paging.take_wanted(oldest) {
+        return;
+    }
+    let Some(filters) = timeline.kind.older_filters(oldest, paging::PAGE_SIZE) else {
+        return;
+    };
+
+    // notes from the same second as the oldest one can be older or newer,
+    // so the page starts there and skips the ones we already have
+    let loaded: Vec<NoteKey> = timeline
+        .all_or_any_notes()
+        .iter()
+        .rev()
+        .take_while(|note| note.created_at == oldest)
+        .map(|note| note.key)
+        .collect();
+
+    match ndb.query(txn, &filters, paging::PAGE_SIZE as i32) {
+        Ok(results) => {
+            let keys: Vec<NoteKey> = results
+                .into_iter()
+                .map(|result| result.note_key)
+                .filter(|key| !loaded.contains(key))
+                .collect();
+            debug!(
+                "{} older notes for {:?} in nostrdb",
+                keys.len(),
+                timeline.kind
+            );
+
+            // only thread timelines are reversed
+            let reversed = false;
+            if let Err(err) = timeline.insert(&keys, ndb, txn, unknown_ids, note_cache, reversed) {
+                error!("load_older_notes: {err}");
+            }
+        }
+        Err(err) => error!("load_older_notes: {err}"),
+    }
+
+    let sub_id = subscriptions::new_sub_id();
+    subs.subs
+        .insert(sub_id.clone(), SubKind::Backfill(timeline.kind.clone()));
+    pool.subscribe(sub_id.clone(), filters);
+    timeline.paging.loading(oldest, sub_id);
+}
+
+/// Fill the gap in `timeline` if the user asked for it, from nostrdb right
+/// away and then from relays
+#[allow(clippy::too_many_arguments)]
+pub fn fill_gap(
+    timeline: &mut Timeline,
+    ndb: &Ndb,
+    txn: &Transaction,
+    unknown_ids: &mut UnknownIds,
+    note_cache: &mut NoteCache,
+    subs: &mut Subscriptions,
+    pool: &mut RelayPool,
+) {
+    let Some((since, until, limit)) = timeline.gaps.take_wanted() else {
+        return;
+    };
+    let Some(filters) = timeline.filter.get_any_ready() else {
+        return;
+    };
+    let filters: Vec<Filter> = filters
+        .iter()
+        .map(|filter| {
+            Filter::copy_from(filter)
+                .until(until)
+                .build()
+                .since_mut(since)
+                .limit_mut(limit)
+        })
+        .collect();
+
+    let loaded: HashSet<NoteKey> = timeline
+        .all_or_any_notes()
+        .iter()
+        .filter(|note| since <= note.created_at && note.created_at <= until)
+        .map(|note| note.key)
+        .collect();
+
+    match ndb.query(txn, &filters, limit as i32) {
+        Ok(results) => {
+            let keys: Vec<NoteKey> = results
+                .into_iter()
+                .map(|result| result.note_key)
+                .filter(|key| !loaded.contains(key))
+                .collect();
+            debug!(
+                "{} missing notes for {:?} in nostrdb",
+                keys.len(),
+                timeline.kind
+            );
+
+            let reversed = false;
+            if let Err(err) = timeline.insert(&keys, ndb, txn, unknown_ids, note_cache, reversed) {
+                error!("fill_gap: {err}");
+            }
+        }
+        Err(err) => error!("fill_gap: {err}"),
+    }
+
+    let sub_id = subscriptions::new_sub_id();
+    subs.subs
+        .insert(sub_id.clone(), SubKind::FillGap(timeline.kind.clone()));
+    pool.subscribe(sub_id.clone(), filters);
+    timeline.gaps.filling(sub_id);
+}
+
+/// Ask relays for the replies in the deep branches of a thread that were
+/// opened. Replies that tag the root come in through the thread's own
+/// subscription.
+pub fn load_branches(timeline: &mut Timeline, subs: &mut Subscriptions, pool: &mut RelayPool) {
+    let branches = timeline.tree.take_wanted();
+    if branches.is_empty() {
+        return;
+    }
+
+    debug!("loading {} branches of {:?}", branches.len(), timeline.kind);
+    let filter = Filter::new().kinds([1]).events(branches.iter()).build();
+    let sub_id = subscriptions::new_sub_id();
+    subs.subs.insert(sub_id.clone(), SubKind::OneShot);
+    pool.subscribe(sub_id, vec![filter]);
+}
+
 /// When adding a new timeline, we may have a situation where the
 /// FilterState is NeedsRemote. This can happen if we don't yet have the
 /// contact list, etc. For these situations, we query all of the relays
@@ -495,8 +857,8 @@ pub fn setup_new_timeline(
         }
     }
 
-    for relay in &mut pool.relays {
-        send_initial_timeline_filter(ndb, since_optimize, subs, relay, timeline);
+    for relay_url in pool.urls() {
+        send_initial_timeline_filter(ndb, since_optimize, subs, pool, &relay_url, timeline);
     }
 }
 
@@ -513,10 +875,12 @@ pub fn send_initial_timeline_filters(
     relay_id: &str,
 ) -> Option<()> {
     info!("Sending initial filters to {}", relay_id);
-    let relay = &mut pool.relays.iter_mut().find(|r| r.url() == relay_id)?;
+    if !pool.has(relay_id) {
+        return None;
+    }
 
     for (_kind, timeline) in timeline_cache.timelines.iter_mut() {
-        send_initial_timeline_filter(ndb, since_optimize, subs, relay, timeline);
+        send_initial_timeline_filter(ndb, since_optimize, subs, pool, relay_id, timeline);
     }
 
     Some(())
@@ -526,10 +890,16 @@ pub fn send_initial_timeline_filter(
     ndb: &Ndb,
     can_since_optimize: bool,
     subs: &mut Subscriptions,
-    relay: &mut PoolRelay,
+    pool: &mut RelayPool,
+    relay_url: &str,
     timeline: &mut Timeline,
 ) {
-    let filter_state = timeline.filter.get_mut(relay.url());
+    // temporary relays only get the queries that sent us there
+    if pool.is_temporary(relay_url) {
+        return;
+    }
+
+    let filter_state = timeline.filter.get_mut(relay_url);
 
     match filter_state {
         FilterState::Broken(err) => {
@@ -548,6 +918,24 @@ pub fn send_initial_timeline_filter(
 
         FilterState::Ready(filter) => {
             let filter = filter.to_owned();
+            let can_negentropy = timeline.kind.can_negentropy_sync();
+
+            // a relay with more new notes than it'll send leaves a gap
+            // above the ones we have
+            let newest = timeline
+                .all_or_any_notes()
+                .first()
+                .map(|note| note.created_at);
+            let remote_limit = filter
+                .iter()
+                .map(|f| f.limit().unwrap_or(u64::MAX))
+                .min()
+                .unwrap_or(u64::MAX)
+                .min(filter::default_remote_limit());
+            if let Some(newest) = newest {
+                timeline.gaps.watch(newest, unix_now(), remote_limit);
+            }
+
             let new_filters = filter.into_iter().map(|f| {
                 // limit the size of remote filters
                 let default_limit = filter::default_remote_limit();
@@ -568,6 +956,13 @@ pub fn send_initial_timeline_filter(
                 // older notes
                 if can_since_optimize && filter::should_since_optimize(lim, notes.len()) {
                     filter = filter::since_optimize_filter(filter, notes);
+                } else if can_negentropy
+                    && !notes.is_empty()
+                    && subs.negentropy.open(ndb, pool, relay_url, &timeline.kind, &filter)
+                {
+                    // the relay will tell us which notes we're missing,
+                    // so the live subscription only needs new ones
+                    filter = filter::since_optimize_filter(filter, notes);
                 } else {
                     warn!("Skipping since optimization for {:?}: number of local notes is less than limit, attempting to backfill.", &timeline.kind);
                 }
@@ -579,14 +974,12 @@ pub fn send_initial_timeline_filter(
             let sub_id = subscriptions::new_sub_id();
             subs.subs.insert(sub_id.clone(), SubKind::Initial);
 
-            if let Err(err) = relay.subscribe(sub_id, new_filters) {
-                error!("error subscribing: {err}");
-            }
+            pool.subscribe_to(sub_id, new_filters, relay_url);
         }
 
         // we need some data first
         FilterState::NeedsRemote(filter) => {
-            fetch_contact_list(filter.to_owned(), ndb, subs, relay, timeline)
+            fetch_contact_list(filter.to_owned(), ndb, subs, pool, relay_url, timeline)
         }
     }
 }
@@ -595,7 +988,8 @@ fn fetch_contact_list(
     filter: Vec<Filter>,
     ndb: &Ndb,
     subs: &mut Subscriptions,
-    relay: &mut PoolRelay,
+    pool: &mut RelayPool,
+    relay_url: &str,
     timeline: &mut Timeline,
 ) {
     let sub_kind = SubKind::FetchingContactList(timeline.kind.clone());
@@ -603,16 +997,14 @@ fn fetch_contact_list(
     let local_sub = ndb.subscribe(&filter).expect("sub");
 
     timeline.filter.set_relay_state(
-        relay.url().to_string(),
+        relay_url.to_string(),
         FilterState::fetching_remote(sub_id.clone(), local_sub),
     );
 
     subs.subs.insert(sub_id.clone(), sub_kind);
 
-    info!("fetching contact list from {}", relay.url());
-    if let Err(err) = relay.subscribe(sub_id, filter) {
-        error!("error subscribing: {err}");
-    }
+    info!("fetching contact list from {relay_url}");
+    pool.subscribe_to(sub_id, filter, relay_url);
 }
 
 fn setup_initial_timeline(
@@ -692,6 +1084,92 @@ fn setup_timeline_nostrdb_sub(
     Ok(())
 }
 
+/// Rebuild a contact timeline's filter after its contact list changed.
+/// Notes from people who were unfollowed drop out, notes from new
+/// follows get loaded from nostrdb and asked for from relays.
+pub fn refresh_contact_timeline(
+    ndb: &mut Ndb,
+    pool: &mut RelayPool,
+    note_cache: &mut NoteCache,
+    timeline: &mut Timeline,
+) {
+    let filter = {
+        let txn = Transaction::new(ndb).expect("txn");
+        let FilterState::Ready(filter) = timeline.kind.filters(&txn, ndb) else {
+            return;
+        };
+        filter
+    };
+
+    info!("contact list changed, refreshing {:?}", timeline.kind);
+    replace_filter(ndb, pool, note_cache, timeline, filter);
+}
+
+/// Take in replies to the `threads` the account follows, on top of what
+/// its notifications timeline normally shows
+pub fn refresh_notifications_timeline(
+    ndb: &mut Ndb,
+    pool: &mut RelayPool,
+    note_cache: &mut NoteCache,
+    timeline: &mut Timeline,
+    threads: &[[u8; 32]],
+) {
+    let mut filter = {
+        let txn = Transaction::new(ndb).expect("txn");
+        let FilterState::Ready(filter) = timeline.kind.filters(&txn, ndb) else {
+            return;
+        };
+        filter
+    };
+    if !threads.is_empty() {
+        filter.push(
+            notedeck::followed_threads::thread_replies_filter(threads)
+                .limit(filter::default_limit())
+                .build(),
+        );
+    }
+
+    debug!("following {} threads in {:?}", threads.len(), timeline.kind);
+    timeline.followed_threads = threads.to_vec();
+    replace_filter(ndb, pool, note_cache, timeline, filter);
+}
+
+/// Swap out a timeline's filter, reloading its notes and replacing its
+/// subscriptions
+fn replace_filter(
+    ndb: &mut Ndb,
+    pool: &mut RelayPool,
+    note_cache: &mut NoteCache,
+    timeline: &mut Timeline,
+    filter: Vec<Filter>,
+) {
+    if let Some(sub) = &mut timeline.subscription {
+        if let Some(local_sub) = sub.local_subid.take() {
+            if let Err(err) = ndb.unsubscribe(local_sub) {
+                error!("replace_filter: couldn't unsubscribe: {err}");
+            }
+        }
+        sub.filters = filter.clone();
+    }
+
+    for view in &mut timeline.views {
+        view.clear();
+    }
+    timeline.filter = FilterStates::new(FilterState::ready(filter.clone()));
+
+    let txn = Transaction::new(ndb).expect("txn");
+    if let Err(err) = setup_initial_timeline(ndb, &txn, timeline, note_cache, &filter) {
+        error!("replace_filter: {err}");
+    }
+
+    // a REQ with an existing subscription id replaces it
+    if let Some(sub) = &mut timeline.subscription {
+        if sub.remote_subid.is_some() {
+            sub.send_remote(pool);
+        }
+    }
+}
+
 /// Check our timeline filter and see if we have any filter data ready.
 /// Our timelines may require additional data before it is functional. For
 /// example, when we have to fetch a contact list before we do the actual
@@ -771,3 +1249,40 @@ pub fn is_timeline_ready(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes(n: u64) -> Vec<NoteRef> {
+        (1..=n)
+            .map(|i| NoteRef {
+                key: NoteKey::new(i),
+                created_at: 100 - i,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn focus_starts_on_screen_and_stays_in_bounds() {
+        let notes = notes(3);
+        let key = |i: u64| Some(NoteKey::new(i));
+
+        assert_eq!(step_focus(&notes, None, key(2), 1), Some(1));
+        assert_eq!(step_focus(&notes, None, None, 1), Some(0));
+        assert_eq!(step_focus(&notes, key(2), None, 1), Some(2));
+        assert_eq!(step_focus(&notes, key(3), None, 1), Some(2));
+        assert_eq!(step_focus(&notes, key(1), None, -1), Some(0));
+        // the focused note was dropped from the timeline
+        assert_eq!(step_focus(&notes, key(9), key(3), 1), Some(2));
+        assert_eq!(step_focus(&[], key(1), None, 1), None);
+    }
+
+    #[test]
+    fn unread_notes_are_the_ones_past_the_watermark() {
+        let notes = notes(3);
+        assert_eq!(count_unread(&notes, 0), 3);
+        assert_eq!(count_unread(&notes, 97), 2);
+        assert_eq!(count_unread(&notes, 99), 0);
+    }
+}
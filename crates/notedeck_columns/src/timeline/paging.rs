@@ -0,0 +1,63 @@
+//! Loading older notes as a timeline is scrolled to the bottom. Each page
+//! is everything older than the oldest note loaded, from nostrdb first and
+//! then from relays, which may have notes nostrdb hasn't seen yet. Notes
+//! from relays come in through the timeline's local subscription like any
+//! other.
+
+/// How many older notes to ask for at a time
+pub const PAGE_SIZE: u64 = 100;
+
+/// How close to the bottom of a timeline, in points, the next page starts
+/// loading
+pub const LOAD_DISTANCE: f32 = 600.0;
+
+#[derive(Debug, Default)]
+pub struct Paging {
+    /// Scrolled near the bottom, the next page should be loaded
+    wanted: bool,
+
+    /// Where the last page started. Asking from there again would only get
+    /// the same notes, so once no older ones turn up we're at the end.
+    last_until: Option<u64>,
+
+    /// The relay subscription for the page being loaded
+    loading: Option<String>,
+}
+
+impl Paging {
+    /// The timeline was scrolled near the bottom, where its oldest note is
+    /// from `oldest`. Returns whether that'll load another page.
+    pub fn want(&mut self, oldest: u64) -> bool {
+        if self.wanted || !self.can_load(oldest) {
+            return false;
+        }
+        self.wanted = true;
+        true
+    }
+
+    /// Whether the next page should be loaded now, starting at `oldest`
+    pub fn take_wanted(&mut self, oldest: u64) -> bool {
+        std::mem::take(&mut self.wanted) && self.can_load(oldest)
+    }
+
+    /// A page from `until` down was asked for under `subid`
+    pub fn loading(&mut self, until: u64, subid: String) {
+        self.last_until = Some(until);
+        self.loading = Some(subid);
+    }
+
+    /// A relay is done sending the page asked for under `subid`
+    pub fn done(&mut self, subid: &str) {
+        if self.loading.as_deref() == Some(subid) {
+            self.loading = None;
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading.is_some()
+    }
+
+    fn can_load(&self, oldest: u64) -> bool {
+        self.loading.is_none() && self.last_until != Some(oldest)
+    }
+}
@@ -1,21 +1,31 @@
 use crate::{
     nav::RenderNavAction,
     profile::ProfileAction,
+    reading_size::ReadingSizes,
     timeline::{TimelineCache, TimelineKind},
     ui::{self, ProfileView},
 };
 
 use enostr::Pubkey;
-use notedeck::{Accounts, MuteFun, NoteContext, UnknownIds};
+use notedeck::{
+    Accounts, FollowedThreads, MuteFun, NoteContext, NotificationFilterSettings, UnknownIds,
+    WebOfTrust,
+};
 use notedeck_ui::{jobs::JobsCache, NoteOptions};
 
 #[allow(clippy::too_many_arguments)]
 pub fn render_timeline_route(
     unknown_ids: &mut UnknownIds,
     timeline_cache: &mut TimelineCache,
+    reading_sizes: &mut ReadingSizes,
     accounts: &mut Accounts,
+    wot: &WebOfTrust,
+    notification_filters: &NotificationFilterSettings,
+    followed_threads: &mut FollowedThreads,
+    acting: Option<Pubkey>,
+    min_pow: u32,
     kind: &TimelineKind,
-    col: usize,
+    column_id: egui::Id,
     mut note_options: NoteOptions,
     depth: usize,
     ui: &mut egui::Ui,
@@ -29,7 +39,18 @@ pub fn render_timeline_route(
         | TimelineKind::Notifications(_)
         | TimelineKind::Universe
         | TimelineKind::Hashtag(_)
+        | TimelineKind::Watchlist(_)
+        | TimelineKind::ProfileMedia(_)
+        | TimelineKind::ProfileZaps(_)
         | TimelineKind::Generic(_) => {
+            // where strangers can show up without anyone we know
+            // bringing them in
+            let notification_filters = kind
+                .is_notifications()
+                .then_some((notification_filters, wot));
+            let wot =
+                matches!(kind, TimelineKind::Hashtag(_) | TimelineKind::Search(_)).then_some(wot);
+
             let note_action = ui::TimelineView::new(
                 kind,
                 timeline_cache,
@@ -39,6 +60,10 @@ pub fn render_timeline_route(
                 &accounts.get_selected_account().map(|a| (&a.key).into()),
                 jobs,
             )
+            .wot(wot)
+            .notification_filters(notification_filters)
+            .min_pow(min_pow)
+            .id_salt(column_id)
             .ui(ui);
 
             note_action.map(RenderNavAction::NoteAction)
@@ -49,9 +74,10 @@ pub fn render_timeline_route(
                 render_profile_route(
                     pubkey,
                     accounts,
+                    acting,
                     timeline_cache,
                     unknown_ids,
-                    col,
+                    column_id,
                     ui,
                     &accounts.mutefun(),
                     note_options,
@@ -69,6 +95,8 @@ pub fn render_timeline_route(
                     &accounts.get_selected_account().map(|a| (&a.key).into()),
                     jobs,
                 )
+                .min_pow(min_pow)
+                .id_salt(column_id)
                 .ui(ui);
 
                 note_action.map(RenderNavAction::NoteAction)
@@ -79,6 +107,7 @@ pub fn render_timeline_route(
             // don't truncate thread notes for now, since they are
             // default truncated everywher eelse
             note_options.set_truncate(false);
+            note_options.set_reply_participants(false);
 
             ui::ThreadView::new(
                 timeline_cache,
@@ -90,7 +119,13 @@ pub fn render_timeline_route(
                 &accounts.get_selected_account().map(|a| (&a.key).into()),
                 jobs,
             )
-            .id_source(egui::Id::new(("threadscroll", col)))
+            .id_source(column_id.with("threadscroll"))
+            .wot(Some(wot))
+            .reading_sizes(reading_sizes)
+            .followed_threads(
+                accounts.selected_account_pubkey().copied(),
+                followed_threads,
+            )
             .ui(ui)
             .map(Into::into)
         }
@@ -101,9 +136,10 @@ pub fn render_timeline_route(
 pub fn render_profile_route(
     pubkey: &Pubkey,
     accounts: &Accounts,
+    acting: Option<Pubkey>,
     timeline_cache: &mut TimelineCache,
     unknown_ids: &mut UnknownIds,
-    col: usize,
+    column_id: egui::Id,
     ui: &mut egui::Ui,
     is_muted: &MuteFun,
     note_options: NoteOptions,
@@ -113,7 +149,8 @@ pub fn render_profile_route(
     let action = ProfileView::new(
         pubkey,
         accounts,
-        col,
+        acting,
+        column_id,
         timeline_cache,
         note_options,
         unknown_ids,
@@ -131,6 +168,15 @@ pub fn render_profile_route(
             ui::profile::ProfileViewAction::Note(note_action) => {
                 Some(RenderNavAction::NoteAction(note_action))
             }
+            ui::profile::ProfileViewAction::Contact(account, contact_action) => Some(
+                RenderNavAction::ProfileAction(ProfileAction::Contact(account, contact_action)),
+            ),
+            ui::profile::ProfileViewAction::DismissContactConflict(account) => Some(
+                RenderNavAction::ProfileAction(ProfileAction::DismissContactConflict(account)),
+            ),
+            ui::profile::ProfileViewAction::RecurringZap(new) => {
+                Some(RenderNavAction::RecurringZap(new))
+            }
         }
     } else {
         None
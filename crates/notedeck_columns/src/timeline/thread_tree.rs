@@ -0,0 +1,265 @@
+//! Threads laid out as a tree, each reply under the note it replies to,
+//! instead of one flat list by time. Replies to the same note are oldest
+//! first. Branches can be collapsed, and ones that run deep are cut off
+//! until they're opened, which also asks relays for the rest of them.
+//!
+//! The thread timeline only has notes that tag the root. The notes between
+//! the selected one and the root are looked up by id as well, so the whole
+//! chain above it shows even when some of them don't.
+
+use std::collections::{HashMap, HashSet};
+
+use nostrdb::{Ndb, Note, NoteKey, NoteReply, Transaction};
+use notedeck::{NoteId, NoteRef, SingleUnkIdAction, UnknownIds};
+
+use crate::timeline::TimelineTab;
+
+/// Branches are cut off every this many replies down, until they're
+/// opened
+pub const BRANCH_DEPTH: usize = 6;
+
+/// Don't follow a reply chain up further than this looking for the root
+const MAX_ANCESTORS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Branch {
+    /// Nothing replies to it
+    Leaf,
+    Open,
+
+    /// Collapsed, with how many replies there are under it
+    Collapsed(usize),
+
+    /// Cut off for being deep, with how many replies there are under it
+    Deep(usize),
+}
+
+/// Where a note goes in the tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Row {
+    pub depth: usize,
+    pub branch: Branch,
+}
+
+/// A note in the thread and the note it replies to
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    pub note: NoteRef,
+    pub id: [u8; 32],
+    pub parent: Option<[u8; 32]>,
+}
+
+impl Entry {
+    pub fn new(note: &Note, note_ref: NoteRef) -> Self {
+        Entry {
+            note: note_ref,
+            id: *note.id(),
+            parent: parent_id(note),
+        }
+    }
+}
+
+/// The note `note` replies to
+fn parent_id(note: &Note) -> Option<[u8; 32]> {
+    let reply = NoteReply::new(note.tags());
+    reply.reply().or(reply.root()).map(|parent| *parent.id)
+}
+
+/// What the user did with a branch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeAction {
+    Toggle(NoteKey),
+    Open(NoteKey),
+}
+
+#[derive(Debug, Default)]
+pub struct ThreadTree {
+    /// The notes that are showing, top to bottom
+    pub tab: TimelineTab,
+
+    rows: HashMap<NoteKey, Row>,
+    ids: HashMap<NoteKey, [u8; 32]>,
+    collapsed: HashSet<NoteKey>,
+    opened: HashSet<NoteKey>,
+
+    /// Deep branches opened since relays were last asked for them
+    wanted: Vec<[u8; 32]>,
+
+    /// How many notes the thread had when it was laid out
+    laid_out_from: Option<usize>,
+}
+
+impl ThreadTree {
+    pub fn row(&self, note: NoteKey) -> Option<Row> {
+        self.rows.get(&note).copied()
+    }
+
+    pub fn apply(&mut self, action: TreeAction) {
+        match action {
+            TreeAction::Toggle(note) => {
+                if !self.collapsed.remove(&note) {
+                    self.collapsed.insert(note);
+                }
+            }
+            TreeAction::Open(note) => {
+                if self.opened.insert(note) {
+                    self.wanted.extend(self.ids.get(&note));
+                }
+            }
+        }
+        self.laid_out_from = None;
+    }
+
+    /// Deep branches to ask relays for the replies in
+    pub fn take_wanted(&mut self) -> Vec<[u8; 32]> {
+        std::mem::take(&mut self.wanted)
+    }
+
+    /// Lay the thread's `notes` out again if they changed. `selected` is
+    /// always shown, along with the notes above it up to `root`.
+    pub fn update(
+        &mut self,
+        ndb: &Ndb,
+        txn: &Transaction,
+        unknown_ids: &mut UnknownIds,
+        notes: &[NoteRef],
+        root: &[u8; 32],
+        selected: &[u8; 32],
+    ) {
+        if self.laid_out_from == Some(notes.len()) {
+            return;
+        }
+        self.laid_out_from = Some(notes.len());
+
+        let mut entries: Vec<Entry> = notes
+            .iter()
+            .filter_map(|note_ref| {
+                let note = ndb.get_note_by_key(txn, note_ref.key).ok()?;
+                Some(Entry::new(&note, *note_ref))
+            })
+            .collect();
+
+        // the chain up to the root is always open, whatever depth it's at
+        let mut opened = self.opened.clone();
+        let mut by_id: HashMap<[u8; 32], usize> = entries
+            .iter()
+            .enumerate()
+            .map(|(ind, entry)| (entry.id, ind))
+            .collect();
+        let mut next = Some(*selected);
+        for _ in 0..MAX_ANCESTORS {
+            let Some(id) = next.filter(|id| id != root) else {
+                break;
+            };
+            if let Some(&ind) = by_id.get(&id) {
+                opened.insert(entries[ind].note.key);
+                next = entries[ind].parent;
+                continue;
+            }
+
+            let Ok(note) = ndb.get_note_by_id(txn, &id) else {
+                SingleUnkIdAction::note_id(NoteId::new(id)).process_action(unknown_ids, ndb, txn);
+                break;
+            };
+            let Some(key) = note.key() else {
+                break;
+            };
+            let entry = Entry::new(&note, NoteRef::new(key, note.created_at()));
+            opened.insert(key);
+            by_id.insert(id, entries.len());
+            next = entry.parent;
+            entries.push(entry);
+        }
+
+        let rows = layout(&entries, root, &self.collapsed, &opened);
+
+        self.ids = entries
+            .iter()
+            .map(|entry| (entry.note.key, entry.id))
+            .collect();
+        self.tab.notes = rows.iter().map(|(note, _)| *note).collect();
+        self.rows = rows
+            .into_iter()
+            .map(|(note, row)| (note.key, row))
+            .collect();
+        self.tab.heights.borrow_mut().sync(&self.tab.notes);
+    }
+}
+
+/// Lay `entries` out top to bottom, each under the note it replies to.
+/// Replies whose parent isn't there go under the `root`. Branches in
+/// `collapsed` are left out, and so are deep ones unless they're `opened`.
+pub fn layout(
+    entries: &[Entry],
+    root: &[u8; 32],
+    collapsed: &HashSet<NoteKey>,
+    opened: &HashSet<NoteKey>,
+) -> Vec<(NoteRef, Row)> {
+    let by_id: HashMap<[u8; 32], usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(ind, entry)| (entry.id, ind))
+        .collect();
+    let root_ind = by_id.get(root).copied();
+
+    let mut top = Vec::new();
+    let mut replies: Vec<Vec<usize>> = vec![Vec::new(); entries.len()];
+    for (ind, entry) in entries.iter().enumerate() {
+        let parent = match entry.parent {
+            Some(parent) => by_id.get(&parent).copied().or(root_ind),
+            None => None,
+        };
+        match parent.filter(|parent| *parent != ind) {
+            Some(parent) => replies[parent].push(ind),
+            None => top.push(ind),
+        }
+    }
+
+    let by_time = |a: &usize, b: &usize| {
+        let (a, b) = (&entries[*a].note, &entries[*b].note);
+        a.created_at.cmp(&b.created_at).then(a.key.cmp(&b.key))
+    };
+    top.sort_by(by_time);
+    for replies in &mut replies {
+        replies.sort_by(by_time);
+    }
+
+    // everything under each note, counted from the bottom up
+    let mut order = Vec::with_capacity(entries.len());
+    let mut seen = vec![false; entries.len()];
+    let mut stack: Vec<usize> = top.iter().rev().copied().collect();
+    while let Some(ind) = stack.pop() {
+        if std::mem::replace(&mut seen[ind], true) {
+            continue;
+        }
+        order.push(ind);
+        stack.extend(replies[ind].iter().rev());
+    }
+    let mut under = vec![0usize; entries.len()];
+    for &ind in order.iter().rev() {
+        under[ind] = replies[ind].iter().map(|reply| 1 + under[*reply]).sum();
+    }
+
+    let mut rows = Vec::with_capacity(entries.len());
+    let mut seen = vec![false; entries.len()];
+    let mut stack: Vec<(usize, usize)> = top.iter().rev().map(|ind| (*ind, 0)).collect();
+    while let Some((ind, depth)) = stack.pop() {
+        if std::mem::replace(&mut seen[ind], true) {
+            continue;
+        }
+        let key = entries[ind].note.key;
+        let branch = if replies[ind].is_empty() {
+            Branch::Leaf
+        } else if collapsed.contains(&key) {
+            Branch::Collapsed(under[ind])
+        } else if depth > 0 && depth % BRANCH_DEPTH == 0 && !opened.contains(&key) {
+            Branch::Deep(under[ind])
+        } else {
+            stack.extend(replies[ind].iter().rev().map(|reply| (*reply, depth + 1)));
+            Branch::Open
+        };
+        rows.push((entries[ind].note, Row { depth, branch }));
+    }
+
+    rows
+}
@@ -0,0 +1,56 @@
+//! How much of a long note timelines show before it's cut off with a
+//! "Show more" link. Threads always show notes in full.
+
+use notedeck::{DataPath, JsonSettings, DEFAULT_TRUNCATE_LINES};
+
+const TRUNCATE_FILE: &str = "truncate.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TruncateSettings {
+    pub enabled: bool,
+    pub max_lines: usize,
+}
+
+impl Default for TruncateSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_lines: DEFAULT_TRUNCATE_LINES,
+        }
+    }
+}
+
+impl TruncateSettings {
+    /// The line limit to render timelines with, None to show notes in full
+    pub fn lines(&self) -> Option<usize> {
+        self.enabled.then_some(self.max_lines.max(1))
+    }
+}
+
+pub struct Truncation {
+    file: JsonSettings<TruncateSettings>,
+    settings: TruncateSettings,
+}
+
+impl Truncation {
+    pub fn new(path: &DataPath) -> Self {
+        let file = JsonSettings::new(path, TRUNCATE_FILE);
+        let settings = file.load();
+
+        Self { file, settings }
+    }
+
+    pub fn settings(&self) -> TruncateSettings {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: TruncateSettings) {
+        if settings == self.settings {
+            return;
+        }
+        self.settings = settings;
+
+        self.file.save(&self.settings);
+    }
+}
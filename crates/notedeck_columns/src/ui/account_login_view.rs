@@ -5,24 +5,71 @@ use egui::{
 };
 use egui::{Layout, TextEdit};
 use enostr::Keypair;
-use notedeck::{fonts::get_font_size, AppAction, NotedeckTextStyle};
+use notedeck::{
+    fonts::get_font_size, AccountSigner, AmberSigner, AppAction, BunkerUri, NotedeckTextStyle,
+    RemoteSigner, SignerStatus,
+};
+use notedeck_ui::qr_code;
 
 pub struct AccountLoginView<'a> {
     manager: &'a mut AcquireKeyState,
+    signer: Option<&'a AccountSigner>,
 }
 
 pub enum AccountLoginResponse {
     CreateNew,
     LoginWith(Keypair),
+
+    /// Start connecting to a bunker
+    ConnectBunker(BunkerUri),
+
+    /// Show a nostrconnect:// code for a signer to scan
+    ConnectQr,
+
+    /// Ask a NIP-55 signer app on this device who we are
+    ConnectAmber,
+
+    /// Stop waiting for the signer
+    CancelSigner,
+
+    /// The signer told us who we are, we can add the account
+    SignerReady,
+}
+
+impl AccountLoginResponse {
+    /// Whether we're done with the login screen after this response
+    pub fn is_complete(&self) -> bool {
+        matches!(
+            self,
+            AccountLoginResponse::CreateNew
+                | AccountLoginResponse::LoginWith(_)
+                | AccountLoginResponse::SignerReady
+        )
+    }
 }
 
 impl<'a> AccountLoginView<'a> {
     pub fn new(state: &'a mut AcquireKeyState) -> Self {
-        AccountLoginView { manager: state }
+        AccountLoginView {
+            manager: state,
+            signer: None,
+        }
+    }
+
+    /// A signer we're in the middle of connecting to
+    pub fn signer(mut self, signer: Option<&'a AccountSigner>) -> Self {
+        self.signer = signer;
+        self
     }
 
     pub fn ui(&mut self, ui: &mut egui::Ui) -> InnerResponse<Option<AccountLoginResponse>> {
-        Frame::new().outer_margin(12.0).show(ui, |ui| self.show(ui))
+        Frame::new().outer_margin(12.0).show(ui, |ui| {
+            if let Some(signer) = self.signer {
+                signer_ui(ui, signer)
+            } else {
+                self.show(ui)
+            }
+        })
     }
 
     fn show(&mut self, ui: &mut egui::Ui) -> Option<AccountLoginResponse> {
@@ -47,10 +94,15 @@ impl<'a> AccountLoginView<'a> {
                         self.manager.toggle_password_visibility();
                     }
                 });
+
+                if self.manager.needs_passphrase() {
+                    ui.add_space(4.0);
+                    ui.add(passphrase_textedit(self.manager));
+                }
                 ui.with_layout(Layout::left_to_right(Align::TOP), |ui| {
                 let help_text_style = NotedeckTextStyle::Small;
                 ui.add(egui::Label::new(
-                    RichText::new("Enter your public key (npub), nostr address (e.g. vrod@damus.io), or private key (nsec). You must enter your private key to be able to post, reply, etc.")
+                    RichText::new("Enter your public key (npub), nostr address (e.g. vrod@damus.io), private key (nsec), bunker:// address, or encrypted key (ncryptsec). You must enter your private key or connect a signer to be able to post, reply, etc.")
                         .text_style(help_text_style.text_style())
                         .size(get_font_size(ui.ctx(), &help_text_style)).color(ui.visuals().weak_text_color()),
                     ).wrap())
@@ -61,6 +113,23 @@ impl<'a> AccountLoginView<'a> {
                 if ui.add(login_button()).clicked() {
                     self.manager.apply_acquire();
                 }
+
+                ui.add_space(8.0);
+
+                if ui
+                    .add(Button::new(RichText::new("Login with a signer app (QR)")).frame(false))
+                    .clicked()
+                {
+                    self.manager.should_connect_qr();
+                }
+
+                if self.manager.has_external_signer()
+                    && ui
+                        .add(Button::new(RichText::new("Login with Amber")).frame(false))
+                        .clicked()
+                {
+                    self.manager.should_connect_amber();
+                }
             });
 
             ui.horizontal(|ui| {
@@ -83,6 +152,18 @@ impl<'a> AccountLoginView<'a> {
             return Some(AccountLoginResponse::CreateNew);
         }
 
+        if let Some(uri) = self.manager.take_bunker_uri() {
+            return Some(AccountLoginResponse::ConnectBunker(uri));
+        }
+
+        if self.manager.take_connect_qr() {
+            return Some(AccountLoginResponse::ConnectQr);
+        }
+
+        if self.manager.take_connect_amber() {
+            return Some(AccountLoginResponse::ConnectAmber);
+        }
+
         if let Some(keypair) = self.manager.get_login_keypair() {
             return Some(AccountLoginResponse::LoginWith(keypair.clone()));
         }
@@ -90,6 +171,74 @@ impl<'a> AccountLoginView<'a> {
     }
 }
 
+fn signer_ui(ui: &mut egui::Ui, signer: &AccountSigner) -> Option<AccountLoginResponse> {
+    if signer.user_pubkey().is_some() && *signer.status() == SignerStatus::Connected {
+        return Some(AccountLoginResponse::SignerReady);
+    }
+
+    let mut cancel = false;
+
+    ui.vertical_centered(|ui| {
+        ui.add_space(32.0);
+        ui.label(
+            RichText::new("Connect your signer")
+                .text_style(NotedeckTextStyle::Heading2.text_style())
+                .strong(),
+        );
+        ui.add_space(16.0);
+
+        match signer {
+            AccountSigner::Remote(signer) => remote_signer_ui(ui, signer),
+            AccountSigner::Amber(signer) => amber_signer_ui(ui, signer),
+        }
+
+        ui.add_space(16.0);
+        cancel = ui.button("Cancel").clicked();
+    });
+
+    cancel.then_some(AccountLoginResponse::CancelSigner)
+}
+
+fn amber_signer_ui(ui: &mut egui::Ui, signer: &AmberSigner) {
+    match signer.status() {
+        SignerStatus::Disconnected(reason) => {
+            ui.label(RichText::new(reason).color(ui.visuals().error_fg_color));
+        }
+        _ => {
+            ui.add(egui::Spinner::new());
+            ui.label("Waiting for Amber...");
+        }
+    }
+}
+
+fn remote_signer_ui(ui: &mut egui::Ui, signer: &RemoteSigner) {
+    if signer.awaiting_scan() {
+        let uri = signer.nostrconnect_uri();
+        ui.label("Scan this with your signer app");
+        ui.add_space(8.0);
+        qr_code(ui, &uri, 240.0);
+        ui.add_space(8.0);
+        if ui.small_button("Copy connection string").clicked() {
+            ui.ctx().copy_text(uri);
+        }
+    } else {
+        match signer.status() {
+            SignerStatus::Disconnected(reason) => {
+                ui.label(RichText::new(reason).color(ui.visuals().error_fg_color));
+            }
+            _ => {
+                ui.add(egui::Spinner::new());
+                ui.label("Waiting for your signer to accept the connection...");
+            }
+        }
+    }
+
+    if let Some(url) = signer.auth_url() {
+        ui.add_space(8.0);
+        ui.hyperlink_to("Approve in browser", url);
+    }
+}
+
 fn login_title_text() -> RichText {
     RichText::new("Login")
         .text_style(NotedeckTextStyle::Heading2.text_style())
@@ -131,6 +280,26 @@ fn login_textedit(manager: &mut AcquireKeyState) -> TextEdit {
     text_edit
 }
 
+fn passphrase_textedit(manager: &mut AcquireKeyState) -> TextEdit {
+    let create_textedit: fn(&mut dyn TextBuffer) -> TextEdit = |text| {
+        egui::TextEdit::singleline(text)
+            .hint_text(
+                RichText::new("Passphrase for your encrypted key...")
+                    .text_style(NotedeckTextStyle::Body.text_style()),
+            )
+            .vertical_align(Align::Center)
+            .min_size(Vec2::new(0.0, 40.0))
+            .margin(Margin::same(12))
+    };
+
+    let is_visible = manager.password_visible();
+    let mut text_edit = manager.get_passphrase_textedit(create_textedit);
+    if !is_visible {
+        text_edit = text_edit.password(true);
+    }
+    text_edit
+}
+
 fn eye_button(ui: &mut egui::Ui, is_visible: bool) -> egui::Response {
     let is_dark_mode = ui.visuals().dark_mode;
     let icon = Image::new(if is_visible && is_dark_mode {
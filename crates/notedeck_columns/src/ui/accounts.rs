@@ -2,7 +2,7 @@ use egui::{
     Align, Button, Frame, Image, InnerResponse, Layout, RichText, ScrollArea, Ui, UiBuilder, Vec2,
 };
 use nostrdb::{Ndb, Transaction};
-use notedeck::{Accounts, Images};
+use notedeck::{AccountColors, Accounts, Images};
 use notedeck_ui::colors::PINK;
 
 use notedeck_ui::profile::preview::SimpleProfilePreview;
@@ -18,6 +18,8 @@ pub enum AccountsViewResponse {
     SelectAccount(usize),
     RemoveAccount(usize),
     RouteToLogin,
+    RouteToExportKey,
+    RouteToAppLock,
 }
 
 #[derive(Debug)]
@@ -37,7 +39,18 @@ impl<'a> AccountsView<'a> {
 
     pub fn ui(&mut self, ui: &mut Ui) -> InnerResponse<Option<AccountsViewResponse>> {
         Frame::new().outer_margin(12.0).show(ui, |ui| {
-            if let Some(resp) = Self::top_section_buttons_widget(ui).inner {
+            let can_export = self
+                .accounts
+                .get_selected_account()
+                .is_some_and(|acc| acc.key.secret_key.is_some());
+
+            let has_secret = self
+                .accounts
+                .get_accounts()
+                .iter()
+                .any(|acc| acc.key.secret_key.is_some() || acc.locked_key.is_some());
+
+            if let Some(resp) = Self::top_section_buttons_widget(ui, can_export, has_secret).inner {
                 return Some(resp);
             }
 
@@ -67,6 +80,7 @@ impl<'a> AccountsView<'a> {
                     return;
                 };
 
+                let colors = AccountColors::new(accounts);
                 for i in 0..accounts.num_accounts() {
                     let (account_pubkey, has_nsec) = match accounts.get_account(i) {
                         Some(acc) => (acc.key.pubkey.bytes(), acc.key.secret_key.is_some()),
@@ -87,7 +101,8 @@ impl<'a> AccountsView<'a> {
                         ui.allocate_new_ui(UiBuilder::new().max_rect(resp.rect), |ui| {
                             let preview =
                                 SimpleProfilePreview::new(profile.as_ref(), img_cache, has_nsec);
-                            show_profile_card(ui, preview, max_size, is_selected, resp)
+                            let color = colors.get(account_pubkey);
+                            show_profile_card(ui, preview, max_size, is_selected, color, resp)
                         })
                         .inner
                     };
@@ -110,16 +125,39 @@ impl<'a> AccountsView<'a> {
 
     fn top_section_buttons_widget(
         ui: &mut egui::Ui,
+        can_export: bool,
+        has_secret: bool,
     ) -> InnerResponse<Option<AccountsViewResponse>> {
         ui.allocate_ui_with_layout(
             Vec2::new(ui.available_size_before_wrap().x, 32.0),
             Layout::left_to_right(egui::Align::Center),
             |ui| {
                 if ui.add(add_account_button()).clicked() {
-                    Some(AccountsViewResponse::RouteToLogin)
-                } else {
-                    None
+                    return Some(AccountsViewResponse::RouteToLogin);
                 }
+
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    if has_secret
+                        && ui
+                            .add(Button::new("App lock"))
+                            .on_hover_text("Encrypt your secret keys with a passphrase or PIN")
+                            .clicked()
+                    {
+                        return Some(AccountsViewResponse::RouteToAppLock);
+                    }
+
+                    if can_export
+                        && ui
+                            .add(Button::new("Export key"))
+                            .on_hover_text("Back up the selected account's key as an ncryptsec")
+                            .clicked()
+                    {
+                        return Some(AccountsViewResponse::RouteToExportKey);
+                    }
+
+                    None
+                })
+                .inner
             },
         )
     }
@@ -130,6 +168,7 @@ fn show_profile_card(
     preview: SimpleProfilePreview,
     max_size: egui::Vec2,
     is_selected: bool,
+    color: Option<egui::Color32>,
     card_resp: egui::Response,
 ) -> Option<ProfilePreviewAction> {
     let mut op: Option<ProfilePreviewAction> = None;
@@ -140,9 +179,12 @@ fn show_profile_card(
             frame = frame.fill(ui.visuals().noninteractive().weak_bg_fill);
         }
         if is_selected {
-            frame = frame.stroke(ui.visuals().noninteractive().fg_stroke);
+            frame = frame.stroke(match color {
+                Some(color) => egui::Stroke::new(2.0, color),
+                None => ui.visuals().noninteractive().fg_stroke,
+            });
         }
-        frame
+        let resp = frame
             .corner_radius(8.0)
             .inner_margin(8.0)
             .show(ui, |ui| {
@@ -162,7 +204,19 @@ fn show_profile_card(
                     });
                 });
             })
-            .response
+            .response;
+
+        // the account's color, on the edge so it doesn't fight the avatar
+        if let Some(color) = color {
+            let rect = resp.rect;
+            let bar = egui::Rect::from_min_max(
+                rect.left_top() + egui::vec2(0.0, 12.0),
+                egui::pos2(rect.left() + 4.0, rect.bottom() - 12.0),
+            );
+            ui.painter().rect_filled(bar, 2.0, color);
+        }
+
+        resp
     });
     ui.add_space(8.0);
     op
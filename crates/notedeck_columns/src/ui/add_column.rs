@@ -10,6 +10,7 @@ use nostrdb::{Ndb, Transaction};
 use tracing::error;
 
 use crate::{
+    app::get_active_columns,
     login_manager::AcquireKeyState,
     route::Route,
     timeline::{kind::ListKind, PubkeySource, TimelineKind},
@@ -31,6 +32,7 @@ pub enum AddColumnResponse {
     Algo(AlgoOption),
     UndecidedIndividual,
     ExternalIndividual,
+    Watchlist,
 }
 
 pub enum NotificationColumnType {
@@ -61,6 +63,7 @@ enum AddColumnOption {
     UndecidedIndividual,
     ExternalIndividual,
     Individual(PubkeySource),
+    UndecidedWatchlist,
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
@@ -79,6 +82,7 @@ pub enum AddColumnRoute {
     Algo(AddAlgoRoute),
     UndecidedIndividual,
     ExternalIndividual,
+    Watchlist,
 }
 
 // Parser for the common case without any payloads
@@ -104,6 +108,7 @@ impl AddColumnRoute {
             Self::UndecidedIndividual => &["column", "individual_selection"],
             Self::ExternalIndividual => &["column", "external_individual_selection"],
             Self::Hashtag => &["column", "hashtag"],
+            Self::Watchlist => &["column", "watchlist"],
             Self::Algo(AddAlgoRoute::Base) => &["column", "algo_selection"],
             Self::Algo(AddAlgoRoute::LastPerPubkey) => {
                 &["column", "algo_selection", "last_per_pubkey"]
@@ -131,6 +136,7 @@ impl TokenSerializable for AddColumnRoute {
                 |p| parse_column_route(p, AddColumnRoute::UndecidedIndividual),
                 |p| parse_column_route(p, AddColumnRoute::ExternalIndividual),
                 |p| parse_column_route(p, AddColumnRoute::Hashtag),
+                |p| parse_column_route(p, AddColumnRoute::Watchlist),
                 |p| parse_column_route(p, AddColumnRoute::Algo(AddAlgoRoute::Base)),
                 |p| parse_column_route(p, AddColumnRoute::Algo(AddAlgoRoute::LastPerPubkey)),
             ],
@@ -157,6 +163,7 @@ impl AddColumnOption {
             AddColumnOption::Individual(pubkey_source) => AddColumnResponse::Timeline(
                 TimelineKind::profile(*pubkey_source.as_pubkey(&cur_account.key.pubkey)),
             ),
+            AddColumnOption::UndecidedWatchlist => AddColumnResponse::Watchlist,
         }
     }
 }
@@ -485,6 +492,12 @@ impl<'a> AddColumnView<'a> {
             icon: egui::include_image!("../../../../assets/icons/profile_icon_4x.png"),
             option: AddColumnOption::UndecidedIndividual,
         });
+        vec.push(ColumnOptionData {
+            title: "Watchlist",
+            description: "Quietly keep an eye on a handful of accounts",
+            icon: egui::include_image!("../../../../assets/icons/eye-dark.png"),
+            option: AddColumnOption::UndecidedWatchlist,
+        });
         vec.push(ColumnOptionData {
             title: "Algo",
             description: "Algorithmic feeds to aid in note discovery",
@@ -597,18 +610,22 @@ pub fn render_add_column_routes(
     col: usize,
     route: &AddColumnRoute,
 ) {
+    // home and notification columns are for the account this column acts as
+    let deck_author = get_active_columns(ctx.accounts, &app.decks_cache)
+        .column(col)
+        .acting_account(ctx.accounts);
     let mut add_column_view = AddColumnView::new(
         &mut app.view_state.id_state_map,
         ctx.ndb,
         ctx.img_cache,
-        ctx.accounts.get_selected_account(),
+        deck_author,
     );
     let resp = match route {
         AddColumnRoute::Base => add_column_view.ui(ui),
         AddColumnRoute::Algo(r) => match r {
             AddAlgoRoute::Base => add_column_view.algo_ui(ui),
             AddAlgoRoute::LastPerPubkey => {
-                if let Some(deck_author) = ctx.accounts.get_selected_account() {
+                if let Some(deck_author) = deck_author {
                     add_column_view.algo_last_per_pk_ui(ui, deck_author.key.pubkey)
                 } else {
                     None
@@ -618,6 +635,7 @@ pub fn render_add_column_routes(
         AddColumnRoute::UndecidedNotification => add_column_view.notifications_ui(ui),
         AddColumnRoute::ExternalNotification => add_column_view.external_notification_ui(ui),
         AddColumnRoute::Hashtag => hashtag_ui(ui, &mut app.view_state.id_string_map),
+        AddColumnRoute::Watchlist => watchlist_ui(ui, &mut app.view_state.id_string_map),
         AddColumnRoute::UndecidedIndividual => add_column_view.individual_ui(ui),
         AddColumnRoute::ExternalIndividual => add_column_view.external_individual_ui(ui),
     };
@@ -724,6 +742,12 @@ pub fn render_add_column_routes(
                     .router_mut()
                     .route_to(crate::route::Route::AddColumn(AddColumnRoute::Hashtag));
             }
+            AddColumnResponse::Watchlist => {
+                app.columns_mut(ctx.accounts)
+                    .column_mut(col)
+                    .router_mut()
+                    .route_to(crate::route::Route::AddColumn(AddColumnRoute::Watchlist));
+            }
             AddColumnResponse::UndecidedIndividual => {
                 app.columns_mut(ctx.accounts)
                     .column_mut(col)
@@ -790,10 +814,86 @@ fn sanitize_hashtag(raw_hashtag: &str) -> String {
     raw_hashtag.replace("#", "")
 }
 
+pub fn watchlist_ui(
+    ui: &mut Ui,
+    id_string_map: &mut HashMap<Id, String>,
+) -> Option<AddColumnResponse> {
+    padding(16.0, ui, |ui| {
+        let id = ui.id().with("watchlist");
+        let text_buffer = id_string_map.entry(id).or_default();
+
+        let text_edit = egui::TextEdit::multiline(text_buffer)
+            .hint_text(
+                RichText::new("Enter the npubs you want to watch, one per line")
+                    .text_style(NotedeckTextStyle::Body.text_style()),
+            )
+            .desired_width(f32::INFINITY)
+            .desired_rows(5)
+            .margin(Margin::same(12));
+        ui.add(text_edit);
+
+        ui.add_space(8.0);
+
+        let pks = match parse_watchlist(text_buffer) {
+            Ok(pks) => pks,
+            Err(bad) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("Invalid key: {bad}"));
+                return None;
+            }
+        };
+
+        if !pks.is_empty() {
+            ui.label(
+                RichText::new(format!(
+                    "Watching {} account{}. This list stays on this device.",
+                    pks.len(),
+                    if pks.len() == 1 { "" } else { "s" }
+                ))
+                .text_style(NotedeckTextStyle::Button.text_style()),
+            );
+            ui.add_space(8.0);
+        }
+
+        if ui
+            .add_sized(egui::vec2(50.0, 40.0), add_column_button())
+            .clicked()
+            && !pks.is_empty()
+        {
+            id_string_map.remove(&id);
+            Some(AddColumnResponse::Timeline(TimelineKind::watchlist(pks)))
+        } else {
+            None
+        }
+    })
+    .inner
+}
+
+/// Parse npubs or hex pubkeys separated by whitespace or commas. On
+/// failure we return the first entry we couldn't parse.
+fn parse_watchlist(input: &str) -> Result<Vec<Pubkey>, &str> {
+    input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|s| s.trim_start_matches("nostr:"))
+        .filter(|s| !s.is_empty())
+        .map(|s| Pubkey::parse(s).map_err(|_| s))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_watchlist() {
+        let a = Pubkey::new([1; 32]);
+        let b = Pubkey::new([2; 32]);
+        let input = format!("{}\n  nostr:{}, \n", a.hex(), b.npub().unwrap());
+
+        assert_eq!(parse_watchlist(&input), Ok(vec![a, b]));
+        assert_eq!(parse_watchlist(""), Ok(vec![]));
+        assert_eq!(parse_watchlist("npub1nope"), Err("npub1nope"));
+    }
+
     #[test]
     fn test_column_serialize() {
         use super::{AddAlgoRoute, AddColumnRoute};
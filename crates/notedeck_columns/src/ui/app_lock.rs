@@ -0,0 +1,263 @@
+use crate::ui::export_key::{passphrase_textedit, strength_meter};
+use egui::{Button, Frame, InnerResponse, RichText};
+use notedeck::{Accounts, AppLock, NotedeckTextStyle, PassphraseStrength};
+
+/// The passphrase being typed into the lock and unlock views
+#[derive(Default)]
+pub struct AppLockState {
+    passphrase: String,
+    confirm: String,
+    show_passphrase: bool,
+
+    /// Picking a new passphrase while the lock is already on
+    changing: bool,
+
+    /// The user chose to browse without unlocking at startup
+    browsing_locked: bool,
+}
+
+impl AppLockState {
+    pub fn browsing_locked(&self) -> bool {
+        self.browsing_locked
+    }
+
+    pub fn browse_locked(&mut self) {
+        self.browsing_locked = true;
+        self.clear_passphrase();
+    }
+
+    pub fn clear_passphrase(&mut self) {
+        self.passphrase.clear();
+        self.confirm.clear();
+        self.changing = false;
+    }
+}
+
+pub enum UnlockResponse {
+    Unlock(String),
+
+    /// Keep going without the secret keys
+    Browse,
+}
+
+/// Asks for the app lock passphrase, at startup or when we need to sign
+pub struct UnlockView<'a> {
+    state: &'a mut AppLockState,
+    lock: &'a AppLock,
+    browsable: bool,
+}
+
+impl<'a> UnlockView<'a> {
+    pub fn new(state: &'a mut AppLockState, lock: &'a AppLock) -> Self {
+        UnlockView {
+            state,
+            lock,
+            browsable: false,
+        }
+    }
+
+    /// Offer to keep browsing without unlocking
+    pub fn browsable(mut self, browsable: bool) -> Self {
+        self.browsable = browsable;
+        self
+    }
+
+    pub fn ui(self, ui: &mut egui::Ui) -> InnerResponse<Option<UnlockResponse>> {
+        Frame::new().outer_margin(12.0).show(ui, |ui| {
+            ui.vertical(|ui| {
+                ui.label(
+                    RichText::new("Locked").text_style(NotedeckTextStyle::Heading2.text_style()),
+                );
+                ui.label(
+                    RichText::new("Enter your passphrase or PIN to unlock your keys.")
+                        .color(ui.visuals().weak_text_color()),
+                );
+                ui.add_space(8.0);
+
+                if self.lock.is_busy() {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::Spinner::new());
+                        ui.label("Unlocking...");
+                    });
+                    // scrypt is done on another thread, check back soon
+                    ui.ctx().request_repaint();
+                    return None;
+                }
+
+                let show = self.state.show_passphrase;
+                let resp = ui.add(passphrase_textedit(
+                    &mut self.state.passphrase,
+                    "Passphrase or PIN",
+                    show,
+                ));
+                let submitted = resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                ui.checkbox(&mut self.state.show_passphrase, "Show passphrase");
+
+                if let Some(err) = self.lock.last_error() {
+                    ui.label(RichText::new(err.to_string()).color(ui.visuals().error_fg_color));
+                }
+
+                ui.add_space(8.0);
+                let can_unlock = !self.state.passphrase.is_empty();
+                let clicked = ui.add_enabled(can_unlock, Button::new("Unlock")).clicked();
+                if can_unlock && (clicked || submitted) {
+                    return Some(UnlockResponse::Unlock(std::mem::take(
+                        &mut self.state.passphrase,
+                    )));
+                }
+
+                if self.browsable {
+                    ui.add_space(8.0);
+                    if ui
+                        .link("Browse without unlocking")
+                        .on_hover_text("You can unlock later when you want to post")
+                        .clicked()
+                    {
+                        return Some(UnlockResponse::Browse);
+                    }
+                }
+
+                None
+            })
+            .inner
+        })
+    }
+}
+
+pub enum AppLockResponse {
+    /// Encrypt the secret keys with this passphrase
+    Enable(String),
+    Unlock(String),
+    Lock,
+    Disable,
+}
+
+/// Turn the app lock on and off
+pub struct AppLockView<'a> {
+    state: &'a mut AppLockState,
+    accounts: &'a Accounts,
+}
+
+impl<'a> AppLockView<'a> {
+    pub fn new(state: &'a mut AppLockState, accounts: &'a Accounts) -> Self {
+        AppLockView { state, accounts }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> InnerResponse<Option<AppLockResponse>> {
+        if self.accounts.is_locked() {
+            let resp = UnlockView::new(self.state, self.accounts.app_lock()).ui(ui);
+            let inner = match resp.inner {
+                Some(UnlockResponse::Unlock(passphrase)) => {
+                    Some(AppLockResponse::Unlock(passphrase))
+                }
+                Some(UnlockResponse::Browse) | None => None,
+            };
+            return InnerResponse::new(inner, resp.response);
+        }
+
+        Frame::new().outer_margin(12.0).show(ui, |ui| {
+            ui.vertical(|ui| {
+                if self.accounts.app_lock().is_busy() {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::Spinner::new());
+                        ui.label("Encrypting keys...");
+                    });
+                    ui.ctx().request_repaint();
+                    return None;
+                }
+
+                if self.accounts.lock_enabled() && !self.state.changing {
+                    return self.enabled_ui(ui);
+                }
+
+                self.passphrase_ui(ui)
+            })
+            .inner
+        })
+    }
+
+    fn enabled_ui(&mut self, ui: &mut egui::Ui) -> Option<AppLockResponse> {
+        ui.label("Your secret keys are encrypted with your passphrase.");
+        ui.label(
+            RichText::new(
+                "Notedeck starts locked. You can still browse, but you'll need to unlock \
+                 before posting.",
+            )
+            .color(ui.visuals().weak_text_color()),
+        );
+        ui.add_space(8.0);
+
+        if ui.button("Lock now").clicked() {
+            return Some(AppLockResponse::Lock);
+        }
+
+        if ui.button("Change passphrase").clicked() {
+            self.state.changing = true;
+        }
+
+        if ui
+            .button("Turn off app lock")
+            .on_hover_text("Save secret keys without a passphrase again")
+            .clicked()
+        {
+            return Some(AppLockResponse::Disable);
+        }
+
+        None
+    }
+
+    fn passphrase_ui(&mut self, ui: &mut egui::Ui) -> Option<AppLockResponse> {
+        ui.label(
+            RichText::new(
+                "Encrypt your secret keys with a passphrase or PIN. You'll need it every time \
+                 you start Notedeck to post, and there's no way to recover it.",
+            )
+            .color(ui.visuals().weak_text_color()),
+        );
+        ui.add_space(8.0);
+
+        let show = self.state.show_passphrase;
+        ui.add(passphrase_textedit(
+            &mut self.state.passphrase,
+            "Passphrase or PIN",
+            show,
+        ));
+        ui.add_space(4.0);
+        ui.add(passphrase_textedit(
+            &mut self.state.confirm,
+            "Confirm passphrase or PIN",
+            show,
+        ));
+        ui.checkbox(&mut self.state.show_passphrase, "Show passphrase");
+
+        ui.add_space(8.0);
+        let strength = PassphraseStrength::of(&self.state.passphrase);
+        strength_meter(ui, strength);
+
+        let matches = self.state.passphrase == self.state.confirm;
+        if !matches && !self.state.confirm.is_empty() {
+            ui.label(RichText::new("Passphrases don't match").color(ui.visuals().error_fg_color));
+        }
+
+        if let Some(err) = self.accounts.app_lock().last_error() {
+            ui.label(RichText::new(err.to_string()).color(ui.visuals().error_fg_color));
+        }
+
+        ui.add_space(8.0);
+        let can_enable = matches && strength.is_acceptable();
+        let label = if self.state.changing {
+            "Change passphrase"
+        } else {
+            "Turn on app lock"
+        };
+        if ui.add_enabled(can_enable, Button::new(label)).clicked() {
+            return Some(AppLockResponse::Enable(self.state.passphrase.clone()));
+        }
+
+        if self.state.changing && ui.button("Cancel").clicked() {
+            self.state.clear_passphrase();
+        }
+
+        None
+    }
+}
@@ -1,4 +1,4 @@
-use crate::column::ColumnsAction;
+use crate::column::{ColumnDrag, ColumnsAction};
 use crate::nav::RenderNavAction;
 use crate::nav::SwitchingAction;
 use crate::{
@@ -11,24 +11,30 @@ use crate::{
 use egui::{Margin, Response, RichText, Sense, Stroke, UiBuilder};
 use enostr::Pubkey;
 use nostrdb::{Ndb, Transaction};
-use notedeck::{Images, NotedeckTextStyle};
+use notedeck::{name::get_display_name, Accounts, Images, NotedeckTextStyle};
 use notedeck_ui::{
     anim::{AnimationHelper, ICON_EXPANSION_MULTIPLE},
     ProfilePic,
 };
 
+/// Minimum proof of work a timeline column can filter by, in bits
+const MIN_POW_PRESETS: [u32; 5] = [8, 12, 16, 20, 24];
+
 pub struct NavTitle<'a> {
     ndb: &'a Ndb,
     img_cache: &'a mut Images,
+    accounts: &'a Accounts,
     columns: &'a Columns,
     routes: &'a [Route],
     col_id: usize,
+    unread: usize,
 }
 
 impl<'a> NavTitle<'a> {
     pub fn new(
         ndb: &'a Ndb,
         img_cache: &'a mut Images,
+        accounts: &'a Accounts,
         columns: &'a Columns,
         routes: &'a [Route],
         col_id: usize,
@@ -36,17 +42,37 @@ impl<'a> NavTitle<'a> {
         NavTitle {
             ndb,
             img_cache,
+            accounts,
             columns,
             routes,
             col_id,
+            unread: 0,
         }
     }
 
+    /// New notes in the column's timeline the user hasn't scrolled up to
+    pub fn unread(mut self, unread: usize) -> Self {
+        self.unread = unread;
+        self
+    }
+
     pub fn show(&mut self, ui: &mut egui::Ui) -> Option<RenderNavAction> {
         notedeck_ui::padding(8.0, ui, |ui| {
             let mut rect = ui.available_rect_before_wrap();
             rect.set_height(48.0);
 
+            // grabbing the header anywhere but its buttons drags the column,
+            // the deck shows where it'll land
+            if self.columns.num_columns() > 1 {
+                let drag = ui.interact(rect, ui.id().with("column_drag"), Sense::drag());
+                if drag.drag_started() {
+                    egui::DragAndDrop::set_payload(ui.ctx(), ColumnDrag(self.col_id));
+                }
+                if drag.hovered() {
+                    ui.ctx().set_cursor_icon(egui::CursorIcon::Grab);
+                }
+            }
+
             let mut child_ui = ui.new_child(
                 UiBuilder::new()
                     .max_rect(rect)
@@ -93,6 +119,12 @@ impl<'a> NavTitle<'a> {
                         ColumnsAction::Switch(from, to_index),
                     )))
                 }
+                TitleResponse::BindAccount(account) => Some(RenderNavAction::SwitchingAction(
+                    SwitchingAction::Columns(ColumnsAction::BindAccount(self.col_id, account)),
+                )),
+                TitleResponse::MinPow(bits) => Some(RenderNavAction::SwitchingAction(
+                    SwitchingAction::Columns(ColumnsAction::MinPow(self.col_id, bits)),
+                )),
             }
         } else if back_button_resp.is_some_and(|r| r.clicked()) {
             tracing::debug!("render nav action back");
@@ -201,6 +233,149 @@ impl<'a> NavTitle<'a> {
         }
     }
 
+    /// Shows who the column acts as, and lets the user pick another
+    /// account for it
+    fn account_section(&mut self, ui: &mut egui::Ui) -> Option<TitleResponse> {
+        let column = self.columns.column(self.col_id);
+        let bound = column.account;
+        if bound.is_none() && self.accounts.num_accounts() < 2 {
+            return None;
+        }
+
+        let id = ui.id().with("column-account");
+        let txn = Transaction::new(self.ndb).unwrap();
+        let acting = column.acting_pubkey(self.accounts);
+
+        let pfp_size = 20.0;
+        let mut resp = if let Some(mut pfp) =
+            acting.and_then(|pk| self.pubkey_pfp(&txn, pk.bytes(), pfp_size))
+        {
+            ui.add(&mut pfp)
+        } else {
+            ui.add(
+                &mut ProfilePic::new(self.img_cache, notedeck::profile::no_pfp_url())
+                    .size(pfp_size)
+                    .sense(Sense::click()),
+            )
+        };
+
+        let open = ui.data(|d| d.get_temp::<bool>(id)).unwrap_or(false);
+        if !open {
+            let hover = match acting {
+                Some(pk) if bound.is_some() => {
+                    format!("Acting as {}", self.account_name(&txn, &pk))
+                }
+                _ => "Acting as the selected account".to_owned(),
+            };
+            resp = resp.on_hover_text(hover);
+        }
+
+        if resp.clicked() {
+            ui.data_mut(|d| d.insert_temp(id, !open));
+        }
+
+        if !open {
+            return None;
+        }
+
+        let mut picked = None;
+        resp.show_tooltip_ui(|ui| {
+            ui.label(
+                RichText::new("Act in this column as")
+                    .text_style(NotedeckTextStyle::Small.text_style())
+                    .color(ui.visuals().weak_text_color()),
+            );
+
+            if ui
+                .selectable_label(bound.is_none(), "Selected account")
+                .clicked()
+            {
+                picked = Some(None);
+            }
+
+            for account in self.accounts.get_accounts() {
+                let pubkey = account.key.pubkey;
+                let name = self.account_name(&txn, &pubkey);
+                if ui.selectable_label(bound == Some(pubkey), name).clicked() {
+                    picked = Some(Some(pubkey));
+                }
+            }
+        });
+
+        if picked.is_some() || resp.clicked_elsewhere() {
+            ui.data_mut(|d| d.remove_temp::<bool>(id));
+        }
+
+        picked.map(TitleResponse::BindAccount)
+    }
+
+    /// Lets a timeline column hide notes without enough proof of work
+    fn pow_section(&mut self, ui: &mut egui::Ui, top: &Route) -> Option<TitleResponse> {
+        if !matches!(top, Route::Timeline(_)) {
+            return None;
+        }
+
+        let min_pow = self.columns.column(self.col_id).min_pow;
+        let id = ui.id().with("column-min-pow");
+
+        let (text, color) = if min_pow > 0 {
+            (format!("⛏{min_pow}"), ui.visuals().text_color())
+        } else {
+            ("⛏".to_owned(), ui.visuals().weak_text_color())
+        };
+        let mut resp =
+            ui.add(egui::Button::new(RichText::new(text).small().color(color)).frame(false));
+
+        let open = ui.data(|d| d.get_temp::<bool>(id)).unwrap_or(false);
+        if !open {
+            resp = resp.on_hover_text(if min_pow > 0 {
+                format!("Showing notes with at least {min_pow} bits of proof of work")
+            } else {
+                "Filter by proof of work".to_owned()
+            });
+        }
+
+        if resp.clicked() {
+            ui.data_mut(|d| d.insert_temp(id, !open));
+        }
+
+        if !open {
+            return None;
+        }
+
+        let mut picked = None;
+        resp.show_tooltip_ui(|ui| {
+            ui.label(
+                RichText::new("Minimum proof of work")
+                    .text_style(NotedeckTextStyle::Small.text_style())
+                    .color(ui.visuals().weak_text_color()),
+            );
+
+            if ui.selectable_label(min_pow == 0, "Any").clicked() {
+                picked = Some(0);
+            }
+            for bits in MIN_POW_PRESETS {
+                if ui
+                    .selectable_label(min_pow == bits, format!("{bits} bits"))
+                    .clicked()
+                {
+                    picked = Some(bits);
+                }
+            }
+        });
+
+        if picked.is_some() || resp.clicked_elsewhere() {
+            ui.data_mut(|d| d.remove_temp::<bool>(id));
+        }
+
+        picked.map(TitleResponse::MinPow)
+    }
+
+    fn account_name(&self, txn: &Transaction, pubkey: &Pubkey) -> String {
+        let profile = self.ndb.get_profile_by_pubkey(txn, pubkey.bytes()).ok();
+        get_display_name(profile.as_ref()).name().to_owned()
+    }
+
     // returns the column index to switch to, if any
     fn move_button_section(&mut self, ui: &mut egui::Ui) -> Option<usize> {
         let cur_id = ui.id().with("move");
@@ -435,7 +610,11 @@ impl<'a> NavTitle<'a> {
                     ),
                 ),
 
-                TimelineKind::Profile(pubkey) => Some(self.show_profile(ui, pubkey, pfp_size)),
+                TimelineKind::Profile(pubkey)
+                | TimelineKind::ProfileMedia(pubkey)
+                | TimelineKind::ProfileZaps(pubkey) => {
+                    Some(self.show_profile(ui, pubkey, pfp_size))
+                }
 
                 TimelineKind::Thread(_) => {
                     // no pfp for threads
@@ -453,6 +632,15 @@ impl<'a> NavTitle<'a> {
                 | TimelineKind::Notifications(_)
                 | TimelineKind::Generic(_)
                 | TimelineKind::List(_) => Some(self.timeline_pfp(ui, kind, pfp_size)),
+
+                TimelineKind::Watchlist(_pks) => Some(
+                    ui.add(
+                        egui::Image::new(egui::include_image!(
+                            "../../../../../assets/icons/eye-dark.png"
+                        ))
+                        .fit_to_exact_size(egui::vec2(pfp_size, pfp_size)),
+                    ),
+                ),
             },
             Route::Reply(_) => None,
             Route::Quote(_) => None,
@@ -461,12 +649,20 @@ impl<'a> NavTitle<'a> {
             Route::AddColumn(_add_col_route) => None,
             Route::Support => None,
             Route::Relays => None,
+            Route::Muted => None,
+            Route::Drafts => None,
+            Route::Scheduled => None,
+            Route::RecurringZaps => None,
+            Route::TipJar => None,
+            Route::Settings => None,
+            Route::WhatsNew => None,
             Route::NewDeck => None,
             Route::EditDeck(_) => None,
             Route::EditProfile(pubkey) => Some(self.show_profile(ui, pubkey, pfp_size)),
             Route::Search => Some(ui.add(ui::side_panel::search_button())),
             Route::Wallet(_) => None,
             Route::CustomizeZapAmount(_) => None,
+            Route::Inspect(_) => None,
         }
     }
 
@@ -520,12 +716,14 @@ impl<'a> NavTitle<'a> {
             } else {
                 let move_col = self.move_button_section(ui);
                 let remove_col = self.delete_button_section(ui);
+                let bind_account = self.account_section(ui);
+                let min_pow = self.pow_section(ui, top);
                 if let Some(col) = move_col {
                     Some(TitleResponse::MoveColumn(col))
                 } else if remove_col {
                     Some(TitleResponse::RemoveColumn)
                 } else {
-                    None
+                    bind_account.or(min_pow)
                 }
             }
         })
@@ -546,6 +744,9 @@ impl<'a> NavTitle<'a> {
         }
 
         self.title_label(ui, top);
+        if self.unread > 0 {
+            unread_badge(ui, self.unread);
+        }
 
         pfp_r.and_then(|r| {
             if r.clicked() {
@@ -562,6 +763,28 @@ enum TitleResponse {
     RemoveColumn,
     PfpClicked,
     MoveColumn(usize),
+    BindAccount(Option<Pubkey>),
+    MinPow(u32),
+}
+
+fn unread_badge(ui: &mut egui::Ui, unread: usize) {
+    let text = if unread > 99 {
+        "99+".to_owned()
+    } else {
+        unread.to_string()
+    };
+    egui::Frame::new()
+        .fill(notedeck_ui::colors::PINK)
+        .corner_radius(8.0)
+        .inner_margin(Margin::symmetric(6, 1))
+        .show(ui, |ui| {
+            ui.add(
+                egui::Label::new(RichText::new(text).size(11.0).color(egui::Color32::WHITE))
+                    .selectable(false),
+            );
+        })
+        .response
+        .on_hover_text(format!("{unread} new notes"));
 }
 
 fn prev<R>(xs: &[R]) -> Option<&R> {
@@ -0,0 +1,110 @@
+use egui::{Align, Layout, RichText, Ui};
+use nostrdb::{Ndb, Transaction};
+use notedeck::{name::get_display_name, NotedeckTextStyle};
+use notedeck_ui::padding;
+
+use crate::{draft::Drafts, ui::note::PostType};
+
+/// How much of a draft's text its row shows
+const PREVIEW_CHARS: usize = 80;
+
+pub enum DraftsAction {
+    Resume(PostType),
+    Discard(PostType),
+}
+
+/// The notes, replies and quotes that were started but not posted. They're
+/// autosaved, so these survive restarts.
+pub struct DraftsView<'a> {
+    ndb: &'a Ndb,
+    drafts: &'a Drafts,
+}
+
+impl<'a> DraftsView<'a> {
+    pub fn new(ndb: &'a Ndb, drafts: &'a Drafts) -> Self {
+        DraftsView { ndb, drafts }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<DraftsAction> {
+        let mut action = None;
+        let txn = Transaction::new(self.ndb).expect("txn");
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                padding(16.0, ui, |ui| {
+                    let drafts = self.drafts.saved();
+                    if drafts.is_empty() {
+                        ui.label(
+                            RichText::new("No drafts. Anything you start writing is kept here until you post it.")
+                                .color(ui.visuals().weak_text_color()),
+                        );
+                        return;
+                    }
+
+                    for (post_type, draft) in drafts {
+                        let heading = self.heading(&txn, &post_type);
+                        let mut preview = draft
+                            .buffer
+                            .text_buffer
+                            .chars()
+                            .take(PREVIEW_CHARS)
+                            .collect::<String>();
+                        if draft.buffer.text_buffer.chars().count() > PREVIEW_CHARS {
+                            preview.push('…');
+                        }
+                        let attachments = draft.uploaded_media.len();
+
+                        ui.horizontal(|ui| {
+                            ui.vertical(|ui| {
+                                ui.label(
+                                    RichText::new(heading)
+                                        .text_style(NotedeckTextStyle::Body.text_style())
+                                        .strong(),
+                                );
+                                if !preview.is_empty() {
+                                    ui.label(preview);
+                                }
+                                if attachments > 0 {
+                                    ui.weak(match attachments {
+                                        1 => "1 attachment".to_owned(),
+                                        n => format!("{n} attachments"),
+                                    });
+                                }
+                            });
+
+                            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                if ui.small_button("Discard").clicked() {
+                                    action = Some(DraftsAction::Discard(post_type.clone()));
+                                }
+                                if ui.small_button("Resume").clicked() {
+                                    action = Some(DraftsAction::Resume(post_type.clone()));
+                                }
+                            });
+                        });
+                        ui.separator();
+                    }
+                });
+            });
+
+        action
+    }
+
+    fn heading(&self, txn: &Transaction, post_type: &PostType) -> String {
+        let (what, id) = match post_type {
+            PostType::New => return "New note".to_owned(),
+            PostType::Reply(id) => ("Reply to", id),
+            PostType::Quote(id) => ("Quote of", id),
+        };
+
+        let author = self
+            .ndb
+            .get_note_by_id(txn, id.bytes())
+            .ok()
+            .and_then(|note| self.ndb.get_profile_by_pubkey(txn, note.pubkey()).ok());
+        match author {
+            Some(profile) => format!("{what} {}", get_display_name(Some(&profile)).name()),
+            None => format!("{what} a note"),
+        }
+    }
+}
@@ -14,6 +14,9 @@ static EDIT_TEXT: &str = "Edit Deck";
 pub enum EditDeckResponse {
     Edit(ConfigureDeckResponse),
     Delete,
+
+    /// Copy the deck's columns to share them
+    Export,
 }
 
 impl<'a> EditDeckView<'a> {
@@ -26,9 +29,18 @@ impl<'a> EditDeckView<'a> {
         let mut edit_deck_resp = None;
 
         padding(egui::Margin::symmetric(16, 4), ui, |ui| {
-            if ui.add(delete_button()).clicked() {
-                edit_deck_resp = Some(EditDeckResponse::Delete);
-            }
+            ui.horizontal(|ui| {
+                if ui.add(delete_button()).clicked() {
+                    edit_deck_resp = Some(EditDeckResponse::Delete);
+                }
+                if ui
+                    .add(egui::Button::new("Copy to share").min_size(egui::vec2(108.0, 40.0)))
+                    .on_hover_text("Copy this deck's columns, to import them on another machine")
+                    .clicked()
+                {
+                    edit_deck_resp = Some(EditDeckResponse::Export);
+                }
+            });
         });
 
         if let Some(config_resp) = self.config_view.ui(ui) {
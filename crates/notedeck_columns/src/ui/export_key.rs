@@ -0,0 +1,197 @@
+use egui::{Button, Frame, InnerResponse, Margin, RichText, TextEdit, Vec2};
+use enostr::Pubkey;
+use notedeck::{NcryptsecError, NotedeckTextStyle, PassphraseStrength};
+use notedeck_ui::qr_code;
+use poll_promise::Promise;
+
+/// The passphrase being typed and the ncryptsec we made with it
+#[derive(Default)]
+pub struct ExportKeyState {
+    passphrase: String,
+    confirm: String,
+    show_passphrase: bool,
+
+    /// The account the export below belongs to
+    pubkey: Option<Pubkey>,
+    export: Option<Promise<Result<String, NcryptsecError>>>,
+}
+
+impl ExportKeyState {
+    /// Start over if the selected account changed since we last looked
+    fn check_account(&mut self, pubkey: Option<&Pubkey>) {
+        if self.pubkey.as_ref() != pubkey {
+            *self = ExportKeyState {
+                pubkey: pubkey.copied(),
+                ..Default::default()
+            };
+        }
+    }
+
+    pub fn set_export(&mut self, export: Promise<Result<String, NcryptsecError>>) {
+        self.export = Some(export);
+    }
+
+    /// Forget the passphrase and the ncryptsec we made with it
+    pub fn clear(&mut self) {
+        *self = ExportKeyState::default();
+    }
+}
+
+pub enum ExportKeyResponse {
+    /// Encrypt the account's key with this passphrase
+    Encrypt(String),
+
+    Done,
+}
+
+pub struct ExportKeyView<'a> {
+    state: &'a mut ExportKeyState,
+
+    /// The account we're exporting, if we have its secret key
+    pubkey: Option<&'a Pubkey>,
+}
+
+impl<'a> ExportKeyView<'a> {
+    pub fn new(state: &'a mut ExportKeyState, pubkey: Option<&'a Pubkey>) -> Self {
+        ExportKeyView { state, pubkey }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> InnerResponse<Option<ExportKeyResponse>> {
+        self.state.check_account(self.pubkey);
+
+        Frame::new().outer_margin(12.0).show(ui, |ui| {
+            ui.vertical(|ui| {
+                if self.pubkey.is_none() {
+                    ui.label("This account doesn't have a secret key to export.");
+                    return None;
+                }
+
+                match self.state.export.as_ref().map(|p| p.ready()) {
+                    Some(Some(Ok(ncryptsec))) => exported_ui(ui, ncryptsec),
+                    Some(None) => {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::Spinner::new());
+                            ui.label("Encrypting...");
+                        });
+                        None
+                    }
+                    Some(Some(Err(err))) => {
+                        let err = err.to_string();
+                        let resp = self.passphrase_ui(ui);
+                        ui.label(RichText::new(err).color(ui.visuals().error_fg_color));
+                        resp
+                    }
+                    None => self.passphrase_ui(ui),
+                }
+            })
+            .inner
+        })
+    }
+
+    fn passphrase_ui(&mut self, ui: &mut egui::Ui) -> Option<ExportKeyResponse> {
+        ui.label(
+            RichText::new(
+                "Encrypt your secret key with a passphrase (NIP-49). You'll need both the \
+                 ncryptsec and the passphrase to log in with it.",
+            )
+            .color(ui.visuals().weak_text_color()),
+        );
+        ui.add_space(8.0);
+
+        let show = self.state.show_passphrase;
+        ui.add(passphrase_textedit(
+            &mut self.state.passphrase,
+            "Passphrase",
+            show,
+        ));
+        ui.add_space(4.0);
+        ui.add(passphrase_textedit(
+            &mut self.state.confirm,
+            "Confirm passphrase",
+            show,
+        ));
+        ui.checkbox(&mut self.state.show_passphrase, "Show passphrase");
+
+        ui.add_space(8.0);
+        let strength = PassphraseStrength::of(&self.state.passphrase);
+        strength_meter(ui, strength);
+
+        let matches = self.state.passphrase == self.state.confirm;
+        if !matches && !self.state.confirm.is_empty() {
+            ui.label(RichText::new("Passphrases don't match").color(ui.visuals().error_fg_color));
+        }
+
+        ui.add_space(8.0);
+        let can_encrypt = matches && strength.is_acceptable();
+        if ui
+            .add_enabled(can_encrypt, Button::new("Encrypt key"))
+            .clicked()
+        {
+            return Some(ExportKeyResponse::Encrypt(self.state.passphrase.clone()));
+        }
+
+        None
+    }
+}
+
+fn exported_ui(ui: &mut egui::Ui, ncryptsec: &str) -> Option<ExportKeyResponse> {
+    ui.label("Your encrypted key:");
+    ui.add_space(4.0);
+    ui.add(
+        egui::Label::new(
+            RichText::new(ncryptsec)
+                .monospace()
+                .text_style(NotedeckTextStyle::Small.text_style()),
+        )
+        .wrap(),
+    );
+
+    ui.add_space(4.0);
+    if ui.small_button("Copy").clicked() {
+        ui.ctx().copy_text(ncryptsec.to_owned());
+    }
+
+    ui.add_space(8.0);
+    ui.vertical_centered(|ui| {
+        qr_code(ui, ncryptsec, 240.0);
+    });
+
+    ui.add_space(8.0);
+    ui.button("Done")
+        .clicked()
+        .then_some(ExportKeyResponse::Done)
+}
+
+pub fn passphrase_textedit<'t>(text: &'t mut String, hint: &str, show: bool) -> TextEdit<'t> {
+    TextEdit::singleline(text)
+        .hint_text(hint)
+        .password(!show)
+        .min_size(Vec2::new(0.0, 32.0))
+        .margin(Margin::same(8))
+}
+
+/// A bar that fills up and goes from red to green as the passphrase
+/// gets harder to guess
+pub fn strength_meter(ui: &mut egui::Ui, strength: PassphraseStrength) {
+    let color = match strength {
+        PassphraseStrength::TooShort | PassphraseStrength::Weak => ui.visuals().error_fg_color,
+        PassphraseStrength::Fair => ui.visuals().warn_fg_color,
+        PassphraseStrength::Strong | PassphraseStrength::VeryStrong => {
+            egui::Color32::from_rgb(0x3C, 0xB3, 0x71)
+        }
+    };
+
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::ProgressBar::new(strength.fraction())
+                .desired_width(160.0)
+                .desired_height(6.0)
+                .fill(color),
+        );
+        ui.label(
+            RichText::new(strength.label())
+                .text_style(NotedeckTextStyle::Small.text_style())
+                .color(color),
+        );
+    });
+}
@@ -0,0 +1,58 @@
+use egui::{RichText, TextEdit};
+use notedeck::NotedeckTextStyle;
+use notedeck_ui::padding;
+
+use crate::deck_state::DeckState;
+
+/// Under the new deck form: paste a deck someone copied with "Copy to
+/// share" to get its columns instead of starting empty
+pub struct ImportDeckView<'a> {
+    state: &'a mut DeckState,
+}
+
+impl<'a> ImportDeckView<'a> {
+    pub fn new(state: &'a mut DeckState) -> Self {
+        Self { state }
+    }
+
+    /// The pasted json, once Import is clicked
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<String> {
+        let mut import = None;
+
+        padding(16.0, ui, |ui| {
+            ui.separator();
+            ui.label(
+                RichText::new("Or import a shared deck")
+                    .text_style(NotedeckTextStyle::Body.text_style())
+                    .strong(),
+            );
+            ui.weak("Paste a deck copied with Copy to share from the deck's settings");
+            ui.add_space(4.0);
+
+            let resp = ui.add(
+                TextEdit::multiline(&mut self.state.import_json)
+                    .code_editor()
+                    .desired_rows(4)
+                    .desired_width(f32::INFINITY),
+            );
+            if resp.changed() {
+                self.state.import_error = None;
+            }
+
+            if let Some(err) = &self.state.import_error {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+
+            ui.add_space(4.0);
+            let can_import = !self.state.import_json.trim().is_empty();
+            if ui
+                .add_enabled(can_import, egui::Button::new("Import deck"))
+                .clicked()
+            {
+                import = Some(self.state.import_json.clone());
+            }
+        });
+
+        import
+    }
+}
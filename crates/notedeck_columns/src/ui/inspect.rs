@@ -0,0 +1,149 @@
+use egui::{RichText, Ui};
+use enostr::NoteId;
+use nostrdb::{Ndb, Note, Transaction};
+use notedeck::{nip19, NotedeckTextStyle};
+use notedeck_ui::padding;
+
+/// A note as it came off the wire: its json, tags and the relays we saw
+/// it on. For figuring out why something renders the way it does.
+pub struct InspectView<'a> {
+    ndb: &'a Ndb,
+    note_id: &'a NoteId,
+}
+
+impl<'a> InspectView<'a> {
+    pub fn new(ndb: &'a Ndb, note_id: &'a NoteId) -> Self {
+        InspectView { ndb, note_id }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        let txn = Transaction::new(self.ndb).expect("txn");
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                padding(16.0, ui, |ui| {
+                    let Ok(note) = self.ndb.get_note_by_id(&txn, self.note_id.bytes()) else {
+                        ui.weak("This note isn't in the database.");
+                        return;
+                    };
+
+                    summary_ui(ui, &txn, &note);
+
+                    ui.add_space(16.0);
+                    heading(ui, "Tags");
+                    tags_ui(ui, &note);
+
+                    ui.add_space(16.0);
+                    heading(ui, "Seen on");
+                    let relays: Vec<&str> = note.relays(&txn).collect();
+                    if relays.is_empty() {
+                        ui.weak("No relays, it was published or found locally");
+                    }
+                    for relay in relays {
+                        ui.label(RichText::new(relay).monospace());
+                    }
+
+                    ui.add_space(16.0);
+                    heading(ui, "JSON");
+                    json_ui(ui, &note);
+                });
+            });
+    }
+}
+
+fn heading(ui: &mut Ui, title: &str) {
+    ui.label(RichText::new(title).text_style(NotedeckTextStyle::Heading3.text_style()));
+    ui.add_space(4.0);
+}
+
+fn summary_ui(ui: &mut Ui, txn: &Transaction, note: &Note) {
+    egui::Grid::new("inspect_summary")
+        .num_columns(3)
+        .spacing([8.0, 4.0])
+        .show(ui, |ui| {
+            copyable_row(ui, "id", &hex::encode(note.id()));
+            copyable_row(ui, "pubkey", &hex::encode(note.pubkey()));
+            if let Some(link) = nip19::note_link(note, txn) {
+                copyable_row(ui, "link", &link);
+            }
+
+            ui.weak("kind");
+            ui.label(RichText::new(note.kind().to_string()).monospace());
+            ui.end_row();
+
+            ui.weak("created_at");
+            ui.label(RichText::new(note.created_at().to_string()).monospace())
+                .on_hover_text(notedeck::time_ago_since(note.created_at()));
+            ui.end_row();
+        });
+}
+
+fn copyable_row(ui: &mut Ui, label: &str, value: &str) {
+    ui.weak(label);
+    ui.add(egui::Label::new(RichText::new(value).monospace()).truncate());
+    if ui.small_button("Copy").clicked() {
+        ui.ctx().copy_text(value.to_owned());
+    }
+    ui.end_row();
+}
+
+fn tags_ui(ui: &mut Ui, note: &Note) {
+    if note.tags().count() == 0 {
+        ui.weak("No tags");
+        return;
+    }
+
+    egui::Grid::new("inspect_tags")
+        .striped(true)
+        .spacing([12.0, 4.0])
+        .show(ui, |ui| {
+            for tag in note.tags() {
+                for i in 0..tag.count() {
+                    let Some(value) = tag.get(i) else {
+                        continue;
+                    };
+                    let text = value
+                        .variant()
+                        .str()
+                        .map(str::to_owned)
+                        .or_else(|| value.variant().id().map(hex::encode))
+                        .unwrap_or_default();
+                    let text = RichText::new(text).monospace();
+                    let text = if i == 0 { text.strong() } else { text };
+                    ui.add(egui::Label::new(text).truncate());
+                }
+                ui.end_row();
+            }
+        });
+}
+
+fn json_ui(ui: &mut Ui, note: &Note) {
+    let json = match note.json() {
+        Ok(json) => json,
+        Err(e) => {
+            ui.weak(format!("Couldn't serialize the note: {e}"));
+            return;
+        }
+    };
+    let pretty = serde_json::from_str::<serde_json::Value>(&json)
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .unwrap_or_else(|_| json.clone());
+
+    ui.horizontal(|ui| {
+        if ui.small_button("Copy pretty").clicked() {
+            ui.ctx().copy_text(pretty.clone());
+        }
+        if ui.small_button("Copy compact").clicked() {
+            ui.ctx().copy_text(json.clone());
+        }
+    });
+    ui.add_space(4.0);
+
+    let mut text = pretty.as_str();
+    ui.add(
+        egui::TextEdit::multiline(&mut text)
+            .code_editor()
+            .desired_width(f32::INFINITY),
+    );
+}
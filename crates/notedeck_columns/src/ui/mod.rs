@@ -1,29 +1,50 @@
 pub mod account_login_view;
 pub mod accounts;
 pub mod add_column;
+pub mod app_lock;
 pub mod column;
 pub mod configure_deck;
+pub mod drafts;
 pub mod edit_deck;
+pub mod export_key;
 pub mod images;
+pub mod import_deck;
+pub mod inspect;
+pub mod muted;
 pub mod note;
 pub mod post;
+pub mod pow;
 pub mod preview;
 pub mod profile;
+pub mod recurring_zaps;
 pub mod relay;
+pub mod scheduled;
 pub mod search;
 pub mod search_results;
+pub mod settings;
 pub mod side_panel;
+pub mod signer;
 pub mod support;
 pub mod thread;
 pub mod timeline;
+pub mod tip_jar;
 pub mod wallet;
+pub mod whats_new;
 pub mod widgets;
 
 pub use accounts::AccountsView;
+pub use drafts::{DraftsAction, DraftsView};
+pub use inspect::InspectView;
+pub use muted::{MutedView, MutedViewAction};
 pub use note::{PostReplyView, PostView};
 pub use preview::{Preview, PreviewApp, PreviewConfig};
 pub use profile::ProfileView;
-pub use relay::RelayView;
+pub use recurring_zaps::{RecurringZapsAction, RecurringZapsView};
+pub use relay::{RelayView, RelayViewAction};
+pub use scheduled::{ScheduledAction, ScheduledView};
+pub use settings::{SettingsAction, SettingsView};
 pub use side_panel::{DesktopSidePanel, SidePanelAction};
 pub use thread::ThreadView;
 pub use timeline::TimelineView;
+pub use tip_jar::{TipJarAction, TipJarState, TipJarView};
+pub use whats_new::WhatsNewView;
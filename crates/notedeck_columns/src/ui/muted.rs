@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use egui::{Align, Id, Layout, Margin, RichText, Ui, Vec2};
+use enostr::NoteId;
+use nostrdb::{Ndb, Transaction};
+use notedeck::{
+    MuteAction, MuteItem, Muted, NotedeckTextStyle, WotMode, WotSettings, MAX_WOT_HOPS,
+};
+use notedeck_ui::padding;
+
+use super::widgets::styled_button;
+
+pub enum MutedViewAction {
+    Mute(MuteAction),
+    Wot(WotSettings),
+}
+
+/// Manage the selected account's muted words, hashtags and threads. Edits
+/// are published to its NIP-51 mute list.
+pub struct MutedView<'a> {
+    ndb: &'a Ndb,
+    muted: &'a Muted,
+    wot: WotSettings,
+
+    /// Relays have told us what they have for the mute list
+    synced: bool,
+    id_string_map: &'a mut HashMap<Id, String>,
+}
+
+impl<'a> MutedView<'a> {
+    pub fn new(
+        ndb: &'a Ndb,
+        muted: &'a Muted,
+        wot: WotSettings,
+        synced: bool,
+        id_string_map: &'a mut HashMap<Id, String>,
+    ) -> Self {
+        MutedView {
+            ndb,
+            muted,
+            wot,
+            synced,
+            id_string_map,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<MutedViewAction> {
+        let mut action = None;
+        let mut wot_action = None;
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                padding(16.0, ui, |ui| {
+                    // adding to a list we haven't seen yet would replace it
+                    let can_add = self.synced || !is_empty(self.muted);
+                    if !can_add {
+                        ui.label(
+                            RichText::new("Still fetching your mute list from relays…")
+                                .color(ui.visuals().weak_text_color()),
+                        );
+                        ui.add_space(8.0);
+                    }
+
+                    section_heading(ui, "Words");
+                    let words = self.muted.words.iter().cloned().map(MuteItem::Word);
+                    if let Some(item) = item_list(ui, words.map(|item| (label(&item), item))) {
+                        action = Some(MuteAction::Unmute(item));
+                    }
+                    if let Some(word) =
+                        self.add_entry(ui, "mute-word", "Mute a word or phrase", can_add)
+                    {
+                        action = MuteItem::word(&word).map(MuteAction::Mute);
+                    }
+
+                    ui.add_space(16.0);
+                    section_heading(ui, "Hashtags");
+                    let hashtags = self.muted.hashtags.iter().cloned().map(MuteItem::Hashtag);
+                    if let Some(item) = item_list(ui, hashtags.map(|item| (label(&item), item))) {
+                        action = Some(MuteAction::Unmute(item));
+                    }
+                    if let Some(hashtag) =
+                        self.add_entry(ui, "mute-hashtag", "Mute a hashtag", can_add)
+                    {
+                        action = MuteItem::hashtag(&hashtag).map(MuteAction::Mute);
+                    }
+
+                    ui.add_space(16.0);
+                    section_heading(ui, "Threads");
+                    if self.muted.threads.is_empty() {
+                        ui.label(
+                            RichText::new("Mute a thread from any note's ⋯ menu")
+                                .color(ui.visuals().weak_text_color()),
+                        );
+                    }
+                    let txn = Transaction::new(self.ndb).expect("txn");
+                    let threads = self.muted.threads.iter().map(|id| {
+                        let label = thread_label(self.ndb, &txn, id);
+                        (label, MuteItem::Thread(NoteId::new(*id)))
+                    });
+                    if let Some(item) = item_list(ui, threads) {
+                        action = Some(MuteAction::Unmute(item));
+                    }
+
+                    ui.add_space(16.0);
+                    section_heading(ui, "Web of trust");
+                    wot_action = wot_settings_ui(ui, self.wot);
+                });
+            });
+
+        action.map(MutedViewAction::Mute).or(wot_action)
+    }
+
+    /// A text field with an add button. Returns the text when added.
+    fn add_entry(&mut self, ui: &mut Ui, salt: &str, hint: &str, enabled: bool) -> Option<String> {
+        let id = ui.id().with(salt);
+        let buffer = self.id_string_map.entry(id).or_default();
+
+        let added = ui
+            .horizontal(|ui| {
+                let button_width = 60.0;
+                let text_edit = egui::TextEdit::singleline(buffer)
+                    .hint_text(hint)
+                    .vertical_align(Align::Center)
+                    .desired_width(ui.available_width() - button_width - 8.0)
+                    .min_size(Vec2::new(0.0, 32.0))
+                    .margin(Margin::same(8));
+                let resp = ui.add_enabled(enabled, text_edit);
+                let submitted = resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+                let clicked = ui
+                    .add_enabled(
+                        enabled && !buffer.trim().is_empty(),
+                        styled_button("Mute", notedeck_ui::colors::PINK),
+                    )
+                    .clicked();
+
+                (clicked || submitted) && !buffer.trim().is_empty()
+            })
+            .inner;
+
+        if added {
+            self.id_string_map.remove(&id)
+        } else {
+            None
+        }
+    }
+}
+
+fn is_empty(muted: &Muted) -> bool {
+    muted.pubkeys.is_empty()
+        && muted.hashtags.is_empty()
+        && muted.words.is_empty()
+        && muted.threads.is_empty()
+}
+
+fn section_heading(ui: &mut Ui, title: &str) {
+    ui.label(RichText::new(title).text_style(NotedeckTextStyle::Heading3.text_style()));
+    ui.add_space(4.0);
+}
+
+fn label(item: &MuteItem) -> String {
+    match item {
+        MuteItem::Hashtag(hashtag) => format!("#{hashtag}"),
+        MuteItem::Word(word) => word.clone(),
+        MuteItem::Pubkey(pk) => pk.hex(),
+        MuteItem::Thread(id) => id.hex(),
+    }
+}
+
+/// The start of the thread's root note, or its id if we don't have it
+fn thread_label(ndb: &Ndb, txn: &Transaction, id: &[u8; 32]) -> String {
+    let Ok(note) = ndb.get_note_by_id(txn, id) else {
+        return hex::encode(&id[..8]);
+    };
+
+    let content = note.content().lines().next().unwrap_or_default();
+    if content.chars().count() > 60 {
+        format!("{}…", content.chars().take(60).collect::<String>())
+    } else {
+        content.to_owned()
+    }
+}
+
+/// One row per item with an unmute button. Returns the item to unmute.
+fn item_list(ui: &mut Ui, items: impl Iterator<Item = (String, MuteItem)>) -> Option<MuteItem> {
+    let mut unmute = None;
+
+    for (label, item) in items {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(label).text_style(NotedeckTextStyle::Body.text_style()));
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                if ui.small_button("Unmute").clicked() {
+                    unmute = Some(item);
+                }
+            });
+        });
+    }
+
+    unmute
+}
+
+/// Returns the new settings if they were changed
+fn wot_settings_ui(ui: &mut Ui, current: WotSettings) -> Option<MutedViewAction> {
+    let mut settings = current;
+
+    ui.checkbox(
+        &mut settings.enabled,
+        "Only show replies, hashtags and search results from people near you",
+    );
+
+    ui.add_enabled_ui(settings.enabled, |ui| {
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ui.label("Up to");
+            for hops in 1..=MAX_WOT_HOPS {
+                let label = match hops {
+                    1 => "people you follow",
+                    2 => "who they follow",
+                    _ => "3 follows away",
+                };
+                ui.selectable_value(&mut settings.max_hops, hops, label);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Everyone else is");
+            ui.radio_value(&mut settings.mode, WotMode::Collapse, "collapsed");
+            ui.radio_value(&mut settings.mode, WotMode::Hide, "hidden");
+        });
+    });
+
+    (settings != current).then_some(MutedViewAction::Wot(settings))
+}
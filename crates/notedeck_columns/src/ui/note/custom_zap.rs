@@ -137,7 +137,7 @@ fn show_title(ui: &mut egui::Ui) {
             let (rect, _) = ui.allocate_exact_size(vec2(max_size, max_size), egui::Sense::hover());
             let painter = ui.painter_at(rect);
             let circle_color = lerp_color(
-                egui::Color32::from_rgb(0xFF, 0xB7, 0x57),
+                notedeck::indicators::colors(ui.ctx()).zap,
                 ui.visuals().noninteractive().bg_fill,
                 0.5,
             );
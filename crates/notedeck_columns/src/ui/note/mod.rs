@@ -3,6 +3,6 @@ pub mod post;
 pub mod quote_repost;
 pub mod reply;
 
-pub use post::{NewPostAction, PostAction, PostResponse, PostType, PostView};
+pub use post::{NewPostAction, PostAction, PostResponse, PostType, PostView, ScheduleAction};
 pub use quote_repost::QuoteRepostView;
 pub use reply::PostReplyView;
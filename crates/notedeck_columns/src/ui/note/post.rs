@@ -1,19 +1,29 @@
-use crate::draft::{Draft, Drafts, MentionHint};
-use crate::media_upload::{nostrbuild_nip96_upload, MediaPath};
-use crate::post::{downcast_post_buffer, MentionType, NewPost};
+use crate::draft::{CompletionHint, CompletionItem, Draft, Drafts, MentionHint, PollDraft};
+use crate::emoji::user_emojis;
+use crate::media_upload::{
+    nostrbuild_nip96_upload, parse_media_server, pasted_media_paths, MediaPath, MediaServer,
+    MediaServerKind, PendingUpload,
+};
+use crate::post::{
+    completion_at, downcast_post_buffer, rank_completions, rank_mentions, reply_audience,
+    CompletionKind, MentionType, NewPost, ReplyThread,
+};
+use crate::pow::ProofOfWork;
+use crate::reply_tags::ReplyTagScheme;
 use crate::ui::search_results::SearchResultsView;
 use crate::ui::{self, Preview, PreviewConfig};
 use crate::Result;
 
 use egui::{
-    text::{CCursorRange, LayoutJob},
+    text::{CCursor, CCursorRange, LayoutJob},
     text_edit::TextEditOutput,
     widgets::text_edit::TextEdit,
     Frame, Layout, Margin, Pos2, ScrollArea, Sense, TextBuffer,
 };
-use enostr::{FilledKeypair, FullKeypair, KeypairUnowned, NoteId, Pubkey, RelayPool};
-use nostrdb::{Ndb, Transaction};
+use enostr::{FullKeypair, Keypair, KeypairUnowned, NoteId, Pubkey, RelayPool};
+use nostrdb::{Filter, Ndb, Note, Transaction};
 use notedeck_ui::blur::PixelDimensions;
+use notedeck_ui::contacts::contacts_filter;
 use notedeck_ui::images::{get_render_state, RenderState};
 use notedeck_ui::jobs::JobsCache;
 use notedeck_ui::{
@@ -21,22 +31,49 @@ use notedeck_ui::{
     note::render_note_preview,
     NoteOptions, ProfilePic,
 };
+use std::collections::HashSet;
 
-use notedeck::{name::get_display_name, supported_mime_hosted_at_url, NoteAction, NoteContext};
+use notedeck::{
+    name::{get_display_name, summarize_names},
+    supported_mime_hosted_at_url, Accounts, NoteAction, NoteContext, SignPurpose, UnsignedEvent,
+};
 use tracing::error;
 
+/// How many profiles the search turns up before ranking
+const MENTION_SEARCH_LIMIT: u32 = 30;
+
+/// How many ranked profiles the mention list shows
+const MAX_MENTION_HINTS: usize = 10;
+
+/// How far back we look for people the poster interacted with
+const RECENT_INTERACTIONS: i32 = 100;
+
+/// How many hashtags or emoji the completion list shows
+const MAX_COMPLETIONS: usize = 8;
+
+/// How many of the poster's notes we look through for hashtags they use
+const RECENT_HASHTAG_NOTES: i32 = 100;
+
+#[derive(Clone, Copy)]
+enum HintKey {
+    Up,
+    Down,
+    Select,
+}
+
 pub struct PostView<'a, 'd> {
     note_context: &'a mut NoteContext<'d>,
     draft: &'a mut Draft,
     post_type: PostType,
-    poster: FilledKeypair<'a>,
+    poster: KeypairUnowned<'a>,
     id_source: Option<egui::Id>,
     inner_rect: egui::Rect,
     note_options: NoteOptions,
     jobs: &'a mut JobsCache,
+    media_server: Option<&'a mut MediaServer>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum PostType {
     New,
     Quote(NoteId),
@@ -49,8 +86,50 @@ pub enum PostAction {
 
     /// The reply/new post action
     NewPostAction(NewPostAction),
+
+    /// Throw away the draft for this post
+    Discard(PostType),
+
+    /// Hold the draft back and post it later
+    Schedule(ScheduleAction),
+}
+
+pub struct ScheduleAction {
+    pub post_type: PostType,
+    pub poster: Pubkey,
+
+    /// Unix time to publish at
+    pub publish_at: u64,
 }
 
+/// The delays offered when scheduling, in seconds
+const SCHEDULE_PRESETS: [(&str, u64); 4] = [
+    ("In 1 hour", 3600),
+    ("In 3 hours", 3 * 3600),
+    ("Tomorrow", 24 * 3600),
+    ("In a week", 7 * 24 * 3600),
+];
+
+/// How long an expiring note can be kept for, in seconds
+const EXPIRATION_PRESETS: [(&str, u64); 4] = [
+    ("1h", 3600),
+    ("1d", 24 * 3600),
+    ("1w", 7 * 24 * 3600),
+    ("30d", 30 * 24 * 3600),
+];
+
+/// Most options a poll can have
+const MAX_POLL_OPTIONS: usize = 10;
+
+/// How long a poll can stay open for, in seconds
+const POLL_DURATIONS: [(&str, Option<u64>); 5] = [
+    ("1 hour", Some(3600)),
+    ("1 day", Some(24 * 3600)),
+    ("3 days", Some(3 * 24 * 3600)),
+    ("1 week", Some(7 * 24 * 3600)),
+    ("No end", None),
+];
+
 pub struct NewPostAction {
     post_type: PostType,
     post: NewPost,
@@ -61,31 +140,85 @@ impl NewPostAction {
         NewPostAction { post_type, post }
     }
 
+    /// The account this will be posted from
+    pub fn poster(&self) -> &Pubkey {
+        &self.post.account.pubkey
+    }
+
+    pub fn post_type(&self) -> &PostType {
+        &self.post_type
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn execute(
         &self,
         ndb: &Ndb,
         txn: &Transaction,
         pool: &mut RelayPool,
+        accounts: &mut Accounts,
         drafts: &mut Drafts,
+        pow: &mut ProofOfWork,
+        reply_tags: ReplyTagScheme,
+    ) -> Result<()> {
+        self.publish(ndb, txn, pool, accounts, pow, reply_tags)?;
+        drafts.get_from_post_type(&self.post_type).clear();
+
+        Ok(())
+    }
+
+    /// Sign and send the post, leaving the draft it came from alone
+    pub fn publish(
+        &self,
+        ndb: &Ndb,
+        txn: &Transaction,
+        pool: &mut RelayPool,
+        accounts: &mut Accounts,
+        pow: &mut ProofOfWork,
+        reply_tags: ReplyTagScheme,
     ) -> Result<()> {
-        let seckey = self.post.account.secret_key.to_secret_bytes();
+        let pubkey = self.post.account.pubkey;
+        let local_seckey = self
+            .post
+            .account
+            .secret_key
+            .as_ref()
+            .map(|sk| sk.to_secret_bytes());
+
+        // accounts with a remote signer build the note with a throwaway
+        // key and have the signer sign its contents
+        let seckey = local_seckey
+            .or_else(|| accounts.signer_session_key(pubkey.bytes()))
+            .ok_or_else(|| crate::Error::Generic("no way to sign for this account".to_owned()))?;
 
         let note = match self.post_type {
             PostType::New => self.post.to_note(&seckey),
 
             PostType::Reply(target) => {
                 let replying_to = ndb.get_note_by_id(txn, target.bytes())?;
-                self.post.to_reply(&seckey, &replying_to)
+                let thread = ReplyThread::new(ndb, txn, &replying_to);
+                self.post
+                    .to_reply(&seckey, &replying_to, &thread, reply_tags)
             }
 
             PostType::Quote(target) => {
                 let quoting = ndb.get_note_by_id(txn, target.bytes())?;
-                self.post.to_quote(&seckey, &quoting)
+                let relays = notedeck::nip19::relay_hints(&quoting, txn);
+                self.post.to_quote(&seckey, &quoting, &relays)
             }
         };
 
-        pool.send(&enostr::ClientMessage::event(&note)?);
-        drafts.get_from_post_type(&self.post_type).clear();
+        if let Some(target) = pow.settings().target() {
+            // signed once it's mined, the id changes with the nonce
+            let unsigned = UnsignedEvent::from_note(&note)
+                .map_err(|e| crate::Error::Generic(e.to_string()))?;
+            pow.start(pubkey, local_seckey, unsigned, target);
+        } else if local_seckey.is_some() {
+            pool.send(&enostr::ClientMessage::event(&note)?);
+        } else {
+            let unsigned = UnsignedEvent::from_note(&note)
+                .map_err(|e| crate::Error::Generic(e.to_string()))?;
+            accounts.sign_remote(pool, pubkey.bytes(), &unsigned, SignPurpose::Publish);
+        }
 
         Ok(())
     }
@@ -102,7 +235,7 @@ impl<'a, 'd> PostView<'a, 'd> {
         note_context: &'a mut NoteContext<'d>,
         draft: &'a mut Draft,
         post_type: PostType,
-        poster: FilledKeypair<'a>,
+        poster: KeypairUnowned<'a>,
         inner_rect: egui::Rect,
         note_options: NoteOptions,
         jobs: &'a mut JobsCache,
@@ -117,6 +250,7 @@ impl<'a, 'd> PostView<'a, 'd> {
             inner_rect,
             note_options,
             jobs,
+            media_server: None,
         }
     }
 
@@ -125,10 +259,22 @@ impl<'a, 'd> PostView<'a, 'd> {
         self
     }
 
+    /// Where attached media is uploaded. Without one uploads go to
+    /// nostr.build and the server can't be changed.
+    pub fn media_server(mut self, media_server: Option<&'a mut MediaServer>) -> Self {
+        self.media_server = media_server;
+        self
+    }
+
     fn editbox(&mut self, txn: &nostrdb::Transaction, ui: &mut egui::Ui) -> egui::Response {
         ui.spacing_mut().item_spacing.x = 12.0;
 
         let pfp_size = 24.0;
+        let pfp_border = self
+            .note_context
+            .account_colors
+            .get(self.poster.pubkey.bytes())
+            .map(|color| egui::Stroke::new(2.0, color));
 
         // TODO: refactor pfp control to do all of this for us
         let poster_pfp = self
@@ -141,14 +287,15 @@ impl<'a, 'd> PostView<'a, 'd> {
                 Some(ProfilePic::from_profile(self.note_context.img_cache, p)?.size(pfp_size))
             });
 
-        if let Some(mut pfp) = poster_pfp {
-            ui.add(&mut pfp);
-        } else {
-            ui.add(
-                &mut ProfilePic::new(self.note_context.img_cache, notedeck::profile::no_pfp_url())
-                    .size(pfp_size),
-            );
+        let mut pfp = match poster_pfp {
+            Some(pfp) => pfp,
+            None => ProfilePic::new(self.note_context.img_cache, notedeck::profile::no_pfp_url())
+                .size(pfp_size),
+        };
+        if let Some(border) = pfp_border {
+            pfp = pfp.border(border);
         }
+        ui.add(&mut pfp);
 
         let mut updated_layout = false;
         let mut layouter = |ui: &egui::Ui, buf: &dyn TextBuffer, wrap_width: f32| {
@@ -176,12 +323,18 @@ impl<'a, 'd> PostView<'a, 'd> {
             ui.fonts(|f| f.layout_job(layout_job))
         };
 
+        if self.focused(ui) {
+            self.take_pasted_files(ui);
+            self.take_dropped_files(ui);
+        }
+
         let textedit = TextEdit::multiline(&mut self.draft.buffer)
             .hint_text(egui::RichText::new("Write a banger note here...").weak())
             .frame(false)
             .desired_width(ui.available_width())
             .layouter(&mut layouter);
 
+        let hint_key = self.take_hint_key(ui);
         let out = textedit.show(ui);
 
         if updated_layout {
@@ -189,7 +342,10 @@ impl<'a, 'd> PostView<'a, 'd> {
         }
 
         if let Some(cursor_index) = get_cursor_index(&out.state.cursor.char_range()) {
-            self.show_mention_hints(txn, ui, cursor_index, &out);
+            self.show_mention_hints(txn, ui, cursor_index, &out, hint_key);
+            self.show_completions(txn, ui, cursor_index, &out, hint_key);
+        } else {
+            self.draft.cur_completion = None;
         }
 
         let focused = out.response.has_focus();
@@ -205,85 +361,345 @@ impl<'a, 'd> PostView<'a, 'd> {
         ui: &mut egui::Ui,
         cursor_index: usize,
         textedit_output: &TextEditOutput,
+        hint_key: Option<HintKey>,
     ) {
-        let Some(mention) = self.draft.buffer.get_mention(cursor_index) else {
+        let Some(mention) = self
+            .draft
+            .buffer
+            .get_mention(cursor_index)
+            .filter(|mention| mention.info.mention_type == MentionType::Pending)
+        else {
+            self.draft.cur_mention_hint = None;
             return;
         };
 
-        if mention.info.mention_type != MentionType::Pending {
-            return;
-        }
-
         if ui.ctx().input(|r| r.key_pressed(egui::Key::Escape)) {
             self.draft.buffer.delete_mention(mention.index);
+            self.draft.cur_mention_hint = None;
             return;
         }
 
         let mention_str = self.draft.buffer.get_mention_string(&mention);
 
         if !mention_str.is_empty() {
+            let stale = !matches!(
+                &self.draft.cur_mention_hint,
+                Some(hint) if hint.text == mention_str
+            );
+            let results = stale.then(|| self.mention_results(txn, mention_str));
+
             if let Some(mention_hint) = &mut self.draft.cur_mention_hint {
                 if mention_hint.index != mention.index {
                     mention_hint.index = mention.index;
                     mention_hint.pos =
                         calculate_mention_hints_pos(textedit_output, mention.info.start_index);
                 }
-                mention_hint.text = mention_str.to_owned();
+                if let Some(results) = results {
+                    mention_hint.text = mention_str.to_owned();
+                    mention_hint.results = results;
+                    mention_hint.selected = 0;
+                }
             } else {
                 self.draft.cur_mention_hint = Some(MentionHint {
                     index: mention.index,
                     text: mention_str.to_owned(),
                     pos: calculate_mention_hints_pos(textedit_output, mention.info.start_index),
+                    results: results.unwrap_or_default(),
+                    selected: 0,
                 });
             }
         }
 
-        let hint_rect = {
-            let hint = if let Some(hint) = &self.draft.cur_mention_hint {
-                hint
-            } else {
-                return;
-            };
-
-            let mut hint_rect = self.inner_rect;
-            hint_rect.set_top(hint.pos.y);
-            hint_rect
-        };
-
-        let Ok(res) = self.note_context.ndb.search_profile(txn, mention_str, 10) else {
+        let Some(hint) = &mut self.draft.cur_mention_hint else {
             return;
         };
 
+        let mut selection = None;
+        if !hint.results.is_empty() {
+            match hint_key {
+                Some(HintKey::Down) => hint.selected = (hint.selected + 1) % hint.results.len(),
+                Some(HintKey::Up) => {
+                    hint.selected = hint
+                        .selected
+                        .checked_sub(1)
+                        .unwrap_or(hint.results.len() - 1)
+                }
+                Some(HintKey::Select) => selection = Some(hint.selected),
+                None => {}
+            }
+        }
+
+        let mut hint_rect = self.inner_rect;
+        hint_rect.set_top(hint.pos.y);
+
+        let results: Vec<&[u8; 32]> = hint.results.iter().collect();
         let resp = SearchResultsView::new(
             self.note_context.img_cache,
             self.note_context.ndb,
             txn,
-            &res,
+            &results,
         )
+        .selected(Some(hint.selected), hint_key.is_some())
         .show_in_rect(hint_rect, ui);
 
         match resp {
-            ui::search_results::SearchResultsResponse::SelectResult(selection) => {
-                if let Some(hint_index) = selection {
-                    if let Some(pk) = res.get(hint_index) {
-                        let record = self.note_context.ndb.get_profile_by_pubkey(txn, pk);
-
-                        self.draft.buffer.select_mention_and_replace_name(
-                            mention.index,
-                            get_display_name(record.ok().as_ref()).name(),
-                            Pubkey::new(**pk),
-                        );
-                        self.draft.cur_mention_hint = None;
-                    }
+            ui::search_results::SearchResultsResponse::SelectResult(clicked) => {
+                if let Some(pk) = clicked.or(selection).and_then(|i| hint.results.get(i)) {
+                    let pk = *pk;
+                    let record = self.note_context.ndb.get_profile_by_pubkey(txn, &pk);
+
+                    self.draft.buffer.select_mention_and_replace_name(
+                        mention.index,
+                        get_display_name(record.ok().as_ref()).name(),
+                        Pubkey::new(pk),
+                    );
+                    self.draft.cur_mention_hint = None;
                 }
             }
 
             ui::search_results::SearchResultsResponse::DeleteMention => {
-                self.draft.buffer.delete_mention(mention.index)
+                self.draft.buffer.delete_mention(mention.index);
+                self.draft.cur_mention_hint = None;
+            }
+        }
+    }
+
+    /// Profiles matching `query`, ranked by how likely the poster is to
+    /// mean them
+    fn mention_results(&self, txn: &Transaction, query: &str) -> Vec<[u8; 32]> {
+        let ndb = self.note_context.ndb;
+        let Ok(results) = ndb.search_profile(txn, query, MENTION_SEARCH_LIMIT) else {
+            return Vec::new();
+        };
+
+        let me = self.poster.pubkey.bytes();
+        let follows: HashSet<[u8; 32]> = ndb
+            .query(txn, &[contacts_filter(me)], 1)
+            .ok()
+            .and_then(|res| res.into_iter().next())
+            .map(|res| p_tags(&res.note).collect())
+            .unwrap_or_default();
+
+        // who we've replied to, reposted and reacted to lately
+        let recent_filter = Filter::new()
+            .authors([me])
+            .kinds([1, 6, 7])
+            .limit(RECENT_INTERACTIONS as u64)
+            .build();
+        let mut recent: Vec<[u8; 32]> = Vec::new();
+        if let Ok(mut notes) = ndb.query(txn, &[recent_filter], RECENT_INTERACTIONS) {
+            notes.sort_by_key(|res| std::cmp::Reverse(res.note.created_at()));
+            for res in &notes {
+                for pk in p_tags(&res.note) {
+                    if !recent.contains(&pk) {
+                        recent.push(pk);
+                    }
+                }
+            }
+        }
+
+        rank_mentions(&results, &follows, &recent, MAX_MENTION_HINTS)
+    }
+
+    fn show_completions(
+        &mut self,
+        txn: &Transaction,
+        ui: &mut egui::Ui,
+        cursor_index: usize,
+        textedit_output: &TextEditOutput,
+        hint_key: Option<HintKey>,
+    ) {
+        // mentions get their own list
+        let query = if self.draft.cur_mention_hint.is_some() {
+            None
+        } else {
+            completion_at(self.draft.buffer.as_str(), cursor_index)
+        };
+        let Some(query) = query else {
+            self.draft.cur_completion = None;
+            return;
+        };
+
+        if ui.ctx().input(|r| r.key_pressed(egui::Key::Escape)) {
+            self.draft.cur_completion = None;
+            return;
+        }
+
+        let stale = !matches!(
+            &self.draft.cur_completion,
+            Some(hint) if hint.kind == query.kind && hint.start == query.start && hint.text == query.text
+        );
+        if stale {
+            let (kind, start, text) = (query.kind, query.start, query.text.to_owned());
+            let items = self.completion_items(txn, kind, &text);
+            self.draft.cur_completion = Some(CompletionHint {
+                kind,
+                start,
+                text,
+                pos: calculate_mention_hints_pos(textedit_output, start),
+                items,
+                selected: 0,
+            });
+        }
+
+        let area_id = self.id().with("completions");
+        let Some(hint) = &mut self.draft.cur_completion else {
+            return;
+        };
+        if hint.items.is_empty() {
+            return;
+        }
+
+        let mut picked = None;
+        match hint_key {
+            Some(HintKey::Down) => hint.selected = (hint.selected + 1) % hint.items.len(),
+            Some(HintKey::Up) => {
+                hint.selected = hint.selected.checked_sub(1).unwrap_or(hint.items.len() - 1)
+            }
+            Some(HintKey::Select) => picked = Some(hint.selected),
+            None => {}
+        }
+
+        let area = egui::Area::new(area_id)
+            .order(egui::Order::Foreground)
+            .fixed_pos(hint.pos)
+            .show(ui.ctx(), |ui| {
+                Frame::popup(ui.style())
+                    .show(ui, |ui| {
+                        ui.set_min_width(200.0);
+                        ScrollArea::vertical()
+                            .max_height(240.0)
+                            .show(ui, |ui| {
+                                completion_list(
+                                    ui,
+                                    self.note_context.img_cache,
+                                    hint,
+                                    hint_key.is_some(),
+                                )
+                            })
+                            .inner
+                    })
+                    .inner
+            });
+
+        let Some(i) = area.inner.or(picked) else {
+            return;
+        };
+        let Some(hint) = self.draft.cur_completion.take() else {
+            return;
+        };
+        let Some(item) = hint.items.into_iter().nth(i) else {
+            return;
+        };
+
+        let replacement = item.replacement();
+        self.draft
+            .buffer
+            .delete_char_range(hint.start..cursor_index);
+        self.draft.buffer.insert_text(&replacement, hint.start);
+        if let CompletionItem::Emoji(emoji) = item {
+            if !self.draft.emojis.contains(&emoji) {
+                self.draft.emojis.push(emoji);
+            }
+        }
+
+        let cursor = CCursor::new(hint.start + replacement.chars().count());
+        let mut state = textedit_output.state.clone();
+        state.cursor.set_char_range(Some(CCursorRange::one(cursor)));
+        state.store(ui.ctx(), textedit_output.response.id);
+    }
+
+    fn completion_items(
+        &self,
+        txn: &Transaction,
+        kind: CompletionKind,
+        query: &str,
+    ) -> Vec<CompletionItem> {
+        let ndb = self.note_context.ndb;
+        let me = self.poster.pubkey.bytes();
+
+        match kind {
+            CompletionKind::Hashtag => {
+                let mut candidates: Vec<String> = Vec::new();
+
+                // hashtags we've used lately, then ones we follow
+                let recent_filter = Filter::new()
+                    .authors([me])
+                    .kinds([1])
+                    .limit(RECENT_HASHTAG_NOTES as u64)
+                    .build();
+                if let Ok(mut notes) = ndb.query(txn, &[recent_filter], RECENT_HASHTAG_NOTES) {
+                    notes.sort_by_key(|res| std::cmp::Reverse(res.note.created_at()));
+                    for res in &notes {
+                        candidates.extend(t_tags(&res.note).map(str::to_owned));
+                    }
+                }
+                if let Some(contacts) = ndb
+                    .query(txn, &[contacts_filter(me)], 1)
+                    .ok()
+                    .and_then(|res| res.into_iter().next())
+                {
+                    candidates.extend(t_tags(&contacts.note).map(str::to_owned));
+                }
+
+                rank_completions(
+                    query,
+                    candidates.iter().map(String::as_str),
+                    MAX_COMPLETIONS,
+                )
+                .into_iter()
+                .map(|tag| CompletionItem::Hashtag(tag.to_lowercase()))
+                .collect()
+            }
+
+            CompletionKind::Emoji => {
+                let emojis = user_emojis(ndb, txn, me);
+                let ranked = rank_completions(
+                    query,
+                    emojis.iter().map(|e| e.shortcode.as_str()),
+                    MAX_COMPLETIONS,
+                );
+                ranked
+                    .into_iter()
+                    .filter_map(|code| emojis.iter().find(|e| e.shortcode == code))
+                    .map(|emoji| CompletionItem::Emoji(emoji.clone()))
+                    .collect()
             }
         }
     }
 
+    /// While the mention list is up, arrow keys move through it and enter
+    /// or tab picks a profile instead of editing the text
+    fn take_hint_key(&self, ui: &egui::Ui) -> Option<HintKey> {
+        let has_hints = self
+            .draft
+            .cur_mention_hint
+            .as_ref()
+            .is_some_and(|hint| !hint.results.is_empty())
+            || self
+                .draft
+                .cur_completion
+                .as_ref()
+                .is_some_and(|hint| !hint.items.is_empty());
+        if !has_hints || !self.focused(ui) {
+            return None;
+        }
+
+        ui.input_mut(|i| {
+            if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown) {
+                Some(HintKey::Down)
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp) {
+                Some(HintKey::Up)
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::Enter)
+                || i.consume_key(egui::Modifiers::NONE, egui::Key::Tab)
+            {
+                Some(HintKey::Select)
+            } else {
+                None
+            }
+        })
+    }
+
     fn focused(&self, ui: &egui::Ui) -> bool {
         ui.ctx()
             .data(|d| d.get_temp::<bool>(self.id()).unwrap_or(false))
@@ -303,10 +719,15 @@ impl<'a, 'd> PostView<'a, 'd> {
 
     pub fn ui(&mut self, txn: &Transaction, ui: &mut egui::Ui) -> PostResponse {
         let focused = self.focused(ui);
-        let stroke = if focused {
-            ui.visuals().selection.stroke
-        } else {
-            ui.visuals().noninteractive().bg_stroke
+        let account_color = self
+            .note_context
+            .account_colors
+            .get(self.poster.pubkey.bytes());
+        let stroke = match (account_color, focused) {
+            (Some(color), true) => egui::Stroke::new(2.0, color),
+            (Some(color), false) => egui::Stroke::new(1.0, color),
+            (None, true) => ui.visuals().selection.stroke,
+            (None, false) => ui.visuals().noninteractive().bg_stroke,
         };
 
         let mut frame = egui::Frame::default()
@@ -333,41 +754,6 @@ impl<'a, 'd> PostView<'a, 'd> {
     fn input_ui(&mut self, txn: &Transaction, ui: &mut egui::Ui) -> PostResponse {
         let edit_response = ui.horizontal(|ui| self.editbox(txn, ui)).inner;
 
-        let note_response = if let PostType::Quote(id) = self.post_type {
-            let avail_size = ui.available_size_before_wrap();
-            Some(
-                ui.with_layout(Layout::left_to_right(egui::Align::TOP), |ui| {
-                    Frame::NONE
-                        .show(ui, |ui| {
-                            ui.vertical(|ui| {
-                                ui.set_max_width(avail_size.x * 0.8);
-
-                                let zapping_acc = self
-                                    .note_context
-                                    .current_account_has_wallet
-                                    .then(|| KeypairUnowned::from(&self.poster));
-
-                                render_note_preview(
-                                    ui,
-                                    self.note_context,
-                                    zapping_acc.as_ref(),
-                                    txn,
-                                    id.bytes(),
-                                    nostrdb::NoteKey::new(0),
-                                    self.note_options,
-                                    self.jobs,
-                                )
-                            })
-                            .inner
-                        })
-                        .inner
-                })
-                .inner,
-            )
-        } else {
-            None
-        };
-
         Frame::new()
             .inner_margin(Margin::symmetric(0, 8))
             .show(ui, |ui| {
@@ -379,14 +765,31 @@ impl<'a, 'd> PostView<'a, 'd> {
                 });
             });
 
+        if self.focused(ui) && ui.input(|i| !i.raw.hovered_files.is_empty()) {
+            ui.label(egui::RichText::new("Drop to attach").weak());
+        }
+
+        if let PostType::Reply(id) = self.post_type {
+            self.reply_audience_ui(txn, ui, &id);
+        }
+
+        // laid out like the quote will be: text, then media, then the note
+        let note_response = if let PostType::Quote(id) = self.post_type {
+            Some(self.quote_preview_ui(txn, ui, &id))
+        } else {
+            None
+        };
+
         self.transfer_uploads(ui);
         self.show_upload_errors(ui);
+        self.show_content_warning_reason(ui);
+        self.show_poll_options(ui);
 
         let post_action = ui.horizontal(|ui| self.input_buttons(ui)).inner;
 
         let action = note_response
             .and_then(|nr| nr.action.map(PostAction::QuotedNoteAction))
-            .or(post_action.map(PostAction::NewPostAction));
+            .or(post_action);
 
         PostResponse {
             action,
@@ -394,41 +797,215 @@ impl<'a, 'd> PostView<'a, 'd> {
         }
     }
 
-    fn input_buttons(&mut self, ui: &mut egui::Ui) -> Option<NewPostAction> {
+    /// Who the reply will notify. Opened up, it lists everyone the note
+    /// being replied to tags, to untick the ones to leave out. Its author
+    /// is always notified.
+    fn reply_audience_ui(&mut self, txn: &Transaction, ui: &mut egui::Ui, id: &NoteId) {
+        let ndb = self.note_context.ndb;
+        let Ok(replying_to) = ndb.get_note_by_id(txn, id.bytes()) else {
+            return;
+        };
+        let audience = reply_audience(&replying_to);
+        let untagged = &mut self.draft.untagged;
+
+        let tagged: Vec<&Pubkey> = audience
+            .iter()
+            .enumerate()
+            .filter(|(ind, pk)| *ind == 0 || !untagged.contains(pk))
+            .map(|(_, pk)| pk)
+            .collect();
+        let profiles: Vec<_> = tagged
+            .iter()
+            .take(2)
+            .map(|pk| ndb.get_profile_by_pubkey(txn, pk.bytes()).ok())
+            .collect();
+        let names: Vec<&str> = profiles
+            .iter()
+            .map(|profile| get_display_name(profile.as_ref()).name())
+            .collect();
+        let summary = egui::RichText::new(format!(
+            "Notifies {}",
+            summarize_names(&names, tagged.len())
+        ))
+        .weak();
+
+        if audience.len() == 1 {
+            ui.label(summary);
+            return;
+        }
+
+        egui::CollapsingHeader::new(summary)
+            .id_salt(("reply_audience", id.bytes()))
+            .show(ui, |ui| {
+                for (ind, pk) in audience.iter().enumerate() {
+                    let profile = ndb.get_profile_by_pubkey(txn, pk.bytes()).ok();
+                    let name = get_display_name(profile.as_ref()).name();
+                    let mut tag = ind == 0 || !untagged.contains(pk);
+
+                    let resp = ui.add_enabled(ind > 0, egui::Checkbox::new(&mut tag, name));
+                    if ind == 0 {
+                        resp.on_disabled_hover_text("The author of the note is always notified");
+                    } else if resp.changed() {
+                        if tag {
+                            untagged.retain(|untagged| untagged != pk);
+                        } else {
+                            untagged.push(*pk);
+                        }
+                    }
+                }
+            });
+    }
+
+    /// The note being quoted, as a card under what's being written
+    fn quote_preview_ui(
+        &mut self,
+        txn: &Transaction,
+        ui: &mut egui::Ui,
+        id: &NoteId,
+    ) -> notedeck_ui::note::NoteResponse {
+        let zapping_acc = self
+            .note_context
+            .current_account_has_wallet
+            .then_some(self.poster);
+
+        Frame::new()
+            .inner_margin(Margin::same(8))
+            .stroke(ui.visuals().noninteractive().bg_stroke)
+            .corner_radius(8.0)
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                render_note_preview(
+                    ui,
+                    self.note_context,
+                    zapping_acc.as_ref(),
+                    txn,
+                    id.bytes(),
+                    nostrdb::NoteKey::new(0),
+                    self.note_options,
+                    self.jobs,
+                )
+            })
+            .inner
+    }
+
+    fn input_buttons(&mut self, ui: &mut egui::Ui) -> Option<PostAction> {
         ui.with_layout(egui::Layout::left_to_right(egui::Align::BOTTOM), |ui| {
             self.show_upload_media_button(ui);
+            self.show_content_warning_button(ui);
+            self.show_expiration_button(ui);
+            if matches!(self.post_type, PostType::New) {
+                self.show_poll_button(ui);
+            }
         });
 
         ui.with_layout(egui::Layout::right_to_left(egui::Align::BOTTOM), |ui| {
-            let post_button_clicked = ui
-                .add_sized([91.0, 32.0], post_button(!self.draft.buffer.is_empty()))
-                .clicked();
+            let poll_ready = !self
+                .draft
+                .poll
+                .as_ref()
+                .is_some_and(|poll| !poll.is_ready());
+            let can_post = !self.draft.buffer.is_empty() && poll_ready;
+            let post_button_clicked = ui.add_sized([91.0, 32.0], post_button(can_post)).clicked();
 
             let shortcut_pressed = ui.input(|i| {
                 (i.modifiers.ctrl || i.modifiers.command) && i.key_pressed(egui::Key::Enter)
             });
 
-            if post_button_clicked
-                || (!self.draft.buffer.is_empty() && shortcut_pressed && self.focused(ui))
-            {
+            if post_button_clicked || (can_post && shortcut_pressed && self.focused(ui)) {
                 let output = self.draft.buffer.output();
                 let new_post = NewPost::new(
                     output.text,
-                    self.poster.to_full(),
+                    Keypair::new(*self.poster.pubkey, self.poster.secret_key.cloned()),
                     self.draft.uploaded_media.clone(),
                     output.mentions,
-                );
-                Some(NewPostAction::new(self.post_type.clone(), new_post))
-            } else {
-                None
+                )
+                .content_warning(self.draft.content_warning.clone())
+                .emojis(self.draft.emojis.clone())
+                .expiration(
+                    self.draft
+                        .expires_in
+                        .map(|secs| crate::scheduled::unix_now() + secs),
+                )
+                .poll(self.draft.poll.as_ref().map(PollDraft::to_poll))
+                .untagged(self.draft.untagged.clone());
+                return Some(PostAction::NewPostAction(NewPostAction::new(
+                    self.post_type.clone(),
+                    new_post,
+                )));
             }
+
+            if !self.draft.buffer.is_empty() {
+                if let Some(publish_at) = self.schedule_menu(ui) {
+                    return Some(PostAction::Schedule(ScheduleAction {
+                        post_type: self.post_type.clone(),
+                        poster: *self.poster.pubkey,
+                        publish_at,
+                    }));
+                }
+            }
+
+            let has_content =
+                !self.draft.buffer.is_empty() || !self.draft.uploaded_media.is_empty();
+            if has_content && ui.button("Discard").clicked() {
+                return Some(PostAction::Discard(self.post_type.clone()));
+            }
+
+            None
         })
         .inner
     }
 
+    /// Pick when to post instead of posting now. Returns the unix time
+    /// picked.
+    fn schedule_menu(&mut self, ui: &mut egui::Ui) -> Option<u64> {
+        let custom_id = self.id().with("schedule_custom");
+        let mut delay = None;
+
+        ui.menu_button("Schedule", |ui| {
+            for (label, secs) in SCHEDULE_PRESETS {
+                if ui.button(label).clicked() {
+                    delay = Some(secs);
+                }
+            }
+
+            ui.separator();
+            let (mut hours, mut minutes) = ui
+                .data(|d| d.get_temp::<(u64, u64)>(custom_id))
+                .unwrap_or((0, 30));
+            ui.horizontal(|ui| {
+                ui.label("In");
+                ui.add(
+                    egui::DragValue::new(&mut hours)
+                        .range(0..=24 * 30)
+                        .suffix(" h"),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut minutes)
+                        .range(0..=59)
+                        .suffix(" min"),
+                );
+            });
+            ui.data_mut(|d| d.insert_temp(custom_id, (hours, minutes)));
+
+            let custom = hours * 3600 + minutes * 60;
+            if ui
+                .add_enabled(custom > 0, egui::Button::new("Schedule"))
+                .clicked()
+            {
+                delay = Some(custom);
+            }
+
+            if delay.is_some() {
+                ui.close_menu();
+            }
+        });
+
+        delay.map(|secs| crate::scheduled::unix_now() + secs)
+    }
+
     fn show_media(&mut self, ui: &mut egui::Ui) {
         let mut to_remove = Vec::new();
-        for (i, media) in self.draft.uploaded_media.iter().enumerate() {
+        for (i, media) in self.draft.uploaded_media.iter_mut().enumerate() {
             let (width, height) = if let Some(dims) = media.dimensions {
                 (dims.0, dims.1)
             } else {
@@ -454,16 +1031,21 @@ impl<'a, 'd> PostView<'a, 'd> {
                 notedeck_ui::images::ImageType::Content,
             );
 
-            render_post_view_media(
-                ui,
-                &mut self.draft.upload_errors,
-                &mut to_remove,
-                i,
-                width,
-                height,
-                cur_state,
-                url,
-            )
+            ui.vertical(|ui| {
+                let shown = render_post_view_media(
+                    ui,
+                    &mut self.draft.upload_errors,
+                    &mut to_remove,
+                    i,
+                    width,
+                    height,
+                    cur_state,
+                    url,
+                );
+                if let Some(rect) = shown {
+                    alt_text_edit(ui, &mut media.alt, rect.width());
+                }
+            });
         }
         to_remove.reverse();
         for i in to_remove {
@@ -471,45 +1053,287 @@ impl<'a, 'd> PostView<'a, 'd> {
         }
     }
 
+    fn show_content_warning_button(&mut self, ui: &mut egui::Ui) {
+        let enabled = self.draft.content_warning.is_some();
+        let resp = ui
+            .add_sized([32.0, 32.0], egui::Button::new("CW").selected(enabled))
+            .on_hover_text("Add a content warning");
+
+        if resp.clicked() {
+            self.draft.content_warning = if enabled { None } else { Some(String::new()) };
+        }
+    }
+
+    fn show_expiration_button(&mut self, ui: &mut egui::Ui) {
+        let current = self.draft.expires_in;
+        let label = match EXPIRATION_PRESETS
+            .iter()
+            .find(|(_, secs)| Some(*secs) == current)
+        {
+            Some((label, _)) => format!("⏳ {label}"),
+            None => "⏳".to_owned(),
+        };
+
+        let resp = ui
+            .menu_button(label, |ui| {
+                ui.label(
+                    egui::RichText::new("Ask relays to delete it after")
+                        .small()
+                        .weak(),
+                );
+                if ui.selectable_label(current.is_none(), "Never").clicked() {
+                    self.draft.expires_in = None;
+                    ui.close_menu();
+                }
+                for (label, secs) in EXPIRATION_PRESETS {
+                    if ui.selectable_label(current == Some(secs), label).clicked() {
+                        self.draft.expires_in = Some(secs);
+                        ui.close_menu();
+                    }
+                }
+            })
+            .response;
+
+        resp.on_hover_text("Make this note expire");
+    }
+
+    fn show_content_warning_reason(&mut self, ui: &mut egui::Ui) {
+        let Some(reason) = &mut self.draft.content_warning else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Content warning");
+            ui.add(
+                TextEdit::singleline(reason)
+                    .hint_text("Reason (optional)")
+                    .desired_width(f32::INFINITY),
+            );
+        });
+    }
+
+    fn show_poll_button(&mut self, ui: &mut egui::Ui) {
+        let enabled = self.draft.poll.is_some();
+        let resp = ui
+            .add_sized([32.0, 32.0], egui::Button::new("📊").selected(enabled))
+            .on_hover_text("Make this a poll");
+
+        if resp.clicked() {
+            self.draft.poll = if enabled {
+                None
+            } else {
+                Some(PollDraft::default())
+            };
+        }
+    }
+
+    fn show_poll_options(&mut self, ui: &mut egui::Ui) {
+        let duration_id = self.id().with("poll_duration");
+        let Some(poll) = &mut self.draft.poll else {
+            return;
+        };
+
+        let mut remove = None;
+        for (i, option) in poll.options.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(option)
+                        .hint_text(format!("Option {}", i + 1))
+                        .desired_width(ui.available_width() - 32.0),
+                );
+                // a poll needs two options to pick between
+                if i >= 2 && ui.small_button("✕").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            poll.options.remove(i);
+        }
+
+        ui.horizontal(|ui| {
+            if poll.options.len() < MAX_POLL_OPTIONS && ui.button("Add option").clicked() {
+                poll.options.push(String::new());
+            }
+
+            ui.checkbox(&mut poll.multiple_choice, "Allow more than one pick");
+
+            let closes = POLL_DURATIONS
+                .iter()
+                .find(|(_, secs)| *secs == poll.ends_in)
+                .map_or("Custom", |(label, _)| *label);
+            egui::ComboBox::from_id_salt(duration_id)
+                .selected_text(format!("Open for {closes}"))
+                .show_ui(ui, |ui| {
+                    for (label, secs) in POLL_DURATIONS {
+                        ui.selectable_value(&mut poll.ends_in, secs, label);
+                    }
+                });
+        });
+    }
+
     fn show_upload_media_button(&mut self, ui: &mut egui::Ui) {
-        if ui.add(media_upload_button()).clicked() {
+        // uploads are authorized with a signature from the poster, which
+        // we can only make with a local key for now
+        if self.poster.secret_key.is_none() {
+            return;
+        }
+
+        let host = self
+            .media_server
+            .as_ref()
+            .map(|server| server.host())
+            .unwrap_or_else(|| "nostr.build".to_owned());
+        let resp = ui
+            .add(media_upload_button())
+            .on_hover_text(format!("Upload to {host}. Right-click to change."));
+
+        resp.context_menu(|ui| self.media_server_menu(ui));
+
+        if resp.clicked() {
             #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
             {
                 if let Some(files) = rfd::FileDialog::new().pick_files() {
                     for file in files {
-                        match MediaPath::new(file) {
-                            Ok(media_path) => {
-                                let promise = nostrbuild_nip96_upload(
-                                    self.poster.secret_key.secret_bytes(),
-                                    media_path,
-                                );
-                                self.draft.uploading_media.push(promise);
-                            }
-                            Err(e) => {
-                                error!("{e}");
-                                self.draft.upload_errors.push(e.to_string());
-                            }
-                        }
+                        self.start_upload(file);
                     }
                 }
             }
         }
     }
 
+    fn media_server_menu(&mut self, ui: &mut egui::Ui) {
+        let id = self.id().with("media_server");
+        let Some(media_server) = self.media_server.as_deref_mut() else {
+            ui.label("Uploads go to nostr.build");
+            return;
+        };
+
+        let (mut kind, mut input) = ui.ctx().data_mut(|d| {
+            d.get_temp_mut_or_insert_with(id, || {
+                let host = media_server.url().host_str().unwrap_or_default().to_owned();
+                (media_server.kind(), host)
+            })
+            .clone()
+        });
+
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut kind, MediaServerKind::Nip96, "NIP-96");
+            ui.radio_value(&mut kind, MediaServerKind::Blossom, "Blossom");
+        });
+
+        let using_server_list =
+            kind == MediaServerKind::Blossom && !media_server.blossom_servers().is_empty();
+        if using_server_list {
+            ui.label(
+                egui::RichText::new(format!(
+                    "Using your server list: {}",
+                    media_server.blossom_servers().join(", ")
+                ))
+                .weak(),
+            );
+        }
+
+        let resp = ui.add_enabled(
+            !using_server_list,
+            TextEdit::singleline(&mut input).hint_text("nostr.build"),
+        );
+        let parsed = parse_media_server(&input);
+
+        let save = ui.add_enabled(parsed.is_some(), egui::Button::new("Save"));
+        if let Some(url) = parsed {
+            let submitted = resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if save.clicked() || submitted {
+                media_server.set(kind, url);
+                ui.ctx()
+                    .data_mut(|d| d.remove::<(MediaServerKind, String)>(id));
+                ui.close_menu();
+                return;
+            }
+        }
+
+        ui.ctx().data_mut(|d| d.insert_temp(id, (kind, input)));
+    }
+
+    fn start_upload(&mut self, path: std::path::PathBuf) {
+        let Some(secret_key) = self.poster.secret_key else {
+            self.draft
+                .upload_errors
+                .push("Uploading needs this account's private key".to_owned());
+            return;
+        };
+
+        match MediaPath::new(path) {
+            Ok(media_path) => {
+                let file_name = media_path.file_name().to_owned();
+                let promise = match &self.media_server {
+                    Some(server) => server.upload(secret_key.secret_bytes(), media_path),
+                    None => nostrbuild_nip96_upload(secret_key.secret_bytes(), media_path),
+                };
+                self.draft
+                    .uploading_media
+                    .push(PendingUpload { file_name, promise });
+            }
+            Err(e) => {
+                error!("{e}");
+                self.draft.upload_errors.push(e.to_string());
+            }
+        }
+    }
+
+    /// Pasted files show up as their paths. Take them before the text
+    /// edit does and upload them instead.
+    fn take_pasted_files(&mut self, ui: &mut egui::Ui) {
+        let paths = ui.input_mut(|i| {
+            let mut paths = Vec::new();
+            i.events.retain(|event| {
+                let egui::Event::Paste(text) = event else {
+                    return true;
+                };
+                let Some(pasted) = pasted_media_paths(text) else {
+                    return true;
+                };
+                paths.extend(pasted);
+                false
+            });
+            paths
+        });
+
+        for path in paths {
+            self.start_upload(path);
+        }
+    }
+
+    fn take_dropped_files(&mut self, ui: &mut egui::Ui) {
+        let dropped = ui.input_mut(|i| std::mem::take(&mut i.raw.dropped_files));
+
+        for path in dropped.into_iter().filter_map(|file| file.path) {
+            self.start_upload(path);
+        }
+    }
+
     fn transfer_uploads(&mut self, ui: &mut egui::Ui) {
         let mut indexes_to_remove = Vec::new();
-        for (i, promise) in self.draft.uploading_media.iter().enumerate() {
-            match promise.ready() {
+        for (i, upload) in self.draft.uploading_media.iter().enumerate() {
+            match upload.promise.ready() {
                 Some(Ok(media)) => {
                     self.draft.uploaded_media.push(media.clone());
                     indexes_to_remove.push(i);
                 }
                 Some(Err(e)) => {
-                    self.draft.upload_errors.push(e.to_string());
+                    self.draft
+                        .upload_errors
+                        .push(format!("Couldn't upload {}: {e}", upload.file_name));
                     error!("{e}");
+                    indexes_to_remove.push(i);
                 }
                 None => {
-                    ui.spinner();
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(
+                            egui::RichText::new(format!("Uploading {}…", upload.file_name)).weak(),
+                        );
+                    });
                 }
             }
         }
@@ -553,14 +1377,16 @@ fn render_post_view_media(
     height: u32,
     render_state: RenderState,
     url: &str,
-) {
+) -> Option<egui::Rect> {
     match render_state.texture_state {
         notedeck::TextureState::Pending => {
             ui.spinner();
+            None
         }
         notedeck::TextureState::Error(e) => {
             upload_errors.push(e.to_string());
             error!("{e}");
+            None
         }
         notedeck::TextureState::Loaded(renderable_media) => {
             let max_size = 300;
@@ -577,7 +1403,7 @@ fn render_post_view_media(
 
             let texture_handle = handle_repaint(
                 ui,
-                retrieve_latest_texture(url, render_state.gifs, renderable_media),
+                retrieve_latest_texture(ui, url, render_state.gifs, renderable_media),
             );
             let img_resp = ui.add(
                 egui::Image::new(texture_handle)
@@ -595,10 +1421,26 @@ fn render_post_view_media(
                 to_remove.push(cur_index);
             }
             ui.advance_cursor_after_rect(img_resp.rect);
+            Some(img_resp.rect)
         }
     }
 }
 
+/// A description of the image that goes out in its imeta tag
+fn alt_text_edit(ui: &mut egui::Ui, alt: &mut Option<String>, width: f32) {
+    let mut text = alt.clone().unwrap_or_default();
+    let resp = ui.add(
+        egui::TextEdit::multiline(&mut text)
+            .hint_text("Describe this image")
+            .desired_rows(1)
+            .desired_width(width),
+    );
+
+    if resp.changed() {
+        *alt = (!text.is_empty()).then_some(text);
+    }
+}
+
 fn post_button(interactive: bool) -> impl egui::Widget {
     move |ui: &mut egui::Ui| {
         let button = egui::Button::new("Post now");
@@ -702,6 +1544,84 @@ fn get_cursor_index(cursor: &Option<CCursorRange>) -> Option<usize> {
     }
 }
 
+/// The pubkeys a note tags
+fn p_tags<'a>(note: &'a Note<'a>) -> impl Iterator<Item = [u8; 32]> + 'a {
+    note.tags().into_iter().filter_map(|tag| {
+        if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some("p") {
+            return None;
+        }
+        tag.get_unchecked(1).variant().id().copied()
+    })
+}
+
+/// The hashtags a note tags
+fn t_tags<'a>(note: &'a Note<'a>) -> impl Iterator<Item = &'a str> + 'a {
+    note.tags().into_iter().filter_map(|tag| {
+        if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some("t") {
+            return None;
+        }
+        tag.get_unchecked(1).variant().str()
+    })
+}
+
+/// The hashtag or emoji suggestions. Returns the one that was clicked.
+fn completion_list(
+    ui: &mut egui::Ui,
+    img_cache: &mut notedeck::Images,
+    hint: &CompletionHint,
+    scroll_to_selected: bool,
+) -> Option<usize> {
+    let mut clicked = None;
+    for (i, item) in hint.items.iter().enumerate() {
+        let selected = i == hint.selected;
+        let resp = ui
+            .horizontal(|ui| {
+                let label = match item {
+                    CompletionItem::Hashtag(tag) => format!("#{tag}"),
+                    CompletionItem::Emoji(emoji) => {
+                        emoji_image(ui, img_cache, &emoji.url, 20.0);
+                        format!(":{}:", emoji.shortcode)
+                    }
+                };
+                ui.add_sized(
+                    [ui.available_width(), 24.0],
+                    egui::SelectableLabel::new(selected, label),
+                )
+            })
+            .inner;
+
+        if selected && scroll_to_selected {
+            resp.scroll_to_me(None);
+        }
+        if resp.clicked() {
+            clicked = Some(i);
+        }
+    }
+    clicked
+}
+
+fn emoji_image(ui: &mut egui::Ui, img_cache: &mut notedeck::Images, url: &str, size: f32) {
+    let cache_type = supported_mime_hosted_at_url(&mut img_cache.urls, url)
+        .unwrap_or(notedeck::MediaCacheType::Image);
+    let cur_state = get_render_state(
+        ui.ctx(),
+        img_cache,
+        cache_type,
+        url,
+        notedeck_ui::images::ImageType::Content,
+    );
+
+    match cur_state.texture_state {
+        notedeck::TextureState::Loaded(img) => {
+            let texture = handle_repaint(ui, retrieve_latest_texture(ui, url, cur_state.gifs, img));
+            ui.add(egui::Image::new(texture).fit_to_exact_size(egui::vec2(size, size)));
+        }
+        _ => {
+            ui.allocate_space(egui::vec2(size, size));
+        }
+    }
+}
+
 fn calculate_mention_hints_pos(out: &TextEditOutput, char_pos: usize) -> egui::Pos2 {
     let mut cur_pos = 0;
 
@@ -784,14 +1704,25 @@ mod preview {
                 zaps: app.zaps,
                 pool: app.pool,
                 job_pool: app.job_pool,
+                counts: app.counts,
+                polls: app.polls,
+                zap_goals: app.zap_goals,
+                paid_content: app.paid_content,
                 current_account_has_wallet: false,
+                current_account_can_sign: true,
+                acting_pubkey: None,
+                account_colors: Default::default(),
+                truncate_lines: Some(notedeck::DEFAULT_TRUNCATE_LINES),
             };
 
             PostView::new(
                 &mut note_context,
                 &mut self.draft,
                 PostType::New,
-                self.poster.to_filled(),
+                KeypairUnowned {
+                    pubkey: &self.poster.pubkey,
+                    secret_key: Some(&self.poster.secret_key),
+                },
                 ui.available_rect_before_wrap(),
                 NoteOptions::default(),
                 &mut self.jobs,
@@ -1,29 +1,31 @@
 use super::{PostResponse, PostType};
 use crate::{
     draft::Draft,
+    media_upload::MediaServer,
     ui::{self},
 };
 
-use enostr::{FilledKeypair, NoteId};
+use enostr::{KeypairUnowned, NoteId};
 use notedeck::NoteContext;
 use notedeck_ui::{jobs::JobsCache, NoteOptions};
 
 pub struct QuoteRepostView<'a, 'd> {
     note_context: &'a mut NoteContext<'d>,
-    poster: FilledKeypair<'a>,
+    poster: KeypairUnowned<'a>,
     draft: &'a mut Draft,
     quoting_note: &'a nostrdb::Note<'a>,
     id_source: Option<egui::Id>,
     inner_rect: egui::Rect,
     note_options: NoteOptions,
     jobs: &'a mut JobsCache,
+    media_server: Option<&'a mut MediaServer>,
 }
 
 impl<'a, 'd> QuoteRepostView<'a, 'd> {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         note_context: &'a mut NoteContext<'d>,
-        poster: FilledKeypair<'a>,
+        poster: KeypairUnowned<'a>,
         draft: &'a mut Draft,
         quoting_note: &'a nostrdb::Note<'a>,
         inner_rect: egui::Rect,
@@ -40,6 +42,7 @@ impl<'a, 'd> QuoteRepostView<'a, 'd> {
             inner_rect,
             note_options,
             jobs,
+            media_server: None,
         }
     }
 
@@ -57,6 +60,7 @@ impl<'a, 'd> QuoteRepostView<'a, 'd> {
             self.jobs,
         )
         .id_source(id)
+        .media_server(self.media_server.as_deref_mut())
         .ui(self.quoting_note.txn().unwrap(), ui);
         post_resp
     }
@@ -66,6 +70,11 @@ impl<'a, 'd> QuoteRepostView<'a, 'd> {
         self
     }
 
+    pub fn media_server(mut self, media_server: Option<&'a mut MediaServer>) -> Self {
+        self.media_server = media_server;
+        self
+    }
+
     pub fn id(&self) -> egui::Id {
         self.id_source
             .unwrap_or_else(|| egui::Id::new("quote-repost-view"))
@@ -1,30 +1,32 @@
 use crate::draft::Draft;
+use crate::media_upload::MediaServer;
 use crate::ui::{
     self,
     note::{PostAction, PostResponse, PostType},
 };
 
-use enostr::{FilledKeypair, KeypairUnowned, NoteId};
+use enostr::{KeypairUnowned, NoteId};
 use notedeck::NoteContext;
 use notedeck_ui::jobs::JobsCache;
 use notedeck_ui::{NoteOptions, NoteView, ProfilePic};
 
 pub struct PostReplyView<'a, 'd> {
     note_context: &'a mut NoteContext<'d>,
-    poster: FilledKeypair<'a>,
+    poster: KeypairUnowned<'a>,
     draft: &'a mut Draft,
     note: &'a nostrdb::Note<'a>,
     id_source: Option<egui::Id>,
     inner_rect: egui::Rect,
     note_options: NoteOptions,
     jobs: &'a mut JobsCache,
+    media_server: Option<&'a mut MediaServer>,
 }
 
 impl<'a, 'd> PostReplyView<'a, 'd> {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         note_context: &'a mut NoteContext<'d>,
-        poster: FilledKeypair<'a>,
+        poster: KeypairUnowned<'a>,
         draft: &'a mut Draft,
         note: &'a nostrdb::Note<'a>,
         inner_rect: egui::Rect,
@@ -41,6 +43,7 @@ impl<'a, 'd> PostReplyView<'a, 'd> {
             inner_rect,
             note_options,
             jobs,
+            media_server: None,
         }
     }
 
@@ -49,6 +52,11 @@ impl<'a, 'd> PostReplyView<'a, 'd> {
         self
     }
 
+    pub fn media_server(mut self, media_server: Option<&'a mut MediaServer>) -> Self {
+        self.media_server = media_server;
+        self
+    }
+
     pub fn id(&self) -> egui::Id {
         self.id_source
             .unwrap_or_else(|| egui::Id::new("post-reply-view"))
@@ -70,7 +78,7 @@ impl<'a, 'd> PostReplyView<'a, 'd> {
             let zapping_acc = self
                 .note_context
                 .current_account_has_wallet
-                .then(|| KeypairUnowned::from(&self.poster));
+                .then_some(self.poster);
 
             let quoted_note = egui::Frame::NONE
                 .outer_margin(egui::Margin::same(note_offset))
@@ -106,6 +114,7 @@ impl<'a, 'd> PostReplyView<'a, 'd> {
                     self.jobs,
                 )
                 .id_source(id)
+                .media_server(self.media_server.as_deref_mut())
                 .ui(self.note.txn().unwrap(), ui)
             };
 
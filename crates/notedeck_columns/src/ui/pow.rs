@@ -0,0 +1,41 @@
+use egui::{Align2, Frame, Vec2};
+
+use crate::pow::ProofOfWork;
+
+/// Shows how far along mining is while notes wait on proof of work, with
+/// a way to send them without it
+pub fn mining_overlay(ctx: &egui::Context, pow: &mut ProofOfWork) {
+    let Some((count, progress)) = pow.progress() else {
+        return;
+    };
+
+    let mut skip = false;
+
+    egui::Area::new(egui::Id::new("pow_mining_overlay"))
+        .anchor(Align2::CENTER_BOTTOM, Vec2::new(0.0, -16.0))
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_max_width(260.0);
+
+                ui.horizontal(|ui| {
+                    ui.add(egui::Spinner::new());
+                    ui.label(if count == 1 {
+                        "Adding proof of work".to_owned()
+                    } else {
+                        format!("Adding proof of work to {count} notes")
+                    });
+                });
+                ui.add(egui::ProgressBar::new(progress).show_percentage());
+
+                skip = ui
+                    .button("Post now")
+                    .on_hover_text("Send without waiting for the proof of work")
+                    .clicked();
+            });
+        });
+
+    if skip {
+        pow.skip_all();
+    }
+}
@@ -1,22 +1,39 @@
 use core::f32;
 
-use crate::profile_state::ProfileState;
-use egui::{vec2, Button, CornerRadius, Layout, Margin, RichText, ScrollArea, TextEdit};
+use crate::media_upload::{nostrbuild_nip96_upload, MediaPath};
+use crate::profile_state::{ProfileImage, ProfileState};
+use egui::{vec2, Button, CornerRadius, Layout, Margin, RichText, ScrollArea, Sense, TextEdit};
 use notedeck::{profile::unwrap_profile_url, Images, NotedeckTextStyle};
 use notedeck_ui::{profile::banner, ProfilePic};
+use tracing::error;
 
 pub struct EditProfileView<'a> {
     state: &'a mut ProfileState,
     img_cache: &'a mut Images,
+
+    /// Signs upload requests, we can't upload images without it
+    upload_key: Option<[u8; 32]>,
 }
 
 impl<'a> EditProfileView<'a> {
     pub fn new(state: &'a mut ProfileState, img_cache: &'a mut Images) -> Self {
-        Self { state, img_cache }
+        Self {
+            state,
+            img_cache,
+            upload_key: None,
+        }
+    }
+
+    /// Allow uploading new images, authorized with this secret key
+    pub fn upload_key(mut self, secret_key: [u8; 32]) -> Self {
+        self.upload_key = Some(secret_key);
+        self
     }
 
     // return true to save
     pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        self.state.poll_uploads();
+
         ScrollArea::vertical()
             .show(ui, |ui| {
                 banner(ui, Some(&self.state.banner), 188.0);
@@ -31,8 +48,13 @@ impl<'a> EditProfileView<'a> {
                 let mut save = false;
                 notedeck_ui::padding(padding, ui, |ui| {
                     ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
+                        // wait for uploads so we don't save the old urls
+                        let can_save = !self.state.is_any_uploading();
                         if ui
-                            .add(button("Save changes", 119.0).fill(notedeck_ui::colors::PINK))
+                            .add_enabled(
+                                can_save,
+                                button("Save changes", 119.0).fill(notedeck_ui::colors::PINK),
+                            )
                             .clicked()
                         {
                             save = true;
@@ -75,15 +97,9 @@ impl<'a> EditProfileView<'a> {
             ui.add(singleline_textedit(&mut self.state.name));
         });
 
-        in_frame(ui, |ui| {
-            ui.add(label("Profile picture"));
-            ui.add(multiline_textedit(&mut self.state.picture));
-        });
-
-        in_frame(ui, |ui| {
-            ui.add(label("Banner"));
-            ui.add(multiline_textedit(&mut self.state.banner));
-        });
+        self.image_field(ui, "Profile picture", ProfileImage::Picture);
+        self.image_field(ui, "Banner", ProfileImage::Banner);
+        self.upload_error(ui);
 
         in_frame(ui, |ui| {
             ui.add(label("About"));
@@ -130,6 +146,67 @@ impl<'a> EditProfileView<'a> {
     }
 }
 
+impl EditProfileView<'_> {
+    fn image_field(&mut self, ui: &mut egui::Ui, title: &str, image: ProfileImage) {
+        in_frame(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(label(title));
+
+                let Some(upload_key) = self.upload_key else {
+                    return;
+                };
+
+                ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
+                    if self.state.is_uploading(image) {
+                        ui.spinner();
+                    } else if ui.button("Upload").clicked() {
+                        self.pick_and_upload(upload_key, image);
+                    }
+                });
+            });
+            ui.add(multiline_textedit(self.state.image_url_mut(image)));
+        });
+    }
+
+    fn pick_and_upload(&mut self, upload_key: [u8; 32], image: ProfileImage) {
+        #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+        {
+            let Some(file) = rfd::FileDialog::new().pick_file() else {
+                return;
+            };
+
+            match MediaPath::new(file) {
+                Ok(media_path) => self
+                    .state
+                    .upload(image, nostrbuild_nip96_upload(upload_key, media_path)),
+                Err(e) => {
+                    error!("{e}");
+                    self.state.upload_error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    fn upload_error(&mut self, ui: &mut egui::Ui) {
+        let Some(err) = &self.state.upload_error else {
+            return;
+        };
+
+        let dismissed = ui
+            .add(
+                egui::Label::new(RichText::new(err).color(ui.visuals().warn_fg_color))
+                    .sense(Sense::click())
+                    .selectable(false),
+            )
+            .on_hover_text_at_pointer("Dismiss")
+            .clicked();
+
+        if dismissed {
+            self.state.upload_error = None;
+        }
+    }
+}
+
 fn label(text: &str) -> impl egui::Widget + '_ {
     move |ui: &mut egui::Ui| -> egui::Response {
         ui.label(RichText::new(text).font(NotedeckTextStyle::Body.get_bolded_font(ui.ctx())))
@@ -1,29 +1,37 @@
 pub mod edit;
+mod tabs;
 
 pub use edit::EditProfileView;
 use egui::{vec2, Color32, CornerRadius, Layout, Rect, RichText, ScrollArea, Sense, Stroke};
 use enostr::Pubkey;
-use nostrdb::{ProfileRecord, Transaction};
+use nostrdb::{Ndb, NoteKey, ProfileRecord, Transaction};
 use tracing::error;
 
 use crate::{
+    recurring_zaps::{NewRecurringZap, ZapInterval},
     timeline::{TimelineCache, TimelineKind},
-    ui::timeline::{tabs_ui, TimelineTabView},
+    ui::timeline::{labeled_tabs_ui, load_older_near_bottom, older_notes_spinner, TimelineTabView},
 };
 use notedeck::{
-    name::get_display_name, profile::get_profile_url, Accounts, MuteFun, NoteAction, NoteContext,
-    NotedeckTextStyle, UnknownIds,
+    name::get_display_name, nip19, profile::get_profile_url, Accounts, ContactAction, CountKind,
+    MuteFun, NoteAction, NoteContext, NotedeckTextStyle, UnknownIds,
 };
 use notedeck_ui::{
+    colors,
+    contacts::pk1_is_following_pk2,
     jobs::JobsCache,
     profile::{about_section_widget, banner, display_name_widget},
     NoteOptions, ProfilePic,
 };
 
+/// The first two are the profile timeline's own views
+const PROFILE_TABS: [&str; 4] = ["Notes", "Notes & Replies", "Media", "Zaps"];
+
 pub struct ProfileView<'a, 'd> {
     pubkey: &'a Pubkey,
     accounts: &'a Accounts,
-    col_id: usize,
+    acting: Option<Pubkey>,
+    col_id: egui::Id,
     timeline_cache: &'a mut TimelineCache,
     note_options: NoteOptions,
     unknown_ids: &'a mut UnknownIds,
@@ -35,6 +43,11 @@ pub struct ProfileView<'a, 'd> {
 pub enum ProfileViewAction {
     EditProfile,
     Note(NoteAction),
+
+    /// Follow or unfollow as the account acting in this column
+    Contact(Pubkey, ContactAction),
+    DismissContactConflict(Pubkey),
+    RecurringZap(NewRecurringZap),
 }
 
 impl<'a, 'd> ProfileView<'a, 'd> {
@@ -42,7 +55,8 @@ impl<'a, 'd> ProfileView<'a, 'd> {
     pub fn new(
         pubkey: &'a Pubkey,
         accounts: &'a Accounts,
-        col_id: usize,
+        acting: Option<Pubkey>,
+        col_id: egui::Id,
         timeline_cache: &'a mut TimelineCache,
         note_options: NoteOptions,
         unknown_ids: &'a mut UnknownIds,
@@ -53,6 +67,7 @@ impl<'a, 'd> ProfileView<'a, 'd> {
         ProfileView {
             pubkey,
             accounts,
+            acting,
             col_id,
             timeline_cache,
             note_options,
@@ -64,7 +79,7 @@ impl<'a, 'd> ProfileView<'a, 'd> {
     }
 
     pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<ProfileViewAction> {
-        let scroll_id = egui::Id::new(("profile_scroll", self.col_id, self.pubkey));
+        let scroll_id = self.col_id.with(("profile_scroll", self.pubkey));
         let offset_id = scroll_id.with("scroll_offset");
 
         let mut scroll_area = ScrollArea::vertical().id_salt(scroll_id);
@@ -81,9 +96,7 @@ impl<'a, 'd> ProfileView<'a, 'd> {
                 .ndb
                 .get_profile_by_pubkey(&txn, self.pubkey.bytes())
             {
-                if self.profile_body(ui, profile) {
-                    action = Some(ProfileViewAction::EditProfile);
-                }
+                action = self.profile_body(ui, &txn, profile);
             }
             let profile_timeline = self
                 .timeline_cache
@@ -95,12 +108,42 @@ impl<'a, 'd> ProfileView<'a, 'd> {
                 )
                 .get_ptr();
 
-            profile_timeline.selected_view =
-                tabs_ui(ui, profile_timeline.selected_view, &profile_timeline.views);
+            let tab_id = self.col_id.with(("profile_tab", self.pubkey));
+            let tab = ui
+                .data(|d| d.get_temp::<usize>(tab_id))
+                .unwrap_or(profile_timeline.selected_view);
+            let tab = labeled_tabs_ui(ui, tab, &PROFILE_TABS);
+            ui.data_mut(|d| d.insert_temp(tab_id, tab));
+
+            let [media, zaps] = TimelineKind::profile_tabs(self.pubkey);
+            let kind = match tab {
+                2 => media,
+                3 => zaps,
+                view => {
+                    profile_timeline.selected_view = view;
+                    TimelineKind::Profile(*self.pubkey)
+                }
+            };
+
+            // the other tabs subscribe the first time they're shown, and
+            // close along with the profile. A new timeline has nothing
+            // stale to catch up on.
+            if !self.timeline_cache.timelines.contains_key(&kind) {
+                let _ = self.timeline_cache.open(
+                    self.note_context.ndb,
+                    self.note_context.note_cache,
+                    &txn,
+                    self.note_context.pool,
+                    &kind,
+                );
+            }
+            let Some(timeline) = self.timeline_cache.timelines.get_mut(&kind) else {
+                return (action, kind);
+            };
 
             let reversed = false;
             // poll for new notes and insert them into our existing notes
-            if let Err(e) = profile_timeline.poll_notes_into_view(
+            if let Err(e) = timeline.poll_notes_into_view(
                 self.note_context.ndb,
                 &txn,
                 self.unknown_ids,
@@ -110,34 +153,63 @@ impl<'a, 'd> ProfileView<'a, 'd> {
                 error!("Profile::poll_notes_into_view: {e}");
             }
 
-            if let Some(note_action) = TimelineTabView::new(
-                profile_timeline.current_view(),
-                reversed,
-                self.note_options,
-                &txn,
-                self.is_muted,
-                self.note_context,
-                &self
-                    .accounts
-                    .get_selected_account()
-                    .map(|a| (&a.key).into()),
-                self.jobs,
-            )
-            .show(ui)
-            {
+            let cur_acc = self.accounts.get_selected_account().map(|a| a.key.pubkey);
+            let note_action = match &kind {
+                TimelineKind::ProfileMedia(pk) => tabs::media_grid_ui(
+                    ui,
+                    self.note_context,
+                    &txn,
+                    cur_acc.as_ref(),
+                    pk,
+                    &timeline.current_view().notes,
+                ),
+                TimelineKind::ProfileZaps(pk) => tabs::zaps_ui(
+                    ui,
+                    self.note_context,
+                    &txn,
+                    pk,
+                    &timeline.current_view().notes,
+                ),
+                _ => TimelineTabView::new(
+                    timeline.current_view(),
+                    reversed,
+                    self.note_options,
+                    &txn,
+                    self.is_muted,
+                    self.note_context,
+                    &self
+                        .accounts
+                        .get_selected_account()
+                        .map(|a| (&a.key).into()),
+                    self.jobs,
+                )
+                .show(ui),
+            };
+            if let Some(note_action) = note_action {
                 action = Some(ProfileViewAction::Note(note_action));
             }
 
-            action
+            older_notes_spinner(ui, timeline);
+
+            (action, kind)
         });
 
         ui.data_mut(|d| d.insert_temp(offset_id, output.state.offset.y));
 
-        output.inner
+        if let Some(timeline) = self.timeline_cache.timelines.get_mut(&output.inner.1) {
+            load_older_near_bottom(ui, &output, timeline);
+        }
+
+        output.inner.0
     }
 
-    fn profile_body(&mut self, ui: &mut egui::Ui, profile: ProfileRecord<'_>) -> bool {
-        let mut action = false;
+    fn profile_body(
+        &mut self,
+        ui: &mut egui::Ui,
+        txn: &Transaction,
+        profile: ProfileRecord<'_>,
+    ) -> Option<ProfileViewAction> {
+        let mut action = None;
         ui.vertical(|ui| {
             banner(
                 ui,
@@ -164,7 +236,10 @@ impl<'a, 'd> ProfileView<'a, 'd> {
                         .border(ProfilePic::border_stroke(ui)),
                     );
 
-                    if ui.add(copy_key_widget(&pfp_rect)).clicked() {
+                    let copy_resp = ui
+                        .add(copy_key_widget(&pfp_rect))
+                        .on_hover_text("Copy npub, right click for more");
+                    if copy_resp.clicked() {
                         let to_copy = if let Some(bech) = self.pubkey.npub() {
                             bech
                         } else {
@@ -173,16 +248,36 @@ impl<'a, 'd> ProfileView<'a, 'd> {
                         };
                         ui.ctx().copy_text(to_copy)
                     }
+                    copy_resp.context_menu(|ui| {
+                        copy_profile_menu(ui, self.note_context.ndb, txn, self.pubkey, &profile);
+                    });
 
                     if self.accounts.contains_full_kp(self.pubkey) {
                         ui.with_layout(Layout::right_to_left(egui::Align::Max), |ui| {
                             if ui.add(edit_profile_button()).clicked() {
-                                action = true;
+                                action = Some(ProfileViewAction::EditProfile);
                             }
                         });
+                    } else if let Some(acting) = self.acting {
+                        if acting != *self.pubkey && self.note_context.current_account_can_sign {
+                            ui.with_layout(Layout::right_to_left(egui::Align::Max), |ui| {
+                                if let Some(contact_action) = self.follow_ui(ui, txn, &acting) {
+                                    action =
+                                        Some(ProfileViewAction::Contact(acting, contact_action));
+                                }
+                            });
+                        }
                     }
                 });
 
+                if let Some(acting) = self.acting {
+                    if let Some(conflict) = self.accounts.contact_conflict(&acting) {
+                        if contact_conflict_ui(ui, conflict.missing.len()) {
+                            action = Some(ProfileViewAction::DismissContactConflict(acting));
+                        }
+                    }
+                }
+
                 ui.add_space(18.0);
 
                 ui.add(display_name_widget(
@@ -194,6 +289,15 @@ impl<'a, 'd> ProfileView<'a, 'd> {
 
                 ui.add(about_section_widget(&profile));
 
+                let followers = self
+                    .note_context
+                    .counts
+                    .get(CountKind::Followers(*self.pubkey));
+                if followers > 0 {
+                    ui.add_space(4.0);
+                    ui.add(followers_widget(followers));
+                }
+
                 ui.horizontal_wrapped(|ui| {
                     if let Some(website_url) = profile
                         .record()
@@ -211,6 +315,16 @@ impl<'a, 'd> ProfileView<'a, 'd> {
                         .filter(|s| !s.is_empty())
                     {
                         handle_lud16(ui, lud16);
+
+                        if let Some(sender) = self
+                            .acting
+                            .filter(|acting| acting != self.pubkey)
+                            .filter(|_| self.note_context.current_account_has_wallet)
+                        {
+                            if let Some(new) = recurring_zap_menu(ui, sender, *self.pubkey) {
+                                action = Some(ProfileViewAction::RecurringZap(new));
+                            }
+                        }
                     }
                 });
             });
@@ -218,6 +332,173 @@ impl<'a, 'd> ProfileView<'a, 'd> {
 
         action
     }
+
+    fn follow_ui(
+        &self,
+        ui: &mut egui::Ui,
+        txn: &Transaction,
+        acting: &Pubkey,
+    ) -> Option<ContactAction> {
+        let following = pk1_is_following_pk2(
+            self.note_context.ndb,
+            txn,
+            acting.bytes(),
+            self.pubkey.bytes(),
+        );
+
+        // with no contact list to edit, following would publish a list of
+        // one over whatever the relays have
+        let state = match following {
+            None if !self.accounts.contacts_synced(acting) => None,
+            following => Some(following.unwrap_or(false)),
+        };
+
+        if !ui.add(follow_button(state)).clicked() {
+            return None;
+        }
+
+        match state? {
+            true => Some(ContactAction::Unfollow(*self.pubkey)),
+            false => Some(ContactAction::Follow(*self.pubkey)),
+        }
+    }
+}
+
+/// None while we're still waiting on relays for our contact list
+fn follow_button(following: Option<bool>) -> impl egui::Widget + 'static {
+    move |ui: &mut egui::Ui| -> egui::Response {
+        let sense = if following.is_some() {
+            Sense::click()
+        } else {
+            Sense::hover()
+        };
+        let (rect, resp) = ui.allocate_exact_size(vec2(100.0, 32.0), sense);
+        let painter = ui.painter_at(rect);
+        #[allow(deprecated)]
+        let rect = painter.round_rect_to_pixels(rect);
+
+        let visuals = ui.visuals();
+        let (fill, text, text_color) = match following {
+            Some(true) if resp.hovered() => (
+                visuals.widgets.active.bg_fill,
+                "Unfollow",
+                visuals.text_color(),
+            ),
+            Some(true) => (
+                visuals.widgets.inactive.bg_fill,
+                "Following",
+                visuals.text_color(),
+            ),
+            Some(false) => (colors::PINK, "Follow", Color32::WHITE),
+            None => (
+                visuals.widgets.noninteractive.bg_fill,
+                "Follow",
+                visuals.weak_text_color(),
+            ),
+        };
+
+        painter.rect_filled(rect, CornerRadius::same(8), fill);
+        let galley = painter.layout_no_wrap(
+            text.to_owned(),
+            NotedeckTextStyle::Button.get_font_id(ui.ctx()),
+            text_color,
+        );
+        painter.galley(rect.center() - galley.size() / 2.0, galley, text_color);
+
+        if following.is_none() {
+            resp.on_hover_text("Still fetching your contact list")
+        } else {
+            resp
+        }
+    }
+}
+
+/// Our last follow or unfollow replaced a copy of the contact list we
+/// hadn't seen yet. Returns true when the warning is dismissed.
+/// A menu for setting up a zap to this profile that repeats
+fn recurring_zap_menu(
+    ui: &mut egui::Ui,
+    sender: Pubkey,
+    recipient: Pubkey,
+) -> Option<NewRecurringZap> {
+    let mut new = None;
+    ui.menu_button("🔁 Zap regularly", |ui| {
+        let id = egui::Id::new(("recurring_zap_form", recipient));
+        let (mut sats, mut interval) = ui.data_mut(|d| {
+            d.get_temp_mut_or_insert_with(id, || ("1000".to_owned(), ZapInterval::Weekly))
+                .clone()
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut sats).desired_width(80.0));
+            ui.label("sats");
+        });
+        egui::ComboBox::from_id_salt(id.with("interval"))
+            .selected_text(interval.label())
+            .show_ui(ui, |ui| {
+                for option in ZapInterval::ALL {
+                    ui.selectable_value(&mut interval, option, option.label());
+                }
+            });
+
+        let amount = sats.trim().parse::<u64>().ok().filter(|sats| *sats > 0);
+        if ui
+            .add_enabled(amount.is_some(), egui::Button::new("Start"))
+            .clicked()
+        {
+            new = amount.map(|sats| NewRecurringZap {
+                sender,
+                recipient,
+                msats: sats * 1000,
+                interval,
+            });
+            ui.close_menu();
+        }
+
+        ui.data_mut(|d| d.insert_temp(id, (sats, interval)));
+    });
+    new
+}
+
+fn contact_conflict_ui(ui: &mut egui::Ui, missing: usize) -> bool {
+    let follows = if missing == 1 { "follow" } else { "follows" };
+    let mut dismissed = false;
+
+    ui.add_space(8.0);
+    ui.horizontal_wrapped(|ui| {
+        ui.label(
+            RichText::new(format!(
+                "Your contact list was changed on another client before your last edit, \
+                 {missing} {follows} from that copy were dropped."
+            ))
+            .color(ui.visuals().warn_fg_color),
+        );
+        dismissed = ui.small_button("Dismiss").clicked();
+    });
+
+    dismissed
+}
+
+fn followers_widget(followers: u64) -> impl egui::Widget {
+    move |ui: &mut egui::Ui| -> egui::Response {
+        let count = if followers < 1000 {
+            followers.to_string()
+        } else {
+            human_format::Formatter::new()
+                .with_decimals(1)
+                .with_separator("")
+                .format(followers as f64)
+        };
+
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 4.0;
+            ui.label(RichText::new(count).strong());
+            ui.label(
+                RichText::new("Followers").color(ui.visuals().noninteractive().fg_stroke.color),
+            );
+        })
+        .response
+    }
 }
 
 fn handle_link(ui: &mut egui::Ui, website_url: &str) {
@@ -244,6 +525,36 @@ fn handle_lud16(ui: &mut egui::Ui, lud16: &str) {
     let _ = ui.label(RichText::new(lud16).color(notedeck_ui::colors::PINK));
 }
 
+/// The profile as an npub or nprofile. Relay hints come from wherever we
+/// got their profile from.
+fn copy_profile_menu(
+    ui: &mut egui::Ui,
+    ndb: &Ndb,
+    txn: &Transaction,
+    pubkey: &Pubkey,
+    profile: &ProfileRecord<'_>,
+) {
+    let mut to_copy = None;
+    if ui.button("npub").clicked() {
+        to_copy = pubkey.npub();
+    }
+    if ui.button("nprofile").clicked() {
+        to_copy = nip19::nprofile(pubkey.bytes(), &[]);
+    }
+    if ui.button("nprofile with relay hints").clicked() {
+        let relays = ndb
+            .get_note_by_key(txn, NoteKey::new(profile.record().note_key()))
+            .map(|note| nip19::relay_hints(&note, txn))
+            .unwrap_or_default();
+        to_copy = nip19::nprofile(pubkey.bytes(), &relays);
+    }
+
+    if let Some(bech) = to_copy {
+        ui.ctx().copy_text(bech);
+        ui.close_menu();
+    }
+}
+
 fn copy_key_widget(pfp_rect: &egui::Rect) -> impl egui::Widget + '_ {
     |ui: &mut egui::Ui| -> egui::Response {
         let painter = ui.painter();
@@ -0,0 +1,182 @@
+use egui::{vec2, RichText, Sense};
+use enostr::{NoteId, Pubkey};
+use nostrdb::{Note, Transaction};
+use notedeck::{
+    name::get_display_name,
+    notifications::{zap_msats, zap_sender},
+    time_ago_since, NoteAction, NoteContext, NoteRef,
+};
+use notedeck_ui::{contacts::trust_media_from_pk2, images::paint_square_thumbnail, show_pointer};
+
+use crate::timeline::media::image_urls;
+
+const GRID_COLUMNS: usize = 3;
+const GRID_SPACING: f32 = 2.0;
+
+/// The pictures in `author`'s notes, three to a row. Each note shows its
+/// first picture, and clicking it opens the note. Pictures from people
+/// the account doesn't follow stay blank here, like they start out
+/// blurred in the note.
+pub fn media_grid_ui(
+    ui: &mut egui::Ui,
+    note_context: &mut NoteContext,
+    txn: &Transaction,
+    cur_acc: Option<&Pubkey>,
+    author: &Pubkey,
+    notes: &[NoteRef],
+) -> Option<NoteAction> {
+    if notes.is_empty() {
+        ui.vertical_centered(|ui| {
+            ui.add_space(24.0);
+            ui.weak("No pictures yet");
+        });
+        return None;
+    }
+
+    let trusted = trust_media_from_pk2(
+        note_context.ndb,
+        txn,
+        cur_acc.map(|pk| pk.bytes()),
+        author.bytes(),
+    );
+    let gaps = GRID_SPACING * (GRID_COLUMNS - 1) as f32;
+    let side = ((ui.available_width() - gaps) / GRID_COLUMNS as f32).floor();
+    let mut action = None;
+
+    ui.add_space(GRID_SPACING);
+    for row in notes.chunks(GRID_COLUMNS) {
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = GRID_SPACING;
+            for note_ref in row {
+                let (rect, resp) = ui.allocate_exact_size(vec2(side, side), Sense::click());
+                if !ui.is_rect_visible(rect) {
+                    continue;
+                }
+                ui.painter()
+                    .rect_filled(rect, 0.0, ui.visuals().faint_bg_color);
+
+                let Ok(note) = note_context.ndb.get_note_by_key(txn, note_ref.key) else {
+                    continue;
+                };
+                if let Some(url) = image_urls(&note).first().filter(|_| trusted) {
+                    paint_square_thumbnail(ui, note_context.img_cache, url, rect, 0.0);
+                }
+
+                if resp.hovered() {
+                    show_pointer(ui);
+                }
+                if resp.clicked() {
+                    action = Some(NoteAction::Note(NoteId::new(*note.id())));
+                }
+            }
+        });
+        ui.add_space(GRID_SPACING);
+    }
+
+    action
+}
+
+/// Zaps `profile` got and sent, newest first, one to a line
+pub fn zaps_ui(
+    ui: &mut egui::Ui,
+    note_context: &mut NoteContext,
+    txn: &Transaction,
+    profile: &Pubkey,
+    receipts: &[NoteRef],
+) -> Option<NoteAction> {
+    if receipts.is_empty() {
+        ui.vertical_centered(|ui| {
+            ui.add_space(24.0);
+            ui.weak("No zaps yet");
+        });
+        return None;
+    }
+
+    let mut action = None;
+    for receipt in receipts {
+        let Ok(receipt) = note_context.ndb.get_note_by_key(txn, receipt.key) else {
+            continue;
+        };
+        notedeck_ui::padding(8.0, ui, |ui| {
+            if let Some(zap_action) = zap_receipt_ui(ui, note_context, txn, &receipt, profile) {
+                action = Some(zap_action);
+            }
+        });
+        notedeck_ui::hline(ui);
+    }
+
+    action
+}
+
+/// How much, from or to whom, and a line of the note it was for
+fn zap_receipt_ui(
+    ui: &mut egui::Ui,
+    note_context: &NoteContext,
+    txn: &Transaction,
+    receipt: &Note,
+    profile: &Pubkey,
+) -> Option<NoteAction> {
+    let recipient = first_tag_id(receipt, "p")?;
+    let (other, direction) = if recipient == *profile.bytes() {
+        (zap_sender(receipt)?, "from")
+    } else {
+        (recipient, "to")
+    };
+
+    let amount = match zap_msats(receipt) {
+        Some(msats) => format!("⚡ {} sats {direction}", msats / 1000),
+        None => format!("⚡ zapped {direction}"),
+    };
+    let profile = note_context.ndb.get_profile_by_pubkey(txn, &other).ok();
+    let name = get_display_name(profile.as_ref()).name();
+    let weak = ui.visuals().weak_text_color();
+    let mut action = None;
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label(amount);
+        if ui.link(RichText::new(name).strong()).clicked() {
+            action = Some(NoteAction::Profile(Pubkey::new(other)));
+        }
+        ui.label(RichText::new(time_ago_since(receipt.created_at())).color(weak));
+    });
+
+    let Some(target) = first_tag_id(receipt, "e") else {
+        return action;
+    };
+    let preview = note_context
+        .ndb
+        .get_note_by_id(txn, &target)
+        .map(|note| {
+            note.content()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default();
+    if preview.is_empty() {
+        return action;
+    }
+
+    let resp = ui.add(
+        egui::Label::new(RichText::new(preview).color(weak))
+            .truncate()
+            .sense(Sense::click()),
+    );
+    if resp.hovered() {
+        show_pointer(ui);
+    }
+    if resp.clicked() {
+        action = Some(NoteAction::Note(NoteId::new(target)));
+    }
+
+    action
+}
+
+fn first_tag_id(note: &Note, name: &str) -> Option<[u8; 32]> {
+    note.tags().into_iter().find_map(|tag| {
+        if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some(name) {
+            return None;
+        }
+        tag.get_unchecked(1).variant().id().copied()
+    })
+}
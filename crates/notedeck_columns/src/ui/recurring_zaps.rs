@@ -0,0 +1,113 @@
+use egui::{Align, Layout, RichText, Ui};
+use enostr::Pubkey;
+use notedeck::{time_ago_since, NotedeckTextStyle};
+use notedeck_ui::padding;
+
+use crate::{
+    recurring_zaps::{RecurringZap, RecurringZaps},
+    scheduled::unix_now,
+};
+
+/// Runs shown under each zap, most recent first
+const SHOWN_RUNS: usize = 5;
+
+pub enum RecurringZapsAction {
+    Cancel(u64),
+}
+
+/// The recurring zaps that are set up, when they go out next and how the
+/// last few went
+pub struct RecurringZapsView<'a> {
+    recurring: &'a RecurringZaps,
+
+    /// Names of the senders and recipients
+    names: &'a [(Pubkey, String)],
+}
+
+impl<'a> RecurringZapsView<'a> {
+    pub fn new(recurring: &'a RecurringZaps, names: &'a [(Pubkey, String)]) -> Self {
+        RecurringZapsView { recurring, names }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<RecurringZapsAction> {
+        let mut action = None;
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                padding(16.0, ui, |ui| {
+                    let zaps = self.recurring.zaps();
+                    if zaps.is_empty() {
+                        ui.label(
+                            RichText::new("No recurring zaps. Pick Zap regularly on someone's profile to set one up.")
+                                .color(ui.visuals().weak_text_color()),
+                        );
+                        return;
+                    }
+
+                    for zap in zaps {
+                        ui.horizontal(|ui| {
+                            ui.vertical(|ui| self.zap_ui(ui, zap));
+
+                            ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                                if ui.small_button("Cancel").clicked() {
+                                    action = Some(RecurringZapsAction::Cancel(zap.id));
+                                }
+                            });
+                        });
+                        ui.separator();
+                    }
+                });
+            });
+
+        action
+    }
+
+    fn zap_ui(&self, ui: &mut Ui, zap: &RecurringZap) {
+        ui.label(
+            RichText::new(format!(
+                "⚡ {} sats to {} {}",
+                zap.msats / 1000,
+                self.name(&zap.recipient),
+                zap.interval.label()
+            ))
+            .text_style(NotedeckTextStyle::Body.text_style())
+            .strong(),
+        );
+        ui.weak(format!("From {}", self.name(&zap.sender)));
+
+        if zap.is_paying() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.weak("Zapping now…");
+            });
+        } else if zap.next_at > unix_now() {
+            ui.weak(format!(
+                "Next zap in {}",
+                time_ago_since(zap.next_at).trim_start_matches('+')
+            ));
+        }
+
+        for run in zap.history.iter().rev().take(SHOWN_RUNS) {
+            let when = time_ago_since(run.at);
+            match &run.error {
+                None => {
+                    ui.weak(format!("✔ Sent {when} ago"));
+                }
+                Some(error) => {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!("✖ Failed {when} ago: {error}"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn name(&self, pubkey: &Pubkey) -> &str {
+        self.names
+            .iter()
+            .find(|(pk, _)| pk == pubkey)
+            .map_or("someone", |(_, name)| name.as_str())
+    }
+}
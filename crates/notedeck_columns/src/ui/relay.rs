@@ -1,13 +1,14 @@
 use std::collections::HashMap;
 
 use crate::relay_pool_manager::{RelayPoolManager, RelayStatus};
+use crate::relay_test::{RelayTest, RelayTests};
 use crate::ui::{Preview, PreviewConfig};
 use egui::{
     Align, Button, CornerRadius, Frame, Id, Image, Layout, Margin, Rgba, RichText, Ui, Vec2,
 };
-use enostr::RelayPool;
+use enostr::{Compression, RelayInformation, RelayPool, RelayRoles};
 use notedeck::{Accounts, NotedeckTextStyle};
-use notedeck_ui::{colors::PINK, padding, View};
+use notedeck_ui::{colors::PINK, padding};
 use tracing::debug;
 
 use super::widgets::styled_button;
@@ -16,10 +17,29 @@ pub struct RelayView<'a> {
     accounts: &'a mut Accounts,
     manager: RelayPoolManager<'a>,
     id_string_map: &'a mut HashMap<Id, String>,
+    tests: &'a RelayTests,
 }
 
-impl View for RelayView<'_> {
-    fn ui(&mut self, ui: &mut egui::Ui) {
+pub enum RelayViewAction {
+    OpenMuted,
+
+    /// The relay was removed from the account's relay list
+    Removed(String),
+
+    /// Check whether the relay accepts our events
+    Test(String),
+}
+
+enum RelayRowAction {
+    Remove(String),
+    Test(String),
+    Roles(String, RelayRoles),
+}
+
+impl RelayView<'_> {
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<RelayViewAction> {
+        let mut action = None;
+
         Frame::new()
             .inner_margin(Margin::symmetric(10, 0))
             .show(ui, |ui| {
@@ -32,6 +52,16 @@ impl View for RelayView<'_> {
                                 .text_style(NotedeckTextStyle::Heading2.text_style()),
                         );
                     });
+
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        if ui
+                            .add(Button::new(RichText::new("Muted").color(PINK)).frame(false))
+                            .on_hover_text("Muted words, hashtags and threads")
+                            .clicked()
+                        {
+                            action = Some(RelayViewAction::OpenMuted);
+                        }
+                    });
                 });
 
                 ui.add_space(8.0);
@@ -40,9 +70,22 @@ impl View for RelayView<'_> {
                     .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysHidden)
                     .auto_shrink([false; 2])
                     .show(ui, |ui| {
-                        if let Some(relay_to_remove) = self.show_relays(ui) {
-                            self.accounts
-                                .remove_advertised_relay(&relay_to_remove, self.manager.pool);
+                        self.show_groups(ui);
+                        ui.add_space(8.0);
+
+                        match self.show_relays(ui) {
+                            Some(RelayRowAction::Remove(relay_to_remove)) => {
+                                self.accounts
+                                    .remove_advertised_relay(&relay_to_remove, self.manager.pool);
+                                action = Some(RelayViewAction::Removed(relay_to_remove));
+                            }
+                            Some(RelayRowAction::Test(relay)) => {
+                                action = Some(RelayViewAction::Test(relay));
+                            }
+                            Some(RelayRowAction::Roles(relay, roles)) => {
+                                self.manager.pool.set_roles(&relay, roles);
+                            }
+                            None => {}
                         }
                         ui.add_space(8.0);
                         if let Some(relay_to_add) = self.show_add_relay_ui(ui) {
@@ -51,6 +94,8 @@ impl View for RelayView<'_> {
                         }
                     });
             });
+
+        action
     }
 }
 
@@ -59,11 +104,13 @@ impl<'a> RelayView<'a> {
         accounts: &'a mut Accounts,
         manager: RelayPoolManager<'a>,
         id_string_map: &'a mut HashMap<Id, String>,
+        tests: &'a RelayTests,
     ) -> Self {
         RelayView {
             accounts,
             manager,
             id_string_map,
+            tests,
         }
     }
 
@@ -71,9 +118,10 @@ impl<'a> RelayView<'a> {
         egui::CentralPanel::default().show(ui.ctx(), |ui| self.ui(ui));
     }
 
-    /// Show the current relays and return a relay the user selected to delete
-    fn show_relays(&'a self, ui: &mut Ui) -> Option<String> {
-        let mut relay_to_remove = None;
+    /// Show the current relays and return a relay the user selected to
+    /// delete or test
+    fn show_relays(&'a self, ui: &mut Ui) -> Option<RelayRowAction> {
+        let mut row_action = None;
         for (index, relay_info) in self.manager.get_relay_infos().iter().enumerate() {
             ui.add_space(8.0);
             ui.vertical_centered_justified(|ui| {
@@ -92,7 +140,7 @@ impl<'a> RelayView<'a> {
                                                 - get_right_side_width(relay_info.status),
                                         ) // TODO: refactor to dynamically check the size of the 'right to left' portion and set the max width to be the screen width minus padding minus 'right to left' width
                                         .show(ui, |ui| {
-                                            ui.label(
+                                            let resp = ui.label(
                                                 RichText::new(relay_info.relay_url)
                                                     .text_style(
                                                         NotedeckTextStyle::Monospace.text_style(),
@@ -105,26 +153,151 @@ impl<'a> RelayView<'a> {
                                                             .color,
                                                     ),
                                             );
+
+                                            if let Some(info) = relay_info.info {
+                                                resp.on_hover_ui(|ui| {
+                                                    show_relay_information(ui, info)
+                                                });
+                                            }
                                         });
                                 });
                         });
 
                         ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
                             if ui.add(delete_button(ui.visuals().dark_mode)).clicked() {
-                                relay_to_remove = Some(relay_info.relay_url.to_string());
+                                row_action =
+                                    Some(RelayRowAction::Remove(relay_info.relay_url.to_string()));
                             };
 
+                            if let Some(current) = relay_info.roles {
+                                if let Some(roles) = roles_ui(ui, current) {
+                                    row_action = Some(RelayRowAction::Roles(
+                                        relay_info.relay_url.to_string(),
+                                        roles,
+                                    ));
+                                }
+                            }
+
+                            let testing = self
+                                .tests
+                                .get(relay_info.relay_url)
+                                .is_some_and(RelayTest::is_running);
+                            let connected = matches!(relay_info.status, RelayStatus::Connected);
+                            if ui
+                                .add_enabled(connected && !testing, Button::new("Test").small())
+                                .on_hover_text("Publish a throwaway event to check this relay takes your writes")
+                                .clicked()
+                            {
+                                row_action =
+                                    Some(RelayRowAction::Test(relay_info.relay_url.to_string()));
+                            }
+
                             show_connection_status(ui, relay_info.status);
+
+                            if let Some(rtt) = relay_info.rtt {
+                                ui.label(
+                                    RichText::new(format!("{}ms", rtt.as_millis()))
+                                        .text_style(NotedeckTextStyle::Small.text_style())
+                                        .color(ui.visuals().weak_text_color()),
+                                );
+                            }
+
+                            if relay_info.events > 0 {
+                                let dup_pct = relay_info.duplicates * 100 / relay_info.events;
+                                ui.label(
+                                    RichText::new(format!("{dup_pct}% dup"))
+                                        .text_style(NotedeckTextStyle::Small.text_style())
+                                        .color(ui.visuals().weak_text_color()),
+                                )
+                                .on_hover_text(format!(
+                                    "{} of {} events from this relay were already received",
+                                    relay_info.duplicates, relay_info.events
+                                ));
+                            }
+
+                            if let Some(compression) = relay_info.compression {
+                                show_compression(ui, compression);
+                            }
                         });
                     });
+
+                    if let Some(test) = self.tests.get(relay_info.relay_url) {
+                        show_test_result(ui, test);
+                    }
                 });
             });
         }
-        relay_to_remove
+        row_action
     }
 
     const RELAY_PREFILL: &'static str = "wss://";
 
+    /// Pick the relay group in use, or save the relays shown as one
+    fn show_groups(&mut self, ui: &mut Ui) {
+        let groups = self.accounts.relay_groups().clone();
+        let current = groups.active.clone();
+
+        ui.horizontal(|ui| {
+            ui.label("Relay group");
+            let selected = current.as_deref().unwrap_or("Account relays");
+            egui::ComboBox::from_id_salt("relay_group")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    let mut choice = current.clone();
+                    ui.selectable_value(&mut choice, None, "Account relays");
+                    for group in &groups.groups {
+                        ui.selectable_value(&mut choice, Some(group.name.clone()), &group.name);
+                    }
+                    if choice != current {
+                        self.accounts
+                            .switch_relay_group(choice.as_deref(), self.manager.pool);
+                    }
+                });
+
+            if let Some(group) = groups.active_group() {
+                let mut publish = group.publish;
+                if ui
+                    .checkbox(&mut publish, "Publish as relay list")
+                    .on_hover_text("Switching to this group updates your NIP-65 relay list")
+                    .changed()
+                {
+                    self.accounts.set_relay_group_publish(&group.name, publish);
+                }
+                if ui.button("Delete").clicked() {
+                    self.accounts.remove_relay_group(&group.name);
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let id = ui.id().with("relay-group-name");
+            let name = self.id_string_map.entry(id).or_default();
+            ui.add(
+                egui::TextEdit::singleline(name)
+                    .hint_text("Group name, like home or work")
+                    .desired_width(200.0),
+            );
+            let name = name.trim().to_owned();
+            if ui
+                .add_enabled(
+                    !name.is_empty(),
+                    Button::new("Save these relays as a group"),
+                )
+                .clicked()
+            {
+                let relays = self
+                    .manager
+                    .pool
+                    .urls()
+                    .into_iter()
+                    .filter(|url| url.starts_with("ws"))
+                    .collect();
+                self.accounts.save_relay_group(name, relays);
+                self.id_string_map.remove(&id);
+            }
+        });
+    }
+
     fn show_add_relay_ui(&mut self, ui: &mut Ui) -> Option<String> {
         let id = ui.id().with("add-relay)");
         match self.id_string_map.get(&id) {
@@ -199,14 +372,118 @@ fn add_relay_button2(is_enabled: bool) -> impl egui::Widget + 'static {
     }
 }
 
+fn show_relay_information(ui: &mut Ui, info: &RelayInformation) {
+    if let Some(name) = &info.name {
+        ui.strong(name);
+    }
+
+    if let Some(software) = &info.software {
+        let version = info.version.as_deref().unwrap_or_default();
+        ui.label(format!("{software} {version}"));
+    }
+
+    let nips = match info.supported_nips.as_deref() {
+        None | Some([]) => "none listed".to_owned(),
+        Some(nips) => nips
+            .iter()
+            .map(|nip| nip.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    ui.label(format!("Supported NIPs: {nips}"));
+}
+
+fn show_compression(ui: &mut Ui, compression: Compression) {
+    let (text, hover) = match compression {
+        Compression::Deflate => ("deflate", "Messages are compressed with permessage-deflate"),
+        Compression::None => ("raw", "This relay doesn't support compression"),
+    };
+
+    ui.label(
+        RichText::new(text)
+            .text_style(NotedeckTextStyle::Small.text_style())
+            .color(ui.visuals().weak_text_color()),
+    )
+    .on_hover_text(hover);
+}
+
 fn get_right_side_width(status: RelayStatus) -> f32 {
     match status {
-        RelayStatus::Connected => 150.0,
-        RelayStatus::Connecting => 160.0,
-        RelayStatus::Disconnected => 175.0,
+        RelayStatus::Connected => 425.0,
+        RelayStatus::Connecting => 295.0,
+        RelayStatus::Disconnected => 310.0,
     }
 }
 
+/// Toggles for what the relay is used for, laid out right to left.
+/// Returns the new roles if one was flipped.
+fn roles_ui(ui: &mut Ui, current: RelayRoles) -> Option<RelayRoles> {
+    let mut roles = current;
+
+    for (on, label, hover) in [
+        (&mut roles.search, "S", "Search: send NIP-50 searches here"),
+        (&mut roles.write, "W", "Write: publish your notes here"),
+        (
+            &mut roles.read,
+            "R",
+            "Read: load timelines and notes from here",
+        ),
+    ] {
+        ui.toggle_value(on, RichText::new(label).small())
+            .on_hover_text(hover);
+    }
+
+    (roles != current).then_some(roles)
+}
+
+fn show_test_result(ui: &mut Ui, test: &RelayTest) {
+    let weak = ui.visuals().weak_text_color();
+    let bad = ui.visuals().error_fg_color;
+    let small =
+        |text: String| RichText::new(text).text_style(NotedeckTextStyle::Small.text_style());
+
+    ui.horizontal_wrapped(|ui| match test {
+        RelayTest::Publishing { .. } => {
+            ui.spinner();
+            ui.label(small("Publishing a test event…".to_owned()).color(weak));
+        }
+        RelayTest::Querying { result, .. } => {
+            ui.label(small(format!("Accepted in {}ms", result.latency.as_millis())).color(weak));
+            ui.spinner();
+            ui.label(small("reading it back…".to_owned()).color(weak));
+        }
+        RelayTest::Done(result) if result.accepted => {
+            ui.label(small(format!("Accepted in {}ms", result.latency.as_millis())).color(weak));
+            match result.queryable {
+                Some(true) => ui.label(small("· readable back".to_owned()).color(weak)),
+                _ => ui
+                    .label(small("· couldn't read it back".to_owned()).color(bad))
+                    .on_hover_text(
+                        "The relay took the event but doesn't return it, \
+                         so others may not see what you post there",
+                    ),
+            };
+        }
+        RelayTest::Done(result) => {
+            let reason = if result.message.is_empty() {
+                "no reason given"
+            } else {
+                result.message.as_str()
+            };
+            ui.label(
+                small(format!(
+                    "Rejected in {}ms: {reason}",
+                    result.latency.as_millis()
+                ))
+                .color(bad),
+            );
+        }
+        RelayTest::TimedOut => {
+            ui.label(small("No answer from the relay".to_owned()).color(bad));
+        }
+    });
+}
+
 fn delete_button(_dark_mode: bool) -> egui::Button<'static> {
     /*
     let img_data = if dark_mode {
@@ -229,10 +506,11 @@ fn relay_frame(ui: &mut Ui) -> Frame {
 }
 
 fn show_connection_status(ui: &mut Ui, status: RelayStatus) {
+    let colors = notedeck::indicators::colors(ui.ctx());
     let fg_color = match status {
-        RelayStatus::Connected => ui.visuals().selection.bg_fill,
-        RelayStatus::Connecting => ui.visuals().warn_fg_color,
-        RelayStatus::Disconnected => ui.visuals().error_fg_color,
+        RelayStatus::Connected => colors.ok,
+        RelayStatus::Connecting => colors.pending,
+        RelayStatus::Disconnected => colors.error,
     };
     let bg_color = egui::lerp(Rgba::from(fg_color)..=Rgba::BLACK, 0.8).into();
 
@@ -292,10 +570,12 @@ mod preview {
         fn update(&mut self, app: &mut AppContext<'_>, ui: &mut egui::Ui) -> Option<AppAction> {
             self.pool.try_recv();
             let mut id_string_map = HashMap::new();
+            let tests = RelayTests::default();
             RelayView::new(
                 app.accounts,
                 RelayPoolManager::new(&mut self.pool),
                 &mut id_string_map,
+                &tests,
             )
             .ui(ui);
             None
@@ -0,0 +1,95 @@
+use egui::{Align, Layout, RichText, Ui};
+use notedeck::{time_ago_since, NotedeckTextStyle};
+use notedeck_ui::padding;
+
+use crate::{
+    scheduled::{unix_now, ScheduledPosts},
+    ui::note::PostType,
+};
+
+/// How much of a post's text its row shows
+const PREVIEW_CHARS: usize = 80;
+
+pub enum ScheduledAction {
+    /// Take it off the schedule and back into the composer
+    Edit(u64),
+    Cancel(u64),
+}
+
+/// Posts waiting for their time to be published
+pub struct ScheduledView<'a> {
+    scheduled: &'a ScheduledPosts,
+}
+
+impl<'a> ScheduledView<'a> {
+    pub fn new(scheduled: &'a ScheduledPosts) -> Self {
+        ScheduledView { scheduled }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<ScheduledAction> {
+        let mut action = None;
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                padding(16.0, ui, |ui| {
+                    let posts = self.scheduled.posts();
+                    if posts.is_empty() {
+                        ui.label(
+                            RichText::new("Nothing scheduled. Pick Schedule when writing a note to post it later.")
+                                .color(ui.visuals().weak_text_color()),
+                        );
+                        return;
+                    }
+
+                    for post in posts {
+                        let what = match post.post_type {
+                            PostType::New => "Note",
+                            PostType::Reply(_) => "Reply",
+                            PostType::Quote(_) => "Quote",
+                        };
+                        let text = &post.draft.buffer.text_buffer;
+                        let mut preview = text.chars().take(PREVIEW_CHARS).collect::<String>();
+                        if text.chars().count() > PREVIEW_CHARS {
+                            preview.push('…');
+                        }
+
+                        ui.horizontal(|ui| {
+                            ui.vertical(|ui| {
+                                let when = time_ago_since(post.publish_at);
+                                let heading = if post.publish_at > unix_now() {
+                                    format!("{what}, posting in {}", when.trim_start_matches('+'))
+                                } else {
+                                    format!("{what}, was due {when} ago")
+                                };
+                                ui.label(
+                                    RichText::new(heading)
+                                    .text_style(NotedeckTextStyle::Body.text_style())
+                                    .strong(),
+                                );
+                                ui.label(preview);
+                                if let Some(error) = &post.error {
+                                    ui.colored_label(
+                                        ui.visuals().error_fg_color,
+                                        format!("Couldn't post: {error}"),
+                                    );
+                                }
+                            });
+
+                            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                if ui.small_button("Cancel").clicked() {
+                                    action = Some(ScheduledAction::Cancel(post.id));
+                                }
+                                if ui.small_button("Edit").clicked() {
+                                    action = Some(ScheduledAction::Edit(post.id));
+                                }
+                            });
+                        });
+                        ui.separator();
+                    }
+                });
+            });
+
+        action
+    }
+}
@@ -4,8 +4,8 @@ use state::TypingType;
 
 use crate::{timeline::TimelineTab, ui::timeline::TimelineTabView};
 use egui_winit::clipboard::Clipboard;
-use nostrdb::{Filter, Ndb, Transaction};
-use notedeck::{MuteFun, NoteAction, NoteContext, NoteRef};
+use nostrdb::{Filter, Ndb, QueryResult, Transaction};
+use notedeck::{MuteFun, NoteAction, NoteContext, NoteRef, WebOfTrust};
 use notedeck_ui::{icons::search_icon, jobs::JobsCache, padding, NoteOptions};
 use std::time::{Duration, Instant};
 use tracing::{error, info, warn};
@@ -21,6 +21,7 @@ pub struct SearchView<'a, 'd> {
     note_options: NoteOptions,
     txn: &'a Transaction,
     is_muted: &'a MuteFun,
+    wot: Option<&'a WebOfTrust>,
     note_context: &'a mut NoteContext<'d>,
     cur_acc: &'a Option<KeypairUnowned<'a>>,
     jobs: &'a mut JobsCache,
@@ -39,6 +40,7 @@ impl<'a, 'd> SearchView<'a, 'd> {
         Self {
             txn,
             is_muted,
+            wot: None,
             query,
             note_options,
             note_context,
@@ -47,6 +49,12 @@ impl<'a, 'd> SearchView<'a, 'd> {
         }
     }
 
+    /// Collapse or hide results from outside the web of trust
+    pub fn wot(mut self, wot: Option<&'a WebOfTrust>) -> Self {
+        self.wot = wot;
+        self
+    }
+
     pub fn show(&mut self, ui: &mut egui::Ui, clipboard: &mut Clipboard) -> Option<NoteAction> {
         padding(8.0, ui, |ui| self.show_impl(ui, clipboard)).inner
     }
@@ -159,6 +167,7 @@ impl<'a, 'd> SearchView<'a, 'd> {
                     self.cur_acc,
                     self.jobs,
                 )
+                .wot(self.wot)
                 .show(ui)
             })
             .inner
@@ -184,7 +193,7 @@ fn execute_search(
     };
 
     tab.notes = note_refs;
-    tab.list.borrow_mut().reset();
+    tab.heights.get_mut().sync(&tab.notes);
     ctx.request_repaint();
 }
 
@@ -407,7 +416,7 @@ fn search_string(
         Ok(qrs) => {
             info!("queried '{}' and got {} results", query, qrs.len());
 
-            return Some(qrs.into_iter().map(NoteRef::from_query_result).collect());
+            return Some(unexpired(qrs));
         }
 
         Err(err) => {
@@ -421,6 +430,7 @@ fn search_string(
 fn search_note(noteid: &NoteId, ndb: &Ndb, txn: &Transaction) -> Option<NoteRef> {
     ndb.get_note_by_id(txn, noteid.bytes())
         .ok()
+        .filter(|n| !notedeck::note::is_expired(n))
         .map(|n| NoteRef::from_note(&n))
 }
 
@@ -432,7 +442,7 @@ fn search_pk(pk: &Pubkey, ndb: &Ndb, txn: &Transaction, max_results: u64) -> Opt
         .build();
 
     let qrs = ndb.query(txn, &[filter], max_results as i32).ok()?;
-    Some(qrs.into_iter().map(NoteRef::from_query_result).collect())
+    Some(unexpired(qrs))
 }
 
 fn search_hashtag(
@@ -448,5 +458,13 @@ fn search_hashtag(
         .build();
 
     let qrs = ndb.query(txn, &[filter], max_results as i32).ok()?;
-    Some(qrs.into_iter().map(NoteRef::from_query_result).collect())
+    Some(unexpired(qrs))
+}
+
+/// nostrdb keeps notes after they expire, leave those out of results
+fn unexpired(qrs: Vec<QueryResult>) -> Vec<NoteRef> {
+    qrs.into_iter()
+        .filter(|qr| !notedeck::note::is_expired(&qr.note))
+        .map(NoteRef::from_query_result)
+        .collect()
 }
@@ -16,6 +16,10 @@ pub struct SearchResultsView<'a> {
     txn: &'a Transaction,
     img_cache: &'a mut Images,
     results: &'a Vec<&'a [u8; 32]>,
+
+    /// Highlighted by the keyboard, and kept in view when `scroll_to` is set
+    selected: Option<usize>,
+    scroll_to: bool,
 }
 
 pub enum SearchResultsResponse {
@@ -35,9 +39,17 @@ impl<'a> SearchResultsView<'a> {
             txn,
             img_cache,
             results,
+            selected: None,
+            scroll_to: false,
         }
     }
 
+    pub fn selected(mut self, selected: Option<usize>, scroll_to: bool) -> Self {
+        self.selected = selected;
+        self.scroll_to = scroll_to;
+        self
+    }
+
     fn show(&mut self, ui: &mut egui::Ui, width: f32) -> SearchResultsResponse {
         let mut search_results_selection = None;
         ui.vertical(|ui| {
@@ -50,10 +62,12 @@ impl<'a> SearchResultsView<'a> {
                     }
                 };
 
-                if ui
-                    .add(user_result(&profile, self.img_cache, i, width))
-                    .clicked()
-                {
+                let is_selected = self.selected == Some(i);
+                let resp = ui.add(user_result(&profile, self.img_cache, i, width, is_selected));
+                if is_selected && self.scroll_to {
+                    resp.scroll_to_me(None);
+                }
+                if resp.clicked() {
                     search_results_selection = Some(i)
                 }
             }
@@ -121,6 +135,7 @@ fn user_result<'a>(
     cache: &'a mut Images,
     index: usize,
     width: f32,
+    selected: bool,
 ) -> impl egui::Widget + 'a {
     move |ui: &mut egui::Ui| -> egui::Response {
         let min_img_size = 48.0;
@@ -129,6 +144,13 @@ fn user_result<'a>(
         let body_font_size = get_font_size(ui.ctx(), &NotedeckTextStyle::Body);
 
         let helper = AnimationHelper::new(ui, ("user_result", index), vec2(width, max_image));
+        if selected {
+            ui.painter().rect_filled(
+                helper.get_animation_rect(),
+                8.0,
+                ui.visuals().widgets.hovered.weak_bg_fill,
+            );
+        }
 
         let icon_rect = {
             let r = helper.get_animation_rect();
@@ -0,0 +1,694 @@
+use egui::{RichText, Ui};
+use enostr::Pubkey;
+use notedeck::indicators::IndicatorPalette;
+use notedeck::pow::MAX_DIFFICULTY;
+use notedeck::quiet_hours::{QuietRange, Weekdays};
+use notedeck::{
+    AccessibilitySettings, AnimationSettings, AuthorGroup, CacheQuotaSettings, DiskQuota,
+    LinkPreviewSettings, MotionPreference, NotedeckTextStyle, NotificationFilterSettings,
+    OsNotificationSettings, QuietHoursSettings, Sound, SoundSettings,
+};
+use notedeck_ui::padding;
+
+use crate::post_as::PostAsSettings;
+use crate::pow::PowSettings;
+use crate::reply_tags::ReplyTagSettings;
+use crate::truncate::TruncateSettings;
+use crate::undo_send::{UndoSendSettings, UNDO_SEND_DELAYS};
+
+pub enum SettingsAction {
+    OpenRelays,
+    OpenMuted,
+    OpenDrafts,
+    OpenScheduled,
+    OpenRecurringZaps,
+    OpenTipJar,
+    OpenWhatsNew,
+    Animation(AnimationSettings),
+    LinkPreviews(LinkPreviewSettings),
+    Accessibility(AccessibilitySettings),
+    PostAs(PostAsSettings),
+    Truncate(TruncateSettings),
+    UndoSend(UndoSendSettings),
+    Pow(PowSettings),
+    ReplyTags(ReplyTagSettings),
+    QuietHours(QuietHoursSettings),
+    Sounds(SoundSettings),
+    PreviewSound(Sound),
+    OsNotifications(OsNotificationSettings),
+    NotificationFilters(NotificationFilterSettings),
+    CacheQuota(CacheQuotaSettings),
+    ClearMediaCache,
+}
+
+/// App preferences, and the way to relays and mutes
+pub struct SettingsView<'a> {
+    animation: AnimationSettings,
+    accessibility: AccessibilitySettings,
+    link_previews: LinkPreviewSettings,
+
+    /// Whether the OS asked for reduced motion
+    os_reduce_motion: bool,
+    disk_quota: &'a DiskQuota,
+    post_as: &'a PostAsSettings,
+    truncate: TruncateSettings,
+    undo_send: UndoSendSettings,
+    pow: PowSettings,
+    reply_tags: ReplyTagSettings,
+    quiet_hours: &'a QuietHoursSettings,
+    sounds: SoundSettings,
+    os_notifications: OsNotificationSettings,
+    notification_filters: &'a NotificationFilterSettings,
+
+    /// The logged in accounts and their names
+    accounts: &'a [(Pubkey, String)],
+
+    /// Names of the people whose DMs get through quiet hours
+    dm_exceptions: &'a [(Pubkey, String)],
+
+    /// Names of the people whose notifications are muted
+    notification_mutes: &'a [(Pubkey, String)],
+}
+
+impl<'a> SettingsView<'a> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        animation: AnimationSettings,
+        accessibility: AccessibilitySettings,
+        link_previews: LinkPreviewSettings,
+        os_reduce_motion: bool,
+        disk_quota: &'a DiskQuota,
+        post_as: &'a PostAsSettings,
+        truncate: TruncateSettings,
+        undo_send: UndoSendSettings,
+        pow: PowSettings,
+        reply_tags: ReplyTagSettings,
+        quiet_hours: &'a QuietHoursSettings,
+        sounds: SoundSettings,
+        os_notifications: OsNotificationSettings,
+        notification_filters: &'a NotificationFilterSettings,
+        accounts: &'a [(Pubkey, String)],
+        dm_exceptions: &'a [(Pubkey, String)],
+        notification_mutes: &'a [(Pubkey, String)],
+    ) -> Self {
+        SettingsView {
+            animation,
+            accessibility,
+            link_previews,
+            os_reduce_motion,
+            disk_quota,
+            post_as,
+            truncate,
+            undo_send,
+            pow,
+            reply_tags,
+            quiet_hours,
+            sounds,
+            os_notifications,
+            notification_filters,
+            accounts,
+            dm_exceptions,
+            notification_mutes,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<SettingsAction> {
+        let mut action = None;
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                padding(16.0, ui, |ui| {
+                    section_heading(ui, "Account");
+                    if ui.link("Relays").clicked() {
+                        action = Some(SettingsAction::OpenRelays);
+                    }
+                    if ui.link("Muted words, hashtags and threads").clicked() {
+                        action = Some(SettingsAction::OpenMuted);
+                    }
+                    if ui.link("Recurring zaps").clicked() {
+                        action = Some(SettingsAction::OpenRecurringZaps);
+                    }
+                    if ui.link("What's new").clicked() {
+                        action = Some(SettingsAction::OpenWhatsNew);
+                    }
+
+                    ui.add_space(16.0);
+                    section_heading(ui, "Tipping");
+                    ui.weak("A pinned note with your lightning address, a QR and some suggested amounts.");
+                    if ui.link("Set up a tip jar").clicked() {
+                        action = Some(SettingsAction::OpenTipJar);
+                    }
+
+                    ui.add_space(16.0);
+                    section_heading(ui, "Timelines");
+                    if let Some(settings) = truncate_settings_ui(ui, self.truncate) {
+                        action = Some(SettingsAction::Truncate(settings));
+                    }
+
+                    ui.add_space(16.0);
+                    section_heading(ui, "Posting");
+                    if ui.link("Drafts").clicked() {
+                        action = Some(SettingsAction::OpenDrafts);
+                    }
+                    if ui.link("Scheduled posts").clicked() {
+                        action = Some(SettingsAction::OpenScheduled);
+                    }
+                    ui.add_space(4.0);
+                    if let Some(settings) = undo_send_settings_ui(ui, self.undo_send) {
+                        action = Some(SettingsAction::UndoSend(settings));
+                    }
+                    if let Some(settings) = pow_settings_ui(ui, self.pow) {
+                        action = Some(SettingsAction::Pow(settings));
+                    }
+                    if let Some(settings) = reply_tag_settings_ui(ui, self.reply_tags) {
+                        action = Some(SettingsAction::ReplyTags(settings));
+                    }
+                    if let Some(settings) = post_as_settings_ui(ui, self.post_as, self.accounts) {
+                        action = Some(SettingsAction::PostAs(settings));
+                    }
+
+                    ui.add_space(16.0);
+                    section_heading(ui, "Notifications");
+                    if let Some(settings) = notification_filter_settings_ui(
+                        ui,
+                        self.notification_filters,
+                        self.notification_mutes,
+                    ) {
+                        action = Some(SettingsAction::NotificationFilters(settings));
+                    }
+                    ui.add_space(8.0);
+                    if let Some(sound_action) = sound_settings_ui(ui, self.sounds) {
+                        action = Some(sound_action);
+                    }
+                    ui.add_space(8.0);
+                    if let Some(settings) = os_notification_settings_ui(ui, self.os_notifications)
+                    {
+                        action = Some(SettingsAction::OsNotifications(settings));
+                    }
+                    ui.add_space(8.0);
+                    if let Some(settings) =
+                        quiet_hours_settings_ui(ui, self.quiet_hours, self.dm_exceptions)
+                    {
+                        action = Some(SettingsAction::QuietHours(settings));
+                    }
+
+                    ui.add_space(16.0);
+                    section_heading(ui, "Accessibility");
+                    if let Some(settings) =
+                        accessibility_settings_ui(ui, self.accessibility, self.os_reduce_motion)
+                    {
+                        action = Some(SettingsAction::Accessibility(settings));
+                    }
+
+                    ui.add_space(16.0);
+                    section_heading(ui, "Media");
+                    if let Some(settings) = animation_settings_ui(ui, self.animation) {
+                        action = Some(SettingsAction::Animation(settings));
+                    }
+                    if let Some(settings) = link_preview_settings_ui(ui, self.link_previews) {
+                        action = Some(SettingsAction::LinkPreviews(settings));
+                    }
+
+                    ui.add_space(8.0);
+                    if let Some(cache_action) = media_cache_ui(ui, self.disk_quota) {
+                        action = Some(cache_action);
+                    }
+                });
+            });
+
+        action
+    }
+}
+
+fn section_heading(ui: &mut Ui, title: &str) {
+    ui.label(RichText::new(title).text_style(NotedeckTextStyle::Heading3.text_style()));
+    ui.add_space(4.0);
+}
+
+/// Returns the new settings if they were changed
+fn animation_settings_ui(ui: &mut Ui, current: AnimationSettings) -> Option<AnimationSettings> {
+    let mut settings = current;
+
+    ui.horizontal(|ui| {
+        ui.label("Animate up to");
+        ui.add(egui::DragValue::new(&mut settings.max_playing).range(0..=32));
+        ui.label("images at once");
+    });
+    ui.checkbox(
+        &mut settings.pause_hidden,
+        "Pause animations that are out of view",
+    );
+
+    (settings != current).then_some(settings)
+}
+
+/// Returns the new settings if they were changed
+fn link_preview_settings_ui(
+    ui: &mut Ui,
+    current: LinkPreviewSettings,
+) -> Option<LinkPreviewSettings> {
+    let mut settings = current;
+
+    ui.checkbox(&mut settings.enabled, "Show previews for links");
+    ui.weak("Previews are fetched from the linked site, which sees your IP address");
+
+    (settings != current).then_some(settings)
+}
+
+/// Returns the new settings if they were changed
+fn accessibility_settings_ui(
+    ui: &mut Ui,
+    current: AccessibilitySettings,
+    os_reduce_motion: bool,
+) -> Option<AccessibilitySettings> {
+    let mut settings = current;
+
+    ui.checkbox(&mut settings.high_contrast, "High contrast colors");
+
+    let system = if os_reduce_motion {
+        "Follow system (reduced)"
+    } else {
+        "Follow system (full)"
+    };
+    ui.horizontal(|ui| {
+        ui.label("Motion");
+        egui::ComboBox::from_id_salt("motion_preference")
+            .selected_text(match settings.motion {
+                MotionPreference::System => system,
+                MotionPreference::Reduced => "Reduced",
+                MotionPreference::Full => "Full",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut settings.motion, MotionPreference::System, system);
+                ui.selectable_value(&mut settings.motion, MotionPreference::Reduced, "Reduced");
+                ui.selectable_value(&mut settings.motion, MotionPreference::Full, "Full");
+            });
+    });
+    ui.weak("Reduced motion turns off animations and holds animated images still");
+
+    ui.horizontal(|ui| {
+        ui.label("Indicator colors");
+        egui::ComboBox::from_id_salt("indicator_palette")
+            .selected_text(settings.palette.label())
+            .show_ui(ui, |ui| {
+                for palette in IndicatorPalette::ALL {
+                    ui.selectable_value(&mut settings.palette, palette, palette.label());
+                }
+            });
+    });
+
+    ui.checkbox(
+        &mut settings.note_summaries,
+        "Read notes to screen readers as one summary",
+    );
+
+    (settings != current).then_some(settings)
+}
+
+/// Returns the new settings if they were changed
+fn truncate_settings_ui(ui: &mut Ui, current: TruncateSettings) -> Option<TruncateSettings> {
+    let mut settings = current;
+
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut settings.enabled, "Shorten long notes to");
+        ui.add_enabled(
+            settings.enabled,
+            egui::DragValue::new(&mut settings.max_lines).range(2..=50),
+        );
+        ui.label("lines");
+    });
+
+    (settings != current).then_some(settings)
+}
+
+/// Returns the new settings if they were changed
+fn undo_send_settings_ui(ui: &mut Ui, current: UndoSendSettings) -> Option<UndoSendSettings> {
+    let mut settings = current;
+
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut settings.enabled, "Wait");
+        ui.add_enabled(
+            settings.enabled,
+            egui::DragValue::new(&mut settings.delay_secs)
+                .range(UNDO_SEND_DELAYS)
+                .suffix(" s"),
+        );
+        ui.label("before posting, so it can be undone");
+    });
+
+    (settings != current).then_some(settings)
+}
+
+/// Returns the new settings if they were changed
+fn pow_settings_ui(ui: &mut Ui, current: PowSettings) -> Option<PowSettings> {
+    let mut settings = current;
+
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut settings.enabled, "Add proof of work to my notes,");
+        ui.add_enabled(
+            settings.enabled,
+            egui::DragValue::new(&mut settings.difficulty)
+                .range(1..=MAX_DIFFICULTY)
+                .suffix(" bits"),
+        );
+    });
+    if settings.enabled && settings.difficulty > 20 {
+        ui.weak("This can take minutes per note");
+    }
+
+    (settings != current).then_some(settings)
+}
+
+/// Returns the new settings if they were changed
+fn reply_tag_settings_ui(ui: &mut Ui, current: ReplyTagSettings) -> Option<ReplyTagSettings> {
+    let mut settings = current;
+
+    ui.checkbox(
+        &mut settings.positional,
+        "Tag replies the old way, for clients that put them in the wrong thread",
+    );
+    if settings.positional {
+        ui.weak("Replies go out without root and reply markers or the authors of those notes");
+    }
+
+    (settings != current).then_some(settings)
+}
+
+/// Returns the new settings if they were changed
+fn post_as_settings_ui(
+    ui: &mut Ui,
+    current: &PostAsSettings,
+    accounts: &[(Pubkey, String)],
+) -> Option<PostAsSettings> {
+    let mut settings = current.clone();
+
+    ui.add_enabled_ui(accounts.len() > 1, |ui| {
+        ui.checkbox(
+            &mut settings.confirm_secondary,
+            "Confirm before posting from an account other than my primary",
+        );
+    });
+
+    if accounts.len() < 2 {
+        ui.weak("Log in to another account to use this");
+    } else if settings.confirm_secondary {
+        if settings.primary.is_none() {
+            settings.primary = accounts.first().map(|(pk, _)| *pk);
+        }
+
+        let selected = accounts
+            .iter()
+            .find(|(pk, _)| Some(*pk) == settings.primary)
+            .map(|(_, name)| name.as_str())
+            .unwrap_or("Logged out account");
+
+        ui.horizontal(|ui| {
+            ui.label("Primary account");
+            egui::ComboBox::from_id_salt("primary_account")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (pubkey, name) in accounts {
+                        ui.selectable_value(&mut settings.primary, Some(*pubkey), name);
+                    }
+                });
+        });
+    }
+
+    (settings != *current).then_some(settings)
+}
+
+/// Returns the new settings if they were changed
+fn notification_filter_settings_ui(
+    ui: &mut Ui,
+    current: &NotificationFilterSettings,
+    mutes: &[(Pubkey, String)],
+) -> Option<NotificationFilterSettings> {
+    let mut settings = current.clone();
+
+    ui.label("Who can notify me");
+    egui::Grid::new("notification_filters")
+        .num_columns(2)
+        .show(ui, |ui| {
+            for (label, group) in [
+                ("Mentions", &mut settings.mention),
+                ("DMs", &mut settings.dm),
+                ("Zaps", &mut settings.zap),
+            ] {
+                ui.label(label);
+                egui::ComboBox::from_id_salt(("notification_filter", label))
+                    .selected_text(group.label())
+                    .show_ui(ui, |ui| {
+                        for choice in AuthorGroup::ALL {
+                            ui.selectable_value(group, choice, choice.label());
+                        }
+                    });
+                ui.end_row();
+            }
+        });
+    ui.weak("Mentions from outside the group are hidden from the notifications column too");
+    ui.add_space(4.0);
+
+    ui.horizontal(|ui| {
+        ui.label("Smallest zap to notify me about");
+        ui.add(
+            egui::DragValue::new(&mut settings.min_zap_sats)
+                .range(0..=1_000_000)
+                .suffix(" sats"),
+        );
+    });
+    ui.checkbox(&mut settings.hide_reactions, "Hide reactions");
+    ui.checkbox(&mut settings.hide_reposts, "Hide reposts");
+
+    ui.add_space(4.0);
+    ui.label("Never notify me about these people");
+    for (pubkey, name) in mutes {
+        ui.horizontal(|ui| {
+            ui.label(name);
+            if ui.small_button("Remove").clicked() {
+                settings.muted.retain(|pk| pk != pubkey);
+            }
+        });
+    }
+
+    let input_id = ui.id().with("notification_mute");
+    let mut input = ui.data(|d| d.get_temp::<String>(input_id).unwrap_or_default());
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut input).hint_text("npub"));
+        let pubkey = Pubkey::parse(input.trim()).ok();
+        if ui
+            .add_enabled(pubkey.is_some(), egui::Button::new("Add"))
+            .clicked()
+        {
+            if let Some(pubkey) = pubkey {
+                if !settings.muted.contains(&pubkey) {
+                    settings.muted.push(pubkey);
+                }
+                input.clear();
+            }
+        }
+    });
+    ui.data_mut(|d| d.insert_temp(input_id, input));
+
+    (settings != *current).then_some(settings)
+}
+
+fn sound_settings_ui(ui: &mut Ui, current: SoundSettings) -> Option<SettingsAction> {
+    let mut action = None;
+    let mut settings = current;
+
+    ui.checkbox(&mut settings.enabled, "Play sounds");
+    ui.add_enabled_ui(settings.enabled, |ui| {
+        egui::Grid::new("notification_sounds")
+            .num_columns(3)
+            .show(ui, |ui| {
+                for (label, sound) in [
+                    ("Mentions", &mut settings.mention),
+                    ("DMs", &mut settings.dm),
+                    ("Zaps", &mut settings.zap),
+                ] {
+                    ui.label(label);
+                    egui::ComboBox::from_id_salt(("notification_sound", label))
+                        .selected_text(sound.label())
+                        .show_ui(ui, |ui| {
+                            for choice in Sound::ALL {
+                                ui.selectable_value(sound, choice, choice.label());
+                            }
+                        });
+                    if ui
+                        .add_enabled(*sound != Sound::Silent, egui::Button::new("Play"))
+                        .clicked()
+                    {
+                        action = Some(SettingsAction::PreviewSound(*sound));
+                    }
+                    ui.end_row();
+                }
+            });
+    });
+
+    if settings != current {
+        action = Some(SettingsAction::Sounds(settings));
+    }
+    action
+}
+
+/// Returns the new settings if they were changed
+fn os_notification_settings_ui(
+    ui: &mut Ui,
+    current: OsNotificationSettings,
+) -> Option<OsNotificationSettings> {
+    let mut settings = current;
+
+    ui.checkbox(&mut settings.enabled, "Desktop notifications")
+        .on_hover_text("Clicking one opens the note or profile it's about");
+    ui.add_enabled_ui(settings.enabled, |ui| {
+        ui.checkbox(&mut settings.previews, "Show what the note says");
+    });
+
+    (settings != current).then_some(settings)
+}
+
+/// Returns the new settings if they were changed
+fn quiet_hours_settings_ui(
+    ui: &mut Ui,
+    current: &QuietHoursSettings,
+    dm_exceptions: &[(Pubkey, String)],
+) -> Option<QuietHoursSettings> {
+    let mut settings = current.clone();
+
+    ui.checkbox(&mut settings.enabled, "Quiet hours");
+    ui.weak("No sounds or popups at these times. Unread counts still go up.");
+    ui.add_space(4.0);
+
+    ui.add_enabled_ui(settings.enabled, |ui| {
+        let mut removed = None;
+        for (i, range) in settings.ranges.iter_mut().enumerate() {
+            ui.horizontal_wrapped(|ui| {
+                ui.push_id(i, |ui| {
+                    time_of_day_ui(ui, &mut range.start);
+                    ui.label("to");
+                    time_of_day_ui(ui, &mut range.end);
+                    for (day, name) in Weekdays::NAMES.iter().enumerate() {
+                        let day = day as u8;
+                        let mut on = range.days.contains(day);
+                        if ui.toggle_value(&mut on, *name).changed() {
+                            range.days.set(day, on);
+                        }
+                    }
+                    if ui.small_button("Remove").clicked() {
+                        removed = Some(i);
+                    }
+                });
+            });
+        }
+        if let Some(i) = removed {
+            settings.ranges.remove(i);
+        }
+        if ui.link("Add a time").clicked() {
+            settings.ranges.push(QuietRange::default());
+        }
+
+        ui.add_space(4.0);
+        ui.label("DMs from these people come through anyway");
+        for (pubkey, name) in dm_exceptions {
+            ui.horizontal(|ui| {
+                ui.label(name);
+                if ui.small_button("Remove").clicked() {
+                    settings.dm_exceptions.retain(|pk| pk != pubkey);
+                }
+            });
+        }
+
+        let input_id = ui.id().with("quiet_hours_dm_exception");
+        let mut input = ui.data(|d| d.get_temp::<String>(input_id).unwrap_or_default());
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut input).hint_text("npub"));
+            let pubkey = Pubkey::parse(input.trim()).ok();
+            if ui
+                .add_enabled(pubkey.is_some(), egui::Button::new("Add"))
+                .clicked()
+            {
+                if let Some(pubkey) = pubkey {
+                    if !settings.dm_exceptions.contains(&pubkey) {
+                        settings.dm_exceptions.push(pubkey);
+                    }
+                    input.clear();
+                }
+            }
+        });
+        ui.data_mut(|d| d.insert_temp(input_id, input));
+    });
+
+    (settings != *current).then_some(settings)
+}
+
+/// Hour and minute pickers for minutes after midnight
+fn time_of_day_ui(ui: &mut Ui, minutes: &mut u16) {
+    let mut hour = *minutes / 60;
+    let mut minute = *minutes % 60;
+
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 2.0;
+        ui.add(
+            egui::DragValue::new(&mut hour)
+                .range(0..=23)
+                .custom_formatter(|n, _| format!("{n:02}")),
+        );
+        ui.label(":");
+        ui.add(
+            egui::DragValue::new(&mut minute)
+                .range(0..=59)
+                .custom_formatter(|n, _| format!("{n:02}")),
+        );
+    });
+
+    *minutes = hour * 60 + minute;
+}
+
+fn media_cache_ui(ui: &mut Ui, disk_quota: &DiskQuota) -> Option<SettingsAction> {
+    let mut action = None;
+    let current = disk_quota.settings();
+    let mut settings = current;
+
+    ui.horizontal(|ui| {
+        ui.label("Keep up to");
+        ui.add(
+            egui::DragValue::new(&mut settings.max_mb)
+                .range(64..=65536)
+                .speed(16)
+                .suffix(" MB"),
+        );
+        ui.label("of images and video on disk");
+    });
+    if settings != current {
+        action = Some(SettingsAction::CacheQuota(settings));
+    }
+
+    ui.horizontal(|ui| {
+        let usage = match disk_quota.usage() {
+            Some(bytes) => format!("Using {}", format_bytes(bytes)),
+            None => "Measuring...".to_owned(),
+        };
+        ui.weak(usage);
+
+        let clear = ui.add_enabled(
+            !disk_quota.is_busy(),
+            egui::Button::new("Clear media cache"),
+        );
+        if clear.clicked() {
+            action = Some(SettingsAction::ClearMediaCache);
+        }
+    });
+
+    action
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const MB: f64 = 1024.0 * 1024.0;
+    let mb = bytes as f64 / MB;
+    if mb >= 1024.0 {
+        format!("{:.1} GB", mb / 1024.0)
+    } else {
+        format!("{mb:.0} MB")
+    }
+}
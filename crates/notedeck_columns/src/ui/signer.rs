@@ -0,0 +1,89 @@
+use egui::{Align2, Frame, RichText, Vec2};
+use notedeck::{Accounts, NotedeckTextStyle, SignerStatus};
+
+/// Shows a small panel in the corner while the selected account's remote
+/// signer has requests waiting on it, so it's clear why a post hasn't
+/// gone out yet
+pub fn pending_signer_overlay(ctx: &egui::Context, accounts: &mut Accounts) {
+    let Some(signer) = accounts.selected_signer() else {
+        return;
+    };
+
+    let pending = signer.pending_signatures();
+    let disconnected = matches!(signer.status(), SignerStatus::Disconnected(_));
+    let error = signer.last_error().map(|e| e.to_string());
+
+    if pending == 0 && !disconnected && error.is_none() {
+        return;
+    }
+
+    let mut cancel = false;
+    let mut dismiss = false;
+
+    egui::Area::new(egui::Id::new("pending_signer_overlay"))
+        .anchor(Align2::RIGHT_BOTTOM, Vec2::new(-16.0, -16.0))
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_max_width(260.0);
+
+                match signer.status() {
+                    SignerStatus::Disconnected(reason) => {
+                        ui.label(
+                            RichText::new("Signer disconnected")
+                                .strong()
+                                .color(ui.visuals().error_fg_color),
+                        );
+                        ui.label(
+                            RichText::new(format!("{reason}. Retrying..."))
+                                .text_style(NotedeckTextStyle::Small.text_style())
+                                .color(ui.visuals().weak_text_color()),
+                        );
+                    }
+
+                    SignerStatus::Connecting | SignerStatus::Connected if pending > 0 => {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::Spinner::new());
+                            ui.label(if pending == 1 {
+                                "Waiting for your signer to approve".to_owned()
+                            } else {
+                                format!("Waiting for your signer to approve {pending} notes")
+                            });
+                        });
+                    }
+
+                    _ => {}
+                }
+
+                if let Some(url) = signer.auth_url() {
+                    ui.hyperlink_to("Approve in browser", url);
+                }
+
+                if let Some(error) = &error {
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            RichText::new(error)
+                                .text_style(NotedeckTextStyle::Small.text_style())
+                                .color(ui.visuals().error_fg_color),
+                        );
+                        if ui.small_button("Dismiss").clicked() {
+                            dismiss = true;
+                        }
+                    });
+                }
+
+                if pending > 0 && ui.button("Cancel").clicked() {
+                    cancel = true;
+                }
+            });
+        });
+
+    if let Some(signer) = accounts.selected_signer_mut() {
+        if cancel {
+            signer.cancel_pending();
+        }
+        if dismiss {
+            signer.clear_error();
+        }
+    }
+}
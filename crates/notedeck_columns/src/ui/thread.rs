@@ -1,12 +1,86 @@
-use enostr::KeypairUnowned;
-use nostrdb::Transaction;
-use notedeck::{MuteFun, NoteAction, NoteContext, RootNoteId, UnknownIds};
+use egui::{Align, Key, Layout, Modifiers};
+use enostr::{KeypairUnowned, Pubkey};
+use nostrdb::{NoteKey, Transaction};
+use notedeck::{
+    FollowedThreads, MuteFun, NoteAction, NoteContext, RootNoteId, UnknownIds, WebOfTrust,
+};
 use notedeck_ui::jobs::JobsCache;
 use notedeck_ui::NoteOptions;
 use tracing::error;
 
+use crate::reading_size::{
+    ReadingSizes, ReadingView, MAX_TEXT_SCALE, MIN_TEXT_SCALE, TEXT_SCALE_STEP,
+};
 use crate::timeline::{ThreadSelection, TimelineCache, TimelineKind};
-use crate::ui::timeline::TimelineTabView;
+use crate::ui::timeline::{NoteHighlights, TimelineTabView};
+
+/// Find in thread, kept per thread view while the app is open
+#[derive(Clone, Default)]
+struct FindState {
+    open: bool,
+    query: String,
+
+    /// Notes that match, in the order they're shown
+    matches: Vec<NoteKey>,
+    current: usize,
+
+    /// Scroll to the current match once it's laid out
+    jump: bool,
+
+    /// The query and note count `matches` was found with
+    matched: (String, usize),
+}
+
+impl FindState {
+    fn current(&self) -> Option<NoteKey> {
+        self.matches.get(self.current).copied()
+    }
+
+    fn step(&mut self, forward: bool) {
+        let len = self.matches.len();
+        if len == 0 {
+            return;
+        }
+        self.current = if forward {
+            (self.current + 1) % len
+        } else {
+            (self.current + len - 1) % len
+        };
+        self.jump = true;
+    }
+}
+
+/// How long the note a thread was opened at stays highlighted
+const TARGET_FLASH_SECS: f64 = 2.0;
+
+/// Give up on scrolling to the target if it hasn't loaded by then
+const TARGET_WAIT_SECS: f64 = 5.0;
+
+/// Landing on the note the thread was opened at, when it isn't the root
+#[derive(Clone)]
+struct TargetState {
+    note_id: [u8; 32],
+    opened_at: f64,
+    scrolled: bool,
+
+    /// When it was scrolled into view, the flash starts from there
+    shown_at: Option<f64>,
+}
+
+impl TargetState {
+    fn pending(&self, now: f64) -> bool {
+        !self.scrolled && now - self.opened_at < TARGET_WAIT_SECS
+    }
+
+    /// How much highlight is left, None once it's faded out
+    fn flash(&self, now: f64) -> Option<f32> {
+        let Some(shown_at) = self.shown_at else {
+            return self.pending(now).then_some(1.0);
+        };
+        let t = (now - shown_at) / TARGET_FLASH_SECS;
+        (t < 1.0).then(|| (1.0 - t) as f32)
+    }
+}
 
 pub struct ThreadView<'a, 'd> {
     timeline_cache: &'a mut TimelineCache,
@@ -15,9 +89,16 @@ pub struct ThreadView<'a, 'd> {
     note_options: NoteOptions,
     id_source: egui::Id,
     is_muted: &'a MuteFun,
+    wot: Option<&'a WebOfTrust>,
     note_context: &'a mut NoteContext<'d>,
     cur_acc: &'a Option<KeypairUnowned<'a>>,
     jobs: &'a mut JobsCache,
+
+    /// Where the thread's text size is kept, no size control without it
+    reading_sizes: Option<&'a mut ReadingSizes>,
+
+    /// The account getting notified of replies, and the threads it follows
+    followed_threads: Option<(Pubkey, &'a mut FollowedThreads)>,
 }
 
 impl<'a, 'd> ThreadView<'a, 'd> {
@@ -40,9 +121,12 @@ impl<'a, 'd> ThreadView<'a, 'd> {
             note_options,
             id_source,
             is_muted,
+            wot: None,
             note_context,
             cur_acc,
             jobs,
+            reading_sizes: None,
+            followed_threads: None,
         }
     }
 
@@ -51,9 +135,142 @@ impl<'a, 'd> ThreadView<'a, 'd> {
         self
     }
 
+    /// Collapse or hide replies from outside the web of trust
+    pub fn wot(mut self, wot: Option<&'a WebOfTrust>) -> Self {
+        self.wot = wot;
+        self
+    }
+
+    pub fn reading_sizes(mut self, reading_sizes: &'a mut ReadingSizes) -> Self {
+        self.reading_sizes = Some(reading_sizes);
+        self
+    }
+
+    /// Let `account` follow the thread, or stop following it
+    pub fn followed_threads(
+        mut self,
+        account: Option<Pubkey>,
+        followed_threads: &'a mut FollowedThreads,
+    ) -> Self {
+        self.followed_threads = account.map(|account| (account, followed_threads));
+        self
+    }
+
     pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<NoteAction> {
         let txn = Transaction::new(self.note_context.ndb).expect("txn");
 
+        let root_id = match RootNoteId::new(
+            self.note_context.ndb,
+            self.note_context.note_cache,
+            &txn,
+            self.selected_note_id,
+        ) {
+            Ok(root_id) => root_id,
+
+            Err(err) => {
+                ui.label(format!("Error loading thread: {:?}", err));
+                return None;
+            }
+        };
+
+        let thread_timeline = self
+            .timeline_cache
+            .notes(
+                self.note_context.ndb,
+                self.note_context.note_cache,
+                &txn,
+                &TimelineKind::Thread(ThreadSelection::from_root_id(root_id.to_owned())),
+            )
+            .get_ptr();
+
+        // TODO(jb55): skip poll if ThreadResult is fresh?
+
+        let reversed = true;
+        // poll for new notes and insert them into our existing notes
+        if let Err(err) = thread_timeline.poll_notes_into_view(
+            self.note_context.ndb,
+            &txn,
+            self.unknown_ids,
+            self.note_context.note_cache,
+            reversed,
+        ) {
+            error!("error polling notes into thread timeline: {err}");
+        }
+        thread_timeline.tree.update(
+            self.note_context.ndb,
+            &txn,
+            self.unknown_ids,
+            &thread_timeline.views[thread_timeline.selected_view].notes,
+            root_id.bytes(),
+            self.selected_note_id,
+        );
+
+        let find_id = self.id_source.with(("find", root_id.bytes()));
+        let mut find = ui
+            .data(|d| d.get_temp::<FindState>(find_id))
+            .unwrap_or_default();
+
+        let hovered = ui.rect_contains_pointer(ui.max_rect());
+        if hovered && ui.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::F)) {
+            find.open = true;
+            ui.memory_mut(|m| m.request_focus(find_id.with("query")));
+        }
+
+        let notes = &thread_timeline.tree.tab.notes;
+
+        let now = ui.input(|i| i.time);
+        let target_id = self.id_source.with("target");
+        let mut target = ui
+            .data(|d| d.get_temp::<TargetState>(target_id))
+            .filter(|t| t.note_id == *self.selected_note_id)
+            .unwrap_or_else(|| TargetState {
+                note_id: *self.selected_note_id,
+                opened_at: now,
+                // the root is already at the top
+                scrolled: root_id.bytes() == self.selected_note_id,
+                shown_at: None,
+            });
+        let target_key = self
+            .note_context
+            .ndb
+            .get_notekey_by_id(&txn, self.selected_note_id)
+            .ok()
+            .filter(|key| notes.iter().any(|n| n.key == *key));
+
+        if let Some((account, followed_threads)) = &mut self.followed_threads {
+            let following = followed_threads.is_following(account.bytes(), root_id.bytes());
+            match follow_thread_ui(ui, following) {
+                Some(true) => followed_threads.follow(account.bytes(), *root_id.bytes()),
+                Some(false) => followed_threads.unfollow(account.bytes(), root_id.bytes()),
+                None => {}
+            }
+        }
+
+        let text_scale = if let Some(reading_sizes) = self.reading_sizes.as_deref_mut() {
+            let scale = reading_sizes.scale(ReadingView::Thread);
+            if let Some(scale) = text_size_ui(ui, scale) {
+                reading_sizes.set_scale(ReadingView::Thread, scale);
+            }
+            reading_sizes.scale(ReadingView::Thread)
+        } else {
+            1.0
+        };
+
+        if find.open {
+            if find.matched != (find.query.clone(), notes.len()) {
+                let previous = find.current();
+                find.matches = find_matches(self.note_context.ndb, &txn, notes, &find.query);
+                find.current = previous
+                    .and_then(|key| find.matches.iter().position(|k| *k == key))
+                    .unwrap_or(0);
+                // new results from typing get shown, new replies coming in don't
+                find.jump |= find.matched.0 != find.query;
+                find.matched = (find.query.clone(), notes.len());
+            }
+
+            find_bar_ui(ui, find_id, &mut find);
+        }
+
         let mut scroll_area = egui::ScrollArea::vertical()
             .id_salt(self.id_source)
             .animated(false)
@@ -66,48 +283,57 @@ impl<'a, 'd> ThreadView<'a, 'd> {
             scroll_area = scroll_area.vertical_scroll_offset(offset);
         }
 
-        let output = scroll_area.show(ui, |ui| {
-            let root_id = match RootNoteId::new(
-                self.note_context.ndb,
-                self.note_context.note_cache,
-                &txn,
-                self.selected_note_id,
-            ) {
-                Ok(root_id) => root_id,
-
-                Err(err) => {
-                    ui.label(format!("Error loading thread: {:?}", err));
-                    return None;
+        // a match that isn't laid out yet can't scroll itself into view, so
+        // get close using the average note height and finish next frame
+        let jump_to = if find.open {
+            find.current().filter(|_| find.jump)
+        } else {
+            target_key.filter(|_| target.pending(now))
+        };
+        if let Some(key) = jump_to {
+            let size_id = self.id_source.with("content_height");
+            let position = notes.iter().position(|n| n.key == key);
+            if let (Some(position), Some(height)) =
+                (position, ui.data(|d| d.get_temp::<f32>(size_id)))
+            {
+                let offset = position as f32 * height / notes.len().max(1) as f32;
+                let viewport = ui.available_height();
+                let current = ui
+                    .data(|d| d.get_temp::<f32>(offset_id))
+                    .unwrap_or_default();
+                if offset < current || offset > current + viewport {
+                    scroll_area = scroll_area.vertical_scroll_offset(offset);
                 }
-            };
-
-            let thread_timeline = self
-                .timeline_cache
-                .notes(
-                    self.note_context.ndb,
-                    self.note_context.note_cache,
-                    &txn,
-                    &TimelineKind::Thread(ThreadSelection::from_root_id(root_id.to_owned())),
-                )
-                .get_ptr();
-
-            // TODO(jb55): skip poll if ThreadResult is fresh?
-
-            let reversed = true;
-            // poll for new notes and insert them into our existing notes
-            if let Err(err) = thread_timeline.poll_notes_into_view(
-                self.note_context.ndb,
-                &txn,
-                self.unknown_ids,
-                self.note_context.note_cache,
-                reversed,
-            ) {
-                error!("error polling notes into thread timeline: {err}");
             }
+        }
+
+        let flash = target.flash(now);
+        let highlights = if find.open {
+            Some(NoteHighlights {
+                notes: &find.matches,
+                current: find.current(),
+                scroll_to_current: jump_to.is_some(),
+                strength: 1.0,
+            })
+        } else if let (Some(strength), Some(key)) = (flash, target_key) {
+            ui.ctx().request_repaint();
+            Some(NoteHighlights {
+                notes: &[],
+                current: Some(key),
+                scroll_to_current: jump_to.is_some(),
+                strength,
+            })
+        } else {
+            None
+        };
+        let mut scrolled_to_current = false;
+        let mut tree_action = None;
 
-            TimelineTabView::new(
-                thread_timeline.current_view(),
-                true,
+        let output = scroll_area.show(ui, |ui| {
+            scale_text(ui, text_scale);
+            let mut view = TimelineTabView::new(
+                &thread_timeline.tree.tab,
+                false,
                 self.note_options,
                 &txn,
                 self.is_muted,
@@ -115,11 +341,179 @@ impl<'a, 'd> ThreadView<'a, 'd> {
                 self.cur_acc,
                 self.jobs,
             )
-            .show(ui)
+            .wot(self.wot)
+            .trusted_note(self.selected_note_id)
+            .highlights(highlights)
+            .tree(&thread_timeline.tree);
+
+            let action = view.show(ui);
+            scrolled_to_current = view.scrolled_to_current;
+            tree_action = view.tree_action;
+            action
         });
 
-        ui.data_mut(|d| d.insert_temp(offset_id, output.state.offset.y));
+        if let Some(tree_action) = tree_action {
+            thread_timeline.tree.apply(tree_action);
+        }
+
+        if find.open {
+            if scrolled_to_current || find.matches.is_empty() {
+                find.jump = false;
+            }
+        } else if scrolled_to_current && !target.scrolled {
+            target.scrolled = true;
+            target.shown_at = Some(now);
+        }
+
+        ui.data_mut(|d| {
+            d.insert_temp(offset_id, output.state.offset.y);
+            d.insert_temp(self.id_source.with("content_height"), output.content_size.y);
+            d.insert_temp(find_id, find);
+            d.insert_temp(target_id, target);
+        });
 
         output.inner
     }
 }
+
+/// Notes whose text contains `query`, ignoring case, in the order the
+/// thread shows them. Replies in collapsed branches aren't searched.
+fn find_matches(
+    ndb: &nostrdb::Ndb,
+    txn: &Transaction,
+    notes: &[notedeck::NoteRef],
+    query: &str,
+) -> Vec<NoteKey> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    notes
+        .iter()
+        .filter(|note_ref| {
+            ndb.get_note_by_key(txn, note_ref.key)
+                .is_ok_and(|note| note.content().to_lowercase().contains(&query))
+        })
+        .map(|note_ref| note_ref.key)
+        .collect()
+}
+
+fn find_bar_ui(ui: &mut egui::Ui, find_id: egui::Id, find: &mut FindState) {
+    ui.horizontal(|ui| {
+        let resp = ui.add(
+            egui::TextEdit::singleline(&mut find.query)
+                .id(find_id.with("query"))
+                .hint_text("Find in thread")
+                .desired_width(180.0),
+        );
+
+        let (enter, shift, escape) = ui.input(|i| {
+            (
+                i.key_pressed(Key::Enter),
+                i.modifiers.shift,
+                i.key_pressed(Key::Escape),
+            )
+        });
+
+        if resp.lost_focus() && enter {
+            find.step(!shift);
+            resp.request_focus();
+        }
+
+        if !find.matches.is_empty() {
+            ui.weak(format!("{} of {}", find.current + 1, find.matches.len()));
+        } else if !find.query.trim().is_empty() {
+            ui.weak("No matches");
+        }
+
+        let has_matches = !find.matches.is_empty();
+        if ui
+            .add_enabled(has_matches, egui::Button::new("⏶").frame(false))
+            .on_hover_text("Previous match (Shift+Enter)")
+            .clicked()
+        {
+            find.step(false);
+        }
+        if ui
+            .add_enabled(has_matches, egui::Button::new("⏷").frame(false))
+            .on_hover_text("Next match (Enter)")
+            .clicked()
+        {
+            find.step(true);
+        }
+
+        // the text edit gives up focus on escape
+        if ui.button("✕").on_hover_text("Close (Esc)").clicked() || (resp.lost_focus() && escape)
+        {
+            *find = FindState::default();
+        }
+    });
+    ui.add_space(4.0);
+}
+
+/// Scale every text style of `ui` by `scale`
+fn scale_text(ui: &mut egui::Ui, scale: f32) {
+    if (scale - 1.0).abs() < f32::EPSILON {
+        return;
+    }
+    for font in ui.style_mut().text_styles.values_mut() {
+        font.size *= scale;
+    }
+}
+
+/// Whether replies to the thread come in as notifications. Returns the new
+/// setting when it's toggled.
+fn follow_thread_ui(ui: &mut egui::Ui, following: bool) -> Option<bool> {
+    let mut checked = following;
+    ui.checkbox(&mut checked, "Notify me of new replies")
+        .on_hover_text("Replying in a thread turns this on");
+    (checked != following).then_some(checked)
+}
+
+/// Buttons to make text smaller or bigger, with the current size in
+/// between that resets it, at the right of the view. Returns the new
+/// scale when it's changed.
+fn text_size_ui(ui: &mut egui::Ui, scale: f32) -> Option<f32> {
+    let mut changed = None;
+
+    ui.horizontal(|ui| {
+        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+            ui.spacing_mut().item_spacing.x = 2.0;
+
+            // laid out from the right, so bigger comes first
+            let bigger = ui
+                .add_enabled(
+                    scale < MAX_TEXT_SCALE - f32::EPSILON,
+                    egui::Button::new("A+").frame(false),
+                )
+                .on_hover_text("Bigger text");
+            if bigger.clicked() {
+                changed = Some(scale + TEXT_SCALE_STEP);
+            }
+
+            let reset = ui
+                .add(
+                    egui::Button::new(format!("{:.0}%", scale * 100.0))
+                        .frame(false)
+                        .small(),
+                )
+                .on_hover_text("Reset text size");
+            if reset.clicked() {
+                changed = Some(1.0);
+            }
+
+            let smaller = ui
+                .add_enabled(
+                    scale > MIN_TEXT_SCALE + f32::EPSILON,
+                    egui::Button::new("A−").frame(false),
+                )
+                .on_hover_text("Smaller text");
+            if smaller.clicked() {
+                changed = Some(scale - TEXT_SCALE_STEP);
+            }
+        });
+    });
+
+    changed
+}
@@ -1,25 +1,44 @@
 use egui::containers::scroll_area::ScrollBarVisibility;
-use egui::{vec2, Direction, Layout, Pos2, Stroke};
+use egui::{vec2, Direction, Layout, Pos2, Stroke, UiBuilder};
 use egui_tabs::TabColor;
 use enostr::KeypairUnowned;
-use nostrdb::Transaction;
+use nostrdb::{Note, NoteKey, Transaction};
 use notedeck_ui::jobs::JobsCache;
 use std::f32::consts::PI;
 use tracing::{error, warn};
 
-use crate::timeline::{TimelineCache, TimelineKind, TimelineTab, ViewFilter};
-use notedeck::{note::root_note_id_from_selected_id, MuteFun, NoteAction, NoteContext};
+use crate::timeline::{
+    gap::{self, Gap},
+    paging::LOAD_DISTANCE,
+    thread_tree::{Branch, Row, ThreadTree, TreeAction},
+    ScrollAnchor, Timeline, TimelineCache, TimelineKind, TimelineTab,
+};
+use notedeck::{
+    note::root_note_id_from_selected_id, MuteFun, NoteAction, NoteContext,
+    NotificationFilterSettings, WebOfTrust, WotVerdict,
+};
 use notedeck_ui::{
     anim::{AnimationHelper, ICON_EXPANSION_MULTIPLE},
     show_pointer, NoteOptions, NoteView,
 };
 
+/// How far past the edges of the view notes are laid out
+const OVERSCAN: f32 = 50.0;
+
+/// How far each level of replies is indented in a thread, up to a point
+const TREE_INDENT: f32 = 14.0;
+const MAX_TREE_INDENT: usize = 8;
+
 pub struct TimelineView<'a, 'd> {
     timeline_id: &'a TimelineKind,
     timeline_cache: &'a mut TimelineCache,
     note_options: NoteOptions,
     reverse: bool,
     is_muted: &'a MuteFun,
+    wot: Option<&'a WebOfTrust>,
+    notification_filters: Option<(&'a NotificationFilterSettings, &'a WebOfTrust)>,
+    min_pow: u32,
+    id_salt: egui::Id,
     note_context: &'a mut NoteContext<'d>,
     cur_acc: &'a Option<KeypairUnowned<'a>>,
     jobs: &'a mut JobsCache,
@@ -43,6 +62,10 @@ impl<'a, 'd> TimelineView<'a, 'd> {
             note_options,
             reverse,
             is_muted,
+            wot: None,
+            notification_filters: None,
+            min_pow: 0,
+            id_salt: egui::Id::NULL,
             note_context,
             cur_acc,
             jobs,
@@ -57,6 +80,10 @@ impl<'a, 'd> TimelineView<'a, 'd> {
             self.reverse,
             self.note_options,
             self.is_muted,
+            self.wot,
+            self.notification_filters,
+            self.min_pow,
+            self.id_salt,
             self.note_context,
             self.cur_acc,
             self.jobs,
@@ -67,6 +94,34 @@ impl<'a, 'd> TimelineView<'a, 'd> {
         self.reverse = true;
         self
     }
+
+    /// Collapse or hide notes from outside the web of trust
+    pub fn wot(mut self, wot: Option<&'a WebOfTrust>) -> Self {
+        self.wot = wot;
+        self
+    }
+
+    /// Hide what the notification filters leave out
+    pub fn notification_filters(
+        mut self,
+        notification_filters: Option<(&'a NotificationFilterSettings, &'a WebOfTrust)>,
+    ) -> Self {
+        self.notification_filters = notification_filters;
+        self
+    }
+
+    /// Hide notes with less proof of work than this
+    pub fn min_pow(mut self, min_pow: u32) -> Self {
+        self.min_pow = min_pow;
+        self
+    }
+
+    /// Keep the scroll position apart from other views of the same
+    /// timeline, like the column it's in on another deck
+    pub fn id_salt(mut self, id_salt: egui::Id) -> Self {
+        self.id_salt = id_salt;
+        self
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -77,6 +132,10 @@ fn timeline_ui(
     reversed: bool,
     note_options: NoteOptions,
     is_muted: &MuteFun,
+    wot: Option<&WebOfTrust>,
+    notification_filters: Option<(&NotificationFilterSettings, &WebOfTrust)>,
+    min_pow: u32,
+    id_salt: egui::Id,
     note_context: &mut NoteContext,
     cur_acc: &Option<KeypairUnowned>,
     jobs: &mut JobsCache,
@@ -88,7 +147,7 @@ fn timeline_ui(
 
     */
 
-    let scroll_id = {
+    let (scroll_id, unread) = {
         let timeline = if let Some(timeline) = timeline_cache.timelines.get_mut(timeline_id) {
             timeline
         } else {
@@ -103,7 +162,20 @@ fn timeline_ui(
         // need this for some reason??
         ui.add_space(3.0);
 
-        egui::Id::new(("tlscroll", timeline.view_id()))
+        let scroll_id = id_salt.with(("tlscroll", timeline.view_id()));
+
+        // scrolled away from the newest notes, new ones wait behind the
+        // pill instead of counting as seen
+        let offset = ui.data(|d| d.get_temp::<f32>(scroll_id.with("timeline_scroll_offset")));
+        let unread = match offset {
+            Some(y) if y > 0.0 && !reversed => timeline.unread(),
+            _ => {
+                timeline.mark_read();
+                0
+            }
+        };
+
+        (scroll_id, unread)
     };
 
     let show_top_button_id = ui.id().with((scroll_id, "at_top"));
@@ -124,6 +196,18 @@ fn timeline_ui(
         None
     };
 
+    let new_notes_resp = if unread > 0 {
+        let pill_pos = ui.available_rect_before_wrap().center_top() + vec2(0.0, 12.0);
+        egui::Area::new(ui.id().with("new_notes_area"))
+            .order(egui::Order::Middle)
+            .pivot(egui::Align2::CENTER_TOP)
+            .fixed_pos(pill_pos)
+            .show(ui.ctx(), |ui| Some(ui.add(new_notes_pill(unread))))
+            .inner
+    } else {
+        None
+    };
+
     let mut scroll_area = egui::ScrollArea::vertical()
         .id_salt(scroll_id)
         .animated(false)
@@ -136,10 +220,10 @@ fn timeline_ui(
         scroll_area = scroll_area.vertical_scroll_offset(offset);
     }
 
-    if let Some(goto_top_resp) = goto_top_resp {
-        if goto_top_resp.clicked() {
+    for resp in [goto_top_resp, new_notes_resp].into_iter().flatten() {
+        if resp.clicked() {
             scroll_area = scroll_area.vertical_scroll_offset(0.0);
-        } else if goto_top_resp.hovered() {
+        } else if resp.hovered() {
             show_pointer(ui);
         }
     }
@@ -156,7 +240,7 @@ fn timeline_ui(
 
         let txn = Transaction::new(note_context.ndb).expect("failed to create txn");
 
-        TimelineTabView::new(
+        let mut view = TimelineTabView::new(
             timeline.current_view(),
             reversed,
             note_options,
@@ -166,11 +250,35 @@ fn timeline_ui(
             cur_acc,
             jobs,
         )
-        .show(ui)
+        .wot(wot)
+        .notification_filters(notification_filters)
+        .min_pow(min_pow)
+        .gap(timeline.gaps.gap());
+        let action = view.show(ui);
+        let fill_gap = view.fill_gap;
+
+        older_notes_spinner(ui, timeline);
+
+        Some((action, fill_gap))
     });
 
+    let (action, fill_gap) = scroll_output.inner.unwrap_or((None, false));
+    if fill_gap {
+        if let Some(timeline) = timeline_cache.timelines.get_mut(timeline_id) {
+            if timeline.gaps.want_fill() {
+                ui.ctx().request_repaint();
+            }
+        }
+    }
+
     ui.data_mut(|d| d.insert_temp(offset_id, scroll_output.state.offset.y));
 
+    if !reversed {
+        if let Some(timeline) = timeline_cache.timelines.get_mut(timeline_id) {
+            load_older_near_bottom(ui, &scroll_output, timeline);
+        }
+    }
+
     let at_top_after_scroll = scroll_output.state.offset.y == 0.0;
     let cur_show_top_button = ui.ctx().data(|d| d.get_temp::<bool>(show_top_button_id));
 
@@ -184,7 +292,53 @@ fn timeline_ui(
             .data_mut(|d| d.insert_temp(show_top_button_id, true));
     }
 
-    scroll_output.inner
+    action
+}
+
+/// Ask for the timeline's older notes once it's scrolled near the bottom
+pub fn load_older_near_bottom<R>(
+    ui: &egui::Ui,
+    scroll_output: &egui::scroll_area::ScrollAreaOutput<R>,
+    timeline: &mut Timeline,
+) {
+    let view_bottom = scroll_output.state.offset.y + scroll_output.inner_rect.height();
+    if scroll_output.content_size.y - view_bottom > LOAD_DISTANCE {
+        return;
+    }
+
+    if timeline.want_older() {
+        ui.ctx().request_repaint();
+    }
+}
+
+/// Shown under the notes while older ones are on their way
+pub fn older_notes_spinner(ui: &mut egui::Ui, timeline: &Timeline) {
+    if !timeline.paging.is_loading() {
+        return;
+    }
+
+    ui.vertical_centered(|ui| {
+        ui.add_space(8.0);
+        ui.add(egui::Spinner::new());
+        ui.add_space(8.0);
+    });
+}
+
+/// "N new notes", floating over the top of a timeline scrolled away from them
+fn new_notes_pill(unread: usize) -> impl egui::Widget {
+    move |ui: &mut egui::Ui| -> egui::Response {
+        let text = if unread == 1 {
+            "↑ 1 new note".to_owned()
+        } else {
+            format!("↑ {unread} new notes")
+        };
+        ui.add(
+            egui::Button::new(egui::RichText::new(text).color(egui::Color32::WHITE))
+                .fill(notedeck_ui::colors::PINK)
+                .corner_radius(16.0)
+                .min_size(vec2(0.0, 28.0)),
+        )
+    }
 }
 
 fn goto_top_button(center: Pos2) -> impl egui::Widget {
@@ -243,9 +397,16 @@ fn goto_top_button(center: Pos2) -> impl egui::Widget {
 }
 
 pub fn tabs_ui(ui: &mut egui::Ui, selected: usize, views: &[TimelineTab]) -> usize {
+    let labels: Vec<&str> = views.iter().map(|view| view.filter.name()).collect();
+    labeled_tabs_ui(ui, selected, &labels)
+}
+
+/// Tabs along the top of a view, underlining the `selected` one. Returns
+/// the one selected now.
+pub fn labeled_tabs_ui(ui: &mut egui::Ui, selected: usize, labels: &[&str]) -> usize {
     ui.spacing_mut().item_spacing.y = 0.0;
 
-    let tab_res = egui_tabs::Tabs::new(views.len() as i32)
+    let tab_res = egui_tabs::Tabs::new(labels.len() as i32)
         .selected(selected as i32)
         .hover_bg(TabColor::none())
         .selected_fg(TabColor::none())
@@ -259,10 +420,7 @@ pub fn tabs_ui(ui: &mut egui::Ui, selected: usize, views: &[TimelineTab]) -> usi
 
             let ind = state.index();
 
-            let txt = match views[ind as usize].filter {
-                ViewFilter::Notes => "Notes",
-                ViewFilter::NotesAndReplies => "Notes & Replies",
-            };
+            let txt = labels[ind as usize];
 
             let res = ui.add(egui::Label::new(txt).selectable(false));
 
@@ -330,15 +488,50 @@ fn shrink_range_to_width(range: egui::Rangef, width: f32) -> egui::Rangef {
     egui::Rangef::new(min, max)
 }
 
+/// Notes to pick out in a timeline, like the matches of a find in thread
+pub struct NoteHighlights<'a> {
+    pub notes: &'a [NoteKey],
+
+    /// The one that's selected, scrolled into view when `scroll_to_current`
+    /// is set
+    pub current: Option<NoteKey>,
+    pub scroll_to_current: bool,
+
+    /// From 0 to 1, for fading a highlight out
+    pub strength: f32,
+}
+
 pub struct TimelineTabView<'a, 'd> {
     tab: &'a TimelineTab,
     reversed: bool,
     note_options: NoteOptions,
     txn: &'a Transaction,
     is_muted: &'a MuteFun,
+    wot: Option<&'a WebOfTrust>,
+    notification_filters: Option<(&'a NotificationFilterSettings, &'a WebOfTrust)>,
+    min_pow: u32,
+    /// Shown even if it's from outside the web of trust
+    trusted_note: Option<&'a [u8; 32]>,
+    highlights: Option<NoteHighlights<'a>>,
+
+    /// Notes missing from the timeline, shown under the note they're
+    /// missing from below
+    gap: Option<&'a Gap>,
+
+    /// Lays the notes out as replies under each other
+    tree: Option<&'a ThreadTree>,
     note_context: &'a mut NoteContext<'d>,
     cur_acc: &'a Option<KeypairUnowned<'a>>,
     jobs: &'a mut JobsCache,
+
+    /// Set once the current highlight has been laid out and scrolled to
+    pub scrolled_to_current: bool,
+
+    /// Set when the user asked for the gap to be filled
+    pub fill_gap: bool,
+
+    /// Set when the user collapsed, expanded or opened a branch
+    pub tree_action: Option<TreeAction>,
 }
 
 impl<'a, 'd> TimelineTabView<'a, 'd> {
@@ -359,81 +552,536 @@ impl<'a, 'd> TimelineTabView<'a, 'd> {
             note_options,
             txn,
             is_muted,
+            wot: None,
+            notification_filters: None,
+            min_pow: 0,
+            trusted_note: None,
+            highlights: None,
+            gap: None,
+            tree: None,
             note_context,
             cur_acc,
             jobs,
+            scrolled_to_current: false,
+            fill_gap: false,
+            tree_action: None,
         }
     }
 
+    pub fn highlights(mut self, highlights: Option<NoteHighlights<'a>>) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
+    pub fn wot(mut self, wot: Option<&'a WebOfTrust>) -> Self {
+        self.wot = wot;
+        self
+    }
+
+    pub fn notification_filters(
+        mut self,
+        notification_filters: Option<(&'a NotificationFilterSettings, &'a WebOfTrust)>,
+    ) -> Self {
+        self.notification_filters = notification_filters;
+        self
+    }
+
+    pub fn min_pow(mut self, min_pow: u32) -> Self {
+        self.min_pow = min_pow;
+        self
+    }
+
+    pub fn trusted_note(mut self, note_id: &'a [u8; 32]) -> Self {
+        self.trusted_note = Some(note_id);
+        self
+    }
+
+    pub fn gap(mut self, gap: Option<&'a Gap>) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    pub fn tree(mut self, tree: &'a ThreadTree) -> Self {
+        self.tree = Some(tree);
+        self
+    }
+
     pub fn show(&mut self, ui: &mut egui::Ui) -> Option<NoteAction> {
+        let tab = self.tab;
+        let reversed = self.reversed;
         let mut action: Option<NoteAction> = None;
-        let len = self.tab.notes.len();
 
-        let is_muted = self.is_muted;
+        ui.spacing_mut().item_spacing.y = 0.0;
+        ui.spacing_mut().item_spacing.x = 4.0;
 
-        self.tab
-            .list
-            .borrow_mut()
-            .ui_custom_layout(ui, len, |ui, start_index| {
-                ui.spacing_mut().item_spacing.y = 0.0;
-                ui.spacing_mut().item_spacing.x = 4.0;
+        let mut heights = tab.heights.borrow_mut();
+        heights.fit(ui.available_width(), &tab.notes);
 
-                let ind = if self.reversed {
-                    len - start_index - 1
-                } else {
-                    start_index
+        // positions in the list are from here down
+        let origin = ui.cursor().top();
+        let clip = ui.clip_rect();
+
+        // notes that arrived above the view pushed it down. lay out as if
+        // that's already been scrolled back, so nothing on screen moves
+        let shift = if clip.top() > origin {
+            tab.pending_shift.take()
+        } else {
+            tab.pending_shift.set(0.0);
+            0.0
+        };
+        if shift != 0.0 {
+            ui.scroll_with_delta(vec2(0.0, -shift));
+        }
+        let view_top = clip.top() - origin + shift;
+        let view_bottom = clip.bottom() - origin + shift;
+
+        let gap = self.gap.filter(|_| !reversed);
+        let gap_under = gap
+            .and_then(|gap| gap::note_above(&tab.notes, gap.since))
+            .map(|note| note.key);
+
+        let restore = tab.restore_scroll.get();
+        let mut first_visible = None;
+        let mut laid_out: Option<(usize, usize)> = None;
+        // how much notes above the view grew once measured
+        let mut grew_above = 0.0;
+
+        if let Some(mut ind) = heights.index_at((view_top - OVERSCAN).max(0.0), reversed) {
+            let mut y = heights.top_of(ind, reversed);
+            ui.add_space((y - shift).max(0.0));
+
+            loop {
+                let note_key = tab.notes[ind].key;
+                let before = ui.cursor().top();
+                let note_action = match self.tree.and_then(|tree| tree.row(note_key)) {
+                    Some(row) => self.tree_row_ui(ui, note_key, row, restore, &mut first_visible),
+                    None => self.note_ui(ui, note_key, restore, &mut first_visible),
                 };
+                if let Some(note_action) = note_action {
+                    action = Some(note_action);
+                }
+                if let Some(gap) = gap.filter(|_| gap_under == Some(note_key)) {
+                    self.fill_gap |= gap_ui(ui, gap);
+                }
+                let height = ui.cursor().top() - before;
+                let grew = heights.measure(note_key, height);
+                if y + height <= view_top {
+                    grew_above += grew;
+                }
+                y += height;
+                laid_out = Some(laid_out.map_or((ind, ind), |(lo, hi)| (lo.min(ind), hi.max(ind))));
 
-                let note_key = self.tab.notes[ind].key;
-
-                let note =
-                    if let Ok(note) = self.note_context.ndb.get_note_by_key(self.txn, note_key) {
-                        note
-                    } else {
-                        warn!("failed to query note {:?}", note_key);
-                        return 0;
-                    };
-
-                // should we mute the thread? we might not have it!
-                let muted = if let Ok(root_id) = root_note_id_from_selected_id(
-                    self.note_context.ndb,
-                    self.note_context.note_cache,
-                    self.txn,
-                    note.id(),
-                ) {
-                    is_muted(&note, root_id.bytes())
+                let next = if reversed {
+                    ind.checked_sub(1)
                 } else {
-                    false
+                    Some(ind + 1).filter(|next| *next < tab.notes.len())
                 };
+                match next {
+                    Some(next) if y < view_bottom + OVERSCAN => ind = next,
+                    _ => {
+                        let below = heights.total() - heights.bottom_of(ind, reversed);
+                        ui.add_space(below.max(0.0));
+                        break;
+                    }
+                }
+            }
+        }
+
+        if grew_above != 0.0 {
+            ui.scroll_with_delta(vec2(0.0, -grew_above));
+            ui.ctx().request_repaint();
+        }
 
-                if !muted {
-                    let zapping_acc = self
-                        .cur_acc
-                        .as_ref()
-                        .filter(|_| self.note_context.current_account_has_wallet)
-                        .or(self.cur_acc.as_ref());
-
-                    notedeck_ui::padding(8.0, ui, |ui| {
-                        let resp = NoteView::new(
-                            self.note_context,
-                            zapping_acc,
-                            &note,
-                            self.note_options,
-                            self.jobs,
-                        )
-                        .show(ui);
-
-                        if let Some(note_action) = resp.action {
-                            action = Some(note_action)
-                        }
-                    });
-
-                    notedeck_ui::hline(ui);
+        tab.first_visible.set(first_visible);
+        let is_laid_out = |ind: usize| laid_out.is_some_and(|(lo, hi)| lo <= ind && ind <= hi);
+
+        // notes that weren't laid out can be jumped to straight away, and
+        // are lined up exactly once they are
+        if tab.scroll_to_focus.get() {
+            match tab.focused.and_then(|key| heights.index_of(key)) {
+                Some(ind) if !is_laid_out(ind) => {
+                    let top = heights.top_of(ind, reversed);
+                    ui.scroll_with_delta(vec2(0.0, view_top - top));
+                    ui.ctx().request_repaint();
                 }
+                _ => tab.scroll_to_focus.set(false),
+            }
+        }
+
+        if let Some(anchor) = restore {
+            let user_scrolled =
+                ui.rect_contains_pointer(clip) && ui.input(|i| i.raw_scroll_delta.y != 0.0);
+            match heights.index_of(anchor.note) {
+                _ if user_scrolled => tab.restore_scroll.set(None),
+                // restored while it was laid out
+                _ if tab.restore_scroll.get().is_none() => {}
+                Some(ind) if !is_laid_out(ind) => {
+                    let top = heights.top_of(ind, reversed) + anchor.offset;
+                    ui.scroll_with_delta(vec2(0.0, view_top - top));
+                    ui.ctx().request_repaint();
+                }
+                // laid out but not shown, like a muted note
+                Some(_) => tab.restore_scroll.set(None),
+                // not loaded yet
+                None => {}
+            }
+        }
+
+        action
+    }
+
+    /// A note in a thread, indented under the one it replies to, with a
+    /// line down the side for each level
+    fn tree_row_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        note_key: NoteKey,
+        row: Row,
+        restore: Option<ScrollAnchor>,
+        first_visible: &mut Option<ScrollAnchor>,
+    ) -> Option<NoteAction> {
+        let levels = row.depth.min(MAX_TREE_INDENT);
+        let mut rect = ui.available_rect_before_wrap();
+        let left = rect.left();
+        rect.min.x += levels as f32 * TREE_INDENT;
+
+        let resp = ui.allocate_new_ui(UiBuilder::new().max_rect(rect), |ui| {
+            let action = self.note_ui(ui, note_key, restore, first_visible);
+            if let Some(tree_action) = branch_ui(ui, note_key, row.branch) {
+                self.tree_action = Some(tree_action);
+            }
+            action
+        });
+
+        let stroke = ui.visuals().widgets.noninteractive.bg_stroke;
+        let y = resp.response.rect.y_range();
+        for level in 0..levels {
+            let x = left + (level as f32 + 0.5) * TREE_INDENT;
+            ui.painter().vline(x, y, stroke);
+        }
+
+        resp.inner
+    }
+
+    fn note_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        note_key: NoteKey,
+        restore: Option<ScrollAnchor>,
+        first_visible: &mut Option<ScrollAnchor>,
+    ) -> Option<NoteAction> {
+        let mut action = None;
+        let is_muted = self.is_muted;
+
+        let Ok(note) = self.note_context.ndb.get_note_by_key(self.txn, note_key) else {
+            warn!("failed to query note {:?}", note_key);
+            return None;
+        };
+
+        // should we mute the thread? we might not have it!
+        let muted = if let Ok(root_id) = root_note_id_from_selected_id(
+            self.note_context.ndb,
+            self.note_context.note_cache,
+            self.txn,
+            note.id(),
+        ) {
+            is_muted(&note, root_id.bytes())
+        } else {
+            false
+        };
 
-                1
+        let muted = muted
+            || self
+                .notification_filters
+                .is_some_and(|(filters, wot)| !filters.shows(wot, &note))
+            || notedeck::note::is_expired(&note)
+            || (self.min_pow > 0 && notedeck::pow::committed_difficulty(&note) < self.min_pow);
+
+        let verdict = match self.wot {
+            Some(wot) if self.trusted_note != Some(note.id()) => wot.verdict(note.pubkey()),
+            _ => WotVerdict::Show,
+        };
+
+        let expanded_id = egui::Id::new(("wot_expanded", note.id()));
+        let collapsed = verdict == WotVerdict::Collapse
+            && !ui.data(|d| d.get_temp::<bool>(expanded_id).unwrap_or(false));
+
+        let focused = self.tab.focused == Some(note_key);
+        if focused && (muted || collapsed || verdict == WotVerdict::Hide) {
+            // nothing to scroll to
+            self.tab.scroll_to_focus.set(false);
+        }
+
+        if !muted && collapsed {
+            notedeck_ui::padding(8.0, ui, |ui| {
+                if collapsed_note_ui(ui) {
+                    ui.data_mut(|d| d.insert_temp(expanded_id, true));
+                }
+            });
+
+            notedeck_ui::hline(ui);
+        } else if !muted && verdict != WotVerdict::Hide {
+            let zapping_acc = self
+                .cur_acc
+                .as_ref()
+                .filter(|_| self.note_context.current_account_has_wallet)
+                .or(self.cur_acc.as_ref());
+
+            let h_strength = self.highlights.as_ref().map_or(0.0, |h| h.strength);
+            let highlight = self.highlights.as_ref().and_then(|h| {
+                if h.current == Some(note_key) {
+                    Some((true, h.scroll_to_current))
+                } else {
+                    h.notes.contains(&note_key).then_some((false, false))
+                }
             });
+            let bg = ui.painter().add(egui::Shape::Noop);
+            let together: Vec<[u8; 32]> = self
+                .tab
+                .groups
+                .get(note_key)
+                .map(|group| group.authors().copied().collect())
+                .unwrap_or_default();
+
+            let resp = notedeck_ui::padding(8.0, ui, |ui| {
+                let note_action = if is_reaction_or_zap(&note) {
+                    reaction_ui(ui, self.note_context, self.txn, &note, &together)
+                } else {
+                    NoteView::new(
+                        self.note_context,
+                        zapping_acc,
+                        &note,
+                        self.note_options,
+                        self.jobs,
+                    )
+                    .reposted_by(&together)
+                    .show(ui)
+                    .action
+                };
+
+                if let Some(note_action) = note_action {
+                    action = Some(note_action)
+                }
+            })
+            .response;
+
+            if let Some((current, scroll_to)) = highlight {
+                let fill = ui.visuals().selection.bg_fill;
+                let alpha = if current { 0.35 } else { 0.15 };
+                let fill = fill.gamma_multiply(alpha * h_strength);
+                ui.painter()
+                    .set(bg, egui::Shape::rect_filled(resp.rect, 0.0, fill));
+
+                if scroll_to {
+                    resp.scroll_to_me(Some(egui::Align::Center));
+                    self.scrolled_to_current = true;
+                }
+            }
+
+            if focused {
+                ui.painter().rect_stroke(
+                    resp.rect,
+                    0.0,
+                    ui.visuals().selection.stroke,
+                    egui::StrokeKind::Inside,
+                );
+                if self.tab.scroll_to_focus.take() {
+                    resp.scroll_to_me(None);
+                }
+            }
+            let offset = ui.clip_rect().top() - resp.rect.top();
+            if first_visible.is_none() && resp.rect.bottom() > ui.clip_rect().top() {
+                *first_visible = Some(ScrollAnchor {
+                    note: note_key,
+                    offset,
+                });
+            }
+            if let Some(anchor) = restore.filter(|anchor| anchor.note == note_key) {
+                ui.scroll_with_delta(vec2(0.0, offset - anchor.offset));
+                self.tab.restore_scroll.set(None);
+            }
+
+            notedeck_ui::hline(ui);
+        }
 
         action
     }
 }
+
+/// Reactions and zap receipts don't read as notes, the notifications
+/// timeline shows them as a line instead
+fn is_reaction_or_zap(note: &Note) -> bool {
+    matches!(note.kind(), 7 | 9735)
+}
+
+/// Who reacted or zapped, over a line of the note it was for. Reactions
+/// to the same note are one line, with everyone who reacted `together`.
+fn reaction_ui(
+    ui: &mut egui::Ui,
+    note_context: &NoteContext,
+    txn: &Transaction,
+    note: &Note,
+    together: &[[u8; 32]],
+) -> Option<NoteAction> {
+    let (who, what) = if note.kind() == 9735 {
+        let who = notedeck::notifications::zap_sender(note)?;
+        let what = match notedeck::notifications::zap_msats(note) {
+            Some(msats) => format!("⚡ zapped {} sats", msats / 1000),
+            None => "⚡ zapped".to_owned(),
+        };
+        (who, what)
+    } else if together.len() > 1 {
+        (together[0], "reacted to".to_owned())
+    } else {
+        let what = match note.content() {
+            "" | "+" => "❤ liked".to_owned(),
+            "-" => "👎 disliked".to_owned(),
+            emoji => format!("reacted {emoji} to"),
+        };
+        (*note.pubkey(), what)
+    };
+
+    // NIP-25 puts the note being reacted to in the last e tag
+    let target = note
+        .tags()
+        .into_iter()
+        .filter(|tag| tag.count() >= 2 && tag.get_unchecked(0).variant().str() == Some("e"))
+        .filter_map(|tag| tag.get_unchecked(1).variant().id().copied())
+        .last();
+
+    let profiles: Vec<_> = if together.len() > 1 {
+        together.iter().take(2).collect()
+    } else {
+        vec![&who]
+    }
+    .into_iter()
+    .map(|pubkey| note_context.ndb.get_profile_by_pubkey(txn, pubkey).ok())
+    .collect();
+    let names: Vec<&str> = profiles
+        .iter()
+        .map(|profile| notedeck::name::get_display_name(profile.as_ref()).name())
+        .collect();
+    let name = notedeck::name::summarize_names(&names, together.len().max(1));
+    let weak = ui.visuals().weak_text_color();
+    let mut action = None;
+
+    ui.horizontal_wrapped(|ui| {
+        if ui.link(egui::RichText::new(name).strong()).clicked() {
+            action = Some(NoteAction::Profile(enostr::Pubkey::new(who)));
+        }
+        ui.label(match target {
+            Some(_) => format!("{what} your note"),
+            None => format!("{what} you"),
+        });
+        ui.label(egui::RichText::new(notedeck::time_ago_since(note.created_at())).color(weak));
+    });
+
+    let target = target?;
+    let preview = note_context
+        .ndb
+        .get_note_by_id(txn, &target)
+        .map(|target| {
+            target
+                .content()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default();
+    if !preview.is_empty() {
+        let resp = ui.add(
+            egui::Label::new(egui::RichText::new(preview).color(weak))
+                .truncate()
+                .sense(egui::Sense::click()),
+        );
+        if resp.hovered() {
+            show_pointer(ui);
+        }
+        if resp.clicked() {
+            action = Some(NoteAction::Note(enostr::NoteId::new(target)));
+        }
+    }
+
+    action
+}
+
+/// Where notes are missing from a timeline. True when the user asks for
+/// them.
+fn gap_ui(ui: &mut egui::Ui, gap: &Gap) -> bool {
+    let mut clicked = false;
+    ui.vertical_centered(|ui| {
+        ui.add_space(8.0);
+        if gap.is_filling() {
+            ui.add(egui::Spinner::new());
+        } else {
+            let resp = ui.link("Load missing notes");
+            if resp.hovered() {
+                show_pointer(ui);
+            }
+            clicked = resp.clicked();
+        }
+        ui.add_space(8.0);
+    });
+    clicked
+}
+
+/// Under a note with replies in a thread: hide them, show them again, or
+/// carry on down a branch that was cut off for being deep
+fn branch_ui(ui: &mut egui::Ui, note_key: NoteKey, branch: Branch) -> Option<TreeAction> {
+    let (text, action) = match branch {
+        Branch::Leaf => return None,
+        Branch::Open => ("▾ Hide replies".to_owned(), TreeAction::Toggle(note_key)),
+        Branch::Collapsed(under) => (
+            format!("▸ Show {}", replies_text(under)),
+            TreeAction::Toggle(note_key),
+        ),
+        Branch::Deep(under) => (
+            format!("Continue thread ({})", replies_text(under)),
+            TreeAction::Open(note_key),
+        ),
+    };
+
+    let mut clicked = false;
+    notedeck_ui::padding(4.0, ui, |ui| {
+        let resp = ui.add(
+            egui::Button::new(
+                egui::RichText::new(text)
+                    .small()
+                    .color(ui.visuals().weak_text_color()),
+            )
+            .frame(false),
+        );
+        if resp.hovered() {
+            show_pointer(ui);
+        }
+        clicked = resp.clicked();
+    });
+    clicked.then_some(action)
+}
+
+fn replies_text(count: usize) -> String {
+    if count == 1 {
+        "1 reply".to_owned()
+    } else {
+        format!("{count} replies")
+    }
+}
+
+/// Stands in for a note from outside the web of trust. True when the
+/// user asks to see it.
+fn collapsed_note_ui(ui: &mut egui::Ui) -> bool {
+    ui.horizontal(|ui| {
+        ui.label(
+            egui::RichText::new("Note from outside your network")
+                .color(ui.visuals().weak_text_color()),
+        );
+        ui.add_space(8.0);
+
+        let resp = ui.link("Show");
+        if resp.hovered() {
+            show_pointer(ui);
+        }
+        resp.clicked()
+    })
+    .inner
+}
@@ -0,0 +1,192 @@
+use egui::{Frame, Margin, RichText, TextEdit, Ui};
+use enostr::{ClientMessage, FullKeypair, NoteId, RelayPool};
+use nostrdb::{IngestMetadata, Ndb, NoteBuildOptions, NoteBuilder};
+use notedeck::tip_jar::{is_lightning_address, parse_amounts, TipJar};
+use notedeck::{Accounts, NotedeckTextStyle};
+use notedeck_ui::{padding, qr_code};
+use tracing::{error, info};
+
+use super::widgets::styled_button;
+
+const DEFAULT_MESSAGE: &str = "If you like what I post, you can send me a tip. Thank you! 🧡";
+const DEFAULT_AMOUNTS: &str = "1000, 5000, 21000";
+
+/// The tip jar being filled in, before it's published
+pub struct TipJarState {
+    pub lud16: String,
+    pub amounts: String,
+    pub message: String,
+}
+
+impl TipJarState {
+    /// Start from the lightning address on the profile, if there is one
+    pub fn new(lud16: String) -> Self {
+        Self {
+            lud16,
+            amounts: DEFAULT_AMOUNTS.to_owned(),
+            message: DEFAULT_MESSAGE.to_owned(),
+        }
+    }
+
+    fn tip_jar(&self) -> Result<TipJar, &'static str> {
+        let lud16 = self.lud16.trim();
+        if !is_lightning_address(lud16) {
+            return Err("A lightning address looks like you@wallet.com");
+        }
+        let amounts_sats = parse_amounts(&self.amounts)
+            .ok_or("Suggested amounts are whole numbers of sats, like 1000, 5000")?;
+
+        Ok(TipJar {
+            lud16: lud16.to_owned(),
+            amounts_sats,
+        })
+    }
+}
+
+pub enum TipJarAction {
+    Publish { tip_jar: TipJar, message: String },
+}
+
+impl TipJarAction {
+    /// Post the tip jar note as `kp` and pin it to their profile
+    pub fn process(
+        self,
+        ndb: &Ndb,
+        pool: &mut RelayPool,
+        accounts: &mut Accounts,
+        kp: FullKeypair,
+    ) -> Result<(), String> {
+        let TipJarAction::Publish { tip_jar, message } = self;
+
+        let mut builder = NoteBuilder::new()
+            .kind(1)
+            .content(&tip_jar.content(&message))
+            .start_tag();
+        for value in tip_jar.tag() {
+            builder = builder.tag_str(&value);
+        }
+        let note = builder
+            .options(
+                NoteBuildOptions::default()
+                    .created_at(true)
+                    .sign(&kp.secret_key.to_secret_bytes()),
+            )
+            .build()
+            .ok_or_else(|| "Couldn't build the tip jar note".to_owned())?;
+        let json = note.json().map_err(|e| e.to_string())?;
+
+        if let Err(err) = ndb.process_event_with(
+            &format!("[\"EVENT\",{json}]"),
+            IngestMetadata::new().client(true),
+        ) {
+            error!("couldn't ingest our tip jar: {err}");
+        }
+        info!("publishing tip jar for {}", kp.pubkey.hex());
+        pool.send(&ClientMessage::event_json(json).map_err(|e| e.to_string())?);
+
+        accounts
+            .pin_note(ndb, pool, &kp.pubkey, &NoteId::new(*note.id()))
+            .map_err(|e| format!("Posted, but couldn't pin it: {e}"))
+    }
+}
+
+/// Builds a "support me" note from a template: the lightning address,
+/// some suggested amounts and a few words
+pub struct TipJarView<'a> {
+    state: &'a mut TipJarState,
+}
+
+impl<'a> TipJarView<'a> {
+    pub fn new(state: &'a mut TipJarState) -> Self {
+        TipJarView { state }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<TipJarAction> {
+        let mut action = None;
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                padding(16.0, ui, |ui| {
+                    ui.weak(
+                        "Post a note asking for tips and pin it to the top of your profile. \
+                         People can scan its QR or zap one of the amounts you suggest.",
+                    );
+                    ui.add_space(12.0);
+
+                    field_label(ui, "Lightning address");
+                    ui.add(
+                        TextEdit::singleline(&mut self.state.lud16)
+                            .hint_text("you@wallet.com")
+                            .desired_width(f32::INFINITY),
+                    );
+                    ui.add_space(8.0);
+
+                    field_label(ui, "Suggested amounts, in sats");
+                    ui.add(
+                        TextEdit::singleline(&mut self.state.amounts)
+                            .hint_text(DEFAULT_AMOUNTS)
+                            .desired_width(f32::INFINITY),
+                    );
+                    ui.add_space(8.0);
+
+                    field_label(ui, "Message");
+                    ui.add(
+                        TextEdit::multiline(&mut self.state.message)
+                            .desired_rows(3)
+                            .desired_width(f32::INFINITY),
+                    );
+                    ui.add_space(16.0);
+
+                    match self.state.tip_jar() {
+                        Ok(tip_jar) => {
+                            field_label(ui, "Preview");
+                            preview_ui(ui, &tip_jar, &self.state.message);
+                            ui.add_space(12.0);
+
+                            if ui
+                                .add(styled_button("Publish and pin", notedeck_ui::colors::PINK))
+                                .clicked()
+                            {
+                                action = Some(TipJarAction::Publish {
+                                    tip_jar,
+                                    message: self.state.message.clone(),
+                                });
+                            }
+                        }
+                        Err(problem) => {
+                            ui.colored_label(ui.visuals().warn_fg_color, problem);
+                        }
+                    }
+                });
+            });
+
+        action
+    }
+}
+
+fn field_label(ui: &mut Ui, label: &str) {
+    ui.label(
+        RichText::new(label)
+            .text_style(NotedeckTextStyle::Body.text_style())
+            .strong(),
+    );
+}
+
+/// Roughly how the note will look
+fn preview_ui(ui: &mut Ui, tip_jar: &TipJar, message: &str) {
+    Frame::group(ui.style())
+        .inner_margin(Margin::same(12))
+        .show(ui, |ui| {
+            ui.label(tip_jar.content(message));
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                qr_code(ui, &tip_jar.uri(), 96.0);
+                ui.horizontal_wrapped(|ui| {
+                    for sats in &tip_jar.amounts_sats {
+                        ui.add_enabled(false, egui::Button::new(format!("⚡ {sats}")));
+                    }
+                });
+            });
+        });
+}
@@ -0,0 +1,68 @@
+use egui::{RichText, Ui};
+use notedeck::NotedeckTextStyle;
+use notedeck_ui::{colors::PINK, padding};
+
+use crate::whats_new::{Change, Feature, Release, WhatsNew, CHANGELOG, CURRENT_VERSION};
+
+/// The changelog, newest release first, with the releases since the last
+/// version the user ran marked as new
+pub struct WhatsNewView<'a> {
+    whats_new: &'a WhatsNew,
+}
+
+impl<'a> WhatsNewView<'a> {
+    pub fn new(whats_new: &'a WhatsNew) -> Self {
+        Self { whats_new }
+    }
+
+    /// Returns the feature to open if one of the links was clicked
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<Feature> {
+        let mut open = None;
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                padding(16.0, ui, |ui| {
+                    ui.weak(format!("You're on Notedeck {CURRENT_VERSION}"));
+                    for release in CHANGELOG {
+                        ui.add_space(16.0);
+                        release_heading(ui, release, self.whats_new.is_new(release));
+                        for change in release.changes {
+                            if let Some(feature) = change_ui(ui, change) {
+                                open = Some(feature);
+                            }
+                        }
+                    }
+                });
+            });
+
+        open
+    }
+}
+
+fn release_heading(ui: &mut Ui, release: &Release, new: bool) {
+    ui.horizontal(|ui| {
+        ui.label(
+            RichText::new(release.version).text_style(NotedeckTextStyle::Heading3.text_style()),
+        );
+        if new {
+            ui.label(RichText::new("New").color(PINK).strong());
+        }
+    });
+    ui.add_space(4.0);
+}
+
+fn change_ui(ui: &mut Ui, change: &Change) -> Option<Feature> {
+    let mut open = None;
+
+    ui.add_space(8.0);
+    ui.label(RichText::new(change.title).strong());
+    ui.label(change.description);
+    if let Some(feature) = change.open {
+        if ui.link(feature.label()).clicked() {
+            open = Some(feature);
+        }
+    }
+
+    open
+}
@@ -0,0 +1,168 @@
+//! Undo for local changes that are easy to make by accident. Each one
+//! is recorded as a command that knows how to put things back, and is
+//! offered to the user as an "Undo" toast.
+
+use std::collections::VecDeque;
+
+use enostr::Pubkey;
+use nostrdb::Transaction;
+use notedeck::{AppContext, MuteAction, MuteItem};
+use tracing::{error, info};
+
+use crate::{
+    app::get_active_columns_mut, column::Column, draft::Draft, route::Route,
+    timeline::TimelineKind, ui::note::PostType, Damus,
+};
+
+/// Older commands than this can't be undone anymore
+const MAX_UNDO: usize = 20;
+
+pub enum UndoCommand {
+    CloseColumn { index: usize, column: Column },
+    RemoveRelay(String),
+    Mute { account: Pubkey, item: MuteItem },
+    DiscardDraft { post_type: PostType, draft: Draft },
+}
+
+impl UndoCommand {
+    fn description(&self) -> String {
+        match self {
+            UndoCommand::CloseColumn { .. } => "Closed column".to_owned(),
+            UndoCommand::RemoveRelay(url) => format!("Removed {url}"),
+            UndoCommand::Mute { item, .. } => match item {
+                MuteItem::Pubkey(_) => "Muted user".to_owned(),
+                MuteItem::Thread(_) => "Muted thread".to_owned(),
+                MuteItem::Hashtag(hashtag) => format!("Muted #{hashtag}"),
+                MuteItem::Word(word) => format!("Muted \"{word}\""),
+            },
+            UndoCommand::DiscardDraft { .. } => "Discarded draft".to_owned(),
+        }
+    }
+
+    fn undo(self, app: &mut Damus, ctx: &mut AppContext<'_>) -> Result<(), String> {
+        match self {
+            UndoCommand::CloseColumn { index, column } => {
+                let kinds: Vec<TimelineKind> = column
+                    .router()
+                    .routes()
+                    .iter()
+                    .filter_map(|route| match route {
+                        Route::Timeline(kind) => Some(kind.clone()),
+                        _ => None,
+                    })
+                    .collect();
+
+                let columns = get_active_columns_mut(ctx.accounts, &mut app.decks_cache);
+                let index = index.min(columns.num_columns());
+                columns.add_column_at(column, index as u32);
+
+                let txn = Transaction::new(ctx.ndb).map_err(|e| e.to_string())?;
+                for kind in &kinds {
+                    let Some(result) =
+                        app.timeline_cache
+                            .open(ctx.ndb, ctx.note_cache, &txn, ctx.pool, kind)
+                    else {
+                        continue;
+                    };
+                    result.process(
+                        ctx.ndb,
+                        ctx.note_cache,
+                        &txn,
+                        &mut app.timeline_cache,
+                        ctx.unknown_ids,
+                        &ctx.accounts.mutefun(),
+                    );
+                }
+            }
+
+            UndoCommand::RemoveRelay(url) => {
+                ctx.accounts.add_advertised_relay(&url, ctx.pool);
+            }
+
+            UndoCommand::Mute { account, item } => {
+                ctx.accounts
+                    .apply_mute_action(ctx.ndb, ctx.pool, &account, &MuteAction::Unmute(item))
+                    .map_err(|e| format!("Couldn't unmute: {e}"))?;
+            }
+
+            UndoCommand::DiscardDraft { post_type, draft } => {
+                *app.drafts.get_from_post_type(&post_type) = draft;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct UndoStack {
+    next_id: u64,
+    commands: VecDeque<(egui::Id, UndoCommand)>,
+
+    /// Pushed since the last update, still needs a toast
+    unannounced: Vec<egui::Id>,
+}
+
+impl UndoStack {
+    /// Remember how to take back a change that was just made
+    pub fn push(&mut self, command: UndoCommand) {
+        let id = egui::Id::new(("undo", self.next_id));
+        self.next_id += 1;
+
+        self.commands.push_back((id, command));
+        self.unannounced.push(id);
+        if self.commands.len() > MAX_UNDO {
+            self.commands.pop_front();
+        }
+    }
+
+    /// Show toasts for new commands and take the ones the user asked to
+    /// undo, from a toast or with ctrl+z outside of a text field
+    fn update(&mut self, ctx: &egui::Context) -> Vec<UndoCommand> {
+        for id in std::mem::take(&mut self.unannounced) {
+            if let Some((_, command)) = self.commands.iter().find(|(cid, _)| *cid == id) {
+                notedeck::toast::with_action(ctx, command.description(), "Undo", id);
+            }
+        }
+
+        let mut requested: Vec<egui::Id> = self
+            .commands
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(|id| notedeck::toast::take_clicked(ctx, *id))
+            .collect();
+
+        // text fields have their own undo
+        let editing = ctx.memory(|m| m.focused().is_some());
+        if !editing && ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z)) {
+            if let Some((id, _)) = self.commands.back() {
+                notedeck::toast::dismiss(ctx, *id);
+                requested.push(*id);
+            }
+        }
+
+        let mut commands = Vec::new();
+        for id in requested {
+            if let Some(pos) = self.commands.iter().position(|(cid, _)| *cid == id) {
+                if let Some((_, command)) = self.commands.remove(pos) {
+                    commands.push(command);
+                }
+            }
+        }
+        commands
+    }
+}
+
+/// Undo whatever the user asked to this frame
+pub fn process_undo(app: &mut Damus, ctx: &mut AppContext<'_>, egui_ctx: &egui::Context) {
+    for command in app.undo.update(egui_ctx) {
+        let description = command.description();
+        match command.undo(app, ctx) {
+            Ok(()) => info!("undid: {description}"),
+            Err(e) => {
+                error!("couldn't undo {description}: {e}");
+                notedeck::toast::error(egui_ctx, e);
+            }
+        }
+    }
+}
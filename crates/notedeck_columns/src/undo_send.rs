@@ -0,0 +1,254 @@
+//! An optional pause between hitting Post and the note going out to
+//! relays. While it's held there's a toast to undo it, which puts it back
+//! in the composer it came from.
+
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+use nostrdb::Transaction;
+use notedeck::{AppContext, DataPath, JsonSettings};
+use tracing::error;
+
+use crate::{
+    app::get_active_columns_mut,
+    draft::Draft,
+    route::Route,
+    ui::note::{NewPostAction, PostType},
+    undo::UndoCommand,
+    Damus,
+};
+
+const UNDO_SEND_FILE: &str = "undo_send.json";
+
+/// Seconds a post can be held for
+pub const UNDO_SEND_DELAYS: RangeInclusive<u64> = 5..=30;
+
+/// How much of the post its toast quotes
+const TOAST_PREVIEW_CHARS: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct UndoSendSettings {
+    pub enabled: bool,
+    pub delay_secs: u64,
+}
+
+impl Default for UndoSendSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            delay_secs: 10,
+        }
+    }
+}
+
+impl UndoSendSettings {
+    /// How long to hold posts for, None to send right away
+    pub fn delay(&self) -> Option<Duration> {
+        self.enabled.then(|| {
+            let secs = self
+                .delay_secs
+                .clamp(*UNDO_SEND_DELAYS.start(), *UNDO_SEND_DELAYS.end());
+            Duration::from_secs(secs)
+        })
+    }
+}
+
+/// A post waiting out the delay, with the draft it was written in
+struct HeldPost {
+    id: egui::Id,
+    col: usize,
+    send_at: Instant,
+    action: NewPostAction,
+    draft: Draft,
+    announced: bool,
+}
+
+pub struct UndoSend {
+    file: JsonSettings<UndoSendSettings>,
+    settings: UndoSendSettings,
+    held: Vec<HeldPost>,
+    next_id: u64,
+}
+
+impl UndoSend {
+    pub fn new(path: &DataPath) -> Self {
+        let file = JsonSettings::new(path, UNDO_SEND_FILE);
+        let settings = file.load();
+
+        Self {
+            file,
+            settings,
+            held: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn settings(&self) -> UndoSendSettings {
+        self.settings
+    }
+
+    /// The drafts of posts still being held. They're out of the composer
+    /// but not sent, so they're autosaved with the other drafts.
+    pub fn held_drafts(&self) -> impl Iterator<Item = (&PostType, &Draft)> {
+        self.held
+            .iter()
+            .map(|held| (held.action.post_type(), &held.draft))
+    }
+
+    pub fn set_settings(&mut self, settings: UndoSendSettings) {
+        if settings == self.settings {
+            return;
+        }
+        self.settings = settings;
+
+        self.file.save(&self.settings);
+    }
+}
+
+/// Post `action` from column `col`, or hold it back if there's a delay
+/// set. Either way the draft it came from is cleared. A held draft is
+/// still autosaved until it's sent, so quitting while it's held brings it
+/// back as a draft next time.
+pub fn send_post(
+    app: &mut Damus,
+    ctx: &mut AppContext<'_>,
+    col: usize,
+    action: NewPostAction,
+) -> crate::Result<()> {
+    let Some(delay) = app.undo_send.settings.delay() else {
+        let txn = Transaction::new(ctx.ndb).expect("txn");
+        return action.execute(
+            ctx.ndb,
+            &txn,
+            ctx.pool,
+            ctx.accounts,
+            &mut app.drafts,
+            &mut app.pow,
+            app.reply_tags.scheme(),
+        );
+    };
+
+    let draft = std::mem::take(app.drafts.get_from_post_type(action.post_type()));
+    let undo_send = &mut app.undo_send;
+    let id = egui::Id::new(("undo_send", undo_send.next_id));
+    undo_send.next_id += 1;
+    undo_send.held.push(HeldPost {
+        id,
+        col,
+        send_at: Instant::now() + delay,
+        action,
+        draft,
+        announced: false,
+    });
+
+    Ok(())
+}
+
+/// Send held posts whose time is up, and put back the ones the user took
+/// back. Called once a frame.
+pub fn process_held_posts(app: &mut Damus, ctx: &mut AppContext<'_>, egui_ctx: &egui::Context) {
+    let now = Instant::now();
+    let mut i = 0;
+    while i < app.undo_send.held.len() {
+        let held = &mut app.undo_send.held[i];
+
+        if !held.announced {
+            held.announced = true;
+            let secs = held.send_at.saturating_duration_since(now).as_secs_f64();
+            notedeck::toast::with_action_for(
+                egui_ctx,
+                format!("Posting \"{}\"", preview(&held.draft)),
+                "Undo",
+                held.id,
+                secs,
+            );
+        }
+
+        if notedeck::toast::take_clicked(egui_ctx, held.id) {
+            let held = app.undo_send.held.remove(i);
+            restore(app, ctx, held);
+            continue;
+        }
+
+        if held.send_at > now {
+            i += 1;
+            continue;
+        }
+
+        let held = app.undo_send.held.remove(i);
+        notedeck::toast::dismiss(egui_ctx, held.id);
+        let txn = Transaction::new(ctx.ndb).expect("txn");
+        if let Err(err) = held.action.publish(
+            ctx.ndb,
+            &txn,
+            ctx.pool,
+            ctx.accounts,
+            &mut app.pow,
+            app.reply_tags.scheme(),
+        ) {
+            error!("Error executing held post action: {err}");
+            notedeck::toast::error(egui_ctx, format!("Couldn't post: {err}"));
+            restore(app, ctx, held);
+        }
+    }
+
+    if let Some(next) = app.undo_send.held.iter().map(|h| h.send_at).min() {
+        egui_ctx.request_repaint_after(next.saturating_duration_since(now));
+    }
+}
+
+/// Put a held post back in its composer and show it again
+fn restore(app: &mut Damus, ctx: &mut AppContext<'_>, held: HeldPost) {
+    let post_type = held.action.post_type().clone();
+    let replaced = std::mem::replace(app.drafts.get_from_post_type(&post_type), held.draft);
+    // something new may have been started there since
+    if !replaced.is_empty() {
+        app.undo.push(UndoCommand::DiscardDraft {
+            post_type: post_type.clone(),
+            draft: replaced,
+        });
+    }
+
+    let route = match post_type {
+        PostType::New => Route::ComposeNote,
+        PostType::Reply(id) => Route::Reply(id),
+        PostType::Quote(id) => Route::Quote(id),
+    };
+    let columns = get_active_columns_mut(ctx.accounts, &mut app.decks_cache);
+    if held.col < columns.num_columns() {
+        let router = columns.column_mut(held.col).router_mut();
+        if *router.top() != route {
+            router.route_to(route);
+        }
+    }
+}
+
+fn preview(draft: &Draft) -> String {
+    let text = draft.buffer.text_buffer.trim();
+    let mut preview: String = text.chars().take(TOAST_PREVIEW_CHARS).collect();
+    if text.chars().count() > TOAST_PREVIEW_CHARS {
+        preview.push('…');
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let mut settings = UndoSendSettings::default();
+        assert_eq!(settings.delay(), None);
+
+        settings.enabled = true;
+        assert_eq!(settings.delay(), Some(Duration::from_secs(10)));
+
+        // hand edited files can hold anything
+        settings.delay_secs = 600;
+        assert_eq!(settings.delay(), Some(Duration::from_secs(30)));
+        settings.delay_secs = 0;
+        assert_eq!(settings.delay(), Some(Duration::from_secs(5)));
+    }
+}
@@ -5,17 +5,23 @@ use enostr::Pubkey;
 use crate::deck_state::DeckState;
 use crate::login_manager::AcquireKeyState;
 use crate::profile_state::ProfileState;
+use crate::ui::app_lock::AppLockState;
+use crate::ui::export_key::ExportKeyState;
 use crate::ui::search::SearchQueryState;
+use crate::ui::tip_jar::TipJarState;
 
 /// Various state for views
 #[derive(Default)]
 pub struct ViewState {
     pub login: AcquireKeyState,
+    pub export_key: ExportKeyState,
+    pub app_lock: AppLockState,
     pub id_to_deck_state: HashMap<egui::Id, DeckState>,
     pub id_state_map: HashMap<egui::Id, AcquireKeyState>,
     pub id_string_map: HashMap<egui::Id, String>,
     pub searches: HashMap<egui::Id, SearchQueryState>,
     pub pubkey_to_profile_state: HashMap<Pubkey, ProfileState>,
+    pub tip_jars: HashMap<Pubkey, TipJarState>,
 }
 
 impl ViewState {
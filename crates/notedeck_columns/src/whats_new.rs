@@ -0,0 +1,250 @@
+//! The changelog that ships with the app, and the what's-new screen it
+//! becomes. The first launch of a version with changes the user hasn't
+//! seen opens the screen in the selected column. Changes can link to the
+//! feature they're about.
+
+use notedeck::{AppContext, DataPath, JsonSettings};
+
+use crate::{route::Route, Damus};
+
+const WHATS_NEW_FILE: &str = "whats_new.json";
+
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub struct Release {
+    pub version: &'static str,
+    pub changes: &'static [Change],
+}
+
+pub struct Change {
+    pub title: &'static str,
+    pub description: &'static str,
+
+    /// Where to go to try it out
+    pub open: Option<Feature>,
+}
+
+/// The places a change can link to. Kept apart from [`Route`] so the
+/// changelog can be a const.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Relays,
+    Settings,
+    Scheduled,
+    RecurringZaps,
+    TipJar,
+}
+
+impl Feature {
+    pub fn route(self) -> Route {
+        match self {
+            Feature::Relays => Route::Relays,
+            Feature::Settings => Route::Settings,
+            Feature::Scheduled => Route::Scheduled,
+            Feature::RecurringZaps => Route::RecurringZaps,
+            Feature::TipJar => Route::TipJar,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Feature::Relays => "Open relays",
+            Feature::Settings => "Open settings",
+            Feature::Scheduled => "Open scheduled posts",
+            Feature::RecurringZaps => "Open recurring zaps",
+            Feature::TipJar => "Set up a tip jar",
+        }
+    }
+}
+
+/// Newest first
+pub const CHANGELOG: &[Release] = &[Release {
+    version: "0.4.0",
+    changes: &[
+        Change {
+            title: "Relay groups",
+            description: "Save the relays you use as a named group and switch between groups, \
+                          optionally publishing the group as your relay list.",
+            open: Some(Feature::Relays),
+        },
+        Change {
+            title: "Relay roles and write tests",
+            description: "Choose which relays you read from, write to and search, and check \
+                          that a relay actually takes your notes.",
+            open: Some(Feature::Relays),
+        },
+        Change {
+            title: "Unread counts",
+            description: "Columns count the notes that came in above where you're reading, \
+                          and a pill takes you back up to them.",
+            open: None,
+        },
+        Change {
+            title: "Columns remember where you were",
+            description: "Scroll positions are kept across restarts.",
+            open: None,
+        },
+        Change {
+            title: "Desktop notifications",
+            description: "Mentions, messages and zaps can show up as system notifications, \
+                          with quiet hours and filters to keep them down.",
+            open: Some(Feature::Settings),
+        },
+        Change {
+            title: "Scheduled posts and drafts",
+            description: "Write now and post later, or pick up where you left off.",
+            open: Some(Feature::Scheduled),
+        },
+        Change {
+            title: "Recurring zaps",
+            description: "Support the people you like on a schedule.",
+            open: Some(Feature::RecurringZaps),
+        },
+        Change {
+            title: "Tip jar",
+            description: "Pin a note with your lightning address and suggested amounts \
+                          to your profile.",
+            open: Some(Feature::TipJar),
+        },
+    ],
+}];
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct Seen {
+    version: Option<String>,
+}
+
+/// The last version the user was shown what's new in
+pub struct WhatsNew {
+    /// Nothing in it on a fresh install
+    file: JsonSettings<Option<Seen>>,
+
+    /// What was seen before this launch, so the screen can tell which
+    /// releases are new
+    previous: Option<String>,
+    pending: bool,
+}
+
+impl WhatsNew {
+    pub fn new(path: &DataPath) -> Self {
+        let file = JsonSettings::new(path, WHATS_NEW_FILE);
+        let seen = file.load();
+
+        let mut whats_new = Self {
+            file,
+            previous: None,
+            pending: false,
+        };
+
+        match seen {
+            // a fresh install, everything is new so nothing needs pointing out
+            None => whats_new.save(),
+            Some(seen) => {
+                whats_new.pending = !unseen(CHANGELOG, seen.version.as_deref()).is_empty();
+                whats_new.previous = seen.version;
+            }
+        }
+
+        whats_new
+    }
+
+    /// Whether a release came with changes the user hasn't been shown
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Whether `release` came out after the version seen before this launch
+    pub fn is_new(&self, release: &Release) -> bool {
+        self.previous.is_some() && is_newer(release.version, self.previous.as_deref())
+    }
+
+    /// The screen was shown, don't open it again until the next update
+    pub fn mark_seen(&mut self) {
+        if !self.pending {
+            return;
+        }
+        self.pending = false;
+        self.save();
+    }
+
+    fn save(&self) {
+        self.file.save(&Some(Seen {
+            version: Some(CURRENT_VERSION.to_owned()),
+        }));
+    }
+}
+
+/// Open the what's-new screen in the selected column if there's anything
+/// the user hasn't seen
+pub fn show_if_updated(app: &mut Damus, ctx: &mut AppContext<'_>) {
+    if !app.whats_new.is_pending() {
+        return;
+    }
+
+    let columns = app.columns_mut(ctx.accounts);
+    if columns.columns().is_empty() {
+        return;
+    }
+    columns.selected().router_mut().route_to(Route::WhatsNew);
+    app.whats_new.mark_seen();
+}
+
+/// The releases in `changelog` newer than `seen`, newest first
+fn unseen<'a>(changelog: &'a [Release], seen: Option<&str>) -> &'a [Release] {
+    let count = changelog
+        .iter()
+        .take_while(|release| is_newer(release.version, seen))
+        .count();
+    &changelog[..count]
+}
+
+fn is_newer(version: &str, seen: Option<&str>) -> bool {
+    let Some(seen) = seen else {
+        return true;
+    };
+    match (parse_version(version), parse_version(seen)) {
+        (Some(version), Some(seen)) => version > seen,
+        _ => version != seen,
+    }
+}
+
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+    // pre-release suffixes like -beta sort with their release
+    let version = version.split(['-', '+']).next()?;
+    version.split('.').map(|part| part.parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_releases_after_the_seen_one_are_new() {
+        const LOG: &[Release] = &[
+            Release {
+                version: "0.10.0",
+                changes: &[],
+            },
+            Release {
+                version: "0.9.1",
+                changes: &[],
+            },
+            Release {
+                version: "0.9.0",
+                changes: &[],
+            },
+        ];
+
+        let versions = |seen| -> Vec<&str> {
+            unseen(LOG, seen)
+                .iter()
+                .map(|release| release.version)
+                .collect()
+        };
+        assert_eq!(versions(Some("0.9.0")), vec!["0.10.0", "0.9.1"]);
+        assert_eq!(versions(Some("0.10.0")), Vec::<&str>::new());
+        assert_eq!(versions(Some("0.10.0-beta")), Vec::<&str>::new());
+        assert_eq!(versions(None).len(), 3);
+    }
+}
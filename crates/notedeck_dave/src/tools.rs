@@ -464,7 +464,11 @@ impl QueryCall {
     pub fn execute(&self, txn: &Transaction, ndb: &Ndb) -> QueryResponse {
         let notes = {
             if let Ok(results) = ndb.query(txn, &[self.to_filter()], self.limit() as i32) {
-                results.into_iter().map(|r| r.note_key.as_u64()).collect()
+                results
+                    .into_iter()
+                    .filter(|r| !notedeck::note::is_expired(&r.note))
+                    .map(|r| r.note_key.as_u64())
+                    .collect()
             } else {
                 vec![]
             }
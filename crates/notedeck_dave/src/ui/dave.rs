@@ -4,7 +4,7 @@ use crate::{
 };
 use egui::{Align, Key, KeyboardShortcut, Layout, Modifiers};
 use nostrdb::{Ndb, Transaction};
-use notedeck::{Accounts, AppContext, Images, NoteAction, NoteContext};
+use notedeck::{AccountColors, Accounts, AppContext, Images, NoteAction, NoteContext};
 use notedeck_ui::{icons::search_icon, jobs::JobsCache, NoteOptions, ProfilePic};
 
 /// DaveUi holds all of the data it needs to render itself
@@ -216,7 +216,15 @@ impl<'a> DaveUi<'a> {
             zaps: ctx.zaps,
             pool: ctx.pool,
             job_pool: ctx.job_pool,
+            counts: ctx.counts,
+            polls: ctx.polls,
+            zap_goals: ctx.zap_goals,
+            paid_content: ctx.paid_content,
             current_account_has_wallet: false,
+            current_account_can_sign: false,
+            acting_pubkey: None,
+            account_colors: AccountColors::new(ctx.accounts),
+            truncate_lines: Some(notedeck::DEFAULT_TRUNCATE_LINES),
         };
 
         let txn = Transaction::new(note_context.ndb).unwrap();
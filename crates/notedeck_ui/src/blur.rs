@@ -15,6 +15,10 @@ pub struct PixelDimensions {
     pub y: u32,
 }
 
+/// The widest we'll decode a blurhash. Dimensions come from a note's
+/// imeta tag, so they can be anything.
+const MAX_BLUR_WIDTH: u32 = 4096;
+
 impl PixelDimensions {
     pub fn to_points(&self, ppp: f32) -> PointDimensions {
         PointDimensions {
@@ -22,6 +26,32 @@ impl PixelDimensions {
             y: (self.y as f32) / ppp,
         }
     }
+
+    /// Scale down to `max`'s height keeping the aspect ratio, like the
+    /// media carousel does. Absurdly wide results are scaled down further
+    /// so decoding the blurhash stays cheap.
+    pub fn scaled_to_fit(&self, max: PixelDimensions) -> PixelDimensions {
+        if self.x == 0 || self.y == 0 {
+            tracing::error!("The blur dimensions should not be zero");
+            return max;
+        }
+
+        let (mut x, mut y) = (self.x as f32, self.y as f32);
+        if self.y > max.y {
+            x *= max.y as f32 / y;
+            y = max.y as f32;
+        }
+
+        if x > MAX_BLUR_WIDTH as f32 {
+            y *= MAX_BLUR_WIDTH as f32 / x;
+            x = MAX_BLUR_WIDTH as f32;
+        }
+
+        PixelDimensions {
+            x: (x.round() as u32).max(1),
+            y: (y.round() as u32).max(1),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -44,6 +74,19 @@ impl PointDimensions {
 }
 
 impl Blur<'_> {
+    /// How big the image will show up once it's loaded, from the
+    /// dimensions in the imeta tag. Images are shown at their own size,
+    /// scaled down to `max_height`.
+    pub fn display_size(&self, max_height: f32) -> Option<egui::Vec2> {
+        let dims = self.dimensions.as_ref().filter(|d| d.x > 0 && d.y > 0)?;
+        let size = egui::vec2(dims.x as f32, dims.y as f32);
+        if size.y > max_height {
+            Some(size * (max_height / size.y))
+        } else {
+            Some(size)
+        }
+    }
+
     pub fn scaled_pixel_dimensions(
         &self,
         ui: &egui::Ui,
@@ -55,22 +98,7 @@ impl Blur<'_> {
             return max_pixels;
         };
 
-        if defined_dimensions.x == 0 || defined_dimensions.y == 0 {
-            tracing::error!("The blur dimensions should not be zero");
-            return max_pixels;
-        }
-
-        if defined_dimensions.y <= max_pixels.y {
-            return defined_dimensions.clone();
-        }
-
-        let scale_factor = (max_pixels.y as f32) / (defined_dimensions.y as f32);
-        let max_width_scaled = scale_factor * (defined_dimensions.x as f32);
-
-        PixelDimensions {
-            x: (max_width_scaled.round() as u32),
-            y: max_pixels.y,
-        }
+        defined_dimensions.scaled_to_fit(max_pixels)
     }
 }
 
@@ -1,6 +1,6 @@
 use nostrdb::{Filter, Ndb, Note, Transaction};
 
-fn pk1_is_following_pk2(
+pub fn pk1_is_following_pk2(
     ndb: &Ndb,
     txn: &Transaction,
     pk1: &[u8; 32],
@@ -23,8 +23,17 @@ pub fn handle_repaint<'a>(ui: &egui::Ui, latest: LatextTexture<'a>) -> &'a Textu
     latest.texture
 }
 
+/// Whether an animation about to be drawn in `ui` can be seen. We don't
+/// know its size yet, so this looks at where it will start.
+fn animation_visible(ui: &egui::Ui) -> bool {
+    let minimized = ui.ctx().input(|i| i.viewport().minimized == Some(true));
+    let start = egui::Rect::from_min_size(ui.next_widget_position(), egui::vec2(32.0, 32.0));
+    !minimized && ui.is_rect_visible(start)
+}
+
 #[must_use = "caller should pass the return value to `gif::handle_repaint`"]
 pub fn retrieve_latest_texture<'a>(
+    ui: &egui::Ui,
     url: &str,
     gifs: &'a mut GifStateMap,
     cached_image: &'a mut TexturedImage,
@@ -51,7 +60,16 @@ pub fn retrieve_latest_texture<'a>(
             }
 
             let now = Instant::now();
+            let may_play = gifs.may_play(url, animation_visible(ui));
             let (texture, maybe_new_state, request_next_repaint) = match gifs.get(url) {
+                Some(prev_state) if !may_play => {
+                    // held on its current frame, and no repaints for it
+                    let texture = match animation.get_frame(prev_state.last_frame_index) {
+                        Some(frame) => &frame.texture,
+                        None => &animation.first_frame.texture,
+                    };
+                    (texture, None, None)
+                }
                 Some(prev_state) => {
                     let should_advance =
                         now - prev_state.last_frame_rendered >= prev_state.last_frame_duration;
@@ -1,7 +1,9 @@
 use egui::{pos2, Color32, ColorImage, Context, Rect, Sense, SizeHint};
 use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
 use image::imageops::FilterType;
-use image::{AnimationDecoder, DynamicImage, FlatSamples, Frame};
+use image::{AnimationDecoder, DynamicImage, FlatSamples, Frame, ImageFormat};
 use notedeck::{
     Animation, GifStateMap, ImageFrame, Images, LoadableTextureState, MediaCache, MediaCacheType,
     TextureFrame, TextureState, TexturedImage,
@@ -184,6 +186,7 @@ fn fetch_img_from_disk(
     let path = path.to_owned();
 
     Promise::spawn_async(async move {
+        MediaCache::touch(&path);
         Some(async_fetch_img_from_disk(ctx, url, &path, cache_type).await)
     })
 }
@@ -197,6 +200,12 @@ async fn async_fetch_img_from_disk(
     match cache_type {
         MediaCacheType::Image => {
             let data = fs::read(path).await?;
+            if let Some(frames) = animated_frames(&data) {
+                return generate_animation(ctx, url, path, frames, false, |i| {
+                    buffer_to_color_image(i.as_flat_samples_u8(), i.width(), i.height())
+                });
+            }
+
             let image_buffer = image::load_from_memory(&data).map_err(notedeck::Error::Image)?;
 
             let img = buffer_to_color_image(
@@ -231,6 +240,52 @@ fn generate_gif(
         let reader = Cursor::new(data.as_slice());
         GifDecoder::new(reader)?
     };
+
+    let frames: VecDeque<Frame> = decoder
+        .into_frames()
+        .collect::<std::result::Result<VecDeque<_>, image::ImageError>>()
+        .map_err(|e| notedeck::Error::Generic(e.to_string()))?;
+
+    generate_animation(ctx, url, path, frames, write_to_disk, process_to_egui)
+}
+
+/// The frames of an animated png, webp or gif. None for still images.
+fn animated_frames(data: &[u8]) -> Option<VecDeque<Frame>> {
+    let frames = match image::guess_format(data).ok()? {
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(data)).ok()?;
+            if !decoder.is_apng().ok()? {
+                return None;
+            }
+            decoder.apng().ok()?.into_frames()
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(Cursor::new(data)).ok()?;
+            if !decoder.has_animation() {
+                return None;
+            }
+            decoder.into_frames()
+        }
+        ImageFormat::Gif => GifDecoder::new(Cursor::new(data)).ok()?.into_frames(),
+        _ => return None,
+    };
+
+    frames
+        .collect::<std::result::Result<VecDeque<_>, image::ImageError>>()
+        .ok()
+        .filter(|frames| frames.len() > 1)
+}
+
+/// Decode the rest of `frames` into textures in the background. With
+/// `write_to_disk`, they're also cached as a gif.
+fn generate_animation(
+    ctx: egui::Context,
+    url: String,
+    path: &path::Path,
+    mut frames: VecDeque<Frame>,
+    write_to_disk: bool,
+    process_to_egui: impl Fn(DynamicImage) -> ColorImage + Send + Copy + 'static,
+) -> Result<TexturedImage, notedeck::Error> {
     let (tex_input, tex_output) = mpsc::sync_channel(4);
     let (maybe_encoder_input, maybe_encoder_output) = if write_to_disk {
         let (inp, out) = mpsc::sync_channel(4);
@@ -239,11 +294,6 @@ fn generate_gif(
         (None, None)
     };
 
-    let mut frames: VecDeque<Frame> = decoder
-        .into_frames()
-        .collect::<std::result::Result<VecDeque<_>, image::ImageError>>()
-        .map_err(|e| notedeck::Error::Generic(e.to_string()))?;
-
     let first_frame = frames.pop_front().map(|frame| {
         generate_animation_frame(
             &ctx,
@@ -361,6 +411,19 @@ pub fn fetch_img(
     url: &str,
     imgtyp: ImageType,
     cache_type: MediaCacheType,
+) -> Promise<Option<Result<TexturedImage, notedeck::Error>>> {
+    fetch_img_with_fallbacks(img_cache_path, ctx, url, imgtyp, cache_type, &[])
+}
+
+/// Like [`fetch_img`], but a blob url that 404s is tried on each of
+/// `blossom_servers` before giving up
+pub fn fetch_img_with_fallbacks(
+    img_cache_path: &Path,
+    ctx: &egui::Context,
+    url: &str,
+    imgtyp: ImageType,
+    cache_type: MediaCacheType,
+    blossom_servers: &[String],
 ) -> Promise<Option<Result<TexturedImage, notedeck::Error>>> {
     let key = MediaCache::key(url);
     let path = img_cache_path.join(key);
@@ -368,28 +431,62 @@ pub fn fetch_img(
     if path.exists() {
         fetch_img_from_disk(ctx, url, &path, cache_type)
     } else {
-        fetch_img_from_net(img_cache_path, ctx, url, imgtyp, cache_type)
+        let fallbacks = notedeck::blossom::fallback_urls(url, blossom_servers);
+        fetch_img_from_net(img_cache_path, ctx, url, fallbacks, imgtyp, cache_type)
     }
 
     // TODO: fetch image from local cache
 }
 
+/// Fetch the first of `urls` that the server has
+fn fetch_first_found(
+    mut urls: VecDeque<String>,
+    on_done: impl FnOnce(ehttp::Result<ehttp::Response>) + Send + 'static,
+) {
+    let Some(url) = urls.pop_front() else {
+        on_done(Err("no url to fetch".to_owned()));
+        return;
+    };
+
+    ehttp::fetch(ehttp::Request::get(&url), move |response| match response {
+        Ok(resp) if resp.status == 404 && !urls.is_empty() => {
+            tracing::debug!("{url} not found, trying {}", urls[0]);
+            fetch_first_found(urls, on_done)
+        }
+        response => on_done(response),
+    });
+}
+
 fn fetch_img_from_net(
     cache_path: &path::Path,
     ctx: &egui::Context,
     url: &str,
+    fallbacks: Vec<String>,
     imgtyp: ImageType,
     cache_type: MediaCacheType,
 ) -> Promise<Option<Result<TexturedImage, notedeck::Error>>> {
     let (sender, promise) = Promise::new();
-    let request = ehttp::Request::get(url);
+    let mut urls = VecDeque::from(fallbacks);
+    urls.push_front(url.to_owned());
     let ctx = ctx.clone();
     let cloned_url = url.to_owned();
     let cache_path = cache_path.to_owned();
-    ehttp::fetch(request, move |response| {
+    fetch_first_found(urls, move |response| {
         let handle = response.map_err(notedeck::Error::Generic).and_then(|resp| {
             match cache_type {
                 MediaCacheType::Image => {
+                    // animated webp and apng are served as plain images
+                    if let Some(frames) = animated_frames(&resp.bytes) {
+                        return generate_animation(
+                            ctx.clone(),
+                            cloned_url,
+                            &cache_path,
+                            frames,
+                            true,
+                            move |img| process_pfp_bitmap(imgtyp, img),
+                        );
+                    }
+
                     let img = parse_img_response(resp, imgtyp);
                     img.map(|img| {
                         let texture_handle =
@@ -436,8 +533,16 @@ pub fn get_render_state<'a>(
         MediaCacheType::Gif => &mut images.gifs,
     };
 
+    let blossom_servers = &images.blossom_servers;
     let cur_state = cache.textures_cache.handle_and_get_or_insert(url, || {
-        crate::images::fetch_img(&cache.cache_dir, ctx, url, img_type, cache_type)
+        crate::images::fetch_img_with_fallbacks(
+            &cache.cache_dir,
+            ctx,
+            url,
+            img_type,
+            cache_type,
+            blossom_servers,
+        )
     });
 
     RenderState {
@@ -446,6 +551,44 @@ pub fn get_render_state<'a>(
     }
 }
 
+/// `url`'s image cropped to fill the square `rect`, the middle of its long
+/// side kept. Nothing is painted until it's loaded.
+pub fn paint_square_thumbnail(
+    ui: &mut egui::Ui,
+    img_cache: &mut Images,
+    url: &str,
+    rect: Rect,
+    corner_radius: f32,
+) {
+    let cache_type = notedeck::supported_mime_hosted_at_url(&mut img_cache.urls, url)
+        .unwrap_or(MediaCacheType::Image);
+    let state = get_render_state(ui.ctx(), img_cache, cache_type, url, ImageType::Content);
+    let TextureState::Loaded(image) = state.texture_state else {
+        return;
+    };
+
+    let texture = crate::gif::handle_repaint(
+        ui,
+        crate::gif::retrieve_latest_texture(ui, url, state.gifs, image),
+    );
+    let [width, height] = texture.size().map(|side| side as f32);
+    if width == 0.0 || height == 0.0 {
+        return;
+    }
+
+    let side = width.min(height);
+    let margin = egui::vec2((width - side) / width / 2.0, (height - side) / height / 2.0);
+    let uv = Rect::from_min_max(
+        pos2(margin.x, margin.y),
+        pos2(1.0 - margin.x, 1.0 - margin.y),
+    );
+
+    egui::Image::new(texture)
+        .uv(uv)
+        .corner_radius(corner_radius)
+        .paint_at(ui, rect);
+}
+
 pub struct LoadableRenderState<'a> {
     pub texture_state: LoadableTextureState<'a>,
     pub gifs: &'a mut GifStateMap,
@@ -10,13 +10,15 @@ pub mod jobs;
 pub mod mention;
 pub mod note;
 pub mod profile;
+mod qr;
 mod username;
 pub mod widgets;
 
 pub use anim::{AnimationHelper, PulseAlpha};
 pub use mention::Mention;
 pub use note::{NoteContents, NoteOptions, NoteView};
-pub use profile::{ProfilePic, ProfilePreview};
+pub use profile::{ProfileHoverCard, ProfilePic, ProfilePreview};
+pub use qr::qr_code;
 pub use username::Username;
 
 use egui::Margin;
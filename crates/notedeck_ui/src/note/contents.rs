@@ -12,9 +12,19 @@ use enostr::KeypairUnowned;
 use nostrdb::{BlockType, Mention, Note, NoteKey, Transaction};
 use tracing::warn;
 
-use notedeck::NoteContext;
-
-use super::media::{find_renderable_media, image_carousel, RenderableMedia};
+use notedeck::paid::Paywall;
+use notedeck::poll::{Poll, POLL_KIND};
+use notedeck::tip_jar::TipJar;
+use notedeck::zap_goal::{ZapGoal, ZAP_GOAL_KIND};
+use notedeck::{LinkPreview, NoteContext};
+
+use super::link_preview::link_preview_ui;
+use super::media::{find_renderable_media, image_carousel, imeta_alts, RenderableMedia};
+use super::paid::paywall_ui;
+use super::poll::poll_ui;
+use super::tip_jar::tip_jar_ui;
+use super::video::video_player;
+use super::zap_goal::zap_goal_ui;
 
 pub struct NoteContents<'a, 'd> {
     note_context: &'a mut NoteContext<'d>,
@@ -50,6 +60,19 @@ impl<'a, 'd> NoteContents<'a, 'd> {
 
 impl egui::Widget for &mut NoteContents<'_, '_> {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        if let Some(reason) = content_warning(self.note) {
+            let revealed_id = egui::Id::new(("content_warning", self.note.id()));
+            let revealed = ui.data(|d| d.get_temp::<bool>(revealed_id).unwrap_or(false));
+
+            if !revealed {
+                let resp = content_warning_ui(ui, reason);
+                if resp.clicked() {
+                    ui.data_mut(|d| d.insert_temp(revealed_id, true));
+                }
+                return resp;
+            }
+        }
+
         let result = render_note_contents(
             ui,
             self.note_context,
@@ -80,7 +103,7 @@ pub fn render_note_preview(
 ) -> NoteResponse {
     let note = if let Ok(note) = note_context.ndb.get_note_by_id(txn, id) {
         // TODO: support other preview kinds
-        if note.kind() == 1 {
+        if matches!(note.kind(), 1 | POLL_KIND | ZAP_GOAL_KIND) {
             note
         } else {
             return NoteResponse::new(ui.colored_label(
@@ -125,9 +148,14 @@ pub fn render_note_contents(
     let hide_media = options.has_hide_media();
     let link_color = ui.visuals().hyperlink_color;
 
-    // The current length of the rendered blocks. Used in trucation logic
-    let mut current_len: usize = 0;
-    let truncate_len = 280;
+    // how tall the text can get before it's cut off, unless it's been
+    // expanded this session
+    let show_more_id = egui::Id::new(("show_more", note.id()));
+    let expanded = ui.data(|d| d.get_temp::<bool>(show_more_id).unwrap_or(false));
+    let max_height = note_context
+        .truncate_lines
+        .filter(|_| options.has_truncate())
+        .map(|lines| lines as f32 * ui.text_style_height(&egui::TextStyle::Body));
 
     if !options.has_is_preview() {
         // need this for the rect to take the full width of the column
@@ -135,9 +163,12 @@ pub fn render_note_contents(
     }
 
     let mut supported_medias: Vec<RenderableMedia> = vec![];
+    let mut videos: Vec<&str> = vec![];
+    let mut link_preview: Option<(&str, LinkPreview)> = None;
     let blurhashes = OnceCell::new();
+    let alts = OnceCell::new();
 
-    let response = ui.horizontal_wrapped(|ui| {
+    let blocks_ui = |ui: &mut egui::Ui| {
         let blocks = if let Ok(blocks) = note_context.ndb.get_blocks_by_key(txn, note_key) {
             blocks
         } else {
@@ -150,8 +181,13 @@ pub fn render_note_contents(
 
         for block in blocks.iter(note) {
             match block.blocktype() {
-                BlockType::MentionBech32 => match block.as_mention().unwrap() {
-                    Mention::Profile(profile) => {
+                BlockType::MentionBech32 => match block.as_mention() {
+                    // nostrdb tagged it but couldn't decode it, show it as text
+                    None => {
+                        ui.label(block.as_str());
+                    }
+
+                    Some(Mention::Profile(profile)) => {
                         let act = crate::Mention::new(
                             note_context.ndb,
                             note_context.img_cache,
@@ -165,7 +201,7 @@ pub fn render_note_contents(
                         }
                     }
 
-                    Mention::Pubkey(npub) => {
+                    Some(Mention::Pubkey(npub)) => {
                         let act = crate::Mention::new(
                             note_context.ndb,
                             note_context.img_cache,
@@ -179,16 +215,19 @@ pub fn render_note_contents(
                         }
                     }
 
-                    Mention::Note(note) if options.has_note_previews() => {
+                    Some(Mention::Note(note)) if options.has_note_previews() => {
                         inline_note = Some((note.id(), block.as_str()));
                     }
 
-                    Mention::Event(note) if options.has_note_previews() => {
+                    Some(Mention::Event(note)) if options.has_note_previews() => {
                         inline_note = Some((note.id(), block.as_str()));
                     }
 
                     _ => {
-                        ui.colored_label(link_color, format!("@{}", &block.as_str()[4..16]));
+                        ui.colored_label(
+                            link_color,
+                            format!("@{}", abbreviate_bech(block.as_str())),
+                        );
                     }
                 },
 
@@ -206,11 +245,20 @@ pub fn render_note_contents(
                     let mut found_supported = || -> bool {
                         let url = block.as_str();
 
-                        let blurs = blurhashes.get_or_init(|| imeta_blurhashes(note));
+                        if notedeck::video::is_video_url(url) {
+                            videos.push(url);
+                            return true;
+                        }
 
-                        let Some(media_type) =
-                            find_renderable_media(&mut note_context.img_cache.urls, blurs, url)
-                        else {
+                        let blurs = blurhashes.get_or_init(|| imeta_blurhashes(note));
+                        let alts = alts.get_or_init(|| imeta_alts(note));
+
+                        let Some(media_type) = find_renderable_media(
+                            &mut note_context.img_cache.urls,
+                            blurs,
+                            alts,
+                            url,
+                        ) else {
                             return false;
                         };
 
@@ -219,34 +267,31 @@ pub fn render_note_contents(
                     };
 
                     if hide_media || !found_supported() {
-                        ui.add(Hyperlink::from_label_and_url(
-                            RichText::new(block.as_str()).color(link_color),
-                            block.as_str(),
-                        ));
+                        let url = block.as_str();
+
+                        // the first plain link gets a card, which stands in
+                        // for the link when it's at the end of the note
+                        let mut replaced = false;
+                        if !hide_media && !options.has_textmode() && link_preview.is_none() {
+                            if let Some(preview) =
+                                note_context.img_cache.link_previews.get(ui.ctx(), url)
+                            {
+                                replaced = note.content().trim_end().ends_with(url);
+                                link_preview = Some((url, preview.clone()));
+                            }
+                        }
+
+                        if !replaced {
+                            ui.add(Hyperlink::from_label_and_url(
+                                RichText::new(url).color(link_color),
+                                url,
+                            ));
+                        }
                     }
                 }
 
                 BlockType::Text => {
-                    // truncate logic
-                    let mut truncate = false;
-                    let block_str = if options.has_truncate()
-                        && (current_len + block.as_str().len() > truncate_len)
-                    {
-                        truncate = true;
-                        // The current block goes over the truncate length,
-                        // we'll need to truncate this block
-                        let block_str = block.as_str();
-                        let closest = notedeck::abbrev::floor_char_boundary(
-                            block_str,
-                            truncate_len - current_len,
-                        );
-                        &(block_str[..closest].to_string() + "…")
-                    } else {
-                        let block_str = block.as_str();
-                        current_len += block_str.len();
-                        block_str
-                    };
-
+                    let block_str = block.as_str();
                     if options.has_scramble_text() {
                         ui.add(
                             egui::Label::new(rot13(block_str))
@@ -256,11 +301,6 @@ pub fn render_note_contents(
                     } else {
                         ui.add(egui::Label::new(block_str).wrap().selectable(selectable));
                     }
-
-                    // don't render any more blocks
-                    if truncate {
-                        break;
-                    }
                 }
 
                 _ => {
@@ -268,7 +308,36 @@ pub fn render_note_contents(
                 }
             }
         }
-    });
+    };
+
+    let (response, overflows) = match max_height {
+        Some(max_height) if !expanded => {
+            let out = egui::ScrollArea::vertical()
+                .id_salt(("truncated", note_key))
+                .max_height(max_height)
+                .auto_shrink([false, true])
+                .enable_scrolling(false)
+                .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysHidden)
+                .show(ui, |ui| ui.horizontal_wrapped(blocks_ui));
+            let overflows = out.content_size.y > max_height + 1.0;
+            (out.inner, overflows)
+        }
+        _ => {
+            let response = ui.horizontal_wrapped(blocks_ui);
+            let overflows =
+                max_height.is_some_and(|max_height| response.response.rect.height() > max_height);
+            (response, overflows)
+        }
+    };
+
+    if overflows {
+        let resp = ui.link(if expanded { "Show less" } else { "Show more" });
+        if resp.clicked() {
+            ui.data_mut(|d| d.insert_temp(show_more_id, !expanded));
+        } else if resp.hovered() {
+            crate::show_pointer(ui);
+        }
+    }
 
     let preview_note_action = if let Some((id, _block_str)) = inline_note {
         render_note_preview(ui, note_context, cur_acc, txn, id, note_key, options, jobs).action
@@ -276,17 +345,21 @@ pub fn render_note_contents(
         None
     };
 
+    let trusted_media = || {
+        trust_media_from_pk2(
+            note_context.ndb,
+            txn,
+            cur_acc.as_ref().map(|k| k.pubkey.bytes()),
+            note.pubkey(),
+        )
+    };
+
     let mut media_action = None;
     if !supported_medias.is_empty() && !options.has_textmode() {
         ui.add_space(2.0);
         let carousel_id = egui::Id::new(("carousel", note.key().expect("expected tx note")));
 
-        let trusted_media = trust_media_from_pk2(
-            note_context.ndb,
-            txn,
-            cur_acc.as_ref().map(|k| k.pubkey.bytes()),
-            note.pubkey(),
-        );
+        let trusted_media = trusted_media();
 
         media_action = image_carousel(
             ui,
@@ -300,8 +373,39 @@ pub fn render_note_contents(
         ui.add_space(2.0);
     }
 
+    if !videos.is_empty() && !options.has_textmode() {
+        let trusted_media = trusted_media();
+        for url in videos {
+            ui.add_space(2.0);
+            video_player(ui, note_context.img_cache, url, trusted_media, 360.0);
+        }
+        ui.add_space(2.0);
+    }
+
+    if let Some((url, preview)) = link_preview {
+        let trusted_media = trusted_media();
+        ui.add_space(2.0);
+        link_preview_ui(ui, note_context.img_cache, url, &preview, trusted_media);
+        ui.add_space(2.0);
+    }
+
+    let poll_action =
+        Poll::from_note(note).and_then(|poll| poll_ui(ui, note_context, txn, note, &poll));
+    let goal_action =
+        ZapGoal::from_note(note).and_then(|goal| zap_goal_ui(ui, note_context, txn, note, &goal));
+    let paywall_action = Paywall::from_note(note)
+        .filter(|_| !options.has_is_preview())
+        .and_then(|paywall| paywall_ui(ui, note_context, note, &paywall));
+    let tip_jar_action = TipJar::from_note(note)
+        .filter(|_| !options.has_is_preview())
+        .and_then(|tip_jar| tip_jar_ui(ui, note_context, note, &tip_jar));
+
     let note_action = preview_note_action
         .or(note_action)
+        .or(poll_action)
+        .or(goal_action)
+        .or(paywall_action)
+        .or(tip_jar_action)
         .or(media_action.map(NoteAction::Media));
 
     NoteResponse::new(response.response).with_action(note_action)
@@ -324,3 +428,55 @@ fn rot13(input: &str) -> String {
         })
         .collect()
 }
+
+/// The start of a bech32 entity after its prefix. Relays can send us
+/// anything, so don't assume it's as long as a real one.
+fn abbreviate_bech(bech: &str) -> &str {
+    bech.get(4..16).unwrap_or(bech)
+}
+
+/// The reason from a NIP-36 `content-warning` tag. Some("") when the
+/// note has one without a reason.
+fn content_warning<'a>(note: &Note<'a>) -> Option<&'a str> {
+    for tag in note.tags() {
+        if tag.count() < 1 {
+            continue;
+        }
+
+        if tag.get_unchecked(0).variant().str() != Some("content-warning") {
+            continue;
+        }
+
+        let reason = tag.get(1).and_then(|t| t.variant().str());
+        return Some(reason.unwrap_or_default());
+    }
+
+    None
+}
+
+/// Stands in for the note's contents until the user asks to see them.
+/// The response is clicked when they do.
+fn content_warning_ui(ui: &mut egui::Ui, reason: &str) -> egui::Response {
+    egui::Frame::new()
+        .fill(ui.visuals().faint_bg_color)
+        .corner_radius(8.0)
+        .inner_margin(egui::Margin::same(12))
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.vertical(|ui| {
+                ui.label(RichText::new("Sensitive content").strong());
+                if !reason.is_empty() {
+                    ui.label(RichText::new(reason).color(ui.visuals().weak_text_color()));
+                }
+                ui.add_space(4.0);
+
+                let resp = ui.link("Show sensitive content");
+                if resp.hovered() {
+                    crate::show_pointer(ui);
+                }
+                resp
+            })
+            .inner
+        })
+        .inner
+}
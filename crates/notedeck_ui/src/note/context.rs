@@ -1,6 +1,12 @@
+use std::collections::BTreeSet;
+
 use egui::{Rect, Vec2};
+use enostr::{PoolRelay, RelayPool, RelayStatus};
 use nostrdb::NoteKey;
-use notedeck::{BroadcastContext, NoteContextSelection};
+use notedeck::{
+    nip19::{self, Nip19Kind},
+    BroadcastContext, NoteContextSelection,
+};
 
 pub struct NoteContextButton {
     put_at: Option<Rect>,
@@ -106,6 +112,8 @@ impl NoteContextButton {
     pub fn menu(
         ui: &mut egui::Ui,
         button_response: egui::Response,
+        note_kind: u32,
+        pool: &RelayPool,
     ) -> Option<NoteContextSelection> {
         let mut context_selection: Option<NoteContextSelection> = None;
 
@@ -115,36 +123,155 @@ impl NoteContextButton {
                 context_selection = Some(NoteContextSelection::CopyText);
                 ui.close_menu();
             }
-            if ui.button("Copy user public key").clicked() {
-                context_selection = Some(NoteContextSelection::CopyPubkey);
+            if ui.button("Copy raw content").clicked() {
+                context_selection = Some(NoteContextSelection::CopyRawText);
+                ui.close_menu();
+            }
+            if ui.button("Copy note link").clicked() {
+                context_selection = Some(NoteContextSelection::CopyNoteLink);
+                ui.close_menu();
+            }
+            if ui.button("Copy njump.me link").clicked() {
+                context_selection = Some(NoteContextSelection::CopyWebLink);
                 ui.close_menu();
             }
-            if ui.button("Copy note id").clicked() {
-                context_selection = Some(NoteContextSelection::CopyNoteId);
+            if ui.button("Open in njump.me").clicked() {
+                context_selection = Some(NoteContextSelection::OpenWebLink);
                 ui.close_menu();
             }
+            ui.menu_button("Copy link as", |ui| {
+                if let Some(selection) = copy_bech32_menu(ui, note_kind) {
+                    context_selection = Some(selection);
+                    ui.close_menu();
+                }
+            });
             if ui.button("Copy note json").clicked() {
                 context_selection = Some(NoteContextSelection::CopyNoteJSON);
                 ui.close_menu();
             }
-            if ui.button("Broadcast").clicked() {
+            if ui.button("Inspect event").clicked() {
+                context_selection = Some(NoteContextSelection::Inspect);
+                ui.close_menu();
+            }
+            if ui.button("Broadcast to my relays").clicked() {
+                context_selection = Some(NoteContextSelection::Broadcast(
+                    BroadcastContext::WriteRelays,
+                ));
+                ui.close_menu();
+            }
+            if ui.button("Broadcast everywhere").clicked() {
                 context_selection = Some(NoteContextSelection::Broadcast(
                     BroadcastContext::Everywhere,
                 ));
                 ui.close_menu();
             }
+            ui.menu_button("Broadcast to…", |ui| {
+                if let Some(relays) = pick_relays_menu(ui, pool) {
+                    context_selection = Some(NoteContextSelection::Broadcast(
+                        BroadcastContext::Relays(relays),
+                    ));
+                    ui.close_menu();
+                }
+            });
             if ui.button("Broadcast to local network").clicked() {
                 context_selection = Some(NoteContextSelection::Broadcast(
                     BroadcastContext::LocalNetwork,
                 ));
                 ui.close_menu();
             }
+            if ui.button("Mute thread").clicked() {
+                context_selection = Some(NoteContextSelection::MuteThread);
+                ui.close_menu();
+            }
+            if ui.button("Mute user").clicked() {
+                context_selection = Some(NoteContextSelection::MuteUser);
+                ui.close_menu();
+            }
         });
 
         context_selection
     }
 }
 
+/// Checkboxes for the connected relays and a button to send to the
+/// checked ones. What's checked is remembered for next time.
+fn pick_relays_menu(ui: &mut egui::Ui, pool: &RelayPool) -> Option<Vec<String>> {
+    let connected: Vec<&str> = pool
+        .relays
+        .iter()
+        .filter(|relay| {
+            matches!(relay, PoolRelay::Websocket(_)) && relay.status() == RelayStatus::Connected
+        })
+        .map(|relay| relay.url())
+        .collect();
+    if connected.is_empty() {
+        ui.weak("Not connected to any relays");
+        return None;
+    }
+
+    let id = egui::Id::new("broadcast_relay_picks");
+    let mut picked: BTreeSet<String> = ui.data(|d| d.get_temp(id).unwrap_or_default());
+
+    for url in &connected {
+        let mut checked = picked.contains(*url);
+        if ui.checkbox(&mut checked, *url).changed() {
+            if checked {
+                picked.insert(url.to_string());
+            } else {
+                picked.remove(*url);
+            }
+        }
+    }
+
+    let relays: Vec<String> = connected
+        .iter()
+        .filter(|url| picked.contains(**url))
+        .map(|url| url.to_string())
+        .collect();
+    ui.data_mut(|d| d.insert_temp(id, picked));
+
+    ui.separator();
+    let label = match relays.len() {
+        1 => "Send to 1 relay".to_owned(),
+        n => format!("Send to {n} relays"),
+    };
+    ui.add_enabled(!relays.is_empty(), egui::Button::new(label))
+        .clicked()
+        .then_some(relays)
+}
+
+/// Every encoding of the note and its author, with and without relay hints
+fn copy_bech32_menu(ui: &mut egui::Ui, note_kind: u32) -> Option<NoteContextSelection> {
+    let mut kinds = vec![
+        Nip19Kind::Npub,
+        Nip19Kind::Nprofile,
+        Nip19Kind::Note,
+        Nip19Kind::Nevent,
+    ];
+    if nip19::is_addressable(note_kind) {
+        kinds.push(Nip19Kind::Naddr);
+    }
+
+    let mut selection = None;
+    for kind in kinds {
+        if ui.button(kind.label()).clicked() {
+            selection = Some(NoteContextSelection::CopyBech32 {
+                kind,
+                relays: false,
+            });
+        }
+        if kind.has_relays()
+            && ui
+                .button(format!("{} with relay hints", kind.label()))
+                .clicked()
+        {
+            selection = Some(NoteContextSelection::CopyBech32 { kind, relays: true });
+        }
+    }
+
+    selection
+}
+
 fn stationary_arbitrary_menu_button<R>(
     ui: &mut egui::Ui,
     button_response: egui::Response,
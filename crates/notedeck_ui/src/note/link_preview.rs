@@ -0,0 +1,85 @@
+use egui::{vec2, RichText, Sense};
+use notedeck::{Images, LinkPreview};
+
+use crate::images::paint_square_thumbnail;
+
+const THUMBNAIL_SIZE: f32 = 72.0;
+
+/// A card with the title, description and thumbnail of a linked page.
+/// Clicking it opens the link.
+pub fn link_preview_ui(
+    ui: &mut egui::Ui,
+    img_cache: &mut Images,
+    url: &str,
+    preview: &LinkPreview,
+    show_thumbnail: bool,
+) -> egui::Response {
+    let site = preview
+        .site_name
+        .as_deref()
+        .unwrap_or_else(|| host(url))
+        .to_owned();
+
+    let frame = egui::Frame::new()
+        .stroke(ui.visuals().noninteractive().bg_stroke)
+        .corner_radius(8.0)
+        .inner_margin(8.0)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.horizontal(|ui| {
+                if let Some(image) = preview.image.as_deref().filter(|_| show_thumbnail) {
+                    thumbnail(ui, img_cache, image);
+                    ui.add_space(4.0);
+                }
+
+                ui.vertical(|ui| {
+                    ui.spacing_mut().item_spacing.y = 2.0;
+                    ui.add(egui::Label::new(RichText::new(site).small().weak()).truncate());
+                    if let Some(title) = &preview.title {
+                        ui.add(egui::Label::new(RichText::new(title).strong()).truncate());
+                    }
+                    if let Some(description) = &preview.description {
+                        ui.add(
+                            egui::Label::new(RichText::new(truncate(description, 200)).weak())
+                                .wrap(),
+                        );
+                    }
+                });
+            });
+        });
+
+    let resp = ui.interact(
+        frame.response.rect,
+        ui.id().with(("link_preview", url)),
+        Sense::click(),
+    );
+    if resp.clicked() {
+        ui.ctx().open_url(egui::OpenUrl::new_tab(url));
+    }
+    if resp.hovered() {
+        crate::show_pointer(ui);
+    }
+    resp.on_hover_text(url)
+}
+
+/// The page's image cropped to a square, or a blank square while it loads
+fn thumbnail(ui: &mut egui::Ui, img_cache: &mut Images, url: &str) {
+    let (rect, _) = ui.allocate_exact_size(vec2(THUMBNAIL_SIZE, THUMBNAIL_SIZE), Sense::hover());
+    ui.painter_at(rect)
+        .rect_filled(rect, 6.0, ui.visuals().faint_bg_color);
+    paint_square_thumbnail(ui, img_cache, url, rect, 6.0);
+}
+
+/// `example.com` out of `https://www.example.com/page`
+fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let host = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    host.trim_start_matches("www.")
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text.to_owned(),
+    }
+}
@@ -1,12 +1,14 @@
 use std::{collections::HashMap, path::Path};
 
 use egui::{
-    Button, Color32, Context, CornerRadius, FontId, Image, Response, Sense, TextureHandle, Window,
+    Button, Color32, Context, CornerRadius, FontId, Image, Rect, Response, RichText, Sense,
+    TextureHandle, UiBuilder, Window,
 };
+use nostrdb::Note;
 use notedeck::{
     fonts::get_font_size, note::MediaAction, show_one_error_message, supported_mime_hosted_at_url,
-    GifState, GifStateMap, Images, JobPool, MediaCache, MediaCacheType, NotedeckTextStyle,
-    TexturedImage, TexturesCache, UrlMimes,
+    GifStateMap, Images, JobPool, MediaCache, MediaCacheType, NotedeckTextStyle, TexturedImage,
+    TexturesCache, UrlMimes,
 };
 
 use crate::{
@@ -37,21 +39,17 @@ pub(crate) fn image_carousel(
             .unwrap_or(false)
     });
 
-    let current_image = 'scope: {
-        if !show_popup {
-            break 'scope None;
-        }
-
-        let Some(media) = medias.first() else {
-            break 'scope None;
-        };
+    // everything the fullscreen viewer can page through
+    let gallery: Vec<(String, MediaCacheType)> = medias
+        .iter()
+        .map(|media| (media.url.to_owned(), media.media_type))
+        .collect();
 
-        Some(ui.ctx().memory(|mem| {
-            mem.data
-                .get_temp::<(String, MediaCacheType)>(carousel_id.with("current_image"))
-                .unwrap_or_else(|| (media.url.to_owned(), media.media_type))
-        }))
-    };
+    let current_index = ui.ctx().memory(|mem| {
+        mem.data
+            .get_temp::<usize>(carousel_id.with("current_index"))
+            .unwrap_or(0)
+    });
     let mut action = None;
 
     //let has_touch_screen = ui.ctx().input(|i| i.has_touch_screen());
@@ -62,13 +60,18 @@ pub(crate) fn image_carousel(
             .id_salt(carousel_id)
             .show(ui, |ui| {
                 ui.horizontal(|ui| {
-                    for media in medias {
+                    for (index, media) in medias.into_iter().enumerate() {
                         let RenderableMedia {
                             url,
                             media_type,
                             obfuscation_type: blur_type,
+                            alt,
                         } = media;
 
+                        // hold the image's space from the start so the
+                        // note doesn't jump when it loads
+                        let placeholder = placeholder_size(&blur_type, height);
+
                         let cache = match media_type {
                             MediaCacheType::Image => &mut img_cache.static_imgs,
                             MediaCacheType::Gif => &mut img_cache.gifs,
@@ -84,6 +87,7 @@ pub(crate) fn image_carousel(
                             url,
                             media_type,
                             &cache.cache_dir,
+                            &img_cache.blossom_servers,
                             blur_type,
                         );
                         if let Some(cur_action) = render_media(
@@ -91,9 +95,11 @@ pub(crate) fn image_carousel(
                             &mut img_cache.gif_states,
                             media_state,
                             url,
-                            media_type,
+                            alt,
                             height,
+                            placeholder,
                             carousel_id,
+                            index,
                         ) {
                             let cur_action = cur_action.to_media_action(
                                 ui.ctx(),
@@ -113,9 +119,12 @@ pub(crate) fn image_carousel(
             .inner
     });
 
-    if show_popup {
-        if let Some((image_url, cache_type)) = current_image {
-            show_full_screen_media(ui, &image_url, cache_type, img_cache, carousel_id);
+    if show_popup && !gallery.is_empty() {
+        let index = current_index.min(gallery.len() - 1);
+        if let Some(viewer_action) =
+            show_full_screen_media(ui, &gallery, index, img_cache, carousel_id)
+        {
+            action = Some(viewer_action);
         }
     }
     action
@@ -167,41 +176,198 @@ impl MediaUIAction {
     }
 }
 
+/// The viewer that opens when an image is tapped. It covers the window and
+/// pages through every image in the note.
 fn show_full_screen_media(
     ui: &mut egui::Ui,
-    image_url: &str,
-    cache_type: MediaCacheType,
+    gallery: &[(String, MediaCacheType)],
+    index: usize,
     img_cache: &mut Images,
     carousel_id: egui::Id,
-) {
+) -> Option<MediaAction> {
+    let (image_url, cache_type) = &gallery[index];
+    let mut action = None;
+
     Window::new("image_popup")
         .title_bar(false)
         .fixed_size(ui.ctx().screen_rect().size())
         .fixed_pos(ui.ctx().screen_rect().min)
         .frame(egui::Frame::NONE)
         .show(ui.ctx(), |ui| {
-            ui.centered_and_justified(|ui| 's: {
-                let cur_state = get_render_state(
-                    ui.ctx(),
-                    img_cache,
-                    cache_type,
-                    image_url,
-                    ImageType::Content,
-                );
+            let media = ui
+                .centered_and_justified(|ui| {
+                    let cur_state = get_render_state(
+                        ui.ctx(),
+                        img_cache,
+                        *cache_type,
+                        image_url,
+                        ImageType::Content,
+                    );
+
+                    let notedeck::TextureState::Loaded(textured_image) = cur_state.texture_state
+                    else {
+                        return None;
+                    };
+
+                    Some(render_full_screen_media(
+                        ui,
+                        textured_image,
+                        cur_state.gifs,
+                        image_url,
+                        carousel_id,
+                    ))
+                })
+                .inner;
 
-                let notedeck::TextureState::Loaded(textured_image) = cur_state.texture_state else {
-                    break 's;
-                };
+            let controls = viewer_controls(ui, gallery, index, carousel_id);
+            action = controls.action;
+
+            let Some(media) = media else {
+                return;
+            };
+
+            if let Some(swipe) = media.swipe {
+                step_gallery(ui.ctx(), carousel_id, gallery.len(), index, swipe);
+            } else if media.response.clicked_elsewhere() && !controls.hovered {
+                close_viewer(ui.ctx(), carousel_id);
+            }
+        });
+
+    action
+}
+
+#[derive(Clone, Copy)]
+enum GalleryStep {
+    Previous,
+    Next,
+}
 
-                render_full_screen_media(
-                    ui,
-                    textured_image,
-                    cur_state.gifs,
-                    image_url,
-                    carousel_id,
+struct ViewerControls {
+    /// The pointer is over one of the buttons, so a click there shouldn't
+    /// close the viewer
+    hovered: bool,
+    action: Option<MediaAction>,
+}
+
+/// The counter, save/share and close buttons along the top, and arrows to
+/// page through the gallery
+fn viewer_controls(
+    ui: &mut egui::Ui,
+    gallery: &[(String, MediaCacheType)],
+    index: usize,
+    carousel_id: egui::Id,
+) -> ViewerControls {
+    let screen_rect = ui.ctx().screen_rect();
+    let (url, _) = &gallery[index];
+    let mut hovered = false;
+    let mut action = None;
+
+    let bar_rect = Rect::from_min_size(screen_rect.min, egui::vec2(screen_rect.width(), 48.0));
+    ui.painter()
+        .rect_filled(bar_rect, 0.0, Color32::from_black_alpha(120));
+    hovered |= ui.rect_contains_pointer(bar_rect);
+
+    ui.allocate_new_ui(UiBuilder::new().max_rect(bar_rect.shrink(8.0)), |ui| {
+        ui.horizontal_centered(|ui| {
+            if gallery.len() > 1 {
+                ui.label(
+                    RichText::new(format!("{} / {}", index + 1, gallery.len()))
+                        .color(Color32::WHITE),
                 );
-            })
+            }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if viewer_button(ui, "✕").on_hover_text("Close").clicked() {
+                    close_viewer(ui.ctx(), carousel_id);
+                }
+                if viewer_button(ui, "Save").clicked() {
+                    action = Some(MediaAction::Save {
+                        url: url.to_owned(),
+                    });
+                }
+                if viewer_button(ui, "Copy Link").clicked() {
+                    ui.ctx().copy_text(url.to_owned());
+                    notedeck::toast::info(ui.ctx(), "Link copied");
+                }
+                if viewer_button(ui, "Open").on_hover_text(url).clicked() {
+                    ui.ctx().open_url(egui::OpenUrl::new_tab(url));
+                }
+            });
         });
+    });
+
+    if gallery.len() > 1 {
+        let arrow_size = egui::vec2(48.0, 64.0);
+        let mid_y = screen_rect.center().y;
+        let mut step = None;
+
+        if index > 0 {
+            let rect =
+                Rect::from_center_size(egui::pos2(screen_rect.left() + 40.0, mid_y), arrow_size);
+            let resp = ui.put(rect, arrow_button("‹"));
+            hovered |= resp.hovered();
+            if resp.clicked() {
+                step = Some(GalleryStep::Previous);
+            }
+        }
+
+        if index + 1 < gallery.len() {
+            let rect =
+                Rect::from_center_size(egui::pos2(screen_rect.right() - 40.0, mid_y), arrow_size);
+            let resp = ui.put(rect, arrow_button("›"));
+            hovered |= resp.hovered();
+            if resp.clicked() {
+                step = Some(GalleryStep::Next);
+            }
+        }
+
+        if ui.input(|i| i.key_pressed(egui::Key::ArrowLeft)) {
+            step = Some(GalleryStep::Previous);
+        } else if ui.input(|i| i.key_pressed(egui::Key::ArrowRight)) {
+            step = Some(GalleryStep::Next);
+        }
+
+        if let Some(step) = step {
+            step_gallery(ui.ctx(), carousel_id, gallery.len(), index, step);
+        }
+    }
+
+    ViewerControls { hovered, action }
+}
+
+fn viewer_button(ui: &mut egui::Ui, label: &str) -> Response {
+    ui.add(Button::new(RichText::new(label).color(Color32::WHITE)).frame(false))
+}
+
+fn arrow_button(label: &str) -> Button<'static> {
+    Button::new(RichText::new(label).size(36.0).color(Color32::WHITE))
+        .fill(Color32::from_black_alpha(120))
+        .corner_radius(8.0)
+}
+
+fn close_viewer(ctx: &Context, carousel_id: egui::Id) {
+    ctx.memory_mut(|mem| {
+        mem.data.insert_temp(carousel_id.with("show_popup"), false);
+    });
+}
+
+/// Move to the neighbouring image, starting it out unzoomed
+fn step_gallery(ctx: &Context, carousel_id: egui::Id, len: usize, index: usize, step: GalleryStep) {
+    let next = match step {
+        GalleryStep::Previous => index.checked_sub(1),
+        GalleryStep::Next => (index + 1 < len).then_some(index + 1),
+    };
+    let Some(next) = next else {
+        return;
+    };
+
+    ctx.memory_mut(|mem| {
+        mem.data
+            .insert_temp(carousel_id.with("current_index"), next);
+        mem.data.remove::<f32>(carousel_id.with("zoom_level"));
+        mem.data
+            .remove::<egui::Vec2>(carousel_id.with("pan_offset"));
+    });
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -215,11 +381,19 @@ pub fn get_content_media_render_state<'a>(
     url: &'a str,
     cache_type: MediaCacheType,
     cache_dir: &Path,
+    blossom_servers: &[String],
     obfuscation_type: ObfuscationType<'a>,
 ) -> MediaRenderState<'a> {
     let render_type = if media_trusted {
         cache.handle_and_get_or_insert_loadable(url, || {
-            crate::images::fetch_img(cache_dir, ui.ctx(), url, ImageType::Content, cache_type)
+            crate::images::fetch_img_with_fallbacks(
+                cache_dir,
+                ui.ctx(),
+                url,
+                ImageType::Content,
+                cache_type,
+                blossom_servers,
+            )
         })
     } else if let Some(render_type) = cache.get_and_handle(url) {
         render_type
@@ -310,17 +484,15 @@ fn get_obfuscated<'a>(
 fn render_full_screen_media(
     ui: &mut egui::Ui,
     renderable_media: &mut TexturedImage,
-    gifs: &mut HashMap<String, GifState>,
+    gifs: &mut GifStateMap,
     image_url: &str,
     carousel_id: egui::Id,
-) {
+) -> FullScreenMedia {
     let screen_rect = ui.ctx().screen_rect();
 
     // escape
     if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
-        ui.ctx().memory_mut(|mem| {
-            mem.data.insert_temp(carousel_id.with("show_popup"), false);
-        });
+        close_viewer(ui.ctx(), carousel_id);
     }
 
     // background
@@ -339,11 +511,19 @@ fn render_full_screen_media(
         .ctx()
         .memory(|mem| mem.data.get_temp(pan_id).unwrap_or(egui::Vec2::ZERO));
 
-    // zoom & scroll
-    if ui.input(|i| i.pointer.hover_pos()).is_some() {
+    // zoom with a pinch, ctrl+scroll or plain scroll
+    if ui.input(|i| i.pointer.hover_pos()).is_some() || ui.input(|i| i.any_touches()) {
+        let pinch = ui.input(|i| i.zoom_delta());
         let scroll_delta = ui.input(|i| i.smooth_scroll_delta);
-        if scroll_delta.y != 0.0 {
-            let zoom_factor = if scroll_delta.y > 0.0 { 1.05 } else { 0.95 };
+        let zoom_factor = if pinch != 1.0 {
+            Some(pinch)
+        } else if scroll_delta.y != 0.0 {
+            Some(if scroll_delta.y > 0.0 { 1.05 } else { 0.95 })
+        } else {
+            None
+        };
+
+        if let Some(zoom_factor) = zoom_factor {
             zoom *= zoom_factor;
             zoom = zoom.clamp(0.1, 5.0);
 
@@ -360,7 +540,7 @@ fn render_full_screen_media(
 
     let texture = handle_repaint(
         ui,
-        retrieve_latest_texture(image_url, gifs, renderable_media),
+        retrieve_latest_texture(ui, image_url, gifs, renderable_media),
     );
 
     let texture_size = texture.size_vec2();
@@ -409,14 +589,26 @@ fn render_full_screen_media(
         .image(texture.id(), rect, uv, egui::Color32::WHITE);
     let img_rect = ui.allocate_rect(rect, Sense::click());
 
-    if img_rect.clicked() {
-        ui.ctx().memory_mut(|mem| {
-            mem.data.insert_temp(carousel_id.with("show_popup"), true);
-        });
-    } else if img_rect.clicked_elsewhere() {
-        ui.ctx().memory_mut(|mem| {
-            mem.data.insert_temp(carousel_id.with("show_popup"), false);
-        });
+    // when there's nothing to pan, a horizontal drag swipes to the next image
+    let swipe_id = carousel_id.with("swipe_distance");
+    let mut swipe = None;
+    if max_pan_x <= 0.0 && (response.dragged() || response.drag_stopped()) {
+        let distance = ui
+            .ctx()
+            .memory(|mem| mem.data.get_temp(swipe_id).unwrap_or(0.0_f32))
+            + response.drag_delta().x;
+
+        if response.drag_stopped() {
+            ui.ctx().memory_mut(|mem| mem.data.remove::<f32>(swipe_id));
+            if distance > SWIPE_DISTANCE {
+                swipe = Some(GalleryStep::Previous);
+            } else if distance < -SWIPE_DISTANCE {
+                swipe = Some(GalleryStep::Next);
+            }
+        } else {
+            ui.ctx()
+                .memory_mut(|mem| mem.data.insert_temp(swipe_id, distance));
+        }
     }
 
     // Handle dragging for pan
@@ -454,6 +646,19 @@ fn render_full_screen_media(
     }
 
     copy_link(image_url, response);
+
+    FullScreenMedia {
+        response: img_rect,
+        swipe,
+    }
+}
+
+/// How far a drag has to go to count as a swipe
+const SWIPE_DISTANCE: f32 = 80.0;
+
+struct FullScreenMedia {
+    response: Response,
+    swipe: Option<GalleryStep>,
 }
 
 fn copy_link(url: &str, img_resp: Response) {
@@ -471,13 +676,15 @@ fn render_media(
     gifs: &mut GifStateMap,
     render_state: MediaRenderState,
     url: &str,
-    cache_type: MediaCacheType,
+    alt: Option<&str>,
     height: f32,
+    placeholder: egui::Vec2,
     carousel_id: egui::Id,
+    index: usize,
 ) -> Option<MediaUIAction> {
     match render_state {
         MediaRenderState::ActualImage(image) => {
-            render_success_media(ui, url, image, gifs, cache_type, height, carousel_id);
+            render_success_media(ui, url, alt, image, gifs, height, carousel_id, index);
             None
         }
         MediaRenderState::Transitioning { image, obfuscation } => match obfuscation {
@@ -494,7 +701,7 @@ fn render_media(
             }
         },
         MediaRenderState::Error(e) => {
-            ui.allocate_space(egui::vec2(height, height));
+            ui.allocate_space(placeholder);
             show_one_error_message(ui, &format!("Could not render media {url}: {e}"));
             Some(MediaUIAction::Error)
         }
@@ -504,7 +711,7 @@ fn render_media(
                     shimmer_blurhash(texture_handle, ui, url, height);
                 }
                 ObfuscatedTexture::Default => {
-                    render_default_blur_bg(ui, height, url, true);
+                    render_default_blur_bg(ui, placeholder, url, true);
                 }
             }
             None
@@ -515,7 +722,7 @@ fn render_media(
                     let resp = ui.add(texture_to_image(texture_handle, height));
                     render_blur_text(ui, url, resp.rect)
                 }
-                ObfuscatedTexture::Default => render_default_blur(ui, height, url),
+                ObfuscatedTexture::Default => render_default_blur(ui, placeholder, url),
             };
 
             if resp
@@ -604,13 +811,18 @@ fn render_blur_text(ui: &mut egui::Ui, url: &str, render_rect: egui::Rect) -> eg
     helper.take_animation_response()
 }
 
-fn render_default_blur(ui: &mut egui::Ui, height: f32, url: &str) -> egui::Response {
-    let rect = render_default_blur_bg(ui, height, url, false);
+fn render_default_blur(ui: &mut egui::Ui, size: egui::Vec2, url: &str) -> egui::Response {
+    let rect = render_default_blur_bg(ui, size, url, false);
     render_blur_text(ui, url, rect)
 }
 
-fn render_default_blur_bg(ui: &mut egui::Ui, height: f32, url: &str, shimmer: bool) -> egui::Rect {
-    let (rect, _) = ui.allocate_exact_size(egui::vec2(height, height), egui::Sense::click());
+fn render_default_blur_bg(
+    ui: &mut egui::Ui,
+    size: egui::Vec2,
+    url: &str,
+    shimmer: bool,
+) -> egui::Rect {
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::click());
 
     let painter = ui.painter_at(rect);
 
@@ -626,10 +838,51 @@ fn render_default_blur_bg(ui: &mut egui::Ui, height: f32, url: &str, shimmer: bo
     rect
 }
 
+/// The size the media will take once loaded if the note's imeta tag told
+/// us, otherwise a square
+fn placeholder_size(obfuscation_type: &ObfuscationType, height: f32) -> egui::Vec2 {
+    match obfuscation_type {
+        ObfuscationType::Blurhash(blur) => blur.display_size(height),
+        ObfuscationType::Default => None,
+    }
+    .unwrap_or(egui::vec2(height, height))
+}
+
 pub(crate) struct RenderableMedia<'a> {
     url: &'a str,
     media_type: MediaCacheType,
     obfuscation_type: ObfuscationType<'a>,
+    alt: Option<&'a str>,
+}
+
+/// The `alt` descriptions in a note's imeta tags, by url
+pub(crate) fn imeta_alts<'a>(note: &'a Note) -> HashMap<&'a str, &'a str> {
+    let mut alts = HashMap::new();
+
+    for tag in note.tags() {
+        if tag.count() < 2 || tag.get_unchecked(0).variant().str() != Some("imeta") {
+            continue;
+        }
+
+        let mut url = None;
+        let mut alt = None;
+        for elem in tag.into_iter().skip(1) {
+            let Some((key, value)) = elem.str().and_then(|s| s.split_once(' ')) else {
+                continue;
+            };
+            match key {
+                "url" => url = Some(value.trim()),
+                "alt" => alt = Some(value.trim()).filter(|a| !a.is_empty()),
+                _ => {}
+            }
+        }
+
+        if let (Some(url), Some(alt)) = (url, alt) {
+            alts.insert(url, alt);
+        }
+    }
+
+    alts
 }
 
 pub enum MediaRenderState<'a> {
@@ -651,6 +904,7 @@ pub enum ObfuscatedTexture<'a> {
 pub(crate) fn find_renderable_media<'a>(
     urls: &mut UrlMimes,
     blurhashes: &'a HashMap<&'a str, Blur<'a>>,
+    alts: &HashMap<&'a str, &'a str>,
     url: &'a str,
 ) -> Option<RenderableMedia<'a>> {
     let media_type = supported_mime_hosted_at_url(urls, url)?;
@@ -664,35 +918,90 @@ pub(crate) fn find_renderable_media<'a>(
         url,
         media_type,
         obfuscation_type,
+        alt: alts.get(url).copied(),
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_success_media(
     ui: &mut egui::Ui,
     url: &str,
+    alt: Option<&str>,
     tex: &mut TexturedImage,
     gifs: &mut GifStateMap,
-    cache_type: MediaCacheType,
     height: f32,
     carousel_id: egui::Id,
+    index: usize,
 ) {
-    let texture = handle_repaint(ui, retrieve_latest_texture(url, gifs, tex));
+    let texture = handle_repaint(ui, retrieve_latest_texture(ui, url, gifs, tex));
     let img = texture_to_image(texture, height);
-    let img_resp = ui.add(Button::image(img).frame(false));
+    let mut img_resp = ui.add(Button::image(img).frame(false));
+    img_resp.widget_info(|| {
+        egui::WidgetInfo::labeled(egui::WidgetType::Image, true, alt.unwrap_or("Image"))
+    });
 
     if img_resp.clicked() {
         ui.ctx().memory_mut(|mem| {
             mem.data.insert_temp(carousel_id.with("show_popup"), true);
-            mem.data.insert_temp(
-                carousel_id.with("current_image"),
-                (url.to_owned(), cache_type),
-            );
+            mem.data
+                .insert_temp(carousel_id.with("current_index"), index);
+            mem.data.remove::<f32>(carousel_id.with("zoom_level"));
+            mem.data
+                .remove::<egui::Vec2>(carousel_id.with("pan_offset"));
         });
     }
 
+    if let Some(alt) = alt {
+        img_resp = alt_text_ui(ui, img_resp, url, alt);
+    }
+
     copy_link(url, img_resp);
 }
 
+/// Marks media that has a description. It shows on hover, and a long
+/// press keeps it up on touch screens.
+fn alt_text_ui(ui: &mut egui::Ui, resp: Response, url: &str, alt: &str) -> Response {
+    let id = egui::Id::new(("show_alt", url));
+    let mut pinned = ui.data(|d| d.get_temp::<bool>(id).unwrap_or(false));
+    if resp.long_touched() {
+        pinned = !pinned;
+        ui.data_mut(|d| d.insert_temp(id, pinned));
+    }
+
+    let rect = resp.rect;
+    let painter = ui.painter_at(rect);
+    let font = FontId::proportional(11.0);
+
+    let badge = painter.layout_no_wrap("ALT".to_owned(), font.clone(), Color32::WHITE);
+    let badge_rect = Rect::from_min_size(
+        rect.left_bottom() + egui::vec2(6.0, -6.0 - badge.size().y - 4.0),
+        badge.size() + egui::vec2(8.0, 4.0),
+    );
+    painter.rect_filled(
+        badge_rect,
+        CornerRadius::same(3),
+        Color32::from_black_alpha(180),
+    );
+    painter.galley(badge_rect.min + egui::vec2(4.0, 2.0), badge, Color32::WHITE);
+
+    if pinned {
+        let galley = painter.layout(alt.to_owned(), font, Color32::WHITE, rect.width() - 16.0);
+        let text_rect = Rect::from_min_max(
+            egui::pos2(rect.left(), rect.bottom() - galley.size().y - 16.0),
+            rect.right_bottom(),
+        );
+        painter.rect_filled(
+            text_rect,
+            CornerRadius::same(5),
+            Color32::from_black_alpha(200),
+        );
+        painter.galley(text_rect.min + egui::vec2(8.0, 8.0), galley, Color32::WHITE);
+        resp
+    } else {
+        resp.on_hover_text(alt)
+    }
+}
+
 fn texture_to_image(tex: &TextureHandle, max_height: f32) -> egui::Image {
     Image::new(tex)
         .max_height(max_height)
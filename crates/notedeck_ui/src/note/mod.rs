@@ -1,13 +1,20 @@
 pub mod contents;
 pub mod context;
+mod link_preview;
 pub mod media;
 pub mod options;
+mod paid;
+mod poll;
 pub mod reply_description;
+mod summary;
+mod tip_jar;
+mod video;
+mod zap_goal;
 
 use crate::jobs::JobsCache;
 use crate::{
-    profile::name::one_line_display_name_widget, widgets::x_button, ProfilePic, ProfilePreview,
-    PulseAlpha, Username,
+    contacts::pk1_is_following_pk2, profile::name::one_line_display_name_widget, widgets::x_button,
+    ProfileHoverCard, ProfilePic, ProfilePreview, PulseAlpha, Username,
 };
 
 pub use contents::{render_note_contents, render_note_preview, NoteContents};
@@ -24,8 +31,8 @@ use nostrdb::{Ndb, Note, NoteKey, ProfileRecord, Transaction};
 use notedeck::{
     name::get_display_name,
     note::{NoteAction, NoteContext, ZapAction},
-    AnyZapState, CachedNote, ContextSelection, NoteCache, NoteZapTarget, NoteZapTargetOwned,
-    NotedeckTextStyle, ZapTarget, Zaps,
+    AnyZapState, CachedNote, ContextSelection, CountKind, NoteCache, NoteCounts, NoteZapTarget,
+    NoteZapTargetOwned, NotedeckTextStyle, ZapTarget, Zaps,
 };
 
 pub struct NoteView<'a, 'd> {
@@ -36,6 +43,10 @@ pub struct NoteView<'a, 'd> {
     framed: bool,
     flags: NoteOptions,
     jobs: &'a mut JobsCache,
+
+    /// Everyone who reposted the note, when a timeline shows their reposts
+    /// as one
+    reposted_by: &'a [[u8; 32]],
 }
 
 pub struct NoteResponse {
@@ -93,9 +104,15 @@ impl<'a, 'd> NoteView<'a, 'd> {
             flags,
             framed,
             jobs,
+            reposted_by: &[],
         }
     }
 
+    pub fn reposted_by(mut self, reposters: &'a [[u8; 32]]) -> Self {
+        self.reposted_by = reposters;
+        self
+    }
+
     pub fn preview_style(self) -> Self {
         self.actionbar(false)
             .small_pfp(true)
@@ -266,7 +283,7 @@ impl<'a, 'd> NoteView<'a, 'd> {
                 );
 
                 let mut pfp = ProfilePic::new(self.note_context.img_cache, pic).size(size);
-                let pfp_resp = ui.put(rect, &mut pfp);
+                ui.put(rect, &mut pfp);
 
                 action = action.or(pfp.action);
 
@@ -274,14 +291,6 @@ impl<'a, 'd> NoteView<'a, 'd> {
                     crate::show_pointer(ui);
                 }
 
-                pfp_resp.on_hover_ui_at_pointer(|ui| {
-                    ui.set_max_width(300.0);
-                    ui.add(ProfilePreview::new(
-                        profile.as_ref().unwrap(),
-                        self.note_context.img_cache,
-                    ));
-                });
-
                 resp
             }
 
@@ -318,16 +327,30 @@ impl<'a, 'd> NoteView<'a, 'd> {
                     ui.add_sized([20.0, 20.0], repost_icon(ui.visuals().dark_mode));
                 });
                 ui.add_space(6.0);
-                let resp = ui.add(one_line_display_name_widget(
-                    ui.visuals(),
-                    get_display_name(profile.as_ref().ok()),
-                    style,
-                ));
-                if let Ok(rec) = &profile {
-                    resp.on_hover_ui_at_pointer(|ui| {
-                        ui.set_max_width(300.0);
-                        ui.add(ProfilePreview::new(rec, self.note_context.img_cache));
-                    });
+                if self.reposted_by.len() > 1 {
+                    ui.add(
+                        Label::new(
+                            RichText::new(reposters_text(
+                                self.note_context.ndb,
+                                txn,
+                                self.reposted_by,
+                            ))
+                            .text_style(style.text_style()),
+                        )
+                        .truncate(),
+                    );
+                } else {
+                    let resp = ui.add(one_line_display_name_widget(
+                        ui.visuals(),
+                        get_display_name(profile.as_ref().ok()),
+                        style,
+                    ));
+                    if let Ok(rec) = &profile {
+                        resp.on_hover_ui_at_pointer(|ui| {
+                            ui.set_max_width(300.0);
+                            ui.add(ProfilePreview::new(rec, self.note_context.img_cache));
+                        });
+                    }
                 }
                 let color = ui.style().visuals.noninteractive().fg_stroke.color;
                 ui.add_space(4.0);
@@ -351,6 +374,12 @@ impl<'a, 'd> NoteView<'a, 'd> {
     }
 
     pub fn show(&mut self, ui: &mut egui::Ui) -> NoteResponse {
+        // quotes, replies and threads look notes up by id, and nostrdb
+        // still has the ones that expired
+        if notedeck::note::is_expired(self.note) {
+            return NoteResponse::new(ui.weak("This note has expired"));
+        }
+
         if self.options().has_textmode() {
             NoteResponse::new(self.textmode_ui(ui))
         } else if self.framed {
@@ -371,21 +400,62 @@ impl<'a, 'd> NoteView<'a, 'd> {
     }
 
     #[profiling::function]
+    /// The author's name and the note's age. Returns the name's response.
     fn note_header(
         ui: &mut egui::Ui,
         note_cache: &mut NoteCache,
         note: &Note,
         profile: &Result<nostrdb::ProfileRecord<'_>, nostrdb::Error>,
-    ) {
+    ) -> egui::Response {
         let note_key = note.key().unwrap();
 
         ui.horizontal(|ui| {
             ui.spacing_mut().item_spacing.x = 2.0;
-            ui.add(Username::new(profile.as_ref().ok(), note.pubkey()).abbreviated(20));
+            let name_resp =
+                ui.add(Username::new(profile.as_ref().ok(), note.pubkey()).abbreviated(20));
 
             let cached_note = note_cache.cached_note_or_insert_mut(note_key, note);
             render_reltime(ui, cached_note, true);
+
+            name_resp
+        })
+        .inner
+    }
+
+    /// Show the author's hover card when `resp` is hovered, on desktop.
+    /// Returns a follow or unfollow from the card's button.
+    fn author_hover_card(
+        &mut self,
+        resp: &Response,
+        txn: &Transaction,
+        profile: &Result<ProfileRecord<'_>, nostrdb::Error>,
+    ) -> Option<NoteAction> {
+        if notedeck::ui::is_compiled_as_mobile() {
+            return None;
+        }
+        let profile = profile.as_ref().ok()?;
+
+        let author = self.note.pubkey();
+        let acting = self.zapping_acc.map(|acc| acc.pubkey);
+        let can_follow = self.note_context.current_account_can_sign
+            && acting.is_some_and(|pk| pk.bytes() != author);
+
+        let mut action = None;
+        resp.clone().on_hover_ui(|ui| {
+            let ndb = self.note_context.ndb;
+            let followers = self
+                .note_context
+                .counts
+                .get(CountKind::Followers(Pubkey::new(*author)));
+            let following =
+                acting.and_then(|pk| pk1_is_following_pk2(ndb, txn, pk.bytes(), author));
+
+            action = ProfileHoverCard::new(profile, author, self.note_context.img_cache, followers)
+                .following(following, can_follow)
+                .show(ui);
         });
+
+        action.map(NoteAction::Contact)
     }
 
     fn wide_ui(
@@ -405,23 +475,32 @@ impl<'a, 'd> NoteView<'a, 'd> {
                 } else if let Some(action) = action {
                     note_action = Some(NoteAction::Media(action));
                 };
+                if let Some(action) = self.author_hover_card(&pfp_resp, txn, profile) {
+                    note_action = Some(action);
+                }
 
                 let size = ui.available_size();
                 ui.vertical(|ui| {
+                    let mut name_resp = None;
                     ui.add_sized(
                         [size.x, self.options().pfp_size() as f32],
                         |ui: &mut egui::Ui| {
                             ui.horizontal_centered(|ui| {
-                                NoteView::note_header(
+                                name_resp = Some(NoteView::note_header(
                                     ui,
                                     self.note_context.note_cache,
                                     self.note,
                                     profile,
-                                );
+                                ));
                             })
                             .response
                         },
                     );
+                    if let Some(action) =
+                        name_resp.and_then(|resp| self.author_hover_card(&resp, txn, profile))
+                    {
+                        note_action = Some(action);
+                    }
 
                     let note_reply = self
                         .note_context
@@ -468,15 +547,21 @@ impl<'a, 'd> NoteView<'a, 'd> {
             }
 
             if self.options().has_actionbar() {
+                let counts = self.note_context.counts.note_counts(self.note.id());
+                if let Some(action) = self.reply_participants(ui, txn, counts.replies) {
+                    note_action = Some(action);
+                }
                 if let Some(action) = render_note_actionbar(
                     ui,
                     self.zapping_acc.as_ref().map(|c| Zapper {
                         zaps: self.note_context.zaps,
                         cur_acc: c,
+                        can_sign: self.note_context.current_account_can_sign,
                     }),
                     self.note.id(),
                     self.note.pubkey(),
                     note_key,
+                    counts,
                 )
                 .inner
                 {
@@ -504,9 +589,16 @@ impl<'a, 'd> NoteView<'a, 'd> {
             } else if let Some(action) = action {
                 note_action = Some(NoteAction::Media(action));
             };
+            if let Some(action) = self.author_hover_card(&pfp_resp, txn, profile) {
+                note_action = Some(action);
+            }
 
             ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
-                NoteView::note_header(ui, self.note_context.note_cache, self.note, profile);
+                let name_resp =
+                    NoteView::note_header(ui, self.note_context.note_cache, self.note, profile);
+                if let Some(action) = self.author_hover_card(&name_resp, txn, profile) {
+                    note_action = Some(action);
+                }
                 ui.horizontal(|ui| {
                     ui.spacing_mut().item_spacing.x = 2.0;
 
@@ -549,15 +641,21 @@ impl<'a, 'd> NoteView<'a, 'd> {
                 }
 
                 if self.options().has_actionbar() {
+                    let counts = self.note_context.counts.note_counts(self.note.id());
+                    if let Some(action) = self.reply_participants(ui, txn, counts.replies) {
+                        note_action = Some(action);
+                    }
                     if let Some(action) = render_note_actionbar(
                         ui,
                         self.zapping_acc.as_ref().map(|c| Zapper {
                             zaps: self.note_context.zaps,
                             cur_acc: c,
+                            can_sign: self.note_context.current_account_can_sign,
                         }),
                         self.note.id(),
                         self.note.pubkey(),
                         note_key,
+                        counts,
                     )
                     .inner
                     {
@@ -571,6 +669,95 @@ impl<'a, 'd> NoteView<'a, 'd> {
         })
     }
 
+    /// Overlapping avatars of the latest repliers and the reply count.
+    /// Clicking it opens the thread.
+    fn reply_participants(
+        &mut self,
+        ui: &mut egui::Ui,
+        txn: &Transaction,
+        replies: u64,
+    ) -> Option<NoteAction> {
+        if replies == 0 || !self.options().has_reply_participants() {
+            return None;
+        }
+
+        let participants = self
+            .note_context
+            .counts
+            .reply_participants(self.note.id(), self.note.pubkey())
+            .to_vec();
+        if participants.is_empty() {
+            return None;
+        }
+
+        let pfp_size = 18.0;
+        let overlap = 6.0;
+        let resp = ui
+            .horizontal(|ui| {
+                let width = pfp_size + (pfp_size - overlap) * (participants.len() - 1) as f32;
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(width, pfp_size), Sense::hover());
+
+                // oldest first, so the newest ends up on top
+                for (i, pubkey) in participants.iter().enumerate().rev() {
+                    let profile = self
+                        .note_context
+                        .ndb
+                        .get_profile_by_pubkey(txn, pubkey.bytes())
+                        .ok();
+                    let min = rect.min + egui::vec2((pfp_size - overlap) * i as f32, 0.0);
+                    let pfp_rect = Rect::from_min_size(min, egui::vec2(pfp_size, pfp_size));
+                    ui.put(
+                        pfp_rect,
+                        &mut ProfilePic::new(
+                            self.note_context.img_cache,
+                            notedeck::profile::get_profile_url(profile.as_ref()),
+                        )
+                        .size(pfp_size)
+                        .border(egui::Stroke::new(1.5, ui.visuals().panel_fill)),
+                    );
+                }
+
+                let label = if replies == 1 {
+                    "1 reply".to_owned()
+                } else {
+                    format!("{replies} replies")
+                };
+                ui.add(
+                    Label::new(
+                        RichText::new(label)
+                            .text_style(NotedeckTextStyle::Small.text_style())
+                            .color(ui.visuals().weak_text_color()),
+                    )
+                    .selectable(false),
+                );
+            })
+            .response
+            .interact(Sense::click());
+
+        if resp.hovered() {
+            crate::show_pointer(ui);
+        }
+
+        resp.clicked()
+            .then(|| NoteAction::Note(NoteId::new(*self.note.id())))
+    }
+
+    /// Whether the note mentions the selected account, other than in
+    /// its own notes
+    fn mentions_me(&mut self, note_key: NoteKey) -> bool {
+        let Some(me) = self.zapping_acc.map(|acc| acc.pubkey.bytes()) else {
+            return false;
+        };
+        if self.note.pubkey() == me {
+            return false;
+        }
+
+        self.note_context
+            .note_cache
+            .cached_note_or_insert(note_key, self.note)
+            .mentions(me)
+    }
+
     #[profiling::function]
     fn show_standard(&mut self, ui: &mut egui::Ui) -> NoteResponse {
         let note_key = self.note.key().expect("todo: support non-db notes");
@@ -584,15 +771,35 @@ impl<'a, 'd> NoteView<'a, 'd> {
         let hitbox_id = note_hitbox_id(note_key, self.options(), self.parent);
         let maybe_hitbox = maybe_note_hitbox(ui, hitbox_id);
 
-        // wide design
-        let response = if self.options().has_wide() {
-            self.wide_ui(ui, txn, note_key, &profile)
-        } else {
-            self.standard_ui(ui, txn, note_key, &profile)
-        };
+        // painted under the note once we know how big it is
+        let highlight = ui.painter().add(egui::Shape::Noop);
+        let mentions_me = self.mentions_me(note_key);
+        let author_color = self.note_context.account_colors.get(self.note.pubkey());
+
+        let summary = self
+            .options()
+            .has_summary()
+            .then(|| summary::note_summary(self.note_context, txn, self.note, note_key));
+
+        let response = summary::with_summary(ui, hitbox_id.with("summary"), summary, |ui| {
+            // wide design
+            if self.options().has_wide() {
+                self.wide_ui(ui, txn, note_key, &profile)
+            } else {
+                self.standard_ui(ui, txn, note_key, &profile)
+            }
+        });
 
         let mut note_action = response.inner;
 
+        if mentions_me {
+            ui.painter()
+                .set(highlight, mention_highlight(ui, response.response.rect));
+        } else if let Some(color) = author_color {
+            ui.painter()
+                .set(highlight, author_marker(response.response.rect, color));
+        }
+
         if self.options().has_options_button() {
             let context_pos = {
                 let size = NoteContextButton::max_width();
@@ -602,7 +809,9 @@ impl<'a, 'd> NoteView<'a, 'd> {
             };
 
             let resp = ui.add(NoteContextButton::new(note_key).place_at(context_pos));
-            if let Some(action) = NoteContextButton::menu(ui, resp.clone()) {
+            if let Some(action) =
+                NoteContextButton::menu(ui, resp.clone(), self.note.kind(), self.note_context.pool)
+            {
                 note_action = Some(NoteAction::Context(ContextSelection { note_key, action }));
             }
         }
@@ -618,6 +827,41 @@ impl<'a, 'd> NoteView<'a, 'd> {
     }
 }
 
+/// A faint fill and an accent down the left edge for notes that mention
+/// the selected account
+fn mention_highlight(ui: &egui::Ui, rect: Rect) -> egui::Shape {
+    let accent = ui.visuals().selection.bg_fill;
+    let rect = rect.expand2(egui::vec2(4.0, 2.0));
+    let bar = Rect::from_min_max(rect.left_top(), pos2(rect.left() + 2.0, rect.bottom()));
+
+    egui::Shape::Vec(vec![
+        egui::Shape::rect_filled(rect, 4.0, accent.gamma_multiply(0.08)),
+        egui::Shape::rect_filled(bar, 1.0, accent),
+    ])
+}
+
+/// A stripe in the account's color down the left edge of notes written by
+/// one of the logged in accounts
+fn author_marker(rect: Rect, color: egui::Color32) -> egui::Shape {
+    let rect = rect.expand2(egui::vec2(4.0, 2.0));
+    let bar = Rect::from_min_max(rect.left_top(), pos2(rect.left() + 2.0, rect.bottom()));
+    egui::Shape::rect_filled(bar, 1.0, color)
+}
+
+/// "A, B and 3 others", the newest of `reposters` first
+fn reposters_text(ndb: &Ndb, txn: &Transaction, reposters: &[[u8; 32]]) -> String {
+    let profiles: Vec<_> = reposters
+        .iter()
+        .take(2)
+        .map(|pubkey| ndb.get_profile_by_pubkey(txn, pubkey).ok())
+        .collect();
+    let names: Vec<&str> = profiles
+        .iter()
+        .map(|profile| get_display_name(profile.as_ref()).name())
+        .collect();
+    notedeck::name::summarize_names(&names, reposters.len())
+}
+
 fn get_reposted_note<'a>(ndb: &Ndb, txn: &'a Transaction, note: &Note) -> Option<Note<'a>> {
     let new_note_id: &[u8; 32] = if note.kind() == 6 {
         let mut res = None;
@@ -693,6 +937,10 @@ fn note_hitbox_clicked(
 struct Zapper<'a> {
     zaps: &'a Zaps,
     cur_acc: &'a KeypairUnowned<'a>,
+
+    /// Zap requests can also be signed by a remote signer, so this isn't
+    /// the same as `cur_acc` having a secret key
+    can_sign: bool,
 }
 
 #[profiling::function]
@@ -702,10 +950,13 @@ fn render_note_actionbar(
     note_id: &[u8; 32],
     note_pubkey: &[u8; 32],
     note_key: NoteKey,
+    counts: NoteCounts,
 ) -> egui::InnerResponse<Option<NoteAction>> {
     ui.horizontal(|ui| 's: {
         let reply_resp = reply_button(ui, note_key);
+        action_count(ui, counts.replies);
         let quote_resp = quote_repost_button(ui, note_key);
+        action_count(ui, counts.reposts);
 
         let to_noteid = |id: &[u8; 32]| NoteId::new(*id);
         if reply_resp.clicked() {
@@ -720,7 +971,12 @@ fn render_note_actionbar(
             crate::show_pointer(ui);
         }
 
-        let Some(Zapper { zaps, cur_acc }) = zapper else {
+        let Some(Zapper {
+            zaps,
+            cur_acc,
+            can_sign,
+        }) = zapper
+        else {
             break 's None;
         };
 
@@ -740,9 +996,11 @@ fn render_note_actionbar(
             break 's Some(NoteAction::Zap(ZapAction::ClearError(target)));
         }
 
-        let zap_resp = {
-            cur_acc.secret_key.as_ref()?;
+        if !can_sign {
+            break 's None;
+        }
 
+        let zap_resp = {
             match zap_state {
                 Ok(any_zap_state) => ui.add(zap_button(any_zap_state, note_id)),
                 Err(err) => {
@@ -772,6 +1030,32 @@ fn render_note_actionbar(
     })
 }
 
+/// A mark in the corner of the zap icon so its state doesn't come down to
+/// color alone: a ring while it's unconfirmed, a dot once it's confirmed
+fn zap_state_badge(ui: &egui::Ui, icon: Rect, state: &AnyZapState, color: egui::Color32) {
+    let radius = 2.5;
+    let center = icon.right_bottom() - egui::vec2(radius, radius);
+    let painter = ui.painter();
+    match state {
+        AnyZapState::None | AnyZapState::Pending => {}
+        AnyZapState::LocalOnly => {
+            painter.circle_stroke(center, radius, egui::Stroke::new(1.0, color));
+        }
+        AnyZapState::Confirmed => {
+            painter.circle_filled(center, radius, color);
+        }
+    }
+}
+
+/// The number next to an action button, hidden when there's nothing yet
+fn action_count(ui: &mut egui::Ui, count: u64) {
+    if count == 0 {
+        return;
+    }
+
+    secondary_label(ui, count.to_string());
+}
+
 fn secondary_label(ui: &mut egui::Ui, s: impl Into<String>) {
     let color = ui.style().visuals.noninteractive().fg_stroke.color;
     ui.add(Label::new(RichText::new(s).size(10.0).color(color)));
@@ -848,6 +1132,7 @@ fn zap_button(state: AnyZapState, noteid: &[u8; 32]) -> impl egui::Widget + use<
         let mut img = egui::Image::new(img_data).max_width(size);
         let id = ui.id().with(("pulse", noteid));
         let ctx = ui.ctx().clone();
+        let zap_color = notedeck::indicators::colors(&ctx).zap;
 
         match state {
             AnyZapState::None => {
@@ -861,13 +1146,11 @@ fn zap_button(state: AnyZapState, noteid: &[u8; 32]) -> impl egui::Widget + use<
                     .with_speed(0.35)
                     .animate();
 
-                let cur_color = egui::Color32::from_rgba_unmultiplied(0xFF, 0xB7, 0x57, cur_alpha);
-                img = img.tint(cur_color);
+                img = img.tint(zap_color.gamma_multiply(cur_alpha as f32 / 255.0));
             }
-            AnyZapState::LocalOnly => {
-                img = img.tint(egui::Color32::from_rgb(0xFF, 0xB7, 0x57));
+            AnyZapState::LocalOnly | AnyZapState::Confirmed => {
+                img = img.tint(zap_color);
             }
-            AnyZapState::Confirmed => {}
         }
 
         // align rect to note contents
@@ -875,7 +1158,14 @@ fn zap_button(state: AnyZapState, noteid: &[u8; 32]) -> impl egui::Widget + use<
         let rect = rect.translate(egui::vec2(-(expand_size / 2.0), 0.0));
 
         let put_resp = ui.put(rect, img);
+        zap_state_badge(ui, rect, &state, zap_color);
 
-        resp.union(put_resp)
+        let hover = match state {
+            AnyZapState::None => "Zap. Right-click to pick an amount.",
+            AnyZapState::Pending => "Sending zap…",
+            AnyZapState::LocalOnly => "Zap sent, waiting for the receipt",
+            AnyZapState::Confirmed => "Zapped",
+        };
+        resp.union(put_resp).on_hover_text(hover)
     }
 }
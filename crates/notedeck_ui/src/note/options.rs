@@ -22,9 +22,17 @@ bitflags! {
         /// Whether the current note is a preview
         const is_preview      = 0b0000010000000000;
 
-        /// Is the content truncated? If the length is over a certain size it
-        /// will end with a ... and a "Show more" button.
+        /// Is the content truncated? Text past the line limit in
+        /// [`notedeck::NoteContext`] is cut off with a "Show more" link.
         const truncate        = 0b0000100000000000;
+
+        /// Show who's been replying, for notes with replies. Off in
+        /// threads, where the replies are right there.
+        const reply_participants = 0b0001000000000000;
+
+        /// Describe the note to screen readers in one line and keep the
+        /// widgets it's made of out of their way
+        const summary         = 0b0010000000000000;
     }
 }
 
@@ -34,6 +42,7 @@ impl Default for NoteOptions {
             | NoteOptions::note_previews
             | NoteOptions::actionbar
             | NoteOptions::truncate
+            | NoteOptions::reply_participants
     }
 }
 
@@ -68,6 +77,12 @@ impl NoteOptions {
     create_bit_methods!(set_scramble_text, has_scramble_text, scramble_text);
     create_bit_methods!(set_is_preview, has_is_preview, is_preview);
     create_bit_methods!(set_truncate, has_truncate, truncate);
+    create_bit_methods!(
+        set_reply_participants,
+        has_reply_participants,
+        reply_participants
+    );
+    create_bit_methods!(set_summary, has_summary, summary);
 
     pub fn new(is_universe_timeline: bool) -> Self {
         let mut options = NoteOptions::default();
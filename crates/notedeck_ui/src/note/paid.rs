@@ -0,0 +1,100 @@
+use egui::RichText;
+use enostr::{NoteId, Pubkey};
+use nostrdb::Note;
+use notedeck::note::ZapTargetAmount;
+use notedeck::paid::{Paywall, Unlock};
+use notedeck::{NoteAction, NoteContext, NoteZapTargetOwned, ZapAction};
+
+/// Below a paid note's teaser: a button to buy the rest with a zap, and
+/// the full content once it's been unlocked
+pub fn paywall_ui(
+    ui: &mut egui::Ui,
+    note_context: &mut NoteContext,
+    note: &Note,
+    paywall: &Paywall,
+) -> Option<NoteAction> {
+    let note_id = NoteId::new(*note.id());
+    let target = NoteZapTargetOwned {
+        note_id,
+        zap_recipient: Pubkey::new(*note.pubkey()),
+    };
+    let mut action = None;
+
+    ui.add_space(4.0);
+    ui.vertical(|ui| {
+        ui.spacing_mut().item_spacing.y = 4.0;
+
+        match note_context.paid_content.state(&note_id) {
+            Some(Unlock::Unlocked(content)) => {
+                ui.separator();
+                ui.add(egui::Label::new(content).wrap().selectable(true));
+                return;
+            }
+            Some(Unlock::Paying) => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.weak("Paying…");
+                });
+                return;
+            }
+            Some(Unlock::Fetching) => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.weak("Unlocking…");
+                });
+                return;
+            }
+            Some(Unlock::Failed(err)) => {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("Couldn't unlock: {err}"),
+                );
+                if ui.small_button("Try again").clicked() {
+                    note_context.paid_content.retry(&note_id);
+                    // a failed zap sticks around until it's cleared
+                    action = Some(NoteAction::Zap(ZapAction::ClearError(target.clone())));
+                }
+                return;
+            }
+            None => {}
+        }
+
+        let Some(buyer) = note_context.acting_pubkey else {
+            return;
+        };
+
+        let paid = note_context.paid_content.is_paid(&buyer, &note_id);
+        let label = if paid {
+            "🔓 Unlock, already paid".to_owned()
+        } else {
+            format!("🔒 Unlock for {} sats ⚡", paywall.price_msats / 1000)
+        };
+        let resp = ui
+            .add_enabled(
+                (paid || note_context.current_account_has_wallet)
+                    && note_context.current_account_can_sign,
+                egui::Button::new(RichText::new(label).strong()),
+            )
+            .on_hover_text(if paid {
+                "Fetch the rest again"
+            } else {
+                "Zap the author to read the rest"
+            })
+            .on_disabled_hover_text("Connect a wallet to unlock");
+        if resp.hovered() {
+            crate::show_pointer(ui);
+        }
+        if resp.clicked()
+            && note_context
+                .paid_content
+                .buy(buyer, target.clone(), paywall.clone())
+        {
+            action = Some(NoteAction::Zap(ZapAction::Send(ZapTargetAmount {
+                target,
+                specified_msats: Some(paywall.price_msats),
+            })));
+        }
+    });
+
+    action
+}
@@ -0,0 +1,162 @@
+use egui::{Color32, Sense};
+use enostr::NoteId;
+use nostrdb::{Note, Transaction};
+use notedeck::poll::{Poll, PollTally};
+use notedeck::{time_ago_since, NoteAction, NoteContext};
+
+const BAR_HEIGHT: f32 = 28.0;
+
+/// A poll's options, as buttons to vote with until the current account
+/// has voted or the poll closes, and as a bar chart of the results after
+pub fn poll_ui(
+    ui: &mut egui::Ui,
+    note_context: &mut NoteContext,
+    txn: &Transaction,
+    note: &Note,
+    poll: &Poll,
+) -> Option<NoteAction> {
+    let tally = note_context.polls.tally(
+        note_context.ndb,
+        txn,
+        note.id(),
+        poll,
+        note_context.acting_pubkey.as_ref(),
+    );
+
+    let closed = poll.is_closed();
+    let can_vote = !closed && tally.mine.is_empty() && note_context.current_account_can_sign;
+    let mut action = None;
+
+    ui.add_space(4.0);
+    ui.vertical(|ui| {
+        ui.spacing_mut().item_spacing.y = 4.0;
+
+        if can_vote {
+            action = vote_ui(ui, note, poll);
+        } else {
+            results_ui(ui, poll, &tally);
+        }
+
+        let mut footer = match tally.voters {
+            1 => "1 vote".to_owned(),
+            n => format!("{n} votes"),
+        };
+        match poll.ends_at {
+            Some(_) if closed => footer.push_str(" · Closed"),
+            Some(at) => footer.push_str(&format!(
+                " · Closes in {}",
+                time_ago_since(at).trim_start_matches('+')
+            )),
+            None => {}
+        }
+        if poll.multiple_choice {
+            footer.push_str(" · Pick any");
+        }
+        ui.weak(footer);
+    });
+
+    action
+}
+
+fn vote_ui(ui: &mut egui::Ui, note: &Note, poll: &Poll) -> Option<NoteAction> {
+    let vote = |options| NoteAction::Vote {
+        poll: NoteId::new(*note.id()),
+        options,
+    };
+
+    if !poll.multiple_choice {
+        for option in &poll.options {
+            if option_button(ui, &option.label).clicked() {
+                return Some(vote(vec![option.id.clone()]));
+            }
+        }
+        return None;
+    }
+
+    // picks are held until they hit vote
+    let picks_id = egui::Id::new(("poll_picks", note.id()));
+    let mut picks: Vec<String> = ui.data(|d| d.get_temp(picks_id).unwrap_or_default());
+    for option in &poll.options {
+        let mut picked = picks.contains(&option.id);
+        if ui.checkbox(&mut picked, &option.label).changed() {
+            if picked {
+                picks.push(option.id.clone());
+            } else {
+                picks.retain(|id| id != &option.id);
+            }
+        }
+    }
+
+    let send = ui
+        .add_enabled(!picks.is_empty(), egui::Button::new("Vote"))
+        .clicked();
+    if send {
+        ui.data_mut(|d| d.remove::<Vec<String>>(picks_id));
+        Some(vote(picks))
+    } else {
+        ui.data_mut(|d| d.insert_temp(picks_id, picks));
+        None
+    }
+}
+
+fn option_button(ui: &mut egui::Ui, label: &str) -> egui::Response {
+    let resp = ui.add_sized(
+        [ui.available_width(), BAR_HEIGHT],
+        egui::Button::new(label).corner_radius(6.0),
+    );
+    if resp.hovered() {
+        crate::show_pointer(ui);
+    }
+    resp
+}
+
+fn results_ui(ui: &mut egui::Ui, poll: &Poll, tally: &PollTally) {
+    let most = tally.votes.iter().copied().max().unwrap_or(0);
+
+    for (option, votes) in poll.options.iter().zip(&tally.votes) {
+        let share = if tally.voters == 0 {
+            0.0
+        } else {
+            *votes as f32 / tally.voters as f32
+        };
+
+        let (rect, _) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), BAR_HEIGHT), Sense::hover());
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+
+        let leading = *votes > 0 && *votes == most;
+        let fill = if leading {
+            visuals.selection.bg_fill
+        } else {
+            visuals.widgets.inactive.bg_fill
+        };
+        painter.rect_filled(rect, 6.0, visuals.faint_bg_color);
+        let mut bar = rect;
+        bar.set_width(rect.width() * share);
+        painter.rect_filled(bar, 6.0, fill);
+
+        let label = if tally.mine.contains(&option.id) {
+            format!("✓ {}", option.label)
+        } else {
+            option.label.clone()
+        };
+        let font = egui::TextStyle::Body.resolve(ui.style());
+        let text_color = visuals.text_color();
+        let inner = rect.shrink2(egui::vec2(8.0, 0.0));
+        painter.text(
+            inner.left_center(),
+            egui::Align2::LEFT_CENTER,
+            label,
+            font.clone(),
+            text_color,
+        );
+        painter.text(
+            inner.right_center(),
+            egui::Align2::RIGHT_CENTER,
+            format!("{:.0}%", share * 100.0),
+            font,
+            if leading { text_color } else { Color32::GRAY },
+        );
+    }
+}
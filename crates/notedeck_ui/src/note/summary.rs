@@ -0,0 +1,177 @@
+//! Notes as screen readers hear them when summaries are on. A note is drawn
+//! with dozens of widgets, and a screen reader walks them in whatever order
+//! they were laid out. With summaries, the note is one node with a label
+//! like "alice, 2 hours ago, replying to bob: gm. 5 reactions, 2 zaps" and
+//! the widgets are hidden under it.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use egui::accesskit::Role;
+use enostr::NoteId;
+use nostrdb::{BlockType, Mention, Ndb, Note, NoteKey, Transaction};
+use notedeck::{name::get_display_name, CountKind, NoteContext};
+
+/// Longest the note's content gets in a summary, in chars
+const MAX_CONTENT_CHARS: usize = 300;
+
+/// One line describing `note`
+pub fn note_summary(
+    note_context: &mut NoteContext,
+    txn: &Transaction,
+    note: &Note,
+    note_key: NoteKey,
+) -> String {
+    let ndb = note_context.ndb;
+    let mut summary = format!(
+        "{}, {}",
+        name_of(ndb, txn, note.pubkey()),
+        spoken_time_ago(note.created_at())
+    );
+
+    let note_reply = note_context
+        .note_cache
+        .cached_note_or_insert_mut(note_key, note)
+        .reply
+        .borrow(note.tags());
+    if let Some(reply) = note_reply.reply() {
+        let to = match ndb.get_note_by_id(txn, reply.id) {
+            Ok(parent) => name_of(ndb, txn, parent.pubkey()),
+            Err(_) => "a note".to_owned(),
+        };
+        summary.push_str(", replying to ");
+        summary.push_str(&to);
+    }
+
+    summary.push_str(": ");
+    summary.push_str(&spoken_content(ndb, txn, note, note_key));
+
+    let id = NoteId::new(*note.id());
+    let counts = note_context.counts.note_counts(note.id());
+    let reactions = note_context.counts.get(CountKind::Reactions(id));
+    let zaps = note_context.counts.get(CountKind::Zaps(id));
+    let received: Vec<String> = [
+        (counts.replies, "reply", "replies"),
+        (counts.reposts, "repost", "reposts"),
+        (reactions, "reaction", "reactions"),
+        (zaps, "zap", "zaps"),
+    ]
+    .into_iter()
+    .filter(|(count, _, _)| *count > 0)
+    .map(|(count, one, many)| {
+        if count == 1 {
+            format!("1 {one}")
+        } else {
+            format!("{count} {many}")
+        }
+    })
+    .collect();
+    if !received.is_empty() {
+        summary.push_str(". ");
+        summary.push_str(&received.join(", "));
+    }
+
+    summary
+}
+
+/// Add the note's widgets with `summary` standing in for them. Without a
+/// summary, or a screen reader to hear it, they're just added.
+pub fn with_summary<R>(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    summary: Option<String>,
+    add_contents: impl FnOnce(&mut egui::Ui) -> R,
+) -> R {
+    let Some(summary) = summary else {
+        return add_contents(ui);
+    };
+
+    let ctx = ui.ctx().clone();
+    ctx.accesskit_node_builder(id, |node| {
+        node.set_role(Role::Article);
+        node.set_label(summary);
+    });
+
+    let parts = id.with("parts");
+    let mut inner = None;
+    ctx.with_accessibility_parent(id, || {
+        ctx.accesskit_node_builder(parts, |node| {
+            node.set_role(Role::GenericContainer);
+            node.set_hidden();
+        });
+        ctx.with_accessibility_parent(parts, || inner = Some(add_contents(ui)));
+    });
+
+    inner.expect("note contents")
+}
+
+fn name_of(ndb: &Ndb, txn: &Transaction, pubkey: &[u8; 32]) -> String {
+    let profile = ndb.get_profile_by_pubkey(txn, pubkey).ok();
+    get_display_name(profile.as_ref()).name().to_owned()
+}
+
+/// The note's text with mentions read as names and links as "a link"
+fn spoken_content(ndb: &Ndb, txn: &Transaction, note: &Note, note_key: NoteKey) -> String {
+    let Ok(blocks) = ndb.get_blocks_by_key(txn, note_key) else {
+        return shorten(note.content());
+    };
+
+    let mut content = String::new();
+    for block in blocks.iter(note) {
+        match block.blocktype() {
+            BlockType::MentionBech32 => match block.as_mention() {
+                Some(Mention::Profile(profile)) => {
+                    content.push_str(&name_of(ndb, txn, profile.pubkey()))
+                }
+                Some(Mention::Pubkey(npub)) => content.push_str(&name_of(ndb, txn, npub.pubkey())),
+                Some(Mention::Note(_)) | Some(Mention::Event(_)) => {
+                    content.push_str("a quoted note")
+                }
+                _ => content.push_str("a mention"),
+            },
+            BlockType::Url => content.push_str("a link"),
+            BlockType::Hashtag => {
+                content.push('#');
+                content.push_str(block.as_str());
+            }
+            _ => content.push_str(block.as_str()),
+        }
+    }
+
+    shorten(&content)
+}
+
+/// `content` on one line, cut off past [`MAX_CONTENT_CHARS`]
+fn shorten(content: &str) -> String {
+    let line = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= MAX_CONTENT_CHARS {
+        return line;
+    }
+    let mut cut: String = line.chars().take(MAX_CONTENT_CHARS - 1).collect();
+    cut.push('…');
+    cut
+}
+
+/// Like the note's timestamp, but in words, "3 hours ago" rather than "3h"
+fn spoken_time_ago(created_at: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let secs = now.saturating_sub(created_at);
+
+    let (count, unit) = match secs {
+        0..=59 => return "just now".to_owned(),
+        60..=3_599 => (secs / 60, "minute"),
+        3_600..=86_399 => (secs / 3_600, "hour"),
+        86_400..=604_799 => (secs / 86_400, "day"),
+        604_800..=2_591_999 => (secs / 604_800, "week"),
+        2_592_000..=31_535_999 => (secs / 2_592_000, "month"),
+        _ => (secs / 31_536_000, "year"),
+    };
+
+    if count == 1 {
+        format!("1 {unit} ago")
+    } else {
+        format!("{count} {unit}s ago")
+    }
+}
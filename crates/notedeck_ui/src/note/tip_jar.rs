@@ -0,0 +1,54 @@
+use egui::RichText;
+use enostr::{NoteId, Pubkey};
+use nostrdb::Note;
+use notedeck::note::ZapTargetAmount;
+use notedeck::tip_jar::TipJar;
+use notedeck::{NoteAction, NoteContext, NoteZapTargetOwned, ZapAction};
+
+/// Under a tip jar note: a QR of the lightning address for wallets on
+/// other devices, and a zap button for each suggested amount
+pub fn tip_jar_ui(
+    ui: &mut egui::Ui,
+    note_context: &NoteContext,
+    note: &Note,
+    tip_jar: &TipJar,
+) -> Option<NoteAction> {
+    let target = NoteZapTargetOwned {
+        note_id: NoteId::new(*note.id()),
+        zap_recipient: Pubkey::new(*note.pubkey()),
+    };
+    let can_zap = note_context.current_account_has_wallet
+        && note_context.current_account_can_sign
+        && note_context.acting_pubkey != Some(target.zap_recipient);
+    let mut action = None;
+
+    ui.add_space(4.0);
+    ui.horizontal(|ui| {
+        crate::qr_code(ui, &tip_jar.uri(), 96.0).on_hover_text(&tip_jar.lud16);
+
+        ui.vertical(|ui| {
+            ui.weak("Scan to tip from any wallet");
+            ui.horizontal_wrapped(|ui| {
+                for sats in &tip_jar.amounts_sats {
+                    let resp = ui
+                        .add_enabled(
+                            can_zap,
+                            egui::Button::new(RichText::new(format!("⚡ {sats}")).strong()),
+                        )
+                        .on_hover_text(format!("Zap {sats} sats"));
+                    if resp.hovered() {
+                        crate::show_pointer(ui);
+                    }
+                    if resp.clicked() {
+                        action = Some(NoteAction::Zap(ZapAction::Send(ZapTargetAmount {
+                            target: target.clone(),
+                            specified_msats: Some(sats * 1000),
+                        })));
+                    }
+                }
+            });
+        });
+    });
+
+    action
+}
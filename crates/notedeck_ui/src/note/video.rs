@@ -0,0 +1,144 @@
+use egui::{Color32, CornerRadius, Hyperlink, Rect, RichText, Sense, Stroke, TextureHandle};
+use notedeck::{video::VideoPlayer, Images, TextureState};
+
+/// A video in a note: its poster frame with a play button until it's
+/// started, then the playing frames with basic controls underneath.
+/// Posters from people you don't follow aren't fetched, the video only
+/// loads once you press play.
+pub(crate) fn video_player(
+    ui: &mut egui::Ui,
+    images: &mut Images,
+    url: &str,
+    trusted: bool,
+    max_height: f32,
+) {
+    let width = ui.available_width().min(max_height * 16.0 / 9.0);
+    let size = egui::vec2(width, width * 9.0 / 16.0);
+
+    ui.vertical(|ui| {
+        let (rect, resp) = ui.allocate_exact_size(size, Sense::click());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, CornerRadius::same(8), Color32::BLACK);
+
+        images.videos.update_player(ui.ctx(), url);
+
+        let player = images.videos.player(url);
+        let started = player.is_some_and(|p| p.frame().is_some());
+        let playing = player.is_some_and(VideoPlayer::is_playing);
+
+        if let Some(frame) = player.and_then(VideoPlayer::frame) {
+            paint_fitted(ui, frame, rect);
+        } else if trusted {
+            match images.videos.poster(ui.ctx(), url) {
+                TextureState::Loaded(poster) => paint_fitted(ui, poster.get_first_texture(), rect),
+                TextureState::Pending => egui::Spinner::new().paint_at(
+                    ui,
+                    Rect::from_center_size(rect.center(), egui::vec2(24.0, 24.0)),
+                ),
+                TextureState::Error(_) => {}
+            }
+        } else {
+            painter.text(
+                rect.center_top() + egui::vec2(0.0, 24.0),
+                egui::Align2::CENTER_TOP,
+                "Video from someone you don't follow",
+                egui::FontId::proportional(14.0),
+                Color32::WHITE,
+            );
+        }
+
+        if !playing {
+            paint_play_button(&painter, rect.center(), resp.hovered());
+        }
+
+        let resp = resp.on_hover_cursor(egui::CursorIcon::PointingHand);
+        if resp.clicked() {
+            if playing {
+                images.videos.player_mut(url).pause();
+            } else {
+                images.videos.play(ui.ctx(), url);
+            }
+        }
+
+        resp.context_menu(|ui| {
+            if ui.button("Copy Link").clicked() {
+                ui.ctx().copy_text(url.to_owned());
+                ui.close_menu();
+            }
+        });
+
+        if let Some(error) = images.videos.player(url).and_then(VideoPlayer::error) {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+            ui.add(Hyperlink::from_label_and_url(
+                RichText::new(url).color(ui.visuals().hyperlink_color),
+                url,
+            ));
+        } else if started || playing {
+            controls(ui, images.videos.player_mut(url), width);
+        }
+    });
+}
+
+/// Play/pause, the time and a seek bar once we know how long it is
+fn controls(ui: &mut egui::Ui, player: &mut VideoPlayer, width: f32) {
+    ui.horizontal(|ui| {
+        ui.set_width(width);
+
+        let label = if player.is_playing() { "Pause" } else { "Play" };
+        if ui.small_button(label).clicked() {
+            if player.is_playing() {
+                player.pause();
+            } else {
+                player.play(ui.ctx());
+            }
+        }
+
+        let pos = player.position();
+        let Some(duration) = player.duration() else {
+            ui.weak(format_time(pos));
+            return;
+        };
+
+        ui.weak(format!("{} / {}", format_time(pos), format_time(duration)));
+
+        let mut seek_to = pos;
+        ui.spacing_mut().slider_width = ui.available_width();
+        let resp = ui.add(egui::Slider::new(&mut seek_to, 0.0..=duration).show_value(false));
+        if resp.drag_stopped() || (resp.clicked() && !resp.dragged()) {
+            player.seek(ui.ctx(), seek_to);
+        }
+    });
+}
+
+fn paint_fitted(ui: &egui::Ui, texture: &TextureHandle, rect: Rect) {
+    let tex_size = texture.size_vec2();
+    let scale = (rect.width() / tex_size.x).min(rect.height() / tex_size.y);
+    let fitted = Rect::from_center_size(rect.center(), tex_size * scale);
+
+    egui::Image::new(texture)
+        .corner_radius(8.0)
+        .paint_at(ui, fitted);
+}
+
+fn paint_play_button(painter: &egui::Painter, center: egui::Pos2, hovered: bool) {
+    let radius = 28.0;
+    let alpha = if hovered { 200 } else { 150 };
+    painter.circle_filled(center, radius, Color32::from_black_alpha(alpha));
+
+    let h = radius * 0.5;
+    let triangle = vec![
+        center + egui::vec2(-h * 0.6, -h),
+        center + egui::vec2(h, 0.0),
+        center + egui::vec2(-h * 0.6, h),
+    ];
+    painter.add(egui::Shape::convex_polygon(
+        triangle,
+        Color32::WHITE,
+        Stroke::NONE,
+    ));
+}
+
+fn format_time(secs: f64) -> String {
+    let secs = secs.max(0.0) as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
@@ -0,0 +1,89 @@
+use egui::{RichText, Sense};
+use enostr::{NoteId, Pubkey};
+use nostrdb::{Note, Transaction};
+use notedeck::zap_goal::ZapGoal;
+use notedeck::{time_ago_since, NoteAction, NoteContext, NoteZapTargetOwned, ZapAction};
+
+const BAR_HEIGHT: f32 = 10.0;
+
+/// A zap goal's progress bar, and a button to zap toward it. The goal's
+/// description is the note's content, that's rendered as usual.
+pub fn zap_goal_ui(
+    ui: &mut egui::Ui,
+    note_context: &mut NoteContext,
+    txn: &Transaction,
+    note: &Note,
+    goal: &ZapGoal,
+) -> Option<NoteAction> {
+    let progress = note_context
+        .zap_goals
+        .progress(note_context.ndb, txn, note.id(), goal);
+    let fraction = progress.fraction(goal);
+    let closed = goal.is_closed();
+    let mut action = None;
+
+    ui.add_space(4.0);
+    ui.vertical(|ui| {
+        ui.spacing_mut().item_spacing.y = 4.0;
+
+        if let Some(summary) = &goal.summary {
+            ui.label(RichText::new(summary).strong());
+        }
+
+        let (rect, _) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), BAR_HEIGHT), Sense::hover());
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+        painter.rect_filled(rect, BAR_HEIGHT / 2.0, visuals.faint_bg_color);
+        let mut bar = rect;
+        bar.set_width(rect.width() * fraction.min(1.0));
+        painter.rect_filled(bar, BAR_HEIGHT / 2.0, visuals.selection.bg_fill);
+
+        let mut footer = format!(
+            "{} of {} sats · {:.0}%",
+            progress.raised_msats / 1000,
+            goal.target_msats / 1000,
+            fraction * 100.0
+        );
+        match progress.zaps {
+            0 => {}
+            1 => footer.push_str(" · 1 zap"),
+            n => footer.push_str(&format!(" · {n} zaps")),
+        }
+        match goal.closed_at {
+            Some(_) if closed => footer.push_str(" · Closed"),
+            Some(at) => footer.push_str(&format!(
+                " · Closes in {}",
+                time_ago_since(at).trim_start_matches('+')
+            )),
+            None => {}
+        }
+
+        ui.horizontal(|ui| {
+            ui.weak(footer);
+            if closed {
+                return;
+            }
+
+            let resp = ui
+                .add_enabled(
+                    note_context.current_account_has_wallet,
+                    egui::Button::new("⚡ Contribute").small(),
+                )
+                .on_disabled_hover_text("Connect a wallet to zap");
+            if resp.hovered() {
+                crate::show_pointer(ui);
+            }
+            if resp.clicked() {
+                action = Some(NoteAction::Zap(ZapAction::CustomizeAmount(
+                    NoteZapTargetOwned {
+                        note_id: NoteId::new(*note.id()),
+                        zap_recipient: Pubkey::new(*note.pubkey()),
+                    },
+                )));
+            }
+        });
+    });
+
+    action
+}
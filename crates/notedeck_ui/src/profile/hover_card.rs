@@ -0,0 +1,136 @@
+use egui::{Label, RichText};
+use nostrdb::ProfileRecord;
+use notedeck::{
+    name::get_display_name, profile::get_profile_url, ContactAction, Images, NotedeckTextStyle,
+};
+
+use crate::ProfilePic;
+
+use super::display_name_widget;
+
+/// How much of the bio fits on the card
+const BIO_SNIPPET_CHARS: usize = 160;
+
+/// A compact profile shown when hovering an author on desktop, with a
+/// follow button for the account that's looking
+pub struct ProfileHoverCard<'a, 'cache> {
+    profile: &'a ProfileRecord<'a>,
+    pubkey: &'a [u8; 32],
+    cache: &'cache mut Images,
+    followers: u64,
+
+    /// Whether the account looking follows them. None when there's no
+    /// account that can follow, or its contact list hasn't shown up yet.
+    following: Option<bool>,
+    can_follow: bool,
+}
+
+impl<'a, 'cache> ProfileHoverCard<'a, 'cache> {
+    pub fn new(
+        profile: &'a ProfileRecord<'a>,
+        pubkey: &'a [u8; 32],
+        cache: &'cache mut Images,
+        followers: u64,
+    ) -> Self {
+        ProfileHoverCard {
+            profile,
+            pubkey,
+            cache,
+            followers,
+            following: None,
+            can_follow: false,
+        }
+    }
+
+    pub fn following(mut self, following: Option<bool>, can_follow: bool) -> Self {
+        self.following = following;
+        self.can_follow = can_follow;
+        self
+    }
+
+    pub fn show(self, ui: &mut egui::Ui) -> Option<ContactAction> {
+        let mut action = None;
+        ui.set_max_width(280.0);
+
+        ui.horizontal(|ui| {
+            ui.add(
+                &mut ProfilePic::new(self.cache, get_profile_url(Some(self.profile)))
+                    .size(48.0)
+                    .border(ProfilePic::border_stroke(ui)),
+            );
+            ui.vertical(|ui| {
+                ui.add(display_name_widget(
+                    &get_display_name(Some(self.profile)),
+                    false,
+                ));
+            });
+        });
+
+        if let Some(about) = self
+            .profile
+            .record()
+            .profile()
+            .and_then(|p| p.about())
+            .filter(|about| !about.trim().is_empty())
+        {
+            ui.add_space(4.0);
+            ui.add(Label::new(bio_snippet(about)).wrap().selectable(false));
+        }
+
+        ui.add_space(6.0);
+        ui.horizontal(|ui| {
+            let followers = if self.followers == 1 {
+                "1 follower".to_owned()
+            } else {
+                format!("{} followers", self.followers)
+            };
+            ui.label(
+                RichText::new(followers)
+                    .text_style(NotedeckTextStyle::Small.text_style())
+                    .color(crate::colors::MID_GRAY),
+            );
+
+            if !self.can_follow {
+                return;
+            }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let pubkey = enostr::Pubkey::new(*self.pubkey);
+                match self.following {
+                    Some(true) => {
+                        if ui.button("Unfollow").clicked() {
+                            action = Some(ContactAction::Unfollow(pubkey));
+                        }
+                    }
+                    Some(false) => {
+                        if ui.button("Follow").clicked() {
+                            action = Some(ContactAction::Follow(pubkey));
+                        }
+                    }
+                    // following now would replace the contact list we
+                    // haven't seen with a list of one
+                    None => {
+                        ui.add_enabled(false, egui::Button::new("Follow"))
+                            .on_disabled_hover_text("Still loading your contact list");
+                    }
+                }
+            });
+        });
+
+        action
+    }
+}
+
+/// The start of the bio, cut at a word where possible
+fn bio_snippet(about: &str) -> String {
+    let about = about.trim();
+    let Some((cut, _)) = about.char_indices().nth(BIO_SNIPPET_CHARS) else {
+        return about.to_owned();
+    };
+
+    let head = &about[..cut];
+    let head = head
+        .rsplit_once(char::is_whitespace)
+        .map_or(head, |(h, _)| h);
+    format!("{}…", head.trim_end())
+}
@@ -1,9 +1,11 @@
 use nostrdb::ProfileRecord;
 
+pub mod hover_card;
 pub mod name;
 pub mod picture;
 pub mod preview;
 
+pub use hover_card::ProfileHoverCard;
 pub use picture::ProfilePic;
 pub use preview::ProfilePreview;
 
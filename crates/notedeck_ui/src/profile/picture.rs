@@ -142,7 +142,7 @@ fn render_pfp(
         notedeck::TextureState::Loaded(textured_image) => {
             let texture_handle = handle_repaint(
                 ui,
-                retrieve_latest_texture(url, cur_state.gifs, textured_image),
+                retrieve_latest_texture(ui, url, cur_state.gifs, textured_image),
             );
 
             egui::InnerResponse::new(None, pfp_image(ui, texture_handle, ui_size, border, sense))
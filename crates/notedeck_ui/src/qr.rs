@@ -0,0 +1,42 @@
+use egui::{Color32, Sense, Stroke, Vec2};
+use qrcode::QrCode;
+
+/// Draw `data` as a QR code, `size` points on each side
+pub fn qr_code(ui: &mut egui::Ui, data: &str, size: f32) -> egui::Response {
+    let (rect, resp) = ui.allocate_exact_size(Vec2::splat(size), Sense::hover());
+
+    let Ok(code) = QrCode::new(data) else {
+        ui.painter().rect_stroke(
+            rect,
+            4.0,
+            Stroke::new(1.0, ui.visuals().error_fg_color),
+            egui::StrokeKind::Inside,
+        );
+        return resp;
+    };
+
+    // leave a quiet zone around the code, some scanners need it
+    let quiet = 2;
+    let width = code.width();
+    let module = size / (width + quiet * 2) as f32;
+    let painter = ui.painter_at(rect);
+
+    painter.rect_filled(rect, 4.0, Color32::WHITE);
+
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color != qrcode::Color::Dark {
+            continue;
+        }
+
+        let x = (i % width + quiet) as f32 * module;
+        let y = (i / width + quiet) as f32 * module;
+        let min = rect.min + Vec2::new(x, y);
+        painter.rect_filled(
+            egui::Rect::from_min_size(min, Vec2::splat(module)),
+            0.0,
+            Color32::BLACK,
+        );
+    }
+
+    resp
+}
@@ -0,0 +1,17 @@
+//! Entities people paste into the search bar or we find in tags
+
+#![no_main]
+
+use enostr::{NoteId, Pubkey};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let _ = Pubkey::parse(input);
+    let _ = Pubkey::try_from_bech32_string(input, true);
+    let _ = NoteId::from_bech(input);
+
+    if let Some(rest) = input.strip_prefix("nostr:") {
+        let _ = Pubkey::parse(rest);
+        let _ = NoteId::from_bech(rest);
+    }
+});
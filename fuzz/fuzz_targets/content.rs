@@ -0,0 +1,108 @@
+//! Note content as the renderer sees it: nostrdb splits it into blocks,
+//! then we decode mentions, invoices and urls out of them.
+
+#![no_main]
+
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use libfuzzer_sys::fuzz_target;
+use lightning_invoice::Bolt11Invoice;
+use nostrdb::{
+    BlockType, Config, IngestMetadata, Mention, Ndb, NoteBuildOptions, NoteBuilder, Transaction,
+};
+use tempfile::TempDir;
+
+/// A throwaway key, fuzzing doesn't care whose notes these are
+const SECKEY: [u8; 32] = [7; 32];
+
+fn ndb() -> &'static Ndb {
+    static NDB: OnceLock<(TempDir, Ndb)> = OnceLock::new();
+
+    &NDB.get_or_init(|| {
+        let dir = TempDir::new().expect("fuzz dir");
+        let ndb = Ndb::new(dir.path().to_str().expect("fuzz dir"), &Config::new()).expect("ndb");
+        (dir, ndb)
+    })
+    .1
+}
+
+fuzz_target!(|content: &str| {
+    let ndb = ndb();
+
+    let Some(note) = NoteBuilder::new()
+        .kind(1)
+        .content(content)
+        .options(NoteBuildOptions::default().sign(&SECKEY))
+        .build()
+    else {
+        return;
+    };
+    let Ok(json) = note.json() else {
+        return;
+    };
+
+    if ndb
+        .process_event_with(
+            &format!("[\"EVENT\",{json}]"),
+            IngestMetadata::new().client(true),
+        )
+        .is_err()
+    {
+        return;
+    }
+
+    // ingestion happens on nostrdb's writer threads
+    let deadline = Instant::now() + Duration::from_millis(500);
+    let note_key = loop {
+        let txn = Transaction::new(ndb).expect("txn");
+        if let Ok(key) = ndb.get_notekey_by_id(&txn, note.id()) {
+            break key;
+        }
+        if Instant::now() > deadline {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    };
+
+    let txn = Transaction::new(ndb).expect("txn");
+    let Ok(note) = ndb.get_note_by_key(&txn, note_key) else {
+        return;
+    };
+    let Ok(blocks) = ndb.get_blocks_by_key(&txn, note_key) else {
+        return;
+    };
+
+    for block in blocks.iter(&note) {
+        match block.blocktype() {
+            BlockType::MentionBech32 => match block.as_mention() {
+                Some(Mention::Profile(profile)) => {
+                    let _ = profile.pubkey();
+                }
+                Some(Mention::Pubkey(npub)) => {
+                    let _ = npub.pubkey();
+                }
+                Some(Mention::Note(note)) => {
+                    let _ = note.id();
+                }
+                Some(Mention::Event(event)) => {
+                    let _ = event.id();
+                }
+                _ => {}
+            },
+
+            BlockType::Invoice => {
+                let _ = Bolt11Invoice::from_str(block.as_str());
+            }
+
+            BlockType::Url => {
+                let _ = url::Url::parse(block.as_str());
+            }
+
+            _ => {
+                let _ = block.as_str();
+            }
+        }
+    }
+});
@@ -0,0 +1,40 @@
+//! imeta tags, which decide what blurhash we decode and at what size
+//! before the media itself has loaded.
+//!
+//! Input is one tag per line with tab separated elements.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use nostrdb::{NoteBuildOptions, NoteBuilder};
+use notedeck_ui::blur::{imeta_blurhashes, PixelDimensions};
+
+const SECKEY: [u8; 32] = [7; 32];
+
+/// About what a column gives the media carousel
+const MAX_DIMS: PixelDimensions = PixelDimensions { x: 600, y: 300 };
+
+fuzz_target!(|input: &str| {
+    let mut builder = NoteBuilder::new().kind(1).content("");
+    for line in input.lines() {
+        builder = builder.start_tag();
+        for elem in line.split('\t') {
+            builder = builder.tag_str(elem);
+        }
+    }
+
+    let Some(note) = builder
+        .options(NoteBuildOptions::default().sign(&SECKEY))
+        .build()
+    else {
+        return;
+    };
+
+    for blur in imeta_blurhashes(&note).values() {
+        let dims = match &blur.dimensions {
+            Some(dims) => dims.scaled_to_fit(MAX_DIMS.clone()),
+            None => MAX_DIMS.clone(),
+        };
+        let _ = blurhash::decode(blur.blurhash, dims.x, dims.y, 1.0);
+    }
+});
@@ -0,0 +1,10 @@
+//! Raw websocket text straight from a relay
+
+#![no_main]
+
+use enostr::RelayMessage;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|msg: &str| {
+    let _ = RelayMessage::from_json(msg);
+});